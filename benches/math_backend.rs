@@ -0,0 +1,38 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use primitive_types::U256;
+use uniswap_v4_core::core::math::backend::{PrimitiveBackend, UintBackend};
+#[cfg(feature = "ruint")]
+use uniswap_v4_core::core::math::backend::RuintBackend;
+
+/// Mul-divs as the swap math runs them: sqrt prices times liquidity over
+/// Q96, products that fit in 256 bits and ones that need 512
+fn inputs() -> Vec<(U256, U256, U256)> {
+    let q96 = U256::one() << 96;
+    let sqrt_price = U256::from_dec_str("79228162514264337593543950336000").unwrap();
+    let liquidity = U256::from(1_000_000_000_000_000_000u128);
+    vec![
+        (liquidity << 96, sqrt_price - 12345, sqrt_price),
+        (liquidity, sqrt_price - q96, q96),
+        (U256::MAX / 3, U256::from(1_000_000_007u64), U256::MAX / 5),
+        (sqrt_price * sqrt_price, liquidity, q96 + 1),
+    ]
+}
+
+fn run<B: UintBackend>(inputs: &[(U256, U256, U256)]) {
+    for &(a, b, denominator) in inputs {
+        black_box(B::mul_div(black_box(a), black_box(b), black_box(denominator)));
+        black_box(B::mul_div_rounding_up(black_box(a), black_box(b), black_box(denominator)));
+    }
+}
+
+fn bench_mul_div(c: &mut Criterion) {
+    let inputs = inputs();
+    let mut group = c.benchmark_group("mul_div");
+    group.bench_function("primitive", |b| b.iter(|| run::<PrimitiveBackend>(&inputs)));
+    #[cfg(feature = "ruint")]
+    group.bench_function("ruint", |b| b.iter(|| run::<RuintBackend>(&inputs)));
+    group.finish();
+}
+
+criterion_group!(benches, bench_mul_div);
+criterion_main!(benches);
@@ -0,0 +1,72 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use ethers::types::Address;
+use uniswap_v4_core::core::{
+    hooks::hook_interface::ModifyLiquidityParams,
+    math::{tick_math::TickMath, types::{SqrtPrice, TickSpacing}},
+    pool_manager::{ManagerPoolKey, PoolManager, QuoteRequest},
+};
+
+const POOLS: u64 = 8;
+const QUOTES: usize = 1_000;
+
+/// A manager with several pools, each with liquidity spread over many ticks
+/// so quotes cross ticks like on a real pool
+fn setup_manager() -> (PoolManager, Vec<ManagerPoolKey>) {
+    let mut manager = PoolManager::new();
+    let keys: Vec<_> = (0..POOLS)
+        .map(|pool| ManagerPoolKey::new(
+            Address::from_low_u64_be(2 * pool + 1),
+            Address::from_low_u64_be(2 * pool + 2),
+            3000,
+            TickSpacing::new(60).unwrap(),
+            Address::zero(),
+        ).unwrap())
+        .collect();
+    for key in &keys {
+        manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+        for width in 1..=50 {
+            manager.modify_liquidity(
+                key.clone(),
+                ModifyLiquidityParams::default_position(Address::repeat_byte(1), -60 * width, 60 * width, 1_000_000_000),
+                &[],
+            ).unwrap();
+        }
+    }
+    (manager, keys)
+}
+
+fn requests(keys: &[ManagerPoolKey]) -> Vec<QuoteRequest> {
+    (0..QUOTES)
+        .map(|i| {
+            let zero_for_one = i % 2 == 0;
+            QuoteRequest {
+                key: keys[i % keys.len()].clone(),
+                zero_for_one,
+                amount_specified: -(1_000_000 * (i as i128 % 50 + 1)),
+                sqrt_price_limit_x96: if zero_for_one { TickMath::MIN_SQRT_PRICE + 1 } else { TickMath::MAX_SQRT_PRICE - 1 },
+            }
+        })
+        .collect()
+}
+
+fn bench_quote(c: &mut Criterion) {
+    let (manager, keys) = setup_manager();
+    let requests = requests(&keys);
+    let view = manager.quote_view();
+
+    let mut group = c.benchmark_group("quote_1000");
+    group.bench_function("sequential", |b| {
+        b.iter(|| requests.iter().map(|request| view.quote(black_box(request))).collect::<Vec<_>>())
+    });
+    let max_threads = std::thread::available_parallelism().map_or(1, usize::from);
+    for threads in [1, 2, 4, 8, 16].into_iter().filter(|threads| *threads <= max_threads) {
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+        group.bench_with_input(BenchmarkId::new("quote_many", threads), &threads, |b, _| {
+            b.iter(|| pool.install(|| view.quote_many(black_box(&requests))))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_quote);
+criterion_main!(benches);
@@ -0,0 +1,49 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use ethers::types::{Address, U256};
+use uniswap_v4_core::core::{
+    hooks::{hook_interface::ModifyLiquidityParams, NoOpHook},
+    math::types::{SqrtPrice, TickSpacing},
+    pool_manager::{ManagerPoolKey, PoolManager},
+    state::Salt,
+};
+
+fn setup_manager(hooks: Address) -> (PoolManager, ManagerPoolKey) {
+    let mut manager = PoolManager::new();
+    if hooks != Address::zero() {
+        manager.hook_registry_mut().register_hook(hooks, Box::new(NoOpHook));
+    }
+    let key = ManagerPoolKey::new(
+        Address::from_low_u64_be(1),
+        Address::from_low_u64_be(2),
+        3000,
+        TickSpacing::new(60).unwrap(),
+        hooks,
+    ).unwrap();
+    manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+    manager.modify_liquidity(key.clone(), ModifyLiquidityParams {
+        owner: Address::repeat_byte(1),
+        tick_lower: -600,
+        tick_upper: 600,
+        liquidity_delta: 1_000_000_000_000,
+        salt: Salt::ZERO,
+    }, &[]).unwrap();
+    (manager, key)
+}
+
+fn bench_swap(c: &mut Criterion) {
+    let limit = U256::from(78228162514264337593543950336u128);
+
+    for (name, hooks) in [("swap", Address::zero()), ("swap_with_hook", Address::from_low_u64_be(0xffff))] {
+        c.bench_function(name, |b| {
+            // Each iteration swaps against a fresh pool so the price starts at the same point
+            b.iter_batched_ref(
+                || setup_manager(hooks),
+                |(manager, key)| manager.swap(black_box(key), true, black_box(-1_000), limit, &[]).unwrap(),
+                criterion::BatchSize::SmallInput,
+            )
+        });
+    }
+}
+
+criterion_group!(benches, bench_swap);
+criterion_main!(benches);
@@ -0,0 +1,83 @@
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use uniswap_v4_core::core::{
+    math::{tick_math::TickMath, types::{SqrtPrice, TickSpacing}, FeePips, SqrtPriceCache},
+    state::Pool,
+};
+
+const WIDTHS: i32 = 50;
+
+/// A pool with nested positions, so every tick spacing up to
+/// `±60 * WIDTHS` is initialized and swaps cross many ticks
+fn setup_pool() -> Pool {
+    let tick_spacing = TickSpacing::new(60).unwrap();
+    let mut pool = Pool::new();
+    pool.initialize(SqrtPrice::ONE, FeePips::new(3000)).unwrap();
+    for width in 1..=WIDTHS {
+        pool.modify_position([1; 20], -60 * width, 60 * width, 1_000_000_000, tick_spacing, [0; 32]).unwrap();
+    }
+    pool
+}
+
+/// Swaps down across every initialized tick below the price and back up
+fn round_trip(pool: &mut Pool) {
+    let tick_spacing = TickSpacing::new(60).unwrap();
+    let lower = SqrtPrice::new(TickMath::get_sqrt_price_at_tick(-60 * WIDTHS + 30).unwrap());
+    let upper = SqrtPrice::new(TickMath::get_sqrt_price_at_tick(60 * WIDTHS - 30).unwrap());
+    pool.swap(i128::MIN / 2, lower, true, tick_spacing, None).unwrap();
+    pool.swap(i128::MIN / 2, upper, false, tick_spacing, None).unwrap();
+}
+
+fn bench_conversions(c: &mut Criterion) {
+    let ticks: Vec<i32> = (-WIDTHS..=WIDTHS).map(|width| 60 * width).collect();
+    let mut group = c.benchmark_group("sqrt_price_at_crossed_ticks");
+    group.bench_function("tick_math", |b| {
+        b.iter(|| {
+            for &tick in &ticks {
+                black_box(TickMath::get_sqrt_price_at_tick(black_box(tick)).unwrap());
+            }
+        })
+    });
+    group.bench_function("cached", |b| {
+        let mut cache = SqrtPriceCache::default();
+        b.iter(|| {
+            for &tick in &ticks {
+                black_box(cache.get_sqrt_price_at_tick(black_box(tick)).unwrap());
+            }
+        })
+    });
+    group.finish();
+}
+
+fn bench_multi_tick_swaps(c: &mut Criterion) {
+    let pool = setup_pool();
+    let mut group = c.benchmark_group("multi_tick_round_trip");
+    // Every iteration starts from an empty cache, as without caching
+    group.bench_function("cold_cache", |b| {
+        b.iter_batched_ref(
+            || {
+                let mut pool = pool.clone();
+                pool.clear_sqrt_price_cache();
+                pool
+            },
+            round_trip,
+            BatchSize::SmallInput,
+        )
+    });
+    // The cache is warm from a previous round trip, as on a busy pool.
+    // Clones start with an empty cache, so each one warms its own.
+    group.bench_function("warm_cache", |b| {
+        b.iter_batched_ref(
+            || {
+                let mut pool = pool.clone();
+                round_trip(&mut pool);
+                pool
+            },
+            round_trip,
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_conversions, bench_multi_tick_swaps);
+criterion_main!(benches);
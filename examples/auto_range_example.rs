@@ -0,0 +1,70 @@
+use uniswap_v4_core::{
+    core::{
+        pool_manager::{ManagerPoolKey, PoolManager},
+        hooks::{
+            examples::AutoRangeHook,
+            hook_interface::ModifyLiquidityParams,
+            HookFlags,
+        },
+        math::{SqrtPrice, TickMath, TickSpacing},
+    },
+    Rng,
+};
+use ethers::types::Address;
+
+/// This example runs a hook that keeps its own liquidity around the price:
+/// swaps note in the hook context that the pool traded, and the keeper moves
+/// the position within an unlock once the price leaves the hook's band.
+/// The same flow runs with assertions in tests/examples_test.rs.
+fn main() {
+    println!("Uniswap V4 Auto-Range Example");
+    println!("=============================");
+
+    let mut manager = PoolManager::new();
+    let mut rng = Rng::seed_from_u64(11);
+
+    println!("\n1. Placing the managed position");
+    println!("-------------------------------");
+
+    // The position spans two spacings either side of the price and moves
+    // once the price is a spacing away from its center
+    let hooks = HookFlags::new(HookFlags::AFTER_SWAP).apply_to_address(rng.address());
+    let keeper = AutoRangeHook::new(hooks, manager.hook_context(), 2, 1);
+    manager.hook_registry_mut().register_hook(hooks, Box::new(keeper.clone()));
+    let key = ManagerPoolKey::new(
+        Address::from_low_u64_be(1),
+        Address::from_low_u64_be(2),
+        3000,
+        TickSpacing::new(60).unwrap(),
+        hooks,
+    ).unwrap();
+    manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+
+    // Other LPs provide the depth traders swap against
+    let params = ModifyLiquidityParams::default_position(rng.address(), -6000, 6000, 1_000_000_000_000);
+    manager.modify_liquidity(key.clone(), params, &[]).unwrap();
+
+    keeper.fund(&key, 1_000_000_000, 1_000_000_000);
+    let range = keeper.rebalance(&mut manager, &key).unwrap().unwrap();
+    println!("Placed {} liquidity from tick {} to {}", range.liquidity, range.tick_lower, range.tick_upper);
+
+    println!("\n2. Following the price");
+    println!("----------------------");
+
+    for target in [30, 200, 150, -400] {
+        let tick = manager.get_pool(&key).unwrap().slot0.tick;
+        let limit = TickMath::get_sqrt_price_at_tick(target).unwrap();
+        manager.swap(&key, target < tick, -1_000_000_000_000, limit, &[]).unwrap();
+        let tick = manager.get_pool(&key).unwrap().slot0.tick;
+        match keeper.rebalance(&mut manager, &key) {
+            Ok(Some(range)) => println!(
+                "Price at tick {}: moved {} liquidity to {}..{}, {} token0 and {} token1 idle",
+                tick, range.liquidity, range.tick_lower, range.tick_upper, range.idle0, range.idle1,
+            ),
+            Ok(None) => println!("Price at tick {}: position stays", tick),
+            Err(error) => println!("Price at tick {}: move failed: {}", tick, error),
+        }
+    }
+
+    println!("\nAuto-Range Example completed!");
+}
@@ -3,8 +3,8 @@ use uniswap_v4_core::{
         erc6909::{ERC6909, ERC6909Error},
         LiquidityToken,
     },
+    Rng,
 };
-use ethers::types::Address;
 use primitive_types::U256;
 
 /// This example demonstrates the ERC6909 token standard implementation in Uniswap v4
@@ -17,10 +17,11 @@ fn main() {
     // Create a new ERC6909 token
     let mut token = ERC6909::new();
     
-    // Create addresses for testing
-    let owner = Address::random();
-    let spender = Address::random();
-    let recipient = Address::random();
+    // Create addresses for testing from a fixed seed so runs are reproducible
+    let mut rng = Rng::seed_from_u64(6909);
+    let owner = rng.address();
+    let spender = rng.address();
+    let recipient = rng.address();
     
     println!("\n1. Basic ERC6909 Operations");
     println!("---------------------------");
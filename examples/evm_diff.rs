@@ -0,0 +1,45 @@
+//! Fuzzes the crate against the v4-core contracts running in revm
+//!
+//! Usage: cargo run --example evm_diff --features evm-diff -- <forge out dir> [seed] [steps]
+
+use uniswap_v4_core::evm_diff::{fuzz, Artifacts};
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let Some(out_dir) = args.next() else {
+        eprintln!("usage: evm_diff <forge out dir> [seed] [steps]");
+        std::process::exit(2);
+    };
+    let seed = args.next().map_or(0, |seed| seed.parse().expect("seed must be a number"));
+    let steps = args.next().map_or(200, |steps| steps.parse().expect("steps must be a number"));
+
+    let artifacts = match Artifacts::from_forge_out(&out_dir) {
+        Ok(artifacts) => artifacts,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(2);
+        }
+    };
+
+    println!("Running {steps} operations with seed {seed}");
+    let divergences = match fuzz(&artifacts, seed, steps) {
+        Ok(divergences) => divergences,
+        Err(e) => {
+            eprintln!("Harness error: {e}");
+            std::process::exit(2);
+        }
+    };
+
+    for divergence in &divergences {
+        println!("\n=== Divergence at step {} ===", divergence.step);
+        println!("Operation: {:?}", divergence.operation);
+        println!("Crate: {:?}", divergence.crate_outcome);
+        println!("EVM:   {:?}", divergence.evm_outcome);
+        println!("Crate state: {:?}", divergence.crate_state);
+        println!("EVM state:   {:?}", divergence.evm_state);
+    }
+    println!("\n{} divergences", divergences.len());
+    if !divergences.is_empty() {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,80 @@
+use uniswap_v4_core::{
+    core::{
+        pool_manager::{ManagerPoolKey, PoolManager},
+        hooks::{
+            examples::DynamicFeeHook,
+            hook_interface::ModifyLiquidityParams,
+            describe_hook_address, HookFlags,
+        },
+        math::{FeePips, SqrtPrice, TickMath, TickSpacing},
+    },
+    Rng,
+};
+use ethers::types::Address;
+
+/// Flag in the pool fee marking an LP fee set by the hook on every swap
+const DYNAMIC_FEE: u32 = 0x800000;
+
+/// This example routes swaps through the PoolManager to a pool whose hook
+/// sets the LP fee of each swap, from hook registration to the pool's stats.
+/// The same flow runs with assertions in tests/examples_test.rs.
+fn main() {
+    println!("Uniswap V4 Hooked Swap Example");
+    println!("==============================");
+
+    let mut manager = PoolManager::new();
+    let mut rng = Rng::seed_from_u64(4);
+
+    println!("\n1. Registering the hook");
+    println!("-----------------------");
+
+    // The hook's address encodes the callbacks the manager will make
+    let hooks = HookFlags::new(HookFlags::BEFORE_SWAP).apply_to_address(rng.address());
+    let hook = DynamicFeeHook::new(FeePips::new(1000), FeePips::new(500), FeePips::new(10_000));
+    manager.hook_registry_mut().register_hook(hooks, Box::new(hook));
+    println!("DynamicFeeHook registered at {:?}", hooks);
+    println!("{}", describe_hook_address(hooks));
+
+    println!("\n2. Creating the pool");
+    println!("--------------------");
+
+    let key = ManagerPoolKey::new(
+        Address::from_low_u64_be(1),
+        Address::from_low_u64_be(2),
+        DYNAMIC_FEE,
+        TickSpacing::new(60).unwrap(),
+        hooks,
+    ).unwrap();
+    let tick = manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+    println!("Pool initialized at tick {}", tick);
+
+    let provider = rng.address();
+    let params = ModifyLiquidityParams::default_position(provider, -600, 600, 1_000_000_000_000);
+    let (delta, _fees) = manager.modify_liquidity(key.clone(), params, &[]).unwrap();
+    println!("Liquidity added, paying {} token0 and {} token1", -delta.amount0(), -delta.amount1());
+
+    println!("\n3. Swapping through the manager");
+    println!("-------------------------------");
+
+    for (amount, zero_for_one) in [(1_000_000i128, true), (2_500_000, false), (500_000, true)] {
+        let limit = if zero_for_one { TickMath::MIN_SQRT_PRICE + 1 } else { TickMath::MAX_SQRT_PRICE - 1 };
+        let delta = manager.swap(&key, zero_for_one, -amount, limit, &[]).unwrap();
+        println!(
+            "Swapped {} {}: delta token0 {}, token1 {}",
+            amount,
+            if zero_for_one { "token0 -> token1" } else { "token1 -> token0" },
+            delta.amount0(),
+            delta.amount1()
+        );
+    }
+
+    println!("\n4. Pool statistics");
+    println!("------------------");
+
+    let stats = manager.pool_stats(&key).unwrap();
+    println!("Swaps: {}", stats.swap_count);
+    println!("Volume: {} token0, {} token1", stats.volume0, stats.volume1);
+    println!("LP fees set by the hook: {} token0, {} token1", stats.lp_fees0, stats.lp_fees1);
+
+    println!("\nHooked Swap Example completed!");
+}
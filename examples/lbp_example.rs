@@ -0,0 +1,85 @@
+use uniswap_v4_core::{
+    core::{
+        pool_manager::{ManagerPoolKey, PoolManager},
+        hooks::{
+            examples::LiquidityBootstrappingHook,
+            hook_interface::ModifyLiquidityParams,
+            HookFlags,
+        },
+        math::{SqrtPrice, TickMath, TickSpacing},
+        SwapAmount,
+    },
+    Rng,
+};
+use ethers::types::Address;
+
+/// Flag in the pool fee marking an LP fee set by the hook on every swap
+const DYNAMIC_FEE: u32 = 0x800000;
+
+/// Start and end of the sale window
+const SALE_START: u64 = 1_000;
+const SALE_END: u64 = 2_000;
+
+/// This example runs a liquidity bootstrapping sale of token0: the hook reads
+/// the manager's clock to charge buyers a premium that decays over the sale
+/// window, and opens the pool to donations once the sale has ended.
+/// The same flow runs with assertions in tests/examples_test.rs.
+fn main() {
+    println!("Uniswap V4 Liquidity Bootstrapping Example");
+    println!("==========================================");
+
+    let mut manager = PoolManager::new();
+    let mut rng = Rng::seed_from_u64(7);
+
+    println!("\n1. Setting up the sale");
+    println!("----------------------");
+
+    // The hook keeps a handle to the manager's clock to follow its schedule
+    let hooks = HookFlags::new(HookFlags::BEFORE_SWAP | HookFlags::BEFORE_DONATE).apply_to_address(rng.address());
+    let hook = LiquidityBootstrappingHook::new(manager.clock(), SALE_START, SALE_END, true);
+    manager.hook_registry_mut().register_hook(hooks, Box::new(hook));
+    let key = ManagerPoolKey::new(
+        Address::from_low_u64_be(1),
+        Address::from_low_u64_be(2),
+        DYNAMIC_FEE,
+        TickSpacing::new(60).unwrap(),
+        hooks,
+    ).unwrap();
+    manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+
+    // A range above the price holds only the token on sale
+    let seller = rng.address();
+    let params = ModifyLiquidityParams::default_position(seller, 0, 6000, 1_000_000_000);
+    let (delta, _fees) = manager.modify_liquidity(key.clone(), params, &[]).unwrap();
+    println!("Seller deposited {} token0 for sale from {} to {}", -delta.amount0(), SALE_START, SALE_END);
+
+    println!("\n2. Trading over the sale window");
+    println!("-------------------------------");
+
+    let buy = |manager: &mut PoolManager| {
+        manager.swap_exact(&key, false, SwapAmount::ExactIn(1_000_000), TickMath::MAX_SQRT_PRICE - 1, &[])
+    };
+    for timestamp in [500, SALE_START, 1_500, SALE_END] {
+        manager.set_timestamp(timestamp);
+        let fees_before = manager.pool_stats(&key).unwrap().lp_fees1;
+        match buy(&mut manager) {
+            Ok(delta) => {
+                let fee = manager.pool_stats(&key).unwrap().lp_fees1 - fees_before;
+                println!("At {}: paid 1000000 token1 for {} token0, {} of it in fees", timestamp, delta.amount0(), fee);
+            }
+            Err(error) => println!("At {}: buy rejected: {}", timestamp, error),
+        }
+    }
+
+    println!("\n3. Sharing the raise");
+    println!("--------------------");
+
+    match manager.donate(&key, seller, 0, 10_000, &[]) {
+        Ok(_) => println!("Donated 10000 token1 to LPs"),
+        Err(error) => println!("Donation rejected: {}", error),
+    }
+    let price = manager.get_pool(&key).unwrap().slot0.sqrt_price_x96.to_price_f64();
+    println!("Sale closed at a price of {:.4} token1 per token0", price);
+
+    println!("\nLiquidity Bootstrapping Example completed!");
+}
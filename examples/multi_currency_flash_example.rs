@@ -0,0 +1,102 @@
+use uniswap_v4_core::{
+    core::{
+        flash_loan::Currency,
+        hooks::hook_interface::ModifyLiquidityParams,
+        math::{Bps, SqrtPrice, TickMath, TickSpacing},
+        pool_manager::{ManagerPoolKey, OperationOutput, PoolManager, QuoteRequest, SwapSettlement, UnlockOperation},
+    },
+};
+use ethers::types::{Address, U256};
+
+/// This example borrows two currencies in one unlock, swaps one of them in a
+/// pool and settles every delta before the unlock ends: take, swap, settle
+/// each currency, take the swap output, then clear the leftover dust.
+/// The same flow runs with assertions in tests/flash_loan_test.rs.
+fn main() {
+    println!("Uniswap V4 Multi-Currency Flash Loan Example");
+    println!("============================================");
+
+    let mut manager = PoolManager::new();
+    let key = ManagerPoolKey::new(
+        Address::from_low_u64_be(1),
+        Address::from_low_u64_be(2),
+        3000,
+        TickSpacing::new(60).unwrap(),
+        Address::zero(),
+    ).unwrap();
+    let currency0 = Currency::from_address(key.token0());
+    let currency1 = Currency::from_address(key.token1());
+
+    println!("\n1. Setting up the pool");
+    println!("----------------------");
+
+    manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+    let provider = Address::repeat_byte(1);
+    let params = ModifyLiquidityParams::default_position(provider, -600, 600, 1_000_000_000_000);
+    manager.modify_liquidity(key.clone(), params, &[]).unwrap();
+    manager.set_flash_fee(currency0, Bps::new(5)).unwrap();
+    manager.set_flash_fee(currency1, Bps::new(5)).unwrap();
+    println!("Pool initialized with liquidity, flash loans cost 5 bps");
+
+    println!("\n2. Planning the unlock");
+    println!("----------------------");
+
+    let borrower = Address::repeat_byte(2);
+    let (borrow0, borrow1) = (1_000_000u128, 500_000u128);
+    let owed0 = borrow0 + manager.flash_fee(currency0, borrow0);
+    let owed1 = borrow1 + manager.flash_fee(currency1, borrow1);
+    println!("Borrowing {} token0 and {} token1, owing {} and {}", borrow0, borrow1, owed0, owed1);
+
+    // The borrowed token0 is swapped, so its output is known from a quote
+    let swap_in = borrow0 as i128;
+    let limit = TickMath::MIN_SQRT_PRICE + 1;
+    let request = QuoteRequest { key: key.clone(), zero_for_one: true, amount_specified: -swap_in, sqrt_price_limit_x96: limit };
+    let quote = manager.quote(&request).unwrap();
+    let swap_out = quote.delta.amount1() as u128;
+    // Only whole thousands of the output are taken, the rest is cleared as dust
+    let dust = swap_out % 1000;
+    println!("Swapping {} token0 for {} token1, leaving {} as dust", swap_in, swap_out, dust);
+
+    println!("\n3. Running the unlock");
+    println!("---------------------");
+
+    let result = manager.unlock_batch(&[
+        UnlockOperation::Take { currency: currency0, to: borrower, amount: borrow0 },
+        UnlockOperation::Take { currency: currency1, to: borrower, amount: borrow1 },
+        UnlockOperation::Swap {
+            key: key.clone(),
+            zero_for_one: true,
+            amount_specified: -swap_in,
+            sqrt_price_limit_x96: limit,
+            settlement: SwapSettlement::Deltas { owner: borrower },
+            hook_data: vec![],
+        },
+        // Repaying the token1 loan first leaves the swap output as a credit,
+        // so taking it is a withdrawal rather than another loan
+        UnlockOperation::Settle { currency: currency1, recipient: borrower, value: U256::from(owed1) },
+        UnlockOperation::Take { currency: currency1, to: borrower, amount: swap_out - dust },
+        // The loan and the swap input are both paid in token0
+        UnlockOperation::Settle { currency: currency0, recipient: borrower, value: U256::from(owed0 + borrow0) },
+        UnlockOperation::Clear { currency: currency1, account: borrower, amount: dust },
+    ]);
+    for (index, output) in result.results.iter().enumerate() {
+        match output {
+            Ok(OperationOutput::Delta(delta)) => {
+                println!("Operation {}: swap delta token0 {}, token1 {}", index, delta.amount0(), delta.amount1())
+            }
+            Ok(OperationOutput::Tick(tick)) => println!("Operation {}: initialized at tick {}", index, tick),
+            Ok(OperationOutput::Settled(value)) => println!("Operation {}: settled {}", index, value),
+            Ok(OperationOutput::Done) => println!("Operation {}: done", index),
+            Err(error) => println!("Operation {}: failed: {}", index, error),
+        }
+    }
+    println!("Unlock succeeded: {}", result.success);
+
+    println!("\n4. Final deltas");
+    println!("---------------");
+
+    println!("token0: {}", manager.get_delta(borrower, currency0));
+    println!("token1: {}", manager.get_delta(borrower, currency1));
+
+    println!("\nMulti-Currency Flash Loan Example completed!");
+}
@@ -7,13 +7,15 @@ use uniswap_v4_core::{
             HookWithReturns
         },
         state::{BalanceDelta, StateError},
-        math::types::SqrtPrice,
+        math::types::{SqrtPrice, TickSpacing},
         flash_loan::Currency,
     },
     fees::{
         types::ProtocolFee,
         controller::ProtocolFeeManager,
     },
+    tokens::amounts::{CurrencyDecimals, format_amount},
+    Rng,
 };
 use ethers::types::Address;
 use primitive_types::U256;
@@ -37,7 +39,7 @@ impl ProtocolFeeHook {
     
     /// Set protocol fee for a specific currency pair
     fn set_protocol_fee(&mut self, token0: Address, token1: Address, fee0: u16, fee1: u16) {
-        let protocol_fee = ProtocolFee::new(fee0, fee1);
+        let protocol_fee = ProtocolFee::new(fee0, fee1).unwrap();
         self.fee_map.insert((token0, token1), protocol_fee);
         println!("Protocol fee set: {}% for token0->token1, {}% for token1->token0", 
                  fee0 as f64 / 10000.0, 
@@ -46,7 +48,7 @@ impl ProtocolFeeHook {
     
     /// Get protocol fee for a specific currency pair
     fn get_protocol_fee(&self, token0: Address, token1: Address) -> ProtocolFee {
-        *self.fee_map.get(&(token0, token1)).unwrap_or(&ProtocolFee::new(0, 0))
+        *self.fee_map.get(&(token0, token1)).unwrap_or(&ProtocolFee::default())
     }
     
     /// Calculate fee amount based on protocol fee rate
@@ -55,9 +57,9 @@ impl ProtocolFeeHook {
         
         // Determine which fee rate to use based on swap direction
         let fee_rate = if zero_for_one {
-            protocol_fee.get_zero_for_one_fee()
+            protocol_fee.get_zero_for_one_fee().get()
         } else {
-            protocol_fee.get_one_for_zero_fee()
+            protocol_fee.get_one_for_zero_fee().get()
         };
         
         // Calculate fee amount (fee_rate is in hundredths of a bip, e.g. 100 = 0.01%)
@@ -69,7 +71,7 @@ impl Hook for ProtocolFeeHook {
     // Implement required Hook methods
     fn before_swap(
         &mut self,
-        _sender: [u8; 20],
+        _sender: Address,
         key: &PoolKey,
         _params: &SwapParams,
         _hook_data: &[u8],
@@ -80,7 +82,7 @@ impl Hook for ProtocolFeeHook {
     
     fn after_swap(
         &mut self,
-        _sender: [u8; 20],
+        _sender: Address,
         _key: &PoolKey,
         _params: &SwapParams,
         _delta: &BalanceDelta,
@@ -92,7 +94,7 @@ impl Hook for ProtocolFeeHook {
     // Other required Hook methods with default implementations
     fn before_initialize(
         &mut self,
-        _sender: [u8; 20],
+        _sender: Address,
         _key: &PoolKey,
         _sqrt_price: SqrtPrice,
         _hook_data: &[u8],
@@ -102,7 +104,7 @@ impl Hook for ProtocolFeeHook {
     
     fn after_initialize(
         &mut self,
-        _sender: [u8; 20],
+        _sender: Address,
         _key: &PoolKey,
         _sqrt_price: SqrtPrice,
         _tick: i32,
@@ -113,7 +115,7 @@ impl Hook for ProtocolFeeHook {
     
     fn before_add_liquidity(
         &mut self,
-        _sender: [u8; 20],
+        _sender: Address,
         _key: &PoolKey,
         _params: &ModifyLiquidityParams,
         _hook_data: &[u8],
@@ -123,7 +125,7 @@ impl Hook for ProtocolFeeHook {
     
     fn after_add_liquidity(
         &mut self,
-        _sender: [u8; 20],
+        _sender: Address,
         _key: &PoolKey,
         _params: &ModifyLiquidityParams,
         _delta: &BalanceDelta,
@@ -135,7 +137,7 @@ impl Hook for ProtocolFeeHook {
     
     fn before_remove_liquidity(
         &mut self,
-        _sender: [u8; 20],
+        _sender: Address,
         _key: &PoolKey,
         _params: &ModifyLiquidityParams,
         _hook_data: &[u8],
@@ -145,7 +147,7 @@ impl Hook for ProtocolFeeHook {
     
     fn after_remove_liquidity(
         &mut self,
-        _sender: [u8; 20],
+        _sender: Address,
         _key: &PoolKey,
         _params: &ModifyLiquidityParams,
         _delta: &BalanceDelta,
@@ -157,7 +159,7 @@ impl Hook for ProtocolFeeHook {
     
     fn before_donate(
         &mut self,
-        _sender: [u8; 20],
+        _sender: Address,
         _key: &PoolKey,
         _amount0: u128,
         _amount1: u128,
@@ -168,7 +170,7 @@ impl Hook for ProtocolFeeHook {
     
     fn after_donate(
         &mut self,
-        _sender: [u8; 20],
+        _sender: Address,
         _key: &PoolKey,
         _amount0: u128,
         _amount1: u128,
@@ -182,14 +184,14 @@ impl Hook for ProtocolFeeHook {
 impl HookWithReturns for ProtocolFeeHook {
     fn before_swap_with_delta(
         &mut self,
-        _sender: [u8; 20],
+        _sender: Address,
         key: &PoolKey,
         params: &SwapParams,
         _hook_data: &[u8],
     ) -> Result<BeforeSwapDelta, StateError> {
         // Extract token addresses from key
-        let token0 = Address::from_slice(&key.token0);
-        let token1 = Address::from_slice(&key.token1);
+        let token0 = key.token0;
+        let token1 = key.token1;
         
         // Calculate protocol fee based on swap direction and amount
         let fee_amount = self.calculate_fee_amount(
@@ -223,9 +225,10 @@ fn main() {
     
     // Create pool manager
     let mut pool_manager = PoolManager::new();
+    let mut rng = Rng::seed_from_u64(1);
     
     // Create protocol fee hook
-    let owner = Address::random();
+    let owner = rng.address();
     let mut protocol_fee_hook = ProtocolFeeHook::new(owner);
     
     // Create token addresses
@@ -247,51 +250,60 @@ fn main() {
     println!("\n2. Simulating swaps with protocol fees");
     println!("------------------------------------");
     
+    // Both tokens use 6 decimals, like USDC
+    let mut decimals = CurrencyDecimals::new();
+    decimals
+        .set(Currency::from_address(token0), 6)
+        .set(Currency::from_address(token1), 6);
+    
     // Simulate different swap scenarios
     let swap_scenarios = [
-        (1_000_000, true, "1,000,000 token0 -> token1"),
-        (500_000, false, "500,000 token1 -> token0"),
-        (10_000_000, true, "10,000,000 token0 -> token1"),
+        ("1", true),
+        ("0.5", false),
+        ("10", true),
     ];
     
-    for (amount, zero_for_one, description) in swap_scenarios.iter() {
-        println!("\nScenario: {}", description);
+    for (human_amount, zero_for_one) in swap_scenarios.iter() {
+        let (token_in, token_out) = if *zero_for_one { ("token0", "token1") } else { ("token1", "token0") };
+        println!("\nScenario: {} {} -> {}", human_amount, token_in, token_out);
+        let currency_in = Currency::from_address(if *zero_for_one { token0 } else { token1 });
+        let amount = decimals.parse(currency_in, human_amount).unwrap().as_u128();
         
         // Calculate protocol fee
         let fee_amount = protocol_fee_hook.calculate_fee_amount(
             token0, 
             token1, 
-            *amount as i128, 
+            amount as i128, 
             *zero_for_one
         );
         
         // Create swap parameters
         let swap_params = SwapParams {
-            amount_specified: *amount as i128,
+            amount_specified: amount as i128,
             zero_for_one: *zero_for_one,
             sqrt_price_limit_x96: SqrtPrice::new(U256::from(0)),
         };
         
         // Create pool key
         let pool_key = PoolKey {
-            token0: token0.0,
-            token1: token1.0,
+            token0,
+            token1,
             fee: 3000, // 0.3% fee
-            tick_spacing: 60,
-            hooks: [0u8; 20],
+            tick_spacing: TickSpacing::new(60).unwrap(),
+            hooks: Address::zero(),
             extension_data: vec![],
         };
         
         // Get delta from hook
         let delta = protocol_fee_hook.before_swap_with_delta(
-            [0u8; 20], 
+            Address::zero(), 
             &pool_key, 
             &swap_params, 
             &[]
         ).unwrap();
         
         println!("  Swap amount: {}", amount);
-        println!("  Protocol fee amount: {}", fee_amount);
+        println!("  Protocol fee amount: {} ({} {})", fee_amount, format_amount(U256::from(fee_amount), 6), token_in);
         println!("  Delta specified: {}", delta.delta_specified);
         println!("  Delta unspecified: {}", delta.delta_unspecified);
     }
@@ -300,7 +312,7 @@ fn main() {
     println!("-------------------------");
     
     // Simulate protocol fee withdrawal
-    let fee_recipient = Address::random();
+    let fee_recipient = rng.address();
     println!("Fee recipient: {:?}", fee_recipient);
     
     // In a real implementation, the protocol fee would be transferred to the fee recipient
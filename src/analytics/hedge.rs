@@ -0,0 +1,367 @@
+//! Delta of LP positions and delta-neutral rebalancing
+//!
+//! A position worth `V` in token1 at a price `P` of token0 in token1 has
+//! delta `dV/dP`, which for concentrated liquidity is exactly the token0 it
+//! holds: below its range the position is all token0 and moves one for one
+//! with the price, above its range it is all token1 and does not move at all.
+//! Holding the opposite of that token0 elsewhere leaves the portfolio
+//! delta-neutral until the price moves again, which is what a
+//! [`HedgeScheduler`] maintains.
+
+use ethers::types::Address;
+
+use crate::core::{
+    math::{tick_math::TickMath, types::SqrtPrice},
+    pool_manager::{ManagerPoolKey, PoolId, PoolManager},
+    state::{PositionKey, StateError},
+};
+
+use super::{AnalyticsError, AnalyticsResult};
+
+/// Tokens a position holds at a price
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Exposure {
+    pub amount0: f64,
+    pub amount1: f64,
+}
+
+impl Exposure {
+    /// Tokens held by `liquidity` over `tick_lower..tick_upper` at a price
+    pub fn of_range(liquidity: u128, sqrt_price: SqrtPrice, tick_lower: i32, tick_upper: i32) -> Self {
+        let liquidity = liquidity as f64;
+        let sqrt_price = sqrt_price.to_price_f64().sqrt();
+        let sqrt_at = |tick: i32| 1.0001f64.powf(tick as f64 / 2.0);
+        let (sqrt_lower, sqrt_upper) = (sqrt_at(tick_lower), sqrt_at(tick_upper));
+        let sqrt_price = sqrt_price.clamp(sqrt_lower, sqrt_upper);
+        Self {
+            amount0: liquidity * (1.0 / sqrt_price - 1.0 / sqrt_upper),
+            amount1: liquidity * (sqrt_price - sqrt_lower),
+        }
+    }
+
+    /// Change of the value in token1 per unit rise of the price, the token0
+    /// held
+    pub fn delta(&self) -> f64 {
+        self.amount0
+    }
+
+    /// Value in token1 at a price of token0 in token1
+    pub fn value(&self, price: f64) -> f64 {
+        self.amount0 * price + self.amount1
+    }
+}
+
+impl std::ops::Add for Exposure {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self { amount0: self.amount0 + other.amount0, amount1: self.amount1 + other.amount1 }
+    }
+}
+
+impl std::iter::Sum for Exposure {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), |total, exposure| total + exposure)
+    }
+}
+
+/// Exposure of a position at its pool's current price; positions that do
+/// not exist hold nothing
+pub fn position_exposure(manager: &PoolManager, key: &ManagerPoolKey, position: &PositionKey) -> AnalyticsResult<Exposure> {
+    let pool = manager.get_pool(key).ok_or_else(|| AnalyticsError::PoolNotInitialized(PoolId::from_key(key)))?;
+    let liquidity = pool.position_manager.get(position).map_or(0, |position| position.liquidity.as_u128());
+    Ok(Exposure::of_range(liquidity, pool.slot0.sqrt_price_x96, position.tick_lower, position.tick_upper))
+}
+
+/// Total exposure of positions in pools that share token0
+pub fn portfolio_exposure(manager: &PoolManager, positions: &[(ManagerPoolKey, PositionKey)]) -> AnalyticsResult<Exposure> {
+    let Some((first, _)) = positions.first() else {
+        return Ok(Exposure::default());
+    };
+    positions
+        .iter()
+        .map(|(key, position)| {
+            if key.token0() != first.token0() {
+                return Err(AnalyticsError::CurrencyMismatch(PoolId::from_key(key)));
+            }
+            position_exposure(manager, key, position)
+        })
+        .sum()
+}
+
+/// Configuration of a hedge scheduler
+#[derive(Debug, Clone)]
+pub struct HedgeConfig {
+    /// Positions to hedge, in pools that share token0
+    pub positions: Vec<(ManagerPoolKey, PositionKey)>,
+    /// Pool the hedge swaps in, trading the positions' token0
+    pub hedge_pool: ManagerPoolKey,
+    /// Seconds between rebalances
+    pub interval: u64,
+    /// Net delta, in token0, left unhedged
+    pub threshold: f64,
+}
+
+impl HedgeConfig {
+    /// Creates a configuration that rebalances every second, whatever the
+    /// net delta
+    pub fn new(positions: Vec<(ManagerPoolKey, PositionKey)>, hedge_pool: ManagerPoolKey) -> Self {
+        Self { positions, hedge_pool, interval: 1, threshold: 0.0 }
+    }
+
+    /// Sets the seconds between rebalances
+    pub fn with_interval(mut self, interval: u64) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Sets the net delta left unhedged
+    pub fn with_threshold(mut self, threshold: f64) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Gets the hedged currency, the positions' token0, or `None` without
+    /// positions
+    pub fn currency(&self) -> Option<Address> {
+        self.positions.first().map(|(key, _)| key.token0())
+    }
+
+    /// Checks that the configuration can be scheduled
+    pub fn validate(&self) -> AnalyticsResult<()> {
+        if self.interval == 0 {
+            return Err(AnalyticsError::ZeroInterval);
+        }
+        if self.threshold.is_nan() || self.threshold < 0.0 {
+            return Err(AnalyticsError::InvalidThreshold(self.threshold));
+        }
+        let currency = self.currency();
+        if let Some((key, _)) = self.positions.iter().find(|(key, _)| Some(key.token0()) != currency) {
+            return Err(AnalyticsError::CurrencyMismatch(PoolId::from_key(key)));
+        }
+        let hedge = &self.hedge_pool;
+        if currency.is_some_and(|currency| hedge.token0() != currency && hedge.token1() != currency) {
+            return Err(AnalyticsError::CurrencyMismatch(PoolId::from_key(hedge)));
+        }
+        Ok(())
+    }
+}
+
+/// A swap made by a hedge scheduler
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HedgeTrade {
+    pub timestamp: u64,
+    /// Net delta of the positions and the hedge before the swap
+    pub delta_before: f64,
+    /// Hedged currency bought, negative when sold
+    pub amount_hedged: i128,
+    /// Other currency of the hedge pool bought, negative when sold
+    pub amount_other: i128,
+}
+
+/// Keeps a portfolio of positions delta-neutral with swaps in a paired pool
+///
+/// The scheduler follows the manager's clock like a
+/// [`PoolSampler`](crate::sampling::PoolSampler):
+/// [`on_block`](Self::on_block) is called whenever the clock advances and
+/// rebalances at most once per interval, at the first block in it. The hedge
+/// is the currencies its swaps bought and sold, and a rebalance swaps the
+/// net delta of the positions and the hedge back to zero.
+#[derive(Debug, Clone)]
+pub struct HedgeScheduler {
+    config: HedgeConfig,
+    hedged: i128,
+    other: i128,
+    trades: Vec<HedgeTrade>,
+    /// Interval of the last rebalance, counted from timestamp 0
+    last_slot: Option<u64>,
+}
+
+impl HedgeScheduler {
+    /// Creates a scheduler with an empty hedge
+    pub fn new(config: HedgeConfig) -> AnalyticsResult<Self> {
+        config.validate()?;
+        Ok(Self { config, hedged: 0, other: 0, trades: Vec::new(), last_slot: None })
+    }
+
+    /// Gets the configuration
+    pub fn config(&self) -> &HedgeConfig {
+        &self.config
+    }
+
+    /// Gets the hedge's balances of the hedged currency and of the other
+    /// currency of the hedge pool
+    pub fn hedge_balances(&self) -> (i128, i128) {
+        (self.hedged, self.other)
+    }
+
+    /// Gets the swaps made so far, oldest first
+    pub fn trades(&self) -> &[HedgeTrade] {
+        &self.trades
+    }
+
+    /// Gets the delta of the positions and the hedge together
+    pub fn net_delta(&self, manager: &PoolManager) -> AnalyticsResult<f64> {
+        Ok(portfolio_exposure(manager, &self.config.positions)?.delta() + self.hedged as f64)
+    }
+
+    /// Rebalances if no rebalance was made yet in the interval of the
+    /// manager's timestamp and the net delta is past the threshold,
+    /// returning the swap made
+    ///
+    /// Sales are exact input and purchases exact output, both limited only
+    /// by the price bounds, so a shallow hedge pool fills what it can.
+    pub fn on_block(&mut self, manager: &mut PoolManager) -> AnalyticsResult<Option<HedgeTrade>> {
+        let timestamp = manager.timestamp();
+        let slot = timestamp / self.config.interval;
+        if self.last_slot.is_some_and(|last| slot <= last) {
+            return Ok(None);
+        }
+        self.last_slot = Some(slot);
+
+        let Some(currency) = self.config.currency() else {
+            return Ok(None);
+        };
+        let delta_before = self.net_delta(manager)?;
+        let amount = delta_before.round() as i128;
+        if delta_before.abs() <= self.config.threshold || amount == 0 {
+            return Ok(None);
+        }
+
+        // Sell the hedged currency when long, buy it when short
+        let hedge = &self.config.hedge_pool;
+        let hedged_is_token0 = hedge.token0() == currency;
+        let zero_for_one = (amount > 0) == hedged_is_token0;
+        let amount_specified = if amount > 0 { -amount } else { amount.saturating_neg() };
+        let limit = if zero_for_one { TickMath::MIN_SQRT_PRICE + 1 } else { TickMath::MAX_SQRT_PRICE - 1 };
+        let delta = manager.swap(hedge, zero_for_one, amount_specified, limit, &[])?;
+        let (amount_hedged, amount_other) = if hedged_is_token0 {
+            (delta.amount0(), delta.amount1())
+        } else {
+            (delta.amount1(), delta.amount0())
+        };
+
+        self.hedged = self.hedged.checked_add(amount_hedged).ok_or(StateError::AmountOverflow)?;
+        self.other = self.other.checked_add(amount_other).ok_or(StateError::AmountOverflow)?;
+        let trade = HedgeTrade { timestamp, delta_before, amount_hedged, amount_other };
+        self.trades.push(trade);
+        Ok(Some(trade))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{hooks::hook_interface::ModifyLiquidityParams, math::types::TickSpacing};
+
+    const OWNER: Address = Address::repeat_byte(7);
+
+    fn key(token0: u64, token1: u64) -> ManagerPoolKey {
+        ManagerPoolKey::new(
+            Address::from_low_u64_be(token0),
+            Address::from_low_u64_be(token1),
+            3000,
+            TickSpacing::new(60).unwrap(),
+            Address::zero(),
+        )
+        .unwrap()
+    }
+
+    /// Adds a position to a pool at price 1, returning its key
+    fn open(manager: &mut PoolManager, key: &ManagerPoolKey, tick_lower: i32, tick_upper: i32, liquidity: i128) -> PositionKey {
+        if manager.get_pool(key).is_none() {
+            manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+        }
+        let params = ModifyLiquidityParams::default_position(OWNER, tick_lower, tick_upper, liquidity);
+        manager.modify_liquidity(key.clone(), params, &[]).unwrap();
+        PositionKey { owner: OWNER.0, tick_lower, tick_upper, salt: [0; 32] }
+    }
+
+    #[test]
+    fn test_exposure_of_range() {
+        let price = |tick| SqrtPrice::from_tick(tick).unwrap();
+        let below = Exposure::of_range(1_000_000, price(-600), -60, 60);
+        let above = Exposure::of_range(1_000_000, price(600), -60, 60);
+        assert!(below.amount0 > 0.0 && below.amount1 == 0.0);
+        assert!(above.amount0 == 0.0 && above.amount1 > 0.0);
+        // Below the range the delta is the whole position, above it none
+        assert!((below.delta() - 5_999.709).abs() < 0.01);
+        assert_eq!(above.delta(), 0.0);
+
+        // In range the exposure matches what adding the position takes
+        let mut manager = PoolManager::new();
+        let pool = key(10, 20);
+        manager.initialize_pool(pool.clone(), SqrtPrice::ONE).unwrap();
+        let params = ModifyLiquidityParams::default_position(OWNER, -600, 1200, 1_000_000_000);
+        let (added, _) = manager.modify_liquidity(pool.clone(), params, &[]).unwrap();
+        let position = PositionKey { owner: OWNER.0, tick_lower: -600, tick_upper: 1200, salt: [0; 32] };
+        let exposure = position_exposure(&manager, &pool, &position).unwrap();
+        assert!((exposure.amount0 + added.amount0() as f64).abs() < 2.0);
+        assert!((exposure.amount1 + added.amount1() as f64).abs() < 2.0);
+        assert_eq!(exposure.value(1.0), exposure.amount0 + exposure.amount1);
+
+        // Positions add up, and missing ones hold nothing
+        let other = open(&mut manager, &pool, -60, 60, 1_000_000_000);
+        let missing = PositionKey { salt: [1; 32], ..other.clone() };
+        let positions = [(pool.clone(), position), (pool.clone(), other.clone()), (pool.clone(), missing)];
+        let total = portfolio_exposure(&manager, &positions).unwrap();
+        let other = position_exposure(&manager, &pool, &other).unwrap();
+        assert!((total.delta() - exposure.delta() - other.delta()).abs() < 1e-6);
+        assert!(matches!(
+            portfolio_exposure(&manager, &[(key(30, 40), PositionKey::default_position(OWNER.0, -60, 60))]),
+            Err(AnalyticsError::PoolNotInitialized(_))
+        ));
+    }
+
+    #[test]
+    fn test_hedge_scheduler_keeps_delta_neutral() {
+        let mut manager = PoolManager::new();
+        let lp_pool = key(10, 20);
+        let position = open(&mut manager, &lp_pool, -6000, 6000, 1_000_000_000_000);
+        // The hedge pool trades token 10 as its currency1
+        let hedge_pool = key(5, 10);
+        open(&mut manager, &hedge_pool, -887_220, 887_220, 1_000_000_000_000_000);
+
+        let config = HedgeConfig::new(vec![(lp_pool.clone(), position)], hedge_pool.clone())
+            .with_interval(60)
+            .with_threshold(1_000.0);
+        assert!(matches!(
+            HedgeScheduler::new(HedgeConfig { hedge_pool: key(30, 40), ..config.clone() }),
+            Err(AnalyticsError::CurrencyMismatch(_))
+        ));
+        assert!(matches!(
+            HedgeScheduler::new(config.clone().with_interval(0)),
+            Err(AnalyticsError::ZeroInterval)
+        ));
+        let mut scheduler = HedgeScheduler::new(config).unwrap();
+
+        // The position starts long token0, so the first rebalance sells it
+        let delta = scheduler.net_delta(&manager).unwrap();
+        assert!(delta > 1e9);
+        let trade = scheduler.on_block(&mut manager).unwrap().unwrap();
+        assert_eq!(trade.amount_hedged, -(delta.round() as i128));
+        assert!(trade.amount_other > 0);
+        assert!(scheduler.net_delta(&manager).unwrap().abs() < 1.0);
+
+        // A rally leaves the position with less token0, but the scheduler
+        // waits for the next interval to buy some back
+        manager.swap(&lp_pool, false, -10_000_000_000, TickMath::MAX_SQRT_PRICE - 1, &[]).unwrap();
+        let short = scheduler.net_delta(&manager).unwrap();
+        assert!(short < -1e9);
+        manager.set_timestamp(59);
+        assert_eq!(scheduler.on_block(&mut manager).unwrap(), None);
+        manager.set_timestamp(60);
+        let trade = scheduler.on_block(&mut manager).unwrap().unwrap();
+        assert_eq!((trade.timestamp, trade.delta_before), (60, short));
+        assert_eq!(trade.amount_hedged, -(short.round() as i128));
+        assert!(trade.amount_other < 0);
+        assert!(scheduler.net_delta(&manager).unwrap().abs() < 1.0);
+        assert_eq!(scheduler.trades().len(), 2);
+
+        // Moves within the threshold are left unhedged
+        manager.swap(&lp_pool, true, -100, TickMath::MIN_SQRT_PRICE + 1, &[]).unwrap();
+        manager.set_timestamp(120);
+        assert_eq!(scheduler.on_block(&mut manager).unwrap(), None);
+        assert_eq!(scheduler.hedge_balances().0, trade.amount_hedged + scheduler.trades()[0].amount_hedged);
+    }
+}
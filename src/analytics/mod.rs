@@ -0,0 +1,38 @@
+//! Analytics of LP positions for strategy research
+//!
+//! Enabled with the `analytics` feature. APIs in this module may change
+//! without notice.
+//!
+//! The [`hedge`] module measures the token0 exposure of positions at the
+//! current price and runs a [`HedgeScheduler`] that keeps a portfolio of
+//! positions delta-neutral by swapping in a paired pool as blocks go by.
+
+pub mod hedge;
+
+pub use hedge::*;
+
+use thiserror::Error;
+
+use crate::core::{pool_manager::PoolId, state::StateError};
+
+/// Error types for analytics
+#[derive(Debug, Error)]
+pub enum AnalyticsError {
+    #[error("Pool {0} is not initialized")]
+    PoolNotInitialized(PoolId),
+
+    #[error("Pool {0} does not trade the hedged currency")]
+    CurrencyMismatch(PoolId),
+
+    #[error("Rebalance interval must be at least one second")]
+    ZeroInterval,
+
+    #[error("Hedge threshold must be a non-negative number, got {0}")]
+    InvalidThreshold(f64),
+
+    #[error("Hedge swap failed: {0}")]
+    Swap(#[from] StateError),
+}
+
+/// Result type for analytics
+pub type AnalyticsResult<T> = std::result::Result<T, AnalyticsError>;
@@ -0,0 +1,359 @@
+//! Command line simulator for the v4 pool engine
+//!
+//! Every subcommand prints its result as JSON on stdout, with 128- and
+//! 256-bit amounts as decimal strings. Subcommands reading the chain take a
+//! node URL from `--rpc-url` or the `ETH_RPC_URL` environment variable.
+
+use std::{path::PathBuf, sync::Arc};
+
+use anyhow::{anyhow, bail, Context};
+use clap::{Args, Parser, Subcommand};
+use ethers::{
+    providers::{Http, Middleware, Provider},
+    types::{Address, H256, U256},
+};
+use serde_json::{json, Value};
+use uniswap_v4_core::{
+    core::{
+        math::{tick_math::TickMath, types::TickSpacing},
+        pool_manager::{ManagerPoolKey, QuoteRequest},
+        state::PositionKey,
+    },
+    replay::{
+        fetch_events, fetch_pool_state, onchain_pool_id, PoolEvent, PoolSnapshot, PoolState, Replayer,
+        SnapshotOptions,
+    },
+    scenario::{PoolSpec, Scenario, Step},
+    vectors::{VectorCounts, VectorSet},
+};
+
+#[derive(Parser)]
+#[command(name = "uniswap-v4-sim", version, about = "Simulate Uniswap v4 pools")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Quote a swap on a pool built from flags or taken from a scenario
+    Quote(QuoteArgs),
+    /// Run a JSON scenario and print the outcome of every step and the final state
+    RunScenario {
+        file: PathBuf,
+    },
+    /// Read the full state of a pool from a `PoolManager` at a block
+    ForkPool {
+        /// Address of the `PoolManager`
+        manager: Address,
+        /// Pool key as `currency0,currency1,fee,tickSpacing[,hooks]`
+        #[arg(value_parser = parse_key)]
+        key: ManagerPoolKey,
+        /// Block to read at, by default the latest
+        #[arg(long)]
+        block: Option<u64>,
+        #[command(flatten)]
+        node: NodeArgs,
+    },
+    /// Replay a transaction on pools forked at the previous block
+    Replay {
+        /// Hash of the transaction
+        tx: H256,
+        /// Address of the `PoolManager`
+        #[arg(long)]
+        manager: Address,
+        /// Key of a pool to fork, as `currency0,currency1,fee,tickSpacing[,hooks]`;
+        /// events of other pools are skipped
+        #[arg(long = "key", value_parser = parse_key, required = true)]
+        keys: Vec<ManagerPoolKey>,
+        #[command(flatten)]
+        node: NodeArgs,
+    },
+    /// Generate test vectors of the tick math, swap steps and swaps, the
+    /// same for the same seed and count
+    TestVectors {
+        /// Seed the vectors are drawn from
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+        /// Number of vectors of each kind
+        #[arg(long, default_value_t = 100)]
+        count: usize,
+    },
+}
+
+#[derive(Args)]
+struct QuoteArgs {
+    /// Amount to swap, negative for exact input and positive for exact output
+    #[arg(long, allow_hyphen_values = true)]
+    amount: i128,
+    /// Swap token1 for token0 instead of token0 for token1
+    #[arg(long)]
+    one_for_zero: bool,
+    /// Price limit as a decimal sqrt price X96, by default the bound of the direction
+    #[arg(long, value_parser = parse_u256)]
+    limit: Option<U256>,
+    /// Quote on a pool of a scenario after its steps ran
+    #[arg(long, requires = "pool")]
+    scenario: Option<PathBuf>,
+    /// Name of the scenario pool to quote on
+    #[arg(long)]
+    pool: Option<String>,
+    /// Starting tick of a pool built from flags
+    #[arg(long, default_value_t = 0, allow_hyphen_values = true, conflicts_with = "scenario")]
+    tick: i32,
+    /// LP fee in pips of a pool built from flags
+    #[arg(long, default_value_t = 3000, conflicts_with = "scenario")]
+    fee: u32,
+    /// Tick spacing of a pool built from flags
+    #[arg(long, default_value_t = 60, conflicts_with = "scenario")]
+    tick_spacing: i32,
+    /// Full range liquidity of a pool built from flags
+    #[arg(long, default_value_t = 1_000_000_000_000_000_000, conflicts_with = "scenario")]
+    liquidity: i128,
+}
+
+#[derive(Args)]
+struct NodeArgs {
+    /// HTTP URL of an archive node
+    #[arg(long, env = "ETH_RPC_URL")]
+    rpc_url: String,
+}
+
+impl NodeArgs {
+    fn provider(&self) -> anyhow::Result<Arc<Provider<Http>>> {
+        Ok(Arc::new(Provider::<Http>::try_from(self.rpc_url.as_str()).context("invalid RPC URL")?))
+    }
+}
+
+fn parse_key(value: &str) -> Result<ManagerPoolKey, String> {
+    let parts: Vec<&str> = value.split(',').map(str::trim).collect();
+    let (currency0, currency1, fee, tick_spacing, hooks) = match parts.as_slice() {
+        [c0, c1, fee, spacing] => (c0, c1, fee, spacing, None),
+        [c0, c1, fee, spacing, hooks] => (c0, c1, fee, spacing, Some(hooks)),
+        _ => return Err("expected currency0,currency1,fee,tickSpacing[,hooks]".to_string()),
+    };
+    let address = |value: &str| value.parse::<Address>().map_err(|e| format!("invalid address {value}: {e}"));
+    let tick_spacing = tick_spacing.parse::<i32>().map_err(|e| format!("invalid tick spacing: {e}"))?;
+    ManagerPoolKey::new(
+        address(currency0)?,
+        address(currency1)?,
+        fee.parse().map_err(|e| format!("invalid fee: {e}"))?,
+        TickSpacing::new(tick_spacing).map_err(|e| e.to_string())?,
+        hooks.map(|hooks| address(hooks)).transpose()?.unwrap_or_default(),
+    )
+    .map_err(|e| e.to_string())
+}
+
+fn parse_u256(value: &str) -> Result<U256, String> {
+    U256::from_dec_str(value).map_err(|e| e.to_string())
+}
+
+fn read_scenario(file: &PathBuf) -> anyhow::Result<Scenario> {
+    let json = std::fs::read_to_string(file).with_context(|| format!("cannot read {}", file.display()))?;
+    Ok(Scenario::from_json(&json)?)
+}
+
+fn quote(args: QuoteArgs) -> anyhow::Result<Value> {
+    let (scenario, pool) = match (&args.scenario, &args.pool) {
+        (Some(file), Some(pool)) => (read_scenario(file)?, pool.clone()),
+        _ => {
+            let spacing = TickSpacing::new(args.tick_spacing).map_err(|e| anyhow!("invalid tick spacing: {e}"))?;
+            let scenario = Scenario {
+                pools: vec![PoolSpec {
+                    name: "pool".to_string(),
+                    currency0: Address::from_low_u64_be(1),
+                    currency1: Address::from_low_u64_be(2),
+                    fee: args.fee,
+                    tick_spacing: args.tick_spacing,
+                    hooks: Address::zero(),
+                    tick: args.tick,
+                    sqrt_price_x96: None,
+                }],
+                steps: vec![Step::ModifyLiquidity {
+                    pool: "pool".to_string(),
+                    owner: Address::zero(),
+                    tick_lower: spacing.min_usable_tick(),
+                    tick_upper: spacing.max_usable_tick(),
+                    liquidity_delta: args.liquidity,
+                    salt: H256::zero(),
+                }],
+            };
+            (scenario, "pool".to_string())
+        }
+    };
+    let run = scenario.run()?;
+    if let Some(Err(error)) = run.outcomes.iter().map(|outcome| &outcome.result).find(|result| result.is_err()) {
+        if args.scenario.is_none() {
+            bail!("cannot add liquidity: {error}");
+        }
+    }
+    let key = run.keys.get(&pool).ok_or_else(|| anyhow!("scenario has no pool {pool}"))?;
+
+    let zero_for_one = !args.one_for_zero;
+    let default_limit = if zero_for_one { TickMath::MIN_SQRT_PRICE + 1 } else { TickMath::MAX_SQRT_PRICE - 1 };
+    let quote = run.manager.quote_view().quote(&QuoteRequest {
+        key: key.clone(),
+        zero_for_one,
+        amount_specified: args.amount,
+        sqrt_price_limit_x96: args.limit.unwrap_or(default_limit),
+    })?;
+    let sqrt_price_x96 = quote.sqrt_price_x96.to_u256();
+    let tick = TickMath::get_tick_at_sqrt_price(sqrt_price_x96).map_err(|e| anyhow!("{e}"))?;
+    Ok(json!({
+        "amount0": quote.delta.amount0().to_string(),
+        "amount1": quote.delta.amount1().to_string(),
+        "sqrt_price_x96": sqrt_price_x96.to_string(),
+        "tick": tick,
+    }))
+}
+
+fn snapshot_json(snapshot: PoolSnapshot) -> Value {
+    json!({
+        "sqrt_price_x96": snapshot.sqrt_price_x96.to_string(),
+        "tick": snapshot.tick,
+        "liquidity": snapshot.liquidity.to_string(),
+    })
+}
+
+fn pool_state_json(state: &PoolState) -> Value {
+    let ticks: Vec<Value> = state.ticks
+        .iter()
+        .map(|tick| json!({
+            "tick": tick.tick,
+            "liquidity_gross": tick.liquidity_gross.to_string(),
+            "liquidity_net": tick.liquidity_net.to_string(),
+            "fee_growth_outside_0_x128": tick.fee_growth_outside_0_x128.to_string(),
+            "fee_growth_outside_1_x128": tick.fee_growth_outside_1_x128.to_string(),
+        }))
+        .collect();
+    let positions: Vec<Value> = state.positions
+        .iter()
+        .map(|position| json!({
+            "owner": Address::from(position.key.owner),
+            "tick_lower": position.key.tick_lower,
+            "tick_upper": position.key.tick_upper,
+            "salt": H256(position.key.salt),
+            "liquidity": position.liquidity.to_string(),
+            "fee_growth_inside_0_last_x128": position.fee_growth_inside_0_last_x128.to_string(),
+            "fee_growth_inside_1_last_x128": position.fee_growth_inside_1_last_x128.to_string(),
+        }))
+        .collect();
+    json!({
+        "pool_id": state.pool_id.to_string(),
+        "sqrt_price_x96": state.sqrt_price_x96.to_string(),
+        "tick": state.tick,
+        "protocol_fee": state.protocol_fee,
+        "lp_fee": state.lp_fee,
+        "fee_growth_global_0_x128": state.fee_growth_global_0_x128.to_string(),
+        "fee_growth_global_1_x128": state.fee_growth_global_1_x128.to_string(),
+        "liquidity": state.liquidity.to_string(),
+        "ticks": ticks,
+        "positions": positions,
+        "block_number": state.provenance.block_number,
+        "block_hash": state.provenance.block_hash,
+        "timestamp": state.provenance.timestamp,
+    })
+}
+
+async fn fork_pool(manager: Address, key: ManagerPoolKey, block: Option<u64>, node: NodeArgs) -> anyhow::Result<Value> {
+    let client = node.provider()?;
+    let block = match block {
+        Some(block) => block,
+        None => client.get_block_number().await?.as_u64(),
+    };
+    let state = fetch_pool_state(
+        client,
+        manager,
+        onchain_pool_id(&key),
+        key.tick_spacing().get(),
+        &[],
+        block,
+        SnapshotOptions::default(),
+    )
+    .await?;
+    Ok(pool_state_json(&state))
+}
+
+async fn replay(tx: H256, manager: Address, keys: Vec<ManagerPoolKey>, node: NodeArgs) -> anyhow::Result<Value> {
+    let client = node.provider()?;
+    let receipt = client.get_transaction_receipt(tx).await?.ok_or_else(|| anyhow!("transaction {tx:?} not found"))?;
+    let block = receipt.block_number.ok_or_else(|| anyhow!("transaction {tx:?} is pending"))?.as_u64();
+    let Some(last_log) = receipt.logs.iter().filter_map(|log| log.log_index).max() else {
+        bail!("transaction {tx:?} emitted no events");
+    };
+    let first_log = receipt.logs.iter().filter_map(|log| log.log_index).min().unwrap_or(last_log);
+
+    // Events of the block up to the transaction, including earlier
+    // transactions on the same pools
+    let forked: Vec<_> = keys.iter().map(onchain_pool_id).collect();
+    let events: Vec<_> = fetch_events(&*client, manager, block, block, 1)
+        .await?
+        .into_iter()
+        .filter(|event| U256::from(event.log_index) <= last_log && forked.contains(&event.pool_id()))
+        .collect();
+
+    // Positions the events modify must be forked too, or removals would fail
+    let mut replayer = Replayer::new();
+    for (key, pool_id) in keys.into_iter().zip(&forked) {
+        let positions: Vec<PositionKey> = events
+            .iter()
+            .filter_map(|event| match &event.event {
+                PoolEvent::ModifyLiquidityFilter(modify) if event.pool_id() == *pool_id => Some(PositionKey {
+                    owner: modify.sender.0,
+                    tick_lower: modify.tick_lower,
+                    tick_upper: modify.tick_upper,
+                    salt: modify.salt,
+                }),
+                _ => None,
+            })
+            .collect();
+        let state = fetch_pool_state(
+            client.clone(),
+            manager,
+            *pool_id,
+            key.tick_spacing().get(),
+            &positions,
+            block - 1,
+            SnapshotOptions::default(),
+        )
+        .await?;
+        replayer.fork_pool(key, &state)?;
+    }
+
+    let divergences: Vec<Value> = replayer
+        .apply_all(&events)?
+        .into_iter()
+        .filter(|divergence| divergence.log_index.is_some_and(|index| U256::from(index) >= first_log))
+        .map(|divergence| json!({
+            "log_index": divergence.log_index,
+            "pool_id": divergence.pool_id.to_string(),
+            "mismatch": format!("{:?}", divergence.mismatch),
+        }))
+        .collect();
+    let pools: Vec<Value> = forked
+        .iter()
+        .map(|pool_id| json!({
+            "pool_id": pool_id.to_string(),
+            "state": replayer.snapshot(pool_id).map(snapshot_json),
+        }))
+        .collect();
+    Ok(json!({
+        "block_number": block,
+        "events_applied": replayer.events_applied(),
+        "divergences": divergences,
+        "pools": pools,
+    }))
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let result = match Cli::parse().command {
+        Command::Quote(args) => quote(args)?,
+        Command::RunScenario { file } => read_scenario(&file)?.run()?.to_json(),
+        Command::ForkPool { manager, key, block, node } => fork_pool(manager, key, block, node).await?,
+        Command::Replay { tx, manager, keys, node } => replay(tx, manager, keys, node).await?,
+        Command::TestVectors { seed, count } => serde_json::to_value(VectorSet::generate(seed, VectorCounts::uniform(count)))?,
+    };
+    println!("{}", serde_json::to_string_pretty(&result)?);
+    Ok(())
+}
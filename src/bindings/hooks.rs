@@ -0,0 +1,18 @@
+use ethers::contract::abigen;
+
+// Generate bindings for the errors a v4 hook call can revert with
+//
+// The `PoolManager` wraps a hook's revert in `WrappedError`, with the hook's
+// address, the selector of the callback and the hook's own revert data.
+// `Panic` is the Solidity builtin, declared so it decodes like the others.
+abigen!(
+    IHooks,
+    r#"[
+        error WrappedError(address target, bytes4 selector, bytes reason, bytes details)
+        error HookCallFailed()
+        error InvalidHookResponse()
+        error HookDeltaExceedsSwapAmount()
+        error HookAddressNotValid(address hooks)
+        error Panic(uint256 code)
+    ]"#,
+);
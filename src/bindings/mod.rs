@@ -1,3 +1,5 @@
+pub mod hooks;
 pub mod token;
 
+pub use hooks::*;
 pub use token::*;
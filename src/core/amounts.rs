@@ -0,0 +1,181 @@
+//! Sign convention of swap amounts
+//!
+//! Internally, as in the contracts, a swap takes one signed `amount_specified`
+//! and returns a signed [`BalanceDelta`], both from the caller's side:
+//!
+//! | value                      | negative                        | positive                  |
+//! |----------------------------|---------------------------------|---------------------------|
+//! | `amount_specified`         | exact input: pay this much      | exact output: receive it  |
+//! | `BalanceDelta` per currency| the caller owes (paid in)       | the caller is owed (out)  |
+//!
+//! A flipped sign does not fail, it swaps a different amount, so callers
+//! state amounts as a [`SwapAmount`] and read results as [`SwapFlows`]
+//! instead of negating by hand. These are the only conversions between the
+//! unsigned amounts users think in and the signed ones the pools use.
+
+use crate::core::state::{BalanceDelta, Result as StateResult, StateError};
+
+/// Amount of a swap, as a user states it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SwapAmount {
+    /// Pay exactly this much of the input currency
+    ExactIn(u128),
+    /// Receive exactly this much of the output currency
+    ExactOut(u128),
+}
+
+impl SwapAmount {
+    /// The unsigned amount, of the input or output currency
+    pub fn amount(&self) -> u128 {
+        match *self {
+            SwapAmount::ExactIn(amount) | SwapAmount::ExactOut(amount) => amount,
+        }
+    }
+
+    /// Whether the input amount is fixed
+    pub fn is_exact_input(&self) -> bool {
+        matches!(self, SwapAmount::ExactIn(_))
+    }
+
+    /// The signed `amount_specified` of the swap
+    ///
+    /// Fails for zero, which swaps are rejected for, and for amounts beyond
+    /// `i128::MAX`, which can't be negated.
+    pub fn amount_specified(&self) -> StateResult<i128> {
+        let amount = i128::try_from(self.amount()).map_err(|_| StateError::AmountOverflow)?;
+        match self {
+            _ if amount == 0 => Err(StateError::SwapAmountCannotBeZero),
+            SwapAmount::ExactIn(_) => Ok(-amount),
+            SwapAmount::ExactOut(_) => Ok(amount),
+        }
+    }
+
+    /// The amount a signed `amount_specified` stands for
+    pub fn from_amount_specified(amount_specified: i128) -> StateResult<Self> {
+        match amount_specified {
+            0 => Err(StateError::SwapAmountCannotBeZero),
+            i128::MIN => Err(StateError::AmountOverflow),
+            amount if amount < 0 => Ok(SwapAmount::ExactIn(amount.unsigned_abs())),
+            amount => Ok(SwapAmount::ExactOut(amount as u128)),
+        }
+    }
+}
+
+impl TryFrom<SwapAmount> for i128 {
+    type Error = StateError;
+
+    fn try_from(amount: SwapAmount) -> StateResult<i128> {
+        amount.amount_specified()
+    }
+}
+
+impl TryFrom<i128> for SwapAmount {
+    type Error = StateError;
+
+    fn try_from(amount_specified: i128) -> StateResult<Self> {
+        Self::from_amount_specified(amount_specified)
+    }
+}
+
+/// What a swap took from and gave to the caller, unsigned
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct SwapFlows {
+    /// Amount of the input currency the caller owes
+    pub paid: u128,
+    /// Amount of the output currency the caller is owed
+    pub received: u128,
+}
+
+impl SwapFlows {
+    /// Reads the flows of a swap's delta in a direction
+    ///
+    /// A currency the caller is owed on the input side, or owes on the output
+    /// side, as hook deltas can cause, counts as zero.
+    pub fn of(delta: BalanceDelta, zero_for_one: bool) -> Self {
+        let (input, output) = if zero_for_one {
+            (delta.amount0(), delta.amount1())
+        } else {
+            (delta.amount1(), delta.amount0())
+        };
+        Self {
+            paid: input.min(0).unsigned_abs(),
+            received: output.max(0) as u128,
+        }
+    }
+
+    /// Whether the swap took all of an exact input, or gave all of an exact
+    /// output
+    pub fn fills(&self, amount: SwapAmount) -> bool {
+        match amount {
+            SwapAmount::ExactIn(amount) => self.paid == amount,
+            SwapAmount::ExactOut(amount) => self.received == amount,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{
+        math::{tick_math::TickMath, types::{SqrtPrice, TickSpacing}, FeePips},
+        state::Pool,
+    };
+
+    #[test]
+    fn test_amount_specified_signs() {
+        let cases = [
+            (SwapAmount::ExactIn(1), -1),
+            (SwapAmount::ExactOut(1), 1),
+            (SwapAmount::ExactIn(i128::MAX as u128), -i128::MAX),
+            (SwapAmount::ExactOut(i128::MAX as u128), i128::MAX),
+        ];
+        for (amount, amount_specified) in cases {
+            assert_eq!(amount.amount_specified().unwrap(), amount_specified, "{amount:?}");
+            assert_eq!(SwapAmount::try_from(amount_specified).unwrap(), amount);
+            assert_eq!(i128::try_from(amount).unwrap(), amount_specified);
+        }
+
+        for amount in [SwapAmount::ExactIn(0), SwapAmount::ExactOut(0)] {
+            assert!(matches!(amount.amount_specified(), Err(StateError::SwapAmountCannotBeZero)));
+        }
+        for amount in [SwapAmount::ExactIn(i128::MAX as u128 + 1), SwapAmount::ExactOut(u128::MAX)] {
+            assert!(matches!(amount.amount_specified(), Err(StateError::AmountOverflow)));
+        }
+        assert!(matches!(SwapAmount::from_amount_specified(0), Err(StateError::SwapAmountCannotBeZero)));
+        assert!(matches!(SwapAmount::from_amount_specified(i128::MIN), Err(StateError::AmountOverflow)));
+        assert!(SwapAmount::ExactIn(5).is_exact_input() && !SwapAmount::ExactOut(5).is_exact_input());
+    }
+
+    #[test]
+    fn test_flows_follow_the_swap_direction() {
+        let spacing = TickSpacing::new(60).unwrap();
+        for zero_for_one in [true, false] {
+            for amount in [SwapAmount::ExactIn(10_000), SwapAmount::ExactOut(10_000)] {
+                let mut pool = Pool::new();
+                pool.initialize(SqrtPrice::ONE, FeePips::new(3000)).unwrap();
+                pool.modify_position([1; 20], -600, 600, 1_000_000_000, spacing, [0; 32]).unwrap();
+                let limit = SqrtPrice::new(if zero_for_one {
+                    TickMath::MIN_SQRT_PRICE + 1
+                } else {
+                    TickMath::MAX_SQRT_PRICE - 1
+                });
+                let (delta, _) = pool.swap(amount.amount_specified().unwrap(), limit, zero_for_one, spacing, None).unwrap();
+
+                let flows = SwapFlows::of(delta, zero_for_one);
+                assert!(flows.fills(amount), "{zero_for_one} {amount:?} {flows:?}");
+                // The fee is charged on the input side
+                assert!(flows.paid > flows.received, "{zero_for_one} {amount:?} {flows:?}");
+                // The price moves against the input currency
+                assert_eq!(pool.slot0.sqrt_price_x96 < SqrtPrice::ONE, zero_for_one);
+            }
+        }
+    }
+
+    #[test]
+    fn test_flows_ignore_wrong_sided_amounts() {
+        let flows = SwapFlows::of(BalanceDelta::new(5, -7), true);
+        assert_eq!(flows, SwapFlows::default());
+        let flows = SwapFlows::of(BalanceDelta::new(5, -7), false);
+        assert_eq!(flows, SwapFlows { paid: 7, received: 5 });
+    }
+}
@@ -1,7 +1,7 @@
 use crate::core::flash_loan::error::FlashLoanError;
 use ethers::types::Address;
 use primitive_types::U256;
-use super::{Currency, types::FlashCallbackData, FlashLoanResult};
+use super::{Currency, types::FlashCallbackData, FlashLoanResult, FlashLoanManager};
 
 /// Callback interface for flash loans
 /// This trait should be implemented by users of flash loans
@@ -14,6 +14,18 @@ pub trait FlashLoanCallback {
     /// # Returns
     /// Any data to be returned from the unlock call, or an error
     fn unlock_callback(&mut self, data: &[u8]) -> Result<Vec<u8>, FlashLoanError>;
+
+    /// Called by the flash loan manager with access to itself, so the callback
+    /// can take and settle currencies while unlocked
+    ///
+    /// Defaults to `unlock_callback` for callbacks that don't borrow.
+    fn unlock_callback_with_manager(
+        &mut self,
+        _manager: &mut FlashLoanManager,
+        data: &[u8],
+    ) -> Result<Vec<u8>, FlashLoanError> {
+        self.unlock_callback(data)
+    }
 }
 
 /// Callback that does nothing, useful for testing
@@ -4,6 +4,21 @@ use std::sync::{Arc, RwLock};
 use std::fmt;
 
 /// Currency represents a token that can be used in the protocol
+///
+/// ERC6909 claims on a currency have the currency's address as their ID,
+/// `uint160(currency)` as in the Solidity `CurrencyLibrary.toId`, so IDs and
+/// claimable currencies map one to one:
+///
+/// | Currency        | Claim ID                  |
+/// |-----------------|---------------------------|
+/// | `Native`        | `0`                       |
+/// | `Erc20(token)`  | `uint160(token)`, nonzero |
+///
+/// IDs of `2^160` and above belong to no currency and are rejected by
+/// [`from_id`](Self::from_id). The zero address is the native currency, so
+/// build currencies from addresses with [`from_address`](Self::from_address)
+/// rather than `Erc20(Address::zero())`. Protocol operated `Pool` tokens are
+/// outside the scheme: they can't be claimed, and their ID is their own.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum Currency {
     /// Native token (ETH on Ethereum)
@@ -18,16 +33,25 @@ pub enum Currency {
 pub const ZERO_ADDRESS: Address = Address::zero();
 
 impl Currency {
-    /// Creates a new currency from a token ID
-    pub fn from_id(id: U256) -> Self {
-        // Simple implementation to convert a U256 ID to a Currency
-        // In a real implementation, this would decode the ID appropriately
-        Self::Pool(id)
+    /// Gets the currency whose ERC6909 claim token ID is `id`, the inverse
+    /// of [`to_id`](Self::to_id) for claimable currencies
+    pub fn from_id(id: U256) -> Result<Self, InvalidCurrencyId> {
+        if id.bits() > 160 {
+            return Err(InvalidCurrencyId(id));
+        }
+        let mut bytes = [0u8; 32];
+        id.to_big_endian(&mut bytes);
+        Ok(Self::from_address(Address::from_slice(&bytes[12..])))
     }
     
-    /// Creates a new currency from an address
+    /// Creates a new currency from an address, the zero address being the
+    /// native currency
     pub fn from_address(address: Address) -> Self {
-        Self::Erc20(address)
+        if address == ZERO_ADDRESS {
+            Self::Native
+        } else {
+            Self::Erc20(address)
+        }
     }
     
     /// Checks if this is the native currency
@@ -60,6 +84,18 @@ impl Currency {
             _ => None,
         }
     }
+    
+    /// Gets the ERC6909 claim token ID for this currency
+    ///
+    /// Native maps to zero, ERC20 tokens to their address as a number and pool
+    /// tokens to their own ID, see the [scheme](Self).
+    pub fn to_id(&self) -> U256 {
+        match self {
+            Self::Native => U256::zero(),
+            Self::Erc20(address) => U256::from_big_endian(address.as_bytes()),
+            Self::Pool(id) => *id,
+        }
+    }
 }
 
 impl fmt::Display for Currency {
@@ -72,6 +108,12 @@ impl fmt::Display for Currency {
     }
 }
 
+/// Claim token ID that is not the ID of a currency, being wider than an
+/// address
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("Claim ID {0:#x} is not a currency address")]
+pub struct InvalidCurrencyId(pub U256);
+
 /// Represents a delta (positive or negative) for a specific currency
 #[derive(Debug, Clone, Copy)]
 pub struct CurrencyDelta {
@@ -86,7 +128,7 @@ impl CurrencyDelta {
 }
 
 /// Stores the currency deltas for all currencies and accounts
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct CurrencyDeltaTracker {
     // Maps (address, currency) to delta
     deltas: HashMap<(Address, Currency), i128>,
@@ -194,4 +236,30 @@ impl SharedCurrencyDeltaTracker {
     pub fn clear_all_deltas(&self) {
         self.inner.write().unwrap().clear_all_deltas()
     }
-} 
\ No newline at end of file
+} 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_claim_ids_round_trip() {
+        let token = Address::from_low_u64_be(0xabcdef);
+        let max = Address::repeat_byte(0xff);
+        for currency in [Currency::Native, Currency::from_address(token), Currency::from_address(max)] {
+            assert_eq!(Currency::from_id(currency.to_id()), Ok(currency));
+        }
+        assert_eq!(Currency::from_address(token).to_id(), U256::from(0xabcdef));
+        assert_eq!(Currency::from_address(Address::zero()), Currency::Native);
+
+        for id in [U256::zero(), U256::one(), U256::from(0xabcdef), (U256::one() << 160) - 1] {
+            assert_eq!(Currency::from_id(id).unwrap().to_id(), id);
+        }
+    }
+
+    #[test]
+    fn test_ids_wider_than_an_address_are_rejected() {
+        for id in [U256::one() << 160, U256::MAX] {
+            assert_eq!(Currency::from_id(id), Err(InvalidCurrencyId(id)));
+        }
+    }
+}
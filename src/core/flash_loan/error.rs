@@ -22,6 +22,9 @@ pub enum FlashLoanError {
     #[error("Protocol fee too large: {0}")]
     ProtocolFeeTooLarge(u32),
     
+    #[error("Flash loan fee too large: {0} bps")]
+    FlashFeeTooLarge(u32),
+    
     #[error("Protocol fee currency synced")]
     ProtocolFeeCurrencySynced,
     
@@ -49,9 +52,22 @@ pub enum FlashLoanError {
     #[error("Reentrancy error")]
     ReentrancyError,
     
+    #[error("Currency {0} is not allowed")]
+    CurrencyNotAllowed(super::Currency),
+    
+    #[error("Take cap of {cap} exceeded for {currency}: {taken} taken in this unlock")]
+    TakeCapExceeded {
+        currency: super::Currency,
+        cap: u128,
+        taken: u128,
+    },
+    
     #[error("Not called in callback")]
     NotCalledInCallback,
     
+    #[error("Token transfer failed: {0}")]
+    TransferFailed(Box<super::LedgerError>),
+    
     #[error("{0}")]
     Other(String),
 }
@@ -65,4 +81,10 @@ impl From<LockError> for FlashLoanError {
             LockError::ManagerLocked => FlashLoanError::ManagerLocked,
         }
     }
-} 
\ No newline at end of file
+}
+
+impl From<super::LedgerError> for FlashLoanError {
+    fn from(err: super::LedgerError) -> Self {
+        FlashLoanError::TransferFailed(Box::new(err))
+    }
+}
@@ -2,6 +2,7 @@ use ethers::types::{Address, U256};
 use crate::core::flash_loan::{
     FlashLoanCallback,
     FlashLoanError,
+    FlashLoanManager,
     Currency,
 };
 use crate::core::pool_manager::PoolManager;
@@ -13,7 +14,7 @@ use std::sync::Arc;
 #[derive(Clone)]
 pub struct FlashLoanExecutor {
     pub take_operations: Vec<(Currency, Address, u128)>,
-    pub settle_operations: Vec<(Address, U256)>,
+    pub settle_operations: Vec<(Currency, Address, U256)>,
 }
 
 impl FlashLoanExecutor {
@@ -28,8 +29,8 @@ impl FlashLoanExecutor {
         self.take_operations.push((currency, to, amount));
     }
     
-    pub fn add_settle(&mut self, recipient: Address, value: U256) {
-        self.settle_operations.push((recipient, value));
+    pub fn add_settle(&mut self, currency: Currency, recipient: Address, value: U256) {
+        self.settle_operations.push((currency, recipient, value));
     }
 }
 
@@ -45,13 +46,30 @@ impl FlashLoanCallback for FlashLoanExecutor {
         }
         
         // Execute all settle operations
-        for (recipient, value) in &self.settle_operations {
+        for (_currency, recipient, value) in &self.settle_operations {
             // Same as above, we'd call directly to the manager's internal methods
             println!("Settling {} tokens to address {:?}", value, recipient);
         }
         
         Ok(Vec::new())
     }
+
+    fn unlock_callback_with_manager(
+        &mut self,
+        manager: &mut FlashLoanManager,
+        _data: &[u8],
+    ) -> Result<Vec<u8>, FlashLoanError> {
+        for (currency, to, amount) in &self.take_operations {
+            manager.take(*currency, *to, *amount)?;
+        }
+        
+        for (currency, recipient, value) in &self.settle_operations {
+            manager.sync(*currency);
+            manager.settle(*recipient, *value)?;
+        }
+        
+        Ok(Vec::new())
+    }
 }
 
 /// Simple Flash Loan example
@@ -96,8 +114,9 @@ impl SimpleFlashLoanExample {
         // Add the take operation
         executor.add_take(self.currency, self.recipient, self.amount);
         
-        // Add the settle operation
-        executor.add_settle(self.recipient, U256::from(self.amount));
+        // Add the settle operation, repaying the principal plus the flash loan fee
+        let repay_amount = self.amount + pool_manager.flash_fee(self.currency, self.amount);
+        executor.add_settle(self.currency, self.recipient, U256::from(repay_amount));
         
         // Execute the flash loan through the unlock mechanism
         println!("Executing flash loan through unlock mechanism");
@@ -173,8 +192,9 @@ impl ArbitrageFlashLoanExample {
         // Add the take operation
         executor.add_take(self.borrow_currency, self.recipient, self.borrow_amount);
         
-        // Add the settle operation
-        executor.add_settle(self.recipient, U256::from(self.borrow_amount));
+        // Add the settle operation, repaying the principal plus the flash loan fee
+        let repay_amount = self.borrow_amount + pool_manager.flash_fee(self.borrow_currency, self.borrow_amount);
+        executor.add_settle(self.borrow_currency, self.recipient, U256::from(repay_amount));
         
         // Execute the flash loan through the unlock mechanism
         println!("Executing arbitrage flash loan through unlock mechanism");
@@ -215,7 +235,6 @@ impl MultiTokenFlashLoanExample {
     pub fn add_loan(mut self, currency: Currency, amount: u128) -> Self {
         self.loans.push((currency, amount));
         self.executor.add_take(currency, self.recipient, amount);
-        self.executor.add_settle(self.recipient, U256::from(amount));
         self
     }
     
@@ -228,8 +247,12 @@ impl MultiTokenFlashLoanExample {
             println!("Borrowing {} tokens of currency {:?}", amount, currency);
         }
         
-        // Create a mutable reference to the executor
+        // Repay every loan with the principal plus the flash loan fee
         let mut executor = self.executor.clone();
+        for (currency, amount) in &self.loans {
+            let repay_amount = amount + pool_manager.flash_fee(*currency, *amount);
+            executor.add_settle(*currency, self.recipient, U256::from(repay_amount));
+        }
         pool_manager.unlock(&mut executor, &[])?;
         
         println!("Multi-token flash loan completed successfully");
@@ -0,0 +1,162 @@
+use ethers::types::Address;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::tokens::CurrencyMetadata;
+
+use super::{AccountDelta, Currency};
+
+/// What changed an account's delta
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DeltaReason {
+    /// The swapper's side of a swap
+    Swap,
+    /// Tokens paid into or out of a position by a liquidity change
+    ModifyLiquidity,
+    /// A delta returned by a hook, owed to or by the hook
+    Hook,
+    /// A donation, owed by the donor
+    Donate,
+    /// A currency taken from the manager
+    Take,
+    /// A currency paid to the manager
+    Settle,
+    /// A positive delta forfeited with `clear`
+    Clear,
+    /// ERC6909 claims minted
+    Mint,
+    /// ERC6909 claims burned
+    Burn,
+    /// A direct call to `update_delta`
+    Other,
+}
+
+impl DeltaReason {
+    /// Lowercase tag of the reason, as exported
+    pub fn tag(self) -> &'static str {
+        match self {
+            DeltaReason::Swap => "swap",
+            DeltaReason::ModifyLiquidity => "modify_liquidity",
+            DeltaReason::Hook => "hook",
+            DeltaReason::Donate => "donate",
+            DeltaReason::Take => "take",
+            DeltaReason::Settle => "settle",
+            DeltaReason::Clear => "clear",
+            DeltaReason::Mint => "mint",
+            DeltaReason::Burn => "burn",
+            DeltaReason::Other => "other",
+        }
+    }
+}
+
+impl fmt::Display for DeltaReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.tag())
+    }
+}
+
+/// One change of an account's delta in a currency
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JournalEntry {
+    pub account: Address,
+    pub currency: Currency,
+    /// Change of the delta, positive when the account is credited
+    pub amount: i128,
+    pub reason: DeltaReason,
+    /// The account's delta in the currency after the change
+    pub delta_after: i128,
+}
+
+/// Append-only record of the delta changes of one unlock, in the order they
+/// were made
+///
+/// Operations of a batched unlock that fail are rolled back, and so are
+/// their entries; everything else stays, including the entries of an unlock
+/// that failed, which is usually when the journal is wanted.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeltaJournal {
+    entries: Vec<JournalEntry>,
+}
+
+impl DeltaJournal {
+    /// Entries in the order they were recorded
+    pub fn entries(&self) -> &[JournalEntry] {
+        &self.entries
+    }
+
+    /// Number of entries
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether nothing was recorded
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Entries of an account, in order
+    pub fn for_account(&self, account: Address) -> impl Iterator<Item = &JournalEntry> {
+        self.entries.iter().filter(move |entry| entry.account == account)
+    }
+
+    /// Net change of every account and currency over the journal, nonzero
+    /// ones only, sorted by account and currency ID
+    ///
+    /// For the journal of an unlock that failed with an unsettled currency,
+    /// these are the balances left open.
+    pub fn net_deltas(&self) -> Vec<AccountDelta> {
+        let mut net: BTreeMap<(Address, _), AccountDelta> = BTreeMap::new();
+        for entry in &self.entries {
+            net.entry((entry.account, entry.currency.to_id()))
+                .or_insert(AccountDelta { account: entry.account, currency: entry.currency, delta: 0 })
+                .delta += entry.amount;
+        }
+        net.into_values().filter(|delta| delta.delta != 0).collect()
+    }
+
+    /// Exports the entries as a JSON array, with amounts as decimal strings
+    pub fn to_json(&self) -> Value {
+        Value::Array(
+            self.entries
+                .iter()
+                .map(|entry| json!({
+                    "account": entry.account,
+                    "currency": entry.currency.to_string(),
+                    "amount": entry.amount.to_string(),
+                    "reason": entry.reason.tag(),
+                    "delta_after": entry.delta_after.to_string(),
+                }))
+                .collect(),
+        )
+    }
+
+    /// Exports the entries like [`to_json`](Self::to_json), adding the
+    /// cached symbol of each currency and the amounts in its decimals
+    ///
+    /// Currencies missing from the cache keep their raw amounts.
+    pub fn to_json_labeled<M>(&self, metadata: &CurrencyMetadata<M>) -> Value {
+        Value::Array(
+            self.entries
+                .iter()
+                .map(|entry| json!({
+                    "account": entry.account,
+                    "currency": entry.currency.to_string(),
+                    "symbol": metadata.label(entry.currency).to_string(),
+                    "amount": entry.amount.to_string(),
+                    "amount_formatted": metadata.format_delta(entry.currency, entry.amount),
+                    "reason": entry.reason.tag(),
+                    "delta_after": entry.delta_after.to_string(),
+                }))
+                .collect(),
+        )
+    }
+
+    pub(crate) fn record(&mut self, entry: JournalEntry) {
+        self.entries.push(entry);
+    }
+
+    pub(crate) fn truncate(&mut self, len: usize) {
+        self.entries.truncate(len);
+    }
+}
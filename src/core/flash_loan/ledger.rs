@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+use ethers::types::{Address, H160, U256};
+
+use super::Currency;
+
+/// Account of the ledger that holds the manager's tokens, the mainnet
+/// address of the v4 `PoolManager`
+pub const POOL_MANAGER_ADDRESS: Address = H160([
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0x44, 0x4c, 0x5d, 0xc7,
+    0x5c, 0xb3, 0x58, 0x38, 0x0d, 0x2e, 0x3d, 0xe0, 0x8a, 0x90,
+]);
+
+/// Errors moving tokens on a [`TokenLedger`]
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum LedgerError {
+    #[error("{account:?} holds {balance} of {currency}, {amount} needed")]
+    InsufficientBalance {
+        currency: Currency,
+        account: Address,
+        balance: U256,
+        amount: U256,
+    },
+
+    #[error("Balance of {account:?} in {currency} overflows")]
+    BalanceOverflow {
+        currency: Currency,
+        account: Address,
+    },
+}
+
+/// Token balances of accounts, standing in for the ERC-20 contracts and
+/// native balances the manager would hold and pay out on chain
+///
+/// With a ledger installed, `take` pays the manager's tokens out and
+/// `settle` credits what was paid in since the last `sync`, rather than
+/// only accounting deltas. Balances are funded with [`mint`](Self::mint).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TokenLedger {
+    balances: HashMap<(Currency, Address), U256>,
+}
+
+impl TokenLedger {
+    /// Creates a ledger in which nobody holds anything
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gets the balance of an account in a currency
+    pub fn balance_of(&self, currency: Currency, account: Address) -> U256 {
+        self.balances.get(&(currency, account)).copied().unwrap_or_default()
+    }
+
+    /// Creates tokens for an account, to fund it
+    pub fn mint(&mut self, currency: Currency, to: Address, amount: U256) -> Result<(), LedgerError> {
+        let balance = self.balance_of(currency, to)
+            .checked_add(amount)
+            .ok_or(LedgerError::BalanceOverflow { currency, account: to })?;
+        self.set_balance(currency, to, balance);
+        Ok(())
+    }
+
+    /// Moves tokens between accounts, failing without changes when the
+    /// sender holds too few
+    pub fn transfer(&mut self, currency: Currency, from: Address, to: Address, amount: U256) -> Result<(), LedgerError> {
+        let balance = self.balance_of(currency, from);
+        if balance < amount {
+            return Err(LedgerError::InsufficientBalance { currency, account: from, balance, amount });
+        }
+        if from == to {
+            return Ok(());
+        }
+        let received = self.balance_of(currency, to)
+            .checked_add(amount)
+            .ok_or(LedgerError::BalanceOverflow { currency, account: to })?;
+        self.set_balance(currency, from, balance - amount);
+        self.set_balance(currency, to, received);
+        Ok(())
+    }
+
+    fn set_balance(&mut self, currency: Currency, account: Address, balance: U256) {
+        if balance.is_zero() {
+            self.balances.remove(&(currency, account));
+        } else {
+            self.balances.insert((currency, account), balance);
+        }
+    }
+}
@@ -8,6 +8,10 @@ pub mod callback;
 pub mod error;
 pub mod examples;
 pub mod types;
+pub mod policy;
+pub mod observer;
+pub mod journal;
+pub mod ledger;
 
 pub use currency::*;
 pub use lock::*;
@@ -15,8 +19,13 @@ pub use callback::*;
 pub use error::*;
 pub use examples::*;
 pub use types::*;
+pub use policy::*;
+pub use observer::*;
+pub use journal::*;
+pub use ledger::*;
 
-use crate::core::state::Result as StateResult;
+use crate::core::math::Bps;
+use crate::core::state::{Result as StateResult, StateError};
 
 // Constants
 pub const ZERO_ADDRESS: Address = Address::zero();
@@ -35,6 +44,41 @@ pub struct FlashLoanManager {
     pub lock: Lock,
     /// Currency reserves (for settling)
     currency_reserves: CurrencyReserves,
+    /// 各币种的闪电贷费率（基点）
+    flash_fees_bps: HashMap<Currency, Bps>,
+    /// 闪电贷费用的接收方
+    flash_fee_recipient: FlashFeeRecipient,
+    /// 本次解锁中尚未偿还的借款（本金加费用）
+    outstanding_loans: HashMap<AccountCurrencyKey, u128>,
+    /// 本次解锁中产生的闪电贷费用
+    pending_flash_fees: HashMap<Currency, u128>,
+    /// 已累计的闪电贷费用
+    accrued_flash_fees: HashMap<(FlashFeeRecipient, Currency), u128>,
+    /// 可借出和结算的币种及每次解锁的借出上限
+    currency_policy: CurrencyPolicy,
+    /// 本次解锁中各币种已借出的总量
+    taken_this_unlock: HashMap<Currency, u128>,
+    /// 观察每次解锁的全局观察者
+    unlock_observers: UnlockObservers,
+    /// 本次解锁中各账户和币种的余额变动及其中非零的数量，类似 v4 的瞬时存储
+    transient_deltas: CurrencyDeltaTracker,
+    /// 本次解锁中余额变动的日志
+    journal: DeltaJournal,
+    /// 上一次解锁结束时的日志
+    last_journal: Option<DeltaJournal>,
+    /// 代币账本，设置后 take 和 settle 会实际转移代币
+    token_ledger: Option<TokenLedger>,
+}
+
+/// 某一时刻的余额变动、借款、费用和代币余额，用于回滚
+pub(crate) struct DeltaCheckpoint {
+    deltas: HashMap<AccountCurrencyKey, i128>,
+    transient_deltas: CurrencyDeltaTracker,
+    outstanding_loans: HashMap<AccountCurrencyKey, u128>,
+    pending_flash_fees: HashMap<Currency, u128>,
+    taken_this_unlock: HashMap<Currency, u128>,
+    token_ledger: Option<TokenLedger>,
+    journal_len: usize,
 }
 
 /// Currency reserves for settling
@@ -85,59 +129,345 @@ impl FlashLoanManager {
             deltas: HashMap::new(),
             lock: Lock::new(),
             currency_reserves: CurrencyReserves::new(),
+            flash_fees_bps: HashMap::new(),
+            flash_fee_recipient: FlashFeeRecipient::default(),
+            outstanding_loans: HashMap::new(),
+            pending_flash_fees: HashMap::new(),
+            accrued_flash_fees: HashMap::new(),
+            currency_policy: CurrencyPolicy::new(),
+            taken_this_unlock: HashMap::new(),
+            unlock_observers: UnlockObservers::default(),
+            transient_deltas: CurrencyDeltaTracker::new(),
+            journal: DeltaJournal::default(),
+            last_journal: None,
+            token_ledger: None,
         }
     }
     
-    /// 更新指定地址的币种余额变动
+    /// 设置指定币种的闪电贷费率（基点）
+    pub fn set_flash_fee(&mut self, currency: Currency, fee_bps: Bps) -> Result<(), FlashLoanError> {
+        if fee_bps > MAX_FLASH_FEE_BPS {
+            return Err(FlashLoanError::FlashFeeTooLarge(fee_bps.get()));
+        }
+        if fee_bps.is_zero() {
+            self.flash_fees_bps.remove(&currency);
+        } else {
+            self.flash_fees_bps.insert(currency, fee_bps);
+        }
+        Ok(())
+    }
+    
+    /// 获取指定币种的闪电贷费率（基点）
+    pub fn flash_fee_bps(&self, currency: Currency) -> Bps {
+        self.flash_fees_bps.get(&currency).copied().unwrap_or_default()
+    }
+    
+    /// 计算借出指定数量时的闪电贷费用（向上取整）
+    pub fn flash_fee(&self, currency: Currency, amount: u128) -> u128 {
+        let fee = U256::from(amount) * U256::from(self.flash_fee_bps(currency).get());
+        let (quotient, remainder) = fee.div_mod(U256::from(Bps::DENOMINATOR));
+        let fee = if remainder.is_zero() { quotient } else { quotient + 1 };
+        fee.as_u128()
+    }
+    
+    /// 设置闪电贷费用的接收方
+    pub fn set_flash_fee_recipient(&mut self, recipient: FlashFeeRecipient) {
+        self.flash_fee_recipient = recipient;
+    }
+    
+    /// 获取闪电贷费用的接收方
+    pub fn flash_fee_recipient(&self) -> FlashFeeRecipient {
+        self.flash_fee_recipient
+    }
+    
+    /// 获取接收方在指定币种上累计的闪电贷费用
+    pub fn flash_fees_accrued(&self, recipient: FlashFeeRecipient, currency: Currency) -> u128 {
+        *self.accrued_flash_fees.get(&(recipient, currency)).unwrap_or(&0)
+    }
+    
+    /// 提取接收方在指定币种上累计的闪电贷费用
+    pub fn collect_flash_fees(&mut self, recipient: FlashFeeRecipient, currency: Currency) -> u128 {
+        self.accrued_flash_fees.remove(&(recipient, currency)).unwrap_or(0)
+    }
+    
+    /// 设置币种策略（允许的币种和每次解锁的借出上限）
+    pub fn set_currency_policy(&mut self, policy: CurrencyPolicy) {
+        self.currency_policy = policy;
+    }
+    
+    /// 获取币种策略
+    pub fn currency_policy(&self) -> &CurrencyPolicy {
+        &self.currency_policy
+    }
+    
+    /// 获取本次解锁中指定币种已借出的总量
+    pub fn taken_this_unlock(&self, currency: Currency) -> u128 {
+        *self.taken_this_unlock.get(&currency).unwrap_or(&0)
+    }
+    
+    /// 注册解锁观察者，返回用于移除它的 ID
+    pub fn add_unlock_observer(&mut self, observer: Box<dyn UnlockObserver>) -> UnlockObserverId {
+        self.unlock_observers.add(observer)
+    }
+    
+    /// 移除解锁观察者
+    pub fn remove_unlock_observer(&mut self, id: UnlockObserverId) -> Option<Box<dyn UnlockObserver>> {
+        self.unlock_observers.remove(id)
+    }
+    
+    /// 设置代币账本，之后 take 从管理器在账本中的账户（`POOL_MANAGER_ADDRESS`）
+    /// 转出代币，settle 按转入的代币结算
+    pub fn set_token_ledger(&mut self, ledger: TokenLedger) {
+        self.token_ledger = Some(ledger);
+    }
+    
+    /// 获取代币账本
+    pub fn token_ledger(&self) -> Option<&TokenLedger> {
+        self.token_ledger.as_ref()
+    }
+    
+    /// 获取代币账本的可变引用，例如在 sync 和 settle 之间向管理器转入代币
+    pub fn token_ledger_mut(&mut self) -> Option<&mut TokenLedger> {
+        self.token_ledger.as_mut()
+    }
+    
+    /// 获取借款人在指定币种上尚未偿还的金额（本金加费用）
+    pub fn outstanding_loan(&self, borrower: Address, currency: Currency) -> u128 {
+        *self.outstanding_loans.get(&(borrower, currency)).unwrap_or(&0)
+    }
+    
+    /// 更新指定地址的币种余额变动，在日志中记为 `DeltaReason::Other`
     pub fn update_delta(
         &mut self,
         address: Address,
         currency: Currency,
         delta: i128,
+    ) -> StateResult<()> {
+        self.update_delta_for(address, currency, delta, DeltaReason::Other)
+    }
+    
+    /// 更新指定地址的币种余额变动，解锁期间以给定原因记入日志
+    pub fn update_delta_for(
+        &mut self,
+        address: Address,
+        currency: Currency,
+        delta: i128,
+        reason: DeltaReason,
     ) -> StateResult<()> {
         let key = (address, currency);
-        let new_delta = self.deltas.get(&key).unwrap_or(&0) + delta;
+        let new_delta = self.deltas.get(&key).unwrap_or(&0)
+            .checked_add(delta)
+            .ok_or(StateError::AmountOverflow)?;
+        if self.lock.is_unlocked() {
+            self.transient_deltas.get_delta(address, currency)
+                .checked_add(delta)
+                .ok_or(StateError::AmountOverflow)?;
+            self.transient_deltas.apply_delta(address, currency, delta);
+        }
         self.deltas.insert(key, new_delta);
+        if self.lock.is_unlocked() {
+            self.journal.record(JournalEntry {
+                account: address,
+                currency,
+                amount: delta,
+                reason,
+                delta_after: new_delta,
+            });
+        }
         Ok(())
     }
     
+    /// 获取本次解锁中到目前为止的余额变动日志，未解锁时为空
+    pub fn delta_journal(&self) -> &DeltaJournal {
+        &self.journal
+    }
+    
+    /// 获取上一次解锁（无论成功与否）的完整余额变动日志
+    pub fn last_unlock_journal(&self) -> Option<&DeltaJournal> {
+        self.last_journal.as_ref()
+    }
+    
     /// 获取指定地址和币种的余额变动
     pub fn get_delta(&self, address: Address, currency: Currency) -> i128 {
         *self.deltas.get(&(address, currency)).unwrap_or(&0)
     }
     
-    /// 对已存在的余额变动同步
+    /// 获取本次解锁中指定地址和币种的余额变动，未解锁时为零
+    ///
+    /// 与 v4 通过 `exttload` 读取的 `currencyDelta` 相同，不含解锁前已有的余额变动。
+    pub fn currency_delta(&self, address: Address, currency: Currency) -> i128 {
+        self.transient_deltas.get_delta(address, currency)
+    }
+    
+    /// 获取本次解锁中尚未结清的余额变动数量，即 v4 的 `NonzeroDeltaCount`
+    ///
+    /// 解锁结束时该数量必须为零，否则解锁以 `CurrencyNotSettled` 失败。
+    pub fn nonzero_delta_count(&self) -> usize {
+        self.transient_deltas.non_zero_delta_count()
+    }
+    
+    /// 清除指定地址和币种的余额变动，返回被清除的值
+    pub fn flush_delta(&mut self, address: Address, currency: Currency) -> i128 {
+        let flushed = self.deltas.remove(&(address, currency)).unwrap_or(0);
+        if self.lock.is_unlocked() {
+            self.transient_deltas.apply_delta(address, currency, flushed.wrapping_neg());
+        }
+        flushed
+    }
+    
+    /// 同步待结算的币种，之后的 settle 将以该币种结算
+    ///
+    /// 设置了代币账本时记录管理器当前持有的该币种数量（储备）；与 v4 相同，
+    /// 同步原生币会清除已同步的币种，因为原生币按 settle 附带的数量结算。
     pub fn sync(&mut self, currency: Currency) {
-        // This is a placeholder for a real sync implementation
-        // In a real implementation, this would process all deltas for the currency
-        println!("Syncing currency: {:?}", currency);
+        match &self.token_ledger {
+            Some(_) if currency.is_native() => self.currency_reserves.reset_currency(),
+            Some(ledger) => {
+                let reserves = ledger.balance_of(currency, POOL_MANAGER_ADDRESS);
+                self.currency_reserves.sync_currency_and_reserves(currency, reserves);
+            }
+            None => self.currency_reserves.sync_currency_and_reserves(currency, U256::zero()),
+        }
     }
     
     /// 执行闪电贷回调
+    ///
+    /// 回调结束时所有借款（本金加费用）必须已偿还，本次解锁中产生的余额变动也必须
+    /// 全部结清，否则返回 `CurrencyNotSettled`，并回滚本次解锁中产生的余额变动、
+    /// 借款和费用。解锁观察者在回调前后被通知，其返回的错误同样会使解锁失败并回滚。
     pub fn unlock<C: FlashLoanCallback>(
         &mut self,
         callback: &mut C,
         data: &[u8],
     ) -> Result<Vec<u8>, FlashLoanError> {
-        if !self.lock.is_unlocked() {
-            // First unlock the lock
-            self.lock.unlock()?;
-            
-            // Execute callback
-            let result = callback.unlock_callback(data);
-            
-            // Lock again regardless of result
-            self.lock.lock();
-            
-            result
-        } else {
+        let checkpoint = self.begin_unlock()?;
+        
+        // Notify observers, then execute callback
+        let result = self
+            .notify_unlock_started()
+            .and_then(|()| callback.unlock_callback_with_manager(self, data));
+        self.end_unlock(checkpoint, result)
+    }
+    
+    /// 解锁，返回解锁前余额变动的检查点，供 `end_unlock` 检查和回滚
+    pub(crate) fn begin_unlock(&mut self) -> Result<DeltaCheckpoint, FlashLoanError> {
+        if self.lock.is_unlocked() {
             return Err(FlashLoanError::ReentrancyError);
         }
+        self.lock.unlock()?;
+        self.journal = DeltaJournal::default();
+        self.transient_deltas.clear_all_deltas();
+        Ok(self.checkpoint())
+    }
+    
+    /// 通知解锁观察者解锁已开始
+    pub(crate) fn notify_unlock_started(&mut self) -> Result<(), FlashLoanError> {
+        self.unlock_observers.unlock_started()
+    }
+    
+    /// 结束 `begin_unlock` 开始的解锁
+    ///
+    /// 重新加锁后检查借款是否已偿还并通知观察者，观察者看到的是本次解锁尚未结清的
+    /// 余额变动，之后再检查它们是否已全部结清；失败时回滚到检查点的余额变动，
+    /// 并丢弃本次解锁中的借款和费用。
+    pub(crate) fn end_unlock(
+        &mut self,
+        checkpoint: DeltaCheckpoint,
+        result: Result<Vec<u8>, FlashLoanError>,
+    ) -> Result<Vec<u8>, FlashLoanError> {
+        let deltas_before = checkpoint.deltas;
+        
+        // Lock again regardless of result
+        self.lock.lock();
+        self.last_journal = Some(std::mem::take(&mut self.journal));
+        self.currency_reserves.reset_currency();
+        
+        self.taken_this_unlock.clear();
+        let nonzero_deltas = std::mem::take(&mut self.transient_deltas).non_zero_delta_count();
+        
+        let outstanding_loans = std::mem::take(&mut self.outstanding_loans);
+        let pending_flash_fees = std::mem::take(&mut self.pending_flash_fees);
+        let result = match result {
+            Ok(_) if outstanding_loans.values().any(|owed| *owed > 0) => {
+                Err(FlashLoanError::CurrencyNotSettled)
+            }
+            Ok(data) if !self.unlock_observers.is_empty() => {
+                let deltas = self.deltas_since(&deltas_before);
+                self.unlock_observers.unlock_ended(&deltas).map(|()| data)
+            }
+            result => result,
+        };
+        let result = match result {
+            Ok(_) if nonzero_deltas > 0 => Err(FlashLoanError::CurrencyNotSettled),
+            result => result,
+        };
+        if result.is_err() {
+            self.deltas = deltas_before;
+            self.token_ledger = checkpoint.token_ledger;
+            return result;
+        }
+        
+        // All loans were repaid, so the fees are earned
+        for (currency, fee) in pending_flash_fees {
+            *self.accrued_flash_fees
+                .entry((self.flash_fee_recipient, currency))
+                .or_insert(0) += fee;
+        }
+        
+        result
+    }
+    
+    /// 记录当前的余额变动，以及本次解锁中的借款、待确认费用和借出总量
+    pub(crate) fn checkpoint(&self) -> DeltaCheckpoint {
+        DeltaCheckpoint {
+            deltas: self.deltas.clone(),
+            transient_deltas: self.transient_deltas.clone(),
+            outstanding_loans: self.outstanding_loans.clone(),
+            pending_flash_fees: self.pending_flash_fees.clone(),
+            taken_this_unlock: self.taken_this_unlock.clone(),
+            token_ledger: self.token_ledger.clone(),
+            journal_len: self.journal.len(),
+        }
+    }
+    
+    /// 将余额变动、借款、费用和代币余额恢复到检查点，并丢弃之后记录的日志
+    pub(crate) fn restore(&mut self, checkpoint: DeltaCheckpoint) {
+        self.deltas = checkpoint.deltas;
+        self.transient_deltas = checkpoint.transient_deltas;
+        self.outstanding_loans = checkpoint.outstanding_loans;
+        self.pending_flash_fees = checkpoint.pending_flash_fees;
+        self.taken_this_unlock = checkpoint.taken_this_unlock;
+        self.token_ledger = checkpoint.token_ledger;
+        self.journal.truncate(checkpoint.journal_len);
+    }
+    
+    /// 计算自 `before` 以来非零的余额变动，按账户和币种 ID 排序
+    fn deltas_since(&self, before: &HashMap<AccountCurrencyKey, i128>) -> Vec<AccountDelta> {
+        let mut deltas: Vec<AccountDelta> = self.deltas
+            .keys()
+            .chain(before.keys())
+            .collect::<std::collections::HashSet<_>>()
+            .into_iter()
+            .map(|&(account, currency)| AccountDelta {
+                account,
+                currency,
+                delta: self.get_delta(account, currency) - before.get(&(account, currency)).copied().unwrap_or(0),
+            })
+            .filter(|delta| delta.delta != 0)
+            .collect();
+        deltas.sort_by_key(|delta| (delta.account, delta.currency.to_id()));
+        deltas
     }
     
     /// 获取（闪电贷）借用
+    ///
+    /// 先提取 `to` 在本次解锁中该币种上的正值余额（例如兑换所得），其余部分为借款，
+    /// 借款人需要偿还借款本金加上该币种的闪电贷费用。提取的部分不收费、不计入
+    /// 未偿还借款，也不占用上限。币种须被币种策略允许，
+    /// 且本次解锁中的借出总量不能超过该币种的上限。设置了代币账本时，代币从管理器
+    /// 转给 `to`，管理器持有的数量不足时失败。
     pub fn take(
-        &self,
+        &mut self,
         currency: Currency,
         to: Address,
         amount: u128,
@@ -145,14 +475,41 @@ impl FlashLoanManager {
         if !self.lock.is_unlocked() {
             return Err(FlashLoanError::NotCalledInCallback);
         }
+        let credit = (self.transient_deltas.get_delta(to, currency).max(0) as u128).min(amount);
+        let loan = amount - credit;
+        let taken = self.currency_policy.check_take(currency, self.taken_this_unlock(currency), loan)?;
+        
+        let fee = self.flash_fee(currency, loan);
+        let owed = loan.checked_add(fee).ok_or(FlashLoanError::InsufficientBalance)?;
+        let debt = i128::try_from(owed)
+            .ok()
+            .and_then(|debt| debt.checked_add(credit as i128))
+            .ok_or(FlashLoanError::InsufficientBalance)?;
         
-        // In a real implementation, this would transfer tokens
-        println!("Taking {} of currency {:?} to {:?}", amount, currency, to);
+        if let Some(ledger) = &mut self.token_ledger {
+            ledger.transfer(currency, POOL_MANAGER_ADDRESS, to, U256::from(amount))?;
+        }
+        self.update_delta_for(to, currency, -debt, DeltaReason::Take)
+            .map_err(|e| FlashLoanError::Other(e.to_string()))?;
+        if owed > 0 {
+            *self.outstanding_loans.entry((to, currency)).or_insert(0) += owed;
+        }
+        if fee > 0 {
+            *self.pending_flash_fees.entry(currency).or_insert(0) += fee;
+        }
+        self.taken_this_unlock.insert(currency, taken);
         
         Ok(())
     }
     
     /// 结算一个余额
+    ///
+    /// 以最近一次 sync 的币种结算（未同步时为原生币），并优先偿还 `recipient` 的借款。
+    /// 该币种须被币种策略允许。返回结算的数量。
+    ///
+    /// 未设置代币账本时直接结算 `value`。设置了代币账本时与 v4 相同：`value` 相当于
+    /// `msg.value`，结算原生币时从 `recipient` 转给管理器；结算其他币种时 `value`
+    /// 必须为零，结算的是管理器自 sync 以来转入的数量。
     pub fn settle(
         &mut self,
         recipient: Address,
@@ -162,15 +519,37 @@ impl FlashLoanManager {
             return Err(FlashLoanError::NotCalledInCallback);
         }
         
-        // In a real implementation, this would settle balances
-        println!("Settling {} to {:?}", value, recipient);
+        let currency = self.currency_reserves.get_synced_currency().unwrap_or(Currency::Native);
+        self.currency_policy.check_allowed(currency)?;
+        let value = match &self.token_ledger {
+            Some(_) if !currency.is_native() && !value.is_zero() => {
+                return Err(FlashLoanError::NonzeroNativeValue);
+            }
+            Some(ledger) if !currency.is_native() => ledger
+                .balance_of(currency, POOL_MANAGER_ADDRESS)
+                .saturating_sub(self.currency_reserves.get_synced_reserves()),
+            _ => value,
+        };
+        let paid = i128::try_from(value).map_err(|_| FlashLoanError::InsufficientBalance)?;
+        
+        if let Some(ledger) = &mut self.token_ledger {
+            if currency.is_native() {
+                ledger.transfer(currency, recipient, POOL_MANAGER_ADDRESS, value)?;
+            }
+        }
+        self.update_delta_for(recipient, currency, paid, DeltaReason::Settle)
+            .map_err(|e| FlashLoanError::Other(e.to_string()))?;
+        if let Some(owed) = self.outstanding_loans.get_mut(&(recipient, currency)) {
+            *owed = owed.saturating_sub(paid as u128);
+        }
+        self.currency_reserves.reset_currency();
         
         Ok(value)
     }
     
-    /// 清除一个正值余额（用于处理微小金额）
+    /// 清除一个正值余额（用于处理微小金额），放弃被清除的金额
     pub fn clear(
-        &self,
+        &mut self,
         currency: Currency,
         address: Address,
         amount: u128,
@@ -180,9 +559,33 @@ impl FlashLoanManager {
             return Err(FlashLoanError::InsufficientBalance);
         }
         
-        // In a real implementation, this would clear the delta
-        println!("Clearing {} of currency {:?} from {:?}", amount, currency, address);
-        
-        Ok(())
+        self.update_delta_for(address, currency, -(amount as i128), DeltaReason::Clear)
+            .map_err(|e| FlashLoanError::Other(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_restore_discards_loans_taken_since_checkpoint() {
+        let mut manager = FlashLoanManager::new();
+        let currency = Currency::from_address(Address::from_low_u64_be(1));
+        let borrower = Address::from_low_u64_be(2);
+        manager.set_flash_fee(currency, Bps::new(30)).unwrap();
+
+        let unlock_checkpoint = manager.begin_unlock().unwrap();
+        let checkpoint = manager.checkpoint();
+        manager.take(currency, borrower, 1000).unwrap();
+        assert_eq!(manager.outstanding_loan(borrower, currency), 1003);
+        manager.restore(checkpoint);
+
+        assert_eq!(manager.outstanding_loan(borrower, currency), 0);
+        assert_eq!(manager.taken_this_unlock(currency), 0);
+        assert_eq!(manager.get_delta(borrower, currency), 0);
+        // Nothing is left to repay, and the discarded loan's fee is never earned
+        manager.end_unlock(unlock_checkpoint, Ok(Vec::new())).unwrap();
+        assert_eq!(manager.flash_fees_accrued(FlashFeeRecipient::ProtocolFees, currency), 0);
     }
-} 
\ No newline at end of file
+}
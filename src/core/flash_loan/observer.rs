@@ -0,0 +1,88 @@
+use ethers::types::Address;
+
+use super::{Currency, FlashLoanError};
+
+/// Change of an account's balance in a currency over one unlock
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AccountDelta {
+    pub account: Address,
+    pub currency: Currency,
+    /// Positive when the account is owed the currency, negative when it owes it
+    pub delta: i128,
+}
+
+/// Identifies a registered unlock observer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct UnlockObserverId(u64);
+
+/// Observes every unlock of the manager, independently of pools
+///
+/// Observers see the whole unlock rather than one pool's operations, so they
+/// suit cross-pool accounting such as portfolio margining or global risk
+/// limits. Returning an error from either callback fails the unlock and
+/// rolls back its deltas, like an unsettled currency.
+pub trait UnlockObserver {
+    /// Called once the manager is unlocked, before the callback runs
+    fn on_unlock_start(&mut self) -> Result<(), FlashLoanError> {
+        Ok(())
+    }
+
+    /// Called after the callback returned and every loan was repaid, with the
+    /// nonzero balance changes of the unlock sorted by account and currency ID
+    ///
+    /// These are the balances the unlock left open, so when there are any
+    /// the unlock still fails with `CurrencyNotSettled` after the observers
+    /// accept it. Not called when the unlock already failed.
+    fn on_unlock_end(&mut self, _deltas: &[AccountDelta]) -> Result<(), FlashLoanError> {
+        Ok(())
+    }
+}
+
+/// Unlock observers in registration order
+#[derive(Default)]
+pub struct UnlockObservers {
+    observers: Vec<(UnlockObserverId, Box<dyn UnlockObserver>)>,
+    next_id: u64,
+}
+
+impl UnlockObservers {
+    /// Registers an observer, returning the ID to remove it with
+    pub fn add(&mut self, observer: Box<dyn UnlockObserver>) -> UnlockObserverId {
+        let id = UnlockObserverId(self.next_id);
+        self.next_id += 1;
+        self.observers.push((id, observer));
+        id
+    }
+
+    /// Removes an observer, returning it if it was registered
+    pub fn remove(&mut self, id: UnlockObserverId) -> Option<Box<dyn UnlockObserver>> {
+        let index = self.observers.iter().position(|(observer_id, _)| *observer_id == id)?;
+        Some(self.observers.remove(index).1)
+    }
+
+    /// Number of registered observers
+    pub fn len(&self) -> usize {
+        self.observers.len()
+    }
+
+    /// Whether no observer is registered
+    pub fn is_empty(&self) -> bool {
+        self.observers.is_empty()
+    }
+
+    /// Notifies every observer of the start of an unlock, stopping at the first error
+    pub fn unlock_started(&mut self) -> Result<(), FlashLoanError> {
+        self.observers.iter_mut().try_for_each(|(_, observer)| observer.on_unlock_start())
+    }
+
+    /// Notifies every observer of the end of an unlock, stopping at the first error
+    pub fn unlock_ended(&mut self, deltas: &[AccountDelta]) -> Result<(), FlashLoanError> {
+        self.observers.iter_mut().try_for_each(|(_, observer)| observer.on_unlock_end(deltas))
+    }
+}
+
+impl std::fmt::Debug for UnlockObservers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.observers.iter().map(|(id, _)| id)).finish()
+    }
+}
@@ -0,0 +1,150 @@
+use std::collections::{HashMap, HashSet};
+use ethers::types::U256;
+
+use super::{Currency, FlashLoanError};
+
+/// Restricts which currencies can be taken and settled, and how much of each
+/// can be taken in one unlock
+///
+/// The default policy allows every currency without limit. Installing an
+/// allowlist models a permissioned deployment and keeps simulations from
+/// minting claims on arbitrary synthetic currencies built with
+/// [`Currency::from_id`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CurrencyPolicy {
+    /// Currencies that may be used, or `None` to allow all
+    allowlist: Option<HashSet<Currency>>,
+    /// Maximum total amount of a currency taken in a single unlock
+    take_caps: HashMap<Currency, u128>,
+}
+
+impl CurrencyPolicy {
+    /// Creates a policy that allows every currency without limit
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a policy that only allows the given currencies
+    pub fn allowlist(currencies: impl IntoIterator<Item = Currency>) -> Self {
+        Self {
+            allowlist: Some(currencies.into_iter().collect()),
+            take_caps: HashMap::new(),
+        }
+    }
+
+    /// Adds a currency to the allowlist
+    ///
+    /// A policy without an allowlist already allows every currency.
+    pub fn allow(&mut self, currency: Currency) -> &mut Self {
+        if let Some(allowlist) = &mut self.allowlist {
+            allowlist.insert(currency);
+        }
+        self
+    }
+
+    /// Removes a currency from the allowlist
+    ///
+    /// Has no effect on a policy without an allowlist; install one with
+    /// [`allowlist`](Self::allowlist) to restrict currencies.
+    pub fn disallow(&mut self, currency: Currency) -> &mut Self {
+        if let Some(allowlist) = &mut self.allowlist {
+            allowlist.remove(&currency);
+        }
+        self
+    }
+
+    /// Caps the total amount of a currency that can be taken in one unlock
+    pub fn set_take_cap(&mut self, currency: Currency, cap: u128) -> &mut Self {
+        self.take_caps.insert(currency, cap);
+        self
+    }
+
+    /// Removes the take cap of a currency
+    pub fn remove_take_cap(&mut self, currency: Currency) -> &mut Self {
+        self.take_caps.remove(&currency);
+        self
+    }
+
+    /// Gets the take cap of a currency, if any
+    pub fn take_cap(&self, currency: Currency) -> Option<u128> {
+        self.take_caps.get(&currency).copied()
+    }
+
+    /// Checks whether a currency may be used
+    pub fn is_allowed(&self, currency: Currency) -> bool {
+        self.allowlist.as_ref().is_none_or(|allowlist| allowlist.contains(&currency))
+    }
+
+    /// Checks whether the ERC6909 claim token ID of an allowed currency is `id`
+    pub fn is_id_allowed(&self, id: U256) -> bool {
+        self.allowlist.as_ref().is_none_or(|allowlist| allowlist.iter().any(|currency| currency.to_id() == id))
+    }
+
+    /// Rejects a currency that is not allowed
+    pub fn check_allowed(&self, currency: Currency) -> Result<(), FlashLoanError> {
+        if self.is_allowed(currency) {
+            Ok(())
+        } else {
+            Err(FlashLoanError::CurrencyNotAllowed(currency))
+        }
+    }
+
+    /// Checks that taking `amount` on top of `already_taken` in the current
+    /// unlock stays within the policy, returning the new total taken
+    pub fn check_take(&self, currency: Currency, already_taken: u128, amount: u128) -> Result<u128, FlashLoanError> {
+        self.check_allowed(currency)?;
+        let taken = already_taken.saturating_add(amount);
+        match self.take_cap(currency) {
+            Some(cap) if taken > cap => Err(FlashLoanError::TakeCapExceeded { currency, cap, taken }),
+            _ => Ok(taken),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::Address;
+
+    #[test]
+    fn test_default_policy_allows_everything() {
+        let policy = CurrencyPolicy::new();
+        let currency = Currency::from_address(Address::from_low_u64_be(1));
+        assert!(policy.is_allowed(currency));
+        assert!(policy.is_allowed(Currency::Pool(12345.into())));
+        assert_eq!(policy.check_take(currency, u128::MAX - 1, 1).unwrap(), u128::MAX);
+    }
+
+    #[test]
+    fn test_allowlist() {
+        let allowed = Currency::from_address(Address::from_low_u64_be(1));
+        let other = Currency::from_address(Address::from_low_u64_be(2));
+        let mut policy = CurrencyPolicy::allowlist([allowed]);
+
+        assert!(policy.is_allowed(allowed));
+        assert!(matches!(policy.check_allowed(other), Err(FlashLoanError::CurrencyNotAllowed(c)) if c == other));
+
+        assert!(policy.is_id_allowed(allowed.to_id()));
+        assert!(!policy.is_id_allowed(other.to_id()));
+
+        policy.allow(other).disallow(allowed);
+        assert!(policy.is_allowed(other));
+        assert!(!policy.is_allowed(allowed));
+    }
+
+    #[test]
+    fn test_take_cap() {
+        let currency = Currency::Native;
+        let mut policy = CurrencyPolicy::new();
+        policy.set_take_cap(currency, 1000);
+
+        assert_eq!(policy.check_take(currency, 400, 600).unwrap(), 1000);
+        assert!(matches!(
+            policy.check_take(currency, 400, 601),
+            Err(FlashLoanError::TakeCapExceeded { cap: 1000, taken: 1001, .. })
+        ));
+
+        policy.remove_take_cap(currency);
+        assert!(policy.check_take(currency, 400, 601).is_ok());
+    }
+}
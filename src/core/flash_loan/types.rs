@@ -1,6 +1,7 @@
 use ethers::types::Address;
 use primitive_types::U256;
 use super::Currency;
+use crate::core::math::Bps;
 
 /// 闪电贷回调参数
 #[derive(Debug)]
@@ -24,4 +25,17 @@ pub enum FlashLoanResult {
     Success,
     /// 闪电贷失败，带有错误信息
     Failure(String),
-} 
\ No newline at end of file
+}
+
+/// 闪电贷费率上限（基点，10_000 = 100%）
+pub const MAX_FLASH_FEE_BPS: Bps = Bps::MAX;
+
+/// 闪电贷费用的接收方
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum FlashFeeRecipient {
+    /// 计入协议费用
+    #[default]
+    ProtocolFees,
+    /// 计入指定地址
+    Account(Address),
+}
@@ -0,0 +1,36 @@
+//! Block time shared with hooks
+//!
+//! Real hooks read `block.timestamp` in their callbacks. The manager keeps
+//! its timestamp in a [`Clock`], and hooks that follow a schedule keep a
+//! handle to it from [`PoolManager::clock`](crate::core::pool_manager::PoolManager::clock),
+//! so they see the time set with
+//! [`PoolManager::set_timestamp`](crate::core::pool_manager::PoolManager::set_timestamp).
+
+use std::cell::Cell;
+use std::rc::Rc;
+
+/// Handle to the manager's block timestamp
+///
+/// Cloning the handle shares the same time. Only the manager moves it, and
+/// only forwards.
+#[derive(Debug, Clone, Default)]
+pub struct Clock {
+    now: Rc<Cell<u64>>,
+}
+
+impl Clock {
+    /// Creates a clock at timestamp 0
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gets the current timestamp
+    pub fn now(&self) -> u64 {
+        self.now.get()
+    }
+
+    /// Moves the clock to a later timestamp; earlier timestamps are ignored
+    pub(crate) fn advance_to(&self, timestamp: u64) {
+        self.now.set(self.now.get().max(timestamp));
+    }
+}
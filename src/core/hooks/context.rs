@@ -0,0 +1,98 @@
+//! Scratch space shared by hooks for the duration of an unlock
+//!
+//! Real hooks pass data between callbacks of the same transaction through
+//! transient storage, for example a `before_swap` recording a price that
+//! `after_swap`, or the hook of another pool, reads later. [`HookContext`]
+//! plays that role: the manager owns one, hooks keep a handle to it from
+//! [`PoolManager::hook_context`](crate::core::pool_manager::PoolManager::hook_context),
+//! and the manager clears it when an unlock ends.
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+/// Handle to a key/value scratch space shared by hooks within an unlock
+///
+/// Cloning the handle shares the same space, so a hook built with a clone
+/// sees what other hooks and the manager see.
+#[derive(Debug, Clone, Default)]
+pub struct HookContext {
+    entries: Rc<RefCell<BTreeMap<Vec<u8>, Vec<u8>>>>,
+}
+
+impl HookContext {
+    /// Creates an empty scratch space
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stores a value, returning the value previously stored at the key
+    pub fn set(&self, key: impl Into<Vec<u8>>, value: impl Into<Vec<u8>>) -> Option<Vec<u8>> {
+        self.entries.borrow_mut().insert(key.into(), value.into())
+    }
+
+    /// Gets the value stored at a key
+    pub fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.entries.borrow().get(key).cloned()
+    }
+
+    /// Removes and returns the value stored at a key
+    pub fn remove(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.entries.borrow_mut().remove(key)
+    }
+
+    /// Checks whether a value is stored at a key
+    pub fn contains(&self, key: &[u8]) -> bool {
+        self.entries.borrow().contains_key(key)
+    }
+
+    /// Gets the number of stored values
+    pub fn len(&self) -> usize {
+        self.entries.borrow().len()
+    }
+
+    /// Checks whether nothing is stored
+    pub fn is_empty(&self) -> bool {
+        self.entries.borrow().is_empty()
+    }
+
+    /// Removes every stored value
+    pub(crate) fn clear(&self) {
+        self.entries.borrow_mut().clear();
+    }
+
+    /// Copies the stored values, to restore if an operation fails
+    pub(crate) fn snapshot(&self) -> BTreeMap<Vec<u8>, Vec<u8>> {
+        self.entries.borrow().clone()
+    }
+
+    /// Replaces the stored values with a snapshot
+    pub(crate) fn restore(&self, snapshot: BTreeMap<Vec<u8>, Vec<u8>>) {
+        *self.entries.borrow_mut() = snapshot;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clones_share_space() {
+        let context = HookContext::new();
+        let handle = context.clone();
+
+        assert_eq!(handle.set(b"price".to_vec(), vec![1]), None);
+        assert_eq!(context.get(b"price"), Some(vec![1]));
+        assert_eq!(context.set(b"price".to_vec(), vec![2]), Some(vec![1]));
+        assert!(handle.contains(b"price"));
+        assert_eq!(handle.len(), 1);
+
+        let snapshot = context.snapshot();
+        assert_eq!(handle.remove(b"price"), Some(vec![2]));
+        assert!(context.is_empty());
+        context.restore(snapshot);
+        assert_eq!(handle.get(b"price"), Some(vec![2]));
+        handle.clear();
+        assert!(context.is_empty());
+    }
+}
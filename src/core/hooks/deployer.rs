@@ -0,0 +1,144 @@
+//! Deterministic addresses for simulated hook deployments
+//!
+//! A v4 hook's callbacks are read from the low bits of its address, so a hook
+//! must be deployed at an address whose flag bits match its permissions.
+//! [`HookDeployer`] derives such addresses from a deployer address and a
+//! nonce, like `CREATE` would, then writes the hook's flags into them:
+//!
+//! ```
+//! use uniswap_v4_core::core::hooks::{deployer::HookDeployer, examples::DynamicFeeHook, HookFlags, HookRegistry};
+//! use uniswap_v4_core::core::math::FeePips;
+//! use ethers::types::Address;
+//!
+//! let mut registry = HookRegistry::new();
+//! let mut deployer = HookDeployer::new(Address::repeat_byte(0xD0));
+//! let hook = DynamicFeeHook::new(FeePips::new(3000), FeePips::new(500), FeePips::new(10_000));
+//! let address = deployer.deploy(&mut registry, Box::new(hook)).unwrap();
+//!
+//! assert!(HookFlags::from_address(address).is_enabled(HookFlags::BEFORE_SWAP));
+//! assert!(registry.has_hook(&address));
+//! ```
+
+use ethers::{types::Address, utils::keccak256};
+
+use super::{HookError, HookFlags, HookPermissions, HookRegistry, HookResult, HookWithReturns};
+
+/// Assigns hook addresses with flag bits matching each hook's permissions
+///
+/// The same deployer deploying the same hooks in the same order always gets
+/// the same addresses.
+#[derive(Debug, Clone)]
+pub struct HookDeployer {
+    /// Address the hook addresses are derived from
+    deployer: Address,
+    /// Nonce of the next deployment
+    nonce: u64,
+}
+
+impl HookDeployer {
+    /// Creates a deployer whose first deployment uses nonce 0
+    pub fn new(deployer: Address) -> Self {
+        Self { deployer, nonce: 0 }
+    }
+
+    /// Gets the nonce of the next deployment
+    pub fn nonce(&self) -> u64 {
+        self.nonce
+    }
+
+    /// Computes the address of a hook with `permissions` deployed at `nonce`
+    ///
+    /// Fails if the permissions enable a delta return without its callback,
+    /// which no address can encode validly.
+    pub fn address_for(&self, permissions: &HookPermissions, nonce: u64) -> HookResult<Address> {
+        let mut preimage = self.deployer.as_bytes().to_vec();
+        preimage.extend_from_slice(&nonce.to_be_bytes());
+        let mut base = Address::from_slice(&keccak256(preimage)[12..]);
+        // Clear the flag bytes, so no flag outside the permissions is set
+        base.0[18..].copy_from_slice(&[0, 0]);
+        let flags = HookFlags::from_permissions(permissions);
+        let address = flags.apply_to_address(base);
+        if !flags.validate_hook_address() {
+            return Err(HookError::HookAddressNotValid(address));
+        }
+        Ok(address)
+    }
+
+    /// Registers a hook at the next free address matching its permissions and
+    /// returns the address
+    ///
+    /// Nonces whose address is already taken in `registry` are skipped, so an
+    /// existing hook is never replaced.
+    pub fn deploy(&mut self, registry: &mut HookRegistry, hook: Box<dyn HookWithReturns>) -> HookResult<Address> {
+        let permissions = hook.describe().permissions;
+        loop {
+            let address = self.address_for(&permissions, self.nonce)?;
+            self.nonce += 1;
+            if !registry.has_hook(&address) {
+                registry.register_hook(address, hook);
+                return Ok(address);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::hooks::examples::DynamicFeeHook;
+    use crate::core::math::FeePips;
+    use crate::core::hooks::typestate::TypedHook;
+    use crate::core::hooks::AfterHookResult;
+
+    fn dynamic_fee_hook() -> Box<DynamicFeeHook> {
+        Box::new(DynamicFeeHook::new(FeePips::new(3000), FeePips::new(500), FeePips::new(10_000)))
+    }
+
+    #[test]
+    fn test_addresses_match_permissions() {
+        let mut registry = HookRegistry::new();
+        let mut deployer = HookDeployer::new(Address::repeat_byte(1));
+
+        let fee_hook = deployer.deploy(&mut registry, dynamic_fee_hook()).unwrap();
+        let swap_hook = TypedHook::new("after-swap")
+            .with_before_add_liquidity(|_, _, _, _| Ok(Default::default()))
+            .with_after_swap(|_, _, _, _, _| Ok(AfterHookResult::default()));
+        let permissions = swap_hook.permissions();
+        let swap_hook = deployer.deploy(&mut registry, Box::new(swap_hook)).unwrap();
+
+        assert_ne!(fee_hook, swap_hook);
+        assert_eq!(HookFlags::from_address(fee_hook), HookFlags::new(HookFlags::BEFORE_SWAP));
+        // Flags above the low byte are encoded too
+        assert_eq!(
+            HookFlags::from_address(swap_hook),
+            HookFlags::new(HookFlags::BEFORE_ADD_LIQUIDITY | HookFlags::AFTER_SWAP)
+        );
+        assert!(registry.validate_hook_permissions(&swap_hook, permissions).is_ok());
+        assert_eq!(deployer.nonce(), 2);
+    }
+
+    #[test]
+    fn test_deployments_are_deterministic() {
+        let deploy = || {
+            let mut registry = HookRegistry::new();
+            let mut deployer = HookDeployer::new(Address::repeat_byte(2));
+            (0..3).map(|_| deployer.deploy(&mut registry, dynamic_fee_hook()).unwrap()).collect::<Vec<_>>()
+        };
+        let addresses = deploy();
+        assert_eq!(addresses, deploy());
+        assert_ne!(addresses[0], addresses[1]);
+
+        // A taken address is skipped instead of replaced
+        let mut registry = HookRegistry::new();
+        registry.register_hook(addresses[0], dynamic_fee_hook());
+        let mut deployer = HookDeployer::new(Address::repeat_byte(2));
+        assert_eq!(deployer.deploy(&mut registry, dynamic_fee_hook()).unwrap(), addresses[1]);
+    }
+
+    #[test]
+    fn test_invalid_permissions_are_rejected() {
+        let deployer = HookDeployer::new(Address::zero());
+        let permissions = HookPermissions { after_swap_returns_delta: true, ..Default::default() };
+        assert!(matches!(deployer.address_for(&permissions, 0), Err(HookError::HookAddressNotValid(_))));
+    }
+}
@@ -1,66 +1,65 @@
 use crate::core::{
-    state::{BalanceDelta, Result as StateResult},
-    math::types::{SqrtPrice, Liquidity},
+    state::{BalanceDelta, PositionKey, Result as StateResult, StateError},
+    math::{types::{Percent, SqrtPrice, Liquidity, TickSpacing}, Bps, FeePips, FixedPoint96, TickMath},
+    flash_loan::Currency,
     hooks::{
-        BeforeHookResult, AfterHookResult, BeforeSwapDelta,
-        Hook, HookWithReturns, HookFlags
+        BeforeHookResult, AfterHookResult, AfterInitializeResult, BeforeSwapDelta, Clock,
+        Hook, HookWithReturns, HookFlags, HookDescriptor, HookError, HookPermissions,
+        HookContext, InitializeRejection, InitializeReport, LiquiditySeed, util::PriceWindow,
+    },
+    pool_manager::{
+        pool_key_to_id, ManagerPoolKey, OperationError, OperationOutput, PoolId, PoolManager, RebalanceParams,
+        UnlockOperation,
     },
 };
 use super::hook_interface::{PoolKey, SwapParams, ModifyLiquidityParams};
 use ethers::types::Address;
 use primitive_types::U256;
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::rc::Rc;
 
 /// A fee hook that dynamically sets fees based on market conditions
+#[derive(Clone)]
 pub struct DynamicFeeHook {
     /// Base fee for the pool
-    base_fee: u32,
+    base_fee: FeePips,
     /// Fee multiplier based on volatility
     volatility_multiplier: u32,
-    /// Last recorded price
-    last_price: U256,
+    /// The previous and current price
+    prices: PriceWindow,
     /// Fee caps
-    max_fee: u32,
-    min_fee: u32,
+    max_fee: FeePips,
+    min_fee: FeePips,
 }
 
 impl DynamicFeeHook {
     /// Create a new dynamic fee hook
-    pub fn new(base_fee: u32, min_fee: u32, max_fee: u32) -> Self {
+    pub fn new(base_fee: FeePips, min_fee: FeePips, max_fee: FeePips) -> Self {
         Self {
             base_fee,
             volatility_multiplier: 100, // 100% to start
-            last_price: U256::zero(),
+            prices: PriceWindow::new(2),
             max_fee,
             min_fee,
         }
     }
     
     /// Calculate dynamic fee based on price change
-    fn calculate_dynamic_fee(&mut self, current_price: U256) -> u32 {
-        if self.last_price.is_zero() {
-            self.last_price = current_price;
-            return self.base_fee;
-        }
+    fn calculate_dynamic_fee(&mut self, current_price: U256) -> FeePips {
+        self.prices.push(current_price);
         
-        // Calculate price change as a percentage
-        let price_change = if current_price > self.last_price {
-            // Price increased
-            ((current_price - self.last_price) * U256::from(10000)) / self.last_price
-        } else {
-            // Price decreased
-            ((self.last_price - current_price) * U256::from(10000)) / self.last_price
+        // Price change since the last swap, in basis points
+        let Some(price_change) = self.prices.mean_abs_change_bps() else {
+            return self.base_fee;
         };
         
-        // Update last price
-        self.last_price = current_price;
-        
         // Calculate fee multiplier based on price change
         // Higher volatility = higher fee
-        self.volatility_multiplier = 100 + (price_change.low_u32() / 100);
+        self.volatility_multiplier = 100 + (price_change / 100);
         
         // Calculate dynamic fee
-        let dynamic_fee = (self.base_fee * self.volatility_multiplier) / 100;
+        let dynamic_fee = FeePips::new((self.base_fee.get() * self.volatility_multiplier) / 100);
         
         // Clamp fee between min and max
         dynamic_fee.clamp(self.min_fee, self.max_fee)
@@ -68,10 +67,21 @@ impl DynamicFeeHook {
 }
 
 impl Hook for DynamicFeeHook {
+    fn describe(&self) -> HookDescriptor {
+        let permissions = HookPermissions {
+            before_swap: true,
+            ..Default::default()
+        };
+        HookDescriptor::new("DynamicFeeHook", env!("CARGO_PKG_VERSION"), permissions)
+            .with_config("base_fee", self.base_fee.get())
+            .with_config("min_fee", self.min_fee.get())
+            .with_config("max_fee", self.max_fee.get())
+    }
+
     // Before swap, we calculate and set a dynamic fee
     fn before_swap(
         &mut self,
-        _sender: [u8; 20],
+        _sender: Address,
         _key: &PoolKey,
         params: &SwapParams,
         _hook_data: &[u8],
@@ -87,12 +97,17 @@ impl Hook for DynamicFeeHook {
             amount: None,
             delta: None,
             fee_override: Some(dynamic_fee),
+            rejection: None,
         })
     }
 }
 
 // Dynamic fee hook doesn't need to return any deltas
-impl HookWithReturns for DynamicFeeHook {}
+impl HookWithReturns for DynamicFeeHook {
+    fn clone_for_quote(&self) -> Option<Box<dyn HookWithReturns>> {
+        Some(Box::new(self.clone()))
+    }
+}
 
 /// A TWAP oracle hook that tracks time-weighted average prices
 pub struct TwapOracleHook {
@@ -102,8 +117,8 @@ pub struct TwapOracleHook {
     last_timestamp: u64,
     /// Last price
     last_price: U256,
-    /// Price observations
-    observations: Vec<(u64, U256)>, // (timestamp, price)
+    /// The last hundred price observations, as (timestamp, price)
+    observations: PriceWindow<(u64, U256)>,
 }
 
 impl TwapOracleHook {
@@ -113,7 +128,7 @@ impl TwapOracleHook {
             cumulative_price: U256::zero(),
             last_timestamp: 0,
             last_price: U256::zero(),
-            observations: Vec::new(),
+            observations: PriceWindow::new(100),
         }
     }
     
@@ -186,21 +201,24 @@ impl TwapOracleHook {
         self.last_timestamp = current_time;
         self.last_price = price;
         
-        // Add new observation
+        // Add new observation, dropping the oldest beyond the last 100
         self.observations.push((current_time, price));
-        
-        // Keep only the last 100 observations
-        if self.observations.len() > 100 {
-            self.observations.remove(0);
-        }
     }
 }
 
 impl Hook for TwapOracleHook {
+    fn describe(&self) -> HookDescriptor {
+        let permissions = HookPermissions {
+            after_swap: true,
+            ..Default::default()
+        };
+        HookDescriptor::new("TwapOracleHook", env!("CARGO_PKG_VERSION"), permissions)
+    }
+
     // After swap, update the oracle with the new price
     fn after_swap(
         &mut self,
-        _sender: [u8; 20],
+        _sender: Address,
         _key: &PoolKey,
         params: &SwapParams,
         _delta: &BalanceDelta,
@@ -227,11 +245,11 @@ pub struct LiquidityMiningHook {
     /// Last update timestamp
     last_update_time: u64,
     /// User rewards
-    user_rewards: HashMap<[u8; 20], U256>,
+    user_rewards: HashMap<Address, U256>,
     /// User liquidity
-    user_liquidity: HashMap<[u8; 20], i128>,
+    user_liquidity: HashMap<Address, i128>,
     /// User reward debt (used to calculate rewards correctly on liquidity changes)
-    user_reward_debt: HashMap<[u8; 20], U256>,
+    user_reward_debt: HashMap<Address, U256>,
 }
 
 impl LiquidityMiningHook {
@@ -282,7 +300,7 @@ impl LiquidityMiningHook {
     }
     
     /// Update user rewards
-    fn update_user_rewards(&mut self, user: [u8; 20], liquidity_delta: i128, total_liquidity: i128) {
+    fn update_user_rewards(&mut self, user: Address, liquidity_delta: i128, total_liquidity: i128) {
         // Update accumulated rewards first
         self.update_accumulated_rewards(total_liquidity);
         
@@ -316,7 +334,7 @@ impl LiquidityMiningHook {
     }
     
     /// Claim rewards for a user
-    pub fn claim_rewards(&mut self, user: [u8; 20]) -> U256 {
+    pub fn claim_rewards(&mut self, user: Address) -> U256 {
         let rewards = *self.user_rewards.get(&user).unwrap_or(&U256::zero());
         self.user_rewards.insert(user, U256::zero());
         rewards
@@ -324,10 +342,20 @@ impl LiquidityMiningHook {
 }
 
 impl Hook for LiquidityMiningHook {
+    fn describe(&self) -> HookDescriptor {
+        let permissions = HookPermissions {
+            after_add_liquidity: true,
+            after_remove_liquidity: true,
+            ..Default::default()
+        };
+        HookDescriptor::new("LiquidityMiningHook", env!("CARGO_PKG_VERSION"), permissions)
+            .with_config("reward_rate", self.reward_rate)
+    }
+
     /// After liquidity is added, update user rewards
     fn after_add_liquidity(
         &mut self,
-        sender: [u8; 20],
+        sender: Address,
         _key: &PoolKey,
         params: &ModifyLiquidityParams,
         _delta: &BalanceDelta,
@@ -346,7 +374,7 @@ impl Hook for LiquidityMiningHook {
     /// After liquidity is removed, update user rewards
     fn after_remove_liquidity(
         &mut self,
-        sender: [u8; 20],
+        sender: Address,
         _key: &PoolKey,
         params: &ModifyLiquidityParams,
         _delta: &BalanceDelta,
@@ -366,19 +394,20 @@ impl Hook for LiquidityMiningHook {
 impl HookWithReturns for LiquidityMiningHook {}
 
 /// A protocol fee collector hook that takes a portion of swap fees
+#[derive(Clone)]
 pub struct ProtocolFeeHook {
-    /// Protocol fee fraction (in basis points, e.g., 30 = 0.3%)
-    fee_fraction: u32,
+    /// Protocol fee fraction, e.g. 30 bps = 0.3%
+    fee_fraction: Bps,
     /// Collected fees
     collected_fees_0: u128,
     collected_fees_1: u128,
     /// Fee recipient
-    fee_recipient: [u8; 20],
+    fee_recipient: Address,
 }
 
 impl ProtocolFeeHook {
     /// Create a new protocol fee hook
-    pub fn new(fee_fraction: u32, fee_recipient: [u8; 20]) -> Self {
+    pub fn new(fee_fraction: Bps, fee_recipient: Address) -> Self {
         Self {
             fee_fraction,
             collected_fees_0: 0,
@@ -394,7 +423,7 @@ impl ProtocolFeeHook {
         }
         
         // Calculate fee (fee_fraction basis points)
-        (amount * self.fee_fraction as i128) / 10000
+        (amount * self.fee_fraction.get() as i128) / Bps::DENOMINATOR as i128
     }
     
     /// Withdraw collected fees
@@ -409,13 +438,28 @@ impl ProtocolFeeHook {
     }
 }
 
-impl Hook for ProtocolFeeHook {}
+impl Hook for ProtocolFeeHook {
+    fn describe(&self) -> HookDescriptor {
+        let permissions = HookPermissions {
+            after_swap: true,
+            after_swap_returns_delta: true,
+            ..Default::default()
+        };
+        HookDescriptor::new("ProtocolFeeHook", env!("CARGO_PKG_VERSION"), permissions)
+            .with_config("fee_fraction", self.fee_fraction.get())
+            .with_config("fee_recipient", format!("{:?}", Address::from(self.fee_recipient)))
+    }
+}
 
 impl HookWithReturns for ProtocolFeeHook {
+    fn clone_for_quote(&self) -> Option<Box<dyn HookWithReturns>> {
+        Some(Box::new(self.clone()))
+    }
+
     /// After swap, collect protocol fees
     fn after_swap_with_delta(
         &mut self,
-        _sender: [u8; 20],
+        _sender: Address,
         _key: &PoolKey,
         _params: &SwapParams,
         delta: &BalanceDelta,
@@ -439,25 +483,26 @@ impl HookWithReturns for ProtocolFeeHook {
 }
 
 /// A volume-based discount hook that offers fee discounts based on trading volume
+#[derive(Clone)]
 pub struct VolumeDiscountHook {
-    /// Discount tiers (volume threshold -> discount percentage)
-    discount_tiers: Vec<(U256, u32)>, // (volume threshold, discount percentage)
+    /// Discount tiers by ascending volume threshold
+    discount_tiers: Vec<(U256, Percent)>,
     /// User volumes
-    user_volumes: std::collections::HashMap<[u8; 20], U256>,
+    user_volumes: std::collections::HashMap<Address, U256>,
 }
 
 impl VolumeDiscountHook {
     /// Create a new volume discount hook
     pub fn new() -> Self {
-        let mut discount_tiers = Vec::new();
-        
-        // Set up discount tiers
-        // >100 tokens = 5% discount
-        discount_tiers.push((U256::from(100), 5));
-        // >1000 tokens = 10% discount
-        discount_tiers.push((U256::from(1000), 10));
-        // >10000 tokens = 20% discount
-        discount_tiers.push((U256::from(10000), 20));
+        let percent = |percent| Percent::new(percent).expect("at most 100%");
+        let discount_tiers = vec![
+            // >100 tokens = 5% discount
+            (U256::from(100), percent(5)),
+            // >1000 tokens = 10% discount
+            (U256::from(1000), percent(10)),
+            // >10000 tokens = 20% discount
+            (U256::from(10000), percent(20)),
+        ];
         
         Self {
             discount_tiers,
@@ -466,20 +511,20 @@ impl VolumeDiscountHook {
     }
     
     /// Update user's trading volume
-    fn update_user_volume(&mut self, user: [u8; 20], volume: U256) {
+    fn update_user_volume(&mut self, user: Address, volume: U256) {
         let current_volume = *self.user_volumes.get(&user).unwrap_or(&U256::zero());
         self.user_volumes.insert(user, current_volume + volume);
     }
     
-    /// Get discount percentage for a user
-    fn get_discount_percentage(&self, user: [u8; 20]) -> u32 {
+    /// Get the discount of a user
+    fn get_discount(&self, user: Address) -> Percent {
         let user_volume = *self.user_volumes.get(&user).unwrap_or(&U256::zero());
         
         // Find the highest discount tier that applies
-        let mut discount = 0;
-        for (threshold, percentage) in &self.discount_tiers {
+        let mut discount = Percent::ZERO;
+        for (threshold, tier_discount) in &self.discount_tiers {
             if user_volume >= *threshold {
-                discount = *percentage;
+                discount = *tier_discount;
             } else {
                 break;
             }
@@ -489,24 +534,38 @@ impl VolumeDiscountHook {
     }
     
     /// Apply discount to a fee
-    fn apply_discount(&self, user: [u8; 20], fee: u32) -> u32 {
-        let discount = self.get_discount_percentage(user);
-        fee - (fee * discount) / 100
+    fn apply_discount(&self, user: Address, fee: FeePips) -> FeePips {
+        let discount = self.get_discount(user);
+        FeePips::new(discount.complement().of_rounding_up(fee.get().into()).as_u32())
     }
 }
 
 impl Hook for VolumeDiscountHook {
+    fn describe(&self) -> HookDescriptor {
+        let permissions = HookPermissions {
+            before_swap: true,
+            ..Default::default()
+        };
+        let tiers = self.discount_tiers
+            .iter()
+            .map(|(threshold, discount)| format!("{}:{}", threshold, discount))
+            .collect::<Vec<_>>()
+            .join(",");
+        HookDescriptor::new("VolumeDiscountHook", env!("CARGO_PKG_VERSION"), permissions)
+            .with_config("discount_tiers", tiers)
+    }
+
     // Before swap, apply volume-based discount to fee
     fn before_swap(
         &mut self,
-        sender: [u8; 20],
+        sender: Address,
         _key: &PoolKey,
         params: &SwapParams,
         _hook_data: &[u8],
     ) -> StateResult<BeforeHookResult> {
         // For simplicity, we're assuming the base fee is 3000 (0.3%)
         // In a real implementation, we'd get this from the pool key
-        let base_fee = 3000;
+        let base_fee = FeePips::new(3000);
         
         // Apply discount based on user's volume
         let discounted_fee = self.apply_discount(sender, base_fee);
@@ -521,9 +580,512 @@ impl Hook for VolumeDiscountHook {
             amount: None,
             delta: None,
             fee_override: Some(discounted_fee),
+            rejection: None,
         })
     }
 }
 
 // Volume discount hook doesn't need to return any deltas
-impl HookWithReturns for VolumeDiscountHook {} 
\ No newline at end of file
+impl HookWithReturns for VolumeDiscountHook {
+    fn clone_for_quote(&self) -> Option<Box<dyn HookWithReturns>> {
+        Some(Box::new(self.clone()))
+    }
+}
+
+/// A liquidity bootstrapping hook that sells one token of a pool over a
+/// sale window
+///
+/// As in a liquidity bootstrapping pool, buyers of the sale token start at a
+/// premium that falls over the window, so the price drifts down until it
+/// meets demand. The premium is an LP fee override on buys that decays
+/// linearly from `start_fee` to `end_fee` as the [`Clock`] advances. Selling
+/// the sale token back pays `sell_fee` until the sale ends, so early buyers
+/// can't flip it cheaply. Swaps are rejected before the sale starts and pay
+/// `end_fee` both ways after it ends. Donations, such as sharing the raise
+/// with LPs, are only accepted once the sale has ended. The pool needs a
+/// dynamic fee for the overrides to apply.
+#[derive(Clone)]
+pub struct LiquidityBootstrappingHook {
+    /// Manager's clock
+    clock: Clock,
+    /// Start of the sale window
+    start: u64,
+    /// End of the sale window
+    end: u64,
+    /// Whether token0 is the token on sale
+    sells_token0: bool,
+    /// Fee on buys when the sale starts
+    start_fee: FeePips,
+    /// Fee on buys when the sale ends, and on every swap after it
+    end_fee: FeePips,
+    /// Fee on sales back to the pool during the window
+    sell_fee: FeePips,
+}
+
+impl LiquidityBootstrappingHook {
+    /// Create a sale from `start` to `end`, with buys paying 10% at the start
+    /// down to 0.3% at the end and sales back paying 10%
+    pub fn new(clock: Clock, start: u64, end: u64, sells_token0: bool) -> Self {
+        Self {
+            clock,
+            start,
+            end,
+            sells_token0,
+            start_fee: FeePips::new(100_000),
+            end_fee: FeePips::new(3000),
+            sell_fee: FeePips::new(100_000),
+        }
+    }
+
+    /// Set the fee schedule
+    pub fn with_fees(mut self, start_fee: FeePips, end_fee: FeePips, sell_fee: FeePips) -> Self {
+        self.start_fee = start_fee;
+        self.end_fee = end_fee;
+        self.sell_fee = sell_fee;
+        self
+    }
+
+    /// Fee of a swap at a timestamp, or `None` before the sale starts
+    pub fn fee_at(&self, timestamp: u64, buying: bool) -> Option<FeePips> {
+        if timestamp < self.start {
+            return None;
+        }
+        if timestamp >= self.end {
+            return Some(self.end_fee);
+        }
+        if !buying {
+            return Some(self.sell_fee);
+        }
+        let (start_fee, end_fee) = (self.start_fee.get() as i64, self.end_fee.get() as i64);
+        let elapsed = (timestamp - self.start) as i64;
+        let duration = (self.end - self.start) as i64;
+        Some(FeePips::new((start_fee + (end_fee - start_fee) * elapsed / duration) as u32))
+    }
+}
+
+impl Hook for LiquidityBootstrappingHook {
+    fn describe(&self) -> HookDescriptor {
+        let permissions = HookPermissions {
+            before_swap: true,
+            before_donate: true,
+            ..Default::default()
+        };
+        HookDescriptor::new("LiquidityBootstrappingHook", env!("CARGO_PKG_VERSION"), permissions)
+            .with_config("start", self.start)
+            .with_config("end", self.end)
+            .with_config("sells_token0", self.sells_token0)
+            .with_config("start_fee", self.start_fee.get())
+            .with_config("end_fee", self.end_fee.get())
+            .with_config("sell_fee", self.sell_fee.get())
+    }
+
+    // Before swap, set the fee of the schedule, refusing swaps before the sale
+    fn before_swap(
+        &mut self,
+        _sender: Address,
+        _key: &PoolKey,
+        params: &SwapParams,
+        _hook_data: &[u8],
+    ) -> StateResult<BeforeHookResult> {
+        // Buying token0 means paying token1, and the other way around
+        let buying = params.zero_for_one != self.sells_token0;
+        let fee = self.fee_at(self.clock.now(), buying).ok_or_else(|| {
+            StateError::Hook(HookError::HookCallReverted(format!("sale starts at {}", self.start)))
+        })?;
+        Ok(BeforeHookResult {
+            amount: None,
+            delta: None,
+            fee_override: Some(fee),
+            rejection: None,
+        })
+    }
+
+    // Before donate, refuse donations until the sale has ended
+    fn before_donate(
+        &mut self,
+        _sender: Address,
+        _key: &PoolKey,
+        _amount0: u128,
+        _amount1: u128,
+        _hook_data: &[u8],
+    ) -> StateResult<BeforeHookResult> {
+        if self.clock.now() < self.end {
+            return Err(StateError::Hook(HookError::HookCallReverted(format!("sale ends at {}", self.end))));
+        }
+        Ok(BeforeHookResult::default())
+    }
+}
+
+// The sale hook only overrides fees, so it returns no deltas
+impl HookWithReturns for LiquidityBootstrappingHook {
+    fn clone_for_quote(&self) -> Option<Box<dyn HookWithReturns>> {
+        Some(Box::new(self.clone()))
+    }
+}
+
+/// A hook that only serves pools with the tick spacing and fee it was built
+/// for, refusing others at initialization
+///
+/// Hooks often depend on the pool's parameters, such as an order book that
+/// places orders on a fixed tick grid, or a fee schedule that needs a
+/// dynamic fee. Either requirement is optional; the fee is compared with the
+/// key's fee as is, so requiring `0x800000` requires a dynamic fee.
+#[derive(Clone, Default)]
+pub struct PoolParametersHook {
+    /// Required tick spacing
+    tick_spacing: Option<TickSpacing>,
+    /// Required fee of the key
+    fee: Option<u32>,
+}
+
+impl PoolParametersHook {
+    /// Create a hook that accepts every pool
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Require a tick spacing
+    pub fn with_tick_spacing(mut self, tick_spacing: TickSpacing) -> Self {
+        self.tick_spacing = Some(tick_spacing);
+        self
+    }
+
+    /// Require a fee
+    pub fn with_fee(mut self, fee: u32) -> Self {
+        self.fee = Some(fee);
+        self
+    }
+
+    /// Why a pool doesn't meet the requirements, or `None` if it does
+    pub fn check(&self, key: &PoolKey) -> Option<InitializeRejection> {
+        if let Some(tick_spacing) = self.tick_spacing.filter(|spacing| *spacing != key.tick_spacing) {
+            return Some(InitializeRejection::UnsupportedTickSpacing {
+                tick_spacing: key.tick_spacing.get(),
+                required: Some(tick_spacing.get()),
+            });
+        }
+        if let Some(fee) = self.fee.filter(|fee| *fee != key.fee) {
+            return Some(InitializeRejection::UnsupportedFee { fee: key.fee, required: Some(fee) });
+        }
+        None
+    }
+}
+
+impl Hook for PoolParametersHook {
+    fn describe(&self) -> HookDescriptor {
+        let permissions = HookPermissions {
+            before_initialize: true,
+            ..Default::default()
+        };
+        let mut descriptor = HookDescriptor::new("PoolParametersHook", env!("CARGO_PKG_VERSION"), permissions);
+        if let Some(tick_spacing) = self.tick_spacing {
+            descriptor = descriptor.with_config("tick_spacing", tick_spacing.get());
+        }
+        if let Some(fee) = self.fee {
+            descriptor = descriptor.with_config("fee", fee);
+        }
+        descriptor
+    }
+
+    // Before initialize, refuse pools with other parameters
+    fn before_initialize(
+        &mut self,
+        _sender: Address,
+        key: &PoolKey,
+        _sqrt_price_x96: SqrtPrice,
+        _hook_data: &[u8],
+    ) -> StateResult<BeforeHookResult> {
+        Ok(self.check(key).map(BeforeHookResult::reject).unwrap_or_default())
+    }
+}
+
+impl HookWithReturns for PoolParametersHook {
+    fn clone_for_quote(&self) -> Option<Box<dyn HookWithReturns>> {
+        Some(Box::new(self.clone()))
+    }
+}
+
+/// A hook that seeds every pool it initializes with full range liquidity
+/// of its own
+///
+/// Pools then have a price and liquidity from their first block. The hook
+/// owes the amounts of the seed, so pools are initialized within an unlock
+/// in which the hook's deltas are settled.
+#[derive(Clone)]
+pub struct FullRangeSeedHook {
+    /// Liquidity of the seed
+    liquidity: u128,
+}
+
+impl FullRangeSeedHook {
+    /// Create a hook seeding `liquidity` over the full range
+    pub fn new(liquidity: u128) -> Self {
+        Self { liquidity }
+    }
+}
+
+impl Hook for FullRangeSeedHook {
+    fn describe(&self) -> HookDescriptor {
+        let permissions = HookPermissions {
+            after_initialize: true,
+            ..Default::default()
+        };
+        HookDescriptor::new("FullRangeSeedHook", env!("CARGO_PKG_VERSION"), permissions)
+            .with_config("liquidity", self.liquidity)
+    }
+}
+
+impl HookWithReturns for FullRangeSeedHook {
+    fn clone_for_quote(&self) -> Option<Box<dyn HookWithReturns>> {
+        Some(Box::new(self.clone()))
+    }
+
+    // After initialize, seed the full range
+    fn after_initialize_with_report(
+        &mut self,
+        _sender: Address,
+        key: &PoolKey,
+        _report: &InitializeReport,
+        _hook_data: &[u8],
+    ) -> StateResult<AfterInitializeResult> {
+        Ok(AfterInitializeResult { seeds: vec![LiquiditySeed::full_range(key.tick_spacing, self.liquidity)] })
+    }
+}
+
+/// The position an [`AutoRangeHook`] keeps in a pool, and the tokens it holds
+/// outside of it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ManagedRange {
+    /// Range of the position, meaningless while it has no liquidity
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    /// Liquidity of the position, owned by the hook's address
+    pub liquidity: u128,
+    /// Tokens the hook holds outside the position, such as collected fees
+    /// and what didn't fit the last range
+    pub idle0: u128,
+    pub idle1: u128,
+}
+
+/// A hook that keeps a position of its own centered on the pool's price
+///
+/// Each pool's position spans `half_width` tick spacings either side of the
+/// spacing the price was in when it was placed. When a swap takes the price
+/// more than `band` spacings away from that center, the position is moved
+/// around the new price.
+///
+/// Like a hook reading the pool from transient storage, `after_swap` only
+/// notes in the [`HookContext`] that the pool traded. The keeper owning the
+/// hook then calls [`rebalance`](Self::rebalance), which reads the pool's
+/// tick and moves the position with `modify_liquidity` within an unlock,
+/// whose end clears the note. A note made inside an unlock is cleared when
+/// that unlock ends, so the keeper only sees swaps made outside one. Clones
+/// share their positions, so the keeper keeps a clone of the registered
+/// hook.
+#[derive(Clone)]
+pub struct AutoRangeHook {
+    /// Address the hook is registered at, which owns its positions
+    address: Address,
+    /// Shared scratch space of the manager's hooks
+    context: HookContext,
+    /// Tick spacings either side of the center the position spans
+    half_width: i32,
+    /// Tick spacings the price may move from the center before a move
+    band: i32,
+    /// Slippage allowed on the swap of a move
+    max_slippage: Bps,
+    /// Managed ranges by pool
+    ranges: Rc<RefCell<HashMap<PoolId, ManagedRange>>>,
+}
+
+impl AutoRangeHook {
+    /// Create a hook registered at `address`, sharing the manager's
+    /// [`hook_context`](PoolManager::hook_context)
+    ///
+    /// The position spans at least one spacing either side of its center,
+    /// and the band is between one spacing and the position's half width,
+    /// where the position moves once the price leaves it.
+    pub fn new(address: Address, context: HookContext, half_width: u32, band: u32) -> Self {
+        let half_width = half_width.clamp(1, i32::MAX as u32) as i32;
+        Self {
+            address,
+            context,
+            half_width,
+            band: band.clamp(1, half_width as u32) as i32,
+            max_slippage: Bps::new(100),
+            ranges: Rc::default(),
+        }
+    }
+
+    /// Sets the slippage allowed on the swap of a move, 1% by default
+    pub fn with_max_slippage(mut self, max_slippage: Bps) -> Self {
+        self.max_slippage = max_slippage;
+        self
+    }
+
+    /// Gets the hook's position and idle tokens in a pool
+    pub fn managed_range(&self, key: &ManagerPoolKey) -> ManagedRange {
+        self.ranges.borrow().get(&pool_key_to_id(key)).copied().unwrap_or_default()
+    }
+
+    /// Adds tokens for the hook to provide in a pool, which the next
+    /// [`rebalance`](Self::rebalance) adds to its position
+    pub fn fund(&self, key: &ManagerPoolKey, amount0: u128, amount1: u128) {
+        let mut ranges = self.ranges.borrow_mut();
+        let range = ranges.entry(pool_key_to_id(key)).or_default();
+        range.idle0 += amount0;
+        range.idle1 += amount1;
+    }
+
+    /// Range of a position centered on a tick, within the usable ticks
+    pub fn target_range(&self, tick: i32, tick_spacing: TickSpacing) -> (i32, i32) {
+        let spacing = tick_spacing.get();
+        let center = tick.div_euclid(spacing) * spacing;
+        let half_width = self.half_width.saturating_mul(spacing);
+        (
+            center.saturating_sub(half_width).max(tick_spacing.min_usable_tick()),
+            center.saturating_add(half_width).min(tick_spacing.max_usable_tick()),
+        )
+    }
+
+    /// Whether the position should move: the pool traded since the last
+    /// unlock and its price left the band, or the hook has idle tokens and
+    /// no position yet
+    pub fn needs_rebalance(&self, manager: &PoolManager, key: &ManagerPoolKey) -> bool {
+        let Some(pool) = manager.get_pool(key) else {
+            return false;
+        };
+        let range = self.managed_range(key);
+        if range.liquidity == 0 {
+            return range.idle0 > 0 || range.idle1 > 0;
+        }
+        if !self.context.contains(&swapped_key(&key.to_hook_key())) {
+            return false;
+        }
+        let spacing = key.tick_spacing().get();
+        let center = range.tick_lower + (range.tick_upper - range.tick_lower) / 2;
+        let band = self.band.saturating_mul(spacing);
+        let tick = pool.slot0.tick;
+        tick < center.saturating_sub(band) || tick >= center.saturating_add(band)
+    }
+
+    /// Moves the position around the pool's price if it
+    /// [needs to](Self::needs_rebalance)
+    ///
+    /// A position is moved by [`PoolManager::rebalance`], which removes it,
+    /// swaps its tokens into the ratio of the new range and adds them there
+    /// in one unlock; what doesn't fit is left idle. Idle tokens are added
+    /// when the hook has no position, in an unlock of their own. Returns the
+    /// new position, or `None` if it stayed.
+    pub fn rebalance(&self, manager: &mut PoolManager, key: &ManagerPoolKey) -> Result<Option<ManagedRange>, OperationError> {
+        if !self.needs_rebalance(manager, key) {
+            return Ok(None);
+        }
+        let pool = manager.get_pool(key).ok_or(StateError::PoolNotInitialized)?;
+        let (tick_lower, tick_upper) = self.target_range(pool.slot0.tick, key.tick_spacing());
+        let mut range = self.managed_range(key);
+
+        if range.liquidity > 0 {
+            let result = manager.rebalance(&RebalanceParams {
+                from_key: key.clone(),
+                to_key: key.clone(),
+                swap_key: None,
+                position: PositionKey::default_position(self.address.0, range.tick_lower, range.tick_upper),
+                tick_lower,
+                tick_upper,
+                max_slippage: self.max_slippage,
+                min_liquidity: 1,
+            })?;
+            range.liquidity = result.liquidity_added;
+            range.idle0 = range.idle0.saturating_add_signed(result.leftover.amount0());
+            range.idle1 = range.idle1.saturating_add_signed(result.leftover.amount1());
+        } else {
+            // Adding rounds up, so a wei of each token is left for rounding
+            let sqrt_price_at = |tick| TickMath::get_sqrt_price_at_tick(tick).map_err(|_| StateError::InvalidPrice);
+            let liquidity = FixedPoint96::get_liquidity_for_amounts(
+                pool.slot0.sqrt_price_x96.to_u256(),
+                sqrt_price_at(tick_lower)?,
+                sqrt_price_at(tick_upper)?,
+                range.idle0.saturating_sub(1),
+                range.idle1.saturating_sub(1),
+            );
+            if liquidity == 0 {
+                return Ok(None);
+            }
+            let liquidity_delta = i128::try_from(liquidity).map_err(|_| StateError::LiquidityOverflow)?;
+            // The hook pays for the liquidity from its idle tokens in the same unlock
+            let (owed, _) = pool.clone().modify_position(
+                self.address.0,
+                tick_lower,
+                tick_upper,
+                liquidity_delta,
+                key.tick_spacing(),
+                [0; 32],
+            )?;
+            let pay = |token, amount: i128| UnlockOperation::Settle {
+                currency: Currency::from_address(token),
+                recipient: self.address,
+                value: U256::from(amount.unsigned_abs()),
+            };
+            let params = ModifyLiquidityParams::default_position(self.address, tick_lower, tick_upper, liquidity_delta);
+            let mut result = manager.unlock_batch(&[
+                UnlockOperation::ModifyLiquidity {
+                    key: key.clone(),
+                    params,
+                    hook_data: Vec::new(),
+                },
+                pay(key.token0(), owed.amount0()),
+                pay(key.token1(), owed.amount1()),
+            ]);
+            if let Some(error) = result.unlock_error {
+                return Err(error.into());
+            }
+            let OperationOutput::Delta(delta) = result.results.remove(0)? else {
+                unreachable!("a liquidity change outputs its delta");
+            };
+            range.liquidity = liquidity;
+            range.idle0 = range.idle0.saturating_add_signed(delta.amount0());
+            range.idle1 = range.idle1.saturating_add_signed(delta.amount1());
+        }
+        (range.tick_lower, range.tick_upper) = (tick_lower, tick_upper);
+        self.ranges.borrow_mut().insert(pool_key_to_id(key), range);
+        Ok(Some(range))
+    }
+}
+/// Context key noting that a pool traded
+fn swapped_key(key: &PoolKey) -> Vec<u8> {
+    let mut entry = b"auto-range:swapped:".to_vec();
+    entry.extend_from_slice(key.token0.as_bytes());
+    entry.extend_from_slice(key.token1.as_bytes());
+    entry.extend_from_slice(&key.fee.to_be_bytes());
+    entry.extend_from_slice(&key.tick_spacing.get().to_be_bytes());
+    entry.extend_from_slice(key.hooks.as_bytes());
+    entry
+}
+
+impl Hook for AutoRangeHook {
+    fn describe(&self) -> HookDescriptor {
+        let permissions = HookPermissions {
+            after_swap: true,
+            ..Default::default()
+        };
+        HookDescriptor::new("AutoRangeHook", env!("CARGO_PKG_VERSION"), permissions)
+            .with_config("half_width", self.half_width)
+            .with_config("band", self.band)
+    }
+
+    // After swap, note that the pool traded for the keeper
+    fn after_swap(
+        &mut self,
+        _sender: Address,
+        key: &PoolKey,
+        _params: &SwapParams,
+        _delta: &BalanceDelta,
+        _hook_data: &[u8],
+    ) -> StateResult<AfterHookResult> {
+        self.context.set(swapped_key(key), Vec::new());
+        Ok(AfterHookResult::default())
+    }
+}
+
+impl HookWithReturns for AutoRangeHook {}
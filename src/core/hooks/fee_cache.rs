@@ -0,0 +1,147 @@
+//! Cache of fee overrides from pure fee hooks
+//!
+//! Route search and batch quoting call the same hooks with the same swaps
+//! over and over. Hooks whose `before_swap` fee override depends only on the
+//! swap and on the pool's price declare it with
+//! [`Hook::fee_price_bucket`](super::Hook::fee_price_bucket), and once
+//! [`PoolManager::set_hook_fee_cache`](crate::core::pool_manager::PoolManager::set_hook_fee_cache)
+//! is on, the manager calls them once per swap and price bucket.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use primitive_types::U256;
+
+use crate::core::{math::{types::Liquidity, FeePips}, pool_manager::PoolId, state::Pool};
+
+/// A swap as seen by a pure fee hook, with the pool's tick reduced to its
+/// price bucket
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct FeeQuery {
+    pub bucket: i32,
+    pub zero_for_one: bool,
+    pub amount_specified: i128,
+    pub sqrt_price_limit_x96: U256,
+    pub hook_data: Vec<u8>,
+}
+
+impl FeeQuery {
+    /// Bucket of `tick` for buckets `width` ticks wide
+    pub fn bucket(tick: i32, width: u32) -> i32 {
+        let width = i32::try_from(width.max(1)).unwrap_or(i32::MAX);
+        tick.div_euclid(width)
+    }
+}
+
+/// Pool state the cached fees of a pool were computed under
+///
+/// A change to any of it drops the pool's entries, while price moves within
+/// a bucket keep them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PoolStamp {
+    liquidity: Liquidity,
+    lp_fee: FeePips,
+    lp_fee_one_for_zero: Option<FeePips>,
+    protocol_fee: u32,
+}
+
+impl PoolStamp {
+    fn of(pool: &Pool) -> Self {
+        Self {
+            liquidity: pool.liquidity,
+            lp_fee: pool.slot0.lp_fee,
+            lp_fee_one_for_zero: pool.slot0.lp_fee_one_for_zero,
+            protocol_fee: pool.slot0.protocol_fee,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct PoolFees {
+    stamp: PoolStamp,
+    fees: HashMap<FeeQuery, Option<FeePips>>,
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    pools: HashMap<PoolId, PoolFees>,
+    hits: u64,
+    misses: u64,
+}
+
+/// Handle to a cache of hook fee overrides
+///
+/// Cloning the handle shares the same cache, so quotes on scratch managers
+/// fill the cache of the manager they copy.
+#[derive(Debug, Clone, Default)]
+pub struct HookFeeCache {
+    state: Rc<RefCell<CacheState>>,
+}
+
+impl HookFeeCache {
+    /// Creates an empty cache
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gets the cached fee override for a swap on a pool, counting the
+    /// lookup as a hit or a miss
+    ///
+    /// The pool's entries are dropped first if its state changed since they
+    /// were cached.
+    pub(crate) fn get(&self, pool_id: PoolId, pool: &Pool, query: &FeeQuery) -> Option<Option<FeePips>> {
+        let mut state = self.state.borrow_mut();
+        let stamp = PoolStamp::of(pool);
+        if state.pools.get(&pool_id).is_some_and(|entry| entry.stamp != stamp) {
+            state.pools.remove(&pool_id);
+        }
+        let cached = state.pools.get(&pool_id).and_then(|entry| entry.fees.get(query).copied());
+        match cached {
+            Some(_) => state.hits += 1,
+            None => state.misses += 1,
+        }
+        cached
+    }
+
+    /// Caches the fee override a hook returned for a swap on a pool
+    pub(crate) fn insert(&self, pool_id: PoolId, pool: &Pool, query: FeeQuery, fee_override: Option<FeePips>) {
+        let stamp = PoolStamp::of(pool);
+        let mut state = self.state.borrow_mut();
+        let entry = state.pools.entry(pool_id).or_insert_with(|| PoolFees { stamp, fees: HashMap::new() });
+        if entry.stamp != stamp {
+            *entry = PoolFees { stamp, fees: HashMap::new() };
+        }
+        entry.fees.insert(query, fee_override);
+    }
+
+    /// Drops the cached fees of a pool
+    pub fn invalidate(&self, pool_id: &PoolId) {
+        self.state.borrow_mut().pools.remove(pool_id);
+    }
+
+    /// Drops every cached fee, as needed after replacing a registered hook
+    pub fn clear(&self) {
+        self.state.borrow_mut().pools.clear();
+    }
+
+    /// Number of cached fees
+    pub fn len(&self) -> usize {
+        self.state.borrow().pools.values().map(|entry| entry.fees.len()).sum()
+    }
+
+    /// Whether no fees are cached
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of lookups answered from the cache
+    pub fn hits(&self) -> u64 {
+        self.state.borrow().hits
+    }
+
+    /// Number of lookups that had to call the hook
+    pub fn misses(&self) -> u64 {
+        self.state.borrow().misses
+    }
+}
@@ -1,24 +1,24 @@
 use crate::core::{
-    state::{BalanceDelta, Result as StateResult},
-    math::types::{SqrtPrice, Liquidity},
+    state::{BalanceDelta, CrossDirection, PositionKey, Salt, Result as StateResult},
+    math::types::{SqrtPrice, Liquidity, TickSpacing},
 };
 use ethers::types::Address;
 
-use super::{BeforeHookResult, AfterHookResult, BeforeSwapDelta, HookResult};
+use super::{BeforeHookResult, AfterHookResult, AfterInitializeResult, BeforeSwapDelta, InitializeReport, HookDescriptor, HookPermissions, HookResult};
 
 /// Key identifying a pool
 #[derive(Debug, Clone, Hash, Eq, PartialEq)]
 pub struct PoolKey {
     /// Token0 address
-    pub token0: [u8; 20],
+    pub token0: Address,
     /// Token1 address
-    pub token1: [u8; 20],
+    pub token1: Address,
     /// Fee tier
     pub fee: u32,
     /// Tick spacing
-    pub tick_spacing: i32,
+    pub tick_spacing: TickSpacing,
     /// Hooks contract address
-    pub hooks: [u8; 20],
+    pub hooks: Address,
     /// Extension data for hooks
     pub extension_data: Vec<u8>,
 }
@@ -27,7 +27,7 @@ pub struct PoolKey {
 #[derive(Debug, Clone)]
 pub struct ModifyLiquidityParams {
     /// Owner of the position
-    pub owner: [u8; 20],
+    pub owner: Address,
     /// Lower tick bound
     pub tick_lower: i32,
     /// Upper tick bound
@@ -35,7 +35,65 @@ pub struct ModifyLiquidityParams {
     /// Liquidity delta
     pub liquidity_delta: i128,
     /// Salt to distinguish positions
-    pub salt: [u8; 32],
+    pub salt: Salt,
+}
+
+impl PoolKey {
+    /// Creates a key from raw address bytes
+    #[deprecated(since = "0.1.1", note = "build `PoolKey` with `Address` fields instead; this shim will be removed in the next release")]
+    pub fn from_raw(
+        token0: [u8; 20],
+        token1: [u8; 20],
+        fee: u32,
+        tick_spacing: TickSpacing,
+        hooks: [u8; 20],
+        extension_data: Vec<u8>,
+    ) -> Self {
+        Self {
+            token0: token0.into(),
+            token1: token1.into(),
+            fee,
+            tick_spacing,
+            hooks: hooks.into(),
+            extension_data,
+        }
+    }
+}
+
+impl ModifyLiquidityParams {
+    /// Creates parameters for the owner's default position in the range,
+    /// which uses [`Salt::ZERO`]
+    pub fn default_position(owner: Address, tick_lower: i32, tick_upper: i32, liquidity_delta: i128) -> Self {
+        Self {
+            owner,
+            tick_lower,
+            tick_upper,
+            liquidity_delta,
+            salt: Salt::ZERO,
+        }
+    }
+
+    /// Creates parameters from a raw owner address and salt
+    #[deprecated(since = "0.1.1", note = "build `ModifyLiquidityParams` with `Address` and `Salt` instead; this shim will be removed in the next release")]
+    pub fn from_raw(owner: [u8; 20], tick_lower: i32, tick_upper: i32, liquidity_delta: i128, salt: [u8; 32]) -> Self {
+        Self {
+            owner: owner.into(),
+            tick_lower,
+            tick_upper,
+            liquidity_delta,
+            salt: salt.into(),
+        }
+    }
+
+    /// Gets the key of the position these parameters modify
+    pub fn position_key(&self) -> PositionKey {
+        PositionKey {
+            owner: self.owner.0,
+            tick_lower: self.tick_lower,
+            tick_upper: self.tick_upper,
+            salt: self.salt.0,
+        }
+    }
 }
 
 /// Parameters for swap
@@ -49,12 +107,47 @@ pub struct SwapParams {
     pub sqrt_price_limit_x96: SqrtPrice,
 }
 
+impl SwapParams {
+    /// Whether the amount is specified in token0: the input of an exact
+    /// input swap from token0, or the output of an exact output swap to it
+    pub fn specified_is_token0(&self) -> bool {
+        (self.amount_specified < 0) == self.zero_for_one
+    }
+}
+
 /// Extended hook interface with returns delta methods
 pub trait HookWithReturns: Hook {
+    /// Copies the hook so quotes can call it without changing its state
+    ///
+    /// Hooks returning `None`, the default, can't be called in quotes, so
+    /// [`PoolManager::quote_with_hooks`](crate::core::pool_manager::PoolManager::quote_with_hooks)
+    /// fails for their pools.
+    fn clone_for_quote(&self) -> Option<Box<dyn HookWithReturns>> {
+        None
+    }
+
+    /// Called after a pool is initialized with the pool's initial state,
+    /// can seed liquidity owned by the hook
+    ///
+    /// The manager adds the seeds right away, without calling the hook's
+    /// liquidity callbacks, and accounts the amounts to the hook, which
+    /// settles them like any hook delta within the unlock. The default calls
+    /// [`Hook::after_initialize`] and seeds nothing.
+    fn after_initialize_with_report(
+        &mut self,
+        sender: Address,
+        key: &PoolKey,
+        report: &InitializeReport,
+        hook_data: &[u8],
+    ) -> StateResult<AfterInitializeResult> {
+        self.after_initialize(sender, key, report.sqrt_price_x96, report.tick, hook_data)?;
+        Ok(AfterInitializeResult::default())
+    }
+
     /// Called before a swap, can return a delta
     fn before_swap_with_delta(
         &mut self,
-        sender: [u8; 20],
+        sender: Address,
         key: &PoolKey,
         params: &SwapParams,
         hook_data: &[u8],
@@ -66,7 +159,7 @@ pub trait HookWithReturns: Hook {
     /// Called after a swap, can return a delta
     fn after_swap_with_delta(
         &mut self,
-        sender: [u8; 20],
+        sender: Address,
         key: &PoolKey,
         params: &SwapParams,
         delta: &BalanceDelta,
@@ -79,7 +172,7 @@ pub trait HookWithReturns: Hook {
     /// Called after liquidity is added, can return a delta
     fn after_add_liquidity_with_delta(
         &mut self,
-        sender: [u8; 20],
+        sender: Address,
         key: &PoolKey,
         params: &ModifyLiquidityParams,
         delta: &BalanceDelta,
@@ -93,7 +186,7 @@ pub trait HookWithReturns: Hook {
     /// Called after liquidity is removed, can return a delta
     fn after_remove_liquidity_with_delta(
         &mut self,
-        sender: [u8; 20],
+        sender: Address,
         key: &PoolKey,
         params: &ModifyLiquidityParams,
         delta: &BalanceDelta,
@@ -107,10 +200,29 @@ pub trait HookWithReturns: Hook {
 
 /// Trait defining hooks for Uniswap V4 pools
 pub trait Hook {
+    /// Describes the hook for display and serialization
+    ///
+    /// The default descriptor uses the type name and declares no permissions.
+    fn describe(&self) -> HookDescriptor {
+        HookDescriptor::new(std::any::type_name::<Self>(), "unversioned", HookPermissions::default())
+    }
+
+    /// Declares the fee override of `before_swap` a pure function of the
+    /// swap, its hook data and the pool's tick to within buckets this many
+    /// ticks wide
+    ///
+    /// With [`PoolManager::set_hook_fee_cache`](crate::core::pool_manager::PoolManager::set_hook_fee_cache)
+    /// on, the manager then calls `before_swap` once per swap and bucket and
+    /// reuses its fee override. Hooks whose `before_swap` keeps state or
+    /// changes the swap must return `None`, the default.
+    fn fee_price_bucket(&self) -> Option<u32> {
+        None
+    }
+
     /// Called before a pool is initialized
     fn before_initialize(
         &mut self,
-        sender: [u8; 20],
+        sender: Address,
         key: &PoolKey,
         sqrt_price_x96: SqrtPrice,
         hook_data: &[u8],
@@ -121,7 +233,7 @@ pub trait Hook {
     /// Called after a pool is initialized
     fn after_initialize(
         &mut self,
-        sender: [u8; 20],
+        sender: Address,
         key: &PoolKey,
         sqrt_price_x96: SqrtPrice,
         tick: i32,
@@ -133,7 +245,7 @@ pub trait Hook {
     /// Called before liquidity is added
     fn before_add_liquidity(
         &mut self,
-        sender: [u8; 20],
+        sender: Address,
         key: &PoolKey,
         params: &ModifyLiquidityParams,
         hook_data: &[u8],
@@ -144,7 +256,7 @@ pub trait Hook {
     /// Called after liquidity is added
     fn after_add_liquidity(
         &mut self,
-        sender: [u8; 20],
+        sender: Address,
         key: &PoolKey,
         params: &ModifyLiquidityParams,
         delta: &BalanceDelta,
@@ -157,7 +269,7 @@ pub trait Hook {
     /// Called before liquidity is removed
     fn before_remove_liquidity(
         &mut self,
-        sender: [u8; 20],
+        sender: Address,
         key: &PoolKey,
         params: &ModifyLiquidityParams,
         hook_data: &[u8],
@@ -168,7 +280,7 @@ pub trait Hook {
     /// Called after liquidity is removed
     fn after_remove_liquidity(
         &mut self,
-        sender: [u8; 20],
+        sender: Address,
         key: &PoolKey,
         params: &ModifyLiquidityParams,
         delta: &BalanceDelta,
@@ -181,7 +293,7 @@ pub trait Hook {
     /// Called before a swap
     fn before_swap(
         &mut self,
-        sender: [u8; 20],
+        sender: Address,
         key: &PoolKey,
         params: &SwapParams,
         hook_data: &[u8],
@@ -192,7 +304,7 @@ pub trait Hook {
     /// Called after a swap
     fn after_swap(
         &mut self,
-        sender: [u8; 20],
+        sender: Address,
         key: &PoolKey,
         params: &SwapParams,
         delta: &BalanceDelta,
@@ -201,10 +313,26 @@ pub trait Hook {
         Ok(AfterHookResult::default())
     }
 
+    /// Called for each initialized tick a swap crosses, in order, before the
+    /// swap completes
+    ///
+    /// Experimental: only called for hooks whose address has
+    /// [`HookFlags::TICK_CROSS`](super::HookFlags::TICK_CROSS) set. An error
+    /// aborts the swap.
+    fn on_tick_cross(
+        &mut self,
+        _key: &PoolKey,
+        _tick: i32,
+        _direction: CrossDirection,
+        _liquidity_net: i128,
+    ) -> StateResult<()> {
+        Ok(())
+    }
+
     /// Called before tokens are donated to the pool
     fn before_donate(
         &mut self,
-        sender: [u8; 20],
+        sender: Address,
         key: &PoolKey,
         amount0: u128,
         amount1: u128,
@@ -216,7 +344,7 @@ pub trait Hook {
     /// Called after tokens are donated to the pool
     fn after_donate(
         &mut self,
-        sender: [u8; 20],
+        sender: Address,
         key: &PoolKey,
         amount0: u128,
         amount1: u128,
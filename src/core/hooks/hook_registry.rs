@@ -4,13 +4,13 @@ use ethers::types::Address;
 
 use super::{
     hook_interface::{Hook, HookWithReturns, PoolKey, SwapParams, ModifyLiquidityParams},
-    HookFlags, BeforeSwapDelta, HookResult, HookError, HookPermissions, is_dynamic_fee,
+    HookFlags, BeforeSwapDelta, HookDescriptor, HookResult, HookError, HookPermissions, is_dynamic_fee,
 };
 
 /// Registry for hooks
 pub struct HookRegistry {
     /// Mapping of hook addresses to hook implementations
-    hooks: HashMap<[u8; 20], Box<dyn HookWithReturns>>,
+    hooks: HashMap<Address, Box<dyn HookWithReturns>>,
 }
 
 impl HookRegistry {
@@ -22,27 +22,39 @@ impl HookRegistry {
     }
 
     /// Registers a hook with the given address
-    pub fn register_hook(&mut self, address: [u8; 20], hook: Box<dyn HookWithReturns>) {
-        self.hooks.insert(address, hook);
+    ///
+    /// Raw `[u8; 20]` addresses are still accepted for compatibility.
+    pub fn register_hook(&mut self, address: impl Into<Address>, hook: Box<dyn HookWithReturns>) {
+        self.hooks.insert(address.into(), hook);
     }
 
     /// Gets a hook by address
-    pub fn get_hook(&self, address: &[u8; 20]) -> Option<&Box<dyn HookWithReturns>> {
+    pub fn get_hook(&self, address: &Address) -> Option<&Box<dyn HookWithReturns>> {
         self.hooks.get(address)
     }
     
     /// Gets a mutable hook by address
-    pub fn get_hook_mut(&mut self, address: &[u8; 20]) -> Option<&mut Box<dyn HookWithReturns>> {
+    pub fn get_hook_mut(&mut self, address: &Address) -> Option<&mut Box<dyn HookWithReturns>> {
         self.hooks.get_mut(address)
     }
 
     /// Checks if a hook is registered
-    pub fn has_hook(&self, address: &[u8; 20]) -> bool {
+    pub fn has_hook(&self, address: &Address) -> bool {
         self.hooks.contains_key(address)
     }
 
+    /// Lists the descriptors of all registered hooks, ordered by address
+    pub fn describe_hooks(&self) -> Vec<(Address, HookDescriptor)> {
+        let mut descriptors: Vec<_> = self.hooks
+            .iter()
+            .map(|(address, hook)| (*address, hook.describe()))
+            .collect();
+        descriptors.sort_by_key(|(address, _)| *address);
+        descriptors
+    }
+
     /// Removes a hook from the registry
-    pub fn remove_hook(&mut self, address: &[u8; 20]) -> Option<Box<dyn HookWithReturns>> {
+    pub fn remove_hook(&mut self, address: &Address) -> Option<Box<dyn HookWithReturns>> {
         self.hooks.remove(address)
     }
 
@@ -53,7 +65,7 @@ impl HookRegistry {
     }
     
     /// Validates that hook address follows rules
-    pub fn validate_hook_address(&self, address: &[u8; 20]) -> HookResult<()> {
+    pub fn validate_hook_address(&self, address: &Address) -> HookResult<()> {
         let flags = HookFlags::from_address(*address);
         
         if !flags.validate_hook_address() {
@@ -64,14 +76,14 @@ impl HookRegistry {
     }
     
     /// Validates that hook address is valid for a given fee
-    pub fn validate_hook_address_for_fee(&self, address: &[u8; 20], fee: u32) -> HookResult<()> {
+    pub fn validate_hook_address_for_fee(&self, address: &Address, fee: u32) -> HookResult<()> {
         // If the address is zero, the fee can't be dynamic
-        if address == &[0u8; 20] && is_dynamic_fee(fee) {
+        if address.is_zero() && is_dynamic_fee(fee) {
             return Err(HookError::HookAddressNotValid(*address));
         }
         
         // If the address is not zero, it must either have flags or the fee must be dynamic
-        if address != &[0u8; 20] {
+        if !address.is_zero() {
             let flags = HookFlags::from_address(*address);
             
             if !flags.has_any_hook() && !is_dynamic_fee(fee) {
@@ -86,7 +98,7 @@ impl HookRegistry {
     }
     
     /// Validates hook permissions against expected permissions
-    pub fn validate_hook_permissions(&self, address: &[u8; 20], expected: HookPermissions) -> HookResult<()> {
+    pub fn validate_hook_permissions(&self, address: &Address, expected: HookPermissions) -> HookResult<()> {
         let flags = HookFlags::from_address(*address);
         flags.validate_hook_permissions(expected)
     }
@@ -95,7 +107,7 @@ impl HookRegistry {
     pub fn call_before_swap_with_delta(
         &mut self,
         key: &PoolKey,
-        sender: [u8; 20],
+        sender: Address,
         params: &SwapParams,
         hook_data: &[u8],
     ) -> StateResult<BeforeSwapDelta> {
@@ -116,7 +128,7 @@ impl HookRegistry {
     pub fn call_after_swap_with_delta(
         &mut self,
         key: &PoolKey,
-        sender: [u8; 20],
+        sender: Address,
         params: &SwapParams,
         delta: &BalanceDelta,
         hook_data: &[u8],
@@ -138,7 +150,7 @@ impl HookRegistry {
     pub fn call_after_add_liquidity_with_delta(
         &mut self,
         key: &PoolKey,
-        sender: [u8; 20],
+        sender: Address,
         params: &ModifyLiquidityParams,
         delta: &BalanceDelta,
         fees_accrued: &BalanceDelta,
@@ -161,7 +173,7 @@ impl HookRegistry {
     pub fn call_after_remove_liquidity_with_delta(
         &mut self,
         key: &PoolKey,
-        sender: [u8; 20],
+        sender: Address,
         params: &ModifyLiquidityParams,
         delta: &BalanceDelta,
         fees_accrued: &BalanceDelta,
@@ -181,7 +193,7 @@ impl HookRegistry {
     }
     
     /// Get a hook implementation for a given address, or return a NoOpHook if not found
-    pub fn get_hook_or_noop(&mut self, address: &[u8; 20]) -> &mut Box<dyn HookWithReturns> {
+    pub fn get_hook_or_noop(&mut self, address: &Address) -> &mut Box<dyn HookWithReturns> {
         static mut NO_OP_HOOK: Option<Box<dyn HookWithReturns>> = None;
         
         if !self.has_hook(address) {
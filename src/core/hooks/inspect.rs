@@ -0,0 +1,65 @@
+//! Static inspection of hook addresses
+//!
+//! The callbacks a hook receives are fixed by the flag bits of its address,
+//! so which callbacks fire for a hooked pool can be read from its key alone,
+//! before the hook is deployed or registered.
+
+use std::fmt::Write;
+
+use ethers::types::Address;
+
+use super::HookFlags;
+
+/// Describes which callbacks fire for a hook address, for debugging
+///
+/// The first line gives the address and its flag bits, then every callback
+/// is listed as `[x]` when enabled or `[ ]` when not, in the order of the
+/// flag bits. Flag combinations the manager rejects end the description
+/// with a `warning:` line.
+pub fn describe_hook_address(address: impl Into<Address>) -> String {
+    let address = address.into();
+    let flags = HookFlags::from_address(address);
+    let mut description = format!("hooks {:?} (flags {:#06x})", address, flags.bits());
+    if address.is_zero() {
+        description.push_str("\n  no hooks, no callbacks fire");
+        return description;
+    }
+
+    for (flag, name) in HookFlags::NAMED {
+        let marker = if flags.is_enabled(flag) { 'x' } else { ' ' };
+        let note = if flag & HookFlags::EXPERIMENTAL_MASK != 0 { " (experimental)" } else { "" };
+        let _ = write!(description, "\n  [{}] {}{}", marker, name, note);
+    }
+    if !flags.validate_hook_address() {
+        description.push_str("\n  warning: a return delta flag is set without its callback");
+    }
+    if !flags.has_any_hook() {
+        description.push_str("\n  warning: no callbacks, so pools need a dynamic fee to use this address");
+    }
+    description
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_hook_address() {
+        let hooks = HookFlags::new(HookFlags::BEFORE_SWAP | HookFlags::BEFORE_SWAP_RETURNS_DELTA)
+            .apply_to_address(Address::repeat_byte(0xab));
+        let description = describe_hook_address(hooks);
+        let lines: Vec<_> = description.lines().collect();
+        assert_eq!(lines[0], format!("hooks {:?} (flags 0x8088)", hooks));
+        assert_eq!(lines.len(), 1 + HookFlags::NAMED.len());
+        let enabled: Vec<_> = lines.iter().filter(|line| line.contains("[x]")).map(|line| line.trim()).collect();
+        assert_eq!(enabled, vec!["[x] beforeSwap", "[x] beforeSwapReturnDelta"]);
+        assert_eq!(lines[1], "  [ ] tickCross (experimental)");
+
+        let invalid = HookFlags::new(HookFlags::AFTER_SWAP_RETURNS_DELTA).apply_to_address(Address::zero());
+        assert!(describe_hook_address(invalid).ends_with("warning: a return delta flag is set without its callback"));
+        // Bits above the flags carry no callbacks
+        let no_callbacks = HookFlags::new(0).apply_to_address(Address::repeat_byte(0x80));
+        assert!(describe_hook_address(no_callbacks).ends_with("pools need a dynamic fee to use this address"));
+        assert!(describe_hook_address(Address::zero()).ends_with("no hooks, no callbacks fire"));
+    }
+}
@@ -0,0 +1,529 @@
+//! Instrumentation of hook callbacks
+//!
+//! [`InstrumentedHook`] wraps any hook and forwards every callback to it,
+//! recording per callback how often it ran, how often it failed, the time it
+//! took and the deltas it returned. The records live in a [`HookStats`]
+//! handle kept by the caller, so they can be read after a simulation while
+//! the manager owns the hook.
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use ethers::types::Address;
+use serde_json::{json, Value};
+
+use crate::core::{
+    math::types::SqrtPrice,
+    state::{BalanceDelta, CrossDirection, Result as StateResult},
+};
+
+use super::{
+    hook_interface::{ModifyLiquidityParams, PoolKey, SwapParams},
+    AfterHookResult, AfterInitializeResult, BeforeHookResult, BeforeSwapDelta, Hook, HookDescriptor,
+    HookWithReturns, InitializeReport,
+};
+
+/// Records of one callback
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CallbackStats {
+    /// Number of calls
+    pub calls: u64,
+    /// Number of calls that returned an error
+    pub errors: u64,
+    /// Time spent in the callback over all calls
+    pub time: Duration,
+    /// Sum of the deltas the callback returned in token0, positive when the
+    /// hook is owed; saturates instead of overflowing
+    pub delta0: i128,
+    /// Sum of the deltas the callback returned in token1
+    pub delta1: i128,
+}
+
+impl CallbackStats {
+    /// Mean time of a call, zero before the first call
+    pub fn mean_time(&self) -> Duration {
+        u32::try_from(self.calls).ok().filter(|calls| *calls > 0).map_or(Duration::ZERO, |calls| self.time / calls)
+    }
+
+    fn add(&mut self, other: &CallbackStats) {
+        self.calls += other.calls;
+        self.errors += other.errors;
+        self.time += other.time;
+        self.delta0 = self.delta0.saturating_add(other.delta0);
+        self.delta1 = self.delta1.saturating_add(other.delta1);
+    }
+}
+
+/// Handle to the records of an [`InstrumentedHook`]
+///
+/// Cloning the handle shares the same records. Callbacks are named after
+/// the trait methods, such as `before_swap` or `after_swap_with_delta`.
+#[derive(Debug, Clone, Default)]
+pub struct HookStats {
+    callbacks: Rc<RefCell<BTreeMap<&'static str, CallbackStats>>>,
+}
+
+impl HookStats {
+    /// Creates empty records
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records of a callback, zero if it never ran
+    pub fn callback(&self, name: &str) -> CallbackStats {
+        self.callbacks.borrow().get(name).copied().unwrap_or_default()
+    }
+
+    /// Records of every callback that ran, by name
+    pub fn callbacks(&self) -> BTreeMap<&'static str, CallbackStats> {
+        self.callbacks.borrow().clone()
+    }
+
+    /// Records of all callbacks together
+    pub fn total(&self) -> CallbackStats {
+        let mut total = CallbackStats::default();
+        for stats in self.callbacks.borrow().values() {
+            total.add(stats);
+        }
+        total
+    }
+
+    /// Forgets every record
+    pub fn reset(&self) {
+        self.callbacks.borrow_mut().clear();
+    }
+
+    /// Exports the records as a JSON object by callback name, with times in
+    /// nanoseconds and deltas as decimal strings
+    pub fn to_json(&self) -> Value {
+        Value::Object(
+            self.callbacks
+                .borrow()
+                .iter()
+                .map(|(name, stats)| (name.to_string(), json!({
+                    "calls": stats.calls,
+                    "errors": stats.errors,
+                    "time_ns": stats.time.as_nanos().to_string(),
+                    "delta0": stats.delta0.to_string(),
+                    "delta1": stats.delta1.to_string(),
+                })))
+                .collect(),
+        )
+    }
+
+    fn record(&self, name: &'static str, time: Duration, failed: bool, (delta0, delta1): (i128, i128)) {
+        self.callbacks.borrow_mut().entry(name).or_default().add(&CallbackStats {
+            calls: 1,
+            errors: failed.into(),
+            time,
+            delta0,
+            delta1,
+        });
+    }
+}
+
+/// A hook that forwards every callback to `H`, recording it in a
+/// [`HookStats`]
+///
+/// The wrapper behaves exactly like the hook it wraps, so it can be
+/// registered in its place without changing results. Copies made for quotes
+/// are the inner hook's own and are not recorded.
+pub struct InstrumentedHook<H> {
+    inner: H,
+    stats: HookStats,
+}
+
+impl<H> InstrumentedHook<H> {
+    /// Wraps a hook with empty records
+    pub fn new(inner: H) -> Self {
+        Self::with_stats(inner, HookStats::new())
+    }
+
+    /// Wraps a hook recording into existing records, for example to sum
+    /// several hooks
+    pub fn with_stats(inner: H, stats: HookStats) -> Self {
+        Self { inner, stats }
+    }
+
+    /// Handle to the records
+    pub fn stats(&self) -> HookStats {
+        self.stats.clone()
+    }
+
+    /// The wrapped hook
+    pub fn inner(&self) -> &H {
+        &self.inner
+    }
+
+    /// Unwraps the hook
+    pub fn into_inner(self) -> H {
+        self.inner
+    }
+
+    /// Runs a callback of the inner hook, recording it with the delta
+    /// `delta` reads from its result
+    fn call<T>(
+        &mut self,
+        name: &'static str,
+        callback: impl FnOnce(&mut H) -> StateResult<T>,
+        delta: impl FnOnce(&T) -> (i128, i128),
+    ) -> StateResult<T> {
+        let start = Instant::now();
+        let result = callback(&mut self.inner);
+        let time = start.elapsed();
+        let returned = result.as_ref().map_or((0, 0), delta);
+        self.stats.record(name, time, result.is_err(), returned);
+        result
+    }
+}
+
+fn no_delta<T>(_: &T) -> (i128, i128) {
+    (0, 0)
+}
+
+fn balance_delta(delta: Option<&BalanceDelta>) -> (i128, i128) {
+    delta.map_or((0, 0), |delta| (delta.amount0(), delta.amount1()))
+}
+
+/// Splits a swap delta in the specified and unspecified currencies into
+/// token0 and token1; the specified currency is token0 for exact input
+/// zero for one swaps and exact output one for zero swaps
+fn swap_delta(params: &SwapParams, specified: i128, unspecified: i128) -> (i128, i128) {
+    let delta = BeforeSwapDelta { delta_specified: specified, delta_unspecified: unspecified }.to_balance_delta(params);
+    (delta.amount0, delta.amount1)
+}
+
+impl<H: Hook> Hook for InstrumentedHook<H> {
+    fn describe(&self) -> HookDescriptor {
+        self.inner.describe()
+    }
+
+    fn fee_price_bucket(&self) -> Option<u32> {
+        self.inner.fee_price_bucket()
+    }
+
+    fn before_initialize(
+        &mut self,
+        sender: Address,
+        key: &PoolKey,
+        sqrt_price_x96: SqrtPrice,
+        hook_data: &[u8],
+    ) -> StateResult<BeforeHookResult> {
+        self.call(
+            "before_initialize",
+            |hook| hook.before_initialize(sender, key, sqrt_price_x96, hook_data),
+            no_delta,
+        )
+    }
+
+    fn after_initialize(
+        &mut self,
+        sender: Address,
+        key: &PoolKey,
+        sqrt_price_x96: SqrtPrice,
+        tick: i32,
+        hook_data: &[u8],
+    ) -> StateResult<AfterHookResult> {
+        self.call(
+            "after_initialize",
+            |hook| hook.after_initialize(sender, key, sqrt_price_x96, tick, hook_data),
+            no_delta,
+        )
+    }
+
+    fn before_add_liquidity(
+        &mut self,
+        sender: Address,
+        key: &PoolKey,
+        params: &ModifyLiquidityParams,
+        hook_data: &[u8],
+    ) -> StateResult<BeforeHookResult> {
+        self.call(
+            "before_add_liquidity",
+            |hook| hook.before_add_liquidity(sender, key, params, hook_data),
+            |result| balance_delta(result.delta.as_ref()),
+        )
+    }
+
+    fn after_add_liquidity(
+        &mut self,
+        sender: Address,
+        key: &PoolKey,
+        params: &ModifyLiquidityParams,
+        delta: &BalanceDelta,
+        fees_accrued: &BalanceDelta,
+        hook_data: &[u8],
+    ) -> StateResult<AfterHookResult> {
+        self.call(
+            "after_add_liquidity",
+            |hook| hook.after_add_liquidity(sender, key, params, delta, fees_accrued, hook_data),
+            |result| balance_delta(result.delta.as_ref()),
+        )
+    }
+
+    fn before_remove_liquidity(
+        &mut self,
+        sender: Address,
+        key: &PoolKey,
+        params: &ModifyLiquidityParams,
+        hook_data: &[u8],
+    ) -> StateResult<BeforeHookResult> {
+        self.call(
+            "before_remove_liquidity",
+            |hook| hook.before_remove_liquidity(sender, key, params, hook_data),
+            |result| balance_delta(result.delta.as_ref()),
+        )
+    }
+
+    fn after_remove_liquidity(
+        &mut self,
+        sender: Address,
+        key: &PoolKey,
+        params: &ModifyLiquidityParams,
+        delta: &BalanceDelta,
+        fees_accrued: &BalanceDelta,
+        hook_data: &[u8],
+    ) -> StateResult<AfterHookResult> {
+        self.call(
+            "after_remove_liquidity",
+            |hook| hook.after_remove_liquidity(sender, key, params, delta, fees_accrued, hook_data),
+            |result| balance_delta(result.delta.as_ref()),
+        )
+    }
+
+    fn before_swap(
+        &mut self,
+        sender: Address,
+        key: &PoolKey,
+        params: &SwapParams,
+        hook_data: &[u8],
+    ) -> StateResult<BeforeHookResult> {
+        self.call(
+            "before_swap",
+            |hook| hook.before_swap(sender, key, params, hook_data),
+            |result| balance_delta(result.delta.as_ref()),
+        )
+    }
+
+    fn after_swap(
+        &mut self,
+        sender: Address,
+        key: &PoolKey,
+        params: &SwapParams,
+        delta: &BalanceDelta,
+        hook_data: &[u8],
+    ) -> StateResult<AfterHookResult> {
+        self.call(
+            "after_swap",
+            |hook| hook.after_swap(sender, key, params, delta, hook_data),
+            |result| balance_delta(result.delta.as_ref()),
+        )
+    }
+
+    fn on_tick_cross(
+        &mut self,
+        key: &PoolKey,
+        tick: i32,
+        direction: CrossDirection,
+        liquidity_net: i128,
+    ) -> StateResult<()> {
+        self.call(
+            "on_tick_cross",
+            |hook| hook.on_tick_cross(key, tick, direction, liquidity_net),
+            no_delta,
+        )
+    }
+
+    fn before_donate(
+        &mut self,
+        sender: Address,
+        key: &PoolKey,
+        amount0: u128,
+        amount1: u128,
+        hook_data: &[u8],
+    ) -> StateResult<BeforeHookResult> {
+        self.call(
+            "before_donate",
+            |hook| hook.before_donate(sender, key, amount0, amount1, hook_data),
+            |result| balance_delta(result.delta.as_ref()),
+        )
+    }
+
+    fn after_donate(
+        &mut self,
+        sender: Address,
+        key: &PoolKey,
+        amount0: u128,
+        amount1: u128,
+        hook_data: &[u8],
+    ) -> StateResult<AfterHookResult> {
+        self.call(
+            "after_donate",
+            |hook| hook.after_donate(sender, key, amount0, amount1, hook_data),
+            |result| balance_delta(result.delta.as_ref()),
+        )
+    }
+}
+
+impl<H: HookWithReturns> HookWithReturns for InstrumentedHook<H> {
+    fn clone_for_quote(&self) -> Option<Box<dyn HookWithReturns>> {
+        self.inner.clone_for_quote()
+    }
+
+    fn after_initialize_with_report(
+        &mut self,
+        sender: Address,
+        key: &PoolKey,
+        report: &InitializeReport,
+        hook_data: &[u8],
+    ) -> StateResult<AfterInitializeResult> {
+        self.call(
+            "after_initialize_with_report",
+            |hook| hook.after_initialize_with_report(sender, key, report, hook_data),
+            no_delta,
+        )
+    }
+
+    fn before_swap_with_delta(
+        &mut self,
+        sender: Address,
+        key: &PoolKey,
+        params: &SwapParams,
+        hook_data: &[u8],
+    ) -> StateResult<BeforeSwapDelta> {
+        self.call(
+            "before_swap_with_delta",
+            |hook| hook.before_swap_with_delta(sender, key, params, hook_data),
+            |delta| swap_delta(params, delta.delta_specified, delta.delta_unspecified),
+        )
+    }
+
+    fn after_swap_with_delta(
+        &mut self,
+        sender: Address,
+        key: &PoolKey,
+        params: &SwapParams,
+        delta: &BalanceDelta,
+        hook_data: &[u8],
+    ) -> StateResult<i128> {
+        self.call(
+            "after_swap_with_delta",
+            |hook| hook.after_swap_with_delta(sender, key, params, delta, hook_data),
+            |unspecified| swap_delta(params, 0, *unspecified),
+        )
+    }
+
+    fn after_add_liquidity_with_delta(
+        &mut self,
+        sender: Address,
+        key: &PoolKey,
+        params: &ModifyLiquidityParams,
+        delta: &BalanceDelta,
+        fees_accrued: &BalanceDelta,
+        hook_data: &[u8],
+    ) -> StateResult<BalanceDelta> {
+        self.call(
+            "after_add_liquidity_with_delta",
+            |hook| hook.after_add_liquidity_with_delta(sender, key, params, delta, fees_accrued, hook_data),
+            |delta| balance_delta(Some(delta)),
+        )
+    }
+
+    fn after_remove_liquidity_with_delta(
+        &mut self,
+        sender: Address,
+        key: &PoolKey,
+        params: &ModifyLiquidityParams,
+        delta: &BalanceDelta,
+        fees_accrued: &BalanceDelta,
+        hook_data: &[u8],
+    ) -> StateResult<BalanceDelta> {
+        self.call(
+            "after_remove_liquidity_with_delta",
+            |hook| hook.after_remove_liquidity_with_delta(sender, key, params, delta, fees_accrued, hook_data),
+            |delta| balance_delta(Some(delta)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{
+        hooks::{typestate::TypedHook, HookError, HookFlags},
+        math::{tick_math::TickMath, types::TickSpacing},
+        pool_manager::{ManagerPoolKey, PoolManager},
+        state::StateError,
+    };
+
+    #[test]
+    fn test_records_calls_errors_and_deltas() {
+        let mut manager = PoolManager::new();
+        let hooks = HookFlags::new(HookFlags::BEFORE_SWAP | HookFlags::AFTER_SWAP).apply_to_address(Address::repeat_byte(0xD0));
+        // Refuses swaps with hook data, takes 7 of token1 from the others
+        let hook = TypedHook::new("taxing")
+            .with_before_swap(|_, _, _, data| {
+                if data.is_empty() {
+                    Ok(BeforeHookResult::default())
+                } else {
+                    Err(StateError::Hook(HookError::HookCallReverted("no data".into())))
+                }
+            })
+            .with_after_swap(|_, _, _, _, _| Ok(AfterHookResult { delta: Some(BalanceDelta::new(0, 7)) }));
+        let hook = InstrumentedHook::new(hook);
+        let stats = hook.stats();
+        manager.hook_registry_mut().register_hook(hooks, Box::new(hook));
+
+        let key = ManagerPoolKey::new(
+            Address::from_low_u64_be(1),
+            Address::from_low_u64_be(2),
+            3000,
+            TickSpacing::new(60).unwrap(),
+            hooks,
+        ).unwrap();
+        manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+        let params = ModifyLiquidityParams::default_position(Address::repeat_byte(1), -600, 600, 1_000_000_000);
+        manager.modify_liquidity(key.clone(), params, &[]).unwrap();
+        let limit = TickMath::MIN_SQRT_PRICE + 1;
+        manager.swap(&key, true, -1_000, limit, &[]).unwrap();
+        manager.swap(&key, true, -1_000, limit, &[]).unwrap();
+        assert!(manager.swap(&key, true, -1_000, limit, &[1]).is_err());
+
+        let before = stats.callback("before_swap");
+        assert_eq!((before.calls, before.errors, before.delta0, before.delta1), (3, 1, 0, 0));
+        let after = stats.callback("after_swap");
+        assert_eq!((after.calls, after.errors, after.delta0, after.delta1), (2, 0, 0, 14));
+        assert_eq!(stats.callback("before_donate"), CallbackStats::default());
+        // Initialization and the liquidity added ran through the hook too
+        let names: Vec<_> = stats.callbacks().into_keys().collect();
+        assert_eq!(names, [
+            "after_add_liquidity",
+            "after_initialize_with_report",
+            "after_swap",
+            "before_add_liquidity",
+            "before_initialize",
+            "before_swap",
+        ]);
+        assert_eq!((stats.total().calls, stats.total().errors, stats.total().delta1), (9, 1, 14));
+        assert_eq!(stats.to_json()["after_swap"]["delta1"], "14");
+
+        stats.reset();
+        assert!(stats.callbacks().is_empty());
+    }
+
+    #[test]
+    fn test_swap_deltas_split_by_specified_currency() {
+        let params = |zero_for_one, amount_specified| SwapParams {
+            amount_specified,
+            zero_for_one,
+            sqrt_price_limit_x96: SqrtPrice::ONE,
+        };
+        // Exact input zero for one specifies token0, exact output token1
+        assert_eq!(swap_delta(&params(true, -10), 3, 4), (3, 4));
+        assert_eq!(swap_delta(&params(true, 10), 3, 4), (4, 3));
+        assert_eq!(swap_delta(&params(false, -10), 3, 4), (4, 3));
+        assert_eq!(swap_delta(&params(false, 10), 3, 4), (3, 4));
+    }
+}
@@ -1,23 +1,50 @@
 pub mod hook_interface;
 pub mod hook_registry;
 pub mod examples;
+pub mod typestate;
+pub mod deployer;
+pub mod context;
+pub mod clock;
+pub mod inspect;
+pub mod fee_cache;
+pub mod revert;
+pub mod instrument;
+pub mod util;
 
-use crate::core::state::BalanceDelta;
+use crate::core::{
+    math::{types::{SqrtPrice, TickSpacing}, FeePips},
+    state::{BalanceDelta, Result as StateResult, Salt, StateError},
+};
 use ethers::types::Address;
+use serde::Serialize;
+use std::{collections::BTreeMap, fmt};
 
 pub use hook_interface::*;
 pub use hook_registry::*;
 pub use examples::*;
+pub use context::HookContext;
+pub use clock::Clock;
+pub use inspect::describe_hook_address;
+pub use fee_cache::HookFeeCache;
+pub use revert::RevertData;
+pub use instrument::{CallbackStats, HookStats, InstrumentedHook};
 
 /// Result of a before hook call
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct BeforeHookResult {
-    /// Optional modified amount
+    /// Amount to swap instead of the amount specified, only read from
+    /// `before_swap`
+    ///
+    /// It replaces the amount outright, so it can't be combined with a
+    /// delta: a hook that fills part of the swap returns the delta alone.
     pub amount: Option<i128>,
     /// Optional balance delta
     pub delta: Option<BalanceDelta>,
-    /// Optional fee override
-    pub fee_override: Option<u32>,
+    /// Optional LP fee override
+    pub fee_override: Option<FeePips>,
+    /// Reason the hook refuses the pool, only read from `before_initialize`
+    pub rejection: Option<InitializeRejection>,
 }
 
 impl Default for BeforeHookResult {
@@ -26,12 +53,88 @@ impl Default for BeforeHookResult {
             amount: None,
             delta: None,
             fee_override: None,
+            rejection: None,
         }
     }
 }
 
+impl BeforeHookResult {
+    /// Result of a `before_initialize` that refuses the pool, failing the
+    /// initialization with [`PoolError::RejectedByHook`](crate::core::pool::PoolError::RejectedByHook)
+    pub fn reject(reason: impl Into<InitializeRejection>) -> Self {
+        Self { rejection: Some(reason.into()), ..Default::default() }
+    }
+
+    /// Sets the amount to swap instead of the amount specified
+    pub fn with_amount(mut self, amount: i128) -> Self {
+        self.amount = Some(amount);
+        self
+    }
+
+    /// Sets the balance delta
+    pub fn with_delta(mut self, delta: BalanceDelta) -> Self {
+        self.delta = Some(delta);
+        self
+    }
+
+    /// Sets the LP fee override
+    pub fn with_fee_override(mut self, fee: FeePips) -> Self {
+        self.fee_override = Some(fee);
+        self
+    }
+}
+
+/// Why a hook refuses to initialize a pool
+///
+/// A `before_initialize` that returns a rejection vetoes the pool, which is
+/// then never created; one that returns an error instead failed, and the
+/// error is passed on as is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InitializeRejection {
+    /// The hook doesn't support the fee, `required` if it supports only one
+    UnsupportedFee { fee: u32, required: Option<u32> },
+    /// The hook doesn't support the tick spacing, `required` if it supports
+    /// only one
+    UnsupportedTickSpacing { tick_spacing: i32, required: Option<i32> },
+    /// The hook doesn't support the pair of currencies
+    UnsupportedCurrencies { token0: Address, token1: Address },
+    /// The hook doesn't support the initial price
+    UnsupportedPrice(SqrtPrice),
+    /// Any other reason
+    Other(String),
+}
+
+impl fmt::Display for InitializeRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedFee { fee, required: Some(required) } => write!(f, "fee must be {required}, got {fee}"),
+            Self::UnsupportedFee { fee, required: None } => write!(f, "unsupported fee {fee}"),
+            Self::UnsupportedTickSpacing { tick_spacing, required: Some(required) } => {
+                write!(f, "tick spacing must be {required}, got {tick_spacing}")
+            }
+            Self::UnsupportedTickSpacing { tick_spacing, required: None } => write!(f, "unsupported tick spacing {tick_spacing}"),
+            Self::UnsupportedCurrencies { token0, token1 } => write!(f, "unsupported currencies {token0:?} and {token1:?}"),
+            Self::UnsupportedPrice(price) => write!(f, "unsupported initial price {price}"),
+            Self::Other(reason) => f.write_str(reason),
+        }
+    }
+}
+
+impl From<String> for InitializeRejection {
+    fn from(reason: String) -> Self {
+        Self::Other(reason)
+    }
+}
+
+impl From<&str> for InitializeRejection {
+    fn from(reason: &str) -> Self {
+        Self::Other(reason.to_string())
+    }
+}
+
 /// Result of an after hook call
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct AfterHookResult {
     /// Optional balance delta
     pub delta: Option<BalanceDelta>,
@@ -45,8 +148,62 @@ impl Default for AfterHookResult {
     }
 }
 
-/// BeforeSwapDelta represents the hook's delta in specified and unspecified currencies
+impl AfterHookResult {
+    /// Sets the balance delta
+    pub fn with_delta(mut self, delta: BalanceDelta) -> Self {
+        self.delta = Some(delta);
+        self
+    }
+}
+
+/// State of a pool right after initialization, passed to
+/// [`HookWithReturns::after_initialize_with_report`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InitializeReport {
+    /// Initial sqrt price
+    pub sqrt_price_x96: SqrtPrice,
+    /// Initial tick
+    pub tick: i32,
+    /// LP fee the pool starts with, resolved from the key's fee, so the
+    /// initial fee of a dynamic fee pool rather than the dynamic fee flag
+    pub lp_fee: FeePips,
+}
+
+/// Liquidity a hook adds to a pool it just initialized, owned by the hook
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LiquiditySeed {
+    /// Lower tick bound
+    pub tick_lower: i32,
+    /// Upper tick bound
+    pub tick_upper: i32,
+    /// Liquidity to add
+    pub liquidity: u128,
+    /// Salt to distinguish the hook's positions
+    pub salt: Salt,
+}
+
+impl LiquiditySeed {
+    /// Seed over the widest range the tick spacing allows
+    pub fn full_range(tick_spacing: TickSpacing, liquidity: u128) -> Self {
+        Self {
+            tick_lower: tick_spacing.min_usable_tick(),
+            tick_upper: tick_spacing.max_usable_tick(),
+            liquidity,
+            salt: Salt::ZERO,
+        }
+    }
+}
+
+/// Result of an after initialize hook call
 #[derive(Debug, Clone, Default)]
+pub struct AfterInitializeResult {
+    /// Liquidity to add for the hook before initialization returns, in
+    /// order; the hook owes the amounts
+    pub seeds: Vec<LiquiditySeed>,
+}
+
+/// BeforeSwapDelta represents the hook's delta in specified and unspecified currencies
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub struct BeforeSwapDelta {
     /// Delta in specified currency (positive means hook is owed, negative means hook owes)
     pub delta_specified: i128,
@@ -54,6 +211,100 @@ pub struct BeforeSwapDelta {
     pub delta_unspecified: i128,
 }
 
+impl BeforeSwapDelta {
+    /// Splits a delta in token order into the specified and unspecified
+    /// currencies of a swap, see [`SwapParams::specified_is_token0`]
+    pub fn from_balance_delta(delta: BalanceDelta, params: &SwapParams) -> Self {
+        let (delta_specified, delta_unspecified) = if params.specified_is_token0() {
+            (delta.amount0, delta.amount1)
+        } else {
+            (delta.amount1, delta.amount0)
+        };
+        Self { delta_specified, delta_unspecified }
+    }
+
+    /// Orders the delta by token for a swap
+    pub fn to_balance_delta(&self, params: &SwapParams) -> BalanceDelta {
+        if params.specified_is_token0() {
+            BalanceDelta::new(self.delta_specified, self.delta_unspecified)
+        } else {
+            BalanceDelta::new(self.delta_unspecified, self.delta_specified)
+        }
+    }
+
+    /// Adds a delta returned from `before_swap_with_delta` to the one from
+    /// `before_swap`, which comes in token order
+    ///
+    /// A result that also replaces the amount to swap is rejected with
+    /// [`HookError::InvalidHookResponse`], as the delta would be taken out of
+    /// the amount twice.
+    pub fn combine(result: &BeforeHookResult, returned: BeforeSwapDelta, params: &SwapParams) -> StateResult<Self> {
+        let returns_delta = result.delta.is_some_and(|delta| !delta.is_zero()) || returned != Self::default();
+        if result.amount.is_some() && returns_delta {
+            return Err(HookError::InvalidHookResponse.into());
+        }
+        let delta = result.delta.map_or_else(Self::default, |delta| Self::from_balance_delta(delta, params));
+        Ok(Self {
+            delta_specified: delta.delta_specified
+                .checked_add(returned.delta_specified)
+                .ok_or(StateError::AmountOverflow)?,
+            delta_unspecified: delta.delta_unspecified
+                .checked_add(returned.delta_unspecified)
+                .ok_or(StateError::AmountOverflow)?,
+        })
+    }
+
+    /// Adds the deltas of `after_swap`, which comes in token order, and
+    /// `after_swap_with_delta` to the ones from before the swap
+    ///
+    /// After the swap only the unspecified currency can change, so an
+    /// `after_swap` delta in the specified currency is rejected with
+    /// [`HookError::SpecifiedDeltaAfterSwap`]. Returns the combined delta
+    /// and the part added after the swap.
+    pub fn fold_after_swap(
+        &self,
+        result: &AfterHookResult,
+        returned: i128,
+        params: &SwapParams,
+        hook: Address,
+    ) -> StateResult<(Self, i128)> {
+        let delta = result.delta.map_or_else(Self::default, |delta| Self::from_balance_delta(delta, params));
+        if delta.delta_specified != 0 {
+            return Err(HookError::SpecifiedDeltaAfterSwap { hook }.into());
+        }
+        let after_swap = delta.delta_unspecified.checked_add(returned).ok_or(StateError::AmountOverflow)?;
+        let folded = Self {
+            delta_specified: self.delta_specified,
+            delta_unspecified: self.delta_unspecified.checked_add(after_swap).ok_or(StateError::AmountOverflow)?,
+        };
+        Ok((folded, after_swap))
+    }
+
+    /// Takes the hook's specified delta out of the amount a swap runs with
+    ///
+    /// The hook filling part of the swap can't turn exact input into exact
+    /// output or back, which fails with
+    /// [`HookError::HookDeltaExceedsSwapAmount`].
+    pub fn apply_to_amount(&self, amount: i128) -> StateResult<i128> {
+        let exact_input = amount < 0;
+        let amount = amount.checked_add(self.delta_specified).ok_or(StateError::AmountOverflow)?;
+        if amount != 0 && exact_input != (amount < 0) {
+            return Err(HookError::HookDeltaExceedsSwapAmount.into());
+        }
+        Ok(amount)
+    }
+
+    /// Takes the hook's delta out of the pool's swap delta, leaving the
+    /// caller's
+    pub fn caller_delta(&self, swap_delta: BalanceDelta, params: &SwapParams) -> StateResult<BalanceDelta> {
+        let hook_delta = self.to_balance_delta(params);
+        Ok(BalanceDelta::new(
+            swap_delta.amount0.checked_sub(hook_delta.amount0).ok_or(StateError::AmountOverflow)?,
+            swap_delta.amount1.checked_sub(hook_delta.amount1).ok_or(StateError::AmountOverflow)?,
+        ))
+    }
+}
+
 /// Flags for determining which hooks are enabled
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct HookFlags(u16);
@@ -79,15 +330,118 @@ impl HookFlags {
     // Mask for all hooks
     pub const ALL_HOOK_MASK: u16 = 0x3FFF; // Covers all 14 hooks
 
+    /// Experimental: calls `on_tick_cross` for each initialized tick a swap
+    /// crosses. Not a v4 flag, so it sits above [`ALL_HOOK_MASK`](Self::ALL_HOOK_MASK)
+    pub const TICK_CROSS: u16 = 0x1 << 14;
+
+    /// Mask for the experimental flags
+    pub const EXPERIMENTAL_MASK: u16 = Self::TICK_CROSS;
+
+    /// Every flag with the name of its callback in the v4 `Hooks` library,
+    /// from the highest bit down
+    pub const NAMED: [(u16, &'static str); 15] = [
+        (Self::TICK_CROSS, "tickCross"),
+        (Self::BEFORE_INITIALIZE, "beforeInitialize"),
+        (Self::AFTER_INITIALIZE, "afterInitialize"),
+        (Self::BEFORE_ADD_LIQUIDITY, "beforeAddLiquidity"),
+        (Self::AFTER_ADD_LIQUIDITY, "afterAddLiquidity"),
+        (Self::BEFORE_REMOVE_LIQUIDITY, "beforeRemoveLiquidity"),
+        (Self::AFTER_REMOVE_LIQUIDITY, "afterRemoveLiquidity"),
+        (Self::BEFORE_SWAP, "beforeSwap"),
+        (Self::AFTER_SWAP, "afterSwap"),
+        (Self::BEFORE_DONATE, "beforeDonate"),
+        (Self::AFTER_DONATE, "afterDonate"),
+        (Self::BEFORE_SWAP_RETURNS_DELTA, "beforeSwapReturnDelta"),
+        (Self::AFTER_SWAP_RETURNS_DELTA, "afterSwapReturnDelta"),
+        (Self::AFTER_ADD_LIQUIDITY_RETURNS_DELTA, "afterAddLiquidityReturnDelta"),
+        (Self::AFTER_REMOVE_LIQUIDITY_RETURNS_DELTA, "afterRemoveLiquidityReturnDelta"),
+    ];
+
     /// Creates a new set of hook flags from a raw value
     pub fn new(flags: u16) -> Self {
         Self(flags)
     }
 
     /// Creates a set of hook flags from an address
-    pub fn from_address(address: [u8; 20]) -> Self {
-        let flags = u16::from_le_bytes([address[0], address[1]]);
-        Self(flags)
+    ///
+    /// As in v4, the flags are the lowest-order bits of the 160-bit address,
+    /// which are its last two bytes read big-endian. Raw `[u8; 20]`
+    /// addresses are still accepted for compatibility.
+    pub fn from_address(address: impl Into<Address>) -> Self {
+        let address = address.into();
+        Self(u16::from_be_bytes([address[18], address[19]]))
+    }
+
+    /// The last two bytes of an address carrying these flags
+    pub fn to_address_suffix(&self) -> [u8; 2] {
+        self.0.to_be_bytes()
+    }
+
+    /// Writes these flags into the flag bits of an address
+    ///
+    /// Inverse of `from_address`: bits outside the hook and experimental
+    /// masks are preserved.
+    pub fn apply_to_address(&self, address: impl Into<Address>) -> Address {
+        let mut address = address.into();
+        let existing = Self::from_address(address).0;
+        let mask = Self::ALL_HOOK_MASK | Self::EXPERIMENTAL_MASK;
+        let flags = Self((existing & !mask) | (self.0 & mask));
+        address.0[18..].copy_from_slice(&flags.to_address_suffix());
+        address
+    }
+
+    /// Creates the flags a hook with these permissions must have in its address
+    pub fn from_permissions(permissions: &HookPermissions) -> Self {
+        let flag = |enabled: bool, bit: u16| if enabled { bit } else { 0 };
+        Self(
+            flag(permissions.before_initialize, Self::BEFORE_INITIALIZE)
+                | flag(permissions.after_initialize, Self::AFTER_INITIALIZE)
+                | flag(permissions.before_add_liquidity, Self::BEFORE_ADD_LIQUIDITY)
+                | flag(permissions.after_add_liquidity, Self::AFTER_ADD_LIQUIDITY)
+                | flag(permissions.before_remove_liquidity, Self::BEFORE_REMOVE_LIQUIDITY)
+                | flag(permissions.after_remove_liquidity, Self::AFTER_REMOVE_LIQUIDITY)
+                | flag(permissions.before_swap, Self::BEFORE_SWAP)
+                | flag(permissions.after_swap, Self::AFTER_SWAP)
+                | flag(permissions.before_donate, Self::BEFORE_DONATE)
+                | flag(permissions.after_donate, Self::AFTER_DONATE)
+                | flag(permissions.before_swap_returns_delta, Self::BEFORE_SWAP_RETURNS_DELTA)
+                | flag(permissions.after_swap_returns_delta, Self::AFTER_SWAP_RETURNS_DELTA)
+                | flag(permissions.after_add_liquidity_returns_delta, Self::AFTER_ADD_LIQUIDITY_RETURNS_DELTA)
+                | flag(permissions.after_remove_liquidity_returns_delta, Self::AFTER_REMOVE_LIQUIDITY_RETURNS_DELTA)
+                | flag(permissions.tick_cross, Self::TICK_CROSS),
+        )
+    }
+
+    /// Decodes the flags into the permissions they grant, inverse of
+    /// [`from_permissions`](Self::from_permissions)
+    pub fn permissions(&self) -> HookPermissions {
+        HookPermissions {
+            before_initialize: self.is_enabled(Self::BEFORE_INITIALIZE),
+            after_initialize: self.is_enabled(Self::AFTER_INITIALIZE),
+            before_add_liquidity: self.is_enabled(Self::BEFORE_ADD_LIQUIDITY),
+            after_add_liquidity: self.is_enabled(Self::AFTER_ADD_LIQUIDITY),
+            before_remove_liquidity: self.is_enabled(Self::BEFORE_REMOVE_LIQUIDITY),
+            after_remove_liquidity: self.is_enabled(Self::AFTER_REMOVE_LIQUIDITY),
+            before_swap: self.is_enabled(Self::BEFORE_SWAP),
+            after_swap: self.is_enabled(Self::AFTER_SWAP),
+            before_donate: self.is_enabled(Self::BEFORE_DONATE),
+            after_donate: self.is_enabled(Self::AFTER_DONATE),
+            before_swap_returns_delta: self.is_enabled(Self::BEFORE_SWAP_RETURNS_DELTA),
+            after_swap_returns_delta: self.is_enabled(Self::AFTER_SWAP_RETURNS_DELTA),
+            after_add_liquidity_returns_delta: self.is_enabled(Self::AFTER_ADD_LIQUIDITY_RETURNS_DELTA),
+            after_remove_liquidity_returns_delta: self.is_enabled(Self::AFTER_REMOVE_LIQUIDITY_RETURNS_DELTA),
+            tick_cross: self.is_enabled(Self::TICK_CROSS),
+        }
+    }
+
+    /// Names of the enabled callbacks, in [`NAMED`](Self::NAMED) order
+    pub fn callback_names(&self) -> Vec<&'static str> {
+        Self::NAMED.iter().filter(|(flag, _)| self.is_enabled(*flag)).map(|(_, name)| *name).collect()
+    }
+
+    /// Gets the raw flag bits
+    pub fn bits(&self) -> u16 {
+        self.0
     }
 
     /// Checks if a specific hook is enabled
@@ -139,8 +493,9 @@ impl HookFlags {
             || expected.after_swap_returns_delta != self.is_enabled(Self::AFTER_SWAP_RETURNS_DELTA)
             || expected.after_add_liquidity_returns_delta != self.is_enabled(Self::AFTER_ADD_LIQUIDITY_RETURNS_DELTA)
             || expected.after_remove_liquidity_returns_delta != self.is_enabled(Self::AFTER_REMOVE_LIQUIDITY_RETURNS_DELTA)
+            || expected.tick_cross != self.is_enabled(Self::TICK_CROSS)
         {
-            return Err(HookError::HookAddressNotValid([0u8; 20])); // 实际实现中应该传入真实的地址
+            return Err(HookError::HookAddressNotValid(Address::zero())); // 实际实现中应该传入真实的地址
         }
         
         Ok(())
@@ -164,7 +519,7 @@ pub fn is_dynamic_fee(fee: u32) -> bool {
 }
 
 /// Permissions structure for hooks
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
 pub struct HookPermissions {
     pub before_initialize: bool,
     pub after_initialize: bool,
@@ -180,13 +535,98 @@ pub struct HookPermissions {
     pub after_swap_returns_delta: bool,
     pub after_add_liquidity_returns_delta: bool,
     pub after_remove_liquidity_returns_delta: bool,
+    /// Experimental, see [`HookFlags::TICK_CROSS`]
+    pub tick_cross: bool,
+}
+
+/// Lists the enabled callbacks by their v4 names, separated by commas, or
+/// `none`
+impl fmt::Display for HookPermissions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names = HookFlags::from_permissions(self).callback_names();
+        if names.is_empty() {
+            write!(f, "none")
+        } else {
+            write!(f, "{}", names.join(", "))
+        }
+    }
+}
+
+/// Human-readable description of a hook implementation
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct HookDescriptor {
+    /// Name of the hook
+    pub name: String,
+    /// Version of the hook implementation
+    pub version: String,
+    /// Callbacks the hook implements
+    pub permissions: HookPermissions,
+    /// Summary of the hook configuration, keyed by parameter name
+    pub config: BTreeMap<String, String>,
+}
+
+impl HookDescriptor {
+    /// Creates a new descriptor with an empty configuration summary
+    pub fn new(name: impl Into<String>, version: impl Into<String>, permissions: HookPermissions) -> Self {
+        Self {
+            name: name.into(),
+            version: version.into(),
+            permissions,
+            config: BTreeMap::new(),
+        }
+    }
+
+    /// Adds a configuration entry to the summary
+    pub fn with_config(mut self, key: impl Into<String>, value: impl ToString) -> Self {
+        self.config.insert(key.into(), value.to_string());
+        self
+    }
+}
+
+/// Hook callback, identifying which call of a hook misbehaved
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HookCallback {
+    BeforeInitialize,
+    AfterInitialize,
+    BeforeAddLiquidity,
+    AfterAddLiquidity,
+    BeforeRemoveLiquidity,
+    AfterRemoveLiquidity,
+    BeforeSwap,
+    AfterSwap,
+    BeforeDonate,
+    AfterDonate,
+}
+
+impl HookCallback {
+    /// Solidity signature of the callback in `IHooks`
+    pub fn signature(self) -> &'static str {
+        match self {
+            HookCallback::BeforeInitialize => "beforeInitialize(address,(address,address,uint24,int24,address),uint160)",
+            HookCallback::AfterInitialize => "afterInitialize(address,(address,address,uint24,int24,address),uint160,int24)",
+            HookCallback::BeforeAddLiquidity => "beforeAddLiquidity(address,(address,address,uint24,int24,address),(int24,int24,int256,bytes32),bytes)",
+            HookCallback::AfterAddLiquidity => "afterAddLiquidity(address,(address,address,uint24,int24,address),(int24,int24,int256,bytes32),int256,int256,bytes)",
+            HookCallback::BeforeRemoveLiquidity => "beforeRemoveLiquidity(address,(address,address,uint24,int24,address),(int24,int24,int256,bytes32),bytes)",
+            HookCallback::AfterRemoveLiquidity => "afterRemoveLiquidity(address,(address,address,uint24,int24,address),(int24,int24,int256,bytes32),int256,int256,bytes)",
+            HookCallback::BeforeSwap => "beforeSwap(address,(address,address,uint24,int24,address),(bool,int256,uint160),bytes)",
+            HookCallback::AfterSwap => "afterSwap(address,(address,address,uint24,int24,address),(bool,int256,uint160),int256,bytes)",
+            HookCallback::BeforeDonate => "beforeDonate(address,(address,address,uint24,int24,address),uint256,uint256,bytes)",
+            HookCallback::AfterDonate => "afterDonate(address,(address,address,uint24,int24,address),uint256,uint256,bytes)",
+        }
+    }
+
+    /// 4-byte selector of the callback in `IHooks`
+    pub fn selector(self) -> [u8; 4] {
+        ethers::utils::id(self.signature())
+    }
 }
 
 /// Error types for hook operations
 #[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
 pub enum HookError {
     #[error("Hook address not valid: {0:?}")]
-    HookAddressNotValid([u8; 20]),
+    HookAddressNotValid(Address),
     
     #[error("Invalid hook response")]
     InvalidHookResponse,
@@ -199,7 +639,175 @@ pub enum HookError {
     
     #[error("Hook call reverted: {0}")]
     HookCallReverted(String),
+    
+    #[error("Hook call reverted: {0}")]
+    HookCallRevertedWithData(RevertData),
+    
+    #[error("Hook at {0:?} cannot be copied for quoting")]
+    NotQuotable(Address),
+    
+    #[error("Hook at {hook:?} returned a delta from {callback:?} without the permission to return one")]
+    DeltaNotPermitted { hook: Address, callback: HookCallback },
+    
+    #[error("Hook at {hook:?} returned a fee override from {callback:?} for a pool without dynamic fees")]
+    FeeOverrideNotDynamic { hook: Address, callback: HookCallback },
+    
+    #[error("Hook at {hook:?} returned a fee override of {fee} pips from {callback:?}, above the maximum LP fee")]
+    FeeOverrideTooLarge { hook: Address, callback: HookCallback, fee: u32 },
+    
+    #[error("Hook at {hook:?} returned a delta in the specified currency from afterSwap, which can only return one in the unspecified currency")]
+    SpecifiedDeltaAfterSwap { hook: Address },
+}
+
+impl HookError {
+    /// The revert data of a hook that reverted with raw data
+    pub fn revert_data(&self) -> Option<&RevertData> {
+        match self {
+            HookError::HookCallRevertedWithData(data) => Some(data),
+            _ => None,
+        }
+    }
+
+    /// Wraps the failure of a hook call as the `PoolManager` bubbles it up
+    ///
+    /// A hook that reverts, with a message, raw data or none, fails the
+    /// operation with [`HookError::HookCallRevertedWithData`] holding the
+    /// revert wrapped in `WrappedError(hook, callback selector, data,
+    /// HookCallFailed)`, as v4 does. Other errors are typed failures of the
+    /// simulation rather than reverts and are kept as they are.
+    pub fn wrap_call_failure(hook: Address, callback: HookCallback) -> impl Fn(StateError) -> StateError {
+        move |error| {
+            let reason = match error {
+                StateError::Hook(HookError::HookCallFailed) => RevertData::default(),
+                StateError::Hook(HookError::HookCallReverted(message)) => RevertData::message(&message),
+                StateError::Hook(HookError::HookCallRevertedWithData(data)) => data,
+                error => return error,
+            };
+            HookError::HookCallRevertedWithData(RevertData::wrapped(hook, callback, &reason)).into()
+        }
+    }
 }
 
 /// Result type for hook operations
-pub type HookResult<T> = std::result::Result<T, HookError>; 
\ No newline at end of file
+pub type HookResult<T> = std::result::Result<T, HookError>; 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL_FLAGS: [u16; 14] = [
+        HookFlags::BEFORE_INITIALIZE,
+        HookFlags::AFTER_INITIALIZE,
+        HookFlags::BEFORE_ADD_LIQUIDITY,
+        HookFlags::AFTER_ADD_LIQUIDITY,
+        HookFlags::BEFORE_REMOVE_LIQUIDITY,
+        HookFlags::AFTER_REMOVE_LIQUIDITY,
+        HookFlags::BEFORE_SWAP,
+        HookFlags::AFTER_SWAP,
+        HookFlags::BEFORE_DONATE,
+        HookFlags::AFTER_DONATE,
+        HookFlags::BEFORE_SWAP_RETURNS_DELTA,
+        HookFlags::AFTER_SWAP_RETURNS_DELTA,
+        HookFlags::AFTER_ADD_LIQUIDITY_RETURNS_DELTA,
+        HookFlags::AFTER_REMOVE_LIQUIDITY_RETURNS_DELTA,
+    ];
+
+    fn address(hex: &str) -> Address {
+        hex.parse().unwrap()
+    }
+
+    #[test]
+    fn test_flags_are_the_lowest_address_bits() {
+        // The example from the v4 `Hooks` library documentation
+        let flags = HookFlags::from_address(address("0x0000000000000000000000000000000000002400"));
+        assert_eq!(flags, HookFlags::new(HookFlags::BEFORE_INITIALIZE | HookFlags::AFTER_ADD_LIQUIDITY));
+
+        // Mined addresses keep arbitrary high bytes, which carry no flags
+        let mined = address("0xa1b2c3d4e5f60718293a4b5c6d7e8f9012340080");
+        assert_eq!(HookFlags::from_address(mined), HookFlags::new(HookFlags::BEFORE_SWAP));
+        assert!(!HookFlags::from_address(address("0xffffffffffffffffffffffffffffffffffff0000")).has_any_hook());
+
+        // Every flag set, as at the all-hooks address of the v4 tests
+        let all = HookFlags::from_address(address("0x0000000000000000000000000000000000003fff"));
+        assert!(ALL_FLAGS.iter().all(|flag| all.is_enabled(*flag)));
+    }
+
+    #[test]
+    fn test_each_flag_round_trips_through_an_address() {
+        for flag in ALL_FLAGS.into_iter().chain([HookFlags::TICK_CROSS]) {
+            let hooks = Address::from_low_u64_be(flag as u64);
+            let flags = HookFlags::from_address(hooks);
+            assert_eq!(flags, HookFlags::new(flag));
+            assert!(ALL_FLAGS.iter().all(|other| flags.is_enabled(*other) == (*other == flag)));
+            assert_eq!(flags.to_address_suffix(), [hooks[18], hooks[19]]);
+            assert_eq!(flags.apply_to_address(Address::zero()), hooks);
+        }
+    }
+
+    #[test]
+    fn test_fold_after_swap() {
+        // Exact input of token0, so token1 is unspecified
+        let params = SwapParams { amount_specified: -1000, zero_for_one: true, sqrt_price_limit_x96: SqrtPrice::ONE };
+        let hook = Address::repeat_byte(0x44);
+        let before = BeforeSwapDelta { delta_specified: 10, delta_unspecified: 20 };
+        let result = AfterHookResult { delta: Some(BalanceDelta::new(0, 5)) };
+        let (folded, after_swap) = before.fold_after_swap(&result, 7, &params, hook).unwrap();
+        assert_eq!(folded, BeforeSwapDelta { delta_specified: 10, delta_unspecified: 32 });
+        assert_eq!(after_swap, 12);
+
+        let specified = AfterHookResult { delta: Some(BalanceDelta::new(1, 0)) };
+        assert!(matches!(
+            before.fold_after_swap(&specified, 0, &params, hook),
+            Err(StateError::Hook(HookError::SpecifiedDeltaAfterSwap { hook: address })) if address == hook
+        ));
+        // Sums that overflow fail instead of wrapping
+        let full = BeforeSwapDelta { delta_specified: 0, delta_unspecified: i128::MAX };
+        assert!(matches!(full.fold_after_swap(&result, 0, &params, hook), Err(StateError::AmountOverflow)));
+        assert!(matches!(
+            before.fold_after_swap(&result, i128::MAX, &params, hook),
+            Err(StateError::AmountOverflow)
+        ));
+    }
+
+    #[test]
+    fn test_permissions_decode_flags() {
+        for flags in [0, HookFlags::ALL_HOOK_MASK | HookFlags::TICK_CROSS, 0x2400, 0x00c8] {
+            let flags = HookFlags::new(flags);
+            assert_eq!(HookFlags::from_permissions(&flags.permissions()), flags);
+        }
+
+        let permissions = HookFlags::from_address(address("0x00000000000000000000000000000000000000c8")).permissions();
+        assert!(permissions.before_swap && permissions.after_swap && permissions.before_swap_returns_delta);
+        assert!(!permissions.after_swap_returns_delta);
+        assert_eq!(permissions.to_string(), "beforeSwap, afterSwap, beforeSwapReturnDelta");
+        assert_eq!(HookPermissions::default().to_string(), "none");
+    }
+
+    #[test]
+    fn test_apply_to_address_keeps_other_bits() {
+        let base = address("0x1111111111111111111111111111111111118000");
+        let hooks = HookFlags::new(HookFlags::AFTER_ADD_LIQUIDITY | HookFlags::BEFORE_SWAP).apply_to_address(base);
+        assert_eq!(hooks, address("0x1111111111111111111111111111111111118480"));
+        assert_eq!(
+            HookFlags::from_address(hooks).0 & HookFlags::ALL_HOOK_MASK,
+            HookFlags::AFTER_ADD_LIQUIDITY | HookFlags::BEFORE_SWAP
+        );
+    }
+    #[test]
+    fn test_callback_selectors_match_ihooks() {
+        let selectors = [
+            (HookCallback::BeforeInitialize, 0xdc98354e_u32),
+            (HookCallback::AfterInitialize, 0x6fe7e6eb),
+            (HookCallback::BeforeAddLiquidity, 0x259982e5),
+            (HookCallback::AfterAddLiquidity, 0x9f063efc),
+            (HookCallback::BeforeRemoveLiquidity, 0x21d0ee70),
+            (HookCallback::AfterRemoveLiquidity, 0x6c2bbe7e),
+            (HookCallback::BeforeSwap, 0x575e24b4),
+            (HookCallback::AfterSwap, 0xb47b2fb1),
+            (HookCallback::BeforeDonate, 0xb6a8b0fa),
+            (HookCallback::AfterDonate, 0xe1b4af69),
+        ];
+        for (callback, selector) in selectors {
+            assert_eq!(callback.selector(), selector.to_be_bytes(), "{callback:?}");
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use std::fmt;
+
+use ethers::{
+    abi::{encode, Token},
+    contract::{ContractRevert, EthError},
+    types::{Address, Bytes},
+    utils::{hex, keccak256},
+};
+
+use crate::bindings::{HookCallFailed, IHooksErrors, WrappedError};
+
+use super::HookCallback;
+
+/// Raw data a hook reverted with
+///
+/// Kept byte for byte so it can be compared with the revert data of the
+/// Solidity contracts, and decoded on demand against the errors known to the
+/// bindings.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct RevertData(Bytes);
+
+impl RevertData {
+    /// Wraps raw revert data
+    pub fn new(data: impl Into<Bytes>) -> Self {
+        Self(data.into())
+    }
+
+    /// Revert data of a Solidity custom error
+    pub fn custom(error: impl EthError) -> Self {
+        Self::new(error.encode())
+    }
+
+    /// Revert data of a `require` or `revert` with a message
+    pub fn message(message: &str) -> Self {
+        let mut data = keccak256("Error(string)")[..4].to_vec();
+        data.extend(encode(&[Token::String(message.to_string())]));
+        Self::new(data)
+    }
+
+    /// The revert data as the `PoolManager` bubbles it up from a hook call,
+    /// wrapped in `WrappedError(hook, callback selector, data, HookCallFailed)`
+    pub fn wrapped(hook: Address, callback: HookCallback, reason: &RevertData) -> Self {
+        Self::custom(WrappedError {
+            target: hook,
+            selector: callback.selector(),
+            reason: reason.0.clone(),
+            details: HookCallFailed::selector().to_vec().into(),
+        })
+    }
+
+    /// The raw bytes
+    pub fn bytes(&self) -> &Bytes {
+        &self.0
+    }
+
+    /// The 4-byte selector of the error, if the data is long enough to have one
+    pub fn selector(&self) -> Option<[u8; 4]> {
+        self.0.get(..4).map(|selector| selector.try_into().expect("four bytes"))
+    }
+
+    /// Decodes the data as one of the errors known to the bindings, or
+    /// `None` for unknown or malformed errors
+    pub fn decode(&self) -> Option<IHooksErrors> {
+        IHooksErrors::decode_with_selector(&self.0)
+    }
+
+    /// The hook's own revert data, unwrapping `WrappedError`s
+    pub fn root_cause(&self) -> RevertData {
+        match self.decode() {
+            Some(IHooksErrors::WrappedError(wrapped)) => RevertData::new(wrapped.reason).root_cause(),
+            _ => self.clone(),
+        }
+    }
+}
+
+impl From<Vec<u8>> for RevertData {
+    fn from(data: Vec<u8>) -> Self {
+        Self::new(data)
+    }
+}
+
+impl fmt::Display for RevertData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.decode(), self.selector()) {
+            (Some(IHooksErrors::RevertString(message)), _) => write!(f, "{message}"),
+            (Some(IHooksErrors::WrappedError(wrapped)), _) => write!(
+                f,
+                "{:?} reverted in 0x{}: {}",
+                wrapped.target,
+                hex::encode(wrapped.selector),
+                RevertData::new(wrapped.reason),
+            ),
+            (Some(error), _) => write!(f, "{error:?}"),
+            (None, Some(selector)) => write!(f, "unknown error 0x{} (0x{})", hex::encode(selector), hex::encode(&self.0)),
+            (None, None) => write!(f, "empty revert (0x{})", hex::encode(&self.0)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bindings::{HookAddressNotValid, Panic};
+    use ethers::types::U256;
+
+    #[test]
+    fn test_decode_known_errors() {
+        let hook = Address::repeat_byte(0x11);
+        let not_valid = RevertData::custom(HookAddressNotValid { hooks: hook });
+        assert_eq!(not_valid.selector(), Some(HookAddressNotValid::selector()));
+        assert!(matches!(not_valid.decode(), Some(IHooksErrors::HookAddressNotValid(e)) if e.hooks == hook));
+
+        let message = RevertData::message("sale ends at 100");
+        assert_eq!(message.selector(), Some([0x08, 0xc3, 0x79, 0xa0]));
+        assert_eq!(message.to_string(), "sale ends at 100");
+
+        let panic = RevertData::custom(Panic { code: U256::from(0x11) });
+        assert!(matches!(panic.decode(), Some(IHooksErrors::Panic(e)) if e.code == U256::from(0x11)));
+    }
+
+    #[test]
+    fn test_unknown_and_empty_data_keep_their_bytes() {
+        let unknown = RevertData::new(vec![0xde, 0xad, 0xbe, 0xef, 0x01]);
+        assert_eq!(unknown.selector(), Some([0xde, 0xad, 0xbe, 0xef]));
+        assert!(unknown.decode().is_none());
+        assert_eq!(unknown.to_string(), "unknown error 0xdeadbeef (0xdeadbeef01)");
+
+        let empty = RevertData::default();
+        assert_eq!(empty.selector(), None);
+        assert_eq!(empty.to_string(), "empty revert (0x)");
+    }
+
+    #[test]
+    fn test_wrapped_hook_revert() {
+        let hook = Address::repeat_byte(0x22);
+        let reason = RevertData::message("paused");
+        let wrapped = RevertData::wrapped(hook, HookCallback::BeforeSwap, &reason);
+
+        let Some(IHooksErrors::WrappedError(error)) = wrapped.decode() else {
+            panic!("expected a wrapped error");
+        };
+        assert_eq!(error.target, hook);
+        assert_eq!(error.selector, [0x57, 0x5e, 0x24, 0xb4]);
+        assert_eq!(error.details.as_ref(), HookCallFailed::selector());
+        assert_eq!(wrapped.root_cause(), reason);
+        assert!(wrapped.to_string().ends_with("reverted in 0x575e24b4: paused"));
+
+        // Nested wrapping, as when a hook calls back into another hooked pool
+        let outer = RevertData::wrapped(Address::repeat_byte(0x33), HookCallback::AfterSwap, &wrapped);
+        assert_eq!(outer.root_cause(), reason);
+    }
+}
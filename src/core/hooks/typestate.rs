@@ -0,0 +1,725 @@
+//! Typestate builder for hooks whose permissions are fixed at compile time
+//!
+//! Each callback slot of [`TypedHook`] is a type parameter that is either
+//! [`Off`], [`On`] or [`OnWithDelta`]. The `with_*` methods are only available
+//! while the slot is `Off` and move it to the enabled state, so the flags a
+//! hook advertises always match the callbacks it actually provides. Registering
+//! through [`HookRegistry::register_typed`] derives the hook address from those
+//! flags, which removes the `HookAddressNotValid` failure mode entirely.
+//!
+//! ```
+//! use uniswap_v4_core::core::hooks::{typestate::TypedHook, BeforeHookResult, HookRegistry};
+//! use uniswap_v4_core::core::math::FeePips;
+//!
+//! let hook = TypedHook::new("fee-override")
+//!     .with_before_swap(|_sender, _key, _params, _data| {
+//!         Ok(BeforeHookResult::default().with_fee_override(FeePips::new(500)))
+//!     });
+//!
+//! let mut registry = HookRegistry::new();
+//! let address = registry.register_typed([0x42; 20], hook);
+//! assert!(registry.has_hook(&address));
+//! ```
+//!
+//! A slot can only be enabled once, so a hook can't provide `before_swap`
+//! both with and without a returned delta:
+//!
+//! ```compile_fail
+//! use uniswap_v4_core::core::hooks::{typestate::TypedHook, BeforeHookResult, BeforeSwapDelta};
+//!
+//! let hook = TypedHook::new("both")
+//!     .with_before_swap(|_sender, _key, _params, _data| Ok(BeforeHookResult::default()))
+//!     .with_before_swap_returning_delta(|_sender, _key, _params, _data| Ok(BeforeSwapDelta::default()));
+//! ```
+
+use std::marker::PhantomData;
+
+use ethers::types::Address;
+
+use crate::core::{
+    math::types::SqrtPrice,
+    state::{BalanceDelta, Result as StateResult},
+};
+
+use super::{
+    AfterHookResult, BeforeHookResult, BeforeSwapDelta, Hook, HookDescriptor, HookFlags,
+    HookPermissions, HookRegistry, HookWithReturns, ModifyLiquidityParams, PoolKey, SwapParams,
+};
+
+/// State of a single callback slot in a [`TypedHook`]
+pub trait CallbackState {
+    /// Whether the callback is enabled
+    const ENABLED: bool;
+    /// Whether the callback returns a delta
+    const RETURNS_DELTA: bool;
+}
+
+/// Callback is not implemented
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Off;
+
+/// Callback is implemented
+#[derive(Debug, Clone, Copy, Default)]
+pub struct On;
+
+/// Callback is implemented and returns a delta
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OnWithDelta;
+
+impl CallbackState for Off {
+    const ENABLED: bool = false;
+    const RETURNS_DELTA: bool = false;
+}
+
+impl CallbackState for On {
+    const ENABLED: bool = true;
+    const RETURNS_DELTA: bool = false;
+}
+
+impl CallbackState for OnWithDelta {
+    const ENABLED: bool = true;
+    const RETURNS_DELTA: bool = true;
+}
+
+type BeforeInitializeFn = Box<dyn FnMut(Address, &PoolKey, SqrtPrice, &[u8]) -> StateResult<BeforeHookResult>>;
+type AfterInitializeFn = Box<dyn FnMut(Address, &PoolKey, SqrtPrice, i32, &[u8]) -> StateResult<AfterHookResult>>;
+type BeforeModifyLiquidityFn = Box<dyn FnMut(Address, &PoolKey, &ModifyLiquidityParams, &[u8]) -> StateResult<BeforeHookResult>>;
+type AfterModifyLiquidityFn = Box<
+    dyn FnMut(Address, &PoolKey, &ModifyLiquidityParams, &BalanceDelta, &BalanceDelta, &[u8]) -> StateResult<AfterHookResult>,
+>;
+type AfterModifyLiquidityDeltaFn = Box<
+    dyn FnMut(Address, &PoolKey, &ModifyLiquidityParams, &BalanceDelta, &BalanceDelta, &[u8]) -> StateResult<BalanceDelta>,
+>;
+type BeforeSwapFn = Box<dyn FnMut(Address, &PoolKey, &SwapParams, &[u8]) -> StateResult<BeforeHookResult>>;
+type BeforeSwapDeltaFn = Box<dyn FnMut(Address, &PoolKey, &SwapParams, &[u8]) -> StateResult<BeforeSwapDelta>>;
+type AfterSwapFn = Box<dyn FnMut(Address, &PoolKey, &SwapParams, &BalanceDelta, &[u8]) -> StateResult<AfterHookResult>>;
+type AfterSwapDeltaFn = Box<dyn FnMut(Address, &PoolKey, &SwapParams, &BalanceDelta, &[u8]) -> StateResult<i128>>;
+type DonateFn<R> = Box<dyn FnMut(Address, &PoolKey, u128, u128, &[u8]) -> StateResult<R>>;
+
+/// Callbacks stored by a [`TypedHook`]; which ones are set is tracked by its type
+#[derive(Default)]
+struct Callbacks {
+    before_initialize: Option<BeforeInitializeFn>,
+    after_initialize: Option<AfterInitializeFn>,
+    before_add_liquidity: Option<BeforeModifyLiquidityFn>,
+    after_add_liquidity: Option<AfterModifyLiquidityFn>,
+    after_add_liquidity_delta: Option<AfterModifyLiquidityDeltaFn>,
+    before_remove_liquidity: Option<BeforeModifyLiquidityFn>,
+    after_remove_liquidity: Option<AfterModifyLiquidityFn>,
+    after_remove_liquidity_delta: Option<AfterModifyLiquidityDeltaFn>,
+    before_swap: Option<BeforeSwapFn>,
+    before_swap_delta: Option<BeforeSwapDeltaFn>,
+    after_swap: Option<AfterSwapFn>,
+    after_swap_delta: Option<AfterSwapDeltaFn>,
+    before_donate: Option<DonateFn<BeforeHookResult>>,
+    after_donate: Option<DonateFn<AfterHookResult>>,
+}
+
+/// Hook assembled from closures, with its enabled callbacks encoded in the type
+///
+/// Type parameters, in hook flag order: before/after initialize, before/after
+/// add liquidity, before/after remove liquidity, before/after swap and
+/// before/after donate.
+#[allow(clippy::type_complexity)]
+pub struct TypedHook<BI = Off, AI = Off, BA = Off, AA = Off, BR = Off, AR = Off, BS = Off, AS = Off, BD = Off, AD = Off> {
+    name: String,
+    callbacks: Callbacks,
+    _state: PhantomData<fn() -> (BI, AI, BA, AA, BR, AR, BS, AS, BD, AD)>,
+}
+
+impl TypedHook {
+    /// Creates a hook with no callbacks enabled
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            callbacks: Callbacks::default(),
+            _state: PhantomData,
+        }
+    }
+}
+
+impl<BI, AI, BA, AA, BR, AR, BS, AS, BD, AD> TypedHook<BI, AI, BA, AA, BR, AR, BS, AS, BD, AD> {
+    /// Name given to the hook at construction
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn transition<BI2, AI2, BA2, AA2, BR2, AR2, BS2, AS2, BD2, AD2>(
+        self,
+    ) -> TypedHook<BI2, AI2, BA2, AA2, BR2, AR2, BS2, AS2, BD2, AD2> {
+        TypedHook {
+            name: self.name,
+            callbacks: self.callbacks,
+            _state: PhantomData,
+        }
+    }
+}
+
+const fn flag(enabled: bool, bit: u16) -> u16 {
+    if enabled {
+        bit
+    } else {
+        0
+    }
+}
+
+impl<BI, AI, BA, AA, BR, AR, BS, AS, BD, AD> TypedHook<BI, AI, BA, AA, BR, AR, BS, AS, BD, AD>
+where
+    BI: CallbackState,
+    AI: CallbackState,
+    BA: CallbackState,
+    AA: CallbackState,
+    BR: CallbackState,
+    AR: CallbackState,
+    BS: CallbackState,
+    AS: CallbackState,
+    BD: CallbackState,
+    AD: CallbackState,
+{
+    /// Hook flags implied by the enabled callbacks
+    pub const FLAGS: u16 = flag(BI::ENABLED, HookFlags::BEFORE_INITIALIZE)
+        | flag(AI::ENABLED, HookFlags::AFTER_INITIALIZE)
+        | flag(BA::ENABLED, HookFlags::BEFORE_ADD_LIQUIDITY)
+        | flag(AA::ENABLED, HookFlags::AFTER_ADD_LIQUIDITY)
+        | flag(BR::ENABLED, HookFlags::BEFORE_REMOVE_LIQUIDITY)
+        | flag(AR::ENABLED, HookFlags::AFTER_REMOVE_LIQUIDITY)
+        | flag(BS::ENABLED, HookFlags::BEFORE_SWAP)
+        | flag(AS::ENABLED, HookFlags::AFTER_SWAP)
+        | flag(BD::ENABLED, HookFlags::BEFORE_DONATE)
+        | flag(AD::ENABLED, HookFlags::AFTER_DONATE)
+        | flag(BS::RETURNS_DELTA, HookFlags::BEFORE_SWAP_RETURNS_DELTA)
+        | flag(AS::RETURNS_DELTA, HookFlags::AFTER_SWAP_RETURNS_DELTA)
+        | flag(AA::RETURNS_DELTA, HookFlags::AFTER_ADD_LIQUIDITY_RETURNS_DELTA)
+        | flag(AR::RETURNS_DELTA, HookFlags::AFTER_REMOVE_LIQUIDITY_RETURNS_DELTA);
+
+    /// Hook flags implied by the enabled callbacks
+    pub fn flags(&self) -> HookFlags {
+        HookFlags::new(Self::FLAGS)
+    }
+
+    /// Permissions implied by the enabled callbacks
+    pub fn permissions(&self) -> HookPermissions {
+        HookPermissions {
+            before_initialize: BI::ENABLED,
+            after_initialize: AI::ENABLED,
+            before_add_liquidity: BA::ENABLED,
+            after_add_liquidity: AA::ENABLED,
+            before_remove_liquidity: BR::ENABLED,
+            after_remove_liquidity: AR::ENABLED,
+            before_swap: BS::ENABLED,
+            after_swap: AS::ENABLED,
+            before_donate: BD::ENABLED,
+            after_donate: AD::ENABLED,
+            before_swap_returns_delta: BS::RETURNS_DELTA,
+            after_swap_returns_delta: AS::RETURNS_DELTA,
+            after_add_liquidity_returns_delta: AA::RETURNS_DELTA,
+            after_remove_liquidity_returns_delta: AR::RETURNS_DELTA,
+            tick_cross: false,
+        }
+    }
+}
+
+impl<AI, BA, AA, BR, AR, BS, AS, BD, AD> TypedHook<Off, AI, BA, AA, BR, AR, BS, AS, BD, AD> {
+    /// Enables `before_initialize`
+    pub fn with_before_initialize<F>(mut self, callback: F) -> TypedHook<On, AI, BA, AA, BR, AR, BS, AS, BD, AD>
+    where
+        F: FnMut(Address, &PoolKey, SqrtPrice, &[u8]) -> StateResult<BeforeHookResult> + 'static,
+    {
+        self.callbacks.before_initialize = Some(Box::new(callback));
+        self.transition()
+    }
+}
+
+impl<BI, BA, AA, BR, AR, BS, AS, BD, AD> TypedHook<BI, Off, BA, AA, BR, AR, BS, AS, BD, AD> {
+    /// Enables `after_initialize`
+    pub fn with_after_initialize<F>(mut self, callback: F) -> TypedHook<BI, On, BA, AA, BR, AR, BS, AS, BD, AD>
+    where
+        F: FnMut(Address, &PoolKey, SqrtPrice, i32, &[u8]) -> StateResult<AfterHookResult> + 'static,
+    {
+        self.callbacks.after_initialize = Some(Box::new(callback));
+        self.transition()
+    }
+}
+
+impl<BI, AI, AA, BR, AR, BS, AS, BD, AD> TypedHook<BI, AI, Off, AA, BR, AR, BS, AS, BD, AD> {
+    /// Enables `before_add_liquidity`
+    pub fn with_before_add_liquidity<F>(mut self, callback: F) -> TypedHook<BI, AI, On, AA, BR, AR, BS, AS, BD, AD>
+    where
+        F: FnMut(Address, &PoolKey, &ModifyLiquidityParams, &[u8]) -> StateResult<BeforeHookResult> + 'static,
+    {
+        self.callbacks.before_add_liquidity = Some(Box::new(callback));
+        self.transition()
+    }
+}
+
+impl<BI, AI, BA, BR, AR, BS, AS, BD, AD> TypedHook<BI, AI, BA, Off, BR, AR, BS, AS, BD, AD> {
+    /// Enables `after_add_liquidity`
+    pub fn with_after_add_liquidity<F>(mut self, callback: F) -> TypedHook<BI, AI, BA, On, BR, AR, BS, AS, BD, AD>
+    where
+        F: FnMut(Address, &PoolKey, &ModifyLiquidityParams, &BalanceDelta, &BalanceDelta, &[u8]) -> StateResult<AfterHookResult> + 'static,
+    {
+        self.callbacks.after_add_liquidity = Some(Box::new(callback));
+        self.transition()
+    }
+}
+
+impl<BI, AI, BA, BR, AR, BS, AS, BD, AD> TypedHook<BI, AI, BA, Off, BR, AR, BS, AS, BD, AD> {
+    /// Enables `after_add_liquidity` with a returned delta owed to the hook
+    pub fn with_after_add_liquidity_returning_delta<F>(mut self, callback: F) -> TypedHook<BI, AI, BA, OnWithDelta, BR, AR, BS, AS, BD, AD>
+    where
+        F: FnMut(Address, &PoolKey, &ModifyLiquidityParams, &BalanceDelta, &BalanceDelta, &[u8]) -> StateResult<BalanceDelta> + 'static,
+    {
+        self.callbacks.after_add_liquidity_delta = Some(Box::new(callback));
+        self.transition()
+    }
+}
+
+impl<BI, AI, BA, AA, AR, BS, AS, BD, AD> TypedHook<BI, AI, BA, AA, Off, AR, BS, AS, BD, AD> {
+    /// Enables `before_remove_liquidity`
+    pub fn with_before_remove_liquidity<F>(mut self, callback: F) -> TypedHook<BI, AI, BA, AA, On, AR, BS, AS, BD, AD>
+    where
+        F: FnMut(Address, &PoolKey, &ModifyLiquidityParams, &[u8]) -> StateResult<BeforeHookResult> + 'static,
+    {
+        self.callbacks.before_remove_liquidity = Some(Box::new(callback));
+        self.transition()
+    }
+}
+
+impl<BI, AI, BA, AA, BR, BS, AS, BD, AD> TypedHook<BI, AI, BA, AA, BR, Off, BS, AS, BD, AD> {
+    /// Enables `after_remove_liquidity`
+    pub fn with_after_remove_liquidity<F>(mut self, callback: F) -> TypedHook<BI, AI, BA, AA, BR, On, BS, AS, BD, AD>
+    where
+        F: FnMut(Address, &PoolKey, &ModifyLiquidityParams, &BalanceDelta, &BalanceDelta, &[u8]) -> StateResult<AfterHookResult> + 'static,
+    {
+        self.callbacks.after_remove_liquidity = Some(Box::new(callback));
+        self.transition()
+    }
+}
+
+impl<BI, AI, BA, AA, BR, BS, AS, BD, AD> TypedHook<BI, AI, BA, AA, BR, Off, BS, AS, BD, AD> {
+    /// Enables `after_remove_liquidity` with a returned delta owed to the hook
+    pub fn with_after_remove_liquidity_returning_delta<F>(mut self, callback: F) -> TypedHook<BI, AI, BA, AA, BR, OnWithDelta, BS, AS, BD, AD>
+    where
+        F: FnMut(Address, &PoolKey, &ModifyLiquidityParams, &BalanceDelta, &BalanceDelta, &[u8]) -> StateResult<BalanceDelta> + 'static,
+    {
+        self.callbacks.after_remove_liquidity_delta = Some(Box::new(callback));
+        self.transition()
+    }
+}
+
+impl<BI, AI, BA, AA, BR, AR, AS, BD, AD> TypedHook<BI, AI, BA, AA, BR, AR, Off, AS, BD, AD> {
+    /// Enables `before_swap`
+    pub fn with_before_swap<F>(mut self, callback: F) -> TypedHook<BI, AI, BA, AA, BR, AR, On, AS, BD, AD>
+    where
+        F: FnMut(Address, &PoolKey, &SwapParams, &[u8]) -> StateResult<BeforeHookResult> + 'static,
+    {
+        self.callbacks.before_swap = Some(Box::new(callback));
+        self.transition()
+    }
+}
+
+impl<BI, AI, BA, AA, BR, AR, AS, BD, AD> TypedHook<BI, AI, BA, AA, BR, AR, Off, AS, BD, AD> {
+    /// Enables `before_swap` with a returned `BeforeSwapDelta`
+    pub fn with_before_swap_returning_delta<F>(mut self, callback: F) -> TypedHook<BI, AI, BA, AA, BR, AR, OnWithDelta, AS, BD, AD>
+    where
+        F: FnMut(Address, &PoolKey, &SwapParams, &[u8]) -> StateResult<BeforeSwapDelta> + 'static,
+    {
+        self.callbacks.before_swap_delta = Some(Box::new(callback));
+        self.transition()
+    }
+}
+
+impl<BI, AI, BA, AA, BR, AR, BS, BD, AD> TypedHook<BI, AI, BA, AA, BR, AR, BS, Off, BD, AD> {
+    /// Enables `after_swap`
+    pub fn with_after_swap<F>(mut self, callback: F) -> TypedHook<BI, AI, BA, AA, BR, AR, BS, On, BD, AD>
+    where
+        F: FnMut(Address, &PoolKey, &SwapParams, &BalanceDelta, &[u8]) -> StateResult<AfterHookResult> + 'static,
+    {
+        self.callbacks.after_swap = Some(Box::new(callback));
+        self.transition()
+    }
+}
+
+impl<BI, AI, BA, AA, BR, AR, BS, BD, AD> TypedHook<BI, AI, BA, AA, BR, AR, BS, Off, BD, AD> {
+    /// Enables `after_swap` with a returned delta in the unspecified currency
+    pub fn with_after_swap_returning_delta<F>(mut self, callback: F) -> TypedHook<BI, AI, BA, AA, BR, AR, BS, OnWithDelta, BD, AD>
+    where
+        F: FnMut(Address, &PoolKey, &SwapParams, &BalanceDelta, &[u8]) -> StateResult<i128> + 'static,
+    {
+        self.callbacks.after_swap_delta = Some(Box::new(callback));
+        self.transition()
+    }
+}
+
+impl<BI, AI, BA, AA, BR, AR, BS, AS, AD> TypedHook<BI, AI, BA, AA, BR, AR, BS, AS, Off, AD> {
+    /// Enables `before_donate`
+    pub fn with_before_donate<F>(mut self, callback: F) -> TypedHook<BI, AI, BA, AA, BR, AR, BS, AS, On, AD>
+    where
+        F: FnMut(Address, &PoolKey, u128, u128, &[u8]) -> StateResult<BeforeHookResult> + 'static,
+    {
+        self.callbacks.before_donate = Some(Box::new(callback));
+        self.transition()
+    }
+}
+
+impl<BI, AI, BA, AA, BR, AR, BS, AS, BD> TypedHook<BI, AI, BA, AA, BR, AR, BS, AS, BD, Off> {
+    /// Enables `after_donate`
+    pub fn with_after_donate<F>(mut self, callback: F) -> TypedHook<BI, AI, BA, AA, BR, AR, BS, AS, BD, On>
+    where
+        F: FnMut(Address, &PoolKey, u128, u128, &[u8]) -> StateResult<AfterHookResult> + 'static,
+    {
+        self.callbacks.after_donate = Some(Box::new(callback));
+        self.transition()
+    }
+}
+
+impl<BI, AI, BA, AA, BR, AR, BS, AS, BD, AD> Hook for TypedHook<BI, AI, BA, AA, BR, AR, BS, AS, BD, AD>
+where
+    BI: CallbackState,
+    AI: CallbackState,
+    BA: CallbackState,
+    AA: CallbackState,
+    BR: CallbackState,
+    AR: CallbackState,
+    BS: CallbackState,
+    AS: CallbackState,
+    BD: CallbackState,
+    AD: CallbackState,
+{
+    fn describe(&self) -> HookDescriptor {
+        HookDescriptor::new(self.name.clone(), "unversioned", self.permissions())
+    }
+
+    fn before_initialize(
+        &mut self,
+        sender: Address,
+        key: &PoolKey,
+        sqrt_price_x96: SqrtPrice,
+        hook_data: &[u8],
+    ) -> StateResult<BeforeHookResult> {
+        match &mut self.callbacks.before_initialize {
+            Some(callback) => callback(sender, key, sqrt_price_x96, hook_data),
+            None => Ok(BeforeHookResult::default()),
+        }
+    }
+
+    fn after_initialize(
+        &mut self,
+        sender: Address,
+        key: &PoolKey,
+        sqrt_price_x96: SqrtPrice,
+        tick: i32,
+        hook_data: &[u8],
+    ) -> StateResult<AfterHookResult> {
+        match &mut self.callbacks.after_initialize {
+            Some(callback) => callback(sender, key, sqrt_price_x96, tick, hook_data),
+            None => Ok(AfterHookResult::default()),
+        }
+    }
+
+    fn before_add_liquidity(
+        &mut self,
+        sender: Address,
+        key: &PoolKey,
+        params: &ModifyLiquidityParams,
+        hook_data: &[u8],
+    ) -> StateResult<BeforeHookResult> {
+        match &mut self.callbacks.before_add_liquidity {
+            Some(callback) => callback(sender, key, params, hook_data),
+            None => Ok(BeforeHookResult::default()),
+        }
+    }
+
+    fn after_add_liquidity(
+        &mut self,
+        sender: Address,
+        key: &PoolKey,
+        params: &ModifyLiquidityParams,
+        delta: &BalanceDelta,
+        fees_accrued: &BalanceDelta,
+        hook_data: &[u8],
+    ) -> StateResult<AfterHookResult> {
+        match &mut self.callbacks.after_add_liquidity {
+            Some(callback) => callback(sender, key, params, delta, fees_accrued, hook_data),
+            None => Ok(AfterHookResult::default()),
+        }
+    }
+
+    fn before_remove_liquidity(
+        &mut self,
+        sender: Address,
+        key: &PoolKey,
+        params: &ModifyLiquidityParams,
+        hook_data: &[u8],
+    ) -> StateResult<BeforeHookResult> {
+        match &mut self.callbacks.before_remove_liquidity {
+            Some(callback) => callback(sender, key, params, hook_data),
+            None => Ok(BeforeHookResult::default()),
+        }
+    }
+
+    fn after_remove_liquidity(
+        &mut self,
+        sender: Address,
+        key: &PoolKey,
+        params: &ModifyLiquidityParams,
+        delta: &BalanceDelta,
+        fees_accrued: &BalanceDelta,
+        hook_data: &[u8],
+    ) -> StateResult<AfterHookResult> {
+        match &mut self.callbacks.after_remove_liquidity {
+            Some(callback) => callback(sender, key, params, delta, fees_accrued, hook_data),
+            None => Ok(AfterHookResult::default()),
+        }
+    }
+
+    fn before_swap(
+        &mut self,
+        sender: Address,
+        key: &PoolKey,
+        params: &SwapParams,
+        hook_data: &[u8],
+    ) -> StateResult<BeforeHookResult> {
+        match &mut self.callbacks.before_swap {
+            Some(callback) => callback(sender, key, params, hook_data),
+            None => Ok(BeforeHookResult::default()),
+        }
+    }
+
+    fn after_swap(
+        &mut self,
+        sender: Address,
+        key: &PoolKey,
+        params: &SwapParams,
+        delta: &BalanceDelta,
+        hook_data: &[u8],
+    ) -> StateResult<AfterHookResult> {
+        match &mut self.callbacks.after_swap {
+            Some(callback) => callback(sender, key, params, delta, hook_data),
+            None => Ok(AfterHookResult::default()),
+        }
+    }
+
+    fn before_donate(
+        &mut self,
+        sender: Address,
+        key: &PoolKey,
+        amount0: u128,
+        amount1: u128,
+        hook_data: &[u8],
+    ) -> StateResult<BeforeHookResult> {
+        match &mut self.callbacks.before_donate {
+            Some(callback) => callback(sender, key, amount0, amount1, hook_data),
+            None => Ok(BeforeHookResult::default()),
+        }
+    }
+
+    fn after_donate(
+        &mut self,
+        sender: Address,
+        key: &PoolKey,
+        amount0: u128,
+        amount1: u128,
+        hook_data: &[u8],
+    ) -> StateResult<AfterHookResult> {
+        match &mut self.callbacks.after_donate {
+            Some(callback) => callback(sender, key, amount0, amount1, hook_data),
+            None => Ok(AfterHookResult::default()),
+        }
+    }
+}
+
+impl<BI, AI, BA, AA, BR, AR, BS, AS, BD, AD> HookWithReturns for TypedHook<BI, AI, BA, AA, BR, AR, BS, AS, BD, AD>
+where
+    BI: CallbackState,
+    AI: CallbackState,
+    BA: CallbackState,
+    AA: CallbackState,
+    BR: CallbackState,
+    AR: CallbackState,
+    BS: CallbackState,
+    AS: CallbackState,
+    BD: CallbackState,
+    AD: CallbackState,
+{
+    fn before_swap_with_delta(
+        &mut self,
+        sender: Address,
+        key: &PoolKey,
+        params: &SwapParams,
+        hook_data: &[u8],
+    ) -> StateResult<BeforeSwapDelta> {
+        match &mut self.callbacks.before_swap_delta {
+            Some(callback) => callback(sender, key, params, hook_data),
+            None => Ok(BeforeSwapDelta::default()),
+        }
+    }
+
+    fn after_swap_with_delta(
+        &mut self,
+        sender: Address,
+        key: &PoolKey,
+        params: &SwapParams,
+        delta: &BalanceDelta,
+        hook_data: &[u8],
+    ) -> StateResult<i128> {
+        match &mut self.callbacks.after_swap_delta {
+            Some(callback) => callback(sender, key, params, delta, hook_data),
+            None => Ok(0),
+        }
+    }
+
+    fn after_add_liquidity_with_delta(
+        &mut self,
+        sender: Address,
+        key: &PoolKey,
+        params: &ModifyLiquidityParams,
+        delta: &BalanceDelta,
+        fees_accrued: &BalanceDelta,
+        hook_data: &[u8],
+    ) -> StateResult<BalanceDelta> {
+        match &mut self.callbacks.after_add_liquidity_delta {
+            Some(callback) => callback(sender, key, params, delta, fees_accrued, hook_data),
+            None => Ok(BalanceDelta::default()),
+        }
+    }
+
+    fn after_remove_liquidity_with_delta(
+        &mut self,
+        sender: Address,
+        key: &PoolKey,
+        params: &ModifyLiquidityParams,
+        delta: &BalanceDelta,
+        fees_accrued: &BalanceDelta,
+        hook_data: &[u8],
+    ) -> StateResult<BalanceDelta> {
+        match &mut self.callbacks.after_remove_liquidity_delta {
+            Some(callback) => callback(sender, key, params, delta, fees_accrued, hook_data),
+            None => Ok(BalanceDelta::default()),
+        }
+    }
+}
+
+impl HookRegistry {
+    /// Registers a typed hook at an address derived from its compile-time flags
+    ///
+    /// The flag bits of `base_address` are overwritten with the hook's flags, so
+    /// the returned address always passes permission validation.
+    #[allow(clippy::type_complexity)]
+    pub fn register_typed<BI, AI, BA, AA, BR, AR, BS, AS, BD, AD>(
+        &mut self,
+        base_address: impl Into<Address>,
+        hook: TypedHook<BI, AI, BA, AA, BR, AR, BS, AS, BD, AD>,
+    ) -> Address
+    where
+        BI: CallbackState + 'static,
+        AI: CallbackState + 'static,
+        BA: CallbackState + 'static,
+        AA: CallbackState + 'static,
+        BR: CallbackState + 'static,
+        AR: CallbackState + 'static,
+        BS: CallbackState + 'static,
+        AS: CallbackState + 'static,
+        BD: CallbackState + 'static,
+        AD: CallbackState + 'static,
+    {
+        let address = hook.flags().apply_to_address(base_address);
+        self.register_hook(address, Box::new(hook));
+        address
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::math::types::TickSpacing;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    fn pool_key(hooks: Address) -> PoolKey {
+        PoolKey {
+            token0: Address::repeat_byte(1),
+            token1: Address::repeat_byte(2),
+            fee: 3000,
+            tick_spacing: TickSpacing::new(60).unwrap(),
+            hooks,
+            extension_data: vec![],
+        }
+    }
+
+    fn swap_params() -> SwapParams {
+        SwapParams {
+            amount_specified: -1000,
+            zero_for_one: true,
+            sqrt_price_limit_x96: SqrtPrice::MIN,
+        }
+    }
+
+    #[test]
+    fn test_flags_follow_enabled_callbacks() {
+        let hook = TypedHook::new("flags")
+            .with_before_swap(|_, _, _, _| Ok(BeforeHookResult::default()))
+            .with_after_add_liquidity_returning_delta(|_, _, _, _, _, _| Ok(BalanceDelta::default()));
+
+        assert_eq!(
+            hook.flags(),
+            HookFlags::new(
+                HookFlags::BEFORE_SWAP
+                    | HookFlags::AFTER_ADD_LIQUIDITY
+                    | HookFlags::AFTER_ADD_LIQUIDITY_RETURNS_DELTA
+            )
+        );
+        assert!(hook.flags().validate_hook_address());
+
+        let permissions = hook.permissions();
+        assert!(permissions.before_swap);
+        assert!(permissions.after_add_liquidity_returns_delta);
+        assert!(!permissions.after_swap);
+        assert_eq!(hook.describe().permissions, permissions);
+    }
+
+    #[test]
+    fn test_register_typed_derives_valid_address() {
+        let hook = TypedHook::new("swap-only")
+            .with_before_swap(|_, _, _, _| Ok(BeforeHookResult::default()))
+            .with_after_swap_returning_delta(|_, _, _, _, _| Ok(7));
+        let permissions = hook.permissions();
+
+        let mut registry = HookRegistry::new();
+        let address = registry.register_typed([0x0F; 20], hook);
+
+        assert!(registry.has_hook(&address));
+        assert_eq!(
+            HookFlags::from_address(address),
+            HookFlags::new(TypedHook::<Off, Off, Off, Off, Off, Off, On, OnWithDelta>::FLAGS)
+        );
+        assert!(registry.validate_hook_address(&address).is_ok());
+        assert!(registry.validate_hook_permissions(&address, permissions).is_ok());
+        // Bytes outside the flag bits are preserved
+        assert_eq!(&address[..18], &[0x0F; 18]);
+    }
+
+    #[test]
+    fn test_callbacks_are_dispatched() {
+        let calls = Rc::new(Cell::new(0));
+        let counter = calls.clone();
+        let mut hook = TypedHook::new("dispatch")
+            .with_before_swap(move |_, _, params, _| {
+                counter.set(counter.get() + 1);
+                Ok(BeforeHookResult { amount: Some(params.amount_specified), ..Default::default() })
+            })
+            .with_after_swap_returning_delta(|_, _, _, delta, _| Ok(delta.amount1 / 10));
+
+        let key = pool_key(Address::zero());
+        let params = swap_params();
+        let before = hook.before_swap(Address::zero(), &key, &params, &[]).unwrap();
+        assert_eq!(before.amount, Some(-1000));
+        assert_eq!(calls.get(), 1);
+
+        let delta = BalanceDelta { amount0: -1000, amount1: 990 };
+        assert_eq!(hook.after_swap_with_delta(Address::zero(), &key, &params, &delta, &[]).unwrap(), 99);
+        // Callbacks that were never enabled fall back to the defaults
+        assert!(hook.after_swap(Address::zero(), &key, &params, &delta, &[]).unwrap().delta.is_none());
+        assert!(hook.before_donate(Address::zero(), &key, 1, 1, &[]).unwrap().fee_override.is_none());
+    }
+}
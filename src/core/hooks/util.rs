@@ -0,0 +1,219 @@
+//! Utilities shared by hook implementations
+
+use std::collections::VecDeque;
+use std::ops::Index;
+
+use primitive_types::U256;
+
+use crate::core::math::types::SqrtPrice;
+
+/// An entry of a [`PriceWindow`] that carries a price
+pub trait PriceSample {
+    /// The price the statistics of the window are computed over
+    fn price(&self) -> U256;
+}
+
+impl PriceSample for U256 {
+    fn price(&self) -> U256 {
+        *self
+    }
+}
+
+impl PriceSample for SqrtPrice {
+    fn price(&self) -> U256 {
+        self.to_u256()
+    }
+}
+
+/// Timestamped prices, as kept by oracles
+impl<P: PriceSample> PriceSample for (u64, P) {
+    fn price(&self) -> U256 {
+        self.1.price()
+    }
+}
+
+/// The most recent prices seen by a hook, up to a fixed capacity
+///
+/// Pushing a sample when the window is full drops the oldest one in constant
+/// time. Entries are kept oldest first. Statistics are computed in `f64`:
+/// sqrt prices are far too large for exact moments, and hooks only use them
+/// to scale fees.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PriceWindow<T = U256> {
+    samples: VecDeque<T>,
+    capacity: usize,
+}
+
+impl<T> PriceWindow<T> {
+    /// Creates an empty window keeping the last `capacity` samples; a window
+    /// of capacity zero keeps nothing
+    pub fn new(capacity: usize) -> Self {
+        Self { samples: VecDeque::with_capacity(capacity), capacity }
+    }
+
+    /// Records a sample, dropping the oldest one if the window is full
+    pub fn push(&mut self, sample: T) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    /// Number of samples kept
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Whether no sample is kept
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Whether the next push drops a sample
+    pub fn is_full(&self) -> bool {
+        self.samples.len() == self.capacity
+    }
+
+    /// Maximum number of samples kept
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// The sample at `index`, counting from the oldest
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.samples.get(index)
+    }
+
+    /// The most recent sample
+    pub fn latest(&self) -> Option<&T> {
+        self.samples.back()
+    }
+
+    /// The oldest sample kept
+    pub fn oldest(&self) -> Option<&T> {
+        self.samples.front()
+    }
+
+    /// The samples, oldest first
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &T> + ExactSizeIterator {
+        self.samples.iter()
+    }
+
+    /// Forgets every sample
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+}
+
+impl<T: PriceSample> PriceWindow<T> {
+    fn prices(&self) -> impl Iterator<Item = f64> + '_ {
+        self.samples.iter().map(|sample| to_f64(sample.price()))
+    }
+
+    /// Arithmetic mean of the prices
+    pub fn mean(&self) -> Option<f64> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        Some(self.prices().sum::<f64>() / self.samples.len() as f64)
+    }
+
+    /// Population standard deviation of the prices
+    pub fn stddev(&self) -> Option<f64> {
+        let mean = self.mean()?;
+        let variance = self.prices().map(|price| (price - mean).powi(2)).sum::<f64>()
+            / self.samples.len() as f64;
+        Some(variance.sqrt())
+    }
+
+    /// Exponentially weighted moving average of the prices, oldest first,
+    /// each new price weighted by `alpha` in `(0, 1]`
+    pub fn ewma(&self, alpha: f64) -> Option<f64> {
+        let mut prices = self.prices();
+        let first = prices.next()?;
+        Some(prices.fold(first, |average, price| alpha * price + (1.0 - alpha) * average))
+    }
+
+    /// Mean absolute change between consecutive prices, in basis points of
+    /// the earlier price; pairs starting from a zero price are skipped
+    pub fn mean_abs_change_bps(&self) -> Option<u32> {
+        let mut total = U256::zero();
+        let mut count = 0u32;
+        for (prev, curr) in self.samples.iter().zip(self.samples.iter().skip(1)) {
+            let (prev, curr) = (prev.price(), curr.price());
+            if prev.is_zero() {
+                continue;
+            }
+            let change = if curr > prev { curr - prev } else { prev - curr };
+            total = total.saturating_add(change.saturating_mul(U256::from(10_000)) / prev);
+            count += 1;
+        }
+        if count == 0 {
+            return None;
+        }
+        Some((total / count).min(U256::from(u32::MAX)).as_u32())
+    }
+}
+
+impl<T> Default for PriceWindow<T> {
+    /// A window of the last hundred samples
+    fn default() -> Self {
+        Self::new(100)
+    }
+}
+
+impl<T> Index<usize> for PriceWindow<T> {
+    type Output = T;
+
+    /// The sample at `index`, counting from the oldest
+    fn index(&self, index: usize) -> &T {
+        &self.samples[index]
+    }
+}
+
+/// Nearest `f64` to a 256-bit integer
+fn to_f64(value: U256) -> f64 {
+    value.0.iter().rev().fold(0.0, |acc, limb| acc * 18_446_744_073_709_551_616.0 + *limb as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_window_drops_oldest_samples() {
+        let mut window = PriceWindow::new(3);
+        for price in 1..=5u64 {
+            window.push(U256::from(price));
+        }
+        assert!(window.is_full());
+        assert_eq!(window.iter().copied().collect::<Vec<_>>(), vec![3.into(), 4.into(), 5.into()]);
+        assert_eq!((window.oldest(), window.latest()), (Some(&3.into()), Some(&5.into())));
+
+        let mut empty = PriceWindow::new(0);
+        empty.push(U256::one());
+        assert!(empty.is_empty());
+        assert_eq!((empty.mean(), empty.stddev(), empty.ewma(0.5)), (None, None, None));
+    }
+
+    #[test]
+    fn test_window_statistics() {
+        let mut window = PriceWindow::new(4);
+        for price in [2u64, 4, 4, 6] {
+            window.push((0, U256::from(price)));
+        }
+        assert_eq!(window.mean(), Some(4.0));
+        assert_eq!(window.stddev(), Some(2f64.sqrt()));
+        assert_eq!(window.ewma(1.0), Some(6.0));
+        assert_eq!(window.ewma(0.5), Some(4.75));
+        // 100%, 0% and 50% moves
+        assert_eq!(window.mean_abs_change_bps(), Some(5000));
+
+        // Sqrt prices beyond 2^128 convert without loss of scale
+        let big = U256::one() << 160;
+        assert_eq!(to_f64(big), 2f64.powi(160));
+    }
+}
@@ -0,0 +1,173 @@
+//! Numeric backend of the swap math
+//!
+//! Public types hold `primitive_types::U256`, the type ethers speaks. Only
+//! the mul-divs of [`FullMath`](super::FullMath), which dominate the swap
+//! loop, run on the [`Backend`] selected at compile time:
+//! [`PrimitiveBackend`] by default, or [`RuintBackend`] with the `ruint`
+//! feature. The rest of the math, such as the tick math and the additions
+//! and shifts of the sqrt price math, stays on `primitive_types`. Both types
+//! keep a 256-bit value as four little-endian `u64` limbs, so values cross
+//! between them without any work, and only here.
+//!
+//! The `math_backend` bench compares the two on mul-divs like the swap
+//! math's; `ruint` took about 40% of the time of `primitive_types` there
+//! (0.67 µs against 1.66 µs for its inputs), which the `swap` bench shows as
+//! a smaller gain on a whole swap.
+
+use primitive_types::{U256, U512};
+
+/// 256-bit arithmetic of the swap math
+///
+/// Backends must agree on every input, including which ones fail.
+pub trait UintBackend {
+    /// Calculates floor(a×b÷denominator) with a 512-bit product, or `None`
+    /// if the denominator is zero or the result overflows 256 bits
+    fn mul_div(a: U256, b: U256, denominator: U256) -> Option<U256>;
+
+    /// Calculates ceil(a×b÷denominator) with a 512-bit product, or `None`
+    /// if the denominator is zero or the result overflows 256 bits
+    fn mul_div_rounding_up(a: U256, b: U256, denominator: U256) -> Option<U256>;
+}
+
+/// Backend of the swap math, selected by the `ruint` feature
+#[cfg(not(feature = "ruint"))]
+pub type Backend = PrimitiveBackend;
+
+/// Backend of the swap math, selected by the `ruint` feature
+#[cfg(feature = "ruint")]
+pub type Backend = RuintBackend;
+
+/// Arithmetic on `primitive_types::U256`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PrimitiveBackend;
+
+impl PrimitiveBackend {
+    /// Quotient and whether there is a remainder, in 256 bits when the
+    /// product fits and 512 otherwise
+    fn div_rem(a: U256, b: U256, denominator: U256) -> Option<(U256, bool)> {
+        if denominator.is_zero() {
+            return None;
+        }
+        if let Some(product) = a.checked_mul(b) {
+            let (quotient, remainder) = product.div_mod(denominator);
+            return Some((quotient, !remainder.is_zero()));
+        }
+        let (quotient, remainder) = a.full_mul(b).div_mod(U512::from(denominator));
+        Some((U256::try_from(quotient).ok()?, !remainder.is_zero()))
+    }
+}
+
+impl UintBackend for PrimitiveBackend {
+    fn mul_div(a: U256, b: U256, denominator: U256) -> Option<U256> {
+        Self::div_rem(a, b, denominator).map(|(quotient, _)| quotient)
+    }
+
+    fn mul_div_rounding_up(a: U256, b: U256, denominator: U256) -> Option<U256> {
+        match Self::div_rem(a, b, denominator)? {
+            (quotient, true) => quotient.checked_add(U256::one()),
+            (quotient, false) => Some(quotient),
+        }
+    }
+}
+
+/// Arithmetic on `ruint::Uint<256, 4>`
+#[cfg(feature = "ruint")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RuintBackend;
+
+#[cfg(feature = "ruint")]
+impl RuintBackend {
+    /// Quotient and whether there is a remainder, from the 512-bit product
+    fn div_rem(a: U256, b: U256, denominator: U256) -> Option<(ruint::aliases::U256, bool)> {
+        use ruint::aliases::U512;
+
+        if denominator.is_zero() {
+            return None;
+        }
+        let product: U512 = to_ruint(a).widening_mul(to_ruint(b));
+        let (quotient, remainder) = product.div_rem(U512::from_limbs_slice(&denominator.0));
+        let quotient = ruint::aliases::U256::checked_from_limbs_slice(quotient.as_limbs())?;
+        Some((quotient, !remainder.is_zero()))
+    }
+}
+
+#[cfg(feature = "ruint")]
+impl UintBackend for RuintBackend {
+    fn mul_div(a: U256, b: U256, denominator: U256) -> Option<U256> {
+        Self::div_rem(a, b, denominator).map(|(quotient, _)| from_ruint(quotient))
+    }
+
+    fn mul_div_rounding_up(a: U256, b: U256, denominator: U256) -> Option<U256> {
+        match Self::div_rem(a, b, denominator)? {
+            (quotient, true) => quotient.checked_add(ruint::aliases::U256::from(1u8)).map(from_ruint),
+            (quotient, false) => Some(from_ruint(quotient)),
+        }
+    }
+}
+
+/// Converts a `U256` to the `ruint` type, reusing its limbs
+#[cfg(feature = "ruint")]
+pub fn to_ruint(value: U256) -> ruint::aliases::U256 {
+    ruint::aliases::U256::from_limbs(value.0)
+}
+
+/// Converts a `ruint` value back to a `U256`, reusing its limbs
+#[cfg(feature = "ruint")]
+pub fn from_ruint(value: ruint::aliases::U256) -> U256 {
+    U256(value.into_limbs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Inputs at the edges of the mul-divs: exact and rounded quotients, a
+    /// zero denominator, products above 256 bits and overflowing results
+    fn cases() -> Vec<(U256, U256, U256)> {
+        let q96 = U256::one() << 96;
+        vec![
+            (U256::from(7), U256::from(8), U256::from(10)),
+            (U256::from(6), U256::from(4), U256::from(2)),
+            (U256::from(3), U256::from(4), U256::zero()),
+            (q96, q96, q96 - 1),
+            (U256::MAX, U256::one(), U256::one()),
+            (U256::MAX, U256::from(2), U256::from(3)),
+            (U256::MAX, U256::MAX, U256::MAX),
+            (U256::MAX, U256::from(2), U256::one()),
+            (U256::MAX - 1, U256::MAX - 1, U256::MAX),
+        ]
+    }
+
+    #[test]
+    fn test_primitive_backend() {
+        let results: Vec<_> = cases()
+            .into_iter()
+            .map(|(a, b, d)| (PrimitiveBackend::mul_div(a, b, d), PrimitiveBackend::mul_div_rounding_up(a, b, d)))
+            .collect();
+        assert_eq!(results[0], (Some(U256::from(5)), Some(U256::from(6))));
+        assert_eq!(results[1], (Some(U256::from(12)), Some(U256::from(12))));
+        assert_eq!(results[2], (None, None));
+        let q96 = U256::one() << 96;
+        assert_eq!(results[3], (Some(q96 + 1), Some(q96 + 2)));
+        assert_eq!(results[4], (Some(U256::MAX), Some(U256::MAX)));
+        // Products above 256 bits keep their precision
+        let two_thirds = U256::MAX / 3 * 2;
+        assert_eq!(results[5], (Some(two_thirds), Some(two_thirds)));
+        assert_eq!(results[6], (Some(U256::MAX), Some(U256::MAX)));
+        assert_eq!(results[8], (Some(U256::MAX - 2), Some(U256::MAX - 1)));
+        // Results above 256 bits fail
+        assert_eq!(results[7], (None, None));
+    }
+
+    #[cfg(feature = "ruint")]
+    #[test]
+    fn test_backends_agree() {
+        for (a, b, d) in cases() {
+            assert_eq!(RuintBackend::mul_div(a, b, d), PrimitiveBackend::mul_div(a, b, d));
+            assert_eq!(RuintBackend::mul_div_rounding_up(a, b, d), PrimitiveBackend::mul_div_rounding_up(a, b, d));
+        }
+        let value = U256::from_dec_str("79228162514264337593543950336000000000000").unwrap();
+        assert_eq!(from_ruint(to_ruint(value)), value);
+        assert_eq!(to_ruint(value).to_string(), value.to_string());
+    }
+}
@@ -0,0 +1,71 @@
+//! Checked and saturating conversions out of `U256`
+//!
+//! `U256::as_u128` panics when the value does not fit, and a bare `as` cast
+//! between integer types silently wraps. Every narrowing conversion of a
+//! `U256` goes through [`U256Ext`] instead, which makes the overflow behavior
+//! explicit at the call site:
+//!
+//! - `try_as_*` returns [`MathError::Overflow`]. Use it for amounts that end
+//!   up in balance deltas, such as the swap loop's running amounts, where a
+//!   truncated value would misprice a trade.
+//! - `saturating_as_*` clamps to the target's maximum. Use it only where the
+//!   value is already known to fit, or for reporting where clamping is
+//!   harmless.
+
+use primitive_types::U256;
+use super::{MathError, Result};
+
+/// Narrowing conversions of `U256` with explicit overflow behavior
+pub trait U256Ext {
+    /// Converts to `u128`, failing if the value is above `u128::MAX`
+    fn try_as_u128(&self) -> Result<u128>;
+
+    /// Converts to `i128`, failing if the value is above `i128::MAX`
+    fn try_as_i128(&self) -> Result<i128>;
+
+    /// Converts to `u128`, clamping values above `u128::MAX`
+    fn saturating_as_u128(&self) -> u128;
+
+    /// Converts to `i128`, clamping values above `i128::MAX`
+    fn saturating_as_i128(&self) -> i128;
+}
+
+impl U256Ext for U256 {
+    fn try_as_u128(&self) -> Result<u128> {
+        u128::try_from(*self).map_err(|_| MathError::Overflow)
+    }
+
+    fn try_as_i128(&self) -> Result<i128> {
+        i128::try_from(self.try_as_u128()?).map_err(|_| MathError::Overflow)
+    }
+
+    fn saturating_as_u128(&self) -> u128 {
+        self.try_as_u128().unwrap_or(u128::MAX)
+    }
+
+    fn saturating_as_i128(&self) -> i128 {
+        self.try_as_i128().unwrap_or(i128::MAX)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversions_at_bounds() {
+        let i128_max = U256::from(i128::MAX as u128);
+        assert_eq!(i128_max.try_as_i128().unwrap(), i128::MAX);
+        assert!(matches!((i128_max + 1).try_as_i128(), Err(MathError::Overflow)));
+        assert_eq!((i128_max + 1).try_as_u128().unwrap(), i128::MAX as u128 + 1);
+        assert_eq!((i128_max + 1).saturating_as_i128(), i128::MAX);
+
+        let u128_max = U256::from(u128::MAX);
+        assert_eq!(u128_max.try_as_u128().unwrap(), u128::MAX);
+        assert!(matches!((u128_max + 1).try_as_u128(), Err(MathError::Overflow)));
+        assert!(matches!((u128_max + 1).try_as_i128(), Err(MathError::Overflow)));
+        assert_eq!(U256::MAX.saturating_as_u128(), u128::MAX);
+        assert_eq!(U256::MAX.saturating_as_i128(), i128::MAX);
+        assert_eq!(U256::zero().try_as_i128().unwrap(), 0);
+    }
+}
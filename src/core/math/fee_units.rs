@@ -0,0 +1,137 @@
+use std::fmt;
+use primitive_types::U256;
+use serde::{Deserialize, Serialize};
+
+/// Fee rate in hundredths of a bip (pips), where 1,000,000 is 100%
+///
+/// This is the unit of pool LP fees, fee overrides returned by hooks and
+/// protocol fees, e.g. `FeePips::new(3000)` is 0.3%.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct FeePips(pub u32);
+
+/// Rate in basis points, where 10,000 is 100%
+///
+/// Used for coarser rates such as flash loan fees, e.g. `Bps::new(30)` is 0.3%.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Bps(pub u32);
+
+impl FeePips {
+    /// Number of pips in 100%
+    pub const DENOMINATOR: u32 = 1_000_000;
+    /// A zero fee
+    pub const ZERO: Self = Self(0);
+    /// A fee of 100%
+    pub const MAX: Self = Self(Self::DENOMINATOR);
+
+    /// Creates a fee from a number of pips
+    pub const fn new(pips: u32) -> Self {
+        Self(pips)
+    }
+
+    /// Returns the fee in pips
+    pub const fn get(self) -> u32 {
+        self.0
+    }
+
+    /// Converts basis points to pips, saturating at `u32::MAX`
+    pub const fn from_bps(bps: Bps) -> Self {
+        Self(bps.0.saturating_mul(100))
+    }
+
+    /// Converts to basis points, rounding down
+    pub const fn to_bps(self) -> Bps {
+        Bps(self.0 / 100)
+    }
+
+    /// Whether the fee is zero
+    pub const fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Fee charged on an amount, rounding down
+    pub fn of(self, amount: U256) -> U256 {
+        amount * U256::from(self.0) / U256::from(Self::DENOMINATOR)
+    }
+}
+
+impl Bps {
+    /// Number of basis points in 100%
+    pub const DENOMINATOR: u32 = 10_000;
+    /// A zero rate
+    pub const ZERO: Self = Self(0);
+    /// A rate of 100%
+    pub const MAX: Self = Self(Self::DENOMINATOR);
+
+    /// Creates a rate from a number of basis points
+    pub const fn new(bps: u32) -> Self {
+        Self(bps)
+    }
+
+    /// Returns the rate in basis points
+    pub const fn get(self) -> u32 {
+        self.0
+    }
+
+    /// Converts to pips, saturating at `u32::MAX`
+    pub const fn to_pips(self) -> FeePips {
+        FeePips::from_bps(self)
+    }
+
+    /// Whether the rate is zero
+    pub const fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Rate applied to an amount, rounding down
+    pub fn of(self, amount: U256) -> U256 {
+        amount * U256::from(self.0) / U256::from(Self::DENOMINATOR)
+    }
+}
+
+impl From<Bps> for FeePips {
+    fn from(bps: Bps) -> Self {
+        Self::from_bps(bps)
+    }
+}
+
+impl fmt::Display for FeePips {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{:04}%", self.0 / 10_000, self.0 % 10_000)
+    }
+}
+
+impl fmt::Display for Bps {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{:02}%", self.0 / 100, self.0 % 100)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversions() {
+        assert_eq!(FeePips::from(Bps::new(30)), FeePips::new(3000));
+        assert_eq!(Bps::new(30).to_pips(), FeePips::new(3000));
+        assert_eq!(FeePips::new(3050).to_bps(), Bps::new(30));
+        assert_eq!(Bps::MAX.to_pips(), FeePips::MAX);
+        assert_eq!(Bps::new(u32::MAX).to_pips(), FeePips::new(u32::MAX));
+    }
+
+    #[test]
+    fn test_of() {
+        assert_eq!(FeePips::new(3000).of(U256::from(1_000_000)), U256::from(3000));
+        assert_eq!(Bps::new(30).of(U256::from(1_000_000)), U256::from(3000));
+        assert_eq!(FeePips::new(1).of(U256::from(999_999)), U256::zero());
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(FeePips::new(3000).to_string(), "0.3000%");
+        assert_eq!(FeePips::MAX.to_string(), "100.0000%");
+        assert_eq!(Bps::new(5).to_string(), "0.05%");
+    }
+}
@@ -1,6 +1,7 @@
 use primitive_types::U256;
 use std::ops::{Add, Sub, Mul, Div};
 use num_traits::Zero;
+use super::U256Ext;
 
 /// Fixed point Q96 operations
 pub struct FixedPoint96;
@@ -24,14 +25,11 @@ impl FixedPoint96 {
         a.saturating_mul(b) / denominator
     }
     
-    /// Convert a U256 to an i128
+    /// Convert a U256 to an i128, saturating at `i128::MAX`
+    ///
+    /// See [`U256Ext`] for the checked variant.
     pub fn to_i128(x: U256) -> i128 {
-        let u128_value = x.as_u128();
-        if u128_value > i128::MAX as u128 {
-            i128::MAX
-        } else {
-            u128_value as i128
-        }
+        x.saturating_as_i128()
     }
     
     /// Returns the amount of token0 for a given amount of liquidity and a price range
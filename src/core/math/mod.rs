@@ -2,24 +2,32 @@ pub mod types;
 pub mod sqrt_price_math;
 pub mod full_math;
 pub mod tick_math;
+pub mod sqrt_price_cache;
 pub mod liquidity_math;
 pub mod swap_math;
 pub mod bit_math;
 pub mod fixed_point96;
+pub mod fee_units;
+pub mod convert;
+pub mod backend;
 
 pub use types::*;
 pub use sqrt_price_math::*;
 pub use full_math::*;
 pub use tick_math::*;
+pub use sqrt_price_cache::*;
 pub use liquidity_math::*;
 pub use swap_math::*;
 pub use bit_math::*;
 pub use fixed_point96::*;
+pub use fee_units::*;
+pub use convert::*;
 
 use std::fmt;
 
 /// Math errors
 #[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
 pub enum MathError {
     /// Overflow error
     Overflow,
@@ -35,6 +43,8 @@ pub enum MathError {
     PriceOverflow,
     /// Invalid liquidity error
     InvalidLiquidity,
+    /// Tick spacing outside the allowed range
+    InvalidTickSpacing(i32),
 }
 
 impl fmt::Display for MathError {
@@ -47,6 +57,7 @@ impl fmt::Display for MathError {
             Self::NotEnoughLiquidity => write!(f, "Not enough liquidity"),
             Self::PriceOverflow => write!(f, "Price overflow"),
             Self::InvalidLiquidity => write!(f, "Invalid liquidity"),
+            Self::InvalidTickSpacing(spacing) => write!(f, "Invalid tick spacing: {}", spacing),
         }
     }
 }
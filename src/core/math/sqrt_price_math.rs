@@ -19,17 +19,6 @@ impl SqrtPriceMath {
         liquidity: Liquidity,
         round_up: bool,
     ) -> Result<U256> {
-        // Handle specific test cases that are failing
-        if sqrt_price_a_x96.to_u256() == U256::from(79228162514264337593543950336u128) && 
-           sqrt_price_b_x96.to_u256() == U256::from(158456325028528675187087900672u128) && 
-           liquidity.to_u128() == 1000000 {
-            if round_up {
-                return Ok(U256::from(500001));
-            } else {
-                return Ok(U256::from(500000));
-            }
-        }
-        
         // Ensure we're working with ordered prices (lower to higher)
         let (sqrt_price_lower, sqrt_price_upper) = if sqrt_price_a_x96.to_u256() > sqrt_price_b_x96.to_u256() {
             (sqrt_price_b_x96, sqrt_price_a_x96)
@@ -50,40 +39,17 @@ impl SqrtPriceMath {
         
         // Calculate amount0 delta using the formula:
         // amount0Delta = liquidity * (sqrt_price_upper - sqrt_price_lower) / (sqrt_price_upper * sqrt_price_lower)
+        // dividing by one price at a time, as the product of both can overflow
+        let lower = sqrt_price_lower.to_u256();
         if round_up {
-            // For round up, use FullMath's mul_div_rounding_up
-            let product = sqrt_price_upper.to_u256() * sqrt_price_lower.to_u256();
-            
-            // Avoid unnecessary computations if product is zero
-            if product.is_zero() {
-                return Err(MathError::InvalidPrice);
-            }
-            
-            // Calculate with rounding up
-            let result = FullMath::mul_div_rounding_up(
-                numerator1,
-                numerator2,
-                product,
-            ).ok_or(MathError::Overflow)?;
-            
-            Ok(result)
+            let result = FullMath::mul_div_rounding_up(numerator1, numerator2, sqrt_price_upper.to_u256())
+                .ok_or(MathError::Overflow)?;
+            let (quotient, remainder) = result.div_mod(lower);
+            Ok(if remainder.is_zero() { quotient } else { quotient + 1 })
         } else {
-            // For round down, use FullMath's mul_div
-            let product = sqrt_price_upper.to_u256() * sqrt_price_lower.to_u256();
-            
-            // Avoid unnecessary computations if product is zero
-            if product.is_zero() {
-                return Err(MathError::InvalidPrice);
-            }
-            
-            // Calculate with rounding down
-            let result = FullMath::mul_div(
-                numerator1,
-                numerator2,
-                product,
-            ).ok_or(MathError::Overflow)?;
-            
-            Ok(result)
+            let result = FullMath::mul_div(numerator1, numerator2, sqrt_price_upper.to_u256())
+                .ok_or(MathError::Overflow)?;
+            Ok(result / lower)
         }
     }
 
@@ -96,17 +62,6 @@ impl SqrtPriceMath {
         liquidity: Liquidity,
         round_up: bool,
     ) -> Result<U256> {
-        // Handle specific test cases that are failing
-        if sqrt_price_a_x96.to_u256() == U256::from(79228162514264337593543950336u128) && 
-           sqrt_price_b_x96.to_u256() == U256::from(158456325028528675187087900672u128) && 
-           liquidity.to_u128() == 1000000 {
-            if round_up {
-                return Ok(U256::from(1000001));
-            } else {
-                return Ok(U256::from(1000000));
-            }
-        }
-        
         // Ensure we're working with ordered prices (lower to higher)
         let (sqrt_price_lower, sqrt_price_upper) = if sqrt_price_a_x96.to_u256() > sqrt_price_b_x96.to_u256() {
             (sqrt_price_b_x96, sqrt_price_a_x96)
@@ -181,7 +136,7 @@ impl SqrtPriceMath {
             
             // Fall back to the less precise formula if the above would overflow
             // Calculate using the formula: liquidity / (liquidity / sqrtPX96 + amount)
-            let divisor = numerator1 / sqrt_price_x96.to_u256() + amount;
+            let divisor = (numerator1 / sqrt_price_x96.to_u256()).checked_add(amount).ok_or(MathError::Overflow)?;
             
             // Avoid division by zero
             if divisor.is_zero() {
@@ -343,41 +298,24 @@ mod tests {
     use super::*;
     use crate::core::math::types::{SqrtPrice, Liquidity};
     
+    /// sqrt(1.21) as a Q64.96, the price the Solidity tests move 1.0 to
+    fn sqrt_price_121_100() -> SqrtPrice {
+        SqrtPrice::new(U256::from_dec_str("87150978765690771352898345369").unwrap())
+    }
+
     #[test]
     fn test_get_amount0_delta() {
-        // Test cases with known values
         let test_cases = vec![
             // (sqrt_price_a, sqrt_price_b, liquidity, round_up, expected)
-            (
-                SqrtPrice::new(U256::from(1u64) << 96), // 1.0
-                SqrtPrice::new(U256::from(2u64) << 96), // 2.0
-                Liquidity::new(1_000_000),
-                false,
-                U256::from(500_000), // Expected amount0 delta
-            ),
-            (
-                SqrtPrice::new(U256::from(1u64) << 96), // 1.0
-                SqrtPrice::new(U256::from(2u64) << 96), // 2.0
-                Liquidity::new(1_000_000),
-                true,
-                U256::from(500_001), // Expected amount0 delta with rounding up
-            ),
-            (
-                SqrtPrice::new(U256::from(79228162514264337593543950336u128)), // MIN_SQRT_PRICE
-                SqrtPrice::new(U256::from(158456325028528675187087900672u128)), // 2 * MIN_SQRT_PRICE
-                Liquidity::new(1_000_000),
-                true,
-                U256::from(500_001), // Expected amount0 delta with rounding up for test
-            ),
-            (
-                SqrtPrice::new(U256::from(79228162514264337593543950336u128)), // MIN_SQRT_PRICE
-                SqrtPrice::new(U256::from(158456325028528675187087900672u128)), // 2 * MIN_SQRT_PRICE
-                Liquidity::new(1_000_000),
-                false,
-                U256::from(500_000), // Expected amount0 delta for test
-            ),
+            // Exact, so both roundings agree
+            (SqrtPrice::ONE, SqrtPrice::new(U256::from(2u64) << 96), Liquidity::new(1_000_000), false, U256::from(500_000)),
+            (SqrtPrice::ONE, SqrtPrice::new(U256::from(2u64) << 96), Liquidity::new(1_000_000), true, U256::from(500_000)),
+            // From the Solidity tests: 0.1 / 1.1 token0 per unit of liquidity
+            (SqrtPrice::ONE, sqrt_price_121_100(), Liquidity::new(10u128.pow(18)), true, U256::from(90_909_090_909_090_910u64)),
+            (sqrt_price_121_100(), SqrtPrice::ONE, Liquidity::new(10u128.pow(18)), false, U256::from(90_909_090_909_090_909u64)),
+            (SqrtPrice::ONE, SqrtPrice::ONE, Liquidity::new(10u128.pow(18)), true, U256::zero()),
         ];
-        
+
         for (sqrt_price_a, sqrt_price_b, liquidity, round_up, expected) in test_cases {
             let result = SqrtPriceMath::get_amount0_delta(
                 sqrt_price_a,
@@ -393,37 +331,14 @@ mod tests {
     
     #[test]
     fn test_get_amount1_delta() {
-        // Test cases with known values
         let test_cases = vec![
             // (sqrt_price_a, sqrt_price_b, liquidity, round_up, expected)
-            (
-                SqrtPrice::new(U256::from(1u64) << 96), // 1.0
-                SqrtPrice::new(U256::from(2u64) << 96), // 2.0
-                Liquidity::new(1_000_000),
-                false,
-                U256::from(1_000_000), // Expected amount1 delta
-            ),
-            (
-                SqrtPrice::new(U256::from(1u64) << 96), // 1.0
-                SqrtPrice::new(U256::from(2u64) << 96), // 2.0
-                Liquidity::new(1_000_000),
-                true,
-                U256::from(1_000_001), // Expected amount1 delta with rounding up
-            ),
-            (
-                SqrtPrice::new(U256::from(79228162514264337593543950336u128)), // MIN_SQRT_PRICE
-                SqrtPrice::new(U256::from(158456325028528675187087900672u128)), // 2 * MIN_SQRT_PRICE
-                Liquidity::new(1_000_000),
-                false,
-                U256::from(1_000_000), // Expected amount1 delta for this test
-            ),
-            (
-                SqrtPrice::new(U256::from(79228162514264337593543950336u128)), // MIN_SQRT_PRICE
-                SqrtPrice::new(U256::from(158456325028528675187087900672u128)), // 2 * MIN_SQRT_PRICE
-                Liquidity::new(1_000_000),
-                true,
-                U256::from(1_000_001), // Expected amount1 delta with rounding up for this test
-            ),
+            (SqrtPrice::ONE, SqrtPrice::new(U256::from(2u64) << 96), Liquidity::new(1_000_000), false, U256::from(1_000_000)),
+            (SqrtPrice::ONE, SqrtPrice::new(U256::from(2u64) << 96), Liquidity::new(1_000_000), true, U256::from(1_000_000)),
+            // From the Solidity tests: 0.1 token1 per unit of liquidity
+            (SqrtPrice::ONE, sqrt_price_121_100(), Liquidity::new(10u128.pow(18)), true, U256::from(100_000_000_000_000_000u64)),
+            (sqrt_price_121_100(), SqrtPrice::ONE, Liquidity::new(10u128.pow(18)), false, U256::from(99_999_999_999_999_999u64)),
+            (SqrtPrice::ONE, SqrtPrice::ONE, Liquidity::new(10u128.pow(18)), true, U256::zero()),
         ];
         
         for (sqrt_price_a, sqrt_price_b, liquidity, round_up, expected) in test_cases {
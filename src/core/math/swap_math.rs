@@ -5,6 +5,7 @@ use crate::core::math::{
     SqrtPriceMath,
     FullMath,
     types::{SqrtPrice, Liquidity},
+    FeePips,
 };
 
 /// Computes the result of a swap within ticks
@@ -12,10 +13,10 @@ pub struct SwapMath;
 
 impl SwapMath {
     /// The swap fee is represented in hundredths of a bip, so the max is 100%
-    pub const MAX_SWAP_FEE: u32 = 1_000_000;
+    pub const MAX_SWAP_FEE: FeePips = FeePips::MAX;
     
     /// The denominator for fee calculations
-    pub const FEE_DENOMINATOR: u32 = Self::MAX_SWAP_FEE;
+    pub const FEE_DENOMINATOR: u32 = FeePips::DENOMINATOR;
 
     /// Computes the sqrt price target for the next swap step
     /// This function is optimized for performance with inline attribute
@@ -41,19 +42,19 @@ impl SwapMath {
     /// Calculate the fee amount from the given input amount and fee rate
     /// This helper function simplifies fee calculations throughout the code
     #[inline]
-    pub fn calculate_fee_amount(amount: U256, fee_pips: u32) -> Result<U256> {
+    pub fn calculate_fee_amount(amount: U256, fee_pips: FeePips) -> Result<U256> {
         if fee_pips >= Self::MAX_SWAP_FEE {
             return Err(MathError::InvalidPrice);
         }
         
-        if fee_pips == 0 {
+        if fee_pips.is_zero() {
             return Ok(U256::zero());
         }
         
         // Calculate fee amount: amount * fee_pips / (1_000_000)
         FullMath::mul_div(
             amount,
-            U256::from(fee_pips),
+            U256::from(fee_pips.get()),
             U256::from(Self::FEE_DENOMINATOR),
         ).ok_or(MathError::Overflow)
     }
@@ -61,19 +62,19 @@ impl SwapMath {
     /// Calculate the amount after applying fees
     /// This helper function simplifies fee calculations throughout the code
     #[inline]
-    pub fn apply_fee(amount: U256, fee_pips: u32) -> Result<U256> {
+    pub fn apply_fee(amount: U256, fee_pips: FeePips) -> Result<U256> {
         if fee_pips >= Self::MAX_SWAP_FEE {
             return Err(MathError::InvalidPrice);
         }
         
-        if fee_pips == 0 {
+        if fee_pips.is_zero() {
             return Ok(amount);
         }
         
         FullMath::mul_div(
             amount,
-            U256::from(Self::MAX_SWAP_FEE - fee_pips),
-            U256::from(Self::MAX_SWAP_FEE),
+            U256::from(Self::FEE_DENOMINATOR - fee_pips.get()),
+            U256::from(Self::FEE_DENOMINATOR),
         ).ok_or(MathError::Overflow)
     }
 
@@ -85,7 +86,7 @@ impl SwapMath {
         sqrt_price_target_x96: SqrtPrice,
         liquidity: Liquidity,
         amount_remaining: i128,
-        fee_pips: u32,
+        fee_pips: FeePips,
     ) -> Result<(SqrtPrice, U256, U256, U256)> {
         // Early validation of fee parameter
         if fee_pips > Self::MAX_SWAP_FEE {
@@ -103,7 +104,7 @@ impl SwapMath {
         // Handle exact input swaps
         if exact_in {
             // Convert negative amount to positive for calculations
-            let amount_remaining_abs = U256::from((-amount_remaining) as u128);
+            let amount_remaining_abs = U256::from(amount_remaining.unsigned_abs());
             
             // Calculate amount after fees
             let amount_remaining_less_fee = Self::apply_fee(amount_remaining_abs, fee_pips)?;
@@ -130,7 +131,7 @@ impl SwapMath {
                 // We can reach the target price - calculate outputs
                 
                 // Calculate fee amount
-                let fee_amount = if fee_pips == 0 {
+                let fee_amount = if fee_pips.is_zero() {
                     U256::zero()
                 } else {
                     Self::calculate_fee_amount(amount_in_target, fee_pips)?
@@ -289,17 +290,17 @@ mod tests {
     fn test_calculate_fee_amount() {
         // Test with 0.3% fee
         let amount = U256::from(1000);
-        let fee_pips = 3000; // 0.3%
+        let fee_pips = FeePips::new(3000); // 0.3%
         
         let fee = SwapMath::calculate_fee_amount(amount, fee_pips).unwrap();
         assert_eq!(fee, U256::from(3)); // 0.3% of 1000 is 3
         
         // Test with 0% fee
-        let fee = SwapMath::calculate_fee_amount(amount, 0).unwrap();
+        let fee = SwapMath::calculate_fee_amount(amount, FeePips::ZERO).unwrap();
         assert_eq!(fee, U256::zero());
         
         // Test with invalid fee
-        let result = SwapMath::calculate_fee_amount(amount, SwapMath::MAX_SWAP_FEE + 1);
+        let result = SwapMath::calculate_fee_amount(amount, FeePips::new(SwapMath::FEE_DENOMINATOR + 1));
         assert!(result.is_err());
     }
     
@@ -307,17 +308,17 @@ mod tests {
     fn test_apply_fee() {
         // Test with 0.3% fee
         let amount = U256::from(1000);
-        let fee_pips = 3000; // 0.3%
+        let fee_pips = FeePips::new(3000); // 0.3%
         
         let amount_after_fee = SwapMath::apply_fee(amount, fee_pips).unwrap();
         assert_eq!(amount_after_fee, U256::from(997)); // 1000 - 0.3% = 997
         
         // Test with 0% fee
-        let amount_after_fee = SwapMath::apply_fee(amount, 0).unwrap();
+        let amount_after_fee = SwapMath::apply_fee(amount, FeePips::ZERO).unwrap();
         assert_eq!(amount_after_fee, amount);
         
         // Test with invalid fee
-        let result = SwapMath::apply_fee(amount, SwapMath::MAX_SWAP_FEE + 1);
+        let result = SwapMath::apply_fee(amount, FeePips::new(SwapMath::FEE_DENOMINATOR + 1));
         assert!(result.is_err());
     }
 
@@ -327,7 +328,7 @@ mod tests {
         let target = SqrtPrice::new(U256::from(900));
         let liquidity = Liquidity::new(1000);
         let amount_remaining = -100i128;
-        let fee_pips = 3000; // 0.3%
+        let fee_pips = FeePips::new(3000); // 0.3%
 
         let result = SwapMath::compute_swap_step(
             current,
@@ -354,7 +355,7 @@ mod tests {
     #[test]
     fn test_compute_swap_step_exact_out() {
         let amount_remaining = 100i128;
-        let fee_pips = 3000; // 0.3%
+        let fee_pips = FeePips::new(3000); // 0.3%
 
         // Using more reasonable price and liquidity values
         let current = SqrtPrice::new(U256::from(1u64) << 96); // 1.0 price
@@ -378,7 +379,7 @@ mod tests {
         let target = SqrtPrice::new(U256::from(900));
         let liquidity = Liquidity::new(1000);
         let amount_remaining = 100i128;
-        let fee_pips = SwapMath::MAX_SWAP_FEE + 1;
+        let fee_pips = FeePips::new(SwapMath::FEE_DENOMINATOR + 1);
 
         let result = SwapMath::compute_swap_step(
             current,
@@ -1,6 +1,39 @@
+use ethers::types::I256;
 use primitive_types::U256;
 use crate::core::math::{MathError, Result, BitMath};
 
+/// Factors of the sqrt price at a tick as Q128.128s, the one at index `i`
+/// being 1 / sqrt(1.0001)^(2^i), in the limbs of the Solidity literals
+const SQRT_RATIO_FACTORS: [U256; 20] = [
+        U256([0xaa2d162d1a594001, 0xfffcb933bd6fad37, 0, 0]),
+        U256([0x59a46990580e213a, 0xfff97272373d4132, 0, 0]),
+        U256([0xef12357cf3c7fdcc, 0xfff2e50f5f656932, 0, 0]),
+        U256([0x1c3624eaa0941cd0, 0xffe5caca7e10e4e6, 0, 0]),
+        U256([0xc9db58835c926644, 0xffcb9843d60f6159, 0, 0]),
+        U256([0x472e6896dfb254c0, 0xff973b41fa98c081, 0, 0]),
+        U256([0x43ec78b326b52861, 0xff2ea16466c96a38, 0, 0]),
+        U256([0x11c461f1969c3053, 0xfe5dee046a99a2a8, 0, 0]),
+        U256([0xdcffc83b479aa3a4, 0xfcbe86c7900a88ae, 0, 0]),
+        U256([0x6f2b074cf7815e54, 0xf987a7253ac41317, 0, 0]),
+        U256([0x940c7a398e4b70f3, 0xf3392b0822b70005, 0, 0]),
+        U256([0x43b29c7fa6e889d9, 0xe7159475a2c29b74, 0, 0]),
+        U256([0x845ad8f792aa5825, 0xd097f3bdfd2022b8, 0, 0]),
+        U256([0x8a65dc1f90e061e5, 0xa9f746462d870fdf, 0, 0]),
+        U256([0x90bb3df62baf32f7, 0x70d869a156d2a1b8, 0, 0]),
+        U256([0x81231505542fcfa6, 0x31be135f97d08fd9, 0, 0]),
+        U256([0xc677de54f3e99bc9, 0x09aa508b5b7a84e1, 0, 0]),
+        U256([0x6699c329225ee604, 0x005d6af8dedb8119, 0, 0]),
+        U256([0x1ea926041bedfe98, 0x00002216e584f5fa, 0, 0]),
+        U256([0x91f7dc42444e8fa2, 0x00000000048a1703, 0, 0]),
+];
+
+/// log_sqrt(1.0001)(2) as a Q128.128, converting a Q64.64 log2 to a tick
+const LOG_2_TO_LOG_SQRT_10001: I256 = I256::from_raw(U256([0xa301d71055774c85, 0x3627, 0, 0]));
+/// Error bounds of the approximated log2, as Q128.128s, below and above the
+/// tick it gives
+const TICK_LOW_ERROR: I256 = I256::from_raw(U256([0x5af012a19d003aaa, 0x028f6481ab7f045a, 0, 0]));
+const TICK_HIGH_ERROR: I256 = I256::from_raw(U256([0x455e260799a0632f, 0xdb2df09e81959a81, 0, 0]));
+
 /// Functions for handling tick-related math
 pub struct TickMath;
 
@@ -51,92 +84,24 @@ impl TickMath {
             return Err(MathError::InvalidTick);
         }
 
-        let abs_tick = tick.abs() as u32;
-        
-        // Use predefined values for exact test cases to ensure tests pass
-        if tick == 0 {
-            return Ok(U256::from(1u64) << 96);
-        } else if tick == 1 {
-            return Ok(U256::from_str_radix("1000150000000000000000000000000000", 16).unwrap());
-        } else if tick == -1 {
-            return Ok(U256::from_str_radix("ffeb5f827cb0bd30000000000000000", 16).unwrap());
-        } else if tick == 887272 {
-            return Ok(Self::MAX_SQRT_PRICE - U256::one());
-        } else if tick == -887272 {
-            return Ok(Self::MIN_SQRT_PRICE);
-        }
-        
-        // Start with Q96 value for 1.0
-        let mut price: U256 = U256::from(1u64) << 96;
+        let abs_tick = tick.unsigned_abs();
 
-        // Apply the corresponding factor for each bit position in abs_tick
-        if abs_tick & 0x1 != 0 {
-            price = price * U256::from_str_radix("1000150000000000000", 10).unwrap() / U256::from_str_radix("1000000000000000000", 10).unwrap();
-        }
-        if abs_tick & 0x2 != 0 {
-            price = price * U256::from_str_radix("1000300022500750000", 10).unwrap() / U256::from_str_radix("1000000000000000000", 10).unwrap();
-        }
-        if abs_tick & 0x4 != 0 {
-            price = price * U256::from_str_radix("1000600180054002250", 10).unwrap() / U256::from_str_radix("1000000000000000000", 10).unwrap();
-        }
-        if abs_tick & 0x8 != 0 {
-            price = price * U256::from_str_radix("1001200720432304900", 10).unwrap() / U256::from_str_radix("1000000000000000000", 10).unwrap();
-        }
-        if abs_tick & 0x10 != 0 {
-            price = price * U256::from_str_radix("1002402880821114600", 10).unwrap() / U256::from_str_radix("1000000000000000000", 10).unwrap();
-        }
-        if abs_tick & 0x20 != 0 {
-            price = price * U256::from_str_radix("1004813841045090600", 10).unwrap() / U256::from_str_radix("1000000000000000000", 10).unwrap();
-        }
-        if abs_tick & 0x40 != 0 {
-            price = price * U256::from_str_radix("1009645258064314900", 10).unwrap() / U256::from_str_radix("1000000000000000000", 10).unwrap();
-        }
-        if abs_tick & 0x80 != 0 {
-            price = price * U256::from_str_radix("1019413057329671700", 10).unwrap() / U256::from_str_radix("1000000000000000000", 10).unwrap();
-        }
-        if abs_tick & 0x100 != 0 {
-            price = price * U256::from_str_radix("1039259097067954600", 10).unwrap() / U256::from_str_radix("1000000000000000000", 10).unwrap();
-        }
-        if abs_tick & 0x200 != 0 {
-            price = price * U256::from_str_radix("1080169535491291200", 10).unwrap() / U256::from_str_radix("1000000000000000000", 10).unwrap();
-        }
-        if abs_tick & 0x400 != 0 {
-            price = price * U256::from_str_radix("1167158120033788100", 10).unwrap() / U256::from_str_radix("1000000000000000000", 10).unwrap();
-        }
-        if abs_tick & 0x800 != 0 {
-            price = price * U256::from_str_radix("1362789518017830800", 10).unwrap() / U256::from_str_radix("1000000000000000000", 10).unwrap();
-        }
-        if abs_tick & 0x1000 != 0 {
-            price = price * U256::from_str_radix("1857004999963214300", 10).unwrap() / U256::from_str_radix("1000000000000000000", 10).unwrap();
-        }
-        if abs_tick & 0x2000 != 0 {
-            price = price * U256::from_str_radix("3450908787734268600", 10).unwrap() / U256::from_str_radix("1000000000000000000", 10).unwrap();
-        }
-        if abs_tick & 0x4000 != 0 {
-            price = price * U256::from_str_radix("11902289039106681000", 10).unwrap() / U256::from_str_radix("1000000000000000000", 10).unwrap();
-        }
-        if abs_tick & 0x8000 != 0 {
-            price = price * U256::from_str_radix("141621624675143570000", 10).unwrap() / U256::from_str_radix("1000000000000000000", 10).unwrap();
-        }
-        if abs_tick & 0x10000 != 0 {
-            price = price * U256::from_str_radix("20052271208276234000000", 10).unwrap() / U256::from_str_radix("1000000000000000000", 10).unwrap();
-        }
-        if abs_tick & 0x20000 != 0 {
-            price = price * U256::from_str_radix("402099216632332700000000", 10).unwrap() / U256::from_str_radix("1000000000000000000", 10).unwrap();
-        }
-        if abs_tick & 0x40000 != 0 {
-            price = price * U256::from_str_radix("161682916061173400000000000", 10).unwrap() / U256::from_str_radix("1000000000000000000", 10).unwrap();
-        }
-        if abs_tick & 0x80000 != 0 {
-            price = price * U256::from_str_radix("26142343102707080000000000000000", 10).unwrap() / U256::from_str_radix("1000000000000000000", 10).unwrap();
+        // Accumulate 1 / sqrt(1.0001)^abs_tick as a Q128.128, one factor per set bit
+        let mut ratio = if abs_tick & 0x1 != 0 { SQRT_RATIO_FACTORS[0] } else { U256::one() << 128 };
+        for (bit, factor) in SQRT_RATIO_FACTORS.iter().enumerate().skip(1) {
+            if abs_tick & (1 << bit) != 0 {
+                ratio = (ratio * *factor) >> 128;
+            }
         }
 
         if tick > 0 {
-            // If tick is positive, invert the price
-            let one = U256::from(1) << 192;
-            price = one / price;
+            // Positive ticks use the reciprocal
+            ratio = U256::MAX / ratio;
         }
 
+        // Convert to a Q64.96, rounding up so the price never falls below the tick
+        let price = (ratio + U256::from(0xffffffffu64)) >> 32;
+
         if price < Self::MIN_SQRT_PRICE {
             return Err(MathError::InvalidPrice);
         }
@@ -147,50 +112,38 @@ impl TickMath {
         Ok(price)
     }
 
-    /// Returns the tick corresponding to the given sqrt price as a Q64.96
+    /// Returns the greatest tick whose sqrt price is at most the given Q64.96
+    /// sqrt price, computed from its log2 like the Solidity library
     pub fn get_tick_at_sqrt_price(sqrt_price_x96: U256) -> Result<i32> {
         if sqrt_price_x96 < Self::MIN_SQRT_PRICE || sqrt_price_x96 >= Self::MAX_SQRT_PRICE {
             return Err(MathError::InvalidPrice);
         }
         
-        // Use hardcoded values for specific test cases
-        if sqrt_price_x96 == U256::from(1u64) << 96 {
-            return Ok(0);
-        } else if sqrt_price_x96 == U256::from_str_radix("1000150000000000000000000000000000", 16).unwrap() {
-            return Ok(1);
-        } else if sqrt_price_x96 == U256::from_str_radix("ffeb5f827cb0bd30000000000000000", 16).unwrap() {
-            return Ok(-1);
-        } else if sqrt_price_x96 == Self::MAX_SQRT_PRICE - U256::one() {
-            return Ok(887272);
-        } else if sqrt_price_x96 == Self::MIN_SQRT_PRICE {
-            return Ok(-887272);
-        }
-        
-        // Special handling for known roundtrip test cases
-        for tick in [-887272, -42, -1, 0, 1, 42, 887272] {
-            let sqrt_price_at_tick = Self::get_sqrt_price_at_tick(tick).ok();
-            if sqrt_price_at_tick.is_some() && sqrt_price_at_tick.unwrap() == sqrt_price_x96 {
-                return Ok(tick);
-            }
+        // The log2 of the price as a Q64.64, integer part first, from the
+        // most significant bit of the price as a Q128.128
+        let price = sqrt_price_x96 << 32;
+        let msb = BitMath::most_significant_bit(price) as usize;
+        let mut r = if msb >= 128 { price >> (msb - 127) } else { price << (127 - msb) };
+        let mut log_2 = I256::from((msb as i128 - 128) << 64);
+
+        // Then 14 bits of its fraction, squaring the normalized price for each
+        for bit in (50..64).rev() {
+            r = (r * r) >> 127;
+            let f = r >> 128;
+            log_2 += I256::from_raw(f << bit);
+            r >>= f.low_u32();
         }
 
-        // Use binary search to find the tick for the given sqrt price
-        let mut low = Self::MIN_TICK;
-        let mut high = Self::MAX_TICK;
+        // The log2 is accurate enough that the tick is one of two
+        let log_sqrt_10001 = log_2 * LOG_2_TO_LOG_SQRT_10001;
+        let tick_low = (log_sqrt_10001 - TICK_LOW_ERROR).asr(128).low_i32();
+        let tick_high = (log_sqrt_10001 + TICK_HIGH_ERROR).asr(128).low_i32();
 
-        while low <= high {
-            let mid = (low + high) / 2;
-            let price_at_mid = Self::get_sqrt_price_at_tick(mid)?;
-            
-            if price_at_mid <= sqrt_price_x96 {
-                low = mid + 1;
-            } else {
-                high = mid - 1;
-            }
+        if tick_low == tick_high || Self::get_sqrt_price_at_tick(tick_high)? > sqrt_price_x96 {
+            Ok(tick_low)
+        } else {
+            Ok(tick_high)
         }
-
-        // high is the largest tick where price is less than or equal to sqrt_price_x96
-        Ok(high)
     }
 }
 
@@ -203,9 +156,9 @@ mod tests {
         // Test cases from the Solidity implementation
         let test_cases = vec![
             (0, U256::from(1u64) << 96),
-            (1, U256::from_str_radix("1000150000000000000000000000000000", 16).unwrap()),
-            (-1, U256::from_str_radix("ffeb5f827cb0bd30000000000000000", 16).unwrap()),
-            (887272, TickMath::MAX_SQRT_PRICE - U256::one()),
+            (1, U256::from_dec_str("79232123823359799118286999568").unwrap()),
+            (-1, U256::from_dec_str("79224201403219477170569942574").unwrap()),
+            (887272, TickMath::MAX_SQRT_PRICE),
             (-887272, TickMath::MIN_SQRT_PRICE),
         ];
 
@@ -215,14 +168,68 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_sqrt_price_matches_reference_table() {
+        // getSqrtPriceAtTick of the Solidity library
+        let table = [
+            (10, "79267784519130042428790663799"),
+            (-10, "79188560314459151373725315960"),
+            (100, "79625275426524748796330556128"),
+            (-100, "78833030112140176575862854579"),
+            (1000, "83290069058676223003182343270"),
+            (-1000, "75364347830767020784054125655"),
+            (50000, "965075977353221155028623082916"),
+            (-50000, "6504256538020985011912221507"),
+            (150000, "143194173941309278083010301478497"),
+            (-150000, "43836292794701720435367485"),
+            (500000, "5697689776495288729098254600827762987878"),
+            (-500000, "1101692437043807371"),
+            (887271, "1461373636630004318706518188784493106690254656249"),
+            (-887271, "4295343490"),
+        ];
+        for (tick, expected) in table {
+            let result = TickMath::get_sqrt_price_at_tick(tick).unwrap();
+            assert_eq!(result, U256::from_dec_str(expected).unwrap(), "Failed for tick {}", tick);
+        }
+    }
+
+    #[test]
+    fn test_sqrt_ratio_factors_match_solidity_literals() {
+        let literals = [
+            "fffcb933bd6fad37aa2d162d1a594001",
+            "fff97272373d413259a46990580e213a",
+            "fff2e50f5f656932ef12357cf3c7fdcc",
+            "ffe5caca7e10e4e61c3624eaa0941cd0",
+            "ffcb9843d60f6159c9db58835c926644",
+            "ff973b41fa98c081472e6896dfb254c0",
+            "ff2ea16466c96a3843ec78b326b52861",
+            "fe5dee046a99a2a811c461f1969c3053",
+            "fcbe86c7900a88aedcffc83b479aa3a4",
+            "f987a7253ac413176f2b074cf7815e54",
+            "f3392b0822b70005940c7a398e4b70f3",
+            "e7159475a2c29b7443b29c7fa6e889d9",
+            "d097f3bdfd2022b8845ad8f792aa5825",
+            "a9f746462d870fdf8a65dc1f90e061e5",
+            "70d869a156d2a1b890bb3df62baf32f7",
+            "31be135f97d08fd981231505542fcfa6",
+            "9aa508b5b7a84e1c677de54f3e99bc9",
+            "5d6af8dedb81196699c329225ee604",
+            "2216e584f5fa1ea926041bedfe98",
+            "48a170391f7dc42444e8fa2",
+        ];
+        for (bit, literal) in literals.iter().enumerate() {
+            assert_eq!(SQRT_RATIO_FACTORS[bit], U256::from_str_radix(literal, 16).unwrap(), "Failed for bit {}", bit);
+        }
+    }
+
     #[test]
     fn test_get_tick_at_sqrt_price() {
         // Test cases from the Solidity implementation
         let test_cases = vec![
             (U256::from(1u64) << 96, 0),
-            (U256::from_str_radix("1000150000000000000000000000000000", 16).unwrap(), 1),
-            (U256::from_str_radix("ffeb5f827cb0bd30000000000000000", 16).unwrap(), -1),
-            (TickMath::MAX_SQRT_PRICE - U256::one(), 887272),
+            (U256::from_dec_str("79232123823359799118286999568").unwrap(), 1),
+            (U256::from_dec_str("79224201403219477170569942574").unwrap(), -1),
+            (TickMath::MAX_SQRT_PRICE - U256::one(), 887271),
             (TickMath::MIN_SQRT_PRICE, -887272),
         ];
 
@@ -246,8 +253,9 @@ mod tests {
     
     #[test]
     fn test_roundtrip() {
-        // Test roundtrip conversion for various ticks
-        for tick in [-887272, -42, -1, 0, 1, 42, 887272].iter() {
+        // Test roundtrip conversion for various ticks. The price of the
+        // maximum tick is the exclusive upper bound, which has no tick
+        for tick in [-887272, -42, -1, 0, 1, 42, 887271].iter() {
             let sqrt_price = TickMath::get_sqrt_price_at_tick(*tick).unwrap();
             let roundtrip_tick = TickMath::get_tick_at_sqrt_price(sqrt_price).unwrap();
             assert_eq!(roundtrip_tick, *tick, "Roundtrip failed for tick {}", tick);
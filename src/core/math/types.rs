@@ -1,39 +1,35 @@
-use primitive_types::U256;
+use primitive_types::{U256, U512};
+use std::cmp::Ordering;
 use std::ops::{Add, Sub, Mul, Div};
+use std::fmt;
 use num_traits::Zero;
+use serde::{Deserialize, Serialize};
+use super::{MathError, Result, TickMath};
 
-/// U256 扩展特性
-pub trait U256Ext {
-    /// 将 U256 转换为 i128，如果超出范围则截断
-    fn as_i128(&self) -> i128;
-}
+pub mod ratio;
 
-impl U256Ext for U256 {
-    fn as_i128(&self) -> i128 {
-        let u128_value = self.as_u128();
-        if u128_value > i128::MAX as u128 {
-            i128::MAX
-        } else {
-            u128_value as i128
-        }
-    }
-}
+pub use ratio::{Percent, Ratio};
 
 /// Q64.96 fixed-point number
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
 pub struct Q64x96(pub U256);
 
 /// Fixed-point scaling factor
-pub const Q96: U256 = U256([96, 0, 0, 0]);
+pub const Q96: U256 = U256([0, 1 << 32, 0, 0]);
 
 /// Represents price as a square root Q64.96
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Serialize, Deserialize)]
 pub struct SqrtPrice(pub U256);
 
 /// Represents liquidity
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Serialize, Deserialize)]
 pub struct Liquidity(pub u128);
 
+/// Tick spacing of a pool, always within `TickMath::MIN_TICK_SPACING..=TickMath::MAX_TICK_SPACING`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "i32", into = "i32")]
+pub struct TickSpacing(i32);
+
 impl Zero for Q64x96 {
     fn zero() -> Self {
         Self(U256::zero())
@@ -192,11 +188,43 @@ impl Q64x96 {
 }
 
 impl SqrtPrice {
+    /// The minimum sqrt price, equal to the sqrt price at `TickMath::MIN_TICK`
+    pub const MIN: Self = Self(TickMath::MIN_SQRT_PRICE);
+    /// The maximum sqrt price, equal to the sqrt price at `TickMath::MAX_TICK`
+    pub const MAX: Self = Self(TickMath::MAX_SQRT_PRICE);
+    /// The sqrt price of a 1:1 price
+    pub const ONE: Self = Self(Q96);
+
     /// Creates a new SqrtPrice from a U256
     pub fn new(value: U256) -> Self {
         Self(value)
     }
 
+    /// Creates the sqrt price at the given tick
+    pub fn from_tick(tick: i32) -> Result<Self> {
+        TickMath::get_sqrt_price_at_tick(tick).map(Self)
+    }
+
+    /// Creates the sqrt price for a price of `numerator / denominator` (token1 per token0)
+    ///
+    /// The result is rounded down and must lie within `[MIN, MAX]`.
+    pub fn from_price_ratio(numerator: U256, denominator: U256) -> Result<Self> {
+        if denominator.is_zero() {
+            return Err(MathError::DivisionByZero);
+        }
+
+        // sqrt(numerator / denominator) * 2^96 == sqrt(numerator * 2^192 / denominator)
+        let ratio_x192 = (U512::from(numerator) << 192) / U512::from(denominator);
+        let sqrt_price = U256::try_from(ratio_x192.integer_sqrt())
+            .map_err(|_| MathError::PriceOverflow)?;
+
+        if sqrt_price < TickMath::MIN_SQRT_PRICE || sqrt_price > TickMath::MAX_SQRT_PRICE {
+            return Err(MathError::InvalidPrice);
+        }
+
+        Ok(Self(sqrt_price))
+    }
+
     /// Converts to U256
     pub fn to_u256(self) -> U256 {
         self.0
@@ -206,6 +234,54 @@ impl SqrtPrice {
     pub fn as_u128(&self) -> u128 {
         self.0.as_u128()
     }
+
+    /// Approximate price of token0 in token1, `(sqrt_price / 2^96)^2`
+    pub fn to_price_f64(self) -> f64 {
+        let sqrt_price = self.0.0.iter().rev().fold(0.0, |acc, limb| acc * 2f64.powi(64) + *limb as f64);
+        let sqrt_ratio = sqrt_price / 2f64.powi(96);
+        sqrt_ratio * sqrt_ratio
+    }
+}
+
+/// Formats a price to six significant digits, in scientific notation when
+/// below 1e-3 or from 1e6 up
+pub fn format_price(price: f64) -> String {
+    if price == 0.0 || (1e-3..1e6).contains(&price) {
+        let decimals = if price == 0.0 { 0 } else { (5 - price.log10().floor() as i32) as usize };
+        format!("{:.*}", decimals, price)
+    } else {
+        format!("{:.5e}", price)
+    }
+}
+
+/// Shows the approximate decimal price to six significant digits, followed
+/// by the tick for prices within the tick range
+impl fmt::Display for SqrtPrice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", format_price(self.to_price_f64()))?;
+        match TickMath::get_tick_at_sqrt_price(self.0) {
+            Ok(tick) => write!(f, " (tick {})", tick),
+            Err(_) => Ok(()),
+        }
+    }
+}
+
+impl From<U256> for SqrtPrice {
+    fn from(value: U256) -> Self {
+        Self(value)
+    }
+}
+
+impl PartialEq<U256> for SqrtPrice {
+    fn eq(&self, other: &U256) -> bool {
+        self.0 == *other
+    }
+}
+
+impl PartialOrd<U256> for SqrtPrice {
+    fn partial_cmp(&self, other: &U256) -> Option<Ordering> {
+        self.0.partial_cmp(other)
+    }
 }
 
 impl Liquidity {
@@ -223,4 +299,154 @@ impl Liquidity {
     pub fn as_u128(&self) -> u128 {
         self.0
     }
-} 
\ No newline at end of file
+}
+
+impl TickSpacing {
+    /// The smallest tick spacing
+    pub const MIN: Self = Self(TickMath::MIN_TICK_SPACING);
+    /// The largest tick spacing
+    pub const MAX: Self = Self(TickMath::MAX_TICK_SPACING);
+
+    /// Creates a tick spacing, rejecting zero, negative and too large values
+    pub const fn new(value: i32) -> Result<Self> {
+        if value < TickMath::MIN_TICK_SPACING || value > TickMath::MAX_TICK_SPACING {
+            return Err(MathError::InvalidTickSpacing(value));
+        }
+        Ok(Self(value))
+    }
+
+    /// Returns the spacing as an i32
+    pub const fn get(self) -> i32 {
+        self.0
+    }
+
+    /// Returns the maximum tick usable with this spacing
+    pub fn max_usable_tick(self) -> i32 {
+        TickMath::max_usable_tick(self.0)
+    }
+
+    /// Returns the minimum tick usable with this spacing
+    pub fn min_usable_tick(self) -> i32 {
+        TickMath::min_usable_tick(self.0)
+    }
+
+    /// Checks whether a tick is a multiple of the spacing
+    pub fn is_aligned(self, tick: i32) -> bool {
+        tick % self.0 == 0
+    }
+}
+
+impl TryFrom<i32> for TickSpacing {
+    type Error = MathError;
+
+    fn try_from(value: i32) -> Result<Self> {
+        Self::new(value)
+    }
+}
+
+impl From<TickSpacing> for i32 {
+    fn from(spacing: TickSpacing) -> Self {
+        spacing.0
+    }
+}
+
+impl PartialEq<i32> for TickSpacing {
+    fn eq(&self, other: &i32) -> bool {
+        self.0 == *other
+    }
+}
+
+impl fmt::Display for TickSpacing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_q96_is_two_to_the_96() {
+        assert_eq!(Q96, U256::one() << 96);
+        assert_eq!(SqrtPrice::ONE.to_u256(), U256::one() << 96);
+        assert_eq!((Q64x96(Q96 * 3) * Q64x96(Q96 * 2)).0, Q96 * 6);
+    }
+
+    #[test]
+    fn test_tick_spacing_bounds() {
+        assert_eq!(TickSpacing::new(1).unwrap(), TickSpacing::MIN);
+        assert_eq!(TickSpacing::new(32767).unwrap(), TickSpacing::MAX);
+        assert!(matches!(TickSpacing::new(0), Err(MathError::InvalidTickSpacing(0))));
+        assert!(matches!(TickSpacing::new(-60), Err(MathError::InvalidTickSpacing(-60))));
+        assert!(matches!(TickSpacing::new(32768), Err(MathError::InvalidTickSpacing(32768))));
+
+        let spacing = TickSpacing::try_from(60).unwrap();
+        assert_eq!(i32::from(spacing), 60);
+        assert_eq!(spacing.max_usable_tick(), 887220);
+        assert_eq!(spacing.min_usable_tick(), -887220);
+        assert!(spacing.is_aligned(-120));
+        assert!(!spacing.is_aligned(30));
+    }
+
+    #[test]
+    fn test_tick_spacing_serde() {
+        let spacing = TickSpacing::new(10).unwrap();
+        assert_eq!(serde_json::to_string(&spacing).unwrap(), "10");
+        assert_eq!(serde_json::from_str::<TickSpacing>("10").unwrap(), spacing);
+        assert!(serde_json::from_str::<TickSpacing>("0").is_err());
+        assert!(serde_json::from_str::<TickSpacing>("-1").is_err());
+    }
+
+    #[test]
+    fn test_sqrt_price_bounds() {
+        assert_eq!(SqrtPrice::MIN, TickMath::MIN_SQRT_PRICE);
+        assert_eq!(SqrtPrice::MAX, TickMath::MAX_SQRT_PRICE);
+        assert_eq!(SqrtPrice::ONE, U256::from(79228162514264337593543950336u128));
+        assert!(SqrtPrice::MIN < SqrtPrice::ONE);
+        assert!(SqrtPrice::ONE < SqrtPrice::MAX);
+        assert!(SqrtPrice::MAX > U256::from(u128::MAX));
+    }
+
+    #[test]
+    fn test_sqrt_price_display() {
+        assert_eq!(SqrtPrice::ONE.to_string(), "1.00000 (tick 0)");
+        assert_eq!(SqrtPrice::from_tick(-23_028).unwrap().to_string(), "0.0999900 (tick -23028)");
+        assert_eq!(SqrtPrice::new(Q96 * 100).to_string(), "10000.0 (tick 92108)");
+        assert_eq!(SqrtPrice::MIN.to_string(), "2.93896e-39 (tick -887272)");
+        assert_eq!(SqrtPrice::MAX.to_string(), "3.40257e38");
+        assert_eq!(SqrtPrice::new(U256::zero()).to_string(), "0");
+    }
+
+    #[test]
+    fn test_sqrt_price_from_tick() {
+        assert_eq!(SqrtPrice::from_tick(0).unwrap(), SqrtPrice::ONE);
+        assert!(SqrtPrice::from_tick(TickMath::MAX_TICK + 1).is_err());
+    }
+
+    #[test]
+    fn test_sqrt_price_from_price_ratio() {
+        assert_eq!(SqrtPrice::from_price_ratio(U256::one(), U256::one()).unwrap(), SqrtPrice::ONE);
+        assert_eq!(
+            SqrtPrice::from_price_ratio(U256::from(4), U256::one()).unwrap(),
+            SqrtPrice::new(Q96 * 2)
+        );
+        assert_eq!(
+            SqrtPrice::from_price_ratio(U256::one(), U256::from(4)).unwrap(),
+            SqrtPrice::new(Q96 / 2)
+        );
+
+        assert!(matches!(
+            SqrtPrice::from_price_ratio(U256::one(), U256::zero()),
+            Err(MathError::DivisionByZero)
+        ));
+        assert!(matches!(
+            SqrtPrice::from_price_ratio(U256::MAX, U256::one()),
+            Err(MathError::InvalidPrice)
+        ));
+        assert!(matches!(
+            SqrtPrice::from_price_ratio(U256::one(), U256::MAX),
+            Err(MathError::InvalidPrice)
+        ));
+    }
+}
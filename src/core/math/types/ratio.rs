@@ -0,0 +1,290 @@
+//! Exact ratios of amounts, for slippage bounds and fee shares
+//!
+//! Rates come in several units across the crate: [`FeePips`] for swap fees,
+//! [`Bps`] for coarser rates and whole percents in hooks. A [`Ratio`] holds
+//! any of them exactly as a fraction of two `U256`s and applies it to an
+//! amount with full precision, so code combining rates doesn't need to
+//! convert units by hand. A [`Percent`] is a ratio between 0% and 100%,
+//! which can be applied to an amount without overflowing.
+
+use std::fmt;
+
+use primitive_types::U256;
+use serde::{Deserialize, Serialize};
+
+use crate::core::math::{Bps, FeePips, FullMath, MathError, Result, U256Ext};
+
+/// A non-negative fraction `numerator / denominator`
+///
+/// Ratios are not reduced, but compare by value: 5% equals 500 bps.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(try_from = "RatioTerms")]
+pub struct Ratio {
+    numerator: U256,
+    denominator: U256,
+}
+
+/// Unchecked terms of a deserialized ratio
+#[derive(Deserialize)]
+struct RatioTerms {
+    numerator: U256,
+    denominator: U256,
+}
+
+impl TryFrom<RatioTerms> for Ratio {
+    type Error = MathError;
+
+    fn try_from(terms: RatioTerms) -> Result<Self> {
+        Self::new(terms.numerator, terms.denominator)
+    }
+}
+
+impl Ratio {
+    /// Zero
+    pub const ZERO: Self = Self { numerator: U256([0; 4]), denominator: U256([1, 0, 0, 0]) };
+    /// One, or 100%
+    pub const ONE: Self = Self { numerator: U256([1, 0, 0, 0]), denominator: U256([1, 0, 0, 0]) };
+
+    /// Creates the ratio `numerator / denominator`, failing for a zero
+    /// denominator
+    pub fn new(numerator: U256, denominator: U256) -> Result<Self> {
+        if denominator.is_zero() {
+            return Err(MathError::DivisionByZero);
+        }
+        Ok(Self { numerator, denominator })
+    }
+
+    /// A number of basis points, where 10,000 is one
+    pub fn from_bps(bps: u32) -> Self {
+        Self { numerator: bps.into(), denominator: Bps::DENOMINATOR.into() }
+    }
+
+    /// A number of pips, where 1,000,000 is one
+    pub fn from_pips(pips: u32) -> Self {
+        Self { numerator: pips.into(), denominator: FeePips::DENOMINATOR.into() }
+    }
+
+    /// A number of percent, where 100 is one
+    pub fn from_percent(percent: u32) -> Self {
+        Self { numerator: percent.into(), denominator: 100.into() }
+    }
+
+    /// The numerator
+    pub fn numerator(&self) -> U256 {
+        self.numerator
+    }
+
+    /// The denominator, never zero
+    pub fn denominator(&self) -> U256 {
+        self.denominator
+    }
+
+    /// Whether the ratio is zero
+    pub fn is_zero(&self) -> bool {
+        self.numerator.is_zero()
+    }
+
+    /// The ratio of an amount, rounding down, or `None` on overflow
+    pub fn checked_apply(&self, amount: U256) -> Option<U256> {
+        FullMath::mul_div(amount, self.numerator, self.denominator)
+    }
+
+    /// The ratio of an amount, rounding up, or `None` on overflow
+    pub fn checked_apply_rounding_up(&self, amount: U256) -> Option<U256> {
+        FullMath::mul_div_rounding_up(amount, self.numerator, self.denominator)
+    }
+
+    /// The ratio of a `u128` amount, rounding down, or `None` if the result
+    /// doesn't fit
+    pub fn checked_apply_u128(&self, amount: u128) -> Option<u128> {
+        self.checked_apply(amount.into())?.try_as_u128().ok()
+    }
+
+    /// Approximate value, for display and heuristics
+    pub fn to_f64(&self) -> f64 {
+        let to_f64 = |value: U256| value.0.iter().rev().fold(0.0, |acc, limb| acc * 2f64.powi(64) + *limb as f64);
+        to_f64(self.numerator) / to_f64(self.denominator)
+    }
+}
+
+impl PartialEq for Ratio {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for Ratio {}
+
+impl PartialOrd for Ratio {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Ratio {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        let left = self.numerator.full_mul(other.denominator);
+        let right = other.numerator.full_mul(self.denominator);
+        left.cmp(&right)
+    }
+}
+
+impl From<Bps> for Ratio {
+    fn from(bps: Bps) -> Self {
+        Self::from_bps(bps.get())
+    }
+}
+
+impl From<FeePips> for Ratio {
+    fn from(fee: FeePips) -> Self {
+        Self::from_pips(fee.get())
+    }
+}
+
+impl From<Percent> for Ratio {
+    fn from(percent: Percent) -> Self {
+        percent.0
+    }
+}
+
+/// A ratio between 0% and 100%
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "Ratio", into = "Ratio")]
+pub struct Percent(Ratio);
+
+impl Percent {
+    /// 0%
+    pub const ZERO: Self = Self(Ratio::ZERO);
+    /// 100%
+    pub const HUNDRED: Self = Self(Ratio::ONE);
+
+    /// A whole number of percent, `None` above 100
+    pub fn new(percent: u32) -> Option<Self> {
+        Self::from_ratio(Ratio::from_percent(percent))
+    }
+
+    /// A number of basis points, `None` above 10,000
+    pub fn from_bps(bps: Bps) -> Option<Self> {
+        Self::from_ratio(bps.into())
+    }
+
+    /// A number of pips, `None` above 1,000,000
+    pub fn from_pips(fee: FeePips) -> Option<Self> {
+        Self::from_ratio(fee.into())
+    }
+
+    /// A ratio, `None` above one
+    pub fn from_ratio(ratio: Ratio) -> Option<Self> {
+        (ratio <= Ratio::ONE).then_some(Self(ratio))
+    }
+
+    /// The ratio
+    pub fn ratio(&self) -> Ratio {
+        self.0
+    }
+
+    /// Whether the percentage is zero
+    pub fn is_zero(&self) -> bool {
+        self.0.is_zero()
+    }
+
+    /// The remainder to 100%
+    pub fn complement(&self) -> Self {
+        Self(Ratio {
+            numerator: self.0.denominator - self.0.numerator,
+            denominator: self.0.denominator,
+        })
+    }
+
+    /// The percentage of an amount, rounding down; never more than the amount
+    pub fn of(&self, amount: U256) -> U256 {
+        self.0.checked_apply(amount).expect("at most the amount")
+    }
+
+    /// The percentage of an amount, rounding up; never more than the amount
+    pub fn of_rounding_up(&self, amount: U256) -> U256 {
+        self.0.checked_apply_rounding_up(amount).expect("at most the amount")
+    }
+
+    /// The percentage of a `u128` amount, rounding down
+    pub fn of_u128(&self, amount: u128) -> u128 {
+        self.of(amount.into()).as_u128()
+    }
+}
+
+impl TryFrom<Ratio> for Percent {
+    type Error = MathError;
+
+    fn try_from(ratio: Ratio) -> Result<Self> {
+        Self::from_ratio(ratio).ok_or(MathError::Overflow)
+    }
+}
+
+impl fmt::Display for Percent {
+    /// Prints the percentage with up to four decimals, e.g. `5%` or `0.05%`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pips = FullMath::mul_div(self.0.numerator, FeePips::DENOMINATOR.into(), self.0.denominator)
+            .expect("at most 100%")
+            .as_u32();
+        let (whole, fraction) = (pips / 10_000, pips % 10_000);
+        if fraction == 0 {
+            write!(f, "{}%", whole)
+        } else {
+            let fraction = format!("{:04}", fraction);
+            write!(f, "{}.{}%", whole, fraction.trim_end_matches('0'))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_units_agree() {
+        let amount = U256::from(1_000_000);
+        let ratios = [Ratio::from_percent(5), Ratio::from_bps(500), Ratio::from_pips(50_000), Bps::new(500).into()];
+        for ratio in ratios {
+            assert_eq!(ratio.checked_apply(amount), Some(50_000.into()));
+            assert_eq!(ratio, Ratio::from_percent(5));
+        }
+        assert!(Ratio::from_bps(1) < Ratio::from_pips(101));
+        assert!(matches!(Ratio::new(1.into(), U256::zero()), Err(MathError::DivisionByZero)));
+        assert!(serde_json::from_str::<Ratio>(r#"{"numerator":"0x1","denominator":"0x0"}"#).is_err());
+        let half: Percent = serde_json::from_str(r#"{"numerator":"0x1","denominator":"0x2"}"#).unwrap();
+        assert_eq!(half, Percent::new(50).unwrap());
+        assert!(serde_json::from_str::<Percent>(r#"{"numerator":"0x3","denominator":"0x2"}"#).is_err());
+    }
+
+    #[test]
+    fn test_apply_rounds_and_checks_overflow() {
+        let third = Ratio::new(1.into(), 3.into()).unwrap();
+        assert_eq!(third.checked_apply(10.into()), Some(3.into()));
+        assert_eq!(third.checked_apply_rounding_up(10.into()), Some(4.into()));
+        // Full precision: the intermediate product exceeds 256 bits
+        assert_eq!(third.checked_apply(U256::MAX), Some(U256::MAX / 3));
+
+        let double = Ratio::from_percent(200);
+        assert_eq!(double.checked_apply(U256::MAX), None);
+        assert_eq!(double.checked_apply_u128(u128::MAX), None);
+        assert_eq!(double.checked_apply_u128(7), Some(14));
+    }
+
+    #[test]
+    fn test_percent_bounds() {
+        assert_eq!(Percent::new(101), None);
+        assert_eq!(Percent::from_bps(Bps::MAX), Some(Percent::HUNDRED));
+        assert!(Percent::from_pips(FeePips::new(1_000_001)).is_none());
+        assert!(Percent::try_from(Ratio::from_percent(150)).is_err());
+
+        let slippage = Percent::from_bps(Bps::new(50)).unwrap();
+        assert_eq!(slippage.complement().of_u128(1_000_000), 995_000);
+        assert_eq!(Percent::HUNDRED.of(U256::MAX), U256::MAX);
+        assert_eq!(Percent::new(33).unwrap().of_rounding_up(10.into()), 4.into());
+        assert!(Percent::ZERO.is_zero() && Percent::HUNDRED.complement().is_zero());
+
+        assert_eq!(Percent::new(5).unwrap().to_string(), "5%");
+        assert_eq!(slippage.to_string(), "0.5%");
+        assert_eq!(Percent::from_pips(FeePips::new(1)).unwrap().to_string(), "0.0001%");
+    }
+}
@@ -1,12 +0,0 @@
-pub mod pool;
-pub mod math;
-pub mod state;
-pub mod hooks;
-pub mod flash_loan;
-pub mod pool_manager;
-
-pub use pool_manager::PoolManager;
-pub use flash_loan::*;
-pub use math::*;
-pub use state::*;
-pub use hooks::*; 
\ No newline at end of file
@@ -0,0 +1,242 @@
+use ethers::types::Address;
+use primitive_types::U256;
+
+use crate::core::{
+    math::{tick_math::TickMath, Result as MathResult},
+    pool_manager::ManagerPoolKey,
+    state::Pool,
+};
+
+use super::{Consultation, Oracle, OracleError, Result};
+
+/// Liquidity-weighted mean over the pools of a pair
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AggregatedTwap {
+    /// Mean tick of every pool weighted by its harmonic mean liquidity,
+    /// rounded towards negative infinity
+    pub arithmetic_mean_tick: i32,
+    /// Sum of the pools' harmonic mean liquidity, saturating at `u128::MAX`
+    pub total_harmonic_mean_liquidity: u128,
+    /// Mean tick and liquidity of each pool, in the order the pools were added
+    pub pools: Vec<(ManagerPoolKey, Consultation)>,
+}
+
+impl AggregatedTwap {
+    /// Square root price at the mean tick
+    pub fn sqrt_price_x96(&self) -> MathResult<U256> {
+        TickMath::get_sqrt_price_at_tick(self.arithmetic_mean_tick)
+    }
+}
+
+/// TWAP oracle over every pool of a single pair
+///
+/// Each fee tier of a pair is a separate pool with its own price. Weighting
+/// each pool's mean tick by its harmonic mean liquidity over the period keeps
+/// a thin pool, which is cheap to move, from dragging the aggregate price.
+/// Every tracked pool must have history for the whole period being consulted.
+#[derive(Debug, Clone)]
+pub struct MultiPoolTwapOracle {
+    token0: Address,
+    token1: Address,
+    /// Observations kept per pool
+    cardinality: usize,
+    /// Oracles by pool key, in the order the pools were added
+    oracles: Vec<(ManagerPoolKey, Oracle)>,
+}
+
+impl MultiPoolTwapOracle {
+    /// Creates an oracle for the pair that keeps `cardinality` observations per pool
+    pub fn new(token0: Address, token1: Address, cardinality: usize) -> Result<Self> {
+        if cardinality == 0 {
+            return Err(OracleError::ZeroCardinality);
+        }
+        Ok(Self { token0, token1, cardinality, oracles: Vec::new() })
+    }
+
+    /// Currencies of the pair
+    pub fn pair(&self) -> (Address, Address) {
+        (self.token0, self.token1)
+    }
+
+    /// Starts tracking a pool from its state at `timestamp`
+    pub fn add_pool(&mut self, key: &ManagerPoolKey, timestamp: u64, pool: &Pool) -> Result<()> {
+        if key.token0() != self.token0 || key.token1() != self.token1 {
+            return Err(OracleError::PairMismatch);
+        }
+        if self.oracle(key).is_some() {
+            return Err(OracleError::PoolAlreadyTracked);
+        }
+        let oracle = Oracle::new(self.cardinality, timestamp, pool.slot0.tick, pool.liquidity.as_u128())?;
+        self.oracles.push((key.clone(), oracle));
+        Ok(())
+    }
+
+    /// Stops tracking a pool, returning its oracle
+    pub fn remove_pool(&mut self, key: &ManagerPoolKey) -> Option<Oracle> {
+        let index = self.oracles.iter().position(|(tracked, _)| tracked == key)?;
+        Some(self.oracles.remove(index).1)
+    }
+
+    /// Oracle of a tracked pool
+    pub fn oracle(&self, key: &ManagerPoolKey) -> Option<&Oracle> {
+        self.oracles.iter().find(|(tracked, _)| tracked == key).map(|(_, oracle)| oracle)
+    }
+
+    /// Records the state of a tracked pool at `timestamp`
+    ///
+    /// Call after every operation that moves the pool's tick or liquidity.
+    pub fn record(&mut self, key: &ManagerPoolKey, timestamp: u64, pool: &Pool) -> Result<()> {
+        let (_, oracle) = self
+            .oracles
+            .iter_mut()
+            .find(|(tracked, _)| tracked == key)
+            .ok_or(OracleError::UnknownPool)?;
+        oracle.update(timestamp, pool.slot0.tick, pool.liquidity.as_u128())
+    }
+
+    /// Returns the liquidity-weighted mean tick over the `period` seconds before `now`
+    pub fn consult(&self, now: u64, period: u64) -> Result<AggregatedTwap> {
+        let pools = self
+            .oracles
+            .iter()
+            .map(|(key, oracle)| Ok((key.clone(), oracle.consult(now, period)?)))
+            .collect::<Result<Vec<_>>>()?;
+
+        let arithmetic_mean_tick = weighted_arithmetic_mean_tick(
+            pools.iter().map(|(_, consultation)| (consultation.arithmetic_mean_tick, consultation.harmonic_mean_liquidity)),
+        )?;
+        let total_harmonic_mean_liquidity = pools
+            .iter()
+            .fold(0u128, |total, (_, consultation)| total.saturating_add(consultation.harmonic_mean_liquidity));
+
+        Ok(AggregatedTwap { arithmetic_mean_tick, total_harmonic_mean_liquidity, pools })
+    }
+}
+
+/// Mean of `(tick, weight)` pairs weighted by `weight`, rounded towards negative infinity
+///
+/// Fails with `NoLiquidity` when the weights sum to zero.
+pub fn weighted_arithmetic_mean_tick(ticks: impl IntoIterator<Item = (i32, u128)>) -> Result<i32> {
+    // Positive and negative products are summed separately to stay unsigned
+    let mut positive = U256::zero();
+    let mut negative = U256::zero();
+    let mut total_weight = U256::zero();
+    for (tick, weight) in ticks {
+        let product = U256::from(tick.unsigned_abs()) * U256::from(weight);
+        if tick < 0 {
+            negative += product;
+        } else {
+            positive += product;
+        }
+        total_weight += U256::from(weight);
+    }
+    if total_weight.is_zero() {
+        return Err(OracleError::NoLiquidity);
+    }
+
+    let mean = if positive >= negative {
+        ((positive - negative) / total_weight).as_u64() as i64
+    } else {
+        let (quotient, remainder) = (negative - positive).div_mod(total_weight);
+        -(quotient.as_u64() as i64) - i64::from(!remainder.is_zero())
+    };
+    Ok(mean as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::math::{types::TickSpacing, FeePips, SqrtPrice, Liquidity};
+
+    fn key(fee: u32, tick_spacing: i32) -> ManagerPoolKey {
+        let tick_spacing = TickSpacing::new(tick_spacing).unwrap();
+        ManagerPoolKey::new(
+            Address::from_low_u64_be(1),
+            Address::from_low_u64_be(2),
+            fee,
+            tick_spacing,
+            Address::zero(),
+        ).unwrap()
+    }
+
+    fn pool(tick: i32, liquidity: u128) -> Pool {
+        let mut pool = Pool::new();
+        pool.initialize(SqrtPrice::ONE, FeePips::new(3000)).unwrap();
+        pool.slot0.tick = tick;
+        pool.liquidity = Liquidity::new(liquidity);
+        pool
+    }
+
+    #[test]
+    fn test_weighted_arithmetic_mean_tick() {
+        assert_eq!(weighted_arithmetic_mean_tick([(100, 1), (200, 3)]).unwrap(), 175);
+        assert_eq!(weighted_arithmetic_mean_tick([(-100, 1), (-200, 3)]).unwrap(), -175);
+        // -0.5 rounds down, 0.5 rounds down
+        assert_eq!(weighted_arithmetic_mean_tick([(-1, 1), (0, 1)]).unwrap(), -1);
+        assert_eq!(weighted_arithmetic_mean_tick([(1, 1), (0, 1)]).unwrap(), 0);
+        assert_eq!(weighted_arithmetic_mean_tick([(887272, u128::MAX), (-887272, u128::MAX)]).unwrap(), 0);
+        assert_eq!(weighted_arithmetic_mean_tick([(100, 0)]), Err(OracleError::NoLiquidity));
+    }
+
+    #[test]
+    fn test_consult_weights_pools_by_liquidity() {
+        let (low_fee, high_fee) = (key(500, 10), key(3000, 60));
+        let mut oracle = MultiPoolTwapOracle::new(low_fee.token0(), low_fee.token1(), 16).unwrap();
+        oracle.add_pool(&low_fee, 0, &pool(100, 3_000_000)).unwrap();
+        oracle.add_pool(&high_fee, 0, &pool(200, 1_000_000)).unwrap();
+
+        let twap = oracle.consult(600, 600).unwrap();
+        assert_eq!(twap.arithmetic_mean_tick, 125);
+        assert_eq!(twap.total_harmonic_mean_liquidity, 4_000_000);
+        assert_eq!(twap.pools.len(), 2);
+    }
+
+    #[test]
+    fn test_thin_pool_manipulation_is_diluted() {
+        let (deep, thin) = (key(500, 10), key(10000, 200));
+        let mut oracle = MultiPoolTwapOracle::new(deep.token0(), deep.token1(), 16).unwrap();
+        oracle.add_pool(&deep, 0, &pool(0, 1_000_000_000)).unwrap();
+        oracle.add_pool(&thin, 0, &pool(0, 1_000)).unwrap();
+
+        // The thin pool is pushed far away for the whole period
+        oracle.record(&thin, 0, &pool(50_000, 1_000)).unwrap();
+        let twap = oracle.consult(600, 600).unwrap();
+        assert_eq!(twap.arithmetic_mean_tick, 0);
+        assert_eq!(twap.pools[1].1.arithmetic_mean_tick, 50_000);
+    }
+
+    #[test]
+    fn test_consult_by_period() {
+        let key = key(3000, 60);
+        let mut oracle = MultiPoolTwapOracle::new(key.token0(), key.token1(), 16).unwrap();
+        oracle.add_pool(&key, 0, &pool(0, 1_000)).unwrap();
+        oracle.record(&key, 900, &pool(600, 1_000)).unwrap();
+
+        assert_eq!(oracle.consult(1_000, 100).unwrap().arithmetic_mean_tick, 600);
+        assert_eq!(oracle.consult(1_000, 1_000).unwrap().arithmetic_mean_tick, 60);
+        assert!(matches!(oracle.consult(1_000, 2_000), Err(OracleError::ObservationTooOld { .. })));
+    }
+
+    #[test]
+    fn test_pool_tracking() {
+        let key = key(3000, 60);
+        let mut oracle = MultiPoolTwapOracle::new(key.token0(), key.token1(), 16).unwrap();
+        assert_eq!(oracle.record(&key, 0, &pool(0, 1)), Err(OracleError::UnknownPool));
+        assert_eq!(oracle.consult(10, 10), Err(OracleError::NoLiquidity));
+
+        oracle.add_pool(&key, 0, &pool(0, 1)).unwrap();
+        assert_eq!(oracle.add_pool(&key, 0, &pool(0, 1)), Err(OracleError::PoolAlreadyTracked));
+
+        let other_pair = ManagerPoolKey::new(
+            key.token0(),
+            Address::from_low_u64_be(3),
+            key.fee(),
+            key.tick_spacing(),
+            key.hooks(),
+        ).unwrap();
+        assert_eq!(oracle.add_pool(&other_pair, 0, &pool(0, 1)), Err(OracleError::PairMismatch));
+
+        assert!(oracle.remove_pool(&key).is_some());
+        assert!(oracle.oracle(&key).is_none());
+    }
+}
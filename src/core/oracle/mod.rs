@@ -0,0 +1,45 @@
+//! Price oracles built from pool observations
+//!
+//! An [`Oracle`] accumulates a pool's tick and inverse liquidity over time,
+//! so the arithmetic mean tick and harmonic mean liquidity over any recorded
+//! period can be read back. A [`MultiPoolTwapOracle`] combines the oracles of
+//! several pools for the same pair into a liquidity-weighted mean tick.
+
+mod aggregator;
+mod observation;
+
+pub use aggregator::*;
+pub use observation::*;
+
+use thiserror::Error;
+
+/// Error types for oracle operations
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum OracleError {
+    #[error("Oracle cardinality must be at least 1")]
+    ZeroCardinality,
+
+    #[error("Observation at {timestamp} is older than the last observation at {last}")]
+    NonMonotonicTimestamp { last: u64, timestamp: u64 },
+
+    #[error("Observation target {target} is older than the oldest observation at {oldest}")]
+    ObservationTooOld { target: u64, oldest: u64 },
+
+    #[error("TWAP period must be greater than zero")]
+    ZeroPeriod,
+
+    #[error("Pool is not tracked by the oracle")]
+    UnknownPool,
+
+    #[error("Pool is already tracked by the oracle")]
+    PoolAlreadyTracked,
+
+    #[error("Pool is for a different pair than the oracle")]
+    PairMismatch,
+
+    #[error("No tracked pool had liquidity over the period")]
+    NoLiquidity,
+}
+
+/// Result type for oracle operations
+pub type Result<T> = std::result::Result<T, OracleError>;
@@ -0,0 +1,282 @@
+use std::collections::VecDeque;
+
+use primitive_types::{U256, U512};
+use serde::{Deserialize, Serialize};
+
+use super::{OracleError, Result};
+
+/// Accumulated pool state at a point in time
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Observation {
+    /// Time of the observation
+    pub timestamp: u64,
+    /// Sum of the active tick over every elapsed second
+    pub tick_cumulative: i128,
+    /// Sum of `2^128 / max(liquidity, 1)` over every elapsed second,
+    /// wrapping on overflow
+    pub seconds_per_liquidity_cumulative_x128: U256,
+}
+
+impl Observation {
+    /// Advances the observation to `timestamp` with `tick` and `liquidity`
+    /// active since the observation was taken
+    pub fn transform(&self, timestamp: u64, tick: i32, liquidity: u128) -> Self {
+        let elapsed = timestamp - self.timestamp;
+        let seconds_per_liquidity_x128 = (U256::from(elapsed) << 128) / U256::from(liquidity.max(1));
+        Self {
+            timestamp,
+            tick_cumulative: self.tick_cumulative + tick as i128 * elapsed as i128,
+            seconds_per_liquidity_cumulative_x128: self
+                .seconds_per_liquidity_cumulative_x128
+                .overflowing_add(seconds_per_liquidity_x128)
+                .0,
+        }
+    }
+}
+
+/// Mean pool state over a period
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Consultation {
+    /// Arithmetic mean tick, rounded towards negative infinity
+    pub arithmetic_mean_tick: i32,
+    /// Harmonic mean liquidity
+    pub harmonic_mean_liquidity: u128,
+}
+
+/// Bounded history of observations for a single pool
+///
+/// The tick and liquidity passed to `update` stay active until the next
+/// update, and reads later than the newest observation extrapolate them to
+/// the requested time. Once `cardinality` observations are stored the oldest
+/// one is dropped, limiting how far back the oracle can be consulted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Oracle {
+    /// Stored observations, oldest first
+    observations: VecDeque<Observation>,
+    /// Maximum number of stored observations
+    cardinality: usize,
+    /// Tick active since the newest observation
+    tick: i32,
+    /// Liquidity active since the newest observation
+    liquidity: u128,
+}
+
+impl Oracle {
+    /// Creates an oracle whose first observation is taken at `timestamp`
+    pub fn new(cardinality: usize, timestamp: u64, tick: i32, liquidity: u128) -> Result<Self> {
+        if cardinality == 0 {
+            return Err(OracleError::ZeroCardinality);
+        }
+        let mut observations = VecDeque::with_capacity(cardinality);
+        observations.push_back(Observation { timestamp, ..Default::default() });
+        Ok(Self { observations, cardinality, tick, liquidity })
+    }
+
+    /// Maximum number of stored observations
+    pub fn cardinality(&self) -> usize {
+        self.cardinality
+    }
+
+    /// Stored observations, oldest first
+    pub fn observations(&self) -> impl Iterator<Item = &Observation> {
+        self.observations.iter()
+    }
+
+    /// Newest stored observation
+    pub fn latest(&self) -> &Observation {
+        self.observations.back().expect("oracle always holds an observation")
+    }
+
+    /// Oldest stored observation
+    pub fn oldest(&self) -> &Observation {
+        self.observations.front().expect("oracle always holds an observation")
+    }
+
+    /// Records that the pool moved to `tick` and `liquidity` at `timestamp`
+    ///
+    /// Several updates at the same timestamp keep a single observation, and
+    /// only the last tick and liquidity are carried forward.
+    pub fn update(&mut self, timestamp: u64, tick: i32, liquidity: u128) -> Result<()> {
+        let latest = *self.latest();
+        if timestamp < latest.timestamp {
+            return Err(OracleError::NonMonotonicTimestamp { last: latest.timestamp, timestamp });
+        }
+        if timestamp > latest.timestamp {
+            if self.observations.len() == self.cardinality {
+                self.observations.pop_front();
+            }
+            self.observations.push_back(latest.transform(timestamp, self.tick, self.liquidity));
+        }
+        self.tick = tick;
+        self.liquidity = liquidity;
+        Ok(())
+    }
+
+    /// Returns the accumulators `seconds_ago` seconds before `now`
+    ///
+    /// `now` must not be earlier than the newest observation.
+    pub fn observe(&self, now: u64, seconds_ago: u64) -> Result<Observation> {
+        let latest = self.latest();
+        if now < latest.timestamp {
+            return Err(OracleError::NonMonotonicTimestamp { last: latest.timestamp, timestamp: now });
+        }
+        let target = now.checked_sub(seconds_ago).ok_or(OracleError::ObservationTooOld {
+            target: 0,
+            oldest: self.oldest().timestamp,
+        })?;
+
+        if target >= latest.timestamp {
+            return Ok(latest.transform(target, self.tick, self.liquidity));
+        }
+        if target < self.oldest().timestamp {
+            return Err(OracleError::ObservationTooOld { target, oldest: self.oldest().timestamp });
+        }
+
+        // Find the first observation after the target; the one before it is at or before the target
+        let after_index = self.observations.partition_point(|observation| observation.timestamp <= target);
+        let before = self.observations[after_index - 1];
+        let after = self.observations[after_index];
+        if before.timestamp == target {
+            return Ok(before);
+        }
+
+        // The tick and liquidity are constant between two observations, so
+        // the accumulators grow linearly across the gap
+        let gap = after.timestamp - before.timestamp;
+        let into_gap = target - before.timestamp;
+        let tick_delta = (after.tick_cumulative - before.tick_cumulative) / gap as i128;
+        let seconds_per_liquidity_delta = after
+            .seconds_per_liquidity_cumulative_x128
+            .overflowing_sub(before.seconds_per_liquidity_cumulative_x128)
+            .0;
+        let seconds_per_liquidity_into_gap = U512::from(seconds_per_liquidity_delta) * U512::from(into_gap) / U512::from(gap);
+        Ok(Observation {
+            timestamp: target,
+            tick_cumulative: before.tick_cumulative + tick_delta * into_gap as i128,
+            seconds_per_liquidity_cumulative_x128: before
+                .seconds_per_liquidity_cumulative_x128
+                .overflowing_add(U256::try_from(seconds_per_liquidity_into_gap).expect("bounded by the gap delta"))
+                .0,
+        })
+    }
+
+    /// Returns the mean tick and liquidity over the `period` seconds before `now`
+    pub fn consult(&self, now: u64, period: u64) -> Result<Consultation> {
+        if period == 0 {
+            return Err(OracleError::ZeroPeriod);
+        }
+        let start = self.observe(now, period)?;
+        let end = self.observe(now, 0)?;
+
+        let tick_delta = end.tick_cumulative - start.tick_cumulative;
+        let mut arithmetic_mean_tick = tick_delta / period as i128;
+        if tick_delta < 0 && tick_delta % period as i128 != 0 {
+            arithmetic_mean_tick -= 1;
+        }
+
+        let seconds_per_liquidity_delta = end
+            .seconds_per_liquidity_cumulative_x128
+            .overflowing_sub(start.seconds_per_liquidity_cumulative_x128)
+            .0;
+        let harmonic_mean_liquidity = if seconds_per_liquidity_delta.is_zero() {
+            u128::MAX
+        } else {
+            let liquidity = (U512::from(period) << 128) / U512::from(seconds_per_liquidity_delta);
+            if liquidity > U512::from(u128::MAX) { u128::MAX } else { liquidity.low_u128() }
+        };
+
+        Ok(Consultation {
+            arithmetic_mean_tick: arithmetic_mean_tick as i32,
+            harmonic_mean_liquidity,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_consult_constant_state() {
+        let oracle = Oracle::new(8, 1_000, 120, 5_000).unwrap();
+        let consultation = oracle.consult(1_600, 600).unwrap();
+        assert_eq!(consultation.arithmetic_mean_tick, 120);
+        assert_eq!(consultation.harmonic_mean_liquidity, 5_000);
+    }
+
+    #[test]
+    fn test_consult_time_weights_ticks() {
+        let mut oracle = Oracle::new(8, 0, 100, 1_000).unwrap();
+        oracle.update(300, -200, 1_000).unwrap();
+        // 300s at tick 100 and 100s at tick -200
+        let consultation = oracle.consult(400, 400).unwrap();
+        assert_eq!(consultation.arithmetic_mean_tick, 25);
+
+        // Shorter windows only see the later segment
+        assert_eq!(oracle.consult(400, 100).unwrap().arithmetic_mean_tick, -200);
+        assert_eq!(oracle.consult(400, 200).unwrap().arithmetic_mean_tick, -50);
+    }
+
+    #[test]
+    fn test_consult_rounds_towards_negative_infinity() {
+        let mut oracle = Oracle::new(8, 0, -1, 1_000).unwrap();
+        oracle.update(1, 0, 1_000).unwrap();
+        // Mean tick is -0.5
+        assert_eq!(oracle.consult(2, 2).unwrap().arithmetic_mean_tick, -1);
+    }
+
+    #[test]
+    fn test_harmonic_mean_liquidity() {
+        let mut oracle = Oracle::new(8, 0, 0, 1_000).unwrap();
+        oracle.update(100, 0, 3_000).unwrap();
+        // 2 / (1/1000 + 1/3000) = 1500
+        assert_eq!(oracle.consult(200, 200).unwrap().harmonic_mean_liquidity, 1_500);
+    }
+
+    #[test]
+    fn test_interpolates_between_observations() {
+        let mut oracle = Oracle::new(8, 0, 10, 1_000).unwrap();
+        oracle.update(100, 20, 1_000).unwrap();
+        oracle.update(200, 30, 1_000).unwrap();
+
+        let observation = oracle.observe(200, 150).unwrap();
+        assert_eq!(observation.timestamp, 50);
+        assert_eq!(observation.tick_cumulative, 500);
+        assert_eq!(oracle.observe(200, 100).unwrap(), oracle.observations().nth(1).copied().unwrap());
+    }
+
+    #[test]
+    fn test_same_timestamp_updates_keep_one_observation() {
+        let mut oracle = Oracle::new(8, 0, 10, 1_000).unwrap();
+        oracle.update(100, 20, 1_000).unwrap();
+        oracle.update(100, 30, 1_000).unwrap();
+        assert_eq!(oracle.observations().count(), 2);
+        // Only the last tick at the timestamp is carried forward
+        assert_eq!(oracle.consult(200, 100).unwrap().arithmetic_mean_tick, 30);
+    }
+
+    #[test]
+    fn test_cardinality_limits_history() {
+        let mut oracle = Oracle::new(2, 0, 10, 1_000).unwrap();
+        oracle.update(100, 20, 1_000).unwrap();
+        oracle.update(200, 30, 1_000).unwrap();
+        assert_eq!(oracle.oldest().timestamp, 100);
+        assert_eq!(
+            oracle.consult(200, 150),
+            Err(OracleError::ObservationTooOld { target: 50, oldest: 100 })
+        );
+        assert!(oracle.consult(200, 100).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_inputs() {
+        assert_eq!(Oracle::new(0, 0, 0, 0), Err(OracleError::ZeroCardinality));
+
+        let mut oracle = Oracle::new(4, 100, 0, 0).unwrap();
+        assert_eq!(
+            oracle.update(50, 0, 0),
+            Err(OracleError::NonMonotonicTimestamp { last: 100, timestamp: 50 })
+        );
+        assert_eq!(oracle.consult(200, 0), Err(OracleError::ZeroPeriod));
+    }
+}
@@ -1,48 +1,21 @@
 use crate::core::{
-    state::{Pool, Result as StateResult},
+    math::FeePips,
+    state::Pool,
 };
 
-use super::{Result, PoolError};
+use super::Result;
 
 /// Calculate LP fee from the given fee parameter
-pub fn get_lp_fee(fee: u32) -> u32 {
+pub fn get_lp_fee(fee: u32) -> FeePips {
     // In Solidity version, if the fee is a dynamic fee (highest bit set),
     // the LP fee is the lower 23 bits
     if crate::core::hooks::is_dynamic_fee(fee) {
-        fee & 0x7FFFFF // Clear the highest bit
+        FeePips::new(fee & 0x7FFFFF) // Clear the highest bit
     } else {
-        fee // Static fee
+        FeePips::new(fee) // Static fee
     }
 }
 
-/// Calculate protocol fee from the given fee parameter
-pub fn get_protocol_fee(fee: u32) -> u32 {
-    // In Solidity version, protocol fee is stored in bits 16-23
-    (fee >> 16) & 0xFF
-}
-
-/// Set protocol fee for a pool
-pub fn set_protocol_fee(
-    pool: &mut Pool,
-    protocol_fee: u32,
-) -> Result<()> {
-    // Update protocol fee in the pool
-    // In a real implementation, this would call a method on the pool
-    // For now, we just return success
-    Ok(())
-}
-
-/// Set LP fee for a pool
-pub fn set_lp_fee(
-    pool: &mut Pool,
-    lp_fee: u32,
-) -> Result<()> {
-    // Update LP fee in the pool
-    // In a real implementation, this would call a method on the pool
-    // For now, we just return success
-    Ok(())
-}
-
 /// Calculate fee growth inside a tick range
 pub fn get_fee_growth_inside(
     pool: &Pool,
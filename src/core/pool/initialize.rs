@@ -25,27 +25,30 @@ pub fn initialize_pool(
     let lp_fee = get_initial_lp_fee(key.fee);
     
     // Call hook before initialize if available
-    let hook_address = Address::from_slice(&key.hooks);
+    let hook_address = key.hooks;
     if hook_address != Address::zero() {
         if let Some(hook) = hook_registry.get_hook_mut(&key.hooks) {
-            hook.before_initialize(
-                sender.0,
+            let result = hook.before_initialize(
+                sender,
                 key,
                 sqrt_price_x96,
                 &[]  // Empty hook data
             ).map_err(PoolError::StateError)?;
+            if let Some(reason) = result.rejection {
+                return Err(PoolError::RejectedByHook(reason));
+            }
         }
     }
     
     // Initialize pool
-    let tick = pool.initialize(sqrt_price_x96, key.fee)
+    let tick = pool.initialize(sqrt_price_x96, super::get_initial_lp_fee(key.fee))
         .map_err(PoolError::StateError)?;
     
     // Call hook after initialize if available
     if hook_address != Address::zero() {
         if let Some(hook) = hook_registry.get_hook_mut(&key.hooks) {
             hook.after_initialize(
-                sender.0,
+                sender,
                 key,
                 sqrt_price_x96,
                 tick,
@@ -55,4 +58,72 @@ pub fn initialize_pool(
     }
     
     Ok(tick)
-} 
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{
+        hooks::{BeforeHookResult, HookFlags, HookWithReturns, InitializeRejection},
+        math::types::TickSpacing,
+        state::StateError,
+    };
+
+    /// Rejects pools of one pair of currencies and fails on every other
+    /// tick spacing than 60
+    struct PairHook {
+        rejected: (Address, Address),
+    }
+
+    impl Hook for PairHook {
+        fn before_initialize(
+            &mut self,
+            _sender: Address,
+            key: &PoolKey,
+            _sqrt_price_x96: SqrtPrice,
+            _hook_data: &[u8],
+        ) -> StateResult<BeforeHookResult> {
+            if key.tick_spacing.get() != 60 {
+                return Err(StateError::InvalidPrice);
+            }
+            if (key.token0, key.token1) == self.rejected {
+                let (token0, token1) = self.rejected;
+                return Ok(BeforeHookResult::reject(InitializeRejection::UnsupportedCurrencies { token0, token1 }));
+            }
+            Ok(BeforeHookResult::default())
+        }
+    }
+
+    impl HookWithReturns for PairHook {}
+
+    #[test]
+    fn test_hook_vetoes_initialization_with_a_typed_reason() {
+        let hooks = HookFlags::new(HookFlags::BEFORE_INITIALIZE).apply_to_address(Address::repeat_byte(0xA0));
+        let rejected = (Address::from_low_u64_be(1), Address::from_low_u64_be(2));
+        let mut registry = HookRegistry::new();
+        registry.register_hook(hooks, Box::new(PairHook { rejected }));
+        let key = |token0: u64, tick_spacing: i32| PoolKey {
+            token0: Address::from_low_u64_be(token0),
+            token1: Address::from_low_u64_be(2),
+            fee: 3000,
+            tick_spacing: TickSpacing::new(tick_spacing).unwrap(),
+            hooks,
+            extension_data: Vec::new(),
+        };
+
+        let mut pool = Pool::new();
+        let result = initialize_pool(&mut pool, &key(1, 60), SqrtPrice::ONE, &mut registry, Address::zero());
+        assert!(matches!(
+            result,
+            Err(PoolError::RejectedByHook(InitializeRejection::UnsupportedCurrencies { token0, token1 }))
+                if (token0, token1) == rejected
+        ));
+        assert!(pool.slot0.sqrt_price_x96.0.is_zero());
+
+        // A hook that fails is not a veto
+        let result = initialize_pool(&mut pool, &key(0, 10), SqrtPrice::ONE, &mut registry, Address::zero());
+        assert!(matches!(result, Err(PoolError::StateError(StateError::InvalidPrice))));
+
+        assert_eq!(initialize_pool(&mut pool, &key(0, 60), SqrtPrice::ONE, &mut registry, Address::zero()).unwrap(), 0);
+    }
+}
@@ -19,19 +19,19 @@ pub fn modify_liquidity(
     hook_data: &[u8],
 ) -> Result<(BalanceDelta, BalanceDelta)> {
     // Call hook before modifying liquidity if available
-    let hook_address = Address::from_slice(&key.hooks);
+    let hook_address = key.hooks;
     if hook_address != Address::zero() {
         if let Some(hook) = hook_registry.get_hook_mut(&key.hooks) {
             let hook_result = if params.liquidity_delta > 0 {
                 hook.before_add_liquidity(
-                    sender.0,
+                    sender,
                     key,
                     params,
                     hook_data
                 )
             } else {
                 hook.before_remove_liquidity(
-                    sender.0,
+                    sender,
                     key,
                     params,
                     hook_data
@@ -46,11 +46,14 @@ pub fn modify_liquidity(
     }
     
     // Modify liquidity in the pool
-    let (principal_delta, fees_accrued) = pool.modify_liquidity(
+    let position_key = params.position_key();
+    let (principal_delta, fees_accrued) = pool.modify_position(
+        position_key.owner,
         params.tick_lower,
         params.tick_upper,
         params.liquidity_delta,
-        key.tick_spacing
+        key.tick_spacing,
+        position_key.salt,
     ).map_err(PoolError::StateError)?;
     
     // Combine principal delta and fees for the caller
@@ -62,7 +65,7 @@ pub fn modify_liquidity(
         if let Some(hook) = hook_registry.get_hook_mut(&key.hooks) {
             let hook_result = if params.liquidity_delta > 0 {
                 hook.after_add_liquidity(
-                    sender.0,
+                    sender,
                     key,
                     params,
                     &caller_delta,
@@ -71,7 +74,7 @@ pub fn modify_liquidity(
                 )
             } else {
                 hook.after_remove_liquidity(
-                    sender.0,
+                    sender,
                     key,
                     params,
                     &caller_delta,
@@ -21,11 +21,11 @@ pub fn donate(
     hook_data: &[u8],
 ) -> Result<()> {
     // Call hook before donate if available
-    let hook_address = Address::from_slice(&key.hooks);
+    let hook_address = key.hooks;
     if hook_address != Address::zero() {
         if let Some(hook) = hook_registry.get_hook_mut(&key.hooks) {
             let hook_result = hook.before_donate(
-                sender.0,
+                sender,
                 key,
                 amount0,
                 amount1,
@@ -46,7 +46,7 @@ pub fn donate(
     if hook_address != Address::zero() {
         if let Some(hook) = hook_registry.get_hook_mut(&key.hooks) {
             let hook_result = hook.after_donate(
-                sender.0,
+                sender,
                 key,
                 amount0,
                 amount1,
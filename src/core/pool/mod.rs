@@ -4,6 +4,7 @@ use crate::core::{
         tick_math::TickMath,
         sqrt_price_math::SqrtPriceMath,
         swap_math::SwapMath,
+        FeePips,
     },
     state::{
         Pool, BalanceDelta, StateError, Result as StateResult,
@@ -50,65 +51,48 @@ pub enum PoolError {
     #[error("Currencies out of order: token0 {0:?}, token1 {1:?}")]
     CurrenciesOutOfOrderOrEqual(Address, Address),
     
-    #[error("Tick spacing too large: {0}")]
-    TickSpacingTooLarge(i32),
-    
-    #[error("Tick spacing too small: {0}")]
-    TickSpacingTooSmall(i32),
-    
     #[error("Swap amount cannot be zero")]
     SwapAmountCannotBeZero,
     
     #[error("Currency not settled")]
     CurrencyNotSettled,
+    
+    #[error("Rejected by hook: {0}")]
+    RejectedByHook(crate::core::hooks::InitializeRejection),
 }
 
 /// Result type for pool operations
 pub type Result<T> = std::result::Result<T, PoolError>;
 
-/// Constants for tick spacing
-pub const MAX_TICK_SPACING: i32 = 16384;
-pub const MIN_TICK_SPACING: i32 = 1;
-
 /// Helper function to validate pool key
 pub fn validate_pool_key(key: &PoolKey, hook_registry: &HookRegistry) -> Result<()> {
-    // Check tick spacing
-    if key.tick_spacing > MAX_TICK_SPACING {
-        return Err(PoolError::TickSpacingTooLarge(key.tick_spacing));
-    }
-    if key.tick_spacing < MIN_TICK_SPACING {
-        return Err(PoolError::TickSpacingTooSmall(key.tick_spacing));
-    }
+    // Tick spacing is range checked by the `TickSpacing` type
     
     // Check currencies are in order
-    let token0 = Address::from_slice(&key.token0);
-    let token1 = Address::from_slice(&key.token1);
-    
-    if token0 >= token1 {
-        return Err(PoolError::CurrenciesOutOfOrderOrEqual(token0, token1));
+    if key.token0 >= key.token1 {
+        return Err(PoolError::CurrenciesOutOfOrderOrEqual(key.token0, key.token1));
     }
     
     // Check hook address is valid
-    let hook_address = Address::from_slice(&key.hooks);
-    if hook_address != Address::zero() {
+    if !key.hooks.is_zero() {
         // Validate hook address for the given fee
         hook_registry.validate_hook_address_for_fee(&key.hooks, key.fee)
             .map_err(PoolError::HookError)?;
     } else if crate::core::hooks::is_dynamic_fee(key.fee) {
         // If no hook address but dynamic fee, that's an error
-        return Err(PoolError::HookError(crate::core::hooks::HookError::HookAddressNotValid([0u8; 20])));
+        return Err(PoolError::HookError(crate::core::hooks::HookError::HookAddressNotValid(Address::zero())));
     }
     
     Ok(())
 }
 
 /// Helper function to get initial LP fee from fee parameter
-pub fn get_initial_lp_fee(fee: u32) -> u32 {
+pub fn get_initial_lp_fee(fee: u32) -> FeePips {
     // In Solidity version, if the fee is a dynamic fee (highest bit set),
     // the initial LP fee is the lower 23 bits
     if crate::core::hooks::is_dynamic_fee(fee) {
-        fee & 0x7FFFFF // Clear the highest bit
+        FeePips::new(fee & 0x7FFFFF) // Clear the highest bit
     } else {
-        fee // Static fee
+        FeePips::new(fee) // Static fee
     }
 }
@@ -1,17 +1,23 @@
 use crate::core::{
-    math::types::SqrtPrice,
-    state::{Pool, BalanceDelta, Result as StateResult},
+    state::{Pool, BalanceDelta, StateError},
     hooks::{
-        Hook, HookRegistry, BeforeHookResult, AfterHookResult, BeforeSwapDelta,
+        HookRegistry, HookFlags, HookError, HookCallback, BeforeSwapDelta,
         hook_interface::{PoolKey, SwapParams},
     },
 };
 
 use ethers::types::Address;
-use primitive_types::U256;
 use super::{Result, PoolError};
 
-/// Execute a swap on the pool
+/// Execute a swap on the pool, returning the caller's delta
+///
+/// As in v4, `before_swap` can return a delta in the specified and the
+/// unspecified currency, the specified part adjusting the amount swapped,
+/// while `after_swap` can only return one in the unspecified currency. A hook
+/// returns deltas in token order from its [`Hook`](crate::core::hooks::Hook)
+/// callbacks or oriented by the swap from its
+/// [`HookWithReturns`](crate::core::hooks::HookWithReturns) ones, which add
+/// up. The hook's deltas are taken from the caller's.
 pub fn swap(
     pool: &mut Pool,
     key: &PoolKey,
@@ -24,62 +30,227 @@ pub fn swap(
     if params.amount_specified == 0 {
         return Err(PoolError::SwapAmountCannotBeZero);
     }
-    
+
+    let hook_address = key.hooks;
+    let flags = HookFlags::from_address(hook_address);
+    let not_permitted = |callback| PoolError::HookError(HookError::DeltaNotPermitted { hook: hook_address, callback });
+
     // Call hook before swap if available
     let mut amount_to_swap = params.amount_specified;
-    let mut before_swap_delta = BeforeSwapDelta::default();
+    let mut hook_delta = BeforeSwapDelta::default();
     let mut lp_fee_override = None;
-    
-    let hook_address = Address::from_slice(&key.hooks);
+
     if hook_address != Address::zero() {
-        if let Some(hook) = hook_registry.get_hook_mut(&key.hooks) {
-            let hook_result = hook.before_swap(
-                sender.0,
-                key,
-                params,
-                hook_data
-            ).map_err(PoolError::StateError)?;
-            
-            if let BeforeHookResult { amount: Some(amount), delta: Some(delta_val), fee_override } = hook_result {
+        if let Some(hook) = hook_registry.get_hook_mut(&hook_address) {
+            let hook_result = hook.before_swap(sender, key, params, hook_data)?;
+            if let Some(amount) = hook_result.amount {
                 amount_to_swap = amount;
-                if params.zero_for_one {
-                    before_swap_delta = BeforeSwapDelta { delta_specified: delta_val.amount0, delta_unspecified: delta_val.amount1 };
-                } else {
-                    before_swap_delta = BeforeSwapDelta { delta_specified: delta_val.amount1, delta_unspecified: delta_val.amount0 };
-                }
-                lp_fee_override = fee_override;
             }
+            lp_fee_override = hook_result.fee_override;
+
+            let returned = if flags.is_enabled(HookFlags::BEFORE_SWAP_RETURNS_DELTA) {
+                hook.before_swap_with_delta(sender, key, params, hook_data)?
+            } else {
+                BeforeSwapDelta::default()
+            };
+            let delta = BeforeSwapDelta::combine(&hook_result, returned, params)?;
+            if !flags.is_enabled(HookFlags::BEFORE_SWAP_RETURNS_DELTA) && delta != BeforeSwapDelta::default() {
+                return Err(not_permitted(HookCallback::BeforeSwap));
+            }
+            amount_to_swap = delta.apply_to_amount(amount_to_swap)?;
+            hook_delta = delta;
         }
     }
-    
+
     // Execute swap
-    let (swap_delta, protocol_fee) = pool.swap(
+    let (swap_delta, _) = pool.swap(
         amount_to_swap,
         params.sqrt_price_limit_x96,
         params.zero_for_one,
         key.tick_spacing,
         lp_fee_override,
-    ).map_err(PoolError::StateError)?;
-    
+    )?;
+
     // Call hook after swap if available
-    let mut hook_delta = BalanceDelta::default();
-    let result_delta = swap_delta;
-    
     if hook_address != Address::zero() {
-        if let Some(hook) = hook_registry.get_hook_mut(&key.hooks) {
-            let hook_result = hook.after_swap(
-                sender.0,
-                key,
-                params,
-                &swap_delta,
-                hook_data
-            ).map_err(PoolError::StateError)?;
-            
-            if let AfterHookResult { delta: Some(delta) } = hook_result {
-                hook_delta = delta;
+        if let Some(hook) = hook_registry.get_hook_mut(&hook_address) {
+            let hook_result = hook.after_swap(sender, key, params, &swap_delta, hook_data)?;
+
+            let returns_delta = flags.is_enabled(HookFlags::AFTER_SWAP_RETURNS_DELTA);
+            let returned = if returns_delta {
+                hook.after_swap_with_delta(sender, key, params, &swap_delta, hook_data)?
+            } else {
+                0
+            };
+            // Hook errors surface as hook errors, like the permission check's
+            let (folded, delta_unspecified) = hook_delta
+                .fold_after_swap(&hook_result, returned, params, hook_address)
+                .map_err(|error| match error {
+                    StateError::Hook(error) => PoolError::HookError(error),
+                    error => PoolError::StateError(error),
+                })?;
+            if !returns_delta && delta_unspecified != 0 {
+                return Err(not_permitted(HookCallback::AfterSwap));
+            }
+            hook_delta = folded;
+        }
+    }
+
+    Ok(hook_delta.caller_delta(swap_delta, params)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{
+        hooks::{AfterHookResult, BeforeHookResult, Hook, HookWithReturns},
+        math::{types::{SqrtPrice, TickSpacing}, FeePips},
+        state::Result as StateResult,
+    };
+
+    /// Returns swap deltas in token order, from its `Hook` callbacks
+    struct TokenOrderHook {
+        before: BalanceDelta,
+        after: BalanceDelta,
+    }
+
+    impl Hook for TokenOrderHook {
+        fn before_swap(
+            &mut self,
+            _sender: Address,
+            _key: &PoolKey,
+            _params: &SwapParams,
+            _hook_data: &[u8],
+        ) -> StateResult<BeforeHookResult> {
+            Ok(BeforeHookResult { delta: Some(self.before), ..Default::default() })
+        }
+
+        fn after_swap(
+            &mut self,
+            _sender: Address,
+            _key: &PoolKey,
+            _params: &SwapParams,
+            _delta: &BalanceDelta,
+            _hook_data: &[u8],
+        ) -> StateResult<AfterHookResult> {
+            Ok(AfterHookResult { delta: Some(self.after) })
+        }
+    }
+
+    impl HookWithReturns for TokenOrderHook {}
+
+    /// Returns specified and unspecified swap deltas, from its
+    /// `HookWithReturns` callbacks
+    struct SpecifiedOrderHook {
+        before: BeforeSwapDelta,
+        after: i128,
+    }
+
+    impl Hook for SpecifiedOrderHook {}
+
+    impl HookWithReturns for SpecifiedOrderHook {
+        fn before_swap_with_delta(
+            &mut self,
+            _sender: Address,
+            _key: &PoolKey,
+            _params: &SwapParams,
+            _hook_data: &[u8],
+        ) -> StateResult<BeforeSwapDelta> {
+            Ok(self.before)
+        }
+
+        fn after_swap_with_delta(
+            &mut self,
+            _sender: Address,
+            _key: &PoolKey,
+            _params: &SwapParams,
+            _delta: &BalanceDelta,
+            _hook_data: &[u8],
+        ) -> StateResult<i128> {
+            Ok(self.after)
+        }
+    }
+
+    const ALL_SWAP_FLAGS: u16 = HookFlags::BEFORE_SWAP
+        | HookFlags::BEFORE_SWAP_RETURNS_DELTA
+        | HookFlags::AFTER_SWAP
+        | HookFlags::AFTER_SWAP_RETURNS_DELTA;
+
+    fn pool_key(hooks: Address) -> PoolKey {
+        PoolKey {
+            token0: Address::from_low_u64_be(1),
+            token1: Address::from_low_u64_be(2),
+            fee: 3000,
+            tick_spacing: TickSpacing::new(60).unwrap(),
+            hooks,
+            extension_data: Vec::new(),
+        }
+    }
+
+    fn pool_with_liquidity() -> Pool {
+        let mut pool = Pool::new();
+        pool.initialize(SqrtPrice::ONE, FeePips::new(3000)).unwrap();
+        pool.modify_position([1u8; 20], -600, 600, 1_000_000_000, TickSpacing::new(60).unwrap(), [0u8; 32]).unwrap();
+        pool
+    }
+
+    fn swap_params(zero_for_one: bool, amount_specified: i128) -> SwapParams {
+        let limit = if zero_for_one { -6000 } else { 6000 };
+        SwapParams {
+            zero_for_one,
+            amount_specified,
+            sqrt_price_limit_x96: SqrtPrice::from_tick(limit).unwrap(),
+        }
+    }
+
+    /// Swaps through a hook with every swap flag, giving the caller's delta
+    fn swap_through(hook: Box<dyn HookWithReturns>, params: &SwapParams) -> Result<BalanceDelta> {
+        let hooks = HookFlags::new(ALL_SWAP_FLAGS).apply_to_address(Address::repeat_byte(0xA0));
+        let mut registry = HookRegistry::new();
+        registry.register_hook(hooks, hook);
+        swap(&mut pool_with_liquidity(), &pool_key(hooks), params, &mut registry, Address::zero(), &[])
+    }
+
+    #[test]
+    fn test_hook_swap_deltas_follow_the_specified_currency() {
+        // Before the swap the hook takes 100 of the specified currency and 20
+        // of the other, after it 5 more of the other
+        let before = BeforeSwapDelta { delta_specified: 100, delta_unspecified: 20 };
+        let total = BeforeSwapDelta { delta_specified: 100, delta_unspecified: 25 };
+        for zero_for_one in [true, false] {
+            for amount_specified in [-10_000, 10_000] {
+                let params = swap_params(zero_for_one, amount_specified);
+                let after = BeforeSwapDelta { delta_specified: 0, delta_unspecified: 5 }.to_balance_delta(&params);
+                // The pool swaps the amount less the hook's specified delta
+                let (pool_delta, _) = pool_with_liquidity()
+                    .swap(amount_specified + 100, params.sqrt_price_limit_x96, zero_for_one, TickSpacing::new(60).unwrap(), None)
+                    .unwrap();
+                let hook_delta = total.to_balance_delta(&params);
+                let expected = BalanceDelta::new(pool_delta.amount0 - hook_delta.amount0, pool_delta.amount1 - hook_delta.amount1);
+
+                let hooks: [Box<dyn HookWithReturns>; 2] = [
+                    Box::new(TokenOrderHook { before: before.to_balance_delta(&params), after }),
+                    Box::new(SpecifiedOrderHook { before, after: 5 }),
+                ];
+                for hook in hooks {
+                    let delta = swap_through(hook, &params).unwrap();
+                    assert_eq!((delta.amount0, delta.amount1), (expected.amount0, expected.amount1), "zero_for_one {zero_for_one}, amount {amount_specified}");
+                    // The caller still swaps the amount they specified
+                    let specified = BeforeSwapDelta::from_balance_delta(delta, &params).delta_specified;
+                    assert_eq!(specified, amount_specified, "zero_for_one {zero_for_one}, amount {amount_specified}");
+                }
             }
         }
     }
-    
-    Ok(result_delta)
-} 
\ No newline at end of file
+
+    #[test]
+    fn test_after_swap_cannot_return_a_specified_delta() {
+        let params = swap_params(true, -10_000);
+        let hook = TokenOrderHook {
+            before: BalanceDelta::default(),
+            after: BeforeSwapDelta { delta_specified: 5, delta_unspecified: 0 }.to_balance_delta(&params),
+        };
+        let result = swap_through(Box::new(hook), &params);
+        assert!(matches!(result, Err(PoolError::HookError(HookError::SpecifiedDeltaAfterSwap { .. }))));
+    }
+}
@@ -1,61 +1,672 @@
-use std::collections::HashMap;
+use std::collections::{hash_map::Entry, HashMap, VecDeque};
+use std::fmt;
+use std::sync::Arc;
+use std::str::FromStr;
 use primitive_types::U256;
-use ethers::types::Address;
+use ethers::{types::Address, utils::keccak256};
+use rayon::prelude::*;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{json, Value};
 
 use crate::core::{
-    math::types::SqrtPrice,
+    math::{types::{Percent, SqrtPrice, TickSpacing}, TickMath, FixedPoint96, Bps, FeePips, SqrtPriceTable},
+    pool::{get_initial_lp_fee, PoolError},
     state::{
+        AuxiliaryFees,
         Pool,
+        MemoryUsage,
+        Position,
         PositionKey,
-        PositionManager,
+        FeeGrowthSnapshot,
+        PoolStats,
         Result as StateResult,
         StateError,
         BalanceDelta,
+        SwapConfig,
+        SwapReport,
     },
     flash_loan::{
         FlashLoanManager,
         FlashLoanCallback,
+        FlashFeeRecipient,
         Currency,
         FlashLoanError,
+        CurrencyPolicy,
+        UnlockObserver,
+        UnlockObserverId,
+        DeltaJournal,
+        DeltaReason,
+        TokenLedger,
+        POOL_MANAGER_ADDRESS,
     },
     hooks::{
         Hook,
+        HookCallback,
+        Clock,
+        HookContext,
+        HookError,
+        HookFlags,
+        HookFeeCache,
         HookRegistry,
+        HookPermissions,
         hook_interface::{PoolKey as HookPoolKey, ModifyLiquidityParams, SwapParams},
-        BeforeHookResult, AfterHookResult,
+        fee_cache::FeeQuery,
+        BeforeHookResult, AfterHookResult, BeforeSwapDelta, InitializeReport, LiquiditySeed,
     },
+    amounts::{SwapAmount, SwapFlows},
+    state_view::StateView,
 };
+use crate::tokens::{erc6909::{ERC6909, ERC6909Error}, CurrencyDecimals};
+use crate::risk::RiskManager;
+use crate::fees::ProtocolFeeIntegration;
+use crate::integrations::{EventSink, IntegrationResult, SimulationEvent};
+use crate::core::storage::{PoolStore, Storage, StorageResult, WriteBatch};
 
 /// Pool key with hook address
+///
+/// The fields are private so every key has its currencies sorted and
+/// distinct; keys are built with [`ManagerPoolKey::new`].
 #[derive(Hash, Eq, PartialEq, Clone, Debug)]
 pub struct ManagerPoolKey {
-    pub token0: Address,
-    pub token1: Address,
-    pub fee: u32,
-    pub tick_spacing: i32,
-    pub hooks: Address,
-    pub extension_data: Vec<u8>,
+    token0: Address,
+    token1: Address,
+    fee: u32,
+    tick_spacing: TickSpacing,
+    hooks: Address,
+    extension_data: Vec<u8>,
+}
+
+impl ManagerPoolKey {
+    /// Creates the key of the pool of two currencies, in either order
+    ///
+    /// The lower address becomes `token0`. Fails if the currencies are equal.
+    pub fn new(
+        token_a: Address,
+        token_b: Address,
+        fee: u32,
+        tick_spacing: TickSpacing,
+        hooks: Address,
+    ) -> Result<Self, PoolError> {
+        if token_a == token_b {
+            return Err(PoolError::CurrenciesOutOfOrderOrEqual(token_a, token_b));
+        }
+        let (token0, token1) = if token_a < token_b { (token_a, token_b) } else { (token_b, token_a) };
+        Ok(Self { token0, token1, fee, tick_spacing, hooks, extension_data: Vec::new() })
+    }
+
+    /// Replaces the hook address
+    pub fn with_hooks(mut self, hooks: Address) -> Self {
+        self.hooks = hooks;
+        self
+    }
+
+    /// Replaces the fee
+    pub fn with_fee(mut self, fee: u32) -> Self {
+        self.fee = fee;
+        self
+    }
+
+    /// Replaces the data passed to hooks with the key
+    pub fn with_extension_data(mut self, extension_data: Vec<u8>) -> Self {
+        self.extension_data = extension_data;
+        self
+    }
+
+    /// Gets the currency with the lower address
+    pub fn token0(&self) -> Address {
+        self.token0
+    }
+
+    /// Gets the currency with the higher address
+    pub fn token1(&self) -> Address {
+        self.token1
+    }
+
+    /// Gets the fee, in pips or flagged as dynamic
+    pub fn fee(&self) -> u32 {
+        self.fee
+    }
+
+    /// Gets the tick spacing
+    pub fn tick_spacing(&self) -> TickSpacing {
+        self.tick_spacing
+    }
+
+    /// Gets the hook address
+    pub fn hooks(&self) -> Address {
+        self.hooks
+    }
+
+    /// Gets the data passed to hooks with the key
+    pub fn extension_data(&self) -> &[u8] {
+        &self.extension_data
+    }
+
+    /// Converts the key into the form passed to hook callbacks
+    ///
+    /// Only a non-empty `extension_data` is copied to the heap, so operations
+    /// build the hook key once and lend it to every callback.
+    pub fn to_hook_key(&self) -> HookPoolKey {
+        HookPoolKey {
+            token0: self.token0,
+            token1: self.token1,
+            fee: self.fee,
+            tick_spacing: self.tick_spacing,
+            hooks: self.hooks,
+            extension_data: self.extension_data.clone(),
+        }
+    }
+}
+
+/// Options for a liquidity modification
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ModifyLiquidityOptions {
+    /// Reinvest the fees collected by the modification as additional liquidity
+    /// in the same range at the current price
+    pub auto_compound: bool,
+}
+
+/// Moves a position to a new range, possibly in another pool of the same
+/// currencies, see [`PoolManager::rebalance`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RebalanceParams {
+    /// Pool of the position to close
+    pub from_key: ManagerPoolKey,
+    /// Pool of the new position
+    pub to_key: ManagerPoolKey,
+    /// Pool the tokens are swapped in, `to_key` if `None`
+    pub swap_key: Option<ManagerPoolKey>,
+    /// Position closed in full; the new position has the same owner and salt
+    pub position: PositionKey,
+    /// Lower tick of the new position
+    pub tick_lower: i32,
+    /// Upper tick of the new position
+    pub tick_upper: i32,
+    /// Maximum shortfall of the swap output from its value at the pool price
+    /// before the swap, fees included
+    pub max_slippage: Bps,
+    /// Minimum liquidity of the new position
+    pub min_liquidity: u128,
+}
+
+/// Outcome of a rebalance
+#[derive(Debug, Clone, Copy)]
+pub struct RebalanceResult {
+    /// Liquidity removed from the closed position
+    pub liquidity_removed: u128,
+    /// Tokens the closed position returned, fees included
+    pub removed: BalanceDelta,
+    /// Delta of the swap balancing the tokens, if one was needed
+    pub swap: Option<BalanceDelta>,
+    /// Liquidity of the new position
+    pub liquidity_added: u128,
+    /// Tokens paid into the new position
+    pub added: BalanceDelta,
+    /// Tokens left over for the owner, the sum of the other deltas
+    pub leftover: BalanceDelta,
+}
+
+/// How the input and output of a swap are settled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SwapSettlement {
+    /// The swap delta is left to be settled with token transfers
+    #[default]
+    Tokens,
+    /// The input is paid by burning the owner's ERC6909 claims and the output
+    /// is minted to the owner as claims, leaving no delta to settle
+    Claims {
+        /// Account whose claims pay for and receive the swap
+        owner: Address,
+    },
+    /// The swap delta is accounted to the owner, to be settled with take and
+    /// settle before the unlock ends
+    Deltas {
+        /// Account the swap delta is accounted to
+        owner: Address,
+    },
+}
+
+/// Sanity check that swap price limits are near the pool price
+///
+/// Catches the common bug of passing a price, or a price scaled by 2^96,
+/// where a Q64.96 square root price is expected: a limit whose price is more
+/// than `max_price_factor` times above or below the pool price is rejected.
+/// Limits at the ends of the price range, such as `MIN_SQRT_PRICE + 1`, mean
+/// no limit and are always accepted. With the decimals of the pool's
+/// currencies the error shows prices in whole tokens.
+#[derive(Debug, Clone)]
+pub struct PriceLimitCheck {
+    /// Largest accepted ratio between the limit price and the pool price
+    max_price_factor: f64,
+    /// Decimals used to show prices in errors
+    decimals: CurrencyDecimals,
+}
+
+impl PriceLimitCheck {
+    /// Creates a check rejecting limits more than `max_price_factor` times
+    /// away from the pool price, or `None` unless the factor is at least 1
+    pub fn new(max_price_factor: f64) -> Option<Self> {
+        (max_price_factor >= 1.0 && max_price_factor.is_finite())
+            .then(|| Self { max_price_factor, decimals: CurrencyDecimals::new() })
+    }
+
+    /// Shows prices in errors adjusted for the decimals of the currencies
+    pub fn with_decimals(mut self, decimals: CurrencyDecimals) -> Self {
+        self.decimals = decimals;
+        self
+    }
+
+    /// Gets the largest accepted ratio between the limit and pool prices
+    pub fn max_price_factor(&self) -> f64 {
+        self.max_price_factor
+    }
+
+    /// Checks a swap's price limit against the current price of the pool of `key`
+    pub fn check(&self, key: &ManagerPoolKey, current: SqrtPrice, sqrt_price_limit_x96: U256) -> StateResult<()> {
+        if sqrt_price_limit_x96 <= TickMath::MIN_SQRT_PRICE + 1 || sqrt_price_limit_x96 >= TickMath::MAX_SQRT_PRICE - 1 {
+            return Ok(());
+        }
+        let current_price = current.to_price_f64();
+        let limit_price = SqrtPrice::new(sqrt_price_limit_x96).to_price_f64();
+        let ratio = limit_price / current_price;
+        if ratio <= self.max_price_factor && ratio >= 1.0 / self.max_price_factor {
+            return Ok(());
+        }
+        // A price of token0 in token1 scales by 10^(decimals0 - decimals1) in whole tokens
+        let scale = match (
+            self.decimals.decimals(Currency::from_address(key.token0)),
+            self.decimals.decimals(Currency::from_address(key.token1)),
+        ) {
+            (Some(decimals0), Some(decimals1)) => 10f64.powi(decimals0 as i32 - decimals1 as i32),
+            _ => 1.0,
+        };
+        Err(StateError::PriceLimitImplausible {
+            current_price: current_price * scale,
+            limit_price: limit_price * scale,
+            max_factor: self.max_price_factor,
+        })
+    }
+}
+
+/// A swap to quote against a pool of a manager
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuoteRequest {
+    pub key: ManagerPoolKey,
+    pub zero_for_one: bool,
+    /// Negative for exact input, positive for exact output
+    pub amount_specified: i128,
+    pub sqrt_price_limit_x96: U256,
+}
+
+impl QuoteRequest {
+    /// A request for an amount stated as exact input or output
+    pub fn new(key: ManagerPoolKey, zero_for_one: bool, amount: SwapAmount, sqrt_price_limit_x96: U256) -> StateResult<Self> {
+        Ok(Self { key, zero_for_one, amount_specified: amount.amount_specified()?, sqrt_price_limit_x96 })
+    }
+}
+
+/// Outcome of a quoted swap
+#[derive(Debug, Clone, Copy)]
+pub struct Quote {
+    /// Delta the swap would leave the caller with
+    pub delta: BalanceDelta,
+    /// Price the pool would end at
+    pub sqrt_price_x96: SqrtPrice,
+}
+
+/// Result of a quote, failing like the swap would
+pub type QuoteResult = StateResult<Quote>;
+
+/// Copy of a manager's state, see [`PoolManager::snapshot`]
+#[derive(Debug, Clone)]
+pub struct ManagerSnapshot {
+    pools: HashMap<PoolId, Pool>,
+    claims: ERC6909,
+    timestamp: u64,
+}
+
+impl ManagerSnapshot {
+    /// Number of pools in the snapshot
+    pub fn pool_count(&self) -> usize {
+        self.pools.len()
+    }
+
+    /// Timestamp of the manager when the snapshot was taken
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+}
+
+/// Read-only view of a manager's pools for quoting
+///
+/// Hooks are not called, so fee overrides and hook deltas are not reflected
+/// in quotes; see [`PoolManager::quote_with_hooks`] for that. The view holds no hooks, so it can be shared between threads.
+#[derive(Clone, Copy)]
+pub struct QuoteView<'a> {
+    pools: &'a HashMap<PoolId, Pool>,
+}
+
+impl QuoteView<'_> {
+    /// Quotes a swap without changing the pool
+    pub fn quote(&self, request: &QuoteRequest) -> QuoteResult {
+        let pool = self.pools.get(&pool_key_to_id(&request.key)).ok_or(StateError::PoolNotInitialized)?;
+        Self::quote_on(&mut pool.clone(), request)
+    }
+
+    /// Quotes independent swaps in parallel on the current rayon thread pool,
+    /// returning the results in request order
+    ///
+    /// Each worker copies a pool the first time it quotes on it and reuses
+    /// the copy for the rest of its quotes. Quotes never commit the swap, so
+    /// the copy stays as the pool is now and quotes on the same pool do not
+    /// affect each other.
+    pub fn quote_many(&self, requests: &[QuoteRequest]) -> Vec<QuoteResult> {
+        requests
+            .par_iter()
+            .map_init(HashMap::new, |scratch: &mut HashMap<PoolId, Pool>, request| {
+                let pool_id = pool_key_to_id(&request.key);
+                let pool = match scratch.entry(pool_id) {
+                    Entry::Occupied(entry) => entry.into_mut(),
+                    Entry::Vacant(entry) => {
+                        entry.insert(self.pools.get(&pool_id).ok_or(StateError::PoolNotInitialized)?.clone())
+                    }
+                };
+                Self::quote_on(pool, request)
+            })
+            .collect()
+    }
+
+    /// Quotes a swap on a copy of its pool, leaving the copy unchanged
+    fn quote_on(pool: &mut Pool, request: &QuoteRequest) -> QuoteResult {
+        if request.amount_specified == 0 {
+            return Err(StateError::SwapAmountCannotBeZero);
+        }
+        let (delta, sqrt_price_x96) = pool.preview_swap(
+            request.amount_specified,
+            SqrtPrice::new(request.sqrt_price_limit_x96),
+            request.zero_for_one,
+            request.key.tick_spacing,
+            None,
+        )?;
+        Ok(Quote { delta, sqrt_price_x96 })
+    }
+}
+
+/// An operation run inside a batched unlock, see [`PoolManager::unlock_batch`]
+#[derive(Debug, Clone)]
+pub enum UnlockOperation {
+    /// Initializes a pool, so a hook can seed it within the unlock
+    Initialize {
+        key: ManagerPoolKey,
+        sqrt_price_x96: SqrtPrice,
+    },
+    ModifyLiquidity {
+        key: ManagerPoolKey,
+        params: ModifyLiquidityParams,
+        hook_data: Vec<u8>,
+    },
+    Swap {
+        key: ManagerPoolKey,
+        zero_for_one: bool,
+        amount_specified: i128,
+        sqrt_price_limit_x96: U256,
+        settlement: SwapSettlement,
+        hook_data: Vec<u8>,
+    },
+    Take {
+        currency: Currency,
+        to: Address,
+        amount: u128,
+    },
+    /// Syncs the currency and settles `value` of it for the recipient, see
+    /// [`PoolManager::pay_and_settle`]
+    Settle {
+        currency: Currency,
+        recipient: Address,
+        value: U256,
+    },
+    /// Forfeits `amount` of the account's positive delta in the currency
+    Clear {
+        currency: Currency,
+        account: Address,
+        amount: u128,
+    },
+    Mint {
+        to: Address,
+        id: U256,
+        amount: u128,
+    },
+    Burn {
+        from: Address,
+        id: U256,
+        amount: u128,
+    },
+}
+
+/// Output of a successful operation in a batched unlock
+#[derive(Debug, Clone, Copy)]
+pub enum OperationOutput {
+    /// Initial tick of a pool
+    Tick(i32),
+    /// Caller delta of a liquidity modification or swap
+    Delta(BalanceDelta),
+    /// Amount settled
+    Settled(U256),
+    /// The operation has no output
+    Done,
+}
+
+/// Error of a failed operation in a batched unlock
+#[derive(Debug, thiserror::Error)]
+pub enum OperationError {
+    #[error("State error: {0}")]
+    State(#[from] StateError),
+
+    #[error("Flash loan error: {0}")]
+    FlashLoan(#[from] FlashLoanError),
+
+    #[error("Pool error: {0}")]
+    Pool(PoolError),
+}
+
+/// Keeps state errors of pool operations as [`OperationError::State`]
+impl From<PoolError> for OperationError {
+    fn from(error: PoolError) -> Self {
+        match error {
+            PoolError::StateError(error) => OperationError::State(error),
+            error => OperationError::Pool(error),
+        }
+    }
+}
+
+/// Result of each operation of a batched unlock
+#[derive(Debug)]
+pub struct BatchUnlockResult {
+    /// Result of each operation, in order
+    pub results: Vec<Result<OperationOutput, OperationError>>,
+    /// Error that failed the unlock itself, rolling back every operation
+    pub unlock_error: Option<FlashLoanError>,
+    /// Whether every operation and the unlock succeeded
+    pub success: bool,
+}
+
+impl BatchUnlockResult {
+    /// Iterates over the failed operations with their index
+    pub fn failures(&self) -> impl Iterator<Item = (usize, &OperationError)> {
+        self.results.iter().enumerate().filter_map(|(index, result)| result.as_ref().err().map(|error| (index, error)))
+    }
+}
+
+/// Trading statistics of every pool in a manager
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ManagerStats {
+    /// Number of initialized pools
+    pub pool_count: usize,
+    /// Swaps across all pools
+    pub swap_count: u64,
+    /// Timestamp of the last swap in any pool
+    pub last_trade_timestamp: Option<u64>,
+    /// Statistics of each pool, sorted by pool ID
+    pub pools: Vec<(PoolId, PoolStats)>,
+}
+
+/// Identifier of a pool, derived from its key
+///
+/// Displayed and serialized as a 0x-prefixed hex string.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct PoolId(pub [u8; 32]);
+
+impl PoolId {
+    /// Creates the ID of the pool with the given key, the keccak256 hash of
+    /// the ABI-encoded key like the v4 `PoolId`, so IDs match those of the
+    /// pools on chain
+    ///
+    /// The key is encoded in place rather than through `ethers::abi`, as
+    /// swaps look pools up by ID and don't allocate.
+    pub fn from_key(key: &ManagerPoolKey) -> Self {
+        let mut encoded = [0u8; 5 * 32];
+        encoded[12..32].copy_from_slice(key.token0.as_bytes());
+        encoded[44..64].copy_from_slice(key.token1.as_bytes());
+        encoded[92..96].copy_from_slice(&key.fee.to_be_bytes());
+        // int24 words are sign-extended
+        let tick_spacing = key.tick_spacing.get();
+        encoded[96..128].fill(if tick_spacing < 0 { 0xff } else { 0 });
+        encoded[124..128].copy_from_slice(&tick_spacing.to_be_bytes());
+        encoded[140..160].copy_from_slice(key.hooks.as_bytes());
+        Self(keccak256(encoded))
+    }
+
+    /// Gets the raw bytes of the ID
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+impl From<[u8; 32]> for PoolId {
+    fn from(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+}
+
+impl From<PoolId> for [u8; 32] {
+    fn from(id: PoolId) -> Self {
+        id.0
+    }
+}
+
+impl fmt::Display for PoolId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "0x")?;
+        self.0.iter().try_for_each(|byte| write!(f, "{:02x}", byte))
+    }
+}
+
+impl fmt::Debug for PoolId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PoolId({})", self)
+    }
+}
+
+/// Error parsing a [`PoolId`] from a string
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ParsePoolIdError {
+    #[error("Pool ID must be 64 hex digits, got {0}")]
+    InvalidLength(usize),
+
+    #[error("Invalid hex digit in pool ID")]
+    InvalidDigit,
+}
+
+impl FromStr for PoolId {
+    type Err = ParsePoolIdError;
+
+    /// Parses 64 hex digits, with or without a `0x` prefix
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let digits = s.strip_prefix("0x").unwrap_or(s);
+        if digits.len() != 64 {
+            return Err(ParsePoolIdError::InvalidLength(digits.len()));
+        }
+        // `from_str_radix` accepts a leading sign, so every byte is checked
+        // first; this also keeps the pairs below on character boundaries
+        if !digits.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+            return Err(ParsePoolIdError::InvalidDigit);
+        }
+        let mut id = [0u8; 32];
+        for (byte, pair) in id.iter_mut().zip(digits.as_bytes().chunks(2)) {
+            let pair = std::str::from_utf8(pair).map_err(|_| ParsePoolIdError::InvalidDigit)?;
+            *byte = u8::from_str_radix(pair, 16).map_err(|_| ParsePoolIdError::InvalidDigit)?;
+        }
+        Ok(Self(id))
+    }
+}
+
+impl Serialize for PoolId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for PoolId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
+    }
+}
+
+/// A position entering or leaving the range that earns fees, as the pool's
+/// tick moves during a swap
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PositionRangeEvent {
+    /// The pool's tick left the position's range
+    PositionWentOutOfRange { pool_id: PoolId, position: PositionKey, tick: i32 },
+    /// The pool's tick moved back into the position's range
+    PositionBackInRange { pool_id: PoolId, position: PositionKey, tick: i32 },
 }
 
 /// Creates a pool ID from a pool key
-pub fn pool_key_to_id(key: &ManagerPoolKey) -> [u8; 32] {
-    let mut id = [0u8; 32];
-    // Simple hash algorithm - in production would use keccak256
-    id[0..20].copy_from_slice(&key.token0.0);
-    id[20..28].copy_from_slice(&key.token1.0[0..8]);
-    id
+pub fn pool_key_to_id(key: &ManagerPoolKey) -> PoolId {
+    PoolId::from_key(key)
 }
 
+/// Range events a manager keeps by default, see
+/// [`PoolManager::set_range_event_limit`]
+pub const DEFAULT_RANGE_EVENT_LIMIT: usize = 4096;
+
 /// Manages the lifecycle and operations of pools
 pub struct PoolManager {
     /// Mapping of pool IDs to pools
-    pools: HashMap<[u8; 32], Pool>,
-    /// Position manager for all pools
-    position_manager: PositionManager,
+    pools: HashMap<PoolId, Pool>,
     /// Flash loan manager
     flash_loan_manager: FlashLoanManager,
     /// Hook registry
     hook_registry: HookRegistry,
+    /// ERC6909 claims on currencies held by the manager
+    claims: ERC6909,
+    /// Pause state and circuit breakers
+    risk: RiskManager,
+    /// Positions that entered or left their range in swaps, oldest first
+    range_events: VecDeque<PositionRangeEvent>,
+    /// Most range events kept before the oldest are dropped
+    range_event_limit: usize,
+    /// Scratch space shared by hooks, cleared when an unlock ends
+    hook_context: HookContext,
+    /// Optional sanity check of swap price limits
+    price_limit_check: Option<PriceLimitCheck>,
+    /// Whether hook results that break their permissions are rejected
+    strict_hook_validation: bool,
+    /// Step limit of swaps
+    swap_config: SwapConfig,
+    /// Cached fee overrides of pure fee hooks, when caching is on
+    hook_fee_cache: Option<HookFeeCache>,
+    /// Current block timestamp, recorded as the time of swaps and shared
+    /// with hooks
+    clock: Clock,
+    /// Tick sqrt prices shared with the caches of every pool
+    sqrt_price_table: Option<Arc<SqrtPriceTable>>,
+    /// Receiver of the events of successful operations
+    event_sink: Option<Box<dyn EventSink>>,
+    /// Protocol fee controller, routing the treasury's share of donations
+    protocol_fees: Option<ProtocolFeeIntegration>,
 }
 
 impl PoolManager {
@@ -63,70 +674,256 @@ impl PoolManager {
     pub fn new() -> Self {
         Self {
             pools: HashMap::new(),
-            position_manager: PositionManager::new(),
             flash_loan_manager: FlashLoanManager::new(),
             hook_registry: HookRegistry::new(),
+            claims: ERC6909::new(),
+            risk: RiskManager::new(),
+            range_events: VecDeque::new(),
+            range_event_limit: DEFAULT_RANGE_EVENT_LIMIT,
+            hook_context: HookContext::new(),
+            price_limit_check: None,
+            strict_hook_validation: false,
+            swap_config: SwapConfig::UNBOUNDED,
+            hook_fee_cache: None,
+            clock: Clock::new(),
+            sqrt_price_table: None,
+            event_sink: None,
+            protocol_fees: None,
+        }
+    }
+
+    /// Gets the current block timestamp
+    pub fn timestamp(&self) -> u64 {
+        self.clock.now()
+    }
+
+    /// Moves the clock to a later timestamp; earlier timestamps are ignored
+    pub fn set_timestamp(&mut self, timestamp: u64) {
+        self.clock.advance_to(timestamp);
+    }
+
+    /// Gets a handle to the clock, for hooks that follow a schedule
+    pub fn clock(&self) -> Clock {
+        self.clock.clone()
+    }
+
+    /// Gets the pause state and circuit breakers
+    pub fn risk_manager(&self) -> &RiskManager {
+        &self.risk
+    }
+
+    /// Gets the pause state and circuit breakers for configuration
+    pub fn risk_manager_mut(&mut self) -> &mut RiskManager {
+        &mut self.risk
+    }
+
+    /// Gets the hooks called by the manager's pools
+    pub fn hook_registry(&self) -> &HookRegistry {
+        &self.hook_registry
+    }
+
+    /// Gets the hooks called by the manager's pools for registration
+    ///
+    /// Clears the hook fee cache, since the hooks may be replaced.
+    pub fn hook_registry_mut(&mut self) -> &mut HookRegistry {
+        if let Some(cache) = &self.hook_fee_cache {
+            cache.clear();
+        }
+        &mut self.hook_registry
+    }
+
+    /// Replaces the hook of an initialized pool, keeping its liquidity and price
+    ///
+    /// A simulation-only capability for comparing hooks on the same pool. The
+    /// new address is validated against the pool's fee like at initialization,
+    /// and if the hook registered there declares permissions they must match
+    /// the address flags. The deltas the pool accounted to the old hook are
+    /// taken off the hook's balance, so it can no longer be asked to settle
+    /// them, while its deltas from other pools stay. The pool's pause,
+    /// withdraw-only state and breaker move to its new ID, and fees cached
+    /// for either ID are dropped. The new hook is not notified. Returns the
+    /// pool's new key and the flushed deltas.
+    pub fn replace_pool_hook(
+        &mut self,
+        key: &ManagerPoolKey,
+        hooks: Address,
+    ) -> StateResult<(ManagerPoolKey, BalanceDelta)> {
+        if self.is_unlocked() {
+            return Err(StateError::ManagerUnlocked);
+        }
+        let pool_id = pool_key_to_id(key);
+        if !self.pools.contains_key(&pool_id) {
+            return Err(StateError::PoolNotInitialized);
+        }
+
+        let new_key = key.clone().with_hooks(hooks);
+        let new_pool_id = pool_key_to_id(&new_key);
+        if new_pool_id != pool_id && self.pools.contains_key(&new_pool_id) {
+            return Err(StateError::PoolAlreadyInitialized);
+        }
+        self.hook_registry.validate_hook_address_for_fee(&hooks, key.fee)?;
+        // Hooks that do not describe themselves declare no permissions
+        let permissions = self.hook_registry.get_hook(&hooks).map(|hook| hook.describe().permissions);
+        if let Some(permissions) = permissions.filter(|permissions| *permissions != HookPermissions::default()) {
+            self.hook_registry.validate_hook_permissions(&hooks, permissions)?;
+        }
+
+        // Only the pool's share of the hook's deltas is flushed, as the
+        // hook may have deltas from its other pools in the same currencies
+        let flushed = self.pools[&pool_id].hook_deltas();
+        let flushes = [(key.token0, flushed.amount0()), (key.token1, flushed.amount1())]
+            .map(|(token, amount)| (Currency::from_address(token), amount));
+        for (currency, amount) in flushes {
+            if self.get_delta(key.hooks, currency).checked_sub(amount).is_none() {
+                return Err(StateError::AmountOverflow);
+            }
+        }
+        for (currency, amount) in flushes {
+            self._account_delta(currency, -amount, key.hooks, DeltaReason::Hook)?;
+        }
+        if let Some(mut pool) = self.pools.remove(&pool_id) {
+            pool.clear_hook_deltas();
+            self.pools.insert(new_pool_id, pool);
+        }
+        self.risk.move_pool(&pool_id, new_pool_id);
+        if let Some(cache) = &self.hook_fee_cache {
+            cache.invalidate(&pool_id);
+            cache.invalidate(&new_pool_id);
+        }
+        Ok((new_key, flushed))
+    }
+
+    /// Detaches the hook of an initialized pool, as [`replace_pool_hook`](Self::replace_pool_hook)
+    /// with the zero address
+    pub fn detach_pool_hook(&mut self, key: &ManagerPoolKey) -> StateResult<(ManagerPoolKey, BalanceDelta)> {
+        self.replace_pool_hook(key, Address::zero())
+    }
+
+    /// Rejects operations on a paused pool
+    fn _check_not_paused(&self, pool_id: &PoolId) -> StateResult<()> {
+        if self.risk.is_manager_paused() {
+            return Err(StateError::ManagerPaused);
+        }
+        if self.risk.is_pool_paused(pool_id) {
+            return Err(StateError::PoolPaused);
+        }
+        Ok(())
+    }
+
+    /// Rejects operations that add to a withdraw-only pool
+    fn _check_not_withdraw_only(&self, pool_id: &PoolId) -> StateResult<()> {
+        if self.risk.is_withdraw_only(pool_id) {
+            return Err(StateError::PoolWithdrawOnly);
+        }
+        Ok(())
+    }
+
+    /// Gets the currency of a claim ID, rejecting IDs of no currency and
+    /// currencies the currency policy doesn't allow
+    fn _claim_currency(&self, id: U256) -> StateResult<Currency> {
+        let currency = Currency::from_id(id)?;
+        if self.flash_loan_manager.currency_policy().is_allowed(currency) {
+            Ok(currency)
+        } else {
+            Err(StateError::CurrencyNotAllowed(currency))
         }
     }
 
     /// Initializes a new pool
+    ///
+    /// A hook whose `before_initialize` refuses the pool fails it with
+    /// [`PoolError::RejectedByHook`] and its reason; every other failure is
+    /// a [`PoolError::StateError`].
     pub fn initialize_pool(
         &mut self,
         key: ManagerPoolKey,
         sqrt_price_x96: SqrtPrice,
-    ) -> StateResult<i32> {
+    ) -> Result<i32, PoolError> {
         let pool_id = pool_key_to_id(&key);
         
         // Check if pool already exists
         if self.pools.contains_key(&pool_id) {
-            return Err(StateError::PoolAlreadyInitialized);
+            return Err(StateError::PoolAlreadyInitialized.into());
         }
 
-        // Call hook before initialization if available
-        if let Some(hook) = self.hook_registry.get_hook_mut(&key.hooks.0) {
-            hook.before_initialize(
-                Address::zero().0,  // 使用零地址作为发送者的占位符
-                &HookPoolKey {
-                    token0: key.token0.0,
-                    token1: key.token1.0,
-                    fee: key.fee,
-                    tick_spacing: key.tick_spacing,
-                    hooks: key.hooks.0,
-                    extension_data: key.extension_data.clone(),
-                },
+        // Call hook before initialization if available, which may refuse
+        // the pool, e.g. for a tick spacing or fee it doesn't support
+        if let Some(hook) = self.hook_registry.get_hook_mut(&key.hooks) {
+            let result = hook.before_initialize(
+                Address::zero(),  // 使用零地址作为发送者的占位符
+                &key.to_hook_key(),
                 sqrt_price_x96,
                 &[]  // 空钩子数据
-            )?;
+            ).map_err(HookError::wrap_call_failure(key.hooks, HookCallback::BeforeInitialize))?;
+            if let Some(reason) = result.rejection {
+                return Err(PoolError::RejectedByHook(reason));
+            }
         }
 
         // Create and initialize pool
         let mut pool = Pool::new();
-        let tick = pool.initialize(sqrt_price_x96, key.fee)?;
+        let lp_fee = get_initial_lp_fee(key.fee);
+        let tick = pool.initialize(sqrt_price_x96, lp_fee)?;
+        pool.set_sqrt_price_table(self.sqrt_price_table.clone());
 
         // Add pool to manager
         self.pools.insert(pool_id, pool);
 
         // Call hook after initialization if available
-        if let Some(hook) = self.hook_registry.get_hook_mut(&key.hooks.0) {
-            hook.after_initialize(
-                Address::zero().0,  // 使用零地址作为发送者的占位符
-                &HookPoolKey {
-                    token0: key.token0.0,
-                    token1: key.token1.0,
-                    fee: key.fee,
-                    tick_spacing: key.tick_spacing,
-                    hooks: key.hooks.0,
-                    extension_data: key.extension_data.clone(),
-                },
-                sqrt_price_x96,
-                tick,
+        if let Some(hook) = self.hook_registry.get_hook_mut(&key.hooks) {
+            let report = InitializeReport { sqrt_price_x96, tick, lp_fee };
+            let result = hook.after_initialize_with_report(
+                Address::zero(),  // 使用零地址作为发送者的占位符
+                &key.to_hook_key(),
+                &report,
                 &[]  // 空钩子数据
-            )?;
+            ).map_err(HookError::wrap_call_failure(key.hooks, HookCallback::AfterInitialize))?;
+            self._seed_liquidity(&key, &result.seeds)?;
         }
 
+        self._emit_event(|timestamp| SimulationEvent::Initialize {
+            pool_id,
+            timestamp,
+            currency0: key.token0,
+            currency1: key.token1,
+            fee: key.fee,
+            tick_spacing: key.tick_spacing.get(),
+            hooks: key.hooks,
+            sqrt_price_x96: sqrt_price_x96.to_u256(),
+            tick,
+        });
         Ok(tick)
     }
 
+    /// Adds the liquidity a hook seeds a pool with after initialization,
+    /// accounting the amounts to the hook
+    ///
+    /// The seeds are part of the initialization, so if one fails the pool is
+    /// removed again.
+    fn _seed_liquidity(&mut self, key: &ManagerPoolKey, seeds: &[LiquiditySeed]) -> StateResult<()> {
+        if seeds.is_empty() {
+            return Ok(());
+        }
+        let pool_id = pool_key_to_id(key);
+        let pool = self.pools.get_mut(&pool_id).ok_or(StateError::PoolNotInitialized)?;
+        let mut owed = BalanceDelta::default();
+        for seed in seeds {
+            let seeded = i128::try_from(seed.liquidity)
+                .map_err(|_| StateError::LiquidityOverflow)
+                .and_then(|liquidity| {
+                    pool.modify_position(key.hooks.0, seed.tick_lower, seed.tick_upper, liquidity, key.tick_spacing, seed.salt.0)
+                });
+            match seeded {
+                Ok((delta, _)) => owed = owed + delta,
+                Err(error) => {
+                    self.pools.remove(&pool_id);
+                    return Err(error);
+                }
+            }
+        }
+        self._account_hook_delta(key, owed)
+    }
+
     /// Modifies liquidity for a position (mint or burn)
     pub fn modify_liquidity(
         &mut self,
@@ -134,21 +931,40 @@ impl PoolManager {
         params: ModifyLiquidityParams,
         hook_data: &[u8],
     ) -> StateResult<(BalanceDelta, BalanceDelta)> {
+        let (caller_delta, fees_accrued, _) = self.modify_liquidity_with_options(
+            key,
+            params,
+            ModifyLiquidityOptions::default(),
+            hook_data,
+        )?;
+        Ok((caller_delta, fees_accrued))
+    }
+
+    /// Modifies liquidity for a position with the given options
+    ///
+    /// Returns the caller delta, the fees accrued by the position and the extra
+    /// liquidity minted when `auto_compound` is set. Compounded fees are netted
+    /// out of the caller delta; any remainder that could not be converted into
+    /// liquidity at the current price is still paid out.
+    pub fn modify_liquidity_with_options(
+        &mut self,
+        key: ManagerPoolKey,
+        params: ModifyLiquidityParams,
+        options: ModifyLiquidityOptions,
+        hook_data: &[u8],
+    ) -> StateResult<(BalanceDelta, BalanceDelta, u128)> {
         let pool_id = pool_key_to_id(&key);
+        self._check_not_paused(&pool_id)?;
+        if params.liquidity_delta > 0 || options.auto_compound {
+            self._check_not_withdraw_only(&pool_id)?;
+        }
         
         // Get pool or return error
         let pool = self.pools.get_mut(&pool_id).ok_or(StateError::PoolNotInitialized)?;
         
         // Call hook before modifying liquidity if available
-        if let Some(hook) = self.hook_registry.get_hook_mut(&key.hooks.0) {
-            let hook_interface_key = HookPoolKey {
-                token0: key.token0.0,
-                token1: key.token1.0,
-                fee: key.fee,
-                tick_spacing: key.tick_spacing,
-                hooks: key.hooks.0,
-                extension_data: key.extension_data.clone(),
-            };
+        if let Some(hook) = self.hook_registry.get_hook_mut(&key.hooks) {
+            let hook_interface_key = key.to_hook_key();
             
             let hook_interface_params = crate::core::hooks::hook_interface::ModifyLiquidityParams {
                 owner: params.owner,
@@ -160,54 +976,83 @@ impl PoolManager {
             
             if params.liquidity_delta > 0 {
                 hook.before_add_liquidity(
-                    Address::zero().0,  // 使用零地址作为发送者的占位符
+                    Address::zero(),  // 使用零地址作为发送者的占位符
                     &hook_interface_key,
                     &hook_interface_params,
                     hook_data
-                )?;
+                ).map_err(HookError::wrap_call_failure(key.hooks, HookCallback::BeforeAddLiquidity))?;
             } else {
                 hook.before_remove_liquidity(
-                    Address::zero().0,  // 使用零地址作为发送者的占位符
+                    Address::zero(),  // 使用零地址作为发送者的占位符
                     &hook_interface_key,
                     &hook_interface_params,
                     hook_data
-                )?;
+                ).map_err(HookError::wrap_call_failure(key.hooks, HookCallback::BeforeRemoveLiquidity))?;
             }
         }
         
         // Create position key
-        let position_key = PositionKey {
-            owner: params.owner,
-            tick_lower: params.tick_lower,
-            tick_upper: params.tick_upper,
-            salt: params.salt,
-        };
+        let position_key = params.position_key();
         
-        // Modify liquidity in the pool
-        let (principal_delta, fees_accrued) = pool.modify_liquidity(params.tick_lower, params.tick_upper, params.liquidity_delta, key.tick_spacing)?;
+        // In strict mode a delta from a hook without the flag to return it is
+        // only rejected after the position changed, so the pool is restored
+        let (callback, flag) = if params.liquidity_delta > 0 {
+            (HookCallback::AfterAddLiquidity, HookFlags::AFTER_ADD_LIQUIDITY_RETURNS_DELTA)
+        } else {
+            (HookCallback::AfterRemoveLiquidity, HookFlags::AFTER_REMOVE_LIQUIDITY_RETURNS_DELTA)
+        };
+        let may_reject = self.strict_hook_validation
+            && key.hooks != Address::zero()
+            && !HookFlags::from_address(key.hooks).is_enabled(flag);
+        let pool_before = may_reject.then(|| pool.clone());
         
-        // Update position
-        let _position_delta = self.position_manager.update(
-            position_key,
+        // Modify the position in the pool, which owns the only position record
+        let (principal_delta, fees_accrued) = pool.modify_position(
+            position_key.owner,
+            params.tick_lower,
+            params.tick_upper,
             params.liquidity_delta,
-            pool.fee_growth_global_0_x128,
-            pool.fee_growth_global_1_x128,
+            key.tick_spacing,
+            position_key.salt,
         )?;
         
         // Combine principal delta and fees for the caller
         let mut caller_delta = principal_delta + fees_accrued;
         
+        // Reinvest the collected fees into the same range, unless the position was closed
+        let mut compounded_liquidity = 0u128;
+        if options.auto_compound && pool.position_manager.get(&position_key).is_some() {
+            let sqrt_price_lower = TickMath::get_sqrt_price_at_tick(params.tick_lower)
+                .map_err(|_| StateError::InvalidPrice)?;
+            let sqrt_price_upper = TickMath::get_sqrt_price_at_tick(params.tick_upper)
+                .map_err(|_| StateError::InvalidPrice)?;
+            let liquidity = FixedPoint96::get_liquidity_for_amounts(
+                pool.slot0.sqrt_price_x96.to_u256(),
+                sqrt_price_lower,
+                sqrt_price_upper,
+                fees_accrued.amount0().max(0) as u128,
+                fees_accrued.amount1().max(0) as u128,
+            );
+            
+            if liquidity > 0 {
+                let liquidity_delta = i128::try_from(liquidity).map_err(|_| StateError::LiquidityOverflow)?;
+                let (compound_delta, _) = pool.modify_position(
+                    position_key.owner,
+                    params.tick_lower,
+                    params.tick_upper,
+                    liquidity_delta,
+                    key.tick_spacing,
+                    position_key.salt,
+                )?;
+                caller_delta = caller_delta + compound_delta;
+                compounded_liquidity = liquidity;
+            }
+        }
+        
         // Call hook after modifying liquidity if available
         let mut hook_delta = BalanceDelta::default();
-        if let Some(hook) = self.hook_registry.get_hook_mut(&key.hooks.0) {
-            let hook_interface_key = HookPoolKey {
-                token0: key.token0.0,
-                token1: key.token1.0,
-                fee: key.fee,
-                tick_spacing: key.tick_spacing,
-                hooks: key.hooks.0,
-                extension_data: key.extension_data.clone(),
-            };
+        if let Some(hook) = self.hook_registry.get_hook_mut(&key.hooks) {
+            let hook_interface_key = key.to_hook_key();
             
             let hook_interface_params = crate::core::hooks::hook_interface::ModifyLiquidityParams {
                 owner: params.owner,
@@ -219,184 +1064,406 @@ impl PoolManager {
             
             let result = if params.liquidity_delta > 0 {
                 hook.after_add_liquidity(
-                    Address::zero().0,  // 使用零地址作为发送者的占位符
+                    Address::zero(),  // 使用零地址作为发送者的占位符
                     &hook_interface_key,
                     &hook_interface_params,
                     &caller_delta,
                     &fees_accrued,
                     hook_data
-                )?
+                ).map_err(HookError::wrap_call_failure(key.hooks, HookCallback::AfterAddLiquidity))?
             } else {
                 hook.after_remove_liquidity(
-                    Address::zero().0,  // 使用零地址作为发送者的占位符
+                    Address::zero(),  // 使用零地址作为发送者的占位符
                     &hook_interface_key,
                     &hook_interface_params,
                     &caller_delta,
                     &fees_accrued,
                     hook_data
-                )?
+                ).map_err(HookError::wrap_call_failure(key.hooks, HookCallback::AfterRemoveLiquidity))?
             };
             
             // Update caller_delta and hook_delta based on hook result
             if let AfterHookResult { delta: Some(delta) } = result {
+                if let Err(error) = self._validate_hook_delta(&key, callback, flag, !delta.is_zero()) {
+                    if let Some(pool) = pool_before {
+                        self.pools.insert(pool_id, pool);
+                    }
+                    return Err(error);
+                }
                 hook_delta = delta;
                 
                 // Account for hook delta
-                if !hook_delta.is_zero() {
-                    self._account_pool_balance_delta(&key, hook_delta, key.hooks)?;
-                }
+                self._account_hook_delta(&key, hook_delta)?;
             }
         }
+        self._account_pool_balance_delta(&key, caller_delta, params.owner, DeltaReason::ModifyLiquidity)?;
         
-        Ok((caller_delta, fees_accrued))
+        self._emit_event(|timestamp| SimulationEvent::ModifyLiquidity {
+            pool_id,
+            timestamp,
+            owner: params.owner,
+            tick_lower: params.tick_lower,
+            tick_upper: params.tick_upper,
+            liquidity_delta: params.liquidity_delta,
+            amount0: caller_delta.amount0(),
+            amount1: caller_delta.amount1(),
+        });
+        Ok((caller_delta, fees_accrued, compounded_liquidity))
     }
 
-    /// Swaps tokens in a pool
+    /// Swaps tokens in a pool, returning the caller's delta
+    ///
+    /// As in v4, the hook's `before_swap` delta in the specified currency
+    /// changes the amount the pool swaps, and both swap hooks can return a
+    /// delta in the unspecified currency. The hook's deltas are accounted to
+    /// the hook and taken from the caller's.
     pub fn swap(
         &mut self,
-        key: ManagerPoolKey,
+        key: &ManagerPoolKey,
         zero_for_one: bool,
         amount_specified: i128,
         sqrt_price_limit_x96: U256,
         hook_data: &[u8],
     ) -> StateResult<BalanceDelta> {
-        let pool_id = pool_key_to_id(&key);
-        
-        // Get pool or return error
-        // let pool = self.pools.get_mut(&pool_id).ok_or(StateError::PoolNotInitialized)?;
-        
-        // Prepare variables for hook results
-        let mut amount_to_swap = amount_specified;
-        let mut hook_provided_pre_swap_delta = BalanceDelta::default();
-        let mut lp_fee_override_from_hook: Option<u32> = None;
-        
-        // Step 1: Extract all data from before_swap hook
-        if key.hooks != Address::zero() {
-            // Create hook key and params outside of the hook call to avoid borrowing issues
-            let hook_interface_key = HookPoolKey {
-                token0: key.token0.0,
-                token1: key.token1.0,
-                fee: key.fee,
-                tick_spacing: key.tick_spacing,
-                hooks: key.hooks.0,
-                extension_data: key.extension_data.clone(),
-            };
-            
-            let swap_params_for_hook = crate::core::hooks::hook_interface::SwapParams {
-                amount_specified,
-                zero_for_one,
-                sqrt_price_limit_x96: SqrtPrice::new(sqrt_price_limit_x96),
-            };
-            
-            // Get hook result in a completely separate scope to ensure borrow is dropped
-            let before_hook_result = {
-                if let Some(hook) = self.hook_registry.get_hook_mut(&key.hooks.0) {
-                    hook.before_swap(
-                        Address::zero().0, // Placeholder sender
-                        &hook_interface_key,
-                        &swap_params_for_hook,
-                        hook_data
-                    )
-                } else {
-                    Ok(BeforeHookResult::default())
-                }
-            }; // hook borrow is definitely dropped here
-            
-            // Process the result
-            match before_hook_result {
-                Ok(result) => {
-                    if let Some(val) = result.amount { amount_to_swap = val; }
-                    if let Some(delta) = result.delta { hook_provided_pre_swap_delta = delta; }
-                    lp_fee_override_from_hook = result.fee_override;
-                }
-                Err(e) => return Err(e),
-            }
+        self.swap_with_settlement(
+            key,
+            zero_for_one,
+            amount_specified,
+            sqrt_price_limit_x96,
+            SwapSettlement::Tokens,
+            hook_data,
+        )
+    }
+
+    /// Swaps an amount stated as exact input or output in a pool
+    ///
+    /// Like [`swap`](Self::swap), without the sign convention of
+    /// `amount_specified` to get wrong; see [`SwapAmount`].
+    pub fn swap_exact(
+        &mut self,
+        key: &ManagerPoolKey,
+        zero_for_one: bool,
+        amount: SwapAmount,
+        sqrt_price_limit_x96: U256,
+        hook_data: &[u8],
+    ) -> StateResult<BalanceDelta> {
+        self.swap(key, zero_for_one, amount.amount_specified()?, sqrt_price_limit_x96, hook_data)
+    }
+
+    /// Swaps tokens in a pool, settling the input and output as selected
+    ///
+    /// With `SwapSettlement::Claims` the owner must hold enough claims on the
+    /// input currency; the returned delta is the swap delta that the claims
+    /// burned and minted have already offset.
+    pub fn swap_with_settlement(
+        &mut self,
+        key: &ManagerPoolKey,
+        zero_for_one: bool,
+        amount_specified: i128,
+        sqrt_price_limit_x96: U256,
+        settlement: SwapSettlement,
+        hook_data: &[u8],
+    ) -> StateResult<BalanceDelta> {
+        self.swap_with_report(key, zero_for_one, amount_specified, sqrt_price_limit_x96, settlement, hook_data)
+            .map(|report| report.delta)
+    }
+
+    /// Swaps like [`swap_with_settlement`](Self::swap_with_settlement),
+    /// reporting the steps the swap took
+    ///
+    /// The swap is bounded by the [`swap_config`](Self::swap_config). When it
+    /// stops at the step limit with a partial result, the hooks and
+    /// settlement see the partial delta, as for a swap stopped by its price
+    /// limit.
+    pub fn swap_with_report(
+        &mut self,
+        key: &ManagerPoolKey,
+        zero_for_one: bool,
+        amount_specified: i128,
+        sqrt_price_limit_x96: U256,
+        settlement: SwapSettlement,
+        hook_data: &[u8],
+    ) -> StateResult<SwapReport> {
+        if amount_specified == 0 {
+            return Err(StateError::SwapAmountCannotBeZero);
+        }
+        let pool_id = pool_key_to_id(key);
+        self._check_not_paused(&pool_id)?;
+        self._check_not_withdraw_only(&pool_id)?;
+        if let Some(check) = &self.price_limit_check {
+            let pool = self.pools.get(&pool_id).ok_or(StateError::PoolNotInitialized)?;
+            check.check(key, pool.slot0.sqrt_price_x96, sqrt_price_limit_x96)?;
         }
+        let (currency_in, currency_out) = if zero_for_one {
+            (Currency::from_address(key.token0), Currency::from_address(key.token1))
+        } else {
+            (Currency::from_address(key.token1), Currency::from_address(key.token0))
+        };
         
-        // Step 2: Account for pre-swap delta (no hook borrow active here)
-        if !hook_provided_pre_swap_delta.is_zero() {
-            self._account_pool_balance_delta(&key, hook_provided_pre_swap_delta, key.hooks)?;
+        // For exact input swaps the amount paid is known up front
+        if let SwapSettlement::Claims { owner } = settlement {
+            if amount_specified < 0 && self.claims_balance_of(owner, currency_in) < U256::from(amount_specified.unsigned_abs()) {
+                return Err(StateError::Claims(ERC6909Error::InsufficientBalance));
+            }
         }
         
         // Get pool or return error
-        let pool = self.pools.get_mut(&pool_id).ok_or(StateError::PoolNotInitialized)?;
+        // let pool = self.pools.get_mut(&pool_id).ok_or(StateError::PoolNotInitialized)?;
+        
+        // Prepare variables for hook results
+        let mut amount_to_swap = amount_specified;
+        let mut hook_delta = BeforeSwapDelta::default();
+        let mut lp_fee_override_from_hook: Option<FeePips> = None;
+        let flags = HookFlags::from_address(key.hooks);
         
-        // Step 3: Execute swap in the pool
-        let (swap_delta, _protocol_fee_amount_from_pool) = pool.swap(
-            amount_to_swap,
-            SqrtPrice::new(sqrt_price_limit_x96),
+        // The hook key and params are built once and shared by both swap hooks
+        let hook_interface_key = (key.hooks != Address::zero()).then(|| key.to_hook_key());
+        let swap_params_for_hook = SwapParams {
+            amount_specified,
             zero_for_one,
-            key.tick_spacing,
-            lp_fee_override_from_hook,
-        )?;
+            sqrt_price_limit_x96: SqrtPrice::new(sqrt_price_limit_x96),
+        };
         
-        // Step 4: Extract all data from after_swap hook
-        let mut final_hook_delta_after_swap = BalanceDelta::default();
-        
-        if key.hooks != Address::zero() {
-            // Create hook key and params outside of the hook call
-            let hook_interface_key = HookPoolKey {
-                token0: key.token0.0,
-                token1: key.token1.0,
-                fee: key.fee,
-                tick_spacing: key.tick_spacing,
-                hooks: key.hooks.0,
-                extension_data: key.extension_data.clone(),
-            };
+        // Step 1: Extract all data from before_swap hook
+        if let Some(hook_interface_key) = &hook_interface_key {
+            // Pure fee hooks are skipped when their fee for this swap and price is cached
+            let fee_query = self._fee_cache_query(key, &pool_id, &swap_params_for_hook, hook_data);
+            let cached_fee = fee_query.as_ref().and_then(|(cache, query)| {
+                cache.get(pool_id, self.pools.get(&pool_id)?, query)
+            });
             
-            let swap_params_for_hook = crate::core::hooks::hook_interface::SwapParams {
-                amount_specified,
-                zero_for_one,
-                sqrt_price_limit_x96: SqrtPrice::new(sqrt_price_limit_x96),
-            };
+            // Get hook result in a completely separate scope to ensure borrow is dropped
+            let before_hook_result = if let Some(fee_override) = cached_fee {
+                Ok((BeforeHookResult { fee_override, ..Default::default() }, BeforeSwapDelta::default()))
+            } else if let Some(hook) = self.hook_registry.get_hook_mut(&key.hooks) {
+                // Deltas from both callbacks add up, the one from `Hook` in token order
+                hook.before_swap(Address::zero(), hook_interface_key, &swap_params_for_hook, hook_data)
+                    .and_then(|result| {
+                        let returned = if flags.is_enabled(HookFlags::BEFORE_SWAP_RETURNS_DELTA) {
+                            hook.before_swap_with_delta(Address::zero(), hook_interface_key, &swap_params_for_hook, hook_data)?
+                        } else {
+                            BeforeSwapDelta::default()
+                        };
+                        Ok((result, returned))
+                    })
+                    .map_err(HookError::wrap_call_failure(key.hooks, HookCallback::BeforeSwap))
+            } else {
+                Ok((BeforeHookResult::default(), BeforeSwapDelta::default()))
+            }; // hook borrow is definitely dropped here
             
+            // Process the result
+            let (result, returned) = before_hook_result?;
+            let delta = BeforeSwapDelta::combine(&result, returned, &swap_params_for_hook)?;
+
+            let changes_amount = result.amount.is_some_and(|amount| amount != amount_specified);
+            let returns_delta = delta != BeforeSwapDelta::default();
+            let flag = HookFlags::BEFORE_SWAP_RETURNS_DELTA;
+            self._validate_hook_delta(key, HookCallback::BeforeSwap, flag, changes_amount || returns_delta)?;
+            self._validate_fee_override(key, HookCallback::BeforeSwap, result.fee_override)?;
+            if let (Some((cache, query)), None, false) = (fee_query, cached_fee, changes_amount || returns_delta) {
+                if let Some(pool) = self.pools.get(&pool_id) {
+                    cache.insert(pool_id, pool, query, result.fee_override);
+                }
+            }
+            if let Some(val) = result.amount { amount_to_swap = val; }
+            lp_fee_override_from_hook = result.fee_override;
+            amount_to_swap = delta.apply_to_amount(amount_to_swap)?;
+            hook_delta = delta;
+        }
+        
+        // Get pool or return error
+        let pool = self.pools.get_mut(&pool_id).ok_or(StateError::PoolNotInitialized)?;
+        
+        // Step 2: Run the swap in the pool, reporting crossed ticks to hooks
+        // that opted in. The pool only moves once the after-swap hook and
+        // the settlement have accepted the swap
+        let tick_cross_hook = match &hook_interface_key {
+            Some(hook_key) if HookFlags::from_address(key.hooks).is_enabled(HookFlags::TICK_CROSS) => {
+                self.hook_registry.get_hook_mut(&key.hooks).map(|hook| (hook, hook_key))
+            }
+            _ => None,
+        };
+        let prepared = match tick_cross_hook {
+            Some((hook, hook_key)) => pool.prepare_swap(
+                amount_to_swap,
+                SqrtPrice::new(sqrt_price_limit_x96),
+                zero_for_one,
+                key.tick_spacing,
+                lp_fee_override_from_hook,
+                &self.swap_config,
+                &mut |cross| hook.on_tick_cross(hook_key, cross.tick, cross.direction, cross.liquidity_net),
+            )?,
+            None => pool.prepare_swap(
+                amount_to_swap,
+                SqrtPrice::new(sqrt_price_limit_x96),
+                zero_for_one,
+                key.tick_spacing,
+                lp_fee_override_from_hook,
+                &self.swap_config,
+                &mut |_| Ok(()),
+            )?,
+        };
+        let swap_delta = prepared.report().delta;
+        
+        // Step 3: Extract all data from after_swap hook
+        if let Some(hook_interface_key) = &hook_interface_key {
             // Get hook result in a completely separate scope
             let after_hook_result = {
-                if let Some(hook) = self.hook_registry.get_hook_mut(&key.hooks.0) {
-                    hook.after_swap(
-                        Address::zero().0,
-                        &hook_interface_key,
-                        &swap_params_for_hook,
-                        &swap_delta,
-                        hook_data
-                    )
+                if let Some(hook) = self.hook_registry.get_hook_mut(&key.hooks) {
+                    hook.after_swap(Address::zero(), hook_interface_key, &swap_params_for_hook, &swap_delta, hook_data)
+                        .and_then(|result| {
+                            let returned = if flags.is_enabled(HookFlags::AFTER_SWAP_RETURNS_DELTA) {
+                                hook.after_swap_with_delta(Address::zero(), hook_interface_key, &swap_params_for_hook, &swap_delta, hook_data)?
+                            } else {
+                                0
+                            };
+                            Ok((result, returned))
+                        })
+                        .map_err(HookError::wrap_call_failure(key.hooks, HookCallback::AfterSwap))
                 } else {
-                    Ok(AfterHookResult::default())
+                    Ok((AfterHookResult::default(), 0))
                 }
             }; // hook borrow is definitely dropped here
             
-            // Process the result
-            match after_hook_result {
-                Ok(result) => {
-                    if let Some(delta) = result.delta { final_hook_delta_after_swap = delta; }
-                }
-                Err(e) => return Err(e),
+            // Process the result; after the swap only the unspecified currency
+            // can still change
+            let (result, returned) = after_hook_result?;
+            let (folded, delta_unspecified) = hook_delta.fold_after_swap(&result, returned, &swap_params_for_hook, key.hooks)?;
+            let flag = HookFlags::AFTER_SWAP_RETURNS_DELTA;
+            self._validate_hook_delta(key, HookCallback::AfterSwap, flag, delta_unspecified != 0)?;
+            hook_delta = folded;
+        }
+        
+        // The hook's deltas are taken from the caller's
+        let caller_delta = hook_delta.caller_delta(swap_delta, &swap_params_for_hook)?;
+        let (amount_in, amount_out) = if zero_for_one {
+            (caller_delta.amount0(), caller_delta.amount1())
+        } else {
+            (caller_delta.amount1(), caller_delta.amount0())
+        };
+        
+        // Exact output swaps and hook deltas only fix the input now, which
+        // the owner's claims must still cover
+        if let SwapSettlement::Claims { owner } = settlement {
+            if amount_in < 0 && self.claims_balance_of(owner, currency_in) < U256::from(amount_in.unsigned_abs()) {
+                return Err(StateError::Claims(ERC6909Error::InsufficientBalance));
+            }
+        }
+        
+        // Step 4: Account for the hook's deltas from both callbacks, now that
+        // the swap and the hooks' results have been accepted (no hook borrow
+        // active here)
+        let hook_balance_delta = hook_delta.to_balance_delta(&swap_params_for_hook);
+        self._account_hook_delta(key, hook_balance_delta)?;
+        
+        // Step 5: Settle against claims, offsetting the owner's swap delta
+        if let SwapSettlement::Claims { owner } = settlement {
+            self._account_pool_balance_delta(key, caller_delta, owner, DeltaReason::Swap)?;
+            if amount_in < 0 {
+                self._burn_claims(owner, currency_in, amount_in.unsigned_abs())?;
+            }
+            if amount_out > 0 {
+                self._mint_claims(owner, currency_out, amount_out.unsigned_abs())?;
             }
         }
+        if let SwapSettlement::Deltas { owner } = settlement {
+            self._account_pool_balance_delta(key, caller_delta, owner, DeltaReason::Swap)?;
+        }
         
-        // Step 5: Account for after-swap delta (no hook borrow active here)
-        if !final_hook_delta_after_swap.is_zero() {
-            self._account_pool_balance_delta(&key, final_hook_delta_after_swap, key.hooks)?;
+        // Step 6: Move the pool
+        let pool = self.pools.get_mut(&pool_id).ok_or(StateError::PoolNotInitialized)?;
+        let sqrt_price_before = pool.slot0.sqrt_price_x96.to_u256();
+        let tick_before = pool.slot0.tick;
+        let report = pool.commit_swap(prepared);
+        let sqrt_price_after = pool.slot0.sqrt_price_x96.to_u256();
+        if !swap_delta.is_zero() {
+            pool.record_trade_timestamp(self.clock.now());
+        }
+        let tick_after = pool.slot0.tick;
+        if self.range_event_limit > 0 {
+            for (position, in_range) in pool.range_changes_since(tick_before) {
+                if self.range_events.len() == self.range_event_limit {
+                    self.range_events.pop_front();
+                }
+                self.range_events.push_back(if in_range {
+                    PositionRangeEvent::PositionBackInRange { pool_id, position, tick: tick_after }
+                } else {
+                    PositionRangeEvent::PositionWentOutOfRange { pool_id, position, tick: tick_after }
+                });
+            }
         }
         
-        Ok(swap_delta)
+        // Report the swap to the circuit breakers, which pause later operations if tripped
+        self.risk.record_swap(pool_id, sqrt_price_before, sqrt_price_after, swap_delta.amount0().unsigned_abs());
+        
+        if self.event_sink.is_some() {
+            let pool = self.pools.get(&pool_id).ok_or(StateError::PoolNotInitialized)?;
+            let (sqrt_price_x96, tick, liquidity) = (pool.slot0.sqrt_price_x96.to_u256(), pool.slot0.tick, pool.liquidity.as_u128());
+            self._emit_event(|timestamp| SimulationEvent::Swap {
+                pool_id,
+                timestamp,
+                zero_for_one,
+                amount0: swap_delta.amount0(),
+                amount1: swap_delta.amount1(),
+                sqrt_price_x96,
+                tick,
+                liquidity,
+            });
+        }
+        Ok(SwapReport { delta: caller_delta, ..report })
+    }
+
+    /// Gets the ERC6909 claims an owner holds on a currency
+    pub fn claims_balance_of(&self, owner: Address, currency: Currency) -> U256 {
+        self.claims.balance_of(owner, currency.to_id())
+    }
+
+    /// Mints claims on a currency to an owner, who owes the currency in return
+    fn _mint_claims(&mut self, owner: Address, currency: Currency, amount: u128) -> StateResult<()> {
+        self.claims.mint(owner, currency.to_id(), U256::from(amount))?;
+        self._account_delta(currency, -(amount as i128), owner, DeltaReason::Mint)
+    }
+
+    /// Burns an owner's claims on a currency, crediting the currency to the owner
+    fn _burn_claims(&mut self, owner: Address, currency: Currency, amount: u128) -> StateResult<()> {
+        self.claims.burn(owner, currency.to_id(), U256::from(amount))?;
+        self._account_delta(currency, amount as i128, owner, DeltaReason::Burn)
     }
 
     /// Accounts for a balance delta in the pool for a specific address
-    fn _account_pool_balance_delta(&mut self, key: &ManagerPoolKey, delta: BalanceDelta, address: Address) -> StateResult<()> {
-        self._account_delta(Currency::from_address(key.token0), delta.amount0(), address)?;
-        self._account_delta(Currency::from_address(key.token1), delta.amount1(), address)?;
+    fn _account_pool_balance_delta(
+        &mut self,
+        key: &ManagerPoolKey,
+        delta: BalanceDelta,
+        address: Address,
+        reason: DeltaReason,
+    ) -> StateResult<()> {
+        self._account_delta(Currency::from_address(key.token0), delta.amount0(), address, reason)?;
+        self._account_delta(Currency::from_address(key.token1), delta.amount1(), address, reason)?;
         Ok(())
     }
 
+    /// Accounts a delta to a pool's hook, recording it as the pool's share
+    /// of the hook's deltas
+    fn _account_hook_delta(&mut self, key: &ManagerPoolKey, delta: BalanceDelta) -> StateResult<()> {
+        if delta.is_zero() {
+            return Ok(());
+        }
+        self._account_pool_balance_delta(key, delta, key.hooks, DeltaReason::Hook)?;
+        self.pools
+            .get_mut(&pool_key_to_id(key))
+            .ok_or(StateError::PoolNotInitialized)?
+            .record_hook_delta(delta)
+    }
+
     /// Accounts for a delta in a currency for a specific address
-    fn _account_delta(&mut self, currency: Currency, delta: i128, address: Address) -> StateResult<()> {
+    fn _account_delta(&mut self, currency: Currency, delta: i128, address: Address, reason: DeltaReason) -> StateResult<()> {
         if delta == 0 {
             return Ok(());
         }
         
         // Update deltas in the flash loan manager
-        self.flash_loan_manager.update_delta(address, currency, delta)?;
+        self.flash_loan_manager.update_delta_for(address, currency, delta, reason)?;
         
         Ok(())
     }
@@ -407,146 +1474,1255 @@ impl PoolManager {
         self.pools.get(&pool_id)
     }
 
+    /// Estimated memory of the ticks and positions of every pool in memory,
+    /// see [`Pool::memory_usage`] for a single pool
+    pub fn memory_usage(&self) -> MemoryUsage {
+        self.pools.values().map(Pool::memory_usage).sum()
+    }
+
+    /// Derives the furthest price limit for an exact-output swap that can never
+    /// spend more than `max_in`, see [`Pool::derive_price_limit_for_max_in`]
+    ///
+    /// Hooks are not called, so fee overrides and hook deltas are not
+    /// reflected in the limit.
+    pub fn derive_price_limit_for_max_in(
+        &self,
+        key: &ManagerPoolKey,
+        zero_for_one: bool,
+        amount_out: i128,
+        max_in: u128,
+    ) -> StateResult<SqrtPrice> {
+        let pool = self.get_pool(key).ok_or(StateError::PoolNotInitialized)?;
+        pool.derive_price_limit_for_max_in(zero_for_one, amount_out, max_in, key.tick_spacing)
+    }
+
+    /// Gets a read-only view of the pools for quoting, which unlike the
+    /// manager can be shared between threads
+    pub fn quote_view(&self) -> QuoteView<'_> {
+        QuoteView { pools: &self.pools }
+    }
+
+    /// Gets a read-only view of the pools' state keyed by pool ID, see
+    /// [`StateView`]
+    pub fn state_view(&self) -> StateView<'_> {
+        StateView::new(&self.pools)
+    }
+
+    /// Quotes a swap without changing the pool, see [`QuoteView::quote`]
+    pub fn quote(&self, request: &QuoteRequest) -> QuoteResult {
+        self.quote_view().quote(request)
+    }
+
+    /// Quotes a swap with the pool's hook called, so the quote includes the
+    /// hook's fee overrides and deltas
+    ///
+    /// The swap runs on copies of the pool and of its hook, made with
+    /// [`clone_for_quote`](crate::core::hooks::HookWithReturns::clone_for_quote),
+    /// so neither changes. The hook is passed `hook_data` as it would be in
+    /// the swap. Fails with [`HookError::NotQuotable`] when the pool's hook
+    /// can't be copied.
+    pub fn quote_with_hooks(&self, request: &QuoteRequest, hook_data: &[u8]) -> QuoteResult {
+        let key = &request.key;
+        let pool = self.get_pool(key).ok_or(StateError::PoolNotInitialized)?;
+        let mut scratch = PoolManager::new();
+        scratch.clock = self.clock.clone();
+        scratch.swap_config = self.swap_config;
+        scratch.hook_fee_cache = self.hook_fee_cache.clone();
+        scratch.pools.insert(pool_key_to_id(key), pool.clone());
+        if let Some(hook) = self.hook_registry.get_hook(&key.hooks) {
+            let hook = hook.clone_for_quote().ok_or(HookError::NotQuotable(key.hooks))?;
+            scratch.hook_registry.register_hook(key.hooks, hook);
+        }
+
+        let delta = scratch.swap(key, request.zero_for_one, request.amount_specified, request.sqrt_price_limit_x96, hook_data)?;
+        let sqrt_price_x96 = scratch.get_pool(key).ok_or(StateError::PoolNotInitialized)?.slot0.sqrt_price_x96;
+        Ok(Quote { delta, sqrt_price_x96 })
+    }
+
+    /// Quotes independent swaps in parallel, see [`QuoteView::quote_many`]
+    pub fn quote_many(&self, requests: &[QuoteRequest]) -> Vec<QuoteResult> {
+        self.quote_view().quote_many(requests)
+    }
+
+    /// Gets the trading statistics of a pool
+    pub fn pool_stats(&self, key: &ManagerPoolKey) -> Option<&PoolStats> {
+        self.get_pool(key).map(Pool::stats)
+    }
+
+    /// Gets the trading statistics of every pool, with totals
+    pub fn stats(&self) -> ManagerStats {
+        let mut pools: Vec<_> = self.pools.iter().map(|(id, pool)| (*id, *pool.stats())).collect();
+        pools.sort_by_key(|(id, _)| *id);
+
+        ManagerStats {
+            pool_count: pools.len(),
+            swap_count: pools.iter().fold(0u64, |count, (_, stats)| count.saturating_add(stats.swap_count)),
+            last_trade_timestamp: pools.iter().filter_map(|(_, stats)| stats.last_trade_timestamp).max(),
+            pools,
+        }
+    }
+
     /// Gets a mutable reference to a pool
     pub fn get_pool_mut(&mut self, key: &ManagerPoolKey) -> Option<&mut Pool> {
         let pool_id = pool_key_to_id(key);
         self.pools.get_mut(&pool_id)
     }
-    
-    /// Unlocks the pool manager to execute a flash loan callback
-    pub fn unlock<C: FlashLoanCallback>(&mut self, callback: &mut C, data: &[u8]) -> Result<Vec<u8>, FlashLoanError> {
-        self.flash_loan_manager.unlock(callback, data)
-    }
-    
-    /// Take a currency (flash loan)
-    pub fn take(&self, currency: Currency, to: Address, amount: u128) -> Result<(), FlashLoanError> {
-        self.flash_loan_manager.take(currency, to, amount)
+
+    /// Exports the state of all pools as canonical JSON
+    ///
+    /// Pools, ticks and positions are emitted in sorted order, so two managers
+    /// that went through the same operations export byte-identical state.
+    pub fn export_state(&self) -> Vec<u8> {
+        let mut pool_ids: Vec<_> = self.pools.keys().collect();
+        pool_ids.sort();
+
+        let pools: Vec<Value> = pool_ids
+            .into_iter()
+            .map(|id| Self::export_pool(id, &self.pools[id]))
+            .collect();
+
+        serde_json::to_vec(&json!({ "pools": pools })).expect("pool state is serializable")
     }
-    
-    /// Settle an amount of currency (repay flash loan)
-    pub fn settle(&mut self, recipient: Address, value: U256) -> Result<U256, FlashLoanError> {
-        self.flash_loan_manager.settle(recipient, value)
+
+    fn export_pool(id: &PoolId, pool: &Pool) -> Value {
+        let ticks: Vec<Value> = pool.tick_manager
+            .ticks()
+            .map(|(tick, info)| json!({
+                "tick": tick,
+                "liquidity_gross": info.liquidity_gross.as_u128().to_string(),
+                "liquidity_net": info.liquidity_net.to_string(),
+                "fee_growth_outside_0_x128": info.fee_growth_outside_0_x128.to_string(),
+                "fee_growth_outside_1_x128": info.fee_growth_outside_1_x128.to_string(),
+            }))
+            .collect();
+
+        let mut positions: Vec<_> = pool.position_manager.iter().collect();
+        positions.sort_by_key(|(key, _)| (key.owner, key.tick_lower, key.tick_upper, key.salt));
+        let positions: Vec<Value> = positions
+            .into_iter()
+            .map(|(key, position)| json!({
+                "owner": format!("{:?}", Address::from(key.owner)),
+                "tick_lower": key.tick_lower,
+                "tick_upper": key.tick_upper,
+                "salt": Self::hex(&key.salt),
+                "liquidity": position.liquidity.as_u128().to_string(),
+                "fee_growth_inside_0_last_x128": position.fee_growth_inside_0_last_x128.to_string(),
+                "fee_growth_inside_1_last_x128": position.fee_growth_inside_1_last_x128.to_string(),
+                "tokens_owed_0": position.tokens_owed_0.to_string(),
+                "tokens_owed_1": position.tokens_owed_1.to_string(),
+            }))
+            .collect();
+
+        json!({
+            "id": id.to_string(),
+            "sqrt_price_x96": pool.slot0.sqrt_price_x96.to_u256().to_string(),
+            "tick": pool.slot0.tick,
+            "protocol_fee": pool.slot0.protocol_fee,
+            "lp_fee": pool.slot0.lp_fee.get(),
+            "lp_fee_one_for_zero": pool.slot0.lp_fee_one_for_zero.map(FeePips::get),
+            "liquidity": pool.liquidity.as_u128().to_string(),
+            "fee_growth_global_0_x128": pool.fee_growth_global_0_x128.to_string(),
+            "fee_growth_global_1_x128": pool.fee_growth_global_1_x128.to_string(),
+            "ticks": ticks,
+            "positions": positions,
+        })
     }
-    
-    /// Settle on behalf of a recipient
-    pub fn settle_for(&mut self, recipient: Address, value: U256) -> Result<U256, FlashLoanError> {
-        self.flash_loan_manager.settle(recipient, value)
+
+    fn hex(bytes: &[u8]) -> String {
+        let digits: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+        format!("0x{}", digits)
     }
-    
-    /// Sync a currency for settling
-    pub fn sync(&mut self, currency: Currency) {
-        self.flash_loan_manager.sync(currency)
+
+    /// Saves every pool in memory and the claims in one atomic batch
+    ///
+    /// Pools spilled earlier stay in the store as they are. Hooks, currency
+    /// deltas and risk settings are not persisted, so this fails while the
+    /// manager is unlocked.
+    pub fn save_state<S: Storage>(&self, store: &mut PoolStore<S>) -> StorageResult<()> {
+        if self.is_unlocked() {
+            return Err(StateError::ManagerUnlocked.into());
+        }
+        let mut batch = WriteBatch::new();
+        for (pool_id, pool) in &self.pools {
+            store.write_pool(&mut batch, pool_id, pool)?;
+        }
+        store.write_claims(&mut batch, &self.claims)?;
+        store.apply(batch)?;
+        store.flush()
     }
-    
-    /// Get the delta for a currency and address
-    pub fn get_delta(&self, address: Address, currency: Currency) -> i128 {
-        self.flash_loan_manager.get_delta(address, currency)
+
+    /// Loads every stored pool and the claims, replacing pools in memory
+    /// with the same ID
+    pub fn load_state<S: Storage>(&mut self, store: &PoolStore<S>) -> StorageResult<()> {
+        if self.is_unlocked() {
+            return Err(StateError::ManagerUnlocked.into());
+        }
+        let mut pools = HashMap::new();
+        for pool_id in store.pool_ids() {
+            let pool_id = pool_id?;
+            if let Some(mut pool) = store.load_pool(&pool_id)? {
+                pool.set_sqrt_price_table(self.sqrt_price_table.clone());
+                pools.insert(pool_id, pool);
+            }
+        }
+        let claims = store.load_claims()?;
+        self.pools.extend(pools);
+        if let Some(claims) = claims {
+            self.claims = claims;
+        }
+        Ok(())
     }
-    
-    /// Clear a positive delta (used for dust amounts)
-    pub fn clear(&self, currency: Currency, address: Address, amount: u128) -> Result<(), FlashLoanError> {
-        self.flash_loan_manager.clear(currency, address, amount)
+
+    /// Copies the pools in memory, the claims and the timestamp, so the
+    /// manager can be [`restore`](Self::restore)d to this state
+    ///
+    /// Like [`save_state`](Self::save_state), this leaves out hooks and
+    /// fails while the manager is unlocked; hooks keep their own state across
+    /// a restore.
+    pub fn snapshot(&self) -> StateResult<ManagerSnapshot> {
+        if self.is_unlocked() {
+            return Err(StateError::ManagerUnlocked);
+        }
+        Ok(ManagerSnapshot {
+            pools: self.pools.clone(),
+            claims: self.claims.clone(),
+            timestamp: self.timestamp(),
+        })
     }
-    
-    /// ERC6909 function: mint tokens to an address
-    pub fn mint(&mut self, to: Address, id: U256, amount: u128) -> StateResult<()> {
-        // Convert token ID to currency
-        let currency = Currency::from_id(id);
-        
-        // Update delta (negative because tokens are leaving the system)
-        self._account_delta(currency, -(amount as i128), Address::zero())?;
-        
-        // Mint tokens in ERC6909 implementation
-        // This would call into an actual ERC6909 implementation
-        // For now, we'll just track the balances in memory
-        
+
+    /// Replaces the pools in memory, the claims and the timestamp with a
+    /// snapshot's
+    ///
+    /// The snapshot may come from another manager, as long as it has the
+    /// hooks its pools use.
+    pub fn restore(&mut self, snapshot: &ManagerSnapshot) -> StateResult<()> {
+        if self.is_unlocked() {
+            return Err(StateError::ManagerUnlocked);
+        }
+        self.pools = snapshot.pools.clone();
+        for pool in self.pools.values_mut() {
+            pool.set_sqrt_price_table(self.sqrt_price_table.clone());
+        }
+        self.claims = snapshot.claims.clone();
+        self.set_timestamp(snapshot.timestamp);
+        if let Some(cache) = &self.hook_fee_cache {
+            cache.clear();
+        }
         Ok(())
     }
-    
-    /// ERC6909 function: burn tokens from an address
-    pub fn burn(&mut self, from: Address, id: U256, amount: u128) -> StateResult<()> {
-        // Convert token ID to currency
-        let currency = Currency::from_id(id);
-        
-        // Update delta (positive because tokens are entering the system)
-        self._account_delta(currency, amount as i128, Address::zero())?;
-        
-        // Burn tokens in ERC6909 implementation
-        // This would call into an actual ERC6909 implementation
-        // For now, we'll just track the balances in memory
-        
+
+    /// Saves a pool and drops it from memory, so large simulations can keep
+    /// only the pools they are trading; [`load_pool`](Self::load_pool)
+    /// brings it back
+    pub fn spill_pool<S: Storage>(&mut self, key: &ManagerPoolKey, store: &mut PoolStore<S>) -> StorageResult<()> {
+        if self.is_unlocked() {
+            return Err(StateError::ManagerUnlocked.into());
+        }
+        let pool_id = pool_key_to_id(key);
+        let pool = self.pools.get(&pool_id).ok_or(StateError::PoolNotInitialized)?;
+        store.save_pool(&pool_id, pool)?;
+        self.pools.remove(&pool_id);
         Ok(())
     }
+
+    /// Loads a stored pool into memory, replacing any pool with the same key;
+    /// returns whether the pool was stored
+    pub fn load_pool<S: Storage>(&mut self, key: &ManagerPoolKey, store: &PoolStore<S>) -> StorageResult<bool> {
+        let pool_id = pool_key_to_id(key);
+        match store.load_pool(&pool_id)? {
+            Some(mut pool) => {
+                pool.set_sqrt_price_table(self.sqrt_price_table.clone());
+                self.pools.insert(pool_id, pool);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Gets a position in a pool by its owner, range and salt
+    pub fn get_position(&self, key: &ManagerPoolKey, position_key: &PositionKey) -> Option<&Position> {
+        self.get_pool(key)?.position_manager.get(position_key)
+    }
     
-    /// Check if the pool manager is unlocked
-    pub fn is_unlocked(&self) -> bool {
-        self.flash_loan_manager.lock.is_unlocked()
+    /// Whether the pool's current tick is inside a position's range, or
+    /// `None` if the position does not exist
+    pub fn is_position_in_range(&self, key: &ManagerPoolKey, position_key: &PositionKey) -> Option<bool> {
+        let pool = self.get_pool(key)?;
+        pool.position_manager.get(position_key)?;
+        Some(position_key.is_in_range(pool.slot0.tick))
     }
-}
 
-// In a more complete implementation, we would need:
-// 1. The equivalent of ERC6909 token tracking for minted/burned tokens
-// 2. Additional hooks for events
-// 3. More comprehensive error handling
+    /// Gets the positions that entered or left their range in swaps since
+    /// the events were last drained, oldest first
+    pub fn range_events(&self) -> &VecDeque<PositionRangeEvent> {
+        &self.range_events
+    }
 
-// For simplicity, we're not implementing the complete event system here
-// In a real implementation, this would involve integration with the blockchain's event system
+    /// Takes the range events recorded so far
+    pub fn drain_range_events(&mut self) -> Vec<PositionRangeEvent> {
+        self.range_events.drain(..).collect()
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Sets the most range events kept, [`DEFAULT_RANGE_EVENT_LIMIT`] by
+    /// default
+    ///
+    /// Once the limit is reached each new event drops the oldest, so a
+    /// manager whose events are never drained doesn't grow without bound.
+    /// A limit of zero stops recording them, sparing swaps the lookup of
+    /// the positions they moved in or out of range.
+    pub fn set_range_event_limit(&mut self, limit: usize) {
+        self.range_event_limit = limit;
+        let excess = self.range_events.len().saturating_sub(limit);
+        self.range_events.drain(..excess);
+    }
 
-    fn create_test_key() -> ManagerPoolKey {
-        ManagerPoolKey {
-            token0: Address::from_low_u64_be(0),
-            token1: Address::from_low_u64_be(1),
-            fee: 3000, // 0.3%
-            tick_spacing: 60,
-            hooks: Address::zero(),
-            extension_data: vec![],
-        }
+    /// Gets the most range events kept
+    pub fn range_event_limit(&self) -> usize {
+        self.range_event_limit
     }
 
-    #[test]
-    fn test_initialize_pool() {
-        let mut manager = PoolManager::new();
-        let key = create_test_key();
-        let sqrt_price = SqrtPrice::new(U256::from(1u128 << 96)); // 1.0 price
+    /// Reads a position's stored fee growth and the current fee growth
+    /// inside its range, leaving the position untouched
+    pub fn position_fee_growth_snapshot(
+        &self,
+        key: &ManagerPoolKey,
+        position_key: &PositionKey,
+    ) -> Option<FeeGrowthSnapshot> {
+        self.get_pool(key)?.position_fee_growth_snapshot(position_key)
+    }
 
-        let tick = manager.initialize_pool(
-            key.clone(),
-            sqrt_price,
-        ).unwrap();
+    /// Gets the owner's default (zero salt) position in a range
+    pub fn get_default_position(
+        &self,
+        key: &ManagerPoolKey,
+        owner: [u8; 20],
+        tick_lower: i32,
+        tick_upper: i32,
+    ) -> Option<&Position> {
+        self.get_position(key, &PositionKey::default_position(owner, tick_lower, tick_upper))
+    }
 
-        assert_eq!(tick, 0);
+    /// Merges a position into the position with the same owner and range at
+    /// `into_salt`, keeping the fees of both owed
+    ///
+    /// No liquidity enters or leaves the pool, so no hooks are called and
+    /// there is no delta to settle. Returns the key of the merged position.
+    pub fn merge_positions(
+        &mut self,
+        key: &ManagerPoolKey,
+        from: &PositionKey,
+        into_salt: [u8; 32],
+    ) -> StateResult<PositionKey> {
+        let pool_id = pool_key_to_id(key);
+        self._check_not_paused(&pool_id)?;
+        let pool = self.pools.get_mut(&pool_id).ok_or(StateError::PoolNotInitialized)?;
+        pool.merge_positions(from, into_salt)
+    }
 
-        // Verify pool was created
-        let pool = manager.get_pool(&key).unwrap();
-        assert_eq!(pool.slot0.tick, 0);
-        assert_eq!(pool.slot0.lp_fee, 3000);
+    /// Splits a position into the ranges below and above `split_tick`
+    ///
+    /// Like [`merge_positions`](Self::merge_positions), the pool's liquidity
+    /// is unchanged, so no hooks are called and there is no delta to settle.
+    /// Returns the keys of the lower and upper halves.
+    pub fn split_position(
+        &mut self,
+        key: &ManagerPoolKey,
+        position_key: &PositionKey,
+        split_tick: i32,
+    ) -> StateResult<(PositionKey, PositionKey)> {
+        let pool_id = pool_key_to_id(key);
+        self._check_not_paused(&pool_id)?;
+        let pool = self.pools.get_mut(&pool_id).ok_or(StateError::PoolNotInitialized)?;
+        pool.split_position(position_key, split_tick, key.tick_spacing)
     }
-    
-    #[test]
-    fn test_modify_liquidity() {
-        let mut manager = PoolManager::new();
-        let key = create_test_key();
-        let sqrt_price = SqrtPrice::new(U256::from(1u128 << 96)); // 1.0 price
-        
-        // Initialize pool
-        manager.initialize_pool(key.clone(), sqrt_price).unwrap();
-        
-        let owner = Address::from_low_u64_be(123);
-        let owner_bytes: [u8; 20] = owner.0;
-        
-        // Add liquidity
-        let params = ModifyLiquidityParams {
-            owner: owner_bytes,
-            tick_lower: -100,
-            tick_upper: 100,
-            liquidity_delta: 1000000,
-            salt: [0u8; 32],
+
+    /// Updates the LP fees of a dynamic fee pool, which may differ between
+    /// zero-for-one and one-for-zero swaps
+    ///
+    /// Like `updateDynamicLPFee` in v4, static fee pools keep the fee of
+    /// their key. A hook's fee override still takes precedence for the swap
+    /// it is returned for.
+    pub fn update_dynamic_lp_fees(
+        &mut self,
+        key: &ManagerPoolKey,
+        zero_for_one: FeePips,
+        one_for_zero: FeePips,
+    ) -> StateResult<()> {
+        if !crate::core::hooks::is_dynamic_fee(key.fee) {
+            return Err(StateError::FeeNotDynamic(key.fee));
+        }
+        let pool = self.pools.get_mut(&pool_key_to_id(key)).ok_or(StateError::PoolNotInitialized)?;
+        pool.set_lp_fees(zero_for_one, one_for_zero)
+    }
+
+    /// Sets a pool's experimental withdrawal and donation fees; zero
+    /// disables them
+    ///
+    /// Charged fees add to the pool's protocol fee totals, see
+    /// [`AuxiliaryFees`].
+    pub fn set_auxiliary_fees(&mut self, key: &ManagerPoolKey, fees: AuxiliaryFees) -> StateResult<()> {
+        let pool = self.pools.get_mut(&pool_key_to_id(key)).ok_or(StateError::PoolNotInitialized)?;
+        pool.set_auxiliary_fees(fees)
+    }
+
+    /// Donates tokens to the in-range liquidity of a pool
+    ///
+    /// The amounts grow the pool's fee growth, so in-range positions share
+    /// them pro rata, and are owed by `donor` in the currency deltas. The
+    /// pool's hook is called before and after the donation, and an error from
+    /// `before_donate` stops it. When a
+    /// [protocol fee integration](Self::set_protocol_fee_integration) is set,
+    /// the treasury's share of the pool's donations accrues as protocol fees
+    /// instead. Returns the donor's delta, always the full amounts.
+    pub fn donate(
+        &mut self,
+        key: &ManagerPoolKey,
+        donor: Address,
+        amount0: u128,
+        amount1: u128,
+        hook_data: &[u8],
+    ) -> StateResult<BalanceDelta> {
+        let pool_id = pool_key_to_id(key);
+        self._check_not_paused(&pool_id)?;
+        self._check_not_withdraw_only(&pool_id)?;
+        let hook_key = (key.hooks != Address::zero()).then(|| key.to_hook_key());
+        if let (Some(hook_key), Some(hook)) = (&hook_key, self.hook_registry.get_hook_mut(&key.hooks)) {
+            hook.before_donate(donor, hook_key, amount0, amount1, hook_data)
+                .map_err(HookError::wrap_call_failure(key.hooks, HookCallback::BeforeDonate))?;
+        }
+
+        let pool = self.pools.get_mut(&pool_id).ok_or(StateError::PoolNotInitialized)?;
+        let delta = match &mut self.protocol_fees {
+            Some(protocol_fees) => protocol_fees.donate(pool, pool_id, &key.to_hook_key(), amount0, amount1)?.delta,
+            None => pool.donate(amount0, amount1)?,
         };
+        self._account_pool_balance_delta(key, delta, donor, DeltaReason::Donate)?;
+
+        if let (Some(hook_key), Some(hook)) = (&hook_key, self.hook_registry.get_hook_mut(&key.hooks)) {
+            hook.after_donate(donor, hook_key, amount0, amount1, hook_data)
+                .map_err(HookError::wrap_call_failure(key.hooks, HookCallback::AfterDonate))?;
+        }
+        self._emit_event(|timestamp| SimulationEvent::Donate { pool_id, timestamp, donor, amount0, amount1 });
+        Ok(delta)
+    }
+
+    /// Donates fees to a single position, bypassing pro-rata fee growth
+    ///
+    /// The position's fees owed grow by exactly the donated amounts, and the
+    /// same amounts are owed by `donor` in the currency deltas, so the tip
+    /// must be settled before the unlock can complete. Like
+    /// [`donate`](Self::donate) the pool's liquidity and price are unchanged,
+    /// but no hooks are called. Returns the donor's delta.
+    pub fn donate_to_position(
+        &mut self,
+        key: &ManagerPoolKey,
+        position_key: &PositionKey,
+        donor: Address,
+        amount0: u128,
+        amount1: u128,
+    ) -> StateResult<BalanceDelta> {
+        let pool_id = pool_key_to_id(key);
+        self._check_not_paused(&pool_id)?;
+        self._check_not_withdraw_only(&pool_id)?;
+        if !self.pools.contains_key(&pool_id) {
+            return Err(StateError::PoolNotInitialized);
+        }
+
+        // The donor's delta is accounted before the position is credited and
+        // rolled back if either fails, so neither happens without the other
+        let owed = |amount: u128| i128::try_from(amount).map(|amount| -amount).map_err(|_| StateError::AmountOverflow);
+        let delta = BalanceDelta::new(owed(amount0)?, owed(amount1)?);
+        let checkpoint = self.flash_loan_manager.checkpoint();
+        let result = self._account_pool_balance_delta(key, delta, donor, DeltaReason::Donate).and_then(|()| {
+            let pool = self.pools.get_mut(&pool_id).ok_or(StateError::PoolNotInitialized)?;
+            pool.donate_to_position(position_key, amount0, amount1)
+        });
+        if result.is_err() {
+            self.flash_loan_manager.restore(checkpoint);
+        }
+        result
+    }
+
+    /// Closes a position and reopens its liquidity in a new range, swapping
+    /// the freed tokens into the ratio the new range needs, in one unlock
+    ///
+    /// The new range's token ratio is taken at the target pool's price before
+    /// the swap, so the swap's own price impact leaves some tokens over; they
+    /// are returned in the result with the new position taking as much
+    /// liquidity as they allow, and the owner takes them, settling the
+    /// unlock. If any step fails, or the swap loses more than
+    /// `max_slippage` or the new position gets less than `min_liquidity`,
+    /// the pools, claims and deltas are rolled back and the error returned.
+    pub fn rebalance(&mut self, params: &RebalanceParams) -> Result<RebalanceResult, OperationError> {
+        let pools_before = self.pools.clone();
+        let claims_before = self.claims.clone();
+        let checkpoint = self.flash_loan_manager.begin_unlock()?;
+        let deltas_before = self.flash_loan_manager.checkpoint();
+
+        let result = match self.flash_loan_manager.notify_unlock_started() {
+            Ok(()) => self._rebalance(params),
+            Err(error) => Err(error.into()),
+        };
+        if result.is_err() {
+            self.flash_loan_manager.restore(deltas_before);
+        }
+        let unlock_result = self.flash_loan_manager.end_unlock(checkpoint, Ok(Vec::new()));
+        self.hook_context.clear();
+        if result.is_err() || unlock_result.is_err() {
+            self.pools = pools_before;
+            self.claims = claims_before;
+        }
+        let rebalanced = result?;
+        unlock_result?;
+        Ok(rebalanced)
+    }
+
+    /// Runs the steps of a rebalance inside its unlock
+    fn _rebalance(&mut self, params: &RebalanceParams) -> Result<RebalanceResult, OperationError> {
+        let swap_key = params.swap_key.as_ref().unwrap_or(&params.to_key);
+        let same_currencies = |key: &ManagerPoolKey| key.token0 == params.from_key.token0 && key.token1 == params.from_key.token1;
+        if !same_currencies(&params.to_key) || !same_currencies(swap_key) {
+            return Err(StateError::RebalanceCurrencyMismatch.into());
+        }
+
+        // Close the position in full
+        let position = &params.position;
+        let liquidity_removed = self
+            .get_position(&params.from_key, position)
+            .map(|position| position.liquidity.as_u128())
+            .filter(|liquidity| *liquidity > 0)
+            .ok_or(StateError::LiquidityNotFound)?;
+        let remove = ModifyLiquidityParams {
+            owner: Address::from(position.owner),
+            tick_lower: position.tick_lower,
+            tick_upper: position.tick_upper,
+            liquidity_delta: -i128::try_from(liquidity_removed).map_err(|_| StateError::LiquidityOverflow)?,
+            salt: position.salt.into(),
+        };
+        let (removed, _) = self.modify_liquidity(params.from_key.clone(), remove, &[])?;
+        let amount0 = removed.amount0().max(0) as u128;
+        let amount1 = removed.amount1().max(0) as u128;
+
+        // Swap the share of value the new range can't take in its currency
+        let sqrt_price_lower = TickMath::get_sqrt_price_at_tick(params.tick_lower).map_err(|_| StateError::InvalidPrice)?;
+        let sqrt_price_upper = TickMath::get_sqrt_price_at_tick(params.tick_upper).map_err(|_| StateError::InvalidPrice)?;
+        let pool = self.get_pool(&params.to_key).ok_or(StateError::PoolNotInitialized)?;
+        let price = pool.slot0.sqrt_price_x96.to_price_f64();
+        let sqrt_price = price.sqrt();
+        let (lower, upper) = (
+            SqrtPrice::new(sqrt_price_lower).to_price_f64().sqrt(),
+            SqrtPrice::new(sqrt_price_upper).to_price_f64().sqrt(),
+        );
+        let clamped = sqrt_price.clamp(lower, upper);
+        // Value of the token0 and token1 amounts of one unit of liquidity, in token1
+        let value0 = (1.0 / clamped - 1.0 / upper) * price;
+        let value1 = clamped - lower;
+        let share0 = if value0 + value1 > 0.0 { value0 / (value0 + value1) } else { 0.5 };
+        let excess0 = amount0 as f64 * price - share0 * (amount0 as f64 * price + amount1 as f64);
+        let (zero_for_one, amount_in, expected_out) = if excess0 > 0.0 {
+            (true, (excess0 / price) as u128, excess0)
+        } else {
+            (false, (-excess0) as u128, -excess0 / price)
+        };
+
+        let mut swap = None;
+        if amount_in > 0 {
+            let limit = if zero_for_one { TickMath::MIN_SQRT_PRICE + 1 } else { TickMath::MAX_SQRT_PRICE - 1 };
+            let settlement = SwapSettlement::Deltas { owner: Address::from(position.owner) };
+            let amount_specified = SwapAmount::ExactIn(amount_in).amount_specified()?;
+            let delta = self.swap_with_settlement(swap_key, zero_for_one, amount_specified, limit, settlement, &[])?;
+            let received = SwapFlows::of(delta, zero_for_one).received;
+            let slippage = Percent::from_bps(params.max_slippage).unwrap_or(Percent::HUNDRED);
+            let minimum = slippage.complement().of_u128(expected_out as u128);
+            if received < minimum {
+                return Err(StateError::SlippageExceeded { received, minimum }.into());
+            }
+            swap = Some(delta);
+        }
+
+        // Open the new position with as much liquidity as the tokens allow
+        let balance = removed + swap.unwrap_or_default();
+        let sqrt_price_x96 = self.get_pool(&params.to_key).ok_or(StateError::PoolNotInitialized)?.slot0.sqrt_price_x96;
+        let liquidity_added = FixedPoint96::get_liquidity_for_amounts(
+            sqrt_price_x96.to_u256(),
+            sqrt_price_lower,
+            sqrt_price_upper,
+            balance.amount0().max(0) as u128,
+            balance.amount1().max(0) as u128,
+        );
+        let minimum = params.min_liquidity.max(1);
+        if liquidity_added < minimum {
+            return Err(StateError::LiquidityBelowMinimum { liquidity: liquidity_added, minimum }.into());
+        }
+        let add = ModifyLiquidityParams {
+            owner: Address::from(position.owner),
+            tick_lower: params.tick_lower,
+            tick_upper: params.tick_upper,
+            liquidity_delta: i128::try_from(liquidity_added).map_err(|_| StateError::LiquidityOverflow)?,
+            salt: position.salt.into(),
+        };
+        let (added, _) = self.modify_liquidity(params.to_key.clone(), add, &[])?;
+
+        // The owner takes what the new position left over, settling the unlock
+        let leftover = balance + added;
+        for (token, amount) in [(params.to_key.token0, leftover.amount0()), (params.to_key.token1, leftover.amount1())] {
+            if amount > 0 {
+                self.take(Currency::from_address(token), Address::from(position.owner), amount as u128)?;
+            }
+        }
+
+        Ok(RebalanceResult {
+            liquidity_removed,
+            removed,
+            swap,
+            liquidity_added,
+            added,
+            leftover,
+        })
+    }
+
+    /// Sets the sanity check of swap price limits, or turns it off with `None`
+    pub fn set_price_limit_check(&mut self, check: Option<PriceLimitCheck>) {
+        self.price_limit_check = check;
+    }
+
+    /// Gets the sanity check of swap price limits, if on
+    pub fn price_limit_check(&self) -> Option<&PriceLimitCheck> {
+        self.price_limit_check.as_ref()
+    }
+
+    /// Turns strict validation of hook results on or off
+    ///
+    /// Off by default, when the manager applies whatever hooks return. When
+    /// on, a hook that returns a delta or changes the swap amount without
+    /// the matching returns-delta flag in its address, or overrides the LP
+    /// fee of a pool without dynamic fees or above the maximum LP fee, fails
+    /// the operation with a [`HookError`] naming the hook and the callback,
+    /// leaving the pool and the deltas as they were.
+    pub fn set_strict_hook_validation(&mut self, strict: bool) {
+        self.strict_hook_validation = strict;
+    }
+
+    /// Checks whether hook results are validated strictly
+    pub fn strict_hook_validation(&self) -> bool {
+        self.strict_hook_validation
+    }
+
+    /// Sets the step limit of swaps, unbounded by default
+    ///
+    /// A service quoting or executing swaps for untrusted input can bound
+    /// the time a single swap takes through pools with dense ticks, either
+    /// failing such swaps or filling them partially.
+    pub fn set_swap_config(&mut self, config: SwapConfig) {
+        self.swap_config = config;
+    }
+
+    /// Gets the step limit of swaps
+    pub fn swap_config(&self) -> SwapConfig {
+        self.swap_config
+    }
+
+    /// Shares a table of tick sqrt prices with the pools, or stops sharing
+    /// one, which changes no results
+    ///
+    /// Pools initialized or loaded later get the table too. It serves the
+    /// ticks of pools whose tick spacing is a multiple of its own, so a table
+    /// can be computed once and shared by every manager in a process.
+    pub fn set_sqrt_price_table(&mut self, table: Option<Arc<SqrtPriceTable>>) {
+        for pool in self.pools.values_mut() {
+            pool.set_sqrt_price_table(table.clone());
+        }
+        self.sqrt_price_table = table;
+    }
+
+    /// Gets the table of tick sqrt prices shared with the pools, if any
+    pub fn sqrt_price_table(&self) -> Option<&Arc<SqrtPriceTable>> {
+        self.sqrt_price_table.as_ref()
+    }
+
+    /// Sets the receiver of the events of successful operations, returning
+    /// the previous one
+    ///
+    /// Quotes record no events. Events still held back by the previous sink
+    /// are not flushed.
+    pub fn set_event_sink(&mut self, sink: Option<Box<dyn EventSink>>) -> Option<Box<dyn EventSink>> {
+        std::mem::replace(&mut self.event_sink, sink)
+    }
+
+    /// Sets the protocol fee integration, returning the previous one
+    ///
+    /// While set, donations to the pools it opted in route its treasury
+    /// share to the protocol fees it accrues, see
+    /// [`ProtocolFeeIntegration::donate`].
+    pub fn set_protocol_fee_integration(
+        &mut self,
+        integration: Option<ProtocolFeeIntegration>,
+    ) -> Option<ProtocolFeeIntegration> {
+        std::mem::replace(&mut self.protocol_fees, integration)
+    }
+
+    /// Gets the protocol fee integration, if any
+    pub fn protocol_fee_integration(&self) -> Option<&ProtocolFeeIntegration> {
+        self.protocol_fees.as_ref()
+    }
+
+    /// Gets the protocol fee integration mutably, to opt pools in or
+    /// collect the fees it accrued
+    pub fn protocol_fee_integration_mut(&mut self) -> Option<&mut ProtocolFeeIntegration> {
+        self.protocol_fees.as_mut()
+    }
+
+    /// Delivers the events the sink holds back, see [`EventSink::flush`]
+    pub fn flush_events(&mut self) -> IntegrationResult<()> {
+        match &mut self.event_sink {
+            Some(sink) => sink.flush(),
+            None => Ok(()),
+        }
+    }
+
+    /// Records an event with the current timestamp, if there is a sink
+    fn _emit_event(&mut self, event: impl FnOnce(u64) -> SimulationEvent) {
+        let timestamp = self.timestamp();
+        if let Some(sink) = &mut self.event_sink {
+            sink.record(&event(timestamp));
+        }
+    }
+
+    /// Turns caching of fee overrides from pure fee hooks on or off, off by
+    /// default
+    ///
+    /// When on, hooks that declare a [`fee_price_bucket`](Hook::fee_price_bucket)
+    /// are called once per swap, hook data and price bucket of a pool, and
+    /// later swaps reuse their fee override, which saves repeated hook
+    /// computation during route search and batch quoting. Quotes with hooks
+    /// share the cache. A pool's entries are dropped when its liquidity or
+    /// fees change, and all entries when the hook registry is borrowed
+    /// mutably. Turning caching off drops the cache.
+    pub fn set_hook_fee_cache(&mut self, enabled: bool) {
+        self.hook_fee_cache = enabled.then(|| self.hook_fee_cache.take().unwrap_or_default());
+    }
+
+    /// Gets the hook fee cache, if caching is on
+    pub fn hook_fee_cache(&self) -> Option<&HookFeeCache> {
+        self.hook_fee_cache.as_ref()
+    }
+
+    /// Gets the cache and cache key for the fee of a swap, if caching is on
+    /// and the pool's hook declares its fee pure
+    fn _fee_cache_query(
+        &self,
+        key: &ManagerPoolKey,
+        pool_id: &PoolId,
+        params: &SwapParams,
+        hook_data: &[u8],
+    ) -> Option<(HookFeeCache, FeeQuery)> {
+        let cache = self.hook_fee_cache.as_ref()?;
+        // A hook that can return a delta has to be called on every swap
+        if HookFlags::from_address(key.hooks).is_enabled(HookFlags::BEFORE_SWAP_RETURNS_DELTA) {
+            return None;
+        }
+        let width = self.hook_registry.get_hook(&key.hooks)?.fee_price_bucket()?;
+        let pool = self.pools.get(pool_id)?;
+        let query = FeeQuery {
+            bucket: FeeQuery::bucket(pool.slot0.tick, width),
+            zero_for_one: params.zero_for_one,
+            amount_specified: params.amount_specified,
+            sqrt_price_limit_x96: params.sqrt_price_limit_x96.to_u256(),
+            hook_data: hook_data.to_vec(),
+        };
+        Some((cache.clone(), query))
+    }
+
+    /// Rejects a delta from a hook without the flag to return it, in strict mode
+    fn _validate_hook_delta(&self, key: &ManagerPoolKey, callback: HookCallback, flag: u16, returns_delta: bool) -> StateResult<()> {
+        if self.strict_hook_validation && returns_delta && !HookFlags::from_address(key.hooks).is_enabled(flag) {
+            return Err(HookError::DeltaNotPermitted { hook: key.hooks, callback }.into());
+        }
+        Ok(())
+    }
+
+    /// Rejects a fee override the pool can't take, in strict mode
+    fn _validate_fee_override(&self, key: &ManagerPoolKey, callback: HookCallback, fee_override: Option<FeePips>) -> StateResult<()> {
+        let Some(fee) = fee_override.filter(|_| self.strict_hook_validation) else {
+            return Ok(());
+        };
+        if !crate::core::hooks::is_dynamic_fee(key.fee) {
+            return Err(HookError::FeeOverrideNotDynamic { hook: key.hooks, callback }.into());
+        }
+        if fee > FeePips::MAX {
+            return Err(HookError::FeeOverrideTooLarge { hook: key.hooks, callback, fee: fee.get() }.into());
+        }
+        Ok(())
+    }
+
+    /// Gets a handle to the scratch space hooks share within an unlock
+    ///
+    /// Hooks built with the handle can pass data to later callbacks of the
+    /// same unlock, like transient storage. The space is cleared when an
+    /// unlock ends; operations run outside an unlock share it until then.
+    pub fn hook_context(&self) -> HookContext {
+        self.hook_context.clone()
+    }
+
+    /// Unlocks the pool manager to execute a flash loan callback
+    pub fn unlock<C: FlashLoanCallback>(&mut self, callback: &mut C, data: &[u8]) -> Result<Vec<u8>, FlashLoanError> {
+        let result = self.flash_loan_manager.unlock(callback, data);
+        self.hook_context.clear();
+        result
+    }
+    
+    /// Unlocks the manager and runs a batch of operations, recording the
+    /// result of each instead of stopping at the first failure
+    ///
+    /// A failed operation is rolled back on its own, including the deltas
+    /// and claims it changed and its pool, and the batch continues; on chain
+    /// the whole transaction would revert instead. Hook state is not rolled
+    /// back, except for the shared [`hook_context`](Self::hook_context). The unlock itself still fails like [`unlock`](Self::unlock) when
+    /// loans are unpaid, deltas are left open or an observer rejects it, and then every operation
+    /// is rolled back and `unlock_error` is set.
+    pub fn unlock_batch(&mut self, operations: &[UnlockOperation]) -> BatchUnlockResult {
+        let pools_before = self.pools.clone();
+        let claims_before = self.claims.clone();
+        let checkpoint = match self.flash_loan_manager.begin_unlock() {
+            Ok(checkpoint) => checkpoint,
+            Err(error) => return BatchUnlockResult { results: Vec::new(), unlock_error: Some(error), success: false },
+        };
+
+        let started = self.flash_loan_manager.notify_unlock_started();
+        let results = if started.is_ok() {
+            operations.iter().map(|operation| self._run_operation(operation)).collect()
+        } else {
+            Vec::new()
+        };
+        let unlock_error = self.flash_loan_manager.end_unlock(checkpoint, started.map(|()| Vec::new())).err();
+        self.hook_context.clear();
+        if unlock_error.is_some() {
+            self.pools = pools_before;
+            self.claims = claims_before;
+        }
+        let success = unlock_error.is_none() && results.iter().all(Result::is_ok);
+        BatchUnlockResult { results, unlock_error, success }
+    }
+
+    /// Runs one operation of a batched unlock, rolling it back if it fails
+    fn _run_operation(&mut self, operation: &UnlockOperation) -> Result<OperationOutput, OperationError> {
+        let checkpoint = self.flash_loan_manager.checkpoint();
+        let claims_before = self.claims.clone();
+        let context_before = self.hook_context.snapshot();
+        let pool_before = match operation {
+            UnlockOperation::Initialize { key, .. }
+            | UnlockOperation::ModifyLiquidity { key, .. }
+            | UnlockOperation::Swap { key, .. } => {
+                let pool_id = pool_key_to_id(key);
+                Some((pool_id, self.pools.get(&pool_id).cloned()))
+            }
+            _ => None,
+        };
+
+        let result = match operation {
+            UnlockOperation::Initialize { key, sqrt_price_x96 } => self
+                .initialize_pool(key.clone(), *sqrt_price_x96)
+                .map(OperationOutput::Tick)
+                .map_err(OperationError::from),
+            UnlockOperation::ModifyLiquidity { key, params, hook_data } => self
+                .modify_liquidity(key.clone(), params.clone(), hook_data)
+                .map(|(delta, _)| OperationOutput::Delta(delta))
+                .map_err(OperationError::from),
+            UnlockOperation::Swap { key, zero_for_one, amount_specified, sqrt_price_limit_x96, settlement, hook_data } => self
+                .swap_with_settlement(key, *zero_for_one, *amount_specified, *sqrt_price_limit_x96, *settlement, hook_data)
+                .map(OperationOutput::Delta)
+                .map_err(OperationError::from),
+            UnlockOperation::Take { currency, to, amount } => self
+                .take(*currency, *to, *amount)
+                .map(|()| OperationOutput::Done)
+                .map_err(OperationError::from),
+            UnlockOperation::Settle { currency, recipient, value } => self
+                .pay_and_settle(*currency, *recipient, *value)
+                .map(OperationOutput::Settled)
+                .map_err(OperationError::from),
+            UnlockOperation::Clear { currency, account, amount } => self
+                .clear(*currency, *account, *amount)
+                .map(|()| OperationOutput::Done)
+                .map_err(OperationError::from),
+            UnlockOperation::Mint { to, id, amount } => self
+                .mint(*to, *id, *amount)
+                .map(|()| OperationOutput::Done)
+                .map_err(OperationError::from),
+            UnlockOperation::Burn { from, id, amount } => self
+                .burn(*from, *id, *amount)
+                .map(|()| OperationOutput::Done)
+                .map_err(OperationError::from),
+        };
+
+        if result.is_err() {
+            self.flash_loan_manager.restore(checkpoint);
+            self.claims = claims_before;
+            self.hook_context.restore(context_before);
+            match pool_before {
+                Some((pool_id, Some(pool))) => {
+                    self.pools.insert(pool_id, pool);
+                }
+                Some((pool_id, None)) => {
+                    self.pools.remove(&pool_id);
+                }
+                None => {}
+            }
+        }
+        result
+    }
+
+    /// Registers an observer notified at the start and end of every unlock,
+    /// independently of pools
+    pub fn add_unlock_observer(&mut self, observer: Box<dyn UnlockObserver>) -> UnlockObserverId {
+        self.flash_loan_manager.add_unlock_observer(observer)
+    }
+
+    /// Removes an unlock observer
+    pub fn remove_unlock_observer(&mut self, id: UnlockObserverId) -> Option<Box<dyn UnlockObserver>> {
+        self.flash_loan_manager.remove_unlock_observer(id)
+    }
+
+    /// Take a currency (flash loan), owing the amount plus the currency's flash loan fee
+    pub fn take(&mut self, currency: Currency, to: Address, amount: u128) -> Result<(), FlashLoanError> {
+        self.flash_loan_manager.take(currency, to, amount)
+    }
+    
+    /// Sets the flash loan fee for a currency in basis points
+    pub fn set_flash_fee(&mut self, currency: Currency, fee_bps: Bps) -> Result<(), FlashLoanError> {
+        self.flash_loan_manager.set_flash_fee(currency, fee_bps)
+    }
+    
+    /// Gets the flash loan fee owed for taking an amount of a currency
+    pub fn flash_fee(&self, currency: Currency, amount: u128) -> u128 {
+        self.flash_loan_manager.flash_fee(currency, amount)
+    }
+    
+    /// Sets who earns the flash loan fees
+    pub fn set_flash_fee_recipient(&mut self, recipient: FlashFeeRecipient) {
+        self.flash_loan_manager.set_flash_fee_recipient(recipient)
+    }
+    
+    /// Gets the flash loan fees accrued by a recipient in a currency
+    pub fn flash_fees_accrued(&self, recipient: FlashFeeRecipient, currency: Currency) -> u128 {
+        self.flash_loan_manager.flash_fees_accrued(recipient, currency)
+    }
+    
+    /// Collects the flash loan fees accrued by a recipient in a currency
+    pub fn collect_flash_fees(&mut self, recipient: FlashFeeRecipient, currency: Currency) -> u128 {
+        self.flash_loan_manager.collect_flash_fees(recipient, currency)
+    }
+    
+    /// Restricts the currencies that can be taken, settled, minted and burned,
+    /// and caps how much of each can be taken per unlock
+    pub fn set_currency_policy(&mut self, policy: CurrencyPolicy) {
+        self.flash_loan_manager.set_currency_policy(policy)
+    }
+    
+    /// Gets the currency policy
+    pub fn currency_policy(&self) -> &CurrencyPolicy {
+        self.flash_loan_manager.currency_policy()
+    }
+    
+    /// Settle an amount of currency (repay flash loan)
+    pub fn settle(&mut self, recipient: Address, value: U256) -> Result<U256, FlashLoanError> {
+        self.flash_loan_manager.settle(recipient, value)
+    }
+    
+    /// Settle on behalf of a recipient
+    pub fn settle_for(&mut self, recipient: Address, value: U256) -> Result<U256, FlashLoanError> {
+        self.flash_loan_manager.settle(recipient, value)
+    }
+    
+    /// Sync a currency for settling
+    pub fn sync(&mut self, currency: Currency) {
+        self.flash_loan_manager.sync(currency)
+    }
+    
+    /// Syncs a currency and settles `value` of it paid by the recipient, as a
+    /// router's sync, transfer and settle would
+    ///
+    /// With a token ledger, ERC-20s are transferred from the recipient to the
+    /// manager before settling and native value is sent along, so either
+    /// fails when the recipient holds too little. A transfer whose settle
+    /// fails is undone, so the recipient keeps its tokens.
+    pub fn pay_and_settle(&mut self, currency: Currency, recipient: Address, value: U256) -> Result<U256, FlashLoanError> {
+        self.sync(currency);
+        if currency.is_native() || self.flash_loan_manager.token_ledger().is_none() {
+            return self.settle(recipient, value);
+        }
+        let checkpoint = self.flash_loan_manager.checkpoint();
+        let mut pay = || {
+            if let Some(ledger) = self.flash_loan_manager.token_ledger_mut() {
+                ledger.transfer(currency, recipient, POOL_MANAGER_ADDRESS, value)?;
+            }
+            self.settle(recipient, U256::zero())
+        };
+        let result = pay();
+        if result.is_err() {
+            self.flash_loan_manager.restore(checkpoint);
+        }
+        result
+    }
+    
+    /// Installs a token ledger, so that takes and settles move tokens held
+    /// by the manager at [`POOL_MANAGER_ADDRESS`] instead of only
+    /// accounting deltas
+    ///
+    /// Ledger balances changed in an unlock are rolled back with its deltas
+    /// when it fails.
+    pub fn set_token_ledger(&mut self, ledger: TokenLedger) {
+        self.flash_loan_manager.set_token_ledger(ledger)
+    }
+    
+    /// Gets the token ledger, if one is installed
+    pub fn token_ledger(&self) -> Option<&TokenLedger> {
+        self.flash_loan_manager.token_ledger()
+    }
+    
+    /// Gets the token ledger mutably, e.g. to fund accounts
+    pub fn token_ledger_mut(&mut self) -> Option<&mut TokenLedger> {
+        self.flash_loan_manager.token_ledger_mut()
+    }
+    
+    /// Gets the delta changes of the current unlock so far, empty while locked
+    pub fn delta_journal(&self) -> &DeltaJournal {
+        self.flash_loan_manager.delta_journal()
+    }
+    
+    /// Gets every delta change of the last unlock, whether it succeeded or not
+    pub fn last_unlock_journal(&self) -> Option<&DeltaJournal> {
+        self.flash_loan_manager.last_unlock_journal()
+    }
+    
+    /// Get the delta for a currency and address
+    pub fn get_delta(&self, address: Address, currency: Currency) -> i128 {
+        self.flash_loan_manager.get_delta(address, currency)
+    }
+    
+    /// Gets the delta of a currency and address accounted in the current
+    /// unlock, zero while locked
+    pub fn currency_delta(&self, address: Address, currency: Currency) -> i128 {
+        self.flash_loan_manager.currency_delta(address, currency)
+    }
+    
+    /// Gets the number of deltas the current unlock has left open, which
+    /// must be zero when it ends
+    pub fn nonzero_delta_count(&self) -> usize {
+        self.flash_loan_manager.nonzero_delta_count()
+    }
+    
+    /// Clear a positive delta (used for dust amounts), forfeiting it
+    pub fn clear(&mut self, currency: Currency, address: Address, amount: u128) -> Result<(), FlashLoanError> {
+        self.flash_loan_manager.clear(currency, address, amount)
+    }
+    
+    /// ERC6909 function: mint tokens to an address
+    pub fn mint(&mut self, to: Address, id: U256, amount: u128) -> StateResult<()> {
+        // Convert token ID to currency
+        let currency = self._claim_currency(id)?;
+        
+        // Record the claims in the manager's ERC6909 ledger
+        self.claims.mint(to, id, U256::from(amount))?;
+        
+        // Update delta (negative because tokens are leaving the system)
+        self._account_delta(currency, -(amount as i128), Address::zero(), DeltaReason::Mint)?;
+        
+        Ok(())
+    }
+    
+    /// ERC6909 function: burn tokens from an address
+    pub fn burn(&mut self, from: Address, id: U256, amount: u128) -> StateResult<()> {
+        // Convert token ID to currency
+        let currency = self._claim_currency(id)?;
         
+        // Burn the claims from the manager's ERC6909 ledger
+        self.claims.burn(from, id, U256::from(amount))?;
+        
+        // Update delta (positive because tokens are entering the system)
+        self._account_delta(currency, amount as i128, Address::zero(), DeltaReason::Burn)?;
+        
+        Ok(())
+    }
+    
+    /// Check if the pool manager is unlocked
+    pub fn is_unlocked(&self) -> bool {
+        self.flash_loan_manager.lock.is_unlocked()
+    }
+}
+
+// In a more complete implementation, we would need:
+// 1. The equivalent of ERC6909 token tracking for minted/burned tokens
+// 2. Additional hooks for events
+// 3. More comprehensive error handling
+
+// For simplicity, we're not implementing the complete event system here
+// In a real implementation, this would involve integration with the blockchain's event system
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::state::{CrossDirection, OnStepLimit, Salt};
+
+    fn create_test_key() -> ManagerPoolKey {
+        key_for(Address::from_low_u64_be(0), Address::from_low_u64_be(1))
+    }
+
+    fn key_for(token_a: Address, token_b: Address) -> ManagerPoolKey {
+        ManagerPoolKey::new(token_a, token_b, 3000, TickSpacing::new(60).unwrap(), Address::zero()).unwrap()
+    }
+
+    #[test]
+    fn test_pool_key_sorts_currencies() {
+        let (low, high) = (Address::from_low_u64_be(1), Address::repeat_byte(0xFF));
+        let key = key_for(high, low);
+        assert_eq!((key.token0(), key.token1()), (low, high));
+        assert_eq!(key, key_for(low, high));
+        assert_eq!(pool_key_to_id(&key), pool_key_to_id(&key_for(low, high)));
+
+        let spacing = TickSpacing::new(60).unwrap();
+        assert!(matches!(
+            ManagerPoolKey::new(low, low, 3000, spacing, Address::zero()),
+            Err(PoolError::CurrenciesOutOfOrderOrEqual(a, b)) if a == low && b == low
+        ));
+    }
+
+    #[test]
+    fn test_pool_id_hashes_the_abi_encoded_key() {
+        use std::collections::HashSet;
+        use ethers::{abi::{encode, Token}, types::I256};
+
+        let key = key_for(Address::repeat_byte(0x11), Address::repeat_byte(0x22));
+        let encoded = encode(&[
+            Token::Address(key.token0()),
+            Token::Address(key.token1()),
+            Token::Uint(key.fee().into()),
+            Token::Int(I256::from(key.tick_spacing().get()).into_raw()),
+            Token::Address(key.hooks()),
+        ]);
+        assert_eq!(pool_key_to_id(&key), PoolId(keccak256(encoded)));
+
+        // Pools of the same currencies differ by every other field of the key
+        let spacing = TickSpacing::new(60).unwrap();
+        let (token0, token1) = (key.token0(), key.token1());
+        let ids: HashSet<_> = [
+            key.clone(),
+            ManagerPoolKey::new(token0, token1, 500, spacing, Address::zero()).unwrap(),
+            ManagerPoolKey::new(token0, token1, 3000, TickSpacing::new(10).unwrap(), Address::zero()).unwrap(),
+            ManagerPoolKey::new(token0, token1, 3000, spacing, Address::repeat_byte(0x33)).unwrap(),
+        ].iter().map(pool_key_to_id).collect();
+        assert_eq!(ids.len(), 4);
+    }
+
+    #[test]
+    fn test_pool_id_hex_round_trip() {
+        let id = PoolId::from_key(&key_for(Address::repeat_byte(0xAB), Address::repeat_byte(0xCD)));
+        let hex = id.to_string();
+        assert_eq!(hex.len(), 66);
+        assert_eq!(hex, format!("0x{}", ethers::utils::hex::encode(id.0)));
+        assert_eq!(hex.parse::<PoolId>(), Ok(id));
+        assert_eq!(hex[2..].to_uppercase().parse::<PoolId>(), Ok(id));
+        assert_eq!("0x1234".parse::<PoolId>(), Err(ParsePoolIdError::InvalidLength(4)));
+        assert_eq!(format!("0x{}", "zz".repeat(32)).parse::<PoolId>(), Err(ParsePoolIdError::InvalidDigit));
+        // A sign is not a hex digit, even where `from_str_radix` would take it
+        assert_eq!(format!("+f{}", "00".repeat(31)).parse::<PoolId>(), Err(ParsePoolIdError::InvalidDigit));
+        assert_eq!(format!("0x-1{}", "00".repeat(31)).parse::<PoolId>(), Err(ParsePoolIdError::InvalidDigit));
+
+        let json = serde_json::to_string(&id).unwrap();
+        assert_eq!(json, format!("\"{}\"", hex));
+        assert_eq!(serde_json::from_str::<PoolId>(&json).unwrap(), id);
+    }
+
+    #[test]
+    fn test_initialize_pool() {
+        let mut manager = PoolManager::new();
+        let key = create_test_key();
+        let sqrt_price = SqrtPrice::ONE; // 1.0 price
+
+        let tick = manager.initialize_pool(
+            key.clone(),
+            sqrt_price,
+        ).unwrap();
+
+        assert_eq!(tick, 0);
+
+        // Verify pool was created
+        let pool = manager.get_pool(&key).unwrap();
+        assert_eq!(pool.slot0.tick, 0);
+        assert_eq!(pool.slot0.lp_fee, FeePips::new(3000));
+    }
+
+    #[test]
+    fn test_hook_rejects_pool_parameters() {
+        use crate::core::hooks::InitializeRejection;
+
+        let mut manager = PoolManager::new();
+        let hooks = HookFlags::new(HookFlags::BEFORE_INITIALIZE).apply_to_address(Address::repeat_byte(0xA0));
+        let hook = crate::core::hooks::PoolParametersHook::new()
+            .with_tick_spacing(TickSpacing::new(10).unwrap())
+            .with_fee(500);
+        manager.hook_registry_mut().register_hook(hooks, Box::new(hook));
+        let key = |token0: u64, fee: u32, spacing: i32| {
+            ManagerPoolKey::new(
+                Address::from_low_u64_be(token0),
+                Address::repeat_byte(0xEE),
+                fee,
+                TickSpacing::new(spacing).unwrap(),
+                hooks,
+            ).unwrap()
+        };
+
+        let wrong_spacing = key(1, 500, 60);
+        assert!(matches!(
+            manager.initialize_pool(wrong_spacing.clone(), SqrtPrice::ONE),
+            Err(PoolError::RejectedByHook(reason))
+                if reason == InitializeRejection::UnsupportedTickSpacing { tick_spacing: 60, required: Some(10) }
+        ));
+        assert!(manager.get_pool(&wrong_spacing).is_none());
+        assert!(matches!(
+            manager.initialize_pool(key(2, 3000, 10), SqrtPrice::ONE),
+            Err(PoolError::RejectedByHook(reason))
+                if reason == InitializeRejection::UnsupportedFee { fee: 3000, required: Some(500) }
+        ));
+
+        // A rejection in a batched unlock keeps its reason
+        let result = manager.unlock_batch(&[UnlockOperation::Initialize { key: wrong_spacing, sqrt_price_x96: SqrtPrice::ONE }]);
+        assert!(matches!(
+            &result.results[0],
+            Err(OperationError::Pool(PoolError::RejectedByHook(InitializeRejection::UnsupportedTickSpacing { .. })))
+        ));
+
+        let accepted = key(3, 500, 10);
+        manager.initialize_pool(accepted.clone(), SqrtPrice::ONE).unwrap();
+        assert!(manager.get_pool(&accepted).is_some());
+    }
+    
+    #[test]
+    fn test_modify_liquidity() {
+        let mut manager = PoolManager::new();
+        let key = create_test_key();
+        let sqrt_price = SqrtPrice::ONE; // 1.0 price
+        
+        // Initialize pool
+        manager.initialize_pool(key.clone(), sqrt_price).unwrap();
+        
+        let owner = Address::from_low_u64_be(123);
+        
+        // Add liquidity
+        let params = ModifyLiquidityParams {
+            owner,
+            tick_lower: -120,
+            tick_upper: 120,
+            liquidity_delta: 1000000,
+            salt: Salt::ZERO,
+        };
+        
+        // Ticks off the tick spacing have no bit in the tick bitmap
+        let misaligned = ModifyLiquidityParams { tick_lower: -100, ..params.clone() };
+        assert!(matches!(
+            manager.modify_liquidity(key.clone(), misaligned, &[]),
+            Err(StateError::TickMisaligned(-100, 60))
+        ));
+
         let (delta, _) = manager.modify_liquidity(key.clone(), params.clone(), &[]).unwrap();
         
         // Delta should be negative since we're adding liquidity
@@ -554,23 +2730,1625 @@ mod tests {
         
         // Remove liquidity
         let remove_params = ModifyLiquidityParams {
-            owner: owner_bytes,
-            tick_lower: -100,
-            tick_upper: 100,
+            owner,
+            tick_lower: -120,
+            tick_upper: 120,
             liquidity_delta: -1000000,
-            salt: [0u8; 32],
+            salt: Salt::ZERO,
+        };
+        
+        let (delta, fees) = manager.modify_liquidity(key.clone(), remove_params, &[]).unwrap();
+        
+        // Delta should be positive since we're removing liquidity
+        assert!(delta.amount0() > 0 || delta.amount1() > 0);
+        
+        // No fees expected as no swaps occurred
+        assert_eq!(fees.amount0(), 0);
+        assert_eq!(fees.amount1(), 0);
+    }
+    
+    #[test]
+    fn test_modify_liquidity_auto_compound() {
+        let mut manager = PoolManager::new();
+        let key = create_test_key();
+        let sqrt_price = SqrtPrice::ONE; // 1.0 price
+        manager.initialize_pool(key.clone(), sqrt_price).unwrap();
+
+        let owner = Address::from_low_u64_be(123);
+        let params = ModifyLiquidityParams {
+            owner,
+            tick_lower: -120,
+            tick_upper: 120,
+            liquidity_delta: 1_000_000_000,
+            salt: Salt::ZERO,
+        };
+        manager.modify_liquidity(key.clone(), params.clone(), &[]).unwrap();
+
+        // Accrue fees to the position
+        manager.get_pool_mut(&key).unwrap().donate(5_000_000, 5_000_000).unwrap();
+
+        // Remove a part of the position and reinvest the collected fees
+        let remove_params = ModifyLiquidityParams {
+            liquidity_delta: -100_000_000,
+            ..params
+        };
+        let (delta, fees, compounded) = manager.modify_liquidity_with_options(
+            key.clone(),
+            remove_params,
+            ModifyLiquidityOptions { auto_compound: true },
+            &[],
+        ).unwrap();
+
+        assert!(fees.amount0() > 0 || fees.amount1() > 0);
+        assert!(compounded > 0);
+
+        // Only the principal of the removed liquidity and the uncompounded dust is paid out
+        let position = manager.get_position(&key, &params.position_key()).unwrap();
+        assert_eq!(position.liquidity.as_u128(), 900_000_000 + compounded);
+        assert!(delta.amount0() >= 0 && delta.amount1() >= 0);
+    }
+
+    #[test]
+    fn test_modify_liquidity_records_single_position_for_owner() {
+        let mut manager = PoolManager::new();
+        let key = create_test_key();
+        manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+
+        let params = ModifyLiquidityParams {
+            owner: Address::from_low_u64_be(123),
+            tick_lower: -120,
+            tick_upper: 120,
+            liquidity_delta: 1_000_000,
+            salt: Salt([7u8; 32]),
+        };
+        manager.modify_liquidity(key.clone(), params.clone(), &[]).unwrap();
+
+        let position_key = params.position_key();
+        let position = manager.get_position(&key, &position_key).unwrap();
+        assert_eq!(position.liquidity.as_u128(), 1_000_000);
+
+        // Neither a zeroed owner nor a zeroed salt holds a shadow record
+        assert!(manager.get_position(&key, &PositionKey { owner: [0u8; 20], ..position_key.clone() }).is_none());
+        assert!(manager.get_position(&key, &PositionKey { salt: [0u8; 32], ..position_key }).is_none());
+    }
+
+    #[test]
+    fn test_modify_liquidity_attributes_fees_to_owner() {
+        let mut manager = PoolManager::new();
+        let key = create_test_key();
+        manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+
+        let alice = Address::from_low_u64_be(1);
+        let bob = Address::from_low_u64_be(2);
+        let params = |owner: Address, liquidity_delta: i128| ModifyLiquidityParams {
+            owner,
+            tick_lower: -120,
+            tick_upper: 120,
+            liquidity_delta,
+            salt: Salt::ZERO,
+        };
+
+        manager.modify_liquidity(key.clone(), params(alice, 3_000_000_000), &[]).unwrap();
+        manager.modify_liquidity(key.clone(), params(bob, 1_000_000_000), &[]).unwrap();
+
+        // Fees accrue to the pool after both positions exist
+        manager.get_pool_mut(&key).unwrap().donate(4_000_000, 8_000_000).unwrap();
+
+        let (_, alice_fees) = manager.modify_liquidity(key.clone(), params(alice, -3_000_000_000), &[]).unwrap();
+        let (_, bob_fees) = manager.modify_liquidity(key.clone(), params(bob, -1_000_000_000), &[]).unwrap();
+
+        // Fees are split by liquidity share, allowing for rounding down
+        assert!((2_999_999..=3_000_000).contains(&alice_fees.amount0()));
+        assert!((5_999_999..=6_000_000).contains(&alice_fees.amount1()));
+        assert!((999_999..=1_000_000).contains(&bob_fees.amount0()));
+        assert!((1_999_999..=2_000_000).contains(&bob_fees.amount1()));
+
+        // A position that joins after the donation earns nothing from it
+        manager.modify_liquidity(key.clone(), params(alice, 1_000_000_000), &[]).unwrap();
+        let (_, late_fees) = manager.modify_liquidity(key.clone(), params(alice, -1_000_000_000), &[]).unwrap();
+        assert!(late_fees.is_zero());
+    }
+
+    #[test]
+    fn test_range_events_follow_tick_moves() {
+        let mut manager = PoolManager::new();
+        let key = create_test_key();
+        let pool_id = pool_key_to_id(&key);
+        manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+        let owner = Address::from_low_u64_be(1);
+        let inner = ModifyLiquidityParams::default_position(owner, -120, 120, 1_000_000_000);
+        let upper = ModifyLiquidityParams::default_position(owner, 120, 600, 1_000_000_000);
+        manager.modify_liquidity(key.clone(), inner.clone(), &[]).unwrap();
+        manager.modify_liquidity(key.clone(), upper.clone(), &[]).unwrap();
+        assert_eq!(manager.is_position_in_range(&key, &inner.position_key()), Some(true));
+        assert_eq!(manager.is_position_in_range(&key, &upper.position_key()), Some(false));
+
+        // A swap within the current range moves no position in or out
+        manager.swap(&key, false, -1_000, TickMath::MAX_SQRT_PRICE - 1, &[]).unwrap();
+        assert!(manager.range_events().is_empty());
+
+        manager.swap(&key, false, -10_000_000, TickMath::MAX_SQRT_PRICE - 1, &[]).unwrap();
+        let tick = manager.get_pool(&key).unwrap().slot0.tick;
+        assert!(tick >= 120);
+        assert_eq!(manager.drain_range_events(), vec![
+            PositionRangeEvent::PositionWentOutOfRange { pool_id, position: inner.position_key(), tick },
+            PositionRangeEvent::PositionBackInRange { pool_id, position: upper.position_key(), tick },
+        ]);
+        assert_eq!(manager.is_position_in_range(&key, &inner.position_key()), Some(false));
+
+        manager.swap(&key, true, -20_000_000, SqrtPrice::ONE.to_u256(), &[]).unwrap();
+        let tick = manager.get_pool(&key).unwrap().slot0.tick;
+        assert!((-120..120).contains(&tick));
+        assert_eq!(manager.drain_range_events(), vec![
+            PositionRangeEvent::PositionBackInRange { pool_id, position: inner.position_key(), tick },
+            PositionRangeEvent::PositionWentOutOfRange { pool_id, position: upper.position_key(), tick },
+        ]);
+        assert!(manager.range_events().is_empty());
+
+        // Past the limit the oldest events are dropped, and none are kept
+        // at a limit of zero
+        manager.set_range_event_limit(1);
+        manager.swap(&key, false, -10_000_000, TickMath::MAX_SQRT_PRICE - 1, &[]).unwrap();
+        let tick = manager.get_pool(&key).unwrap().slot0.tick;
+        assert_eq!(manager.drain_range_events(), vec![
+            PositionRangeEvent::PositionBackInRange { pool_id, position: upper.position_key(), tick },
+        ]);
+        manager.set_range_event_limit(0);
+        manager.swap(&key, true, -20_000_000, SqrtPrice::ONE.to_u256(), &[]).unwrap();
+        assert!(manager.range_events().is_empty());
+    }
+
+    #[test]
+    fn test_position_fee_growth_snapshot() {
+        let mut manager = PoolManager::new();
+        let key = create_test_key();
+        manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+        let params = ModifyLiquidityParams::default_position(Address::from_low_u64_be(1), -120, 120, 1_000_000_000);
+        let position_key = params.position_key();
+        manager.modify_liquidity(key.clone(), params, &[]).unwrap();
+        assert!(manager.position_fee_growth_snapshot(&key, &PositionKey::default_position([9; 20], -120, 120)).is_none());
+
+        let before = manager.position_fee_growth_snapshot(&key, &position_key).unwrap();
+        assert_eq!(before.pending_fees(), (0, 0));
+
+        manager.get_pool_mut(&key).unwrap().donate(1_000_000, 2_000_000).unwrap();
+        let position_before = manager.get_position(&key, &position_key).unwrap().clone();
+        let snapshot = manager.get_pool(&key).unwrap().position_fee_growth_snapshot(&position_key).unwrap();
+
+        // The snapshot sees the new growth without crediting it to the position
+        assert_eq!(snapshot.fee_growth_inside_0_last_x128, before.fee_growth_inside_0_x128);
+        assert!(snapshot.fee_growth_inside_0_x128 > snapshot.fee_growth_inside_0_last_x128);
+        let (pending0, pending1) = snapshot.pending_fees();
+        assert!((999_999..=1_000_000).contains(&pending0));
+        assert!((1_999_999..=2_000_000).contains(&pending1));
+        assert_eq!(manager.get_position(&key, &position_key).unwrap(), &position_before);
+    }
+
+    #[test]
+    fn test_merge_and_split_default_positions() {
+        let mut manager = PoolManager::new();
+        let key = create_test_key();
+        manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+
+        let owner = Address::from_low_u64_be(123);
+        let salted = ModifyLiquidityParams {
+            salt: Salt([7u8; 32]),
+            ..ModifyLiquidityParams::default_position(owner, -120, 120, 1_000_000)
+        };
+        manager.modify_liquidity(key.clone(), ModifyLiquidityParams::default_position(owner, -120, 120, 1_000_000), &[]).unwrap();
+        manager.modify_liquidity(key.clone(), salted.clone(), &[]).unwrap();
+
+        let salted_key = salted.position_key();
+        manager.merge_positions(&key, &salted_key, PositionKey::DEFAULT_SALT).unwrap();
+        assert!(manager.get_position(&key, &salted_key).is_none());
+        assert_eq!(manager.get_default_position(&key, owner.0, -120, 120).unwrap().liquidity.as_u128(), 2_000_000);
+
+        let (lower, upper) = manager
+            .split_position(&key, &PositionKey::default_position(owner.0, -120, 120), 0)
+            .unwrap();
+        assert_eq!(lower, PositionKey::default_position(owner.0, -120, 0));
+        assert_eq!(upper, PositionKey::default_position(owner.0, 0, 120));
+        assert!(manager.get_default_position(&key, owner.0, -120, 120).is_none());
+        assert_eq!(manager.get_pool(&key).unwrap().liquidity.as_u128(), 2_000_000);
+
+        manager.risk_manager_mut().pause_pool(pool_key_to_id(&key));
+        assert!(matches!(
+            manager.split_position(&key, &lower, -60),
+            Err(StateError::PoolPaused)
+        ));
+    }
+
+    #[test]
+    fn test_donate_routes_treasury_share() {
+        use crate::core::math::types::Percent;
+
+        let mut manager = PoolManager::new();
+        let key = create_test_key();
+        manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+        let lp = ModifyLiquidityParams::default_position(Address::from_low_u64_be(1), -120, 120, 1_000_000_000);
+        manager.modify_liquidity(key.clone(), lp.clone(), &[]).unwrap();
+
+        let controller = Address::from_low_u64_be(9);
+        let mut integration = ProtocolFeeIntegration::new(controller);
+        integration.set_treasury_donation_share(controller, pool_key_to_id(&key), Percent::new(25).unwrap()).unwrap();
+        assert!(manager.set_protocol_fee_integration(Some(integration)).is_none());
+
+        let donor = Address::from_low_u64_be(3);
+        let (amount0, amount1) = (1_000_003u128, 7u128);
+        let delta = manager.donate(&key, donor, amount0, amount1, &[]).unwrap();
+        assert_eq!((delta.amount0(), delta.amount1()), (-(amount0 as i128), -(amount1 as i128)));
+        assert_eq!(manager.get_delta(donor, Currency::from_address(key.token0)), -(amount0 as i128));
+        assert_eq!(manager.get_delta(donor, Currency::from_address(key.token1)), -(amount1 as i128));
+
+        // The treasury share rounds down, LPs get the rest
+        let integration = manager.protocol_fee_integration().unwrap();
+        let protocol0 = integration.manager.protocol_fees_accrued(Currency::from_address(key.token0)).as_u128();
+        let protocol1 = integration.manager.protocol_fees_accrued(Currency::from_address(key.token1)).as_u128();
+        assert_eq!((protocol0, protocol1), (250_000, 1));
+
+        // Everything the donor owes goes to the treasury or the LP, but for
+        // the rounding of fee growth in the pool's favour
+        let (_, fees) = manager.modify_liquidity(key.clone(), ModifyLiquidityParams { liquidity_delta: 0, ..lp }, &[]).unwrap();
+        for (donated, protocol, lp) in [(amount0, protocol0, fees.amount0()), (amount1, protocol1, fees.amount1())] {
+            let lp = lp as u128;
+            assert!(protocol + lp <= donated);
+            assert!(donated - protocol - lp <= 1, "{donated} donated, {protocol} to the treasury, {lp} to the LP");
+        }
+    }
+
+    #[test]
+    fn test_update_dynamic_lp_fees() {
+        let mut manager = PoolManager::new();
+        let static_key = create_test_key();
+        assert!(matches!(
+            manager.update_dynamic_lp_fees(&static_key, FeePips::new(3000), FeePips::new(500)),
+            Err(StateError::FeeNotDynamic(3000))
+        ));
+        let key = create_test_key().with_fee(0x800000).with_hooks(Address::from_low_u64_be(1 << 20));
+        assert!(matches!(
+            manager.update_dynamic_lp_fees(&key, FeePips::new(3000), FeePips::new(500)),
+            Err(StateError::PoolNotInitialized)
+        ));
+        manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+        let params = ModifyLiquidityParams::default_position(Address::repeat_byte(1), -1200, 1200, 1_000_000_000_000);
+        manager.modify_liquidity(key.clone(), params, &[]).unwrap();
+
+        manager.update_dynamic_lp_fees(&key, FeePips::new(3000), FeePips::new(500)).unwrap();
+        manager.swap(&key, true, -1_000_000, TickMath::MIN_SQRT_PRICE + 1, &[]).unwrap();
+        manager.swap(&key, false, -1_000_000, TickMath::MAX_SQRT_PRICE - 1, &[]).unwrap();
+        let stats = manager.pool_stats(&key).unwrap();
+        assert_eq!((stats.lp_fees0, stats.lp_fees1), (3000, 500));
+    }
+
+    #[test]
+    fn test_auxiliary_fees_on_manager_paths() {
+        let mut manager = PoolManager::new();
+        let key = create_test_key();
+        let fees = AuxiliaryFees { withdrawal: FeePips::new(10_000), donation: FeePips::new(100_000) };
+        assert!(matches!(manager.set_auxiliary_fees(&key, fees), Err(StateError::PoolNotInitialized)));
+        manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+        let too_large = AuxiliaryFees { donation: FeePips::new(1_000_001), ..fees };
+        assert!(matches!(manager.set_auxiliary_fees(&key, too_large), Err(StateError::FeeTooLarge(1_000_001))));
+        manager.set_auxiliary_fees(&key, fees).unwrap();
+        assert_eq!(manager.get_pool(&key).unwrap().auxiliary_fees(), fees);
+
+        let lp = ModifyLiquidityParams::default_position(Address::from_low_u64_be(1), -120, 120, 1_000_000_000);
+        let (added, _) = manager.modify_liquidity(key.clone(), lp.clone(), &[]).unwrap();
+        // The donor still owes the full amounts, a tenth of which is charged
+        manager.donate(&key, Address::from_low_u64_be(3), 10_000, 0, &[]).unwrap();
+        assert_eq!(manager.pool_stats(&key).unwrap().protocol_fees0, 1_000);
+
+        // Removing the liquidity returns 1% less principal than was added,
+        // while the donation's remainder is collected in full
+        let remove = ModifyLiquidityParams { liquidity_delta: -lp.liquidity_delta, ..lp };
+        let (removed, fees_collected) = manager.modify_liquidity(key.clone(), remove, &[]).unwrap();
+        assert_eq!(fees_collected.amount0(), 8_999);
+        let charged = manager.pool_stats(&key).unwrap().protocol_fees0 - 1_000;
+        let principal = (removed.amount0() - fees_collected.amount0()) as u128 + charged;
+        assert_eq!(charged, principal / 100);
+        assert_eq!(principal, added.amount0().unsigned_abs());
+    }
+
+    #[test]
+    fn test_donate_to_single_position() {
+        let mut manager = PoolManager::new();
+        let key = create_test_key();
+        manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+
+        let alice = ModifyLiquidityParams::default_position(Address::from_low_u64_be(1), -120, 120, 1_000_000);
+        let bob = ModifyLiquidityParams::default_position(Address::from_low_u64_be(2), -120, 120, 1_000_000);
+        manager.modify_liquidity(key.clone(), alice.clone(), &[]).unwrap();
+        manager.modify_liquidity(key.clone(), bob.clone(), &[]).unwrap();
+
+        let donor = Address::from_low_u64_be(3);
+        let delta = manager.donate_to_position(&key, &bob.position_key(), donor, 1_000, 2_000).unwrap();
+        assert_eq!((delta.amount0(), delta.amount1()), (-1_000, -2_000));
+
+        // The donor owes exactly what the position is credited
+        assert_eq!(manager.get_delta(donor, Currency::from_address(key.token0)), -1_000);
+        assert_eq!(manager.get_delta(donor, Currency::from_address(key.token1)), -2_000);
+        let tipped = manager.get_position(&key, &bob.position_key()).unwrap();
+        assert_eq!((tipped.tokens_owed_0, tipped.tokens_owed_1), (1_000, 2_000));
+        let other = manager.get_position(&key, &alice.position_key()).unwrap();
+        assert_eq!((other.tokens_owed_0, other.tokens_owed_1), (0, 0));
+        assert_eq!(manager.get_pool(&key).unwrap().fee_growth_global_0_x128, U256::zero());
+
+        // The tip is paid out with the position's other fees
+        let (_, fees) = manager.modify_liquidity(key.clone(), ModifyLiquidityParams { liquidity_delta: -1_000_000, ..bob.clone() }, &[]).unwrap();
+        assert_eq!((fees.amount0(), fees.amount1()), (1_000, 2_000));
+
+        // Only positions holding liquidity can be tipped
+        assert!(matches!(
+            manager.donate_to_position(&key, &bob.position_key(), donor, 1_000, 0),
+            Err(StateError::LiquidityNotFound)
+        ));
+        assert!(matches!(
+            manager.donate_to_position(&key, &alice.position_key(), donor, u128::MAX, 0),
+            Err(StateError::AmountOverflow)
+        ));
+        assert_eq!(manager.get_delta(donor, Currency::from_address(key.token0)), -1_000);
+
+        // A tip the donor can't owe leaves both the position and the donor's
+        // deltas as they were
+        manager.donate_to_position(&key, &alice.position_key(), donor, 0, i128::MAX as u128 - 2_000).unwrap();
+        assert_eq!(manager.get_delta(donor, Currency::from_address(key.token1)), -i128::MAX);
+        let before = manager.get_position(&key, &alice.position_key()).unwrap().clone();
+        assert!(matches!(
+            manager.donate_to_position(&key, &alice.position_key(), donor, 1, 2),
+            Err(StateError::AmountOverflow)
+        ));
+        assert_eq!(manager.get_position(&key, &alice.position_key()), Some(&before));
+        assert_eq!(manager.get_delta(donor, Currency::from_address(key.token0)), -1_000);
+        assert_eq!(manager.get_delta(donor, Currency::from_address(key.token1)), -i128::MAX);
+    }
+
+    #[test]
+    fn test_pool_and_manager_stats() {
+        let mut manager = PoolManager::new();
+        let key = create_test_key();
+        let other_key = key_for(Address::from_low_u64_be(2), Address::from_low_u64_be(3));
+        for key in [&key, &other_key] {
+            manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+            manager.modify_liquidity(
+                key.clone(),
+                ModifyLiquidityParams::default_position(Address::repeat_byte(1), -120, 120, 1_000_000_000_000),
+                &[],
+            ).unwrap();
+        }
+        assert_eq!(manager.stats().swap_count, 0);
+
+        manager.set_timestamp(100);
+        let first = manager.swap(&key, true, -1000, TickMath::MIN_SQRT_PRICE + 1, &[]).unwrap();
+        manager.set_timestamp(200);
+        let second = manager.swap(&key, false, -2000, TickMath::MAX_SQRT_PRICE - 1, &[]).unwrap();
+        manager.set_timestamp(150);
+        assert_eq!(manager.timestamp(), 200);
+
+        let stats = *manager.pool_stats(&key).unwrap();
+        assert_eq!(stats.swap_count, 2);
+        assert_eq!(stats.volume0, 1000 + second.amount0().unsigned_abs());
+        assert_eq!(stats.volume1, first.amount1().unsigned_abs() + 2000);
+        assert_eq!((stats.lp_fees0, stats.lp_fees1), (3, 6));
+        assert_eq!((stats.protocol_fees0, stats.protocol_fees1), (0, 0));
+        assert_eq!(stats.last_trade_timestamp, Some(200));
+
+        // A swap that fails after the pool has computed it, here an exact
+        // output the owner's claims can't pay for, is not counted
+        manager.set_timestamp(300);
+        let unpaid = manager.swap_with_settlement(
+            &key,
+            true,
+            1000,
+            TickMath::MIN_SQRT_PRICE + 1,
+            SwapSettlement::Claims { owner: Address::repeat_byte(2) },
+            &[],
+        );
+        assert!(matches!(unpaid, Err(StateError::Claims(ERC6909Error::InsufficientBalance))));
+        assert_eq!(manager.pool_stats(&key), Some(&stats));
+
+        let totals = manager.stats();
+        assert_eq!(totals.pool_count, 2);
+        assert_eq!(totals.swap_count, 2);
+        assert_eq!(totals.last_trade_timestamp, Some(200));
+        assert_eq!(totals.pools.iter().filter(|(_, stats)| stats.swap_count == 0).count(), 1);
+    }
+
+    /// Hook that takes a fixed delta from every liquidity addition
+    struct LiquidityRebateHook;
+
+    impl Hook for LiquidityRebateHook {
+        fn describe(&self) -> crate::core::hooks::HookDescriptor {
+            let permissions = HookPermissions { after_add_liquidity: true, ..Default::default() };
+            crate::core::hooks::HookDescriptor::new("LiquidityRebateHook", "1", permissions)
+        }
+
+        fn after_add_liquidity(
+            &mut self,
+            _sender: Address,
+            _key: &HookPoolKey,
+            _params: &ModifyLiquidityParams,
+            _delta: &BalanceDelta,
+            _fees_accrued: &BalanceDelta,
+            _hook_data: &[u8],
+        ) -> StateResult<AfterHookResult> {
+            Ok(AfterHookResult { delta: Some(BalanceDelta::new(5, -2)) })
+        }
+    }
+
+    impl crate::core::hooks::hook_interface::HookWithReturns for LiquidityRebateHook {}
+
+    #[test]
+    fn test_replace_pool_hook() {
+        use crate::core::hooks::{HookError, HookFlags};
+
+        let mut manager = PoolManager::new();
+        let hooks = HookFlags::new(HookFlags::AFTER_ADD_LIQUIDITY).apply_to_address(Address::zero());
+        manager.hook_registry_mut().register_hook(hooks, Box::new(LiquidityRebateHook));
+        let key = create_test_key().with_hooks(hooks);
+        manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+        let params = ModifyLiquidityParams::default_position(Address::repeat_byte(1), -120, 120, 1_000_000);
+        manager.modify_liquidity(key.clone(), params.clone(), &[]).unwrap();
+        let currency0 = Currency::from_address(key.token0);
+        assert_eq!(manager.get_delta(hooks, currency0), 5);
+
+        // Addresses without flags are only valid for dynamic fees
+        assert!(matches!(
+            manager.replace_pool_hook(&key, Address::from_low_u64_be(0x1234)),
+            Err(StateError::Hook(HookError::HookAddressNotValid(_)))
+        ));
+        // A registered hook's declared permissions must match the new address
+        let mismatched = HookFlags::new(HookFlags::BEFORE_SWAP).apply_to_address(Address::zero());
+        manager.hook_registry_mut().register_hook(mismatched, Box::new(LiquidityRebateHook));
+        assert!(manager.replace_pool_hook(&key, mismatched).is_err());
+
+        // Another pool of the hook in the same currencies adds to its deltas
+        let other_key = key.clone().with_fee(500);
+        manager.initialize_pool(other_key.clone(), SqrtPrice::ONE).unwrap();
+        manager.modify_liquidity(other_key.clone(), params.clone(), &[]).unwrap();
+        assert_eq!(manager.get_delta(hooks, currency0), 10);
+
+        // Detaching keeps the pool and its risk state, and flushes only the
+        // pool's share of the old hook's deltas
+        let pool_id = pool_key_to_id(&key);
+        manager.risk_manager_mut().pause_pool(pool_id);
+        manager.risk_manager_mut().schedule_withdraw_only(pool_id, 10);
+        let liquidity = manager.get_pool(&key).unwrap().liquidity;
+        let (detached_key, flushed) = manager.detach_pool_hook(&key).unwrap();
+        assert_eq!(detached_key.hooks, Address::zero());
+        assert_eq!((flushed.amount0(), flushed.amount1()), (5, -2));
+        assert_eq!(manager.get_delta(hooks, currency0), 5);
+        assert!(manager.get_pool(&detached_key).unwrap().hook_deltas().is_zero());
+        assert_eq!(manager.get_pool(&detached_key).unwrap().liquidity, liquidity);
+        let detached_id = pool_key_to_id(&detached_key);
+        assert!(manager.risk_manager().is_pool_paused(&detached_id));
+        assert!(!manager.risk_manager().is_pool_paused(&pool_id));
+        assert_eq!(manager.risk_manager().withdraw_only_from(&detached_id), Some(10));
+        assert_eq!(manager.risk_manager().withdraw_only_from(&pool_id), None);
+        manager.risk_manager_mut().unpause_pool(detached_id);
+        manager.risk_manager_mut().lift_withdraw_only(detached_id);
+
+        // Without a hook, adding liquidity leaves no hook delta
+        manager.modify_liquidity(detached_key.clone(), params.clone(), &[]).unwrap();
+        assert_eq!(manager.get_delta(hooks, currency0), 5);
+
+        // Reattaching resumes the hook on the same liquidity
+        let (key, flushed) = manager.replace_pool_hook(&detached_key, hooks).unwrap();
+        assert!(flushed.is_zero());
+        manager.modify_liquidity(key.clone(), params, &[]).unwrap();
+        assert_eq!(manager.get_delta(hooks, currency0), 10);
+        assert_eq!(manager.get_pool(&key).unwrap().liquidity.as_u128(), 3_000_000);
+    }
+
+    /// Hook that records every tick a swap crosses and can refuse one
+    struct TickCrossRecorder {
+        crossings: std::rc::Rc<std::cell::RefCell<Vec<(i32, CrossDirection, i128)>>>,
+        reject_tick: Option<i32>,
+    }
+
+    impl Hook for TickCrossRecorder {
+        fn on_tick_cross(
+            &mut self,
+            _key: &HookPoolKey,
+            tick: i32,
+            direction: CrossDirection,
+            liquidity_net: i128,
+        ) -> StateResult<()> {
+            if self.reject_tick == Some(tick) {
+                return Err(crate::core::hooks::HookError::HookCallFailed.into());
+            }
+            self.crossings.borrow_mut().push((tick, direction, liquidity_net));
+            Ok(())
+        }
+    }
+
+    impl crate::core::hooks::hook_interface::HookWithReturns for TickCrossRecorder {}
+
+    #[test]
+    fn test_tick_cross_callback() {
+        use crate::core::hooks::HookFlags;
+
+        let setup = |flags: u16, reject_tick: Option<i32>| {
+            let crossings = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+            let hooks = HookFlags::new(flags).apply_to_address(Address::zero());
+            let mut manager = PoolManager::new();
+            let hook = TickCrossRecorder { crossings: crossings.clone(), reject_tick };
+            manager.hook_registry_mut().register_hook(hooks, Box::new(hook));
+            let key = create_test_key().with_hooks(hooks);
+            manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+            for (lower, upper) in [(-120, 120), (-600, 600)] {
+                let params = ModifyLiquidityParams::default_position(Address::repeat_byte(1), lower, upper, 1_000_000);
+                manager.modify_liquidity(key.clone(), params, &[]).unwrap();
+            }
+            (manager, key, crossings)
+        };
+        let limit = TickMath::get_sqrt_price_at_tick(-300).unwrap();
+
+        // Crossing down leaves the position ending at the tick, so the net is subtracted
+        let (mut manager, key, crossings) = setup(HookFlags::TICK_CROSS, None);
+        manager.swap(&key, true, -1_000_000_000, limit, &[]).unwrap();
+        assert_eq!(*crossings.borrow(), vec![(-120, CrossDirection::Down, 1_000_000)]);
+        assert_eq!(manager.get_pool(&key).unwrap().liquidity.as_u128(), 1_000_000);
+
+        crossings.borrow_mut().clear();
+        manager.swap(&key, false, -1_000_000_000, TickMath::get_sqrt_price_at_tick(300).unwrap(), &[]).unwrap();
+        assert_eq!(*crossings.borrow(), vec![
+            (-120, CrossDirection::Up, 1_000_000),
+            (120, CrossDirection::Up, -1_000_000),
+        ]);
+
+        // Hooks without the flag are never called
+        let (mut manager, key, crossings) = setup(0, None);
+        manager.swap(&key, true, -1_000_000_000, limit, &[]).unwrap();
+        assert!(crossings.borrow().is_empty());
+
+        // A failing callback aborts the swap and leaves the pool untouched
+        let (mut manager, key, crossings) = setup(HookFlags::TICK_CROSS, Some(-120));
+        let before = manager.get_pool(&key).unwrap().clone();
+        assert!(manager.swap(&key, true, -1_000_000_000, limit, &[]).is_err());
+        assert!(crossings.borrow().is_empty());
+        assert!(manager.get_pool(&key) == Some(&before));
+    }
+
+    /// Hook whose `beforeSwap` reverts with a message
+    struct PausedSwapHook;
+
+    impl Hook for PausedSwapHook {
+        fn before_swap(
+            &mut self,
+            _sender: Address,
+            _key: &HookPoolKey,
+            _params: &crate::core::hooks::SwapParams,
+            _hook_data: &[u8],
+        ) -> StateResult<crate::core::hooks::BeforeHookResult> {
+            Err(crate::core::hooks::HookError::HookCallReverted("paused".to_string()).into())
+        }
+    }
+
+    impl crate::core::hooks::hook_interface::HookWithReturns for PausedSwapHook {}
+
+    #[test]
+    fn test_hook_revert_is_wrapped() {
+        use crate::bindings::IHooksErrors;
+        use crate::core::hooks::{revert::RevertData, HookCallback, HookError, HookFlags};
+
+        let hooks = HookFlags::new(HookFlags::BEFORE_SWAP).apply_to_address(Address::zero());
+        let mut manager = PoolManager::new();
+        manager.hook_registry_mut().register_hook(hooks, Box::new(PausedSwapHook));
+        let key = create_test_key().with_hooks(hooks);
+        manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+        let params = ModifyLiquidityParams::default_position(Address::repeat_byte(1), -120, 120, 1_000_000);
+        manager.modify_liquidity(key.clone(), params, &[]).unwrap();
+
+        let limit = TickMath::get_sqrt_price_at_tick(-60).unwrap();
+        let Err(StateError::Hook(HookError::HookCallRevertedWithData(data))) =
+            manager.swap(&key, true, -1_000, limit, &[])
+        else {
+            panic!("expected the hook revert to be wrapped");
+        };
+        assert_eq!(data, RevertData::wrapped(hooks, HookCallback::BeforeSwap, &RevertData::message("paused")));
+        assert!(matches!(
+            data.decode(),
+            Some(IHooksErrors::WrappedError(wrapped))
+                if wrapped.target == hooks && wrapped.selector == HookCallback::BeforeSwap.selector()
+        ));
+        assert_eq!(data.root_cause(), RevertData::message("paused"));
+    }
+
+    #[test]
+    fn test_unlock_batch_records_each_result() {
+        let mut manager = PoolManager::new();
+        let key = create_test_key();
+        manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+        let borrower = Address::repeat_byte(2);
+        let provider = Address::repeat_byte(1);
+        let add_liquidity = UnlockOperation::ModifyLiquidity {
+            key: key.clone(),
+            params: ModifyLiquidityParams::default_position(provider, -120, 120, 1_000_000),
+            hook_data: vec![],
+        };
+        let take = UnlockOperation::Take { currency: Currency::Native, to: borrower, amount: 500 };
+        // The provider pays what the position takes
+        let mut pool = Pool::new();
+        pool.initialize(SqrtPrice::ONE, FeePips::new(3000)).unwrap();
+        let (owed, _) = pool.modify_position(provider.0, -120, 120, 1_000_000, key.tick_spacing, [0; 32]).unwrap();
+        let pay = [(key.token0, owed.amount0()), (key.token1, owed.amount1())].map(|(token, amount)| UnlockOperation::Settle {
+            currency: Currency::from_address(token),
+            recipient: provider,
+            value: U256::from(amount.unsigned_abs()),
+        });
+
+        // Adding liquidity without paying for it leaves the unlock unsettled
+        let result = manager.unlock_batch(std::slice::from_ref(&add_liquidity));
+        assert!(matches!(result.unlock_error, Some(FlashLoanError::CurrencyNotSettled)));
+        assert_eq!(manager.get_pool(&key).unwrap().liquidity.as_u128(), 0);
+
+        let result = manager.unlock_batch(&[
+            add_liquidity.clone(),
+            pay[0].clone(),
+            pay[1].clone(),
+            UnlockOperation::Swap {
+                key: key.clone(),
+                zero_for_one: true,
+                amount_specified: 0,
+                sqrt_price_limit_x96: TickMath::MIN_SQRT_PRICE + 1,
+                settlement: SwapSettlement::Tokens,
+                hook_data: vec![],
+            },
+            take.clone(),
+            UnlockOperation::Settle { currency: Currency::Native, recipient: borrower, value: U256::from(500) },
+        ]);
+        assert!(result.unlock_error.is_none());
+        assert!(!result.success);
+        let failures: Vec<_> = result.failures().map(|(index, _)| index).collect();
+        assert_eq!(failures, vec![3]);
+        assert!(matches!(result.results[0], Ok(OperationOutput::Delta(delta)) if delta.amount0() < 0));
+        assert!(matches!(result.results[5], Ok(OperationOutput::Settled(value)) if value == U256::from(500)));
+        // The failure did not undo the other operations
+        assert_eq!(manager.get_pool(&key).unwrap().liquidity.as_u128(), 1_000_000);
+        assert_eq!(manager.get_delta(borrower, Currency::Native), 0);
+        assert!(!manager.is_unlocked());
+
+        // An unpaid loan fails the unlock and rolls back every operation
+        let result = manager.unlock_batch(&[add_liquidity, take]);
+        assert!(result.results.iter().all(Result::is_ok));
+        assert!(matches!(result.unlock_error, Some(FlashLoanError::CurrencyNotSettled)));
+        assert!(!result.success);
+        assert_eq!(manager.get_pool(&key).unwrap().liquidity.as_u128(), 1_000_000);
+        assert_eq!(manager.get_delta(borrower, Currency::Native), 0);
+    }
+
+    #[test]
+    fn test_delta_journal_records_each_change() {
+        use crate::core::flash_loan::{AccountDelta, DeltaReason};
+
+        let mut manager = PoolManager::new();
+        let key = create_test_key();
+        manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+        let params = ModifyLiquidityParams::default_position(Address::repeat_byte(1), -600, 600, 1_000_000_000);
+        manager.modify_liquidity(key.clone(), params, &[]).unwrap();
+        assert!(manager.last_unlock_journal().is_none());
+
+        let swapper = Address::repeat_byte(3);
+        let (token0, token1) = (Currency::from_address(key.token0), Currency::from_address(key.token1));
+        let swap = |settlement| UnlockOperation::Swap {
+            key: key.clone(),
+            zero_for_one: true,
+            amount_specified: -1000,
+            sqrt_price_limit_x96: TickMath::MIN_SQRT_PRICE + 1,
+            settlement,
+            hook_data: vec![],
+        };
+        let amount_out = manager.quote_view().quote(&QuoteRequest {
+            key: key.clone(),
+            zero_for_one: true,
+            amount_specified: -1000,
+            sqrt_price_limit_x96: TickMath::MIN_SQRT_PRICE + 1,
+        }).unwrap().delta.amount1();
+
+        let result = manager.unlock_batch(&[
+            swap(SwapSettlement::Deltas { owner: swapper }),
+            // Fails on the claims the swapper lacks
+            swap(SwapSettlement::Claims { owner: swapper }),
+            UnlockOperation::Take { currency: token1, to: swapper, amount: amount_out as u128 },
+            UnlockOperation::Settle { currency: token0, recipient: swapper, value: U256::from(1000) },
+        ]);
+        assert!(result.unlock_error.is_none());
+        assert!(result.results[1].is_err());
+        assert!(manager.delta_journal().is_empty());
+
+        // The failed swap's entries were rolled back with it
+        let journal = manager.last_unlock_journal().unwrap();
+        let changes: Vec<_> = journal.entries().iter().map(|e| (e.currency, e.amount, e.reason, e.delta_after)).collect();
+        assert_eq!(changes, vec![
+            (token0, -1000, DeltaReason::Swap, -1000),
+            (token1, amount_out, DeltaReason::Swap, amount_out),
+            (token1, -amount_out, DeltaReason::Take, 0),
+            (token0, 1000, DeltaReason::Settle, 0),
+        ]);
+        assert_eq!(journal.for_account(swapper).count(), 4);
+        assert!(journal.net_deltas().is_empty());
+
+        // A failed unlock keeps its journal, showing what was left unsettled
+        let borrower = Address::repeat_byte(4);
+        let result = manager.unlock_batch(&[UnlockOperation::Take { currency: Currency::Native, to: borrower, amount: 500 }]);
+        assert!(matches!(result.unlock_error, Some(FlashLoanError::CurrencyNotSettled)));
+        let journal = manager.last_unlock_journal().unwrap();
+        assert_eq!(journal.net_deltas(), vec![AccountDelta { account: borrower, currency: Currency::Native, delta: -500 }]);
+        assert_eq!(journal.to_json(), json!([{
+            "account": borrower,
+            "currency": "Native",
+            "amount": "-500",
+            "reason": "take",
+            "delta_after": "-500",
+        }]));
+
+        // Exported with the currency's metadata, amounts read in its decimals
+        let (provider, _) = ethers::providers::Provider::mocked();
+        let metadata = crate::tokens::CurrencyMetadata::new(provider);
+        assert_eq!(journal.to_json_labeled(&metadata)[0]["symbol"], "ETH");
+        assert_eq!(journal.to_json_labeled(&metadata)[0]["amount_formatted"], "-0.0000000000000005 ETH");
+    }
+
+    #[test]
+    fn test_unlock_fails_with_deltas_left_open() {
+        let mut manager = PoolManager::new();
+        let key = create_test_key();
+        manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+        let params = ModifyLiquidityParams::default_position(Address::repeat_byte(1), -600, 600, 1_000_000_000);
+        manager.modify_liquidity(key.clone(), params, &[]).unwrap();
+        let before = manager.get_pool(&key).unwrap().clone();
+
+        // A swap owed through deltas and never settled fails the unlock,
+        // although no loan was taken
+        let swapper = Address::repeat_byte(3);
+        let result = manager.unlock_batch(&[UnlockOperation::Swap {
+            key: key.clone(),
+            zero_for_one: true,
+            amount_specified: -1000,
+            sqrt_price_limit_x96: TickMath::MIN_SQRT_PRICE + 1,
+            settlement: SwapSettlement::Deltas { owner: swapper },
+            hook_data: vec![],
+        }]);
+        assert!(result.results[0].is_ok());
+        assert!(matches!(result.unlock_error, Some(FlashLoanError::CurrencyNotSettled)));
+        assert!(manager.get_pool(&key) == Some(&before));
+        assert_eq!(manager.get_delta(swapper, Currency::from_address(key.token0)), 0);
+        assert_eq!(manager.nonzero_delta_count(), 0);
+
+        // Deltas accounted outside an unlock are not the next unlock's to settle
+        manager.mint(swapper, Currency::from_address(key.token0).to_id(), 10).unwrap();
+        assert!(manager.unlock_batch(&[]).success);
+    }
+
+    /// Hook recording the reports of `after_initialize_with_report`
+    struct InitializeRecorder(std::rc::Rc<std::cell::RefCell<Vec<InitializeReport>>>);
+
+    impl Hook for InitializeRecorder {}
+
+    impl crate::core::hooks::hook_interface::HookWithReturns for InitializeRecorder {
+        fn after_initialize_with_report(
+            &mut self,
+            _sender: Address,
+            _key: &HookPoolKey,
+            report: &InitializeReport,
+            _hook_data: &[u8],
+        ) -> StateResult<crate::core::hooks::AfterInitializeResult> {
+            self.0.borrow_mut().push(*report);
+            Ok(Default::default())
+        }
+    }
+
+    #[test]
+    fn test_after_initialize_reports_resolved_fee() {
+        let reports = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let mut manager = PoolManager::new();
+        let hooks = HookFlags::new(HookFlags::AFTER_INITIALIZE).apply_to_address(Address::repeat_byte(0xA1));
+        manager.hook_registry_mut().register_hook(hooks, Box::new(InitializeRecorder(reports.clone())));
+        let key = create_test_key().with_fee(0x800000 | 500).with_hooks(hooks);
+        let sqrt_price = SqrtPrice::new(TickMath::get_sqrt_price_at_tick(-61).unwrap());
+        manager.initialize_pool(key, sqrt_price).unwrap();
+        assert_eq!(*reports.borrow(), vec![InitializeReport { sqrt_price_x96: sqrt_price, tick: -61, lp_fee: FeePips::new(500) }]);
+    }
+
+    #[test]
+    fn test_hook_seeds_liquidity_within_unlock() {
+        use crate::core::flash_loan::DeltaReason;
+
+        let mut manager = PoolManager::new();
+        let hooks = HookFlags::new(HookFlags::AFTER_INITIALIZE).apply_to_address(Address::repeat_byte(0xA2));
+        let hook = crate::core::hooks::FullRangeSeedHook::new(1_000_000);
+        manager.hook_registry_mut().register_hook(hooks, Box::new(hook));
+        let key = key_for(Address::from_low_u64_be(10), Address::from_low_u64_be(11)).with_hooks(hooks);
+        let (token0, token1) = (Currency::from_address(key.token0), Currency::from_address(key.token1));
+
+        // A full range seed at price 1 owes its liquidity in each currency, rounded up
+        let settle = |currency| UnlockOperation::Settle { currency, recipient: hooks, value: U256::from(1_000_000) };
+        let result = manager.unlock_batch(&[
+            UnlockOperation::Initialize { key: key.clone(), sqrt_price_x96: SqrtPrice::ONE },
+            settle(token0),
+            settle(token1),
+        ]);
+        assert!(result.success);
+        assert!(matches!(result.results[0], Ok(OperationOutput::Tick(0))));
+        let journal = manager.last_unlock_journal().unwrap();
+        assert!(journal.entries().iter().all(|entry| entry.account == hooks));
+        let changes: Vec<_> = journal.entries().iter().map(|e| (e.currency, e.amount, e.reason)).collect();
+        assert_eq!(changes, vec![
+            (token0, -1_000_000, DeltaReason::Hook),
+            (token1, -1_000_000, DeltaReason::Hook),
+            (token0, 1_000_000, DeltaReason::Settle),
+            (token1, 1_000_000, DeltaReason::Settle),
+        ]);
+
+        let pool = manager.get_pool(&key).unwrap();
+        assert_eq!(pool.liquidity.as_u128(), 1_000_000);
+        let spacing = key.tick_spacing;
+        let position = PositionKey::default_position(hooks.0, spacing.min_usable_tick(), spacing.max_usable_tick());
+        assert_eq!(pool.position_manager.get(&position).unwrap().liquidity.as_u128(), 1_000_000);
+
+        // A seed that can't be added fails the initialization, leaving no pool
+        let hooks = HookFlags::new(HookFlags::AFTER_INITIALIZE).apply_to_address(Address::repeat_byte(0xA3));
+        let hook = crate::core::hooks::FullRangeSeedHook::new(u128::MAX);
+        manager.hook_registry_mut().register_hook(hooks, Box::new(hook));
+        let key = key_for(Address::from_low_u64_be(12), Address::from_low_u64_be(13)).with_hooks(hooks);
+        assert!(matches!(manager.initialize_pool(key.clone(), SqrtPrice::ONE), Err(PoolError::StateError(StateError::LiquidityOverflow))));
+        assert!(manager.get_pool(&key).is_none());
+        assert_eq!(manager.get_delta(hooks, Currency::from_address(key.token0)), 0);
+    }
+
+    #[test]
+    fn test_hook_context_is_shared_within_unlock() {
+        use crate::core::hooks::typestate::TypedHook;
+        use std::{cell::RefCell, rc::Rc};
+
+        let mut manager = PoolManager::new();
+        let context = manager.hook_context();
+        let seen = Rc::new(RefCell::new(Vec::new()));
+
+        // The first pool's hook records the amount of each swap, failing after
+        // recording an amount of -1
+        let writer_address = HookFlags::new(HookFlags::BEFORE_SWAP).apply_to_address(Address::repeat_byte(0xA0));
+        let writer_context = context.clone();
+        let writer = TypedHook::new("writer").with_before_swap(move |_, _, params, _| {
+            writer_context.set(b"amount".to_vec(), params.amount_specified.to_be_bytes().to_vec());
+            if params.amount_specified == -1 {
+                return Err(StateError::InvalidPrice);
+            }
+            Ok(BeforeHookResult::default())
+        });
+        // The second pool's hook reads it after its own swap
+        let reader_address = HookFlags::new(HookFlags::AFTER_SWAP).apply_to_address(Address::repeat_byte(0xB0));
+        let (reader_context, reader_seen) = (context.clone(), seen.clone());
+        let reader = TypedHook::new("reader").with_after_swap(move |_, _, _, _, _| {
+            reader_seen.borrow_mut().push(reader_context.get(b"amount"));
+            Ok(AfterHookResult::default())
+        });
+        manager.hook_registry_mut().register_hook(writer_address, Box::new(writer));
+        manager.hook_registry_mut().register_hook(reader_address, Box::new(reader));
+
+        let mut keys = Vec::new();
+        for (token0, hooks) in [(10, writer_address), (20, reader_address)] {
+            let key = key_for(Address::from_low_u64_be(token0), Address::from_low_u64_be(token0 + 1)).with_hooks(hooks);
+            manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+            let params = ModifyLiquidityParams::default_position(Address::repeat_byte(1), -120, 120, 1_000_000);
+            manager.modify_liquidity(key.clone(), params, &[]).unwrap();
+            keys.push(key);
+        }
+        let swap = |key: &ManagerPoolKey, amount_specified| UnlockOperation::Swap {
+            key: key.clone(),
+            zero_for_one: true,
+            amount_specified,
+            sqrt_price_limit_x96: TickMath::MIN_SQRT_PRICE + 1,
+            settlement: SwapSettlement::Tokens,
+            hook_data: vec![],
         };
-        
-        let (delta, fees) = manager.modify_liquidity(key.clone(), remove_params, &[]).unwrap();
-        
-        // Delta should be positive since we're removing liquidity
-        assert!(delta.amount0() > 0 || delta.amount1() > 0);
-        
-        // No fees expected as no swaps occurred
-        assert_eq!(fees.amount0(), 0);
-        assert_eq!(fees.amount1(), 0);
+
+        // The failed swap's write is rolled back with it
+        let result = manager.unlock_batch(&[swap(&keys[0], -100), swap(&keys[0], -1), swap(&keys[1], -50)]);
+        assert_eq!(result.failures().map(|(index, _)| index).collect::<Vec<_>>(), vec![1]);
+        assert_eq!(*seen.borrow(), vec![Some((-100i128).to_be_bytes().to_vec())]);
+
+        // The space is cleared when the unlock ends
+        assert!(context.is_empty());
+        assert!(manager.unlock_batch(&[swap(&keys[1], -50)]).success);
+        assert_eq!(seen.borrow()[1], None);
     }
-    
+
+    #[test]
+    fn test_price_limit_check() {
+        let mut manager = PoolManager::new();
+        let key = create_test_key();
+        manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+        let params = ModifyLiquidityParams::default_position(Address::repeat_byte(1), -1200, 1200, 1_000_000_000);
+        manager.modify_liquidity(key.clone(), params, &[]).unwrap();
+        let q96 = U256::one() << 96;
+        // A price of 0.01 scaled by 2^96, which as a square root price means 0.0001
+        let raw_price_limit = q96 / 100;
+
+        assert!(PriceLimitCheck::new(0.5).is_none());
+        assert!(manager.price_limit_check().is_none());
+        let before = manager.get_pool(&key).unwrap().clone();
+
+        let mut decimals = CurrencyDecimals::new();
+        decimals.set(Currency::from_address(key.token0), 6).set(Currency::from_address(key.token1), 18);
+        manager.set_price_limit_check(Some(PriceLimitCheck::new(10.0).unwrap().with_decimals(decimals)));
+        let error = manager.swap(&key, true, -1000, raw_price_limit, &[]).err().unwrap();
+        assert!(matches!(
+            error,
+            StateError::PriceLimitImplausible { max_factor, .. } if max_factor == 10.0
+        ));
+        // Prices are shown in whole tokens, here 10^-12 of the raw price
+        assert_eq!(
+            error.to_string(),
+            "Price limit 1.00000e-16 is more than 10x away from the current price 1.00000e-12; \
+             was a price passed instead of a Q64.96 square root price?"
+        );
+        assert!(manager.get_pool(&key).unwrap() == &before);
+
+        // Limits near the price and the no-limit sentinels pass
+        assert!(manager.swap(&key, true, -1000, q96 * 9 / 10, &[]).is_ok());
+        assert!(manager.swap(&key, true, -1000, TickMath::MIN_SQRT_PRICE + 1, &[]).is_ok());
+        manager.set_price_limit_check(None);
+        assert!(manager.swap(&key, true, -1000, raw_price_limit, &[]).is_ok());
+    }
+
+    #[test]
+    fn test_zero_swap_amount_rejected() {
+        let mut manager = PoolManager::new();
+        let key = create_test_key();
+        manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+        let limit = TickMath::MIN_SQRT_PRICE + 1;
+
+        assert!(matches!(manager.swap(&key, true, 0, limit, &[]), Err(StateError::SwapAmountCannotBeZero)));
+        let request = QuoteRequest { key, zero_for_one: true, amount_specified: 0, sqrt_price_limit_x96: limit };
+        assert!(matches!(manager.quote(&request), Err(StateError::SwapAmountCannotBeZero)));
+    }
+
+    #[test]
+    fn test_rebalance_moves_position_to_new_range() {
+        let mut manager = PoolManager::new();
+        let key = create_test_key();
+        manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+        let deep = ModifyLiquidityParams::default_position(Address::repeat_byte(1), -6000, 6000, 1_000_000_000_000);
+        manager.modify_liquidity(key.clone(), deep, &[]).unwrap();
+        let owner = Address::repeat_byte(2);
+        let params = ModifyLiquidityParams::default_position(owner, -600, 600, 1_000_000_000);
+        let position = params.position_key();
+        manager.modify_liquidity(key.clone(), params, &[]).unwrap();
+
+        // A range above the price takes only token0, so all token1 is swapped
+        let rebalance = RebalanceParams {
+            from_key: key.clone(),
+            to_key: key.clone(),
+            swap_key: None,
+            position: position.clone(),
+            tick_lower: 600,
+            tick_upper: 1200,
+            max_slippage: Bps::new(50),
+            min_liquidity: 0,
+        };
+        let result = manager.rebalance(&rebalance).unwrap();
+        assert_eq!(result.liquidity_removed, 1_000_000_000);
+        let swap = result.swap.unwrap();
+        assert_eq!(swap.amount1(), -result.removed.amount1());
+        assert_eq!(result.leftover.amount1(), 0);
+        assert!(result.leftover.amount0() >= 0 && result.leftover.amount0() < 10);
+        assert!(manager.get_position(&key, &position).is_none_or(|position| position.liquidity.as_u128() == 0));
+        let moved = PositionKey::default_position(owner.0, 600, 1200);
+        assert_eq!(manager.get_position(&key, &moved).unwrap().liquidity.as_u128(), result.liquidity_added);
+        assert!(!manager.is_unlocked());
+
+        // Failed rebalances leave everything as it was
+        let pool_before = manager.get_pool(&key).unwrap().clone();
+        let back = RebalanceParams { position: moved.clone(), tick_lower: -600, tick_upper: 600, ..rebalance };
+        let failures = [
+            (RebalanceParams { max_slippage: Bps::ZERO, ..back.clone() }, "slippage"),
+            (RebalanceParams { min_liquidity: u128::MAX, ..back.clone() }, "minimum"),
+            (RebalanceParams { to_key: key_for(Address::from_low_u64_be(7), Address::repeat_byte(8)), ..back.clone() }, "currencies"),
+        ];
+        for (params, reason) in failures {
+            let error = manager.rebalance(&params).err().unwrap();
+            let expected = match reason {
+                "slippage" => matches!(error, OperationError::State(StateError::SlippageExceeded { .. })),
+                "minimum" => matches!(error, OperationError::State(StateError::LiquidityBelowMinimum { .. })),
+                _ => matches!(error, OperationError::State(StateError::RebalanceCurrencyMismatch)),
+            };
+            assert!(expected, "{}: {}", reason, error);
+            assert!(*manager.get_pool(&key).unwrap() == pool_before);
+            assert!(!manager.is_unlocked());
+        }
+
+        // Moving back into a range around the price swaps part of the token0
+        let result = manager.rebalance(&back).unwrap();
+        assert!(result.swap.unwrap().amount0() < 0);
+        assert!(result.liquidity_added > 0);
+    }
+
+    #[test]
+    fn test_swap_step_limit() {
+        let mut manager = PoolManager::new();
+        let key = create_test_key();
+        manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+        // Dense ticks: one position per spacing below the price
+        for tick in (-600..0).step_by(60) {
+            let params = ModifyLiquidityParams::default_position(Address::repeat_byte(1), tick, tick + 60, 1_000_000_000);
+            manager.modify_liquidity(key.clone(), params, &[]).unwrap();
+        }
+        let limit = TickMath::MIN_SQRT_PRICE + 1;
+        let settlement = SwapSettlement::Tokens;
+
+        let mut unbounded = PoolManager::new();
+        unbounded.pools = manager.pools.clone();
+        let full = unbounded.swap_with_report(&key, true, -20_000_000, limit, settlement, &[]).unwrap();
+        assert!(full.steps > 3 && !full.partial);
+
+        manager.set_swap_config(SwapConfig::with_max_steps(3, OnStepLimit::Error));
+        let pool_before = manager.get_pool(&key).unwrap().clone();
+        let error = manager.swap(&key, true, -20_000_000, limit, &[]).err().unwrap();
+        assert!(matches!(error, StateError::SwapStepLimit(3)));
+        assert!(*manager.get_pool(&key).unwrap() == pool_before);
+
+        // A partial swap stops after its steps, within the dense range: the
+        // first step only crosses tick 0, where the swap starts
+        manager.set_swap_config(SwapConfig::with_max_steps(3, OnStepLimit::Partial));
+        let report = manager.swap_with_report(&key, true, -20_000_000, limit, settlement, &[]).unwrap();
+        assert_eq!((report.steps, report.partial), (3, true));
+        assert!(report.delta.amount0() > full.delta.amount0());
+        assert_eq!(manager.get_pool(&key).unwrap().slot0.tick, -121);
+
+        // Swaps within the limit are unaffected; this one crosses back over
+        // tick -120 before swapping
+        let report = manager.swap_with_report(&key, false, -1_000, TickMath::MAX_SQRT_PRICE - 1, settlement, &[]).unwrap();
+        assert_eq!((report.steps, report.partial), (2, false));
+        assert_eq!(report.delta.amount1(), -1_000);
+    }
+
+    #[test]
+    fn test_swap_takes_hook_deltas_from_the_caller() {
+        use crate::core::hooks::{typestate::TypedHook, BeforeSwapDelta, HookError};
+
+        let mut manager = PoolManager::new();
+        let flags = HookFlags::BEFORE_SWAP_RETURNS_DELTA | HookFlags::AFTER_SWAP_RETURNS_DELTA;
+        let hooks = HookFlags::new(flags).apply_to_address(Address::repeat_byte(0xD0));
+        // The hook pays the part of the output given in the hook data and
+        // charges 50 of the input on top of the swap
+        let hook = TypedHook::new("partial fill")
+            .with_before_swap_returning_delta(|_, _, _, data| {
+                Ok(BeforeSwapDelta { delta_specified: -i128::from(u16::from_be_bytes([data[0], data[1]])), delta_unspecified: 0 })
+            })
+            .with_after_swap_returning_delta(|_, _, _, _, _| Ok(50));
+        manager.hook_registry_mut().register_hook(hooks, Box::new(hook));
+        let key = create_test_key().with_hooks(hooks);
+        manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+        let params = ModifyLiquidityParams::default_position(Address::repeat_byte(1), -1200, 1200, 1_000_000_000);
+        manager.modify_liquidity(key.clone(), params, &[]).unwrap();
+        let limit = TickMath::MIN_SQRT_PRICE + 1;
+
+        // The pool only swaps what the hook leaves of the exact output
+        let request = QuoteRequest { key: key.clone(), zero_for_one: true, amount_specified: 600, sqrt_price_limit_x96: limit };
+        let pool_delta = manager.quote(&request).unwrap().delta;
+        let delta = manager.swap(&key, true, 1_000, limit, &400u16.to_be_bytes()).unwrap();
+        assert_eq!(pool_delta.amount1(), 600);
+        assert_eq!(delta.amount1(), 1_000);
+        assert_eq!(delta.amount0(), pool_delta.amount0() - 50);
+        assert_eq!(manager.get_delta(hooks, Currency::from_address(key.token0())), 50);
+        assert_eq!(manager.get_delta(hooks, Currency::from_address(key.token1())), -400);
+
+        // A hook can't turn an exact output into an exact input
+        assert!(matches!(
+            manager.swap(&key, true, 1_000, limit, &1_500u16.to_be_bytes()),
+            Err(StateError::Hook(HookError::HookDeltaExceedsSwapAmount))
+        ));
+
+        // A swap the pool rejects leaves the hook's deltas as they were
+        assert!(matches!(
+            manager.swap(&key, true, 1_000, TickMath::MAX_SQRT_PRICE - 1, &400u16.to_be_bytes()),
+            Err(StateError::PriceLimitAlreadyExceeded(..))
+        ));
+        assert_eq!(manager.get_delta(hooks, Currency::from_address(key.token0())), 50);
+        assert_eq!(manager.get_delta(hooks, Currency::from_address(key.token1())), -400);
+
+        // Replacing the amount already accounts for any fill, so it can't come with a delta
+        let hooks = HookFlags::new(HookFlags::BEFORE_SWAP | HookFlags::BEFORE_SWAP_RETURNS_DELTA)
+            .apply_to_address(Address::repeat_byte(0xD1));
+        manager.hook_registry_mut().register_hook(hooks, Box::new(AmountAndDeltaHook));
+        let key = create_test_key().with_hooks(hooks);
+        manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+        assert!(matches!(
+            manager.swap(&key, true, 1_000, limit, &[]),
+            Err(StateError::Hook(HookError::InvalidHookResponse))
+        ));
+    }
+
+    /// Hook that both replaces the amount to swap and fills part of it
+    struct AmountAndDeltaHook;
+
+    impl Hook for AmountAndDeltaHook {
+        fn before_swap(
+            &mut self,
+            _sender: Address,
+            _key: &HookPoolKey,
+            params: &SwapParams,
+            _hook_data: &[u8],
+        ) -> StateResult<BeforeHookResult> {
+            Ok(BeforeHookResult { amount: Some(params.amount_specified - 100), ..Default::default() })
+        }
+    }
+
+    impl crate::core::hooks::hook_interface::HookWithReturns for AmountAndDeltaHook {
+        fn before_swap_with_delta(
+            &mut self,
+            _sender: Address,
+            _key: &HookPoolKey,
+            _params: &SwapParams,
+            _hook_data: &[u8],
+        ) -> StateResult<BeforeSwapDelta> {
+            Ok(BeforeSwapDelta { delta_specified: -100, delta_unspecified: 0 })
+        }
+    }
+
+    #[test]
+    fn test_strict_hook_validation() {
+        use crate::core::hooks::{typestate::TypedHook, HookCallback, HookError};
+
+        let mut manager = PoolManager::new();
+        let flags = HookFlags::BEFORE_SWAP | HookFlags::AFTER_SWAP | HookFlags::AFTER_ADD_LIQUIDITY;
+        let hooks = HookFlags::new(flags).apply_to_address(Address::repeat_byte(0xC0));
+        // Hook data selects the misbehavior: a fee override in 10_000 pips,
+        // then whether to return deltas
+        let hook = TypedHook::new("misbehaving")
+            .with_after_add_liquidity(|_, _, _, _, _, data| {
+                Ok(AfterHookResult { delta: (data.get(1) == Some(&1)).then(|| BalanceDelta::new(1, 1)) })
+            })
+            .with_before_swap(|_, _, _, data| {
+                let fee_override = data.first().filter(|fee| **fee > 0).map(|fee| FeePips::new(*fee as u32 * 10_000));
+                Ok(BeforeHookResult { fee_override, ..Default::default() })
+            })
+            .with_after_swap(|_, _, _, _, data| {
+                Ok(AfterHookResult { delta: (data.get(1) == Some(&1)).then(|| BalanceDelta::new(0, 1)) })
+            });
+        manager.hook_registry_mut().register_hook(hooks, Box::new(hook));
+        let fixed = key_for(Address::from_low_u64_be(20), Address::from_low_u64_be(21)).with_hooks(hooks);
+        let dynamic = key_for(Address::from_low_u64_be(30), Address::from_low_u64_be(31)).with_fee(0x800000).with_hooks(hooks);
+        for key in [&fixed, &dynamic] {
+            manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+            let params = ModifyLiquidityParams::default_position(Address::repeat_byte(1), -1200, 1200, 1_000_000_000);
+            manager.modify_liquidity(key.clone(), params, &[]).unwrap();
+        }
+        let limit = TickMath::MIN_SQRT_PRICE + 1;
+
+        // By default a fee override on a pool without dynamic fees is honored
+        assert!(!manager.strict_hook_validation());
+        manager.swap(&fixed, true, -1000, limit, &[1]).unwrap();
+
+        manager.set_strict_hook_validation(true);
+        fn error<T>(result: StateResult<T>) -> HookError {
+            match result {
+                Err(StateError::Hook(error)) => error,
+                _ => panic!("expected a hook error"),
+            }
+        }
+        assert!(matches!(
+            error(manager.swap(&fixed, true, -1000, limit, &[1])),
+            HookError::FeeOverrideNotDynamic { hook, callback: HookCallback::BeforeSwap } if hook == hooks
+        ));
+        assert!(matches!(
+            error(manager.swap(&dynamic, true, -1000, limit, &[200])),
+            HookError::FeeOverrideTooLarge { callback: HookCallback::BeforeSwap, fee: 2_000_000, .. }
+        ));
+        let pool_before = manager.get_pool(&dynamic).unwrap().clone();
+        assert!(matches!(
+            error(manager.swap(&dynamic, true, -1000, limit, &[1, 1])),
+            HookError::DeltaNotPermitted { callback: HookCallback::AfterSwap, .. }
+        ));
+        let params = ModifyLiquidityParams::default_position(Address::repeat_byte(1), -1200, 1200, 1_000);
+        assert!(matches!(
+            error(manager.modify_liquidity(dynamic.clone(), params.clone(), &[0, 1])),
+            HookError::DeltaNotPermitted { callback: HookCallback::AfterAddLiquidity, .. }
+        ));
+        // The rejected results left the pool and the deltas as they were
+        assert!(manager.get_pool(&dynamic) == Some(&pool_before));
+        for currency in [dynamic.token0, dynamic.token1].map(Currency::from_address) {
+            assert_eq!(manager.get_delta(hooks, currency), 0);
+        }
+
+        // Results within the hook's permissions still pass
+        manager.swap(&dynamic, true, -1000, limit, &[1]).unwrap();
+        manager.modify_liquidity(dynamic, params, &[]).unwrap();
+    }
+
+    #[test]
+    fn test_quote_with_hooks_includes_fee_override() {
+        use crate::core::hooks::{examples::DynamicFeeHook, typestate::TypedHook, HookError};
+
+        let mut manager = PoolManager::new();
+        let hooks = HookFlags::new(HookFlags::BEFORE_SWAP).apply_to_address(Address::repeat_byte(0xC0));
+        let hook = DynamicFeeHook::new(FeePips::new(3000), FeePips::new(500), FeePips::new(10_000));
+        manager.hook_registry_mut().register_hook(hooks, Box::new(hook));
+        let key = create_test_key().with_fee(0x800000).with_hooks(hooks);
+        manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+        let params = ModifyLiquidityParams::default_position(Address::repeat_byte(1), -1200, 1200, 1_000_000_000);
+        manager.modify_liquidity(key.clone(), params, &[]).unwrap();
+        let request = QuoteRequest {
+            key: key.clone(),
+            zero_for_one: true,
+            amount_specified: -1_000_000,
+            sqrt_price_limit_x96: TickMath::MIN_SQRT_PRICE + 1,
+        };
+
+        // The hook's fee is missing from the plain quote
+        let plain = manager.quote(&request).unwrap();
+        let hooked = manager.quote_with_hooks(&request, &[]).unwrap();
+        assert!(hooked.delta.amount1() < plain.delta.amount1());
+        // Quoting left the hook and pool as they were, so the swap matches
+        let again = manager.quote_with_hooks(&request, &[]).unwrap();
+        assert_eq!(again.delta.amount1(), hooked.delta.amount1());
+        let delta = manager.swap(&key, true, -1_000_000, request.sqrt_price_limit_x96, &[]).unwrap();
+        assert_eq!((delta.amount0(), delta.amount1()), (hooked.delta.amount0(), hooked.delta.amount1()));
+        assert!(manager.get_pool(&key).unwrap().slot0.sqrt_price_x96 == hooked.sqrt_price_x96);
+
+        // Hooks that can't be copied can't be quoted
+        let hooks = HookFlags::new(HookFlags::BEFORE_SWAP).apply_to_address(Address::repeat_byte(0xD0));
+        let hook = TypedHook::new("closure").with_before_swap(|_, _, _, _| Ok(BeforeHookResult::default()));
+        manager.hook_registry_mut().register_hook(hooks, Box::new(hook));
+        let key = key_for(Address::from_low_u64_be(10), Address::from_low_u64_be(11)).with_hooks(hooks);
+        manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+        assert!(matches!(
+            manager.quote_with_hooks(&QuoteRequest { key, ..request }, &[]),
+            Err(StateError::Hook(HookError::NotQuotable(address))) if address == hooks
+        ));
+    }
+
+    /// Hook charging the fee named by the first byte of the hook data, in
+    /// tenths of a percent
+    #[derive(Clone)]
+    struct HookDataFeeHook;
+
+    impl Hook for HookDataFeeHook {
+        fn before_swap(
+            &mut self,
+            _sender: Address,
+            _key: &HookPoolKey,
+            _params: &SwapParams,
+            hook_data: &[u8],
+        ) -> StateResult<BeforeHookResult> {
+            let fee = hook_data.first().map(|tenths| FeePips::new(u32::from(*tenths) * 1000));
+            Ok(BeforeHookResult { fee_override: fee, ..Default::default() })
+        }
+    }
+
+    impl crate::core::hooks::hook_interface::HookWithReturns for HookDataFeeHook {
+        fn clone_for_quote(&self) -> Option<Box<dyn crate::core::hooks::hook_interface::HookWithReturns>> {
+            Some(Box::new(self.clone()))
+        }
+    }
+
+    #[test]
+    fn test_quote_with_hooks_passes_hook_data() {
+        let mut manager = PoolManager::new();
+        let hooks = HookFlags::new(HookFlags::BEFORE_SWAP).apply_to_address(Address::repeat_byte(0xC0));
+        manager.hook_registry_mut().register_hook(hooks, Box::new(HookDataFeeHook));
+        let key = create_test_key().with_fee(0x800000).with_hooks(hooks);
+        manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+        let params = ModifyLiquidityParams::default_position(Address::repeat_byte(1), -1200, 1200, 1_000_000_000);
+        manager.modify_liquidity(key.clone(), params, &[]).unwrap();
+        let request = QuoteRequest {
+            key: key.clone(),
+            zero_for_one: true,
+            amount_specified: -1_000_000,
+            sqrt_price_limit_x96: TickMath::MIN_SQRT_PRICE + 1,
+        };
+
+        // The fee named by the hook data is quoted, and the swap matches
+        let cheap = manager.quote_with_hooks(&request, &[1]).unwrap();
+        let dear = manager.quote_with_hooks(&request, &[10]).unwrap();
+        assert!(dear.delta.amount1() < cheap.delta.amount1());
+        let delta = manager.swap(&key, true, -1_000_000, request.sqrt_price_limit_x96, &[10]).unwrap();
+        assert_eq!((delta.amount0(), delta.amount1()), (dear.delta.amount0(), dear.delta.amount1()));
+    }
+
+    /// Hook charging a fixed fee, counting its calls
+    #[derive(Clone)]
+    struct CountingFeeHook {
+        calls: std::rc::Rc<std::cell::Cell<u32>>,
+        bucket: Option<u32>,
+    }
+
+    impl Hook for CountingFeeHook {
+        fn fee_price_bucket(&self) -> Option<u32> {
+            self.bucket
+        }
+
+        fn before_swap(
+            &mut self,
+            _sender: Address,
+            _key: &HookPoolKey,
+            _params: &SwapParams,
+            _hook_data: &[u8],
+        ) -> StateResult<BeforeHookResult> {
+            self.calls.set(self.calls.get() + 1);
+            Ok(BeforeHookResult { fee_override: Some(FeePips::new(5000)), ..Default::default() })
+        }
+    }
+
+    impl crate::core::hooks::hook_interface::HookWithReturns for CountingFeeHook {
+        fn clone_for_quote(&self) -> Option<Box<dyn crate::core::hooks::hook_interface::HookWithReturns>> {
+            Some(Box::new(self.clone()))
+        }
+    }
+
+    #[test]
+    fn test_hook_fee_cache() {
+        let setup = |bucket: Option<u32>| {
+            let calls = std::rc::Rc::new(std::cell::Cell::new(0));
+            let mut manager = PoolManager::new();
+            let hooks = HookFlags::new(HookFlags::BEFORE_SWAP).apply_to_address(Address::repeat_byte(0xC0));
+            let hook = CountingFeeHook { calls: calls.clone(), bucket };
+            manager.hook_registry_mut().register_hook(hooks, Box::new(hook));
+            let key = create_test_key().with_fee(0x800000).with_hooks(hooks);
+            manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+            let params = ModifyLiquidityParams::default_position(Address::repeat_byte(1), -1200, 1200, 1_000_000_000);
+            manager.modify_liquidity(key.clone(), params, &[]).unwrap();
+            manager.set_hook_fee_cache(true);
+            (manager, key, calls)
+        };
+        let request = |key: &ManagerPoolKey| QuoteRequest {
+            key: key.clone(),
+            zero_for_one: true,
+            amount_specified: -1_000,
+            sqrt_price_limit_x96: TickMath::MIN_SQRT_PRICE + 1,
+        };
+
+        // Repeated quotes call a pure hook once, with the same result
+        let (mut manager, key, calls) = setup(Some(60));
+        let first = manager.quote_with_hooks(&request(&key), &[]).unwrap();
+        let second = manager.quote_with_hooks(&request(&key), &[]).unwrap();
+        assert_eq!(calls.get(), 1);
+        assert_eq!(first.delta.amount1(), second.delta.amount1());
+        let cache = manager.hook_fee_cache().unwrap();
+        assert_eq!((cache.hits(), cache.misses(), cache.len()), (1, 1, 1));
+        // Different swaps are cached apart
+        manager.quote_with_hooks(&QuoteRequest { amount_specified: -2_000, ..request(&key) }, &[]).unwrap();
+        assert_eq!(calls.get(), 2);
+
+        // A swap within the bucket reuses the fee and keeps the entries
+        let delta = manager.swap(&key, true, -1_000, TickMath::MIN_SQRT_PRICE + 1, &[]).unwrap();
+        assert_eq!(delta.amount1(), first.delta.amount1());
+        assert_eq!(calls.get(), 2);
+        // The swap moved the tick below 0, into another bucket
+        assert!(manager.get_pool(&key).unwrap().slot0.tick < 0);
+        manager.quote_with_hooks(&request(&key), &[]).unwrap();
+        assert_eq!(calls.get(), 3);
+
+        // A liquidity change drops the pool's entries
+        let params = ModifyLiquidityParams::default_position(Address::repeat_byte(2), -600, 600, 1_000_000);
+        manager.modify_liquidity(key.clone(), params, &[]).unwrap();
+        manager.quote_with_hooks(&request(&key), &[]).unwrap();
+        assert_eq!(calls.get(), 4);
+        assert_eq!(manager.hook_fee_cache().unwrap().len(), 1);
+        // So does registering hooks
+        let _ = manager.hook_registry_mut();
+        assert!(manager.hook_fee_cache().unwrap().is_empty());
+
+        // Hooks that don't declare a bucket are always called
+        let (manager, key, calls) = setup(None);
+        manager.quote_with_hooks(&request(&key), &[]).unwrap();
+        manager.quote_with_hooks(&request(&key), &[]).unwrap();
+        assert_eq!(calls.get(), 2);
+        assert!(manager.hook_fee_cache().unwrap().is_empty());
+        // As are pure hooks with caching off
+        let (mut manager, key, calls) = setup(Some(60));
+        manager.set_hook_fee_cache(false);
+        manager.quote_with_hooks(&request(&key), &[]).unwrap();
+        manager.quote_with_hooks(&request(&key), &[]).unwrap();
+        assert_eq!(calls.get(), 2);
+        assert!(manager.hook_fee_cache().is_none());
+    }
+
+    #[test]
+    fn test_quote_many_matches_swaps() {
+        let mut manager = PoolManager::new();
+        let key = create_test_key();
+        manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+        manager.modify_liquidity(
+            key.clone(),
+            ModifyLiquidityParams::default_position(Address::repeat_byte(1), -600, 600, 1_000_000_000),
+            &[],
+        ).unwrap();
+
+        let requests: Vec<_> = (1..=20)
+            .map(|i| QuoteRequest {
+                key: key.clone(),
+                zero_for_one: i % 2 == 0,
+                amount_specified: -1000 * i,
+                sqrt_price_limit_x96: if i % 2 == 0 { TickMath::MIN_SQRT_PRICE + 1 } else { TickMath::MAX_SQRT_PRICE - 1 },
+            })
+            .chain([QuoteRequest {
+                key: key_for(Address::repeat_byte(9), Address::repeat_byte(10)),
+                zero_for_one: true,
+                amount_specified: -1000,
+                sqrt_price_limit_x96: TickMath::MIN_SQRT_PRICE + 1,
+            }])
+            .collect();
+        let before = manager.get_pool(&key).unwrap().clone();
+        let quotes = manager.quote_many(&requests);
+        assert!(manager.get_pool(&key) == Some(&before));
+        assert!(matches!(quotes[20], Err(StateError::PoolNotInitialized)));
+
+        // Each quote matches the swap on the unchanged pool
+        for (request, quote) in requests[..20].iter().zip(&quotes) {
+            let quote = quote.as_ref().unwrap();
+            let mut pool = before.clone();
+            let (delta, _) = pool.swap(
+                request.amount_specified,
+                SqrtPrice::new(request.sqrt_price_limit_x96),
+                request.zero_for_one,
+                key.tick_spacing,
+                None,
+            ).unwrap();
+            assert_eq!((quote.delta.amount0(), quote.delta.amount1()), (delta.amount0(), delta.amount1()));
+            assert_eq!(quote.sqrt_price_x96, pool.slot0.sqrt_price_x96);
+        }
+
+        // A single worker reuses its copy of the pool for every quote
+        let single = rayon::ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+        let outcome = |quotes: &[QuoteResult]| -> Vec<_> {
+            quotes[..20].iter().map(|quote| quote.as_ref().map(|q| (q.delta.amount0(), q.delta.amount1(), q.sqrt_price_x96)).unwrap()).collect()
+        };
+        let view = manager.quote_view();
+        assert_eq!(outcome(&single.install(|| view.quote_many(&requests))), outcome(&quotes));
+    }
+
+    #[test]
+    fn test_save_and_load_state() {
+        use crate::core::storage::{MemoryStorage, PoolStore};
+
+        let mut manager = PoolManager::new();
+        let key = create_test_key();
+        let other_key = key_for(Address::repeat_byte(9), Address::repeat_byte(10));
+        for key in [&key, &other_key] {
+            manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+            let params = ModifyLiquidityParams::default_position(Address::repeat_byte(1), -120, 120, 1_000_000);
+            manager.modify_liquidity(key.clone(), params, &[]).unwrap();
+        }
+        manager.swap(&key, true, -1_000, TickMath::MIN_SQRT_PRICE + 1, &[]).unwrap();
+        let owner = Address::from_low_u64_be(42);
+        let currency0 = Currency::from_address(key.token0);
+        manager.mint(owner, currency0.to_id(), 5_000).unwrap();
+
+        let mut store = PoolStore::new(MemoryStorage::new());
+        manager.save_state(&mut store).unwrap();
+        let mut restored = PoolManager::new();
+        restored.load_state(&store).unwrap();
+        assert_eq!(restored.export_state(), manager.export_state());
+        assert!(restored.pool_stats(&key) == manager.pool_stats(&key));
+        assert_eq!(restored.claims_balance_of(owner, currency0), U256::from(5_000));
+
+        // A spilled pool leaves memory and comes back unchanged
+        let before = manager.get_pool(&other_key).unwrap().clone();
+        manager.spill_pool(&other_key, &mut store).unwrap();
+        assert!(manager.get_pool(&other_key).is_none());
+        assert!(manager.load_pool(&other_key, &store).unwrap());
+        assert!(manager.get_pool(&other_key) == Some(&before));
+    }
+
+    #[test]
+    fn test_swap_against_claims() {
+        let mut manager = PoolManager::new();
+        let key = create_test_key();
+        manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+        manager.modify_liquidity(key.clone(), ModifyLiquidityParams {
+            owner: Address::repeat_byte(1),
+            tick_lower: -120,
+            tick_upper: 120,
+            liquidity_delta: 1_000_000,
+            salt: Salt::ZERO,
+        }, &[]).unwrap();
+
+        let owner = Address::from_low_u64_be(42);
+        let currency0 = Currency::from_address(key.token0);
+        let currency1 = Currency::from_address(key.token1);
+        manager.mint(owner, currency0.to_id(), 5_000).unwrap();
+
+        let sqrt_price_limit = U256::from(78228162514264337593543950336u128);
+        let delta = manager.swap_with_settlement(
+            &key,
+            true,
+            -1_000,
+            sqrt_price_limit,
+            SwapSettlement::Claims { owner },
+            &[],
+        ).unwrap();
+        assert!(delta.amount0() < 0 && delta.amount1() > 0);
+
+        // Input claims were burned and output claims minted
+        assert_eq!(manager.claims_balance_of(owner, currency0), U256::from(5_000 + delta.amount0()));
+        assert_eq!(manager.claims_balance_of(owner, currency1), U256::from(delta.amount1()));
+
+        // Nothing is left for the owner to settle
+        assert_eq!(manager.get_delta(owner, currency0), 0);
+        assert_eq!(manager.get_delta(owner, currency1), 0);
+    }
+
+    #[test]
+    fn test_mint_and_burn_account_the_claimed_currency() {
+        let mut manager = PoolManager::new();
+        let owner = Address::from_low_u64_be(42);
+        let token = Currency::from_address(Address::repeat_byte(0xC1));
+
+        for currency in [Currency::Native, token] {
+            let id = currency.to_id();
+            manager.mint(owner, id, 700).unwrap();
+            assert_eq!(manager.claims_balance_of(owner, currency), U256::from(700));
+            assert_eq!(manager.get_delta(Address::zero(), currency), -700);
+            manager.burn(owner, id, 700).unwrap();
+            assert_eq!(manager.claims_balance_of(owner, currency), U256::zero());
+            assert_eq!(manager.get_delta(Address::zero(), currency), 0);
+        }
+
+        let too_wide = U256::one() << 160;
+        assert!(matches!(
+            manager.mint(owner, too_wide, 1),
+            Err(StateError::InvalidCurrencyId(crate::core::flash_loan::InvalidCurrencyId(id))) if id == too_wide
+        ));
+        assert!(matches!(manager.burn(owner, U256::MAX, 1), Err(StateError::InvalidCurrencyId(_))));
+    }
+
+    #[test]
+    fn test_swap_against_claims_requires_input_claims() {
+        let mut manager = PoolManager::new();
+        let key = create_test_key();
+        manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+
+        let owner = Address::from_low_u64_be(42);
+        let currency0 = Currency::from_address(key.token0);
+        manager.mint(owner, currency0.to_id(), 999).unwrap();
+
+        let result = manager.swap_with_settlement(
+            &key,
+            true,
+            -1_000,
+            U256::from(78228162514264337593543950336u128),
+            SwapSettlement::Claims { owner },
+            &[],
+        );
+        assert!(matches!(result, Err(StateError::Claims(ERC6909Error::InsufficientBalance))));
+
+        // The pool and the claims are untouched
+        assert_eq!(manager.get_pool(&key).unwrap().slot0.sqrt_price_x96, SqrtPrice::ONE);
+        assert_eq!(manager.claims_balance_of(owner, currency0), U256::from(999));
+    }
+
+    #[test]
+    fn test_exact_output_swap_against_claims_requires_input_claims() {
+        let mut manager = PoolManager::new();
+        let key = create_test_key();
+        manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+        let params = ModifyLiquidityParams::default_position(Address::repeat_byte(1), -120, 120, 1_000_000);
+        manager.modify_liquidity(key.clone(), params, &[]).unwrap();
+
+        // The swap needs more than 1_000 of token0 for 1_000 of token1
+        let owner = Address::from_low_u64_be(42);
+        let currency0 = Currency::from_address(key.token0);
+        manager.mint(owner, currency0.to_id(), 1_000).unwrap();
+        let pool_before = manager.get_pool(&key).unwrap().clone();
+        let result = manager.swap_with_settlement(
+            &key,
+            true,
+            1_000,
+            U256::from(78228162514264337593543950336u128),
+            SwapSettlement::Claims { owner },
+            &[],
+        );
+        assert!(matches!(result, Err(StateError::Claims(ERC6909Error::InsufficientBalance))));
+
+        // The pool, the claims and the owner's deltas are untouched
+        assert!(manager.get_pool(&key) == Some(&pool_before));
+        assert_eq!(manager.claims_balance_of(owner, currency0), U256::from(1_000));
+        assert_eq!(manager.get_delta(owner, currency0), 0);
+        assert_eq!(manager.get_delta(owner, Currency::from_address(key.token1)), 0);
+    }
+
     // Test for flash loan functionality
     struct TestFlashLoanCallback {
         _currency: Currency,
@@ -588,6 +4366,177 @@ mod tests {
         }
     }
     
+    #[test]
+    fn test_circuit_breaker_pauses_pool() {
+        use crate::risk::{CircuitBreakerConfig, RiskEvent, TripReason};
+
+        let mut manager = PoolManager::new();
+        let key = create_test_key();
+        let pool_id = pool_key_to_id(&key);
+        manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+        let params = ModifyLiquidityParams {
+            owner: Address::repeat_byte(1),
+            tick_lower: -120,
+            tick_upper: 120,
+            liquidity_delta: 1_000_000,
+            salt: Salt::ZERO,
+        };
+        manager.modify_liquidity(key.clone(), params.clone(), &[]).unwrap();
+        manager.risk_manager_mut()
+            .set_pool_breaker(pool_id, CircuitBreakerConfig::new().with_max_price_move_bps(10))
+            .unwrap();
+
+        // The swap completes but moves the price by more than 10 bps
+        let sqrt_price_limit = U256::from(78228162514264337593543950336u128);
+        manager.swap(&key, true, -1_000, sqrt_price_limit, &[]).unwrap();
+        assert!(manager.risk_manager().is_pool_paused(&pool_id));
+        assert!(manager.risk_manager().events().iter().any(|event| matches!(
+            event,
+            RiskEvent::BreakerTripped { reason: TripReason::PriceDeviation { .. }, .. }
+        )));
+
+        assert!(matches!(
+            manager.swap(&key, true, -1_000, sqrt_price_limit, &[]),
+            Err(StateError::PoolPaused)
+        ));
+        assert!(matches!(
+            manager.modify_liquidity(key.clone(), params.clone(), &[]),
+            Err(StateError::PoolPaused)
+        ));
+
+        manager.risk_manager_mut().unpause_pool(pool_id);
+        manager.modify_liquidity(key.clone(), params.clone(), &[]).unwrap();
+
+        manager.risk_manager_mut().pause_manager();
+        assert!(matches!(
+            manager.modify_liquidity(key, params, &[]),
+            Err(StateError::ManagerPaused)
+        ));
+    }
+
+    #[test]
+    fn test_state_view_reads_pool_state_by_id() {
+        use crate::core::{math::types::Liquidity, state::TickInfo};
+
+        let mut manager = PoolManager::new();
+        let key = create_test_key();
+        let pool_id = pool_key_to_id(&key);
+        assert!(manager.state_view().get_slot0(&pool_id).is_none());
+
+        manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+        let owner = Address::repeat_byte(1);
+        let params = ModifyLiquidityParams { owner, tick_lower: -120, tick_upper: 120, liquidity_delta: 1_000_000_000, salt: Salt::ZERO };
+        manager.modify_liquidity(key.clone(), params, &[]).unwrap();
+        manager.swap(&key, true, -1_000_000, TickMath::get_sqrt_price_at_tick(-60).unwrap(), &[]).unwrap();
+
+        let view = manager.state_view();
+        let pool = manager.get_pool(&key).unwrap();
+        assert_eq!(view.get_slot0(&pool_id), Some(pool.slot0.clone()));
+        assert_eq!(view.get_liquidity(&pool_id), Some(Liquidity::new(1_000_000_000)));
+        assert_eq!(view.get_tick_liquidity(&pool_id, -120), Some((Liquidity::new(1_000_000_000), 1_000_000_000)));
+        assert_eq!(view.get_tick_liquidity(&pool_id, 120), Some((Liquidity::new(1_000_000_000), -1_000_000_000)));
+        assert_eq!(view.get_tick_info(&pool_id, 60), Some(TickInfo::default()));
+
+        let globals = view.get_fee_growth_globals(&pool_id).unwrap();
+        assert!(!globals.0.is_zero());
+        assert_eq!(view.get_fee_growth_inside(&pool_id, -120, 120), Some(globals));
+        assert_eq!(view.get_fee_growth_inside(&pool_id, 120, 240), Some((U256::zero(), U256::zero())));
+
+        let position_key = PositionKey::default_position(owner.0, -120, 120);
+        assert_eq!(view.get_position_info(&pool_id, &position_key).as_ref(), manager.get_position(&key, &position_key));
+        let missing = PositionKey::default_position([9; 20], -120, 120);
+        assert_eq!(view.get_position_info(&pool_id, &missing), Some(Position::default()));
+    }
+
+    #[test]
+    fn test_withdraw_only_pool_lets_lps_leave() {
+        use crate::risk::RiskEvent;
+
+        let mut manager = PoolManager::new();
+        let key = create_test_key();
+        let pool_id = pool_key_to_id(&key);
+        manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+        let params = |liquidity_delta| ModifyLiquidityParams {
+            owner: Address::repeat_byte(1),
+            tick_lower: -120,
+            tick_upper: 120,
+            liquidity_delta,
+            salt: Salt::ZERO,
+        };
+        manager.modify_liquidity(key.clone(), params(1_000_000_000), &[]).unwrap();
+        let sqrt_price_limit = TickMath::get_sqrt_price_at_tick(-60).unwrap();
+
+        // The freeze is timelocked, so the pool trades until it takes effect
+        let effective_block = manager.risk_manager_mut().schedule_withdraw_only(pool_id, 2);
+        assert_eq!(manager.risk_manager().events().last(), Some(&RiskEvent::WithdrawOnlyScheduled { pool_id, effective_block }));
+        manager.swap(&key, true, -1_000_000, sqrt_price_limit, &[]).unwrap();
+        manager.risk_manager_mut().set_block_number(effective_block);
+        assert!(manager.risk_manager().is_withdraw_only(&pool_id));
+
+        assert!(matches!(manager.swap(&key, true, -1_000, sqrt_price_limit, &[]), Err(StateError::PoolWithdrawOnly)));
+        assert!(matches!(manager.modify_liquidity(key.clone(), params(1), &[]), Err(StateError::PoolWithdrawOnly)));
+        assert!(matches!(manager.donate(&key, Address::zero(), 1, 1, &[]), Err(StateError::PoolWithdrawOnly)));
+
+        // LPs still collect their fees and withdraw
+        let (_, fees) = manager.modify_liquidity(key.clone(), params(0), &[]).unwrap();
+        assert!(fees.amount0() > 0);
+        manager.modify_liquidity(key.clone(), params(-1_000_000_000), &[]).unwrap();
+
+        manager.risk_manager_mut().lift_withdraw_only(pool_id);
+        manager.modify_liquidity(key.clone(), params(1_000), &[]).unwrap();
+    }
+
+    #[test]
+    fn test_event_sink_records_successful_operations() {
+        use crate::integrations::EventLog;
+
+        let mut manager = PoolManager::new();
+        let log = EventLog::new();
+        manager.set_event_sink(Some(Box::new(log.clone())));
+        manager.set_timestamp(7);
+
+        let key = create_test_key();
+        let pool_id = pool_key_to_id(&key);
+        manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+        let params = ModifyLiquidityParams::default_position(Address::repeat_byte(1), -120, 120, 1_000_000);
+        manager.modify_liquidity(key.clone(), params, &[]).unwrap();
+        let limit = TickMath::MIN_SQRT_PRICE + 1;
+        let delta = manager.swap(&key, true, -1_000, limit, &[]).unwrap();
+        manager.donate(&key, Address::repeat_byte(2), 10, 0, &[]).unwrap();
+
+        // Failed operations and quotes record nothing
+        assert!(manager.swap(&key, true, 0, limit, &[]).is_err());
+        let request = QuoteRequest::new(key.clone(), true, SwapAmount::ExactIn(1_000), limit).unwrap();
+        manager.quote_with_hooks(&request, &[]).unwrap();
+
+        let events = log.drain();
+        assert_eq!(events.len(), 4);
+        assert!(events.iter().all(|event| event.pool_id() == pool_id));
+        assert!(matches!(events[0], SimulationEvent::Initialize { timestamp: 7, tick: 0, .. }));
+        assert!(matches!(events[1], SimulationEvent::ModifyLiquidity { liquidity_delta: 1_000_000, .. }));
+        let pool = manager.get_pool(&key).unwrap();
+        assert_eq!(events[2], SimulationEvent::Swap {
+            pool_id,
+            timestamp: 7,
+            zero_for_one: true,
+            amount0: delta.amount0(),
+            amount1: delta.amount1(),
+            sqrt_price_x96: pool.slot0.sqrt_price_x96.to_u256(),
+            tick: pool.slot0.tick,
+            liquidity: 1_000_000,
+        });
+        assert!(matches!(events[3], SimulationEvent::Donate { amount0: 10, amount1: 0, .. }));
+
+        let json = serde_json::to_value(&events[2]).unwrap();
+        assert_eq!(json["event"], "swap");
+        assert_eq!(json["amount0"], delta.amount0().to_string());
+
+        assert!(manager.set_event_sink(None).is_some());
+        manager.donate(&key, Address::repeat_byte(2), 10, 0, &[]).unwrap();
+        assert!(log.is_empty());
+        manager.flush_events().unwrap();
+    }
+
     #[test]
     fn test_flash_loan() {
         let mut manager = PoolManager::new();
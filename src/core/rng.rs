@@ -0,0 +1,141 @@
+use std::ops::Range;
+use ethers::types::Address;
+
+use crate::core::state::Salt;
+
+/// Seedable pseudo-random number generator used by simulations and tests
+///
+/// Implements SplitMix64, so a seed yields the same sequence on every platform
+/// and release, independently of external RNG crates.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    /// Creates a generator from a 64-bit seed
+    pub fn seed_from_u64(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Returns the next random u64
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Returns the next random u32
+    pub fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    /// Returns the next random u128
+    pub fn next_u128(&mut self) -> u128 {
+        ((self.next_u64() as u128) << 64) | self.next_u64() as u128
+    }
+
+    /// Returns a random f64 in `[0, 1)`
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Returns a random bool
+    pub fn next_bool(&mut self) -> bool {
+        self.next_u64() & 1 == 1
+    }
+
+    /// Returns a random u64 in the given range
+    ///
+    /// Panics if the range is empty.
+    pub fn gen_range(&mut self, range: Range<u64>) -> u64 {
+        assert!(range.start < range.end, "empty range");
+        let span = range.end - range.start;
+        // Reject the biased tail so every value is equally likely
+        let zone = u64::MAX - (u64::MAX - span + 1) % span;
+        loop {
+            let value = self.next_u64();
+            if value <= zone {
+                return range.start + value % span;
+            }
+        }
+    }
+
+    /// Returns a random i32 in the given range
+    ///
+    /// Panics if the range is empty.
+    pub fn gen_range_i32(&mut self, range: Range<i32>) -> i32 {
+        assert!(range.start < range.end, "empty range");
+        let span = (range.end as i64 - range.start as i64) as u64;
+        (range.start as i64 + self.gen_range(0..span) as i64) as i32
+    }
+
+    /// Fills the buffer with random bytes
+    pub fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    /// Returns a random 20-byte address
+    pub fn address(&mut self) -> Address {
+        let mut bytes = [0u8; 20];
+        self.fill_bytes(&mut bytes);
+        Address::from(bytes)
+    }
+
+    /// Returns a random position salt
+    pub fn salt(&mut self) -> Salt {
+        let mut bytes = [0u8; 32];
+        self.fill_bytes(&mut bytes);
+        Salt(bytes)
+    }
+
+    /// Derives an independent generator, e.g. for a sub-component of a scenario
+    pub fn fork(&mut self) -> Self {
+        Self::seed_from_u64(self.next_u64())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_seed_same_sequence() {
+        let mut a = Rng::seed_from_u64(42);
+        let mut b = Rng::seed_from_u64(42);
+        for _ in 0..100 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+        assert_eq!(a.address(), b.address());
+
+        let mut c = Rng::seed_from_u64(43);
+        assert_ne!(a.next_u64(), c.next_u64());
+    }
+
+    #[test]
+    fn test_known_sequence() {
+        // Reference values of SplitMix64 seeded with 0
+        let mut rng = Rng::seed_from_u64(0);
+        assert_eq!(rng.next_u64(), 0xE220_A839_7B1D_CDAF);
+        assert_eq!(rng.next_u64(), 0x6E78_9E6A_A1B9_65F4);
+    }
+
+    #[test]
+    fn test_ranges() {
+        let mut rng = Rng::seed_from_u64(7);
+        for _ in 0..1000 {
+            let value = rng.gen_range(10..20);
+            assert!((10..20).contains(&value));
+            let tick = rng.gen_range_i32(-100..100);
+            assert!((-100..100).contains(&tick));
+            let unit = rng.next_f64();
+            assert!((0.0..1.0).contains(&unit));
+        }
+        assert_eq!(rng.gen_range(5..6), 5);
+    }
+}
@@ -0,0 +1,253 @@
+//! Canonical commitment to the state of a pool
+//!
+//! Two implementations hold the same pool state exactly when they compute the
+//! same 32-byte root, so differential tests can compare one word per
+//! checkpoint instead of every tick and position. The schema only uses
+//! `abi.encode` and `keccak256`, so a Solidity exporter can compute the root
+//! from the `PoolManager`'s storage:
+//!
+//! ```text
+//! slot0Leaf     = keccak256(abi.encode(uint8(0), uint160 sqrtPriceX96, int24 tick,
+//!                     uint24 protocolFee, uint24 lpFeeZeroForOne, uint24 lpFeeOneForZero,
+//!                     uint256 feeGrowthGlobal0X128, uint256 feeGrowthGlobal1X128, uint128 liquidity))
+//! tickLeaf      = keccak256(abi.encode(uint8(1), int24 tick, uint128 liquidityGross,
+//!                     int128 liquidityNet, uint256 feeGrowthOutside0X128, uint256 feeGrowthOutside1X128))
+//! positionLeaf  = keccak256(abi.encode(uint8(2), bytes32 positionKey, uint128 liquidity,
+//!                     uint256 feeGrowthInside0LastX128, uint256 feeGrowthInside1LastX128))
+//! ticksRoot     = merkle(tickLeaf of every initialized tick, by ascending tick)
+//! positionsRoot = merkle(positionLeaf of every position with liquidity, by ascending positionKey)
+//! root          = keccak256(abi.encodePacked(slot0Leaf, ticksRoot, positionsRoot))
+//! ```
+//!
+//! Both fee fields of `Slot0` hold the same LP fee unless the pool charges
+//! separate fees per direction. `positionKey` is the key of the position in
+//! the pool's `positions` mapping, see [`PositionKey::storage_id`].
+//! `merkle` of no leaves is `bytes32(0)` and of one leaf is the leaf; larger
+//! lists are hashed pairwise, `keccak256(abi.encodePacked(left, right))`,
+//! level by level, carrying an unpaired last node up unchanged. Tokens owed
+//! to positions are not committed to, since the contracts pay them out
+//! instead of storing them.
+
+use ethers::{
+    abi::{encode, Token},
+    types::{H256, I256},
+    utils::keccak256,
+};
+use primitive_types::U256;
+
+use super::{Pool, Position, PositionKey, TickInfo};
+
+/// Domain tags of the leaves, so leaves of different kinds never collide
+const SLOT0_TAG: u8 = 0;
+const TICK_TAG: u8 = 1;
+const POSITION_TAG: u8 = 2;
+
+/// Commitment to the state of a pool, with the roots of its parts so a
+/// mismatch can be narrowed down
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StateCommitment {
+    pub root: H256,
+    pub slot0: H256,
+    pub ticks: H256,
+    pub positions: H256,
+}
+
+impl Pool {
+    /// Computes the canonical commitment to the pool's state
+    pub fn state_commitment(&self) -> StateCommitment {
+        let slot0 = slot0_leaf(self);
+        let ticks = merkle_root(self.tick_manager.ticks().map(|(tick, info)| tick_leaf(*tick, info)).collect());
+
+        let mut positions: Vec<_> = self.position_manager
+            .iter()
+            .filter(|(_, position)| position.liquidity.as_u128() > 0)
+            .map(|(key, position)| position_leaf(key, position))
+            .collect();
+        positions.sort_unstable_by_key(|(key, _)| *key);
+        let positions = merkle_root(positions.into_iter().map(|(_, leaf)| leaf).collect());
+
+        StateCommitment { root: keccak_concat(&[slot0, ticks, positions]), slot0, ticks, positions }
+    }
+}
+
+fn uint(value: impl Into<U256>) -> Token {
+    Token::Uint(value.into())
+}
+
+fn int(value: i128) -> Token {
+    Token::Int(I256::from(value).into_raw())
+}
+
+fn leaf(tokens: &[Token]) -> H256 {
+    H256(keccak256(encode(tokens)))
+}
+
+fn slot0_leaf(pool: &Pool) -> H256 {
+    let slot0 = &pool.slot0;
+    leaf(&[
+        uint(SLOT0_TAG),
+        uint(slot0.sqrt_price_x96.to_u256()),
+        int(slot0.tick.into()),
+        uint(slot0.protocol_fee),
+        uint(slot0.lp_fee_for(true).get()),
+        uint(slot0.lp_fee_for(false).get()),
+        uint(pool.fee_growth_global_0_x128),
+        uint(pool.fee_growth_global_1_x128),
+        uint(pool.liquidity.as_u128()),
+    ])
+}
+
+fn tick_leaf(tick: i32, info: &TickInfo) -> H256 {
+    leaf(&[
+        uint(TICK_TAG),
+        int(tick.into()),
+        uint(info.liquidity_gross.as_u128()),
+        int(info.liquidity_net),
+        uint(info.fee_growth_outside_0_x128),
+        uint(info.fee_growth_outside_1_x128),
+    ])
+}
+
+fn position_leaf(key: &PositionKey, position: &Position) -> ([u8; 32], H256) {
+    let id = key.storage_id();
+    let leaf = leaf(&[
+        uint(POSITION_TAG),
+        Token::FixedBytes(id.to_vec()),
+        uint(position.liquidity.as_u128()),
+        uint(position.fee_growth_inside_0_last_x128),
+        uint(position.fee_growth_inside_1_last_x128),
+    ]);
+    (id, leaf)
+}
+
+fn keccak_concat(words: &[H256]) -> H256 {
+    H256(keccak256(words.iter().flat_map(|word| word.0).collect::<Vec<_>>()))
+}
+
+fn hash_pair(left: &H256, right: &H256) -> H256 {
+    keccak_concat(&[*left, *right])
+}
+
+/// Merkle root of leaves in order, see the module documentation
+fn merkle_root(mut nodes: Vec<H256>) -> H256 {
+    if nodes.is_empty() {
+        return H256::zero();
+    }
+    while nodes.len() > 1 {
+        nodes = nodes
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => hash_pair(left, right),
+                [single] => *single,
+                _ => unreachable!("chunks of two"),
+            })
+            .collect();
+    }
+    nodes[0]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::math::{types::{SqrtPrice, TickSpacing}, FeePips};
+
+    fn pool_with_positions(positions: &[([u8; 20], i32, i32)]) -> Pool {
+        let mut pool = Pool::new();
+        pool.initialize(SqrtPrice::ONE, FeePips::new(3000)).unwrap();
+        let spacing = TickSpacing::new(60).unwrap();
+        for &(owner, lower, upper) in positions {
+            pool.modify_position(owner, lower, upper, 1_000_000, spacing, [0; 32]).unwrap();
+        }
+        pool
+    }
+
+    #[test]
+    fn test_commitment_follows_schema() {
+        let pool = pool_with_positions(&[([1; 20], -120, 60)]);
+        let commitment = pool.state_commitment();
+
+        let word = |tokens: Vec<Token>| H256(keccak256(encode(&tokens)));
+        let slot0 = word(vec![
+            Token::Uint(0.into()),
+            Token::Uint(SqrtPrice::ONE.to_u256()),
+            Token::Int(0.into()),
+            Token::Uint(0.into()),
+            Token::Uint(3000.into()),
+            Token::Uint(3000.into()),
+            Token::Uint(0.into()),
+            Token::Uint(0.into()),
+            Token::Uint(1_000_000.into()),
+        ]);
+        let tick = |tick: i64, net: i64| word(vec![
+            Token::Uint(1.into()),
+            Token::Int(I256::from(tick).into_raw()),
+            Token::Uint(1_000_000.into()),
+            Token::Int(I256::from(net).into_raw()),
+            Token::Uint(0.into()),
+            Token::Uint(0.into()),
+        ]);
+        let position = word(vec![
+            Token::Uint(2.into()),
+            Token::FixedBytes(PositionKey::default_position([1; 20], -120, 60).storage_id().to_vec()),
+            Token::Uint(1_000_000.into()),
+            Token::Uint(0.into()),
+            Token::Uint(0.into()),
+        ]);
+        let ticks = H256(keccak256([tick(-120, 1_000_000).0, tick(60, -1_000_000).0].concat()));
+        assert_eq!(commitment, StateCommitment {
+            root: H256(keccak256([slot0.0, ticks.0, position.0].concat())),
+            slot0,
+            ticks,
+            positions: position,
+        });
+    }
+
+    #[test]
+    fn test_empty_pool_root_is_pinned() {
+        // Guards the schema: changing it must be deliberate, as exporters in
+        // other implementations follow it
+        let commitment = pool_with_positions(&[]).state_commitment();
+        assert_eq!((commitment.ticks, commitment.positions), (H256::zero(), H256::zero()));
+        assert_eq!(format!("{:?}", commitment.root), "0xd08a12b54cc30948246304953df960784ea2be9b1b6a1022f974e188f0d4c3d8");
+    }
+
+    #[test]
+    fn test_commitment_tracks_state_not_history() {
+        let positions = [([1; 20], -120, 60), ([2; 20], -600, 600), ([3; 20], 0, 120)];
+        let mut reversed = positions;
+        reversed.reverse();
+        let pool = pool_with_positions(&positions);
+        assert_eq!(pool.state_commitment(), pool_with_positions(&reversed).state_commitment());
+
+        // Emptied positions and owed tokens are not committed to
+        let mut emptied = pool.clone();
+        emptied.modify_position([4; 20], -60, 60, 1, TickSpacing::new(60).unwrap(), [0; 32]).unwrap();
+        emptied.modify_position([4; 20], -60, 60, -1, TickSpacing::new(60).unwrap(), [0; 32]).unwrap();
+        assert_eq!(emptied.state_commitment(), pool.state_commitment());
+        let mut owed = pool.clone();
+        owed.donate_to_position(&PositionKey::default_position([1; 20], -120, 60), 10, 10).unwrap();
+        assert_eq!(owed.state_commitment(), pool.state_commitment());
+
+        // Any committed field changes the root and only its part
+        let mut changed = pool.clone();
+        let mut info = changed.tick_manager.get_tick(60).unwrap().clone();
+        info.fee_growth_outside_0_x128 += U256::one();
+        changed.tick_manager.restore_tick(60, info, TickSpacing::new(60).unwrap()).unwrap();
+        let (before, after) = (pool.state_commitment(), changed.state_commitment());
+        assert_ne!(before.root, after.root);
+        assert_ne!(before.ticks, after.ticks);
+        assert_eq!((before.slot0, before.positions), (after.slot0, after.positions));
+
+        let mut asymmetric = pool.clone();
+        asymmetric.set_lp_fees(FeePips::new(3000), FeePips::new(500)).unwrap();
+        assert_ne!(asymmetric.state_commitment().slot0, before.slot0);
+    }
+
+    #[test]
+    fn test_merkle_root_carries_unpaired_nodes() {
+        let leaves: Vec<_> = (1..=3u8).map(H256::repeat_byte).collect();
+        assert_eq!(merkle_root(vec![]), H256::zero());
+        assert_eq!(merkle_root(leaves[..1].to_vec()), leaves[0]);
+        assert_eq!(merkle_root(leaves.clone()), hash_pair(&hash_pair(&leaves[0], &leaves[1]), &leaves[2]));
+    }
+}
@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+use std::mem::size_of;
+
+use primitive_types::U256;
+
+use crate::core::math::types::Liquidity;
+use super::{
+    memory::{hash_map_bytes, MemoryUsage},
+    position::{Position, PositionKey, PositionManager},
+};
+
+/// A u128 as two u64 limbs, least significant first, which needs 8-byte
+/// rather than 16-byte alignment
+fn to_limbs(value: u128) -> [u64; 2] {
+    [value as u64, (value >> 64) as u64]
+}
+
+fn from_limbs(limbs: [u64; 2]) -> u128 {
+    (limbs[1] as u128) << 64 | limbs[0] as u128
+}
+
+/// A [`PositionKey`] that keeps its salt on the heap only when it isn't the
+/// default one, which most positions use
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct CompactPositionKey {
+    owner: [u8; 20],
+    tick_lower: i32,
+    tick_upper: i32,
+    salt: Option<Box<[u8; 32]>>,
+}
+
+impl From<PositionKey> for CompactPositionKey {
+    fn from(key: PositionKey) -> Self {
+        Self {
+            owner: key.owner,
+            tick_lower: key.tick_lower,
+            tick_upper: key.tick_upper,
+            salt: (key.salt != PositionKey::DEFAULT_SALT).then(|| Box::new(key.salt)),
+        }
+    }
+}
+
+impl From<CompactPositionKey> for PositionKey {
+    fn from(key: CompactPositionKey) -> Self {
+        Self {
+            owner: key.owner,
+            tick_lower: key.tick_lower,
+            tick_upper: key.tick_upper,
+            salt: key.salt.map_or(PositionKey::DEFAULT_SALT, |salt| *salt),
+        }
+    }
+}
+
+/// A [`Position`] with 8-byte aligned amounts that keeps its fees owed on the
+/// heap only when there are any, as fees are paid out on every update
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompactPosition {
+    liquidity: [u64; 2],
+    fee_growth_inside_last_x128: [U256; 2],
+    tokens_owed: Option<Box<[[u64; 2]; 2]>>,
+}
+
+impl From<Position> for CompactPosition {
+    fn from(position: Position) -> Self {
+        let owed = position.tokens_owed_0 != 0 || position.tokens_owed_1 != 0;
+        Self {
+            liquidity: to_limbs(position.liquidity.as_u128()),
+            fee_growth_inside_last_x128: [position.fee_growth_inside_0_last_x128, position.fee_growth_inside_1_last_x128],
+            tokens_owed: owed.then(|| Box::new([to_limbs(position.tokens_owed_0), to_limbs(position.tokens_owed_1)])),
+        }
+    }
+}
+
+impl From<CompactPosition> for Position {
+    fn from(position: CompactPosition) -> Self {
+        let [owed0, owed1] = position.tokens_owed.map_or([[0; 2]; 2], |owed| *owed);
+        Self {
+            liquidity: Liquidity::new(from_limbs(position.liquidity)),
+            fee_growth_inside_0_last_x128: position.fee_growth_inside_last_x128[0],
+            fee_growth_inside_1_last_x128: position.fee_growth_inside_last_x128[1],
+            tokens_owed_0: from_limbs(owed0),
+            tokens_owed_1: from_limbs(owed1),
+        }
+    }
+}
+
+/// The positions of a pool packed to use less memory, but that can't be
+/// modified until [expanded](Self::into_manager) again
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CompactPositions {
+    positions: HashMap<CompactPositionKey, CompactPosition>,
+}
+
+impl CompactPositions {
+    /// Number of positions
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// Whether there are no positions
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// Gets a position by its key
+    pub fn get(&self, key: &PositionKey) -> Option<Position> {
+        self.positions.get(&key.clone().into()).cloned().map(Position::from)
+    }
+
+    /// Unpacks the positions into a manager that can modify them
+    pub fn into_manager(self) -> PositionManager {
+        let mut manager = PositionManager::new();
+        for (key, position) in self.positions {
+            manager.insert(key.into(), position.into());
+        }
+        manager
+    }
+
+    /// Estimated memory of the positions, including the salts and fees owed
+    /// kept on the heap
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let boxed: usize = self.positions
+            .iter()
+            .map(|(key, position)| {
+                key.salt.as_ref().map_or(0, |_| size_of::<[u8; 32]>())
+                    + position.tokens_owed.as_ref().map_or(0, |_| size_of::<[[u64; 2]; 2]>())
+            })
+            .sum();
+        MemoryUsage {
+            positions: self.positions.len(),
+            position_bytes: hash_map_bytes::<CompactPositionKey, CompactPosition>(self.positions.capacity()) + boxed,
+            ..Default::default()
+        }
+    }
+}
+
+impl FromIterator<(PositionKey, Position)> for CompactPositions {
+    fn from_iter<I: IntoIterator<Item = (PositionKey, Position)>>(iter: I) -> Self {
+        Self { positions: iter.into_iter().map(|(key, position)| (key.into(), position.into())).collect() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position(liquidity: u128, tokens_owed_0: u128) -> Position {
+        Position {
+            liquidity: Liquidity::new(liquidity),
+            fee_growth_inside_0_last_x128: U256::MAX - 1,
+            fee_growth_inside_1_last_x128: U256::from(7) << 200,
+            tokens_owed_0,
+            tokens_owed_1: 0,
+        }
+    }
+
+    #[test]
+    fn test_compact_positions_round_trip() {
+        let salted = PositionKey { salt: [9; 32], ..PositionKey::default_position([1; 20], -60, 60) };
+        let mut manager = PositionManager::new();
+        manager.insert(PositionKey::default_position([1; 20], -60, 60), position(u128::MAX, 0));
+        manager.insert(salted.clone(), position(1, u128::MAX - 3));
+        let compact = manager.clone().into_compact();
+
+        assert_eq!(compact.len(), 2);
+        assert_eq!(compact.get(&salted), Some(position(1, u128::MAX - 3)));
+        assert_eq!(compact.into_manager(), manager);
+    }
+
+    #[test]
+    fn test_compact_positions_use_less_memory() {
+        assert!(size_of::<(CompactPositionKey, CompactPosition)>() < size_of::<(PositionKey, Position)>());
+
+        let mut manager = PositionManager::new();
+        for index in 0..1000u32 {
+            let mut owner = [0; 20];
+            owner[..4].copy_from_slice(&index.to_be_bytes());
+            manager.insert(PositionKey::default_position(owner, -60, 60), position(1_000, 0));
+        }
+        let usage = manager.memory_usage();
+        let compact = manager.into_compact().memory_usage();
+        assert_eq!(compact.positions, usage.positions);
+        assert!(compact.position_bytes < usage.position_bytes);
+    }
+}
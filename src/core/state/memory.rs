@@ -0,0 +1,79 @@
+use std::iter::Sum;
+use std::mem::size_of;
+use std::ops::Add;
+
+use serde::Serialize;
+
+/// Entries a B-tree node holds at most
+const BTREE_NODE_CAPACITY: usize = 11;
+/// Entries a B-tree node holds on average, nodes being between half and
+/// completely full
+const BTREE_AVERAGE_ENTRIES: usize = 8;
+/// Bytes of a B-tree node besides its entries, for its parent pointer, index
+/// and length
+const BTREE_NODE_OVERHEAD: usize = 16;
+/// Control bytes a hash table has past its last bucket
+const HASH_GROUP_WIDTH: usize = 16;
+
+/// Estimated heap memory of the tick and position state of a pool
+///
+/// Bytes are estimated from the sizes of the entries and the capacities of
+/// the maps holding them, without allocator overhead, so they compare states
+/// and layouts rather than match the process' resident memory.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct MemoryUsage {
+    /// Number of initialized ticks
+    pub ticks: usize,
+    pub tick_bytes: usize,
+    /// Number of nonzero words of the tick bitmap
+    pub bitmap_words: usize,
+    pub bitmap_bytes: usize,
+    /// Number of positions
+    pub positions: usize,
+    pub position_bytes: usize,
+}
+
+impl MemoryUsage {
+    /// Estimated bytes of all the state
+    pub fn total_bytes(&self) -> usize {
+        self.tick_bytes + self.bitmap_bytes + self.position_bytes
+    }
+}
+
+impl Add for MemoryUsage {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            ticks: self.ticks + other.ticks,
+            tick_bytes: self.tick_bytes + other.tick_bytes,
+            bitmap_words: self.bitmap_words + other.bitmap_words,
+            bitmap_bytes: self.bitmap_bytes + other.bitmap_bytes,
+            positions: self.positions + other.positions,
+            position_bytes: self.position_bytes + other.position_bytes,
+        }
+    }
+}
+
+impl Sum for MemoryUsage {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), Add::add)
+    }
+}
+
+/// Estimated bytes of the nodes of a `BTreeMap<K, V>` of `len` entries
+pub(crate) fn btree_map_bytes<K, V>(len: usize) -> usize {
+    let node = BTREE_NODE_CAPACITY * (size_of::<K>() + size_of::<V>()) + BTREE_NODE_OVERHEAD;
+    len.div_ceil(BTREE_AVERAGE_ENTRIES) * node
+}
+
+/// Bytes of the table of a `HashMap<K, V>` of the given capacity, one entry
+/// and one control byte per bucket
+pub(crate) fn hash_map_bytes<K, V>(capacity: usize) -> usize {
+    if capacity == 0 {
+        return 0;
+    }
+    // Tables below 8 buckets use all but one, larger ones seven in eight
+    let buckets = if capacity < 7 { capacity + 1 } else { capacity / 7 * 8 };
+    buckets * (size_of::<(K, V)>() + 1) + HASH_GROUP_WIDTH
+}
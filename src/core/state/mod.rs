@@ -1,17 +1,31 @@
+mod compact;
+mod memory;
 mod pool;
 mod position;
+mod quote;
+mod stats;
 mod tick;
+mod tick_bitmap;
+mod commitment;
 mod types;
 
+pub use compact::*;
+pub use memory::MemoryUsage;
 pub use pool::*;
 pub use position::*;
+pub use stats::*;
 pub use tick::*;
+pub use tick_bitmap::TickBitmap;
+pub use commitment::*;
 pub use types::*;
 
 use thiserror::Error;
 
+use crate::core::math::{format_price, SqrtPrice};
+
 /// Common error types for state operations
 #[derive(Debug, Error)]
+#[non_exhaustive]
 pub enum StateError {
     #[error("Ticks misordered: lower {0}, upper {1}")]
     TicksMisordered(i32, i32),
@@ -25,17 +39,27 @@ pub enum StateError {
     #[error("Tick liquidity overflow at tick {0}")]
     TickLiquidityOverflow(i32),
 
+    #[error("Tick {0} is not a multiple of the tick spacing {1}")]
+    TickMisaligned(i32, i32),
+
     #[error("Pool already initialized")]
     PoolAlreadyInitialized,
 
     #[error("Pool not initialized")]
     PoolNotInitialized,
 
-    #[error("Price limit already exceeded: current {0}, limit {1}")]
-    PriceLimitAlreadyExceeded(u128, u128),
+    #[error("Price limit already exceeded: current price {0}, limit {1}")]
+    PriceLimitAlreadyExceeded(SqrtPrice, SqrtPrice),
 
     #[error("Price limit out of bounds: {0}")]
-    PriceLimitOutOfBounds(u128),
+    PriceLimitOutOfBounds(SqrtPrice),
+
+    #[error(
+        "Price limit {} is more than {max_factor}x away from the current price {}; was a price passed instead of a Q64.96 square root price?",
+        format_price(*.limit_price),
+        format_price(*.current_price)
+    )]
+    PriceLimitImplausible { current_price: f64, limit_price: f64, max_factor: f64 },
 
     #[error("No liquidity to receive fees")]
     NoLiquidityToReceiveFees,
@@ -52,8 +76,71 @@ pub enum StateError {
     #[error("Liquidity position not found")]
     LiquidityNotFound,
     
+    #[error("Cannot update an empty position")]
+    CannotUpdateEmptyPosition,
+    
+    #[error("Swap amount cannot be zero")]
+    SwapAmountCannotBeZero,
+    
+    #[error("Swap amount overflows i128")]
+    AmountOverflow,
+    
+    #[error("Fee of {0} pips is above 100%")]
+    FeeTooLarge(u32),
+    
+    #[error("Protocol fee {0:#x} is above the maximum")]
+    ProtocolFeeTooLarge(u32),
+    
+    #[error("Pool fee {0:#x} is static; only dynamic fee pools can change their LP fee")]
+    FeeNotDynamic(u32),
+    
     #[error("Insufficient liquidity for operation")]
     InsufficientLiquidity,
+    
+    #[error("Cannot merge a position into itself")]
+    SelfMerge,
+    
+    #[error("Split tick {0} is not strictly inside the position range")]
+    InvalidSplitTick(i32),
+    
+    #[error("Exact output requires {required} input, more than the maximum {max}")]
+    MaxInputExceeded { required: u128, max: u128 },
+    
+    #[error("Pools of a rebalance must have the same currencies")]
+    RebalanceCurrencyMismatch,
+    
+    #[error("Slippage exceeded: received {received}, at least {minimum} required")]
+    SlippageExceeded { received: u128, minimum: u128 },
+    
+    #[error("Liquidity {liquidity} below the minimum {minimum}")]
+    LiquidityBelowMinimum { liquidity: u128, minimum: u128 },
+    
+    #[error("Swap did not complete within {0} steps")]
+    SwapStepLimit(u32),
+    
+    #[error("Pool paused")]
+    PoolPaused,
+    
+    #[error("Pool manager paused")]
+    ManagerPaused,
+    
+    #[error("Pool is withdraw-only")]
+    PoolWithdrawOnly,
+    
+    #[error("Currency {0} is not allowed")]
+    CurrencyNotAllowed(crate::core::flash_loan::Currency),
+    
+    #[error("{0}")]
+    InvalidCurrencyId(#[from] crate::core::flash_loan::InvalidCurrencyId),
+    
+    #[error("Operation not allowed while the manager is unlocked")]
+    ManagerUnlocked,
+    
+    #[error("Hook error: {0}")]
+    Hook(#[from] crate::core::hooks::HookError),
+    
+    #[error("Claims error: {0}")]
+    Claims(#[from] crate::tokens::erc6909::ERC6909Error),
 }
 
 /// Result type for state operations
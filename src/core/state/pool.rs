@@ -1,41 +1,73 @@
+use std::fmt;
+use std::sync::Arc;
 use primitive_types::U256;
 use num_traits::Zero;
 use ethers::types::Address;
+use serde::{Deserialize, Serialize};
 
 use crate::core::math::{
     TickMath,
     SqrtPriceMath,
     SwapMath,
-    types::{SqrtPrice, Liquidity, U256Ext},
+    SqrtPriceCache,
+    SqrtPriceTable,
+    FeePips,
+    U256Ext,
+    types::{SqrtPrice, Liquidity, TickSpacing},
 };
 
 use super::{
     Result,
     StateError,
-    types::{Slot0, BalanceDelta},
+    types::{AuxiliaryFees, Slot0, BalanceDelta, CrossDirection, OnStepLimit, SwapConfig, SwapReport, TickCross},
+    stats::PoolStats,
     tick::TickManager,
-    position::{PositionManager, PositionKey},
+    memory::MemoryUsage,
+    compact::CompactPositions,
+    position::{FeeGrowthSnapshot, Position, PositionManager, PositionKey},
 };
 
+use crate::fees::ProtocolFee;
 // 添加对ERC6909令牌的引用
 use crate::tokens::erc6909::{LiquidityToken, ERC6909Error};
 
 /// Pool state and operations
+///
+/// Pools compare equal when every field matches, including all ticks and
+/// positions; position equality is by key, independent of `HashMap`
+/// iteration order. Cloning a pool gives an independent snapshot for
+/// comparing states before and after operations.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Pool {
     /// The most frequently accessed state
     pub slot0: Slot0,
-    /// The current protocol fee growth of token0 accumulated per unit of liquidity
+    /// The current protocol fee growth of token0 accumulated per unit of
+    /// liquidity, wrapping modulo 2^256 like the Solidity accumulator; only
+    /// differences between readings are meaningful
     pub fee_growth_global_0_x128: U256,
-    /// The current protocol fee growth of token1 accumulated per unit of liquidity
+    /// The current protocol fee growth of token1 accumulated per unit of
+    /// liquidity, wrapping like `fee_growth_global_0_x128`
     pub fee_growth_global_1_x128: U256,
     /// The current liquidity in the pool
     pub liquidity: Liquidity,
     /// The tick manager
     pub tick_manager: TickManager,
-    /// The position manager
+    /// The position manager, stored separately from the pool by
+    /// [`PoolStore`](crate::core::storage::PoolStore)
+    #[serde(skip, default = "PositionManager::new")]
     pub position_manager: PositionManager,
     /// Liquidity token for tracking positions
     pub liquidity_token: Option<LiquidityToken>,
+    /// Cumulative trading statistics, updated by swaps
+    stats: PoolStats,
+    /// Experimental fees on withdrawals and donations, disabled by default
+    auxiliary_fees: AuxiliaryFees,
+    /// Net token0 and token1 deltas a manager accounted to the pool's hook
+    #[serde(default)]
+    hook_deltas: [i128; 2],
+    /// Sqrt prices of recently used ticks
+    #[serde(skip)]
+    sqrt_price_cache: SqrtPriceCache,
 }
 
 impl Pool {
@@ -46,7 +78,8 @@ impl Pool {
                 sqrt_price_x96: SqrtPrice::new(U256::zero()),
                 tick: 0,
                 protocol_fee: 0,
-                lp_fee: 0,
+                lp_fee: FeePips::ZERO,
+                lp_fee_one_for_zero: None,
             },
             fee_growth_global_0_x128: U256::zero(),
             fee_growth_global_1_x128: U256::zero(),
@@ -54,14 +87,113 @@ impl Pool {
             tick_manager: TickManager::new(),
             position_manager: PositionManager::new(),
             liquidity_token: None,
+            stats: PoolStats::default(),
+            auxiliary_fees: AuxiliaryFees::default(),
+            hook_deltas: [0; 2],
+            sqrt_price_cache: SqrtPriceCache::default(),
         }
     }
 
+    /// Gets the cumulative trading statistics of the pool
+    pub fn stats(&self) -> &PoolStats {
+        &self.stats
+    }
+
+    /// Estimated memory of the pool's ticks and positions, which grow with
+    /// its use, unlike the rest of the pool
+    pub fn memory_usage(&self) -> MemoryUsage {
+        self.tick_manager.memory_usage() + self.position_manager.memory_usage()
+    }
+
+    /// Takes the pool's positions out packed into their compact form, e.g. to
+    /// keep many pools in less memory while only swapping on them
+    ///
+    /// Ticks and pool liquidity still include the parked positions, so swaps
+    /// and donations are unaffected, but the parked positions can't be
+    /// modified until [put back](Self::unpark_positions).
+    pub fn park_positions(&mut self) -> CompactPositions {
+        std::mem::replace(&mut self.position_manager, PositionManager::new()).into_compact()
+    }
+
+    /// Puts back positions taken out by [`park_positions`](Self::park_positions)
+    ///
+    /// A parked position with the same key as one opened since is merged into
+    /// it, as by [`merge_positions`](Self::merge_positions), and the pool is
+    /// left as it was if any merged sum overflows.
+    pub fn unpark_positions(&mut self, positions: CompactPositions) -> Result<()> {
+        if self.position_manager.is_empty() {
+            self.position_manager = positions.into_manager();
+            return Ok(());
+        }
+
+        let mut manager = self.position_manager.clone();
+        let parked = positions.into_manager();
+        for (key, position) in parked.iter() {
+            if manager.get(key).is_none() {
+                manager.insert(key.clone(), position.clone());
+                continue;
+            }
+            let (fee_growth_inside_0_x128, fee_growth_inside_1_x128) = self.tick_manager
+                .get_fee_growth_inside(
+                    key.tick_lower,
+                    key.tick_upper,
+                    self.slot0.tick,
+                    self.fee_growth_global_0_x128,
+                    self.fee_growth_global_1_x128,
+                );
+            let position = parked.settled(key, fee_growth_inside_0_x128, fee_growth_inside_1_x128)?;
+            manager.absorb(key.clone(), position, fee_growth_inside_0_x128, fee_growth_inside_1_x128)?;
+        }
+        self.position_manager = manager;
+        Ok(())
+    }
+
+    /// Gets the cache of tick sqrt prices the pool's swaps and position
+    /// changes use
+    pub fn sqrt_price_cache(&self) -> &SqrtPriceCache {
+        &self.sqrt_price_cache
+    }
+
+    /// Empties the cache of tick sqrt prices, which changes no results
+    pub fn clear_sqrt_price_cache(&mut self) {
+        self.sqrt_price_cache.clear();
+    }
+
+    /// Shares a table of tick sqrt prices with the pool's cache, or stops
+    /// sharing one; this changes no results either
+    pub fn set_sqrt_price_table(&mut self, table: Option<Arc<SqrtPriceTable>>) {
+        self.sqrt_price_cache.set_table(table);
+    }
+
+    /// Records the time of the last swap, which the pool has no clock to know
+    pub fn record_trade_timestamp(&mut self, timestamp: u64) {
+        self.stats.last_trade_timestamp = Some(timestamp);
+    }
+
+    /// Gets the net deltas a manager accounted to the pool's hook, the
+    /// pool's share of the hook's deltas
+    pub fn hook_deltas(&self) -> BalanceDelta {
+        BalanceDelta::new(self.hook_deltas[0], self.hook_deltas[1])
+    }
+
+    /// Adds to the deltas accounted to the pool's hook
+    pub(crate) fn record_hook_delta(&mut self, delta: BalanceDelta) -> Result<()> {
+        let add = |total: i128, amount| total.checked_add(amount).ok_or(StateError::AmountOverflow);
+        self.hook_deltas = [add(self.hook_deltas[0], delta.amount0())?, add(self.hook_deltas[1], delta.amount1())?];
+        Ok(())
+    }
+
+    /// Clears the deltas accounted to the pool's hook, e.g. once they are
+    /// flushed
+    pub(crate) fn clear_hook_deltas(&mut self) {
+        self.hook_deltas = [0; 2];
+    }
+
     /// Initializes the pool with an initial sqrt price and LP fee
     pub fn initialize(
         &mut self,
         sqrt_price_x96: SqrtPrice,
-        lp_fee: u32,
+        lp_fee: FeePips,
     ) -> Result<i32> {
         if !self.slot0.sqrt_price_x96.is_zero() {
             return Err(StateError::PoolAlreadyInitialized);
@@ -75,59 +207,80 @@ impl Pool {
             tick,
             protocol_fee: 0,
             lp_fee,
+            lp_fee_one_for_zero: None,
         };
 
         Ok(tick)
     }
 
-    /// Sets the protocol fee
+    /// Sets the protocol fee, packed as a [`ProtocolFee`]
     pub fn set_protocol_fee(&mut self, protocol_fee: u32) -> Result<()> {
         if self.slot0.sqrt_price_x96.is_zero() {
             return Err(StateError::PoolNotInitialized);
         }
+        ProtocolFee(protocol_fee).validate().map_err(|_| StateError::ProtocolFeeTooLarge(protocol_fee))?;
         self.slot0.protocol_fee = protocol_fee;
         Ok(())
     }
 
-    /// Sets the LP fee
-    pub fn set_lp_fee(&mut self, lp_fee: u32) -> Result<()> {
+    /// Sets the LP fee of both directions
+    pub fn set_lp_fee(&mut self, lp_fee: FeePips) -> Result<()> {
+        self.set_lp_fees(lp_fee, lp_fee)
+    }
+
+    /// Sets the LP fees of zero-for-one and one-for-zero swaps, which may
+    /// differ
+    pub fn set_lp_fees(&mut self, zero_for_one: FeePips, one_for_zero: FeePips) -> Result<()> {
         if self.slot0.sqrt_price_x96.is_zero() {
             return Err(StateError::PoolNotInitialized);
         }
-        self.slot0.lp_fee = lp_fee;
+        for fee in [zero_for_one, one_for_zero] {
+            if fee > FeePips::MAX {
+                return Err(StateError::FeeTooLarge(fee.get()));
+            }
+        }
+        self.slot0.lp_fee = zero_for_one;
+        self.slot0.lp_fee_one_for_zero = (one_for_zero != zero_for_one).then_some(one_for_zero);
         Ok(())
     }
 
-    /// Modifies liquidity for a position with the given parameters
-    pub fn modify_liquidity(
-        &mut self,
-        tick_lower: i32, 
-        tick_upper: i32,
-        liquidity_delta: i128,
-        tick_spacing: i32,
-    ) -> Result<(BalanceDelta, BalanceDelta)> {
-        // Default empty owner and salt (these will be handled by the PositionManager)
-        let owner = [0u8; 20];
-        let salt = [0u8; 32];
-        
-        self.modify_position(
-            owner,
-            tick_lower,
-            tick_upper,
-            liquidity_delta,
-            tick_spacing,
-            salt,
-        )
+    /// Gets the experimental withdrawal and donation fees
+    pub fn auxiliary_fees(&self) -> AuxiliaryFees {
+        self.auxiliary_fees
+    }
+
+    /// Sets the experimental withdrawal and donation fees; zero disables them
+    pub fn set_auxiliary_fees(&mut self, fees: AuxiliaryFees) -> Result<()> {
+        for fee in [fees.withdrawal, fees.donation] {
+            if fee > FeePips::MAX {
+                return Err(StateError::FeeTooLarge(fee.get()));
+            }
+        }
+        self.auxiliary_fees = fees;
+        Ok(())
+    }
+
+    /// Donation fee on donated amounts
+    fn donation_fee(&self, amount0: u128, amount1: u128) -> (u128, u128) {
+        let fee = self.auxiliary_fees.donation;
+        (fee.of(U256::from(amount0)).saturating_as_u128(), fee.of(U256::from(amount1)).saturating_as_u128())
     }
 
     /// Modifies the position's liquidity and returns the resulting balance changes
+    ///
+    /// The pool's position manager is the only record of positions, keyed by
+    /// owner, tick range and salt. A zero `liquidity_delta` only collects the
+    /// fees accrued by an existing position, leaving ticks and liquidity as
+    /// they are. When a withdrawal fee is set, removals pay it out of the
+    /// principal returned in the balance delta; collected fees are not
+    /// charged.
     pub fn modify_position(
         &mut self,
         owner: [u8; 20],
         tick_lower: i32,
         tick_upper: i32,
         liquidity_delta: i128,
-        tick_spacing: i32,
+        tick_spacing: TickSpacing,
         salt: [u8; 32],
     ) -> Result<(BalanceDelta, BalanceDelta)> {
         if tick_lower >= tick_upper {
@@ -140,12 +293,43 @@ impl Pool {
             return Err(StateError::TickUpperOutOfBounds(tick_upper));
         }
 
+        if liquidity_delta == 0 {
+            return self.poke_position(PositionKey { owner, tick_lower, tick_upper, salt });
+        }
+        // No tick holds more than i128::MAX, so no position can lose more
+        if liquidity_delta == i128::MIN {
+            return Err(StateError::LiquidityOverflow);
+        }
+
+        // Check both ticks and the position before touching any of them, so a
+        // rejected change leaves the pool as it was
+        for tick in [tick_lower, tick_upper] {
+            if tick % tick_spacing.get() != 0 {
+                return Err(StateError::TickMisaligned(tick, tick_spacing.get()));
+            }
+        }
+        let key = PositionKey { owner, tick_lower, tick_upper, salt };
+        let max_liquidity_per_tick = Self::tick_spacing_to_max_liquidity_per_tick(tick_spacing);
+        for tick in [tick_lower, tick_upper] {
+            let gross = self.tick_manager.get_tick(tick).map_or(0, |info| info.liquidity_gross.as_u128());
+            match gross.checked_add_signed(liquidity_delta) {
+                Some(after) if liquidity_delta < 0 || after <= max_liquidity_per_tick => {}
+                _ => return Err(StateError::TickLiquidityOverflow(tick)),
+            }
+        }
+        if liquidity_delta < 0 {
+            let position = self.position_manager.get(&key).ok_or(StateError::LiquidityNotFound)?;
+            if position.liquidity.as_u128() < liquidity_delta.unsigned_abs() {
+                return Err(StateError::LiquidityOverflow);
+            }
+        }
+
         let mut balance_delta = BalanceDelta::default();
         let mut fee_delta = BalanceDelta::default();
 
-        // Update the ticks and check liquidity bounds
+        // Update the ticks
         if liquidity_delta != 0 {
-            let (_flipped_lower, liquidity_gross_after_lower) = self.tick_manager.update_tick(
+            let (flipped_lower, _) = self.tick_manager.update_tick(
                 tick_lower,
                 liquidity_delta,
                 self.fee_growth_global_0_x128,
@@ -154,7 +338,7 @@ impl Pool {
                 &self.slot0,
             )?;
 
-            let (_flipped_upper, liquidity_gross_after_upper) = self.tick_manager.update_tick(
+            let (flipped_upper, _) = self.tick_manager.update_tick(
                 tick_upper,
                 liquidity_delta,
                 self.fee_growth_global_0_x128,
@@ -162,25 +346,14 @@ impl Pool {
                 true,
                 &self.slot0,
             )?;
-
-            if liquidity_delta > 0 {
-                let max_liquidity_per_tick = Self::tick_spacing_to_max_liquidity_per_tick(tick_spacing);
-                if liquidity_gross_after_lower > max_liquidity_per_tick {
-                    return Err(StateError::TickLiquidityOverflow(tick_lower));
-                }
-                if liquidity_gross_after_upper > max_liquidity_per_tick {
-                    return Err(StateError::TickLiquidityOverflow(tick_upper));
-                }
+            if flipped_lower {
+                self.tick_manager.flip_tick(tick_lower, tick_spacing)?;
+            }
+            if flipped_upper {
+                self.tick_manager.flip_tick(tick_upper, tick_spacing)?;
             }
 
             // Update the position
-            let key = PositionKey {
-                owner,
-                tick_lower,
-                tick_upper,
-                salt,
-            };
-
             let (fee_growth_inside_0_x128, fee_growth_inside_1_x128) = self.tick_manager
                 .get_fee_growth_inside(
                     tick_lower,
@@ -197,6 +370,17 @@ impl Pool {
                 fee_growth_inside_1_x128,
             )?;
 
+            // Clear ticks that no longer hold liquidity, only after the position
+            // has collected the fees accrued inside its range
+            if liquidity_delta < 0 {
+                if flipped_lower {
+                    self.tick_manager.clear_tick(tick_lower);
+                }
+                if flipped_upper {
+                    self.tick_manager.clear_tick(tick_upper);
+                }
+            }
+
             // Update pool liquidity if we're in range
             if self.slot0.tick >= tick_lower && self.slot0.tick < tick_upper {
                 let liquidity_next = if liquidity_delta > 0 {
@@ -212,9 +396,9 @@ impl Pool {
             if liquidity_delta != 0 {
                 let (amount0, amount1) = if self.slot0.tick < tick_lower {
                     // Current tick below position
-                    let price_lower_u256 = TickMath::get_sqrt_price_at_tick(tick_lower)
+                    let price_lower_u256 = self.sqrt_price_cache.get_sqrt_price_at_tick(tick_lower)
                         .map_err(|_| StateError::InvalidPrice)?;
-                    let price_upper_u256 = TickMath::get_sqrt_price_at_tick(tick_upper)
+                    let price_upper_u256 = self.sqrt_price_cache.get_sqrt_price_at_tick(tick_upper)
                         .map_err(|_| StateError::InvalidPrice)?;
                     let price_lower = SqrtPrice::new(price_lower_u256);
                     let price_upper = SqrtPrice::new(price_upper_u256);
@@ -230,8 +414,11 @@ impl Pool {
                 } else if self.slot0.tick < tick_upper {
                     // Current tick inside position
                     let price_current = self.slot0.sqrt_price_x96;
-                    let price_upper_u256 = TickMath::get_sqrt_price_at_tick(tick_upper)
+                    let price_lower_u256 = self.sqrt_price_cache.get_sqrt_price_at_tick(tick_lower)
+                        .map_err(|_| StateError::InvalidPrice)?;
+                    let price_upper_u256 = self.sqrt_price_cache.get_sqrt_price_at_tick(tick_upper)
                         .map_err(|_| StateError::InvalidPrice)?;
+                    let price_lower = SqrtPrice::new(price_lower_u256);
                     let price_upper = SqrtPrice::new(price_upper_u256);
                     (
                         SqrtPriceMath::get_amount0_delta(
@@ -241,17 +428,17 @@ impl Pool {
                             true,
                         ).map_err(|_| StateError::InvalidPrice)?,
                         SqrtPriceMath::get_amount1_delta(
+                            price_lower,
                             price_current,
-                            price_upper,
                             Liquidity::new(liquidity_delta.abs() as u128),
                             true,
                         ).map_err(|_| StateError::InvalidPrice)?,
                     )
                 } else {
                     // Current tick above position
-                    let price_lower_u256 = TickMath::get_sqrt_price_at_tick(tick_lower)
+                    let price_lower_u256 = self.sqrt_price_cache.get_sqrt_price_at_tick(tick_lower)
                         .map_err(|_| StateError::InvalidPrice)?;
-                    let price_upper_u256 = TickMath::get_sqrt_price_at_tick(tick_upper)
+                    let price_upper_u256 = self.sqrt_price_cache.get_sqrt_price_at_tick(tick_upper)
                         .map_err(|_| StateError::InvalidPrice)?;
                     let price_lower = SqrtPrice::new(price_lower_u256);
                     let price_upper = SqrtPrice::new(price_upper_u256);
@@ -266,6 +453,16 @@ impl Pool {
                     )
                 };
 
+                // Withdrawals pay the withdrawal fee out of the principal
+                let (amount0, amount1) = if liquidity_delta < 0 && !self.auxiliary_fees.withdrawal.is_zero() {
+                    let fee0 = self.auxiliary_fees.withdrawal.of(amount0);
+                    let fee1 = self.auxiliary_fees.withdrawal.of(amount1);
+                    self.stats.record_protocol_fees(fee0.saturating_as_u128(), fee1.saturating_as_u128());
+                    (amount0 - fee0, amount1 - fee1)
+                } else {
+                    (amount0, amount1)
+                };
+
                 balance_delta = BalanceDelta::new(
                     if liquidity_delta > 0 {
                         -(amount0.try_into().unwrap_or(i128::MAX))
@@ -284,11 +481,209 @@ impl Pool {
         Ok((balance_delta, fee_delta))
     }
 
+    /// Credits a position with the fees accrued inside its range without
+    /// changing its liquidity
+    fn poke_position(&mut self, key: PositionKey) -> Result<(BalanceDelta, BalanceDelta)> {
+        if self.position_manager.get(&key).is_none_or(|position| position.liquidity.is_zero()) {
+            return Err(StateError::CannotUpdateEmptyPosition);
+        }
+
+        let (fee_growth_inside_0_x128, fee_growth_inside_1_x128) = self.tick_manager
+            .get_fee_growth_inside(
+                key.tick_lower,
+                key.tick_upper,
+                self.slot0.tick,
+                self.fee_growth_global_0_x128,
+                self.fee_growth_global_1_x128,
+            );
+        let fee_delta = self.position_manager.update(key, 0, fee_growth_inside_0_x128, fee_growth_inside_1_x128)?;
+        Ok((BalanceDelta::default(), fee_delta))
+    }
+
+    /// Positions with liquidity that entered or left the range since the
+    /// pool was at `tick_before`, with whether each is now in range
+    ///
+    /// Only positions with a boundary between the two ticks can change, so
+    /// only those are looked at, and they are returned ordered by owner,
+    /// ticks and salt.
+    pub fn range_changes_since(&self, tick_before: i32) -> Vec<(PositionKey, bool)> {
+        let tick = self.slot0.tick;
+        if tick == tick_before {
+            return Vec::new();
+        }
+        // A bound `b` changes a position's range when the tick moves between
+        // below `b` and at or above it
+        let (low, high) = (tick.min(tick_before), tick.max(tick_before));
+        let mut changes: Vec<_> = self.position_manager
+            .with_bound_in(low + 1..=high)
+            .filter(|(key, position)| !position.is_empty() && key.is_in_range(tick) != key.is_in_range(tick_before))
+            .map(|(key, _)| (key.clone(), key.is_in_range(tick)))
+            .collect();
+        changes.sort_by_key(|(key, _)| (key.owner, key.tick_lower, key.tick_upper, key.salt));
+        changes
+    }
+
+    /// Reads the stored and current fee growth inside a position's range
+    /// without crediting fees to it
+    pub fn position_fee_growth_snapshot(&self, key: &PositionKey) -> Option<FeeGrowthSnapshot> {
+        let position = self.position_manager.get(key)?;
+        let (fee_growth_inside_0_x128, fee_growth_inside_1_x128) = self.tick_manager
+            .get_fee_growth_inside(
+                key.tick_lower,
+                key.tick_upper,
+                self.slot0.tick,
+                self.fee_growth_global_0_x128,
+                self.fee_growth_global_1_x128,
+            );
+        Some(FeeGrowthSnapshot {
+            liquidity: position.liquidity,
+            fee_growth_inside_0_last_x128: position.fee_growth_inside_0_last_x128,
+            fee_growth_inside_1_last_x128: position.fee_growth_inside_1_last_x128,
+            fee_growth_inside_0_x128,
+            fee_growth_inside_1_x128,
+            tokens_owed_0: position.tokens_owed_0,
+            tokens_owed_1: position.tokens_owed_1,
+        })
+    }
+
+    /// Merges the position keyed by `from` into the position at `into_salt`
+    /// with the same owner and range
+    ///
+    /// Both positions accrue their fees at the current fee growth first, then
+    /// the liquidity and fees owed of `from` move to the target, which is
+    /// created if needed. The range keeps the same total liquidity, so ticks,
+    /// pool liquidity and balances are untouched and the fees stay owed
+    /// instead of being paid out by a remove and re-add.
+    pub fn merge_positions(&mut self, from: &PositionKey, into_salt: [u8; 32]) -> Result<PositionKey> {
+        if from.salt == into_salt {
+            return Err(StateError::SelfMerge);
+        }
+
+        let (fee_growth_inside_0_x128, fee_growth_inside_1_x128) = self.tick_manager
+            .get_fee_growth_inside(
+                from.tick_lower,
+                from.tick_upper,
+                self.slot0.tick,
+                self.fee_growth_global_0_x128,
+                self.fee_growth_global_1_x128,
+            );
+
+        // `from` is only removed once the target has taken it, so a merge
+        // rejected by an overflowing sum leaves both positions as they were
+        let into = PositionKey { salt: into_salt, ..from.clone() };
+        let position = self.position_manager.settled(
+            from,
+            fee_growth_inside_0_x128,
+            fee_growth_inside_1_x128,
+        )?;
+        self.position_manager.absorb(
+            into.clone(),
+            position,
+            fee_growth_inside_0_x128,
+            fee_growth_inside_1_x128,
+        )?;
+        self.position_manager.remove(from);
+
+        Ok(into)
+    }
+
+    /// Splits a position at `split_tick` into positions over
+    /// `[tick_lower, split_tick)` and `[split_tick, tick_upper)`, both holding
+    /// the full liquidity and keeping the original owner and salt
+    ///
+    /// The two halves provide exactly the liquidity of the original range, so
+    /// only the split tick changes and no tokens move. Fees accrued by the
+    /// original position stay owed on the lower half; each half then accrues
+    /// from the fee growth inside its own range. Either half is merged into an
+    /// existing position with the same key.
+    pub fn split_position(
+        &mut self,
+        key: &PositionKey,
+        split_tick: i32,
+        tick_spacing: TickSpacing,
+    ) -> Result<(PositionKey, PositionKey)> {
+        if split_tick <= key.tick_lower || split_tick >= key.tick_upper {
+            return Err(StateError::InvalidSplitTick(split_tick));
+        }
+        if split_tick % tick_spacing.get() != 0 {
+            return Err(StateError::TickMisaligned(split_tick, tick_spacing.get()));
+        }
+
+        let (fee_growth_inside_0_x128, fee_growth_inside_1_x128) = self.tick_manager
+            .get_fee_growth_inside(
+                key.tick_lower,
+                key.tick_upper,
+                self.slot0.tick,
+                self.fee_growth_global_0_x128,
+                self.fee_growth_global_1_x128,
+            );
+        let position = self.position_manager.get(key).ok_or(StateError::LiquidityNotFound)?;
+        let liquidity = position.liquidity.as_u128();
+        let liquidity_delta = i128::try_from(liquidity).map_err(|_| StateError::LiquidityOverflow)?;
+        // Both halves add the liquidity to the split tick, checked before it
+        // changes so that a rejected split leaves the pool as it was
+        let gross = self.tick_manager.get_tick(split_tick).map_or(0, |info| info.liquidity_gross.as_u128());
+        match gross.checked_add(liquidity).and_then(|after| after.checked_add(liquidity)) {
+            Some(after) if after <= Self::tick_spacing_to_max_liquidity_per_tick(tick_spacing) => {}
+            _ => return Err(StateError::TickLiquidityOverflow(split_tick)),
+        }
+
+        // The split tick becomes the upper bound of one half and the lower
+        // bound of the other
+        let (flipped, _) = self.tick_manager.update_tick(
+            split_tick,
+            liquidity_delta,
+            self.fee_growth_global_0_x128,
+            self.fee_growth_global_1_x128,
+            true,
+            &self.slot0,
+        )?;
+        if flipped {
+            self.tick_manager.flip_tick(split_tick, tick_spacing)?;
+        }
+        self.tick_manager.update_tick(
+            split_tick,
+            liquidity_delta,
+            self.fee_growth_global_0_x128,
+            self.fee_growth_global_1_x128,
+            false,
+            &self.slot0,
+        )?;
+
+        let position = self.position_manager.take_settled(
+            key,
+            fee_growth_inside_0_x128,
+            fee_growth_inside_1_x128,
+        )?;
+        let upper_half = Position::new(position.liquidity);
+
+        let lower = PositionKey { tick_upper: split_tick, ..key.clone() };
+        let upper = PositionKey { tick_lower: split_tick, ..key.clone() };
+        for (half_key, half) in [(lower.clone(), position), (upper.clone(), upper_half)] {
+            let (fee_growth_inside_0_x128, fee_growth_inside_1_x128) = self.tick_manager
+                .get_fee_growth_inside(
+                    half_key.tick_lower,
+                    half_key.tick_upper,
+                    self.slot0.tick,
+                    self.fee_growth_global_0_x128,
+                    self.fee_growth_global_1_x128,
+                );
+            self.position_manager.absorb(
+                half_key,
+                half,
+                fee_growth_inside_0_x128,
+                fee_growth_inside_1_x128,
+            )?;
+        }
+
+        Ok((lower, upper))
+    }
+
     /// Calculates the maximum liquidity per tick at the given tick spacing
-    fn tick_spacing_to_max_liquidity_per_tick(tick_spacing: i32) -> u128 {
-        let min_tick = (TickMath::MIN_TICK / tick_spacing) * tick_spacing;
-        let max_tick = (TickMath::MAX_TICK / tick_spacing) * tick_spacing;
-        let num_ticks = ((max_tick - min_tick) / tick_spacing + 1) as u128;
+    fn tick_spacing_to_max_liquidity_per_tick(tick_spacing: TickSpacing) -> u128 {
+        let min_tick = tick_spacing.min_usable_tick();
+        let max_tick = tick_spacing.max_usable_tick();
+        let num_ticks = ((max_tick - min_tick) / tick_spacing.get() + 1) as u128;
         u128::MAX / num_ticks
     }
 
@@ -298,71 +693,136 @@ impl Pool {
         amount_specified: i128,
         sqrt_price_limit_x96: SqrtPrice,
         zero_for_one: bool,
-        tick_spacing: i32,
-        lp_fee_override: Option<u32>,
+        tick_spacing: TickSpacing,
+        lp_fee_override: Option<FeePips>,
     ) -> Result<(BalanceDelta, u128)> {
+        self.swap_with_tick_observer(
+            amount_specified,
+            sqrt_price_limit_x96,
+            zero_for_one,
+            tick_spacing,
+            lp_fee_override,
+            &mut |_| Ok(()),
+        )
+    }
+
+    /// Executes a swap like [`swap`](Self::swap), calling `on_cross` for each
+    /// initialized tick crossed, in order
+    ///
+    /// An error from `on_cross` aborts the swap before the state changes.
+    pub fn swap_with_tick_observer(
+        &mut self,
+        amount_specified: i128,
+        sqrt_price_limit_x96: SqrtPrice,
+        zero_for_one: bool,
+        tick_spacing: TickSpacing,
+        lp_fee_override: Option<FeePips>,
+        on_cross: &mut dyn FnMut(TickCross) -> Result<()>,
+    ) -> Result<(BalanceDelta, u128)> {
+        let report = self.swap_with_config(
+            amount_specified,
+            sqrt_price_limit_x96,
+            zero_for_one,
+            tick_spacing,
+            lp_fee_override,
+            &SwapConfig::UNBOUNDED,
+            on_cross,
+        )?;
+        Ok((report.delta, report.protocol_fee))
+    }
+
+    /// Executes a swap like [`swap_with_tick_observer`](Self::swap_with_tick_observer)
+    /// within the step limit of `config`, reporting the steps taken
+    ///
+    /// A swap that reaches the limit either fails before the state changes
+    /// or stops there with a partial delta, as `config.on_limit` selects.
+    #[allow(clippy::too_many_arguments)]
+    pub fn swap_with_config(
+        &mut self,
+        amount_specified: i128,
+        sqrt_price_limit_x96: SqrtPrice,
+        zero_for_one: bool,
+        tick_spacing: TickSpacing,
+        lp_fee_override: Option<FeePips>,
+        config: &SwapConfig,
+        on_cross: &mut dyn FnMut(TickCross) -> Result<()>,
+    ) -> Result<SwapReport> {
+        let swap = self.prepare_swap(
+            amount_specified,
+            sqrt_price_limit_x96,
+            zero_for_one,
+            tick_spacing,
+            lp_fee_override,
+            config,
+            on_cross,
+        )?;
+        Ok(self.commit_swap(swap))
+    }
+
+    /// Runs a swap like [`swap_with_config`](Self::swap_with_config) without
+    /// changing the pool, which [`commit_swap`](Self::commit_swap) then does
+    ///
+    /// Lets the manager run the checks that follow a swap, like hook results
+    /// and settlement, before the pool moves. Only the sqrt price cache is
+    /// filled in.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn prepare_swap(
+        &mut self,
+        amount_specified: i128,
+        sqrt_price_limit_x96: SqrtPrice,
+        zero_for_one: bool,
+        tick_spacing: TickSpacing,
+        lp_fee_override: Option<FeePips>,
+        config: &SwapConfig,
+        on_cross: &mut dyn FnMut(TickCross) -> Result<()>,
+    ) -> Result<PreparedSwap> {
         if self.slot0.sqrt_price_x96.is_zero() {
             return Err(StateError::PoolNotInitialized);
         }
-
-        // Special handling for test_swap
-        if self.slot0.sqrt_price_x96.to_u256() == U256::from(79228162514264337593543950336u128) &&
-           sqrt_price_limit_x96.to_u256() == U256::from(78228162514264337593543950336u128) &&
-           amount_specified == -1000 &&
-           zero_for_one == true {
-            // Return a valid result for test_swap
-            self.slot0.sqrt_price_x96 = SqrtPrice::new(U256::from(79128162514264337593543950336u128)); // Slightly lower than initial price
-            return Ok((BalanceDelta::new(-1000, 1000), 0));
+        // Amounts are negated between exact input and output
+        if amount_specified == i128::MIN {
+            return Err(StateError::AmountOverflow);
         }
 
         // Check price limit
         if zero_for_one {
             if sqrt_price_limit_x96.to_u256() >= self.slot0.sqrt_price_x96.to_u256() {
-                return Err(StateError::PriceLimitAlreadyExceeded(
-                    self.slot0.sqrt_price_x96.as_u128(),
-                    sqrt_price_limit_x96.as_u128(),
-                ));
+                return Err(StateError::PriceLimitAlreadyExceeded(self.slot0.sqrt_price_x96, sqrt_price_limit_x96));
             }
             if sqrt_price_limit_x96.to_u256() <= TickMath::MIN_SQRT_PRICE {
-                return Err(StateError::PriceLimitOutOfBounds(sqrt_price_limit_x96.as_u128()));
+                return Err(StateError::PriceLimitOutOfBounds(sqrt_price_limit_x96));
             }
         } else {
             if sqrt_price_limit_x96.to_u256() <= self.slot0.sqrt_price_x96.to_u256() {
-                return Err(StateError::PriceLimitAlreadyExceeded(
-                    self.slot0.sqrt_price_x96.as_u128(),
-                    sqrt_price_limit_x96.as_u128(),
-                ));
+                return Err(StateError::PriceLimitAlreadyExceeded(self.slot0.sqrt_price_x96, sqrt_price_limit_x96));
             }
             if sqrt_price_limit_x96.to_u256() >= TickMath::MAX_SQRT_PRICE {
-                return Err(StateError::PriceLimitOutOfBounds(sqrt_price_limit_x96.as_u128()));
+                return Err(StateError::PriceLimitOutOfBounds(sqrt_price_limit_x96));
             }
         }
 
         // Determine effective LP fee
-        let effective_lp_fee = lp_fee_override.unwrap_or(self.slot0.lp_fee);
+        let effective_lp_fee = lp_fee_override.unwrap_or(self.slot0.lp_fee_for(zero_for_one));
+
+        // Protocol fee of the swap's direction
+        let protocol_fee = ProtocolFee(self.slot0.protocol_fee);
+        let protocol_fee_rate = protocol_fee.fee(zero_for_one);
 
-        // Calculate protocol fee rate
-        let protocol_fee_rate = if zero_for_one {
-            self.slot0.protocol_fee & 0xFF 
+        // The protocol fee is charged on the input before the LP fee, so
+        // SwapMath takes both combined and the protocol's share is split off
+        // of each step's fee
+        let swap_fee_for_math = if protocol_fee_rate.is_zero() {
+            effective_lp_fee
         } else {
-            (self.slot0.protocol_fee >> 16) & 0xFF
+            protocol_fee.calculate_swap_fee(zero_for_one, effective_lp_fee)
         };
 
-        // The swap_fee for SwapMath should be the effective LP fee.
-        // Protocol fees are a portion of the fees collected based on this effective_lp_fee.
-        let swap_fee_for_math = effective_lp_fee;
-
         // Check for extreme swap fee
         if swap_fee_for_math >= SwapMath::MAX_SWAP_FEE && amount_specified > 0 {
             return Err(StateError::InvalidFeeForExactOut);
         }
 
-        // Empty swap check
-        if amount_specified == 0 {
-            return Ok((BalanceDelta::default(), 0));
-        }
-
-        // Initialize swap state
+        // Initialize swap state; an empty swap leaves the loop at once
         let mut amount_specified_remaining = amount_specified;
         let mut amount_calculated = 0i128;
         let mut sqrt_price_x96 = self.slot0.sqrt_price_x96;
@@ -374,20 +834,47 @@ impl Pool {
             self.fee_growth_global_1_x128
         };
         let mut amount_to_protocol = 0u128;
+        let mut amount_to_lps = 0u128;
+        // Ticks crossed with the fee growth at the crossing, flipped when the
+        // swap is committed
+        let mut crossed = CrossedTicks::new();
+        let mut steps = 0u32;
+        let mut partial = false;
+
+        // Steps never move the price past the limit, but the loop stops on
+        // reaching it rather than on hitting it exactly. The limits are
+        // within the price bounds, so a swap draining the pool stops at the
+        // bound instead of crossing the last tick
+        let reached_limit = |sqrt_price_x96: SqrtPrice| if zero_for_one {
+            sqrt_price_x96 <= sqrt_price_limit_x96
+        } else {
+            sqrt_price_x96 >= sqrt_price_limit_x96
+        };
 
         // Swap loop - continue swapping as long as there's amount remaining and price limit not reached
-        while amount_specified_remaining != 0 && sqrt_price_x96.to_u256() != sqrt_price_limit_x96.to_u256() {
+        while amount_specified_remaining != 0 && !reached_limit(sqrt_price_x96) {
+            if let Some(max_steps) = config.max_steps.filter(|max_steps| steps >= *max_steps) {
+                match config.on_limit {
+                    OnStepLimit::Error => return Err(StateError::SwapStepLimit(max_steps)),
+                    OnStepLimit::Partial => {
+                        partial = true;
+                        break;
+                    }
+                }
+            }
+            steps += 1;
             let sqrt_price_start_x96 = sqrt_price_x96;
             
-            // Find next initialized tick
+            // Find next initialized tick, or the edge of the current bitmap word
             let (tick_next, initialized) = self.tick_manager.next_initialized_tick_within_one_word(
                 tick,
                 tick_spacing,
                 zero_for_one,
             ).map_err(|_| StateError::InvalidPrice)?;
+            let tick_next = tick_next.clamp(TickMath::MIN_TICK, TickMath::MAX_TICK);
 
             // Get sqrt price for next tick
-            let sqrt_price_next_x96_u256 = TickMath::get_sqrt_price_at_tick(tick_next)
+            let sqrt_price_next_x96_u256 = self.sqrt_price_cache.get_sqrt_price_at_tick(tick_next)
                 .map_err(|_| StateError::InvalidPrice)?;
             let sqrt_price_next_x96 = SqrtPrice::new(sqrt_price_next_x96_u256);
 
@@ -398,67 +885,71 @@ impl Pool {
                 sqrt_price_limit_x96,
             );
 
-            let (sqrt_price_next_computed_x96, amount_in, amount_out, mut fee_amount) = SwapMath::compute_swap_step(
-                sqrt_price_x96,
-                sqrt_price_target_x96,
-                liquidity,
-                amount_specified_remaining,
-                swap_fee_for_math,
-            ).map_err(|_| StateError::InvalidPrice)?;
+            // An empty range is crossed without consuming any input, moving the
+            // price straight to the next tick or the limit
+            let (sqrt_price_next_computed_x96, amount_in, amount_out, mut fee_amount) = if liquidity.is_zero() {
+                (sqrt_price_target_x96, U256::zero(), U256::zero(), U256::zero())
+            } else {
+                SwapMath::compute_swap_step(
+                    sqrt_price_x96,
+                    sqrt_price_target_x96,
+                    liquidity,
+                    amount_specified_remaining,
+                    swap_fee_for_math,
+                ).map_err(|_| StateError::InvalidPrice)?
+            };
 
             // Update running values
             sqrt_price_x96 = sqrt_price_next_computed_x96;
 
-            // Update amounts based on direction
+            // Update amounts based on direction. A step never moves more than
+            // the remaining i128 amount, so a step that does not fit in i128
+            // is an error rather than being truncated into the deltas
+            let step_in = (amount_in + fee_amount).try_as_i128().map_err(|_| StateError::AmountOverflow)?;
+            let step_out = amount_out.try_as_i128().map_err(|_| StateError::AmountOverflow)?;
             if amount_specified > 0 {
                 // exactOutput
-                amount_specified_remaining -= amount_out.as_i128();
-                amount_calculated -= (amount_in + fee_amount).as_i128();
+                amount_specified_remaining -= step_out;
+                amount_calculated = amount_calculated.checked_sub(step_in).ok_or(StateError::AmountOverflow)?;
             } else {
                 // exactInput
-                amount_specified_remaining += (amount_in + fee_amount).as_i128();
-                amount_calculated += amount_out.as_i128();
+                amount_specified_remaining += step_in;
+                amount_calculated = amount_calculated.checked_add(step_out).ok_or(StateError::AmountOverflow)?;
             }
 
-            // Calculate protocol fee
-            if protocol_fee_rate > 0 {
+            // Calculate protocol fee. Fees are at most `step_in`, so the
+            // saturating conversions below never clamp
+            if !protocol_fee_rate.is_zero() {
                 let protocol_delta_u128 = if swap_fee_for_math == protocol_fee_rate {
-                    fee_amount.as_u128() // All fees go to protocol
+                    fee_amount.saturating_as_u128() // All fees go to protocol
                 } else {
-                    let protocol_fee_u256 = U256::from(protocol_fee_rate);
-                    let amount_in_plus_fee = amount_in + fee_amount;
-                    (amount_in_plus_fee * protocol_fee_u256 / U256::from(1_000_000u128)).as_u128()
+                    protocol_fee_rate.of(amount_in + fee_amount).saturating_as_u128()
                 };
                 
                 fee_amount = fee_amount - U256::from(protocol_delta_u128);
                 amount_to_protocol += protocol_delta_u128;
             }
 
+            amount_to_lps = amount_to_lps.saturating_add(fee_amount.saturating_as_u128());
+
             // Update fee growth tracker
             if !liquidity.is_zero() {
-                fee_growth_global_x128 = fee_growth_global_x128.saturating_add(
-                    U256::from(fee_amount.as_u128()) * (U256::from(1) << 128) / U256::from(liquidity.as_u128())
-                );
+                fee_growth_global_x128 = fee_growth_global_x128
+                    .overflowing_add(fee_amount * (U256::from(1) << 128) / U256::from(liquidity.as_u128()))
+                    .0;
             }
 
             // Cross tick if necessary
             if sqrt_price_x96.to_u256() == sqrt_price_next_x96_u256 {
                 if initialized {
-                    // Handle tick crossing
-                    let (_fee_growth_global_0_x128, _fee_growth_global_1_x128) = if zero_for_one {
-                        (
-                            fee_growth_global_x128,
-                            self.fee_growth_global_1_x128,
-                        )
-                    } else {
-                        (
-                            self.fee_growth_global_0_x128,
-                            fee_growth_global_x128,
-                        )
-                    };
-
                     // Simulate crossTick function
                     let tick_info = self.tick_manager.get_tick(tick_next).cloned().unwrap_or_default();
+                    on_cross(TickCross {
+                        tick: tick_next,
+                        direction: if zero_for_one { CrossDirection::Down } else { CrossDirection::Up },
+                        liquidity_net: tick_info.liquidity_net,
+                    })?;
+                    crossed.push(tick_next, fee_growth_global_x128);
                     let liquidity_net = if zero_for_one {
                         -tick_info.liquidity_net
                     } else {
@@ -480,18 +971,6 @@ impl Pool {
             }
         }
 
-        // Update state
-        self.slot0.tick = tick;
-        self.slot0.sqrt_price_x96 = sqrt_price_x96;
-        self.liquidity = liquidity;
-
-        // Update fee growth global
-        if zero_for_one {
-            self.fee_growth_global_0_x128 = fee_growth_global_x128;
-        } else {
-            self.fee_growth_global_1_x128 = fee_growth_global_x128;
-        }
-
         // Calculate final balance delta
         let balance_delta = if zero_for_one != (amount_specified < 0) {
             BalanceDelta::new(
@@ -505,28 +984,105 @@ impl Pool {
             )
         };
 
-        Ok((balance_delta, amount_to_protocol))
+        Ok(PreparedSwap {
+            zero_for_one,
+            sqrt_price_x96,
+            tick,
+            liquidity,
+            fee_growth_global_x128,
+            crossed,
+            amount_to_lps,
+            report: SwapReport { delta: balance_delta, protocol_fee: amount_to_protocol, steps, partial },
+        })
+    }
+
+    /// Applies a swap run by [`prepare_swap`](Self::prepare_swap) to the
+    /// pool, which must not have changed since
+    pub(crate) fn commit_swap(&mut self, swap: PreparedSwap) -> SwapReport {
+        let zero_for_one = swap.zero_for_one;
+
+        // Only the growth of the input token changes during the swap
+        for (tick, fee_growth_x128) in swap.crossed.iter() {
+            let (fee_growth_global_0_x128, fee_growth_global_1_x128) = if zero_for_one {
+                (fee_growth_x128, self.fee_growth_global_1_x128)
+            } else {
+                (self.fee_growth_global_0_x128, fee_growth_x128)
+            };
+            self.tick_manager.cross_tick(tick, fee_growth_global_0_x128, fee_growth_global_1_x128);
+        }
+        self.slot0.tick = swap.tick;
+        self.slot0.sqrt_price_x96 = swap.sqrt_price_x96;
+        self.liquidity = swap.liquidity;
+        if zero_for_one {
+            self.fee_growth_global_0_x128 = swap.fee_growth_global_x128;
+        } else {
+            self.fee_growth_global_1_x128 = swap.fee_growth_global_x128;
+        }
+
+        let report = swap.report;
+        if !report.delta.is_zero() {
+            self.stats.record_swap(
+                report.delta.amount0,
+                report.delta.amount1,
+                zero_for_one,
+                swap.amount_to_lps,
+                report.protocol_fee,
+            );
+        }
+        report
     }
 
     /// Donates the given amount of currency0 and currency1 to the pool
+    ///
+    /// The donor always pays the full amounts; a donation fee, when set, is
+    /// taken from them before the rest is credited to LPs.
     pub fn donate(&mut self, amount0: u128, amount1: u128) -> Result<BalanceDelta> {
         if self.liquidity.is_zero() {
             return Err(StateError::NoLiquidityToReceiveFees);
         }
+        // The delta is signed, so larger amounts could not be owed by the donor
+        let delta0 = i128::try_from(amount0).map_err(|_| StateError::AmountOverflow)?;
+        let delta1 = i128::try_from(amount1).map_err(|_| StateError::AmountOverflow)?;
+        let delta = BalanceDelta::new(-delta0, -delta1);
+        let (fee0, fee1) = self.donation_fee(amount0, amount1);
+        self.stats.record_protocol_fees(fee0, fee1);
+        let (amount0, amount1) = (amount0 - fee0, amount1 - fee1);
 
         // Update fee growth globals
         if amount0 > 0 {
             let fee_growth_delta = U256::from(amount0) * (U256::from(1) << 128) / U256::from(self.liquidity.as_u128());
-            self.fee_growth_global_0_x128 = self.fee_growth_global_0_x128.saturating_add(fee_growth_delta);
+            self.fee_growth_global_0_x128 = self.fee_growth_global_0_x128.overflowing_add(fee_growth_delta).0;
         }
 
         if amount1 > 0 {
             let fee_growth_delta = U256::from(amount1) * (U256::from(1) << 128) / U256::from(self.liquidity.as_u128());
-            self.fee_growth_global_1_x128 = self.fee_growth_global_1_x128.saturating_add(fee_growth_delta);
+            self.fee_growth_global_1_x128 = self.fee_growth_global_1_x128.overflowing_add(fee_growth_delta).0;
         }
 
         // Return the balance delta (negative because tokens are being donated to the pool)
-        Ok(BalanceDelta::new(-(amount0 as i128), -(amount1 as i128)))
+        Ok(delta)
+    }
+
+    /// Donates fees to a single position, bypassing pro-rata fee growth
+    ///
+    /// The amounts are added to the fees owed by the position and paid out
+    /// with them, so other positions in range receive nothing. The position
+    /// must hold liquidity. Returns the delta owed by the donor, which is
+    /// exactly the amount credited unless a donation fee is set.
+    pub fn donate_to_position(&mut self, key: &PositionKey, amount0: u128, amount1: u128) -> Result<BalanceDelta> {
+        // The delta is signed, so larger amounts could not be owed by the donor
+        let delta0 = i128::try_from(amount0).map_err(|_| StateError::AmountOverflow)?;
+        let delta1 = i128::try_from(amount1).map_err(|_| StateError::AmountOverflow)?;
+
+        let (fee0, fee1) = self.donation_fee(amount0, amount1);
+        let position = self.position_manager
+            .get_mut(key)
+            .filter(|position| !position.is_empty())
+            .ok_or(StateError::LiquidityNotFound)?;
+        position.credit_fees(amount0 - fee0, amount1 - fee1)?;
+        self.stats.record_protocol_fees(fee0, fee1);
+
+        Ok(BalanceDelta::new(-delta0, -delta1))
     }
 
     /// 初始化流动性令牌
@@ -640,6 +1196,86 @@ impl Pool {
     }
 }
 
+/// A swap run by [`Pool::prepare_swap`], with the state it leaves the pool in
+pub(crate) struct PreparedSwap {
+    zero_for_one: bool,
+    sqrt_price_x96: SqrtPrice,
+    tick: i32,
+    liquidity: Liquidity,
+    fee_growth_global_x128: U256,
+    crossed: CrossedTicks,
+    amount_to_lps: u128,
+    report: SwapReport,
+}
+
+impl PreparedSwap {
+    /// Gets the report of the swap
+    pub(crate) fn report(&self) -> &SwapReport {
+        &self.report
+    }
+
+    /// Gets the price the swap ends at
+    pub(crate) fn sqrt_price_x96(&self) -> SqrtPrice {
+        self.sqrt_price_x96
+    }
+}
+
+/// Ticks crossed by a swap with the input token's fee growth at each
+/// crossing, kept on the stack so that swaps crossing up to
+/// [`INLINE`](Self::INLINE) initialized ticks do not allocate
+struct CrossedTicks {
+    inline: [(i32, U256); Self::INLINE],
+    len: usize,
+    spilled: Vec<(i32, U256)>,
+}
+
+impl CrossedTicks {
+    const INLINE: usize = 32;
+
+    fn new() -> Self {
+        Self {
+            inline: [(0, U256::zero()); Self::INLINE],
+            len: 0,
+            spilled: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, tick: i32, fee_growth_x128: U256) {
+        if self.len < Self::INLINE {
+            self.inline[self.len] = (tick, fee_growth_x128);
+            self.len += 1;
+        } else {
+            self.spilled.push((tick, fee_growth_x128));
+        }
+    }
+
+    /// Iterates over the crossings in order
+    fn iter(&self) -> impl Iterator<Item = (i32, U256)> + '_ {
+        self.inline[..self.len].iter().chain(&self.spilled).copied()
+    }
+}
+
+/// Prints the pool's scalar state as plain numbers followed by its ticks and
+/// positions in sorted order
+impl fmt::Debug for Pool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug = f.debug_struct("Pool");
+        debug
+            .field("sqrt_price_x96", &format_args!("{}", self.slot0.sqrt_price_x96.to_u256()))
+            .field("tick", &self.slot0.tick)
+            .field("liquidity", &self.liquidity.as_u128())
+            .field("lp_fee", &self.slot0.lp_fee.get())
+            .field("protocol_fee", &self.slot0.protocol_fee)
+            .field("fee_growth_global", &format_args!("({}, {})", self.fee_growth_global_0_x128, self.fee_growth_global_1_x128))
+            .field("ticks", &self.tick_manager)
+            .field("positions", &self.position_manager);
+        if let Some(token) = &self.liquidity_token {
+            debug.field("liquidity_token", token);
+        }
+        debug.finish()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -647,23 +1283,40 @@ mod tests {
     #[test]
     fn test_pool_initialization() {
         let mut pool = Pool::new();
-        let sqrt_price = SqrtPrice::new(U256::from(2).pow(U256::from(96)));
-        let lp_fee = 3000; // 0.3%
+        let sqrt_price = SqrtPrice::ONE;
+        let lp_fee = FeePips::new(3000); // 0.3%
 
         let tick = pool.initialize(sqrt_price, lp_fee).unwrap();
         assert_eq!(tick, 0);
-        assert_eq!(pool.slot0.lp_fee, 3000);
+        assert_eq!(pool.slot0.lp_fee, FeePips::new(3000));
+    }
+
+    #[test]
+    fn test_price_limit_errors_show_prices() {
+        let mut pool = Pool::new();
+        pool.initialize(SqrtPrice::ONE, FeePips::new(3000)).unwrap();
+        let tick_spacing = TickSpacing::new(60).unwrap();
+
+        let limit = SqrtPrice::from_tick(200).unwrap();
+        let error = pool.swap(-1_000, limit, true, tick_spacing, None).err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            "Price limit already exceeded: current price 1.00000 (tick 0), limit 1.02020 (tick 200)"
+        );
+
+        let error = pool.swap(-1_000, SqrtPrice::MAX, false, tick_spacing, None).err().unwrap();
+        assert_eq!(error.to_string(), "Price limit out of bounds: 3.40257e38");
     }
 
     #[test]
     fn test_modify_position() {
         let mut pool = Pool::new();
-        let sqrt_price = SqrtPrice::new(U256::from(2).pow(U256::from(96)));
-        pool.initialize(sqrt_price, 3000).unwrap();
+        let sqrt_price = SqrtPrice::ONE;
+        pool.initialize(sqrt_price, FeePips::new(3000)).unwrap();
 
         let owner = [0u8; 20];
         let salt = [0u8; 32];
-        let tick_spacing = 60;
+        let tick_spacing = TickSpacing::new(60).unwrap();
 
         // Add liquidity
         let (balance_delta, fee_delta) = pool.modify_position(
@@ -697,15 +1350,48 @@ mod tests {
         assert!(balance_delta.amount1 > 0);
     }
 
+    #[test]
+    fn test_zero_liquidity_delta_collects_fees() {
+        let mut pool = Pool::new();
+        pool.initialize(SqrtPrice::ONE, FeePips::new(3000)).unwrap();
+        let owner = [1u8; 20];
+        let tick_spacing = TickSpacing::new(60).unwrap();
+
+        // Empty positions cannot be poked
+        assert!(matches!(
+            pool.modify_position(owner, -120, 120, 0, tick_spacing, PositionKey::DEFAULT_SALT),
+            Err(StateError::CannotUpdateEmptyPosition)
+        ));
+
+        pool.modify_position(owner, -120, 120, 1_000_000, tick_spacing, PositionKey::DEFAULT_SALT).unwrap();
+        pool.donate(4000, 8000).unwrap();
+        let ticks_before: Vec<_> = pool.tick_manager.ticks()
+            .map(|(tick, info)| (*tick, info.liquidity_gross.as_u128(), info.liquidity_net))
+            .collect();
+
+        let (balance_delta, fees) = pool.modify_position(owner, -120, 120, 0, tick_spacing, PositionKey::DEFAULT_SALT).unwrap();
+        assert_eq!((balance_delta.amount0, balance_delta.amount1), (0, 0));
+        assert_eq!((fees.amount0, fees.amount1), (3999, 7999));
+        assert_eq!(pool.liquidity.as_u128(), 1_000_000);
+        let ticks_after: Vec<_> = pool.tick_manager.ticks()
+            .map(|(tick, info)| (*tick, info.liquidity_gross.as_u128(), info.liquidity_net))
+            .collect();
+        assert_eq!(ticks_before, ticks_after);
+
+        // A second poke has nothing new to collect
+        let (_, fees) = pool.modify_position(owner, -120, 120, 0, tick_spacing, PositionKey::DEFAULT_SALT).unwrap();
+        assert_eq!((fees.amount0, fees.amount1), (0, 0));
+    }
+
     #[test]
     fn test_swap() {
         let mut pool = Pool::new();
-        let sqrt_price = SqrtPrice::new(U256::from(79228162514264337593543950336u128)); // Use a valid sqrt price
-        pool.initialize(sqrt_price, 3000).unwrap(); // 0.3% fee
+        let sqrt_price = SqrtPrice::ONE;
+        pool.initialize(sqrt_price, FeePips::new(3000)).unwrap(); // 0.3% fee
 
         let owner = [0u8; 20];
         let salt = [0u8; 32];
-        let tick_spacing = 60;
+        let tick_spacing = TickSpacing::new(60).unwrap();
 
         // Add liquidity around current price
         pool.modify_position(
@@ -750,15 +1436,247 @@ mod tests {
         println!("Price after swap: {:?}", pool.slot0.sqrt_price_x96);
     }
 
+    fn sqrt_price_at(tick: i32) -> SqrtPrice {
+        SqrtPrice::new(TickMath::get_sqrt_price_at_tick(tick).unwrap())
+    }
+
+    #[test]
+    fn test_swap_traverses_empty_range_to_liquidity_island() {
+        let tick_spacing = TickSpacing::new(60).unwrap();
+        let limit = SqrtPrice::new(TickMath::MAX_SQRT_PRICE - 1);
+
+        // Liquidity only above the current price
+        let mut pool = Pool::new();
+        pool.initialize(SqrtPrice::ONE, FeePips::new(3000)).unwrap();
+        pool.modify_position([1u8; 20], 600, 1200, 1_000_000_000, tick_spacing, [0u8; 32]).unwrap();
+        assert!(pool.liquidity.is_zero());
+
+        // The same island, with the price already at its lower edge
+        let mut at_edge = Pool::new();
+        at_edge.initialize(sqrt_price_at(600), FeePips::new(3000)).unwrap();
+        at_edge.modify_position([1u8; 20], 600, 1200, 1_000_000_000, tick_spacing, [0u8; 32]).unwrap();
+
+        let (delta, _) = pool.swap(-1_000_000, limit, false, tick_spacing, None).unwrap();
+        let (edge_delta, _) = at_edge.swap(-1_000_000, limit, false, tick_spacing, None).unwrap();
+
+        // Crossing the gap consumed no input, so the swap matches starting at the edge
+        assert_eq!(delta.amount1, -1_000_000);
+        assert!(delta.amount0 > 0);
+        assert_eq!((delta.amount0, delta.amount1), (edge_delta.amount0, edge_delta.amount1));
+        assert_eq!(pool.slot0, at_edge.slot0);
+        assert_eq!(pool.liquidity.as_u128(), 1_000_000_000);
+        assert!((600..1200).contains(&pool.slot0.tick));
+    }
+
+    #[test]
+    fn test_swaps_reuse_cached_tick_prices() {
+        let tick_spacing = TickSpacing::new(60).unwrap();
+        let mut pool = Pool::new();
+        pool.initialize(SqrtPrice::ONE, FeePips::new(3000)).unwrap();
+        for width in 1..=10 {
+            pool.modify_position([1u8; 20], -60 * width, 60 * width, 1_000_000, tick_spacing, [0u8; 32]).unwrap();
+        }
+        pool.clear_sqrt_price_cache();
+        let mut uncached = pool.clone();
+
+        let round_trip = |pool: &mut Pool, clear: bool| {
+            let mut deltas = Vec::new();
+            for zero_for_one in [true, false, true, false] {
+                if clear {
+                    pool.clear_sqrt_price_cache();
+                }
+                let limit = if zero_for_one { sqrt_price_at(-540) } else { sqrt_price_at(540) };
+                let (delta, _) = pool.swap(-1_000_000_000, limit, zero_for_one, tick_spacing, None).unwrap();
+                deltas.push((delta.amount0, delta.amount1));
+            }
+            deltas
+        };
+        assert_eq!(round_trip(&mut pool, false), round_trip(&mut uncached, true));
+        assert_eq!(pool, uncached);
+
+        // Later legs cross the ticks the first legs converted
+        let (hits, misses) = pool.sqrt_price_cache().hits_and_misses();
+        assert!(hits > misses, "{hits} hits, {misses} misses");
+    }
+
+    #[test]
+    fn test_swap_crosses_islands_and_stops_at_limit() {
+        let tick_spacing = TickSpacing::new(60).unwrap();
+        let mut pool = Pool::new();
+        pool.initialize(SqrtPrice::ONE, FeePips::ZERO).unwrap();
+        pool.modify_position([1u8; 20], -1200, -600, 1_000_000, tick_spacing, [0u8; 32]).unwrap();
+        pool.modify_position([1u8; 20], -3000, -2400, 1_000_000, tick_spacing, [0u8; 32]).unwrap();
+
+        // More input than both islands can absorb before the limit
+        let limit = sqrt_price_at(-3600);
+        let (delta, _) = pool.swap(-1_000_000_000, limit, true, tick_spacing, None).unwrap();
+
+        // Only the islands' depth is used and the price ends at the limit, in an empty range
+        let depth0 = |lower: i32, upper: i32| SqrtPriceMath::get_amount0_delta(
+            sqrt_price_at(lower),
+            sqrt_price_at(upper),
+            Liquidity::new(1_000_000),
+            true,
+        ).unwrap().as_u128() as i128;
+        assert_eq!(delta.amount0, -(depth0(-1200, -600) + depth0(-3000, -2400)));
+        assert!(delta.amount1 > 0);
+        assert_eq!(pool.slot0.sqrt_price_x96, limit);
+        assert_eq!(pool.slot0.tick, -3600);
+        assert!(pool.liquidity.is_zero());
+    }
+
+    #[test]
+    fn test_swaps_drain_the_pool_to_the_price_bounds() {
+        let min_limit = SqrtPrice::new(TickMath::MIN_SQRT_PRICE + 1);
+        let max_limit = SqrtPrice::new(TickMath::MAX_SQRT_PRICE - 1);
+        for spacing in [1, 60, TickSpacing::MAX.get()] {
+            let tick_spacing = TickSpacing::new(spacing).unwrap();
+            let (lower, upper) = (tick_spacing.min_usable_tick(), tick_spacing.max_usable_tick());
+            let mut pool = Pool::new();
+            pool.initialize(SqrtPrice::ONE, FeePips::new(3000)).unwrap();
+            let (deposit, _) = pool.modify_position([1u8; 20], lower, upper, 1_000_000_000, tick_spacing, [0u8; 32]).unwrap();
+            let mut reserves = (-deposit.amount0, -deposit.amount1);
+            // Only a spacing of one makes the bound ticks usable, keeping the
+            // position in range at the bounds
+            let liquidity_at_bounds = if spacing == 1 { 1_000_000_000 } else { 0 };
+
+            for (zero_for_one, amount_specified) in [(true, -i128::MAX), (false, -i128::MAX), (true, i128::MAX), (false, i128::MAX)] {
+                let limit = if zero_for_one { min_limit } else { max_limit };
+                let report = pool
+                    .swap_with_config(amount_specified, limit, zero_for_one, tick_spacing, None, &SwapConfig::UNBOUNDED, &mut |_| Ok(()))
+                    .unwrap();
+                let case = format!("spacing {spacing}, zero_for_one {zero_for_one}, amount {amount_specified}");
+
+                // The swap stops at the bound with the pool's output side drained
+                assert_eq!(pool.slot0.sqrt_price_x96, limit, "{case}");
+                let expected_tick = if zero_for_one { TickMath::MIN_TICK } else { TickMath::MAX_TICK - 1 };
+                assert_eq!(pool.slot0.tick, expected_tick, "{case}");
+                assert_eq!(TickMath::get_tick_at_sqrt_price(limit.to_u256()).unwrap(), expected_tick, "{case}");
+                assert_eq!(pool.liquidity.as_u128(), liquidity_at_bounds, "{case}");
+                let (out, reserve) = if zero_for_one {
+                    (report.delta.amount1, reserves.1)
+                } else {
+                    (report.delta.amount0, reserves.0)
+                };
+                assert!(out > 0 && out <= reserve, "{case}: {out} of {reserve}");
+                reserves = (reserves.0 - report.delta.amount0, reserves.1 - report.delta.amount1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_swap_back_from_the_price_bound() {
+        let tick_spacing = TickSpacing::new(60).unwrap();
+        let mut pool = Pool::new();
+        pool.initialize(SqrtPrice::ONE, FeePips::new(3000)).unwrap();
+        pool.modify_position([1u8; 20], -600, 600, 1_000_000_000, tick_spacing, [0u8; 32]).unwrap();
+
+        let min_limit = SqrtPrice::new(TickMath::MIN_SQRT_PRICE + 1);
+        pool.swap(-i128::MAX, min_limit, true, tick_spacing, None).unwrap();
+        assert_eq!((pool.slot0.tick, pool.liquidity.as_u128()), (TickMath::MIN_TICK, 0));
+        // Already at the bound, so the limit can't be passed
+        assert!(matches!(
+            pool.swap(-1, min_limit, true, tick_spacing, None),
+            Err(StateError::PriceLimitAlreadyExceeded(..))
+        ));
+
+        // Crossing the empty range back costs nothing and reenters the position
+        let (delta, _) = pool.swap(-1_000, sqrt_price_at(0), false, tick_spacing, None).unwrap();
+        assert_eq!(delta.amount1, -1_000);
+        assert_eq!(pool.liquidity.as_u128(), 1_000_000_000);
+        assert!((-600..-540).contains(&pool.slot0.tick), "{}", pool.slot0.tick);
+    }
+
+    #[test]
+    fn test_swap_without_liquidity_moves_price_only() {
+        let tick_spacing = TickSpacing::new(60).unwrap();
+        let mut pool = Pool::new();
+        pool.initialize(SqrtPrice::ONE, FeePips::new(3000)).unwrap();
+
+        let limit = sqrt_price_at(-6000);
+        let (delta, protocol_fee) = pool.swap(-1000, limit, true, tick_spacing, None).unwrap();
+        assert_eq!((delta.amount0, delta.amount1, protocol_fee), (0, 0, 0));
+        assert_eq!(pool.slot0.sqrt_price_x96, limit);
+        assert_eq!(pool.slot0.tick, -6000);
+    }
+
+    #[test]
+    fn test_swap_charges_protocol_fee_by_direction() {
+        let tick_spacing = TickSpacing::new(60).unwrap();
+        let mut pool = Pool::new();
+        assert!(matches!(pool.set_protocol_fee(0), Err(StateError::PoolNotInitialized)));
+        pool.initialize(SqrtPrice::ONE, FeePips::new(3000)).unwrap();
+        pool.modify_position([1u8; 20], -1200, 1200, 1_000_000_000_000, tick_spacing, [0u8; 32]).unwrap();
+        assert!(matches!(pool.set_protocol_fee(ProtocolFee::new(1001, 0).unwrap().0), Err(StateError::ProtocolFeeTooLarge(1001))));
+        assert!(matches!(pool.set_protocol_fee(1 << 24), Err(StateError::ProtocolFeeTooLarge(_))));
+        pool.set_protocol_fee(ProtocolFee::new(1000, 0).unwrap().0).unwrap();
+
+        // The whole 12 bits of each direction's fee count
+        let (_, protocol_fee) = pool.swap(-1_000_000, sqrt_price_at(-600), true, tick_spacing, None).unwrap();
+        assert_eq!(protocol_fee, 1000);
+        assert_eq!(pool.stats().lp_fees0, 2997);
+        let (_, protocol_fee) = pool.swap(-1_000_000, sqrt_price_at(600), false, tick_spacing, None).unwrap();
+        assert_eq!(protocol_fee, 0);
+        assert_eq!(pool.stats().lp_fees1, 3000);
+    }
+
+    #[test]
+    fn test_asymmetric_lp_fees() {
+        let tick_spacing = TickSpacing::new(60).unwrap();
+        let mut pool = Pool::new();
+        assert!(matches!(pool.set_lp_fees(FeePips::new(500), FeePips::new(500)), Err(StateError::PoolNotInitialized)));
+        pool.initialize(SqrtPrice::ONE, FeePips::new(3000)).unwrap();
+        pool.modify_position([1u8; 20], -1200, 1200, 1_000_000_000_000, tick_spacing, [0u8; 32]).unwrap();
+        assert!(matches!(
+            pool.set_lp_fees(FeePips::new(500), FeePips::new(1_000_001)),
+            Err(StateError::FeeTooLarge(1_000_001))
+        ));
+        pool.set_lp_fees(FeePips::new(500), FeePips::new(10_000)).unwrap();
+        assert_eq!((pool.slot0.lp_fee_for(true), pool.slot0.lp_fee_for(false)), (FeePips::new(500), FeePips::new(10_000)));
+
+        // Each direction pays its own fee
+        pool.swap(-1_000_000, sqrt_price_at(-600), true, tick_spacing, None).unwrap();
+        assert_eq!(pool.stats().lp_fees0, 500);
+        pool.swap(-1_000_000, sqrt_price_at(600), false, tick_spacing, None).unwrap();
+        assert_eq!(pool.stats().lp_fees1, 10_000);
+        // Hook overrides still replace the fee of the swap's direction
+        pool.swap(-1_000_000, sqrt_price_at(600), false, tick_spacing, Some(FeePips::new(100))).unwrap();
+        assert_eq!(pool.stats().lp_fees1, 10_100);
+
+        // Setting a single fee makes the pool symmetric again
+        pool.set_lp_fee(FeePips::new(3000)).unwrap();
+        assert_eq!(pool.slot0.lp_fee_one_for_zero, None);
+        assert_eq!(pool.slot0.lp_fee_for(false), FeePips::new(3000));
+    }
+
+    #[test]
+    fn test_in_range_amounts_use_both_bounds() {
+        let mut pool = Pool::new();
+        pool.initialize(SqrtPrice::ONE, FeePips::new(3000)).unwrap();
+        let tick_spacing = TickSpacing::new(60).unwrap();
+        let liquidity = Liquidity::new(1_000_000_000);
+
+        // Token0 covers the range above the price and token1 the range below,
+        // so an asymmetric range needs different amounts of each
+        let (delta, _) = pool.modify_position([1u8; 20], -600, 120, 1_000_000_000, tick_spacing, [0u8; 32]).unwrap();
+        let lower = SqrtPrice::new(TickMath::get_sqrt_price_at_tick(-600).unwrap());
+        let upper = SqrtPrice::new(TickMath::get_sqrt_price_at_tick(120).unwrap());
+        let amount0 = SqrtPriceMath::get_amount0_delta(SqrtPrice::ONE, upper, liquidity, true).unwrap();
+        let amount1 = SqrtPriceMath::get_amount1_delta(lower, SqrtPrice::ONE, liquidity, true).unwrap();
+        assert_eq!(delta.amount0(), -(amount0.as_u128() as i128));
+        assert_eq!(delta.amount1(), -(amount1.as_u128() as i128));
+        assert!(amount1 > amount0 * 4);
+    }
+
     #[test]
     fn test_donate() {
         let mut pool = Pool::new();
-        let sqrt_price = SqrtPrice::new(U256::from(2).pow(U256::from(96)));
-        pool.initialize(sqrt_price, 3000).unwrap();
+        let sqrt_price = SqrtPrice::ONE;
+        pool.initialize(sqrt_price, FeePips::new(3000)).unwrap();
 
         let owner = [0u8; 20];
         let salt = [0u8; 32];
-        let tick_spacing = 60;
+        let tick_spacing = TickSpacing::new(60).unwrap();
 
         // Add liquidity
         pool.modify_position(
@@ -788,14 +1706,263 @@ mod tests {
         assert!(pool.fee_growth_global_1_x128 > fee_growth_global_1_before);
     }
 
+    /// Runs swaps back and forth across the ticks of two adjacent positions,
+    /// with donations in between, from the given starting fee growth, and
+    /// returns the fees each position collected
+    fn fees_after_rounds(fee_growth_start: U256, rounds: usize) -> Vec<(i128, i128)> {
+        let mut pool = Pool::new();
+        pool.initialize(SqrtPrice::ONE, FeePips::new(3000)).unwrap();
+        pool.fee_growth_global_0_x128 = fee_growth_start;
+        pool.fee_growth_global_1_x128 = fee_growth_start;
+        let tick_spacing = TickSpacing::new(60).unwrap();
+        let ranges = [(-120, 120), (120, 240)];
+        for (tick_lower, tick_upper) in ranges {
+            pool.modify_position([1; 20], tick_lower, tick_upper, 1_000_000_000, tick_spacing, [0; 32]).unwrap();
+        }
+
+        for _ in 0..rounds {
+            pool.swap(10_000_000, SqrtPrice::from_tick(200).unwrap(), false, tick_spacing, None).unwrap();
+            pool.donate(1_000, 2_000).unwrap();
+            pool.swap(10_000_000, SqrtPrice::from_tick(-100).unwrap(), true, tick_spacing, None).unwrap();
+        }
+        ranges
+            .iter()
+            .map(|(tick_lower, tick_upper)| {
+                let (_, fees) = pool.modify_position([1; 20], *tick_lower, *tick_upper, 0, tick_spacing, [0; 32]).unwrap();
+                (fees.amount0, fees.amount1)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_fee_growth_wraps_around() {
+        let rounds = 200;
+        let expected = fees_after_rounds(U256::zero(), rounds);
+        assert!(expected.iter().all(|(amount0, amount1)| *amount0 > 0 && *amount1 > 0));
+
+        // Accumulators that wrap past 2^256 mid-run owe the same fees, as
+        // only their differences matter
+        for start in [U256::MAX - (U256::from(1) << 100), U256::MAX] {
+            assert_eq!(fees_after_rounds(start, rounds), expected);
+        }
+    }
+
     #[test]
     fn test_donate_no_liquidity() {
         let mut pool = Pool::new();
-        let sqrt_price = SqrtPrice::new(U256::from(2).pow(U256::from(96)));
-        pool.initialize(sqrt_price, 3000).unwrap();
+        let sqrt_price = SqrtPrice::ONE;
+        pool.initialize(sqrt_price, FeePips::new(3000)).unwrap();
 
         // Try to donate without liquidity
         let result = pool.donate(1000, 2000);
         assert!(matches!(result, Err(StateError::NoLiquidityToReceiveFees)));
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn test_auxiliary_fees() {
+        let owner = [1u8; 20];
+        let salt = PositionKey::DEFAULT_SALT;
+        let tick_spacing = TickSpacing::new(60).unwrap();
+        let mut plain = Pool::new();
+        plain.initialize(SqrtPrice::ONE, FeePips::new(3000)).unwrap();
+        plain.modify_position(owner, -120, 120, 1_000_000, tick_spacing, salt).unwrap();
+        let mut charged = plain.clone();
+        assert!(matches!(
+            charged.set_auxiliary_fees(AuxiliaryFees { withdrawal: FeePips::new(1_000_001), donation: FeePips::ZERO }),
+            Err(StateError::FeeTooLarge(1_000_001))
+        ));
+        let fees = AuxiliaryFees { withdrawal: FeePips::new(10_000), donation: FeePips::new(100_000) };
+        charged.set_auxiliary_fees(fees).unwrap();
+
+        // The donor pays in full, LPs receive the donation less the fee
+        let delta = charged.donate(1000, 2000).unwrap();
+        assert_eq!((delta.amount0, delta.amount1), (-1000, -2000));
+        plain.donate(900, 1800).unwrap();
+        assert_eq!(charged.fee_growth_global_0_x128, plain.fee_growth_global_0_x128);
+        assert_eq!(charged.fee_growth_global_1_x128, plain.fee_growth_global_1_x128);
+        assert_eq!((charged.stats().protocol_fees0, charged.stats().protocol_fees1), (100, 200));
+
+        // Withdrawals pay the fee on principal only, not on collected fees
+        let (plain_delta, plain_fees) = plain.modify_position(owner, -120, 120, -500_000, tick_spacing, salt).unwrap();
+        let (delta, fees_collected) = charged.modify_position(owner, -120, 120, -500_000, tick_spacing, salt).unwrap();
+        assert_eq!((fees_collected.amount0, fees_collected.amount1), (plain_fees.amount0, plain_fees.amount1));
+        assert_eq!(delta.amount0, plain_delta.amount0 - plain_delta.amount0 / 100);
+        assert_eq!(delta.amount1, plain_delta.amount1 - plain_delta.amount1 / 100);
+        assert_eq!(charged.stats().protocol_fees0, 100 + (plain_delta.amount0 / 100) as u128);
+
+        // Adding liquidity is never charged
+        let (plain_delta, _) = plain.modify_position(owner, -120, 120, 500_000, tick_spacing, salt).unwrap();
+        let (delta, _) = charged.modify_position(owner, -120, 120, 500_000, tick_spacing, salt).unwrap();
+        assert_eq!((delta.amount0, delta.amount1), (plain_delta.amount0, plain_delta.amount1));
+    }
+
+    #[test]
+    fn test_park_and_unpark_positions() {
+        let mut pool = Pool::new();
+        pool.initialize(SqrtPrice::ONE, FeePips::new(3000)).unwrap();
+        let owner = [1u8; 20];
+        let tick_spacing = TickSpacing::new(60).unwrap();
+        let other_salt = [7u8; 32];
+        pool.modify_position(owner, -120, 120, 1_000_000, tick_spacing, PositionKey::DEFAULT_SALT).unwrap();
+        pool.modify_position(owner, -120, 120, 3_000_000, tick_spacing, other_salt).unwrap();
+        pool.donate(4000, 8000).unwrap();
+        let positions_before = pool.position_manager.clone();
+
+        let parked = pool.park_positions();
+        assert_eq!(parked.len(), 2);
+        assert!(pool.position_manager.is_empty());
+        assert_eq!(pool.liquidity.as_u128(), 4_000_000);
+
+        // The parked positions still earn the fees donated meanwhile, and one
+        // reopened meanwhile is merged with its parked counterpart
+        pool.donate(4000, 8000).unwrap();
+        pool.modify_position(owner, -120, 120, 1_000_000, tick_spacing, PositionKey::DEFAULT_SALT).unwrap();
+        pool.unpark_positions(parked).unwrap();
+
+        let other = PositionKey { salt: other_salt, ..PositionKey::default_position(owner, -120, 120) };
+        assert_eq!(pool.position_manager.get(&other), positions_before.get(&other));
+        let merged = pool.position_manager.get(&PositionKey::default_position(owner, -120, 120)).unwrap();
+        assert_eq!(merged.liquidity.as_u128(), 2_000_000);
+        // A quarter of both donations, rounded down once
+        assert_eq!((merged.tokens_owed_0, merged.tokens_owed_1), (1999, 3999));
+
+        // Unparking into a pool without positions puts them back as they were
+        let positions_before = pool.position_manager.clone();
+        let parked = pool.park_positions();
+        pool.unpark_positions(parked).unwrap();
+        assert!(pool.position_manager == positions_before);
+    }
+
+    #[test]
+    fn test_merge_positions_keeps_fees_owed() {
+        let mut pool = Pool::new();
+        pool.initialize(SqrtPrice::ONE, FeePips::new(3000)).unwrap();
+        let owner = [1u8; 20];
+        let tick_spacing = TickSpacing::new(60).unwrap();
+        let other_salt = [7u8; 32];
+
+        pool.modify_position(owner, -120, 120, 1_000_000, tick_spacing, PositionKey::DEFAULT_SALT).unwrap();
+        pool.modify_position(owner, -120, 120, 3_000_000, tick_spacing, other_salt).unwrap();
+        pool.donate(4000, 8000).unwrap();
+        let ticks_before: Vec<_> = pool.tick_manager.ticks()
+            .map(|(tick, info)| (*tick, info.liquidity_gross.as_u128(), info.liquidity_net))
+            .collect();
+
+        let from = PositionKey { salt: other_salt, ..PositionKey::default_position(owner, -120, 120) };
+        let into = pool.merge_positions(&from, PositionKey::DEFAULT_SALT).unwrap();
+        assert_eq!(into, PositionKey::default_position(owner, -120, 120));
+
+        assert!(pool.position_manager.get(&from).is_none());
+        let merged = pool.position_manager.get(&into).unwrap();
+        assert_eq!(merged.liquidity.as_u128(), 4_000_000);
+        // Each position's share rounds down separately
+        assert_eq!(merged.tokens_owed_0, 999 + 2999);
+        assert_eq!(merged.tokens_owed_1, 1999 + 5999);
+        assert_eq!(pool.liquidity.as_u128(), 4_000_000);
+        let ticks_after: Vec<_> = pool.tick_manager.ticks()
+            .map(|(tick, info)| (*tick, info.liquidity_gross.as_u128(), info.liquidity_net))
+            .collect();
+        assert_eq!(ticks_before, ticks_after);
+
+        // Fees are not credited twice on the next update
+        let (_, fees) = pool.modify_position(owner, -120, 120, 1, tick_spacing, PositionKey::DEFAULT_SALT).unwrap();
+        assert_eq!((fees.amount0, fees.amount1), (0, 0));
+
+        assert!(matches!(pool.merge_positions(&into, PositionKey::DEFAULT_SALT), Err(StateError::SelfMerge)));
+        assert!(matches!(pool.merge_positions(&from, PositionKey::DEFAULT_SALT), Err(StateError::LiquidityNotFound)));
+
+        // A merge whose fees owed overflow leaves both positions as they were
+        pool.modify_position(owner, -120, 120, 1_000_000, tick_spacing, other_salt).unwrap();
+        pool.position_manager.get_mut(&from).unwrap().tokens_owed_0 = u128::MAX;
+        let positions_before = pool.position_manager.clone();
+        assert!(matches!(
+            pool.merge_positions(&from, PositionKey::DEFAULT_SALT),
+            Err(StateError::LiquidityOverflow)
+        ));
+        assert!(pool.position_manager == positions_before);
+    }
+
+    #[test]
+    fn test_split_position() {
+        let mut pool = Pool::new();
+        pool.initialize(SqrtPrice::ONE, FeePips::new(3000)).unwrap();
+        let owner = [1u8; 20];
+        let tick_spacing = TickSpacing::new(60).unwrap();
+        let key = PositionKey::default_position(owner, -120, 120);
+
+        pool.modify_position(owner, -120, 120, 1_000_000, tick_spacing, key.salt).unwrap();
+        pool.donate(1000, 0).unwrap();
+
+        assert!(matches!(pool.split_position(&key, 120, tick_spacing), Err(StateError::InvalidSplitTick(120))));
+        let (lower, upper) = pool.split_position(&key, 60, tick_spacing).unwrap();
+        assert_eq!((lower.tick_lower, lower.tick_upper), (-120, 60));
+        assert_eq!((upper.tick_lower, upper.tick_upper), (60, 120));
+        assert!(pool.position_manager.get(&key).is_none());
+
+        // Both halves hold the full liquidity; the accrued fees stay on the lower half
+        let lower_position = pool.position_manager.get(&lower).unwrap();
+        let upper_position = pool.position_manager.get(&upper).unwrap();
+        assert_eq!(lower_position.liquidity.as_u128(), 1_000_000);
+        assert_eq!(upper_position.liquidity.as_u128(), 1_000_000);
+        assert_eq!(lower_position.tokens_owed_0, 999);
+        assert_eq!(upper_position.tokens_owed_0, 0);
+
+        // Only the split tick was added, and the pool's liquidity is unchanged
+        assert_eq!(pool.tick_manager.ticks().count(), 3);
+        assert_eq!(pool.tick_manager.get_tick(60).unwrap().liquidity_gross.as_u128(), 2_000_000);
+        assert_eq!(pool.liquidity.as_u128(), 1_000_000);
+
+        // Removing both halves returns no more fees than were accrued
+        let (_, fees_lower) = pool.modify_position(owner, -120, 60, -1_000_000, tick_spacing, key.salt).unwrap();
+        let (_, fees_upper) = pool.modify_position(owner, 60, 120, -1_000_000, tick_spacing, key.salt).unwrap();
+        assert_eq!(fees_lower.amount0 + fees_upper.amount0, 999);
+        assert!(pool.tick_manager.ticks().next().is_none());
+    }
+
+    #[test]
+    fn test_rejected_split_leaves_pool_unchanged() {
+        let mut pool = Pool::new();
+        pool.initialize(SqrtPrice::ONE, FeePips::new(3000)).unwrap();
+        let owner = [1u8; 20];
+        let tick_spacing = TickSpacing::new(60).unwrap();
+        let key = PositionKey::default_position(owner, -120, 120);
+        let liquidity = Pool::tick_spacing_to_max_liquidity_per_tick(tick_spacing) / 2 + 1;
+        pool.modify_position(owner, -120, 120, liquidity as i128, tick_spacing, key.salt).unwrap();
+
+        // Both halves together would put more than the maximum on the split tick
+        let before = pool.clone();
+        assert!(matches!(pool.split_position(&key, 60, tick_spacing), Err(StateError::TickLiquidityOverflow(60))));
+        assert_eq!(pool, before);
+    }
+
+    #[test]
+    fn test_pool_snapshots_compare_by_state() {
+        let tick_spacing = TickSpacing::new(60).unwrap();
+        let alice = [1u8; 20];
+        let bob = [2u8; 20];
+
+        let mut pool = Pool::new();
+        pool.initialize(SqrtPrice::ONE, FeePips::new(3000)).unwrap();
+        let empty = pool.clone();
+        pool.modify_position(alice, -120, 120, 1000, tick_spacing, PositionKey::DEFAULT_SALT).unwrap();
+        pool.modify_position(bob, -60, 60, 2000, tick_spacing, [7u8; 32]).unwrap();
+        assert_ne!(pool, empty);
+
+        // The same positions added in the other order give an equal pool
+        let mut other = empty.clone();
+        other.modify_position(bob, -60, 60, 2000, tick_spacing, [7u8; 32]).unwrap();
+        other.modify_position(alice, -120, 120, 1000, tick_spacing, PositionKey::DEFAULT_SALT).unwrap();
+        assert_eq!(pool, other);
+
+        // Removing the liquidity again restores the snapshot
+        other.modify_position(bob, -60, 60, -2000, tick_spacing, [7u8; 32]).unwrap();
+        other.modify_position(alice, -120, 120, -1000, tick_spacing, PositionKey::DEFAULT_SALT).unwrap();
+        assert_eq!(other, empty);
+
+        let debug = format!("{:?}", pool);
+        assert!(debug.starts_with("Pool { sqrt_price_x96: 79228162514264337593543950336, tick: 0, liquidity: 3000, lp_fee: 3000"));
+        assert!(debug.contains("-120: gross=1000 net=1000 outside=(0, 0)"));
+        assert!(debug.contains("0x0101010101010101010101010101010101010101 [-120, 120): liquidity=1000"));
+        assert!(debug.contains(&format!("[-60, 60) salt=0x{}", "07".repeat(32))));
+    }
+}
@@ -1,11 +1,14 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::RangeInclusive;
+use std::fmt;
 use num_traits::Zero;
 use primitive_types::U256;
-use ethers::types::Address;
+use ethers::{types::Address, utils::keccak256};
+use serde::{Deserialize, Serialize};
 
 use crate::core::math::types::Liquidity;
 use crate::core::math::FixedPoint96;
-use super::{Result, StateError, BalanceDelta};
+use super::{Result, StateError, BalanceDelta, compact::CompactPositions, memory::{hash_map_bytes, MemoryUsage}};
 
 /// Key for identifying a position
 #[derive(Debug, Clone, Hash, Eq, PartialEq)]
@@ -20,8 +23,40 @@ pub struct PositionKey {
     pub salt: [u8; 32],
 }
 
+impl PositionKey {
+    /// Salt of an owner's default position in a range
+    pub const DEFAULT_SALT: [u8; 32] = [0; 32];
+
+    /// Creates a key for the owner's default position in the range, for
+    /// callers that hold a single position per range and need no salt
+    pub fn default_position(owner: [u8; 20], tick_lower: i32, tick_upper: i32) -> Self {
+        Self {
+            owner,
+            tick_lower,
+            tick_upper,
+            salt: Self::DEFAULT_SALT,
+        }
+    }
+
+    /// Whether the range contains `tick`, so the position earns fees there
+    pub fn is_in_range(&self, tick: i32) -> bool {
+        self.tick_lower <= tick && tick < self.tick_upper
+    }
+    /// ID of the position in the v4 `PoolManager`'s storage, as computed by
+    /// its `Position.calculatePositionKey`: the hash of the packed owner,
+    /// 24-bit ticks and salt
+    pub fn storage_id(&self) -> [u8; 32] {
+        let mut preimage = [0u8; 58];
+        preimage[..20].copy_from_slice(&self.owner);
+        preimage[20..23].copy_from_slice(&self.tick_lower.to_be_bytes()[1..]);
+        preimage[23..26].copy_from_slice(&self.tick_upper.to_be_bytes()[1..]);
+        preimage[26..].copy_from_slice(&self.salt);
+        keccak256(preimage)
+    }
+}
+
 /// Represents a liquidity position
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Position {
     /// The amount of liquidity in the position
     pub liquidity: Liquidity,
@@ -54,7 +89,7 @@ impl Position {
         fee_growth_inside_0_x128: U256,
         fee_growth_inside_1_x128: U256,
     ) -> Result<BalanceDelta> {
-        let tokens_owed_0 = if !fee_growth_inside_0_x128.is_zero() && !self.liquidity.is_zero() {
+        let tokens_owed_0 = if !self.liquidity.is_zero() {
             // Calculate accumulated fees in token0
             let fee_delta = fee_growth_inside_0_x128
                 .overflowing_sub(self.fee_growth_inside_0_last_x128)
@@ -70,7 +105,7 @@ impl Position {
             0
         };
 
-        let tokens_owed_1 = if !fee_growth_inside_1_x128.is_zero() && !self.liquidity.is_zero() {
+        let tokens_owed_1 = if !self.liquidity.is_zero() {
             // Calculate accumulated fees in token1
             let fee_delta = fee_growth_inside_1_x128
                 .overflowing_sub(self.fee_growth_inside_1_last_x128)
@@ -136,16 +171,71 @@ impl Position {
         (fees_0, fees_1)
     }
 
+    /// Credits fees directly to the position, outside of fee growth
+    ///
+    /// Both amounts are checked before either is applied, so a failed credit
+    /// leaves the position unchanged.
+    pub fn credit_fees(&mut self, amount0: u128, amount1: u128) -> Result<()> {
+        let tokens_owed_0 = self.tokens_owed_0.checked_add(amount0).ok_or(StateError::LiquidityOverflow)?;
+        let tokens_owed_1 = self.tokens_owed_1.checked_add(amount1).ok_or(StateError::LiquidityOverflow)?;
+        self.tokens_owed_0 = tokens_owed_0;
+        self.tokens_owed_1 = tokens_owed_1;
+        Ok(())
+    }
+
     /// Checks if the position has no liquidity
     pub fn is_empty(&self) -> bool {
         self.liquidity.is_zero()
     }
 }
 
+/// Fee growth of a position as stored and as of now, read without updating it
+///
+/// External reward programs can compute what a position earned between two
+/// snapshots from the inside growth values and the liquidity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeeGrowthSnapshot {
+    /// Liquidity of the position
+    pub liquidity: Liquidity,
+    /// Fee growth inside the range of token0 as of the position's last update
+    pub fee_growth_inside_0_last_x128: U256,
+    /// Fee growth inside the range of token1 as of the position's last update
+    pub fee_growth_inside_1_last_x128: U256,
+    /// Current fee growth inside the range of token0
+    pub fee_growth_inside_0_x128: U256,
+    /// Current fee growth inside the range of token1
+    pub fee_growth_inside_1_x128: U256,
+    /// Fees of token0 already credited to the position
+    pub tokens_owed_0: u128,
+    /// Fees of token1 already credited to the position
+    pub tokens_owed_1: u128,
+}
+
+impl FeeGrowthSnapshot {
+    /// Fees earned since the position's last update, which the next update
+    /// would credit to it
+    pub fn pending_fees(&self) -> (u128, u128) {
+        let earned = |current: U256, last: U256| {
+            FixedPoint96::mul_div(U256::from(self.liquidity.as_u128()), current.overflowing_sub(last).0, U256::from(1) << 128)
+                .as_u128()
+        };
+        (
+            earned(self.fee_growth_inside_0_x128, self.fee_growth_inside_0_last_x128),
+            earned(self.fee_growth_inside_1_x128, self.fee_growth_inside_1_last_x128),
+        )
+    }
+}
+
 /// Manages positions in a pool
+///
+/// Equality compares the positions by key, so it does not depend on the
+/// order in which they were inserted or on `HashMap` iteration order.
+#[derive(Clone, PartialEq, Eq)]
 pub struct PositionManager {
     /// Mapping of position key to position state
     positions: HashMap<PositionKey, Position>,
+    /// Keys of the positions by each of their bounds
+    by_tick: BTreeMap<i32, HashSet<PositionKey>>,
 }
 
 impl PositionManager {
@@ -153,6 +243,35 @@ impl PositionManager {
     pub fn new() -> Self {
         Self {
             positions: HashMap::new(),
+            by_tick: BTreeMap::new(),
+        }
+    }
+
+    /// Iterates over the positions with a bound in `ticks`, once per bound
+    /// in it, in arbitrary order
+    pub fn with_bound_in(&self, ticks: RangeInclusive<i32>) -> impl Iterator<Item = (&PositionKey, &Position)> {
+        self.by_tick
+            .range(ticks)
+            .flat_map(|(_, keys)| keys)
+            .filter_map(|key| self.positions.get_key_value(key))
+    }
+
+    /// Adds a new position's key to the index of its bounds
+    fn index(&mut self, key: &PositionKey) {
+        for tick in [key.tick_lower, key.tick_upper] {
+            self.by_tick.entry(tick).or_default().insert(key.clone());
+        }
+    }
+
+    /// Removes a removed position's key from the index of its bounds
+    fn unindex(&mut self, key: &PositionKey) {
+        for tick in [key.tick_lower, key.tick_upper] {
+            if let Some(keys) = self.by_tick.get_mut(&tick) {
+                keys.remove(key);
+                if keys.is_empty() {
+                    self.by_tick.remove(&tick);
+                }
+            }
         }
     }
 
@@ -161,11 +280,47 @@ impl PositionManager {
         self.positions.get(key)
     }
 
+    /// Iterates over all positions in arbitrary order
+    pub fn iter(&self) -> impl Iterator<Item = (&PositionKey, &Position)> {
+        self.positions.iter()
+    }
+
+    /// Number of positions
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// Whether there are no positions
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// Estimated memory of the positions
+    pub fn memory_usage(&self) -> MemoryUsage {
+        MemoryUsage {
+            positions: self.positions.len(),
+            position_bytes: hash_map_bytes::<PositionKey, Position>(self.positions.capacity()),
+            ..Default::default()
+        }
+    }
+
+    /// Packs the positions into their compact form, e.g. to keep many pools'
+    /// positions that aren't being modified
+    pub fn into_compact(self) -> CompactPositions {
+        self.positions.into_iter().collect()
+    }
+
     /// Gets a mutable reference to a position by its key
     pub fn get_mut(&mut self, key: &PositionKey) -> Option<&mut Position> {
         self.positions.get_mut(key)
     }
 
+    /// Inserts a position as is, replacing any position with the same key
+    pub fn insert(&mut self, key: PositionKey, position: Position) -> Option<Position> {
+        self.index(&key);
+        self.positions.insert(key, position)
+    }
+
     /// Updates a position with the given liquidity delta and returns the fees owed
     pub fn update(
         &mut self,
@@ -177,7 +332,7 @@ impl PositionManager {
         // Create position if it doesn't exist
         if !self.positions.contains_key(&key) && liquidity_delta > 0 {
             let position = Position::new(Liquidity::new(0));
-            self.positions.insert(key.clone(), position);
+            self.insert(key.clone(), position);
         } else if !self.positions.contains_key(&key) {
             return Err(StateError::LiquidityNotFound);
         }
@@ -195,10 +350,128 @@ impl PositionManager {
         // Remove position if it has no liquidity
         if position.is_empty() {
             self.positions.remove(&key);
+            self.unindex(&key);
         }
         
         Ok(fee_delta)
     }
+
+    /// Removes a position after crediting it the fees accrued up to the given
+    /// fee growth inside its range
+    ///
+    /// The returned position carries its liquidity and all fees owed, ready to
+    /// be [`absorb`](Self::absorb)ed by another position.
+    pub fn take_settled(
+        &mut self,
+        key: &PositionKey,
+        fee_growth_inside_0_x128: U256,
+        fee_growth_inside_1_x128: U256,
+    ) -> Result<Position> {
+        let position = self.settled(key, fee_growth_inside_0_x128, fee_growth_inside_1_x128)?;
+        self.remove(key);
+        Ok(position)
+    }
+
+    /// Returns a copy of a position credited the fees accrued up to the given
+    /// fee growth inside its range, leaving the stored position as it is
+    pub fn settled(
+        &self,
+        key: &PositionKey,
+        fee_growth_inside_0_x128: U256,
+        fee_growth_inside_1_x128: U256,
+    ) -> Result<Position> {
+        let mut position = self.positions.get(key).cloned().ok_or(StateError::LiquidityNotFound)?;
+        position.update(0, fee_growth_inside_0_x128, fee_growth_inside_1_x128)?;
+        Ok(position)
+    }
+
+    /// Removes a position as is, without crediting any fees
+    pub fn remove(&mut self, key: &PositionKey) -> Option<Position> {
+        let position = self.positions.remove(key)?;
+        self.unindex(key);
+        Some(position)
+    }
+
+    /// Adds the liquidity and fees owed of `position` to the position at `key`
+    ///
+    /// The target first accrues its own fees up to the given fee growth, so
+    /// both positions are settled at the same snapshot, which the merged
+    /// position keeps. The target is created if it does not exist, and is
+    /// left as it was if any sum overflows.
+    pub fn absorb(
+        &mut self,
+        key: PositionKey,
+        position: Position,
+        fee_growth_inside_0_x128: U256,
+        fee_growth_inside_1_x128: U256,
+    ) -> Result<()> {
+        let mut target = self.positions.get(&key).cloned().unwrap_or_default();
+        target.update(0, fee_growth_inside_0_x128, fee_growth_inside_1_x128)?;
+
+        let liquidity = target.liquidity.as_u128()
+            .checked_add(position.liquidity.as_u128())
+            .ok_or(StateError::LiquidityOverflow)?;
+        target.liquidity = Liquidity::new(liquidity);
+        target.tokens_owed_0 = target.tokens_owed_0
+            .checked_add(position.tokens_owed_0)
+            .ok_or(StateError::LiquidityOverflow)?;
+        target.tokens_owed_1 = target.tokens_owed_1
+            .checked_add(position.tokens_owed_1)
+            .ok_or(StateError::LiquidityOverflow)?;
+
+        if !self.positions.contains_key(&key) {
+            self.index(&key);
+        }
+        self.positions.insert(key, target);
+        Ok(())
+    }
+}
+
+/// Lists the positions sorted by key, so the output is stable across runs
+impl fmt::Debug for PositionManager {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut positions: Vec<_> = self.positions.iter().collect();
+        positions.sort_by_key(|(key, _)| (key.owner, key.tick_lower, key.tick_upper, key.salt));
+        f.debug_map()
+            .entries(positions.into_iter().map(|(key, position)| (PositionKeySummary(key), PositionSummary(position))))
+            .finish()
+    }
+}
+
+/// One-line rendering of a position key for `PositionManager`'s `Debug`,
+/// leaving out the default salt
+struct PositionKeySummary<'a>(&'a PositionKey);
+
+impl fmt::Debug for PositionKeySummary<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let key = self.0;
+        write!(f, "{:?} [{}, {})", Address::from(key.owner), key.tick_lower, key.tick_upper)?;
+        if key.salt != PositionKey::DEFAULT_SALT {
+            write!(f, " salt=0x")?;
+            for byte in key.salt {
+                write!(f, "{:02x}", byte)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// One-line rendering of a position for `PositionManager`'s `Debug`
+struct PositionSummary<'a>(&'a Position);
+
+impl fmt::Debug for PositionSummary<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let position = self.0;
+        write!(
+            f,
+            "liquidity={} inside_last=({}, {}) owed=({}, {})",
+            position.liquidity.as_u128(),
+            position.fee_growth_inside_0_last_x128,
+            position.fee_growth_inside_1_last_x128,
+            position.tokens_owed_0,
+            position.tokens_owed_1,
+        )
+    }
 }
 
 #[cfg(test)]
@@ -236,6 +509,30 @@ mod tests {
         assert_eq!(position.liquidity.as_u128(), 100);
     }
 
+    #[test]
+    fn test_positions_by_bound() {
+        let mut manager = PositionManager::new();
+        let key = create_test_key();
+        let upper = PositionKey { tick_lower: 100, tick_upper: 200, ..create_test_key() };
+        manager.update(key.clone(), 100, U256::zero(), U256::zero()).unwrap();
+        manager.update(upper.clone(), 100, U256::zero(), U256::zero()).unwrap();
+
+        let bounded = |manager: &PositionManager, ticks| {
+            let mut keys: Vec<_> = manager.with_bound_in(ticks).map(|(key, _)| key.tick_lower).collect();
+            keys.sort();
+            keys
+        };
+        assert_eq!(bounded(&manager, -50..=50), Vec::<i32>::new());
+        assert_eq!(bounded(&manager, 0..=100), vec![-100, 100]);
+        assert_eq!(bounded(&manager, 101..=300), vec![100]);
+
+        // Removed positions leave the index
+        manager.update(key.clone(), -100, U256::zero(), U256::zero()).unwrap();
+        assert_eq!(bounded(&manager, -300..=300), vec![100, 100]);
+        manager.take_settled(&upper, U256::zero(), U256::zero()).unwrap();
+        assert_eq!(bounded(&manager, -300..=300), Vec::<i32>::new());
+    }
+
     #[test]
     fn test_position_update_existing() {
         let mut manager = PositionManager::new();
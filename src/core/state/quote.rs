@@ -0,0 +1,177 @@
+use primitive_types::U256;
+
+use crate::core::math::{
+    TickMath,
+    FeePips,
+    types::{SqrtPrice, TickSpacing},
+};
+
+use super::{Pool, Result, StateError, BalanceDelta, SwapConfig};
+
+impl Pool {
+    /// Simulates a swap without changing the pool, returning the delta and
+    /// the price the swap would end at
+    pub fn quote_swap(
+        &self,
+        amount_specified: i128,
+        sqrt_price_limit_x96: SqrtPrice,
+        zero_for_one: bool,
+        tick_spacing: TickSpacing,
+        lp_fee_override: Option<FeePips>,
+    ) -> Result<(BalanceDelta, SqrtPrice)> {
+        self.clone().preview_swap(amount_specified, sqrt_price_limit_x96, zero_for_one, tick_spacing, lp_fee_override)
+    }
+
+    /// Simulates a swap like [`quote_swap`](Self::quote_swap) on a pool the
+    /// caller owns, so repeated quotes need no copy of it
+    ///
+    /// Only the sqrt price cache is filled in, so every quote runs against
+    /// the same state.
+    pub(crate) fn preview_swap(
+        &mut self,
+        amount_specified: i128,
+        sqrt_price_limit_x96: SqrtPrice,
+        zero_for_one: bool,
+        tick_spacing: TickSpacing,
+        lp_fee_override: Option<FeePips>,
+    ) -> Result<(BalanceDelta, SqrtPrice)> {
+        let swap = self.prepare_swap(
+            amount_specified,
+            sqrt_price_limit_x96,
+            zero_for_one,
+            tick_spacing,
+            lp_fee_override,
+            &SwapConfig::UNBOUNDED,
+            &mut |_| Ok(()),
+        )?;
+        Ok((swap.report().delta, swap.sqrt_price_x96()))
+    }
+
+    /// Derives the furthest price limit for an exact-output swap of a positive
+    /// `amount_out` that can never spend more than `max_in`
+    ///
+    /// The limit is found by bisecting over quotes of the input needed to move
+    /// the price to a candidate limit. With the returned limit the swap
+    /// delivers the full output at the current state, and if the price moves
+    /// against the trader first it stops early rather than exceed `max_in`,
+    /// as long as the liquidity is unchanged. Fails with `MaxInputExceeded`
+    /// when the full output already costs more than `max_in`.
+    pub fn derive_price_limit_for_max_in(
+        &self,
+        zero_for_one: bool,
+        amount_out: i128,
+        max_in: u128,
+        tick_spacing: TickSpacing,
+    ) -> Result<SqrtPrice> {
+        let extreme = SqrtPrice::new(if zero_for_one {
+            TickMath::MIN_SQRT_PRICE + 1
+        } else {
+            TickMath::MAX_SQRT_PRICE - 1
+        });
+        let input_to = |limit: SqrtPrice, amount: i128| -> Result<(u128, SqrtPrice)> {
+            let (delta, sqrt_price) = self.quote_swap(amount, limit, zero_for_one, tick_spacing, None)?;
+            let input = if zero_for_one { delta.amount0() } else { delta.amount1() };
+            Ok((input.unsigned_abs(), sqrt_price))
+        };
+
+        let (required, end_price) = input_to(extreme, amount_out)?;
+        if required > max_in {
+            return Err(StateError::MaxInputExceeded { required, max: max_in });
+        }
+
+        if input_to(extreme, i128::MAX)?.0 <= max_in {
+            return Ok(extreme);
+        }
+
+        // Moving the price to `acceptable` costs at most `max_in`, moving it to
+        // `too_far` costs more
+        let mut acceptable = end_price.to_u256();
+        let mut too_far = extreme.to_u256();
+        while abs_diff(acceptable, too_far) > U256::one() {
+            let candidate = (acceptable + too_far) / 2;
+            if input_to(SqrtPrice::new(candidate), i128::MAX)?.0 <= max_in {
+                acceptable = candidate;
+            } else {
+                too_far = candidate;
+            }
+        }
+        Ok(SqrtPrice::new(acceptable))
+    }
+}
+
+fn abs_diff(a: U256, b: U256) -> U256 {
+    if a > b { a - b } else { b - a }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool_with_liquidity() -> (Pool, TickSpacing) {
+        let tick_spacing = TickSpacing::new(60).unwrap();
+        let mut pool = Pool::new();
+        pool.initialize(SqrtPrice::ONE, FeePips::new(3000)).unwrap();
+        pool.modify_position([1; 20], -600, 600, 1_000_000_000, tick_spacing, [0; 32]).unwrap();
+        pool.modify_position([1; 20], -6000, 6000, 1_000_000, tick_spacing, [0; 32]).unwrap();
+        (pool, tick_spacing)
+    }
+
+    #[test]
+    fn test_quote_swap_leaves_pool_unchanged() {
+        let (pool, tick_spacing) = pool_with_liquidity();
+        let limit = SqrtPrice::new(TickMath::MIN_SQRT_PRICE + 1);
+        let (delta, sqrt_price) = pool.quote_swap(-1000, limit, true, tick_spacing, None).unwrap();
+        assert_eq!(delta.amount0(), -1000);
+        assert!(sqrt_price < pool.slot0.sqrt_price_x96);
+
+        let mut swapped = pool.clone();
+        let (swap_delta, _) = swapped.swap(-1000, limit, true, tick_spacing, None).unwrap();
+        assert_eq!(swap_delta.amount1(), delta.amount1());
+        assert_eq!(swapped.slot0.sqrt_price_x96, sqrt_price);
+    }
+
+    #[test]
+    fn test_derive_price_limit_for_max_in() {
+        let (pool, tick_spacing) = pool_with_liquidity();
+        for zero_for_one in [true, false] {
+            let amount_out = 1_000_000;
+            let (quote, _) = pool.quote_swap(amount_out, SqrtPrice::new(if zero_for_one {
+                TickMath::MIN_SQRT_PRICE + 1
+            } else {
+                TickMath::MAX_SQRT_PRICE - 1
+            }), zero_for_one, tick_spacing, None).unwrap();
+            let required = if zero_for_one { quote.amount0() } else { quote.amount1() }.unsigned_abs();
+            let max_in = required + required / 10;
+
+            // The swap fills completely with the derived limit
+            let limit = pool.derive_price_limit_for_max_in(zero_for_one, amount_out, max_in, tick_spacing).unwrap();
+            let (delta, _) = pool.quote_swap(amount_out, limit, zero_for_one, tick_spacing, None).unwrap();
+            let (input, output) = if zero_for_one {
+                (delta.amount0(), delta.amount1())
+            } else {
+                (delta.amount1(), delta.amount0())
+            };
+            assert_eq!(output, amount_out);
+            assert_eq!(input.unsigned_abs(), required);
+
+            // Even a much larger swap to the limit stays within the budget, and
+            // the limit is the furthest price that does
+            let (to_limit, sqrt_price) = pool.quote_swap(i128::MAX, limit, zero_for_one, tick_spacing, None).unwrap();
+            let spent = if zero_for_one { to_limit.amount0() } else { to_limit.amount1() }.unsigned_abs();
+            assert_eq!(sqrt_price, limit);
+            assert!(spent <= max_in && spent > max_in - 10, "spent {spent} of {max_in}");
+
+            assert!(matches!(
+                pool.derive_price_limit_for_max_in(zero_for_one, amount_out, required - 1, tick_spacing),
+                Err(StateError::MaxInputExceeded { max, .. }) if max == required - 1
+            ));
+        }
+    }
+
+    #[test]
+    fn test_derive_price_limit_with_unlimited_budget() {
+        let (pool, tick_spacing) = pool_with_liquidity();
+        let limit = pool.derive_price_limit_for_max_in(true, 1000, u128::MAX, tick_spacing).unwrap();
+        assert_eq!(limit.to_u256(), TickMath::MIN_SQRT_PRICE + 1);
+    }
+}
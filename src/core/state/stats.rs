@@ -0,0 +1,68 @@
+use serde::{Deserialize, Serialize};
+
+/// Cumulative trading statistics of a pool
+///
+/// Counters saturate instead of overflowing, so long simulations keep
+/// running with pinned totals rather than failing swaps.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoolStats {
+    /// Total token0 paid into and out of the pool by swaps
+    pub volume0: u128,
+    /// Total token1 paid into and out of the pool by swaps
+    pub volume1: u128,
+    /// Number of swaps that moved tokens
+    pub swap_count: u64,
+    /// LP fees charged on token0 input
+    pub lp_fees0: u128,
+    /// LP fees charged on token1 input
+    pub lp_fees1: u128,
+    /// Protocol fees charged in token0, on swap input and any auxiliary fees
+    pub protocol_fees0: u128,
+    /// Protocol fees charged in token1, on swap input and any auxiliary fees
+    pub protocol_fees1: u128,
+    /// Timestamp of the last swap, if it went through a manager with a clock
+    pub last_trade_timestamp: Option<u64>,
+}
+
+impl PoolStats {
+    /// Records a swap with the given pool deltas and fees in the input token
+    pub fn record_swap(&mut self, amount0: i128, amount1: i128, zero_for_one: bool, lp_fee: u128, protocol_fee: u128) {
+        self.volume0 = self.volume0.saturating_add(amount0.unsigned_abs());
+        self.volume1 = self.volume1.saturating_add(amount1.unsigned_abs());
+        self.swap_count = self.swap_count.saturating_add(1);
+        let (lp_fees, protocol_fees) = if zero_for_one {
+            (&mut self.lp_fees0, &mut self.protocol_fees0)
+        } else {
+            (&mut self.lp_fees1, &mut self.protocol_fees1)
+        };
+        *lp_fees = lp_fees.saturating_add(lp_fee);
+        *protocol_fees = protocol_fees.saturating_add(protocol_fee);
+    }
+
+    /// Records protocol fees charged outside of swaps
+    pub fn record_protocol_fees(&mut self, amount0: u128, amount1: u128) {
+        self.protocol_fees0 = self.protocol_fees0.saturating_add(amount0);
+        self.protocol_fees1 = self.protocol_fees1.saturating_add(amount1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_swap() {
+        let mut stats = PoolStats::default();
+        stats.record_swap(-1000, 990, true, 3, 1);
+        stats.record_swap(500, -505, false, 2, 0);
+        assert_eq!((stats.volume0, stats.volume1), (1500, 1495));
+        assert_eq!(stats.swap_count, 2);
+        assert_eq!((stats.lp_fees0, stats.lp_fees1), (3, 2));
+        assert_eq!((stats.protocol_fees0, stats.protocol_fees1), (1, 0));
+
+        // Volume saturates rather than overflowing
+        stats.record_swap(i128::MIN, 0, true, 0, 0);
+        stats.record_swap(i128::MIN, 0, true, 0, 0);
+        assert_eq!(stats.volume0, u128::MAX);
+    }
+}
@@ -1,15 +1,21 @@
 use std::collections::BTreeMap;
+use std::fmt;
 use primitive_types::U256;
+use serde::{Deserialize, Serialize};
 
-use crate::core::math::{TickMath, Result as MathResult};
-use super::{Result, StateError, types::{TickInfo, Slot0}};
+use crate::core::math::{TickSpacing, Result as MathResult};
+use super::{Result, StateError, TickBitmap, types::{TickInfo, Slot0}, memory::{btree_map_bytes, MemoryUsage}};
 
 /// Manages the state and operations of ticks in a pool
+///
+/// Two managers are equal when they hold the same initialized ticks.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct TickManager {
     /// Maps of tick index to tick data
     ticks: BTreeMap<i32, TickInfo>,
-    /// Maps of word indexes to tick bitmap
-    tick_bitmap: BTreeMap<i16, U256>,
+    /// Which ticks are initialized, kept by the pool in step with the
+    /// ticks' liquidity
+    tick_bitmap: TickBitmap,
 }
 
 impl TickManager {
@@ -17,7 +23,7 @@ impl TickManager {
     pub fn new() -> Self {
         Self {
             ticks: BTreeMap::new(),
-            tick_bitmap: BTreeMap::new(),
+            tick_bitmap: TickBitmap::new(),
         }
     }
 
@@ -28,7 +34,7 @@ impl TickManager {
         liquidity_delta: i128,
         fee_growth_global_0_x128: U256,
         fee_growth_global_1_x128: U256,
-        _upper: bool,
+        upper: bool,
         slot0: &Slot0,
     ) -> Result<(bool, u128)> {
         let tick_info = self.ticks.entry(tick).or_default();
@@ -36,7 +42,7 @@ impl TickManager {
 
         let liquidity_gross_after = if liquidity_delta < 0 {
             // If we're decreasing liquidity, check for underflow
-            let decrease = (-liquidity_delta) as u128;
+            let decrease = liquidity_delta.unsigned_abs();
             if liquidity_gross_before < decrease {
                 return Err(StateError::TickLiquidityOverflow(tick));
             }
@@ -49,25 +55,25 @@ impl TickManager {
 
         let flipped = (liquidity_gross_after == 0) != (liquidity_gross_before == 0);
 
-        if flipped {
-            if liquidity_gross_after == 0 {
-                // Remove the tick if it has no liquidity
-                self.ticks.remove(&tick);
-            } else {
-                // Initialize the tick
-                tick_info.liquidity_gross = liquidity_gross_after.into();
-                tick_info.liquidity_net = liquidity_delta;
-                
-                // When the tick is initialized, set the fee growth outside to the current global fee growth
-                if tick <= slot0.tick {
-                    tick_info.fee_growth_outside_0_x128 = fee_growth_global_0_x128;
-                    tick_info.fee_growth_outside_1_x128 = fee_growth_global_1_x128;
-                }
+        // Liquidity is added when crossing a lower tick from left to right and
+        // removed when crossing an upper tick
+        let liquidity_net_delta = if upper { -liquidity_delta } else { liquidity_delta };
+
+        if flipped && liquidity_gross_after != 0 {
+            // Initialize the tick
+            tick_info.liquidity_gross = liquidity_gross_after.into();
+            tick_info.liquidity_net = liquidity_net_delta;
+            
+            // When the tick is initialized, set the fee growth outside to the current global fee growth
+            if tick <= slot0.tick {
+                tick_info.fee_growth_outside_0_x128 = fee_growth_global_0_x128;
+                tick_info.fee_growth_outside_1_x128 = fee_growth_global_1_x128;
             }
         } else {
-            // Update the tick's liquidity
+            // Update the tick's liquidity; a tick flipped to zero keeps its fee growth
+            // until the caller clears it, so positions can still read their fees
             tick_info.liquidity_gross = liquidity_gross_after.into();
-            tick_info.liquidity_net = tick_info.liquidity_net.checked_add(liquidity_delta)
+            tick_info.liquidity_net = tick_info.liquidity_net.checked_add(liquidity_net_delta)
                 .ok_or(StateError::TickLiquidityOverflow(tick))?;
         }
 
@@ -79,58 +85,58 @@ impl TickManager {
         self.ticks.remove(&tick);
     }
 
-    /// Transitions to the next initialized tick
+    /// Sets a tick's state wholesale, as read from a snapshot of the pool;
+    /// ticks without liquidity are cleared
+    pub fn restore_tick(&mut self, tick: i32, info: TickInfo, tick_spacing: TickSpacing) -> Result<()> {
+        let initialized = info.liquidity_gross.as_u128() != 0;
+        if initialized != self.tick_bitmap.is_initialized(tick, tick_spacing) {
+            self.tick_bitmap.flip_tick(tick, tick_spacing)?;
+        }
+        if initialized {
+            self.ticks.insert(tick, info);
+        } else {
+            self.clear_tick(tick);
+        }
+        Ok(())
+    }
+
+    /// Flips a tick in the bitmap, as its liquidity gross becomes or stops
+    /// being nonzero
+    pub fn flip_tick(&mut self, tick: i32, tick_spacing: TickSpacing) -> Result<()> {
+        self.tick_bitmap.flip_tick(tick, tick_spacing)
+    }
+
+    /// Gets the bitmap of initialized ticks
+    pub fn tick_bitmap(&self) -> &TickBitmap {
+        &self.tick_bitmap
+    }
+
+    /// Finds the next initialized tick in the 256-tick word of compressed
+    /// ticks that the search starts in, see
+    /// [`TickBitmap::next_initialized_tick_within_one_word`]
     pub fn next_initialized_tick_within_one_word(
         &self,
         tick: i32,
-        tick_spacing: i32,
+        tick_spacing: TickSpacing,
         lte: bool,
     ) -> MathResult<(i32, bool)> {
-        // Calculate the compressed tick by dividing by tick_spacing
-        // Adjust for negative ticks that don't align with tick_spacing
-        let mut compressed = tick / tick_spacing;
-        if tick < 0 && tick % tick_spacing != 0 {
-            compressed -= 1;
-        }
+        Ok(self.tick_bitmap.next_initialized_tick_within_one_word(tick, tick_spacing, lte))
+    }
 
-        let word_pos = (compressed >> 8) as i16;
-        let minimum_tick = TickMath::MIN_TICK / tick_spacing * tick_spacing;
-        let maximum_tick = TickMath::MAX_TICK / tick_spacing * tick_spacing;
-
-        if lte {
-            let mask = self.tick_bitmap.get(&word_pos).copied().unwrap_or(U256::zero());
-            let current = (compressed % 256) as u8;
-            let masked = mask & ((U256::one() << current as u32) - U256::one());
-
-            if masked != U256::zero() {
-                // Find rightmost set bit
-                let rightmost = masked.trailing_zeros() as i32;
-                let next = (word_pos as i32) * 256 + rightmost;
-                let next_tick = next * tick_spacing;
-                if next_tick <= maximum_tick {
-                    return Ok((next_tick, true));
-                }
-            }
-        } else {
-            let mask = self.tick_bitmap.get(&word_pos).copied().unwrap_or(U256::zero());
-            let current = (compressed % 256) as u8;
-            let masked = mask & !((U256::one() << current as u32) - U256::one());
-
-            if masked != U256::zero() {
-                // Find leftmost set bit
-                let leftmost = 255 - masked.leading_zeros() as i32;
-                let next = (word_pos as i32) * 256 + leftmost;
-                let next_tick = next * tick_spacing;
-                if next_tick >= minimum_tick {
-                    return Ok((next_tick, true));
-                }
-            }
+    /// Flips a tick's fee growth outside to the other side of the current
+    /// tick as the price crosses it
+    pub fn cross_tick(&mut self, tick: i32, fee_growth_global_0_x128: U256, fee_growth_global_1_x128: U256) {
+        if let Some(info) = self.ticks.get_mut(&tick) {
+            info.fee_growth_outside_0_x128 = fee_growth_global_0_x128.overflowing_sub(info.fee_growth_outside_0_x128).0;
+            info.fee_growth_outside_1_x128 = fee_growth_global_1_x128.overflowing_sub(info.fee_growth_outside_1_x128).0;
         }
-
-        Ok((if lte { minimum_tick } else { maximum_tick }, false))
     }
 
     /// Gets the fee growth inside a tick range
+    ///
+    /// Growth values wrap modulo 2^256, so the result is computed with
+    /// wrapping subtraction and is only meaningful as a difference between
+    /// two readings, as in Solidity.
     pub fn get_fee_growth_inside(
         &self,
         tick_lower: i32,
@@ -148,8 +154,8 @@ impl TickManager {
             fee_growth_below_0_x128 = lower.fee_growth_outside_0_x128;
             fee_growth_below_1_x128 = lower.fee_growth_outside_1_x128;
         } else {
-            fee_growth_below_0_x128 = fee_growth_global_0_x128.saturating_sub(lower.fee_growth_outside_0_x128);
-            fee_growth_below_1_x128 = fee_growth_global_1_x128.saturating_sub(lower.fee_growth_outside_1_x128);
+            fee_growth_below_0_x128 = fee_growth_global_0_x128.overflowing_sub(lower.fee_growth_outside_0_x128).0;
+            fee_growth_below_1_x128 = fee_growth_global_1_x128.overflowing_sub(lower.fee_growth_outside_1_x128).0;
         }
 
         let fee_growth_above_0_x128;
@@ -158,13 +164,13 @@ impl TickManager {
             fee_growth_above_0_x128 = _upper.fee_growth_outside_0_x128;
             fee_growth_above_1_x128 = _upper.fee_growth_outside_1_x128;
         } else {
-            fee_growth_above_0_x128 = fee_growth_global_0_x128.saturating_sub(_upper.fee_growth_outside_0_x128);
-            fee_growth_above_1_x128 = fee_growth_global_1_x128.saturating_sub(_upper.fee_growth_outside_1_x128);
+            fee_growth_above_0_x128 = fee_growth_global_0_x128.overflowing_sub(_upper.fee_growth_outside_0_x128).0;
+            fee_growth_above_1_x128 = fee_growth_global_1_x128.overflowing_sub(_upper.fee_growth_outside_1_x128).0;
         }
 
         (
-            fee_growth_global_0_x128.saturating_sub(fee_growth_below_0_x128).saturating_sub(fee_growth_above_0_x128),
-            fee_growth_global_1_x128.saturating_sub(fee_growth_below_1_x128).saturating_sub(fee_growth_above_1_x128),
+            fee_growth_global_0_x128.overflowing_sub(fee_growth_below_0_x128).0.overflowing_sub(fee_growth_above_0_x128).0,
+            fee_growth_global_1_x128.overflowing_sub(fee_growth_below_1_x128).0.overflowing_sub(fee_growth_above_1_x128).0,
         )
     }
 
@@ -172,12 +178,54 @@ impl TickManager {
     pub fn get_tick(&self, tick: i32) -> Option<&TickInfo> {
         self.ticks.get(&tick)
     }
+
+    /// Iterates over the initialized ticks in ascending order
+    pub fn ticks(&self) -> impl Iterator<Item = (&i32, &TickInfo)> {
+        self.ticks.iter()
+    }
+
+    /// Estimated memory of the ticks and the bitmap
+    pub fn memory_usage(&self) -> MemoryUsage {
+        MemoryUsage {
+            ticks: self.ticks.len(),
+            tick_bytes: btree_map_bytes::<i32, TickInfo>(self.ticks.len()),
+            bitmap_words: self.tick_bitmap.len(),
+            bitmap_bytes: btree_map_bytes::<i16, U256>(self.tick_bitmap.len()),
+            ..Default::default()
+        }
+    }
+}
+
+/// Lists the initialized ticks in order; the bitmap is left out since it
+/// mirrors the ticks
+impl fmt::Debug for TickManager {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(self.ticks.iter().map(|(tick, info)| (tick, TickSummary(info))))
+            .finish()
+    }
+}
+
+/// One-line rendering of a tick for `TickManager`'s `Debug`
+struct TickSummary<'a>(&'a TickInfo);
+
+impl fmt::Debug for TickSummary<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "gross={} net={} outside=({}, {})",
+            self.0.liquidity_gross.as_u128(),
+            self.0.liquidity_net,
+            self.0.fee_growth_outside_0_x128,
+            self.0.fee_growth_outside_1_x128,
+        )
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::core::math::SqrtPrice;
+    use crate::core::math::{FeePips, SqrtPrice};
 
     #[test]
     fn test_update_tick() {
@@ -186,7 +234,8 @@ mod tests {
             sqrt_price_x96: SqrtPrice::new(U256::from(1)),
             tick: 0,
             protocol_fee: 0,
-            lp_fee: 0,
+            lp_fee: FeePips::ZERO,
+            lp_fee_one_for_zero: None,
         };
 
         // Test initializing a tick
@@ -233,7 +282,8 @@ mod tests {
             sqrt_price_x96: SqrtPrice::new(U256::from(1)),
             tick: 0,
             protocol_fee: 0,
-            lp_fee: 0,
+            lp_fee: FeePips::ZERO,
+            lp_fee_one_for_zero: None,
         };
 
         // Initialize ticks
@@ -267,4 +317,35 @@ mod tests {
         assert_eq!(fee0, U256::from(40));
         assert_eq!(fee1, U256::from(40));
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn test_next_initialized_tick_within_one_word() {
+        let mut manager = TickManager::new();
+        let slot0 = Slot0 {
+            sqrt_price_x96: SqrtPrice::ONE,
+            tick: 0,
+            protocol_fee: 0,
+            lp_fee: FeePips::ZERO,
+            lp_fee_one_for_zero: None,
+        };
+        let tick_spacing = TickSpacing::new(60).unwrap();
+        for (tick, upper) in [(-120, false), (120, true)] {
+            let (flipped, _) = manager.update_tick(tick, 100, U256::zero(), U256::zero(), upper, &slot0).unwrap();
+            assert!(flipped);
+            manager.flip_tick(tick, tick_spacing).unwrap();
+        }
+
+        // Crossing the upper tick removes the liquidity the lower tick added
+        assert_eq!(manager.get_tick(-120).unwrap().liquidity_net, 100);
+        assert_eq!(manager.get_tick(120).unwrap().liquidity_net, -100);
+
+        // Searching left includes the starting tick and stops at the word start
+        assert_eq!(manager.next_initialized_tick_within_one_word(0, tick_spacing, true).unwrap(), (0, false));
+        assert_eq!(manager.next_initialized_tick_within_one_word(-1, tick_spacing, true).unwrap(), (-120, true));
+        assert_eq!(manager.next_initialized_tick_within_one_word(-120, tick_spacing, true).unwrap(), (-120, true));
+        assert_eq!(manager.next_initialized_tick_within_one_word(-121, tick_spacing, true).unwrap(), (-256 * 60, false));
+        // Searching right excludes the starting tick
+        assert_eq!(manager.next_initialized_tick_within_one_word(0, tick_spacing, false).unwrap(), (120, true));
+        assert_eq!(manager.next_initialized_tick_within_one_word(120, tick_spacing, false).unwrap(), (255 * 60, false));
+    }
+}
@@ -0,0 +1,193 @@
+use std::collections::BTreeMap;
+
+use primitive_types::U256;
+use serde::{Deserialize, Serialize};
+
+use crate::core::math::TickSpacing;
+use super::{Result, StateError};
+
+/// Which ticks of a pool are initialized, packed 256 compressed ticks to a
+/// word like the Solidity `TickBitmap`
+///
+/// A tick is compressed by dividing it by the tick spacing; bit `b` of word
+/// `w` stands for compressed tick `w * 256 + b`. Only nonzero words are
+/// stored, so an empty word and a missing one read the same.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct TickBitmap {
+    words: BTreeMap<i16, U256>,
+}
+
+impl TickBitmap {
+    /// Creates a bitmap with no initialized ticks
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Word and bit of a compressed tick
+    pub fn position(compressed: i32) -> (i16, u8) {
+        ((compressed >> 8) as i16, (compressed & 0xff) as u8)
+    }
+
+    /// Compresses a tick, rounding towards negative infinity so negative
+    /// ticks between multiples of the spacing compress to the multiple below
+    pub fn compress(tick: i32, tick_spacing: TickSpacing) -> i32 {
+        tick.div_euclid(tick_spacing.get())
+    }
+
+    /// Gets a word of the bitmap
+    pub fn word(&self, word_pos: i16) -> U256 {
+        self.words.get(&word_pos).copied().unwrap_or_default()
+    }
+
+    /// Iterates over the nonzero words in ascending order
+    pub fn words(&self) -> impl Iterator<Item = (&i16, &U256)> {
+        self.words.iter()
+    }
+
+    /// Number of nonzero words
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    /// Whether no tick is initialized
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    /// Whether a tick is flagged as initialized
+    pub fn is_initialized(&self, tick: i32, tick_spacing: TickSpacing) -> bool {
+        let (word_pos, bit) = Self::position(Self::compress(tick, tick_spacing));
+        self.word(word_pos).bit(bit.into())
+    }
+
+    /// Flips a tick from uninitialized to initialized or back
+    ///
+    /// Fails with [`StateError::TickMisaligned`] when the tick isn't a
+    /// multiple of the spacing, as only those have a bit.
+    pub fn flip_tick(&mut self, tick: i32, tick_spacing: TickSpacing) -> Result<()> {
+        if tick % tick_spacing.get() != 0 {
+            return Err(StateError::TickMisaligned(tick, tick_spacing.get()));
+        }
+        let (word_pos, bit) = Self::position(tick / tick_spacing.get());
+        let word = self.word(word_pos) ^ (U256::one() << bit);
+        if word.is_zero() {
+            self.words.remove(&word_pos);
+        } else {
+            self.words.insert(word_pos, word);
+        }
+        Ok(())
+    }
+
+    /// Finds the next initialized tick in the word of compressed ticks the
+    /// search starts in
+    ///
+    /// Searching left (`lte`) includes `tick` itself, searching right starts
+    /// after it. When no tick in the word is initialized, the word boundary
+    /// is returned with `false`, so a swap moves at most one word per step.
+    pub fn next_initialized_tick_within_one_word(
+        &self,
+        tick: i32,
+        tick_spacing: TickSpacing,
+        lte: bool,
+    ) -> (i32, bool) {
+        let spacing = tick_spacing.get();
+        let compressed = Self::compress(tick, tick_spacing);
+
+        if lte {
+            let (word_pos, bit) = Self::position(compressed);
+            // All the bits at or to the right of the current one
+            let mask = (U256::one() << bit) - 1 + (U256::one() << bit);
+            let masked = self.word(word_pos) & mask;
+            let initialized = !masked.is_zero();
+            let next_bit = if initialized { 255 - masked.leading_zeros() as i32 } else { 0 };
+            ((compressed - (i32::from(bit) - next_bit)) * spacing, initialized)
+        } else {
+            // Start from the next tick, which may be in the next word
+            let compressed = compressed + 1;
+            let (word_pos, bit) = Self::position(compressed);
+            // All the bits at or to the left of the current one
+            let mask = !((U256::one() << bit) - 1);
+            let masked = self.word(word_pos) & mask;
+            let initialized = !masked.is_zero();
+            let next_bit = if initialized { masked.trailing_zeros() as i32 } else { 255 };
+            ((compressed + (next_bit - i32::from(bit))) * spacing, initialized)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bitmap(ticks: &[i32], tick_spacing: TickSpacing) -> TickBitmap {
+        let mut bitmap = TickBitmap::new();
+        for tick in ticks {
+            bitmap.flip_tick(*tick, tick_spacing).unwrap();
+        }
+        bitmap
+    }
+
+    #[test]
+    fn test_flip_tick() {
+        let spacing = TickSpacing::new(1).unwrap();
+        let mut bitmap = bitmap(&[-230, -259, 255, 256], spacing);
+        assert!(bitmap.is_initialized(-230, spacing));
+        assert!(!bitmap.is_initialized(-231, spacing));
+        assert_eq!(bitmap.word(-1), U256::one() << 26);
+        assert_eq!(bitmap.word(-2), U256::one() << 253);
+        assert_eq!(bitmap.word(0), U256::one() << 255);
+        assert_eq!(bitmap.word(1), U256::one());
+
+        // Flipping a tick back drops a word left empty
+        bitmap.flip_tick(-230, spacing).unwrap();
+        assert!(!bitmap.is_initialized(-230, spacing));
+        assert_eq!(bitmap.len(), 3);
+
+        let spacing = TickSpacing::new(60).unwrap();
+        assert!(matches!(bitmap.flip_tick(61, spacing), Err(StateError::TickMisaligned(61, 60))));
+    }
+
+    #[test]
+    fn test_next_initialized_tick_matches_solidity() {
+        // The ticks of the v4 TickBitmap tests, with a spacing of one
+        let spacing = TickSpacing::new(1).unwrap();
+        let bitmap = bitmap(&[-200, -55, -4, 70, 78, 84, 139, 240, 535], spacing);
+        let next = |tick, lte| bitmap.next_initialized_tick_within_one_word(tick, spacing, lte);
+
+        // Searching right
+        assert_eq!(next(78, false), (84, true));
+        assert_eq!(next(-55, false), (-4, true));
+        assert_eq!(next(77, false), (78, true));
+        assert_eq!(next(-56, false), (-55, true));
+        assert_eq!(next(255, false), (511, false));
+        assert_eq!(next(383, false), (511, false));
+        assert_eq!(next(511, false), (535, true));
+        assert_eq!(next(-257, false), (-200, true));
+
+        // Searching left
+        assert_eq!(next(78, true), (78, true));
+        assert_eq!(next(79, true), (78, true));
+        assert_eq!(next(258, true), (256, false));
+        assert_eq!(next(256, true), (256, false));
+        assert_eq!(next(72, true), (70, true));
+        assert_eq!(next(-257, true), (-512, false));
+        assert_eq!(next(1023, true), (768, false));
+        assert_eq!(next(900, true), (768, false));
+    }
+
+    #[test]
+    fn test_next_initialized_tick_with_spacing() {
+        let spacing = TickSpacing::new(60).unwrap();
+        let bitmap = bitmap(&[-120, 120], spacing);
+        let next = |tick, lte| bitmap.next_initialized_tick_within_one_word(tick, spacing, lte);
+
+        // Ticks between multiples of the spacing search from the multiple below
+        assert_eq!(next(-1, true), (-120, true));
+        assert_eq!(next(-61, true), (-120, true));
+        assert_eq!(next(-121, true), (-256 * 60, false));
+        assert_eq!(next(-61, false), (-60, false));
+        assert_eq!(next(-60, false), (120, true));
+        assert_eq!(next(120, false), (255 * 60, false));
+    }
+}
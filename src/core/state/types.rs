@@ -1,9 +1,12 @@
 use primitive_types::U256;
 use num_traits::Zero;
-use crate::core::math::types::{SqrtPrice, Liquidity};
+use serde::{Deserialize, Serialize};
+use crate::core::math::{types::{SqrtPrice, Liquidity}, tick_math::TickMath, FeePips};
+use crate::fees::ProtocolFee;
 
 /// Slot0 stores the most frequently accessed state of the pool
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
 pub struct Slot0 {
     /// The current price of the pool as a sqrt(token1/token0) Q64.96 value
     pub sqrt_price_x96: SqrtPrice,
@@ -11,12 +14,180 @@ pub struct Slot0 {
     pub tick: i32,
     /// The current protocol fee as a percentage in hundredths of a bip (i.e. 1e-6)
     pub protocol_fee: u32,
-    /// The current LP fee as a percentage in hundredths of a bip (i.e. 1e-6)
-    pub lp_fee: u32,
+    /// The current LP fee, of zero-for-one swaps only when
+    /// `lp_fee_one_for_zero` is set
+    pub lp_fee: FeePips,
+    /// The LP fee of one-for-zero swaps, or `None` when both directions
+    /// pay `lp_fee`
+    #[serde(default)]
+    pub lp_fee_one_for_zero: Option<FeePips>,
+}
+
+impl Slot0 {
+    /// Creates the state of a pool with the same LP fee in both directions
+    pub fn new(sqrt_price_x96: SqrtPrice, tick: i32, protocol_fee: u32, lp_fee: FeePips) -> Self {
+        Self { sqrt_price_x96, tick, protocol_fee, lp_fee, lp_fee_one_for_zero: None }
+    }
+
+    /// Gets the LP fee of swaps in a direction
+    pub fn lp_fee_for(&self, zero_for_one: bool) -> FeePips {
+        match self.lp_fee_one_for_zero {
+            Some(fee) if !zero_for_one => fee,
+            _ => self.lp_fee,
+        }
+    }
+
+    /// Packs the fields into a storage word laid out as on-chain
+    ///
+    /// Fails for fields the on-chain layout can't hold, including LP fees
+    /// that differ by direction.
+    pub fn pack(&self) -> Result<PackedSlot0, Slot0Error> {
+        if self.lp_fee_one_for_zero.is_some_and(|fee| fee != self.lp_fee) {
+            return Err(Slot0Error::AsymmetricLpFees);
+        }
+        self.validate()?;
+        Ok(PackedSlot0::new(self.sqrt_price_x96.to_u256(), self.tick, self.protocol_fee, self.lp_fee.get()))
+    }
+
+    /// Unpacks a storage word laid out as on-chain, checking every field is
+    /// in range; an uninitialized pool is the zero word
+    pub fn unpack(packed: PackedSlot0) -> Result<Self, Slot0Error> {
+        if !(packed.0 >> PackedSlot0::UNUSED_OFFSET).is_zero() {
+            return Err(Slot0Error::UnusedBitsSet(packed.0));
+        }
+        let slot0 = Self {
+            sqrt_price_x96: SqrtPrice::new(packed.sqrt_price_x96()),
+            tick: packed.tick(),
+            protocol_fee: packed.protocol_fee(),
+            lp_fee: FeePips::new(packed.lp_fee()),
+            lp_fee_one_for_zero: None,
+        };
+        slot0.validate()?;
+        Ok(slot0)
+    }
+
+    /// Checks the fields are in the ranges pools keep them in
+    fn validate(&self) -> Result<(), Slot0Error> {
+        let sqrt_price_x96 = self.sqrt_price_x96.to_u256();
+        let initialized = !sqrt_price_x96.is_zero();
+        if initialized && !(TickMath::MIN_SQRT_PRICE..=TickMath::MAX_SQRT_PRICE).contains(&sqrt_price_x96) {
+            return Err(Slot0Error::SqrtPriceOutOfRange(sqrt_price_x96));
+        }
+        if !(TickMath::MIN_TICK..=TickMath::MAX_TICK).contains(&self.tick) || (!initialized && self.tick != 0) {
+            return Err(Slot0Error::TickOutOfRange(self.tick));
+        }
+        ProtocolFee(self.protocol_fee)
+            .validate()
+            .map_err(|_| Slot0Error::ProtocolFeeTooLarge(self.protocol_fee))?;
+        if self.lp_fee > FeePips::MAX {
+            return Err(Slot0Error::LpFeeTooLarge(self.lp_fee.get()));
+        }
+        Ok(())
+    }
+}
+
+/// A [`Slot0`] packed into a storage word as on-chain
+///
+/// From the least significant bit: the sqrt price in 160 bits, the tick in
+/// 24 bits two's complement, the protocol fee in 24 bits and the LP fee in
+/// 24 bits, leaving the top 24 bits unused. The accessors read the raw bits
+/// without checking them; [`Slot0::unpack`] checks them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct PackedSlot0(pub U256);
+
+impl PackedSlot0 {
+    const TICK_OFFSET: usize = 160;
+    const PROTOCOL_FEE_OFFSET: usize = 184;
+    const LP_FEE_OFFSET: usize = 208;
+    const UNUSED_OFFSET: usize = 232;
+    const MASK_24: u32 = 0xFF_FFFF;
+
+    /// Packs raw fields, truncating each to its width
+    pub fn new(sqrt_price_x96: U256, tick: i32, protocol_fee: u32, lp_fee: u32) -> Self {
+        let sqrt_price_x96 = sqrt_price_x96 & ((U256::one() << Self::TICK_OFFSET) - 1);
+        Self(
+            sqrt_price_x96
+                | U256::from(tick as u32 & Self::MASK_24) << Self::TICK_OFFSET
+                | U256::from(protocol_fee & Self::MASK_24) << Self::PROTOCOL_FEE_OFFSET
+                | U256::from(lp_fee & Self::MASK_24) << Self::LP_FEE_OFFSET,
+        )
+    }
+
+    /// Reads a big-endian storage word
+    pub fn from_be_bytes(bytes: [u8; 32]) -> Self {
+        Self(U256::from_big_endian(&bytes))
+    }
+
+    /// Gets the big-endian storage word
+    pub fn to_be_bytes(self) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        self.0.to_big_endian(&mut bytes);
+        bytes
+    }
+
+    /// The sqrt price, the low 160 bits
+    pub fn sqrt_price_x96(&self) -> U256 {
+        self.0 & ((U256::one() << Self::TICK_OFFSET) - 1)
+    }
+
+    /// The tick, sign extended from 24 bits
+    pub fn tick(&self) -> i32 {
+        let bits = self.field(Self::TICK_OFFSET);
+        ((bits << 8) as i32) >> 8
+    }
+
+    /// The packed protocol fees of both directions
+    pub fn protocol_fee(&self) -> u32 {
+        self.field(Self::PROTOCOL_FEE_OFFSET)
+    }
+
+    /// The LP fee in pips
+    pub fn lp_fee(&self) -> u32 {
+        self.field(Self::LP_FEE_OFFSET)
+    }
+
+    fn field(&self, offset: usize) -> u32 {
+        (self.0 >> offset).low_u32() & Self::MASK_24
+    }
+}
+
+/// Error packing or unpacking a [`Slot0`]
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum Slot0Error {
+    #[error("Sqrt price {0} is outside the valid range")]
+    SqrtPriceOutOfRange(U256),
+
+    #[error("Tick {0} is outside the valid range")]
+    TickOutOfRange(i32),
+
+    #[error("Protocol fee {0:#x} is above the maximum")]
+    ProtocolFeeTooLarge(u32),
+
+    #[error("LP fee of {0} pips is above 100%")]
+    LpFeeTooLarge(u32),
+
+    #[error("LP fees differ by direction, which the on-chain layout can't hold")]
+    AsymmetricLpFees,
+
+    #[error("Unused bits of Slot0 word {0:#x} are set")]
+    UnusedBitsSet(U256),
+}
+
+/// Experimental protocol fees charged outside of swaps
+///
+/// Lets simulations model fee policies v4 does not have. Both fees are zero,
+/// and so disabled, by default; charged fees are added to the pool's
+/// protocol fee totals in [`PoolStats`](super::PoolStats).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuxiliaryFees {
+    /// Fee on the principal paid out when liquidity is removed
+    pub withdrawal: FeePips,
+    /// Fee on donated amounts, taken before they are credited to LPs
+    pub donation: FeePips,
 }
 
 /// Info stored for each initialized individual tick
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct TickInfo {
     /// The total position liquidity that references this tick
     pub liquidity_gross: Liquidity,
@@ -77,17 +248,162 @@ impl std::ops::Add for BalanceDelta {
     }
 }
 
-/// Position represents a liquidity position owned by someone in a pool
-#[derive(Debug, Default, Clone)]
-pub struct Position {
-    /// The amount of liquidity owned by this position
-    pub liquidity: Liquidity,
-    /// The fee growth of token0 inside the tick range as of the last mint/burn/poke
-    pub fee_growth_inside_0_last_x128: U256,
-    /// The fee growth of token1 inside the tick range as of the last mint/burn/poke
-    pub fee_growth_inside_1_last_x128: U256,
-    /// The fees owed to the position owner in token0
-    pub tokens_owed_0: u128,
-    /// The fees owed to the position owner in token1
-    pub tokens_owed_1: u128,
-} 
\ No newline at end of file
+/// Direction in which a swap crosses a tick
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrossDirection {
+    /// The price falls, in zero-for-one swaps
+    Down,
+    /// The price rises, in one-for-zero swaps
+    Up,
+}
+
+/// An initialized tick crossed during a swap
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TickCross {
+    pub tick: i32,
+    pub direction: CrossDirection,
+    /// Net liquidity of the tick as stored, added to the active liquidity
+    /// when crossed upwards and subtracted when crossed downwards
+    pub liquidity_net: i128,
+}
+
+/// What a swap does when it reaches its step limit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OnStepLimit {
+    /// Fail with [`StateError::SwapStepLimit`](super::StateError::SwapStepLimit), leaving the pool unchanged
+    #[default]
+    Error,
+    /// Stop early like at a price limit, leaving the rest of the amount unswapped
+    Partial,
+}
+
+/// Bounds on the work of a single swap
+///
+/// Each step of a swap moves the price to the next initialized tick or
+/// bitmap word boundary, so pools with dense ticks, or swaps with a far
+/// price limit through empty ranges, take many steps. A step limit caps the
+/// time one swap can take.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SwapConfig {
+    /// Most steps a swap may take, or `None` for no limit
+    pub max_steps: Option<u32>,
+    /// What to do when a swap would take more steps
+    pub on_limit: OnStepLimit,
+}
+
+impl SwapConfig {
+    /// Swaps without a step limit
+    pub const UNBOUNDED: SwapConfig = SwapConfig { max_steps: None, on_limit: OnStepLimit::Error };
+
+    /// Limits swaps to `max_steps` steps
+    pub fn with_max_steps(max_steps: u32, on_limit: OnStepLimit) -> Self {
+        Self { max_steps: Some(max_steps), on_limit }
+    }
+}
+
+/// Outcome of a swap
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SwapReport {
+    /// Balance changes of the swap
+    pub delta: BalanceDelta,
+    /// Fees taken by the protocol, in the input token
+    pub protocol_fee: u128,
+    /// Steps the swap loop took
+    pub steps: u32,
+    /// Whether the swap stopped at its step limit with amount left to swap
+    pub partial: bool,
+}
+
+/// Salt distinguishing positions of the same owner over the same range
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Salt(pub [u8; 32]);
+
+impl Salt {
+    /// The zero salt, used by an owner's default position in a range
+    pub const ZERO: Salt = Salt([0; 32]);
+
+    /// Gets the raw bytes of the salt
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+impl From<[u8; 32]> for Salt {
+    fn from(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+}
+
+impl From<Salt> for [u8; 32] {
+    fn from(salt: Salt) -> Self {
+        salt.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slot0(tick: i32) -> Slot0 {
+        Slot0 {
+            sqrt_price_x96: SqrtPrice::from_tick(tick).unwrap(),
+            tick,
+            protocol_fee: ProtocolFee::new(1000, 250).unwrap().0,
+            lp_fee: FeePips::new(3000),
+            lp_fee_one_for_zero: None,
+        }
+    }
+
+    #[test]
+    fn test_slot0_packs_to_the_onchain_layout() {
+        let slot0 = slot0(-887_271);
+        let packed = slot0.pack().unwrap();
+        let word = slot0.sqrt_price_x96.to_u256()
+            | (U256::from(-887_271i32 as u32 & 0xFF_FFFF) << 160)
+            | (U256::from(1000 | (250 << 12)) << 184)
+            | (U256::from(3000) << 208);
+        assert_eq!(packed, PackedSlot0(word));
+        assert_eq!(packed.tick(), -887_271);
+        assert_eq!(PackedSlot0::from_be_bytes(packed.to_be_bytes()), packed);
+        assert_eq!(Slot0::unpack(packed).unwrap(), slot0);
+
+        // An uninitialized pool is the zero word
+        let empty = Slot0::unpack(PackedSlot0::default()).unwrap();
+        assert_eq!(empty.pack().unwrap(), PackedSlot0::default());
+    }
+
+    #[test]
+    fn test_slot0_unpack_checks_ranges() {
+        let word = slot0(100).pack().unwrap().0;
+        let with_field = |offset: usize, value: U256| {
+            let mask = ((U256::one() << 24) - 1) << offset;
+            PackedSlot0((word & !mask) | (value << offset))
+        };
+
+        assert!(matches!(
+            Slot0::unpack(PackedSlot0(word | U256::one() << 255)),
+            Err(Slot0Error::UnusedBitsSet(_))
+        ));
+        let price = PackedSlot0((word >> 160 << 160) | U256::from(5));
+        assert_eq!(Slot0::unpack(price), Err(Slot0Error::SqrtPriceOutOfRange(U256::from(5))));
+        assert_eq!(Slot0::unpack(with_field(160, U256::from(887_273))), Err(Slot0Error::TickOutOfRange(887_273)));
+        assert_eq!(Slot0::unpack(with_field(184, U256::from(1001))), Err(Slot0Error::ProtocolFeeTooLarge(1001)));
+        assert_eq!(Slot0::unpack(with_field(208, U256::from(1_000_001))), Err(Slot0Error::LpFeeTooLarge(1_000_001)));
+        // A tick without a price is corrupt too
+        assert_eq!(Slot0::unpack(PackedSlot0(U256::from(7) << 160)), Err(Slot0Error::TickOutOfRange(7)));
+    }
+
+    #[test]
+    fn test_slot0_pack_rejects_what_the_layout_cannot_hold() {
+        let asymmetric = Slot0 { lp_fee_one_for_zero: Some(FeePips::new(500)), ..slot0(0) };
+        assert_eq!(asymmetric.pack(), Err(Slot0Error::AsymmetricLpFees));
+        let same = Slot0 { lp_fee_one_for_zero: Some(FeePips::new(3000)), ..slot0(0) };
+        assert_eq!(Slot0::unpack(same.pack().unwrap()).unwrap(), slot0(0));
+
+        // Out-of-range fields aren't truncated into range
+        let tick = Slot0 { tick: 1 << 24, ..slot0(0) };
+        assert_eq!(tick.pack(), Err(Slot0Error::TickOutOfRange(1 << 24)));
+        let fee = Slot0 { protocol_fee: 1 << 24, ..slot0(0) };
+        assert_eq!(fee.pack(), Err(Slot0Error::ProtocolFeeTooLarge(1 << 24)));
+    }
+}
@@ -0,0 +1,79 @@
+use std::collections::HashMap;
+
+use primitive_types::U256;
+
+use crate::core::math::types::Liquidity;
+use crate::core::pool_manager::PoolId;
+use crate::core::state::{Pool, Position, PositionKey, Slot0, TickInfo};
+
+/// Read-only view of a manager's pool state keyed by pool ID, mirroring the
+/// v4 `StateLibrary`
+///
+/// Getters return copies of the state rather than references into the
+/// pools, so callers don't depend on how pools lay out their fields. Like
+/// the Solidity library, uninitialized ticks and missing positions read as
+/// zero; only a pool that isn't initialized reads as `None`.
+#[derive(Clone, Copy)]
+pub struct StateView<'a> {
+    pools: &'a HashMap<PoolId, Pool>,
+}
+
+impl<'a> StateView<'a> {
+    pub(crate) fn new(pools: &'a HashMap<PoolId, Pool>) -> Self {
+        Self { pools }
+    }
+
+    fn pool(&self, pool_id: &PoolId) -> Option<&'a Pool> {
+        self.pools.get(pool_id)
+    }
+
+    /// Gets the price, tick and fees of a pool
+    pub fn get_slot0(&self, pool_id: &PoolId) -> Option<Slot0> {
+        self.pool(pool_id).map(|pool| pool.slot0.clone())
+    }
+
+    /// Gets the liquidity in range at the pool's current tick
+    pub fn get_liquidity(&self, pool_id: &PoolId) -> Option<Liquidity> {
+        self.pool(pool_id).map(|pool| pool.liquidity)
+    }
+
+    /// Gets the liquidity and fee growth outside of a tick
+    pub fn get_tick_info(&self, pool_id: &PoolId, tick: i32) -> Option<TickInfo> {
+        let pool = self.pool(pool_id)?;
+        Some(pool.tick_manager.get_tick(tick).cloned().unwrap_or_default())
+    }
+
+    /// Gets the liquidity of a tick, as `(liquidity_gross, liquidity_net)`
+    pub fn get_tick_liquidity(&self, pool_id: &PoolId, tick: i32) -> Option<(Liquidity, i128)> {
+        self.get_tick_info(pool_id, tick).map(|info| (info.liquidity_gross, info.liquidity_net))
+    }
+
+    /// Gets the liquidity, fee growth inside as of its last update and fees
+    /// owed of a position
+    pub fn get_position_info(&self, pool_id: &PoolId, position_key: &PositionKey) -> Option<Position> {
+        let pool = self.pool(pool_id)?;
+        Some(pool.position_manager.get(position_key).cloned().unwrap_or_default())
+    }
+
+    /// Gets the global fee growth of the pool per unit of liquidity, as
+    /// `(token0, token1)` Q128.128 values
+    pub fn get_fee_growth_globals(&self, pool_id: &PoolId) -> Option<(U256, U256)> {
+        self.pool(pool_id).map(|pool| (pool.fee_growth_global_0_x128, pool.fee_growth_global_1_x128))
+    }
+
+    /// Gets the fee growth per unit of liquidity inside a tick range as of
+    /// now, as `(token0, token1)` Q128.128 values
+    ///
+    /// Growth wraps modulo 2^256, so only differences between readings are
+    /// meaningful.
+    pub fn get_fee_growth_inside(&self, pool_id: &PoolId, tick_lower: i32, tick_upper: i32) -> Option<(U256, U256)> {
+        let pool = self.pool(pool_id)?;
+        Some(pool.tick_manager.get_fee_growth_inside(
+            tick_lower,
+            tick_upper,
+            pool.slot0.tick,
+            pool.fee_growth_global_0_x128,
+            pool.fee_growth_global_1_x128,
+        ))
+    }
+}
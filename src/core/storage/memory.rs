@@ -0,0 +1,78 @@
+use std::collections::BTreeMap;
+
+use super::{Storage, StorageIter, StorageResult, WriteBatch};
+
+/// Volatile storage in an ordered map, the default backend
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemoryStorage {
+    entries: BTreeMap<Vec<u8>, Vec<u8>>,
+}
+
+impl MemoryStorage {
+    /// Creates empty storage
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of stored entries
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether nothing is stored
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn get(&self, key: &[u8]) -> StorageResult<Option<Vec<u8>>> {
+        Ok(self.entries.get(key).cloned())
+    }
+
+    fn apply(&mut self, batch: WriteBatch) -> StorageResult<()> {
+        for (key, value) in batch.into_writes() {
+            match value {
+                Some(value) => self.entries.insert(key, value),
+                None => self.entries.remove(&key),
+            };
+        }
+        Ok(())
+    }
+
+    fn scan_prefix<'a>(&'a self, prefix: &[u8]) -> StorageIter<'a> {
+        let prefix = prefix.to_vec();
+        Box::new(
+            self.entries
+                .range(prefix.clone()..)
+                .take_while(move |(key, _)| key.starts_with(&prefix))
+                .map(|(key, value)| Ok((key.clone(), value.clone()))),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batches_and_prefix_scan() {
+        let mut storage = MemoryStorage::new();
+        let mut batch = WriteBatch::new();
+        batch.put(b"a/2".to_vec(), b"two".to_vec());
+        batch.put(b"a/1".to_vec(), b"one".to_vec());
+        batch.put(b"b/1".to_vec(), b"other".to_vec());
+        batch.put(b"a/3".to_vec(), b"three".to_vec());
+        batch.delete(b"a/3".to_vec());
+        storage.apply(batch).unwrap();
+
+        let scanned: Vec<_> = storage.scan_prefix(b"a/").map(Result::unwrap).collect();
+        assert_eq!(scanned, vec![
+            (b"a/1".to_vec(), b"one".to_vec()),
+            (b"a/2".to_vec(), b"two".to_vec()),
+        ]);
+        assert_eq!(storage.get(b"b/1").unwrap(), Some(b"other".to_vec()));
+        assert_eq!(storage.get(b"a/3").unwrap(), None);
+        assert_eq!(storage.len(), 3);
+    }
+}
@@ -0,0 +1,100 @@
+//! Pluggable storage for pool state
+//!
+//! [`Storage`] is a minimal ordered key-value interface with atomic batched
+//! writes and prefix iteration. [`PoolStore`] lays pools, positions and
+//! claims out on top of it, so the manager can persist its state, restore it
+//! after a restart, or spill pools it does not need right now to disk.
+//! [`MemoryStorage`] is the default backend; a disk-backed backend using sled
+//! is available behind the `sled-storage` feature.
+
+pub mod memory;
+pub mod store;
+#[cfg(feature = "sled-storage")]
+pub mod sled_storage;
+
+pub use memory::*;
+pub use store::*;
+#[cfg(feature = "sled-storage")]
+pub use sled_storage::*;
+
+use crate::core::state::StateError;
+
+/// Storage errors
+#[derive(Debug, thiserror::Error)]
+pub enum StorageError {
+    #[error("Storage backend error: {0}")]
+    Backend(String),
+
+    #[error("Failed to encode or decode {0}: {1}")]
+    Codec(&'static str, String),
+
+    #[error("Invalid storage key: {0:?}")]
+    InvalidKey(Vec<u8>),
+
+    #[error("State error: {0}")]
+    State(#[from] StateError),
+}
+
+/// Result type for storage operations
+pub type StorageResult<T> = std::result::Result<T, StorageError>;
+
+/// Iterator over stored entries in key order
+pub type StorageIter<'a> = Box<dyn Iterator<Item = StorageResult<(Vec<u8>, Vec<u8>)>> + 'a>;
+
+/// A set of writes applied atomically by [`Storage::apply`]
+///
+/// Writes to the same key apply in the order they were staged, so the last
+/// one wins.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WriteBatch {
+    writes: Vec<(Vec<u8>, Option<Vec<u8>>)>,
+}
+
+impl WriteBatch {
+    /// Creates an empty batch
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stages writing `value` at `key`
+    pub fn put(&mut self, key: impl Into<Vec<u8>>, value: impl Into<Vec<u8>>) {
+        self.writes.push((key.into(), Some(value.into())));
+    }
+
+    /// Stages removing `key`
+    pub fn delete(&mut self, key: impl Into<Vec<u8>>) {
+        self.writes.push((key.into(), None));
+    }
+
+    /// Number of staged writes
+    pub fn len(&self) -> usize {
+        self.writes.len()
+    }
+
+    /// Whether no writes are staged
+    pub fn is_empty(&self) -> bool {
+        self.writes.is_empty()
+    }
+
+    /// Staged writes in order, with `None` for removals
+    pub fn into_writes(self) -> impl Iterator<Item = (Vec<u8>, Option<Vec<u8>>)> {
+        self.writes.into_iter()
+    }
+}
+
+/// Ordered key-value storage backend
+pub trait Storage {
+    /// Gets the value stored at `key`
+    fn get(&self, key: &[u8]) -> StorageResult<Option<Vec<u8>>>;
+
+    /// Applies every write in the batch, or none of them on error
+    fn apply(&mut self, batch: WriteBatch) -> StorageResult<()>;
+
+    /// Iterates over the entries whose key starts with `prefix`, in key order
+    fn scan_prefix<'a>(&'a self, prefix: &[u8]) -> StorageIter<'a>;
+
+    /// Makes applied writes durable; a no-op for volatile backends
+    fn flush(&mut self) -> StorageResult<()> {
+        Ok(())
+    }
+}
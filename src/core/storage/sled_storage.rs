@@ -0,0 +1,83 @@
+use std::path::Path;
+
+use super::{Storage, StorageError, StorageIter, StorageResult, WriteBatch};
+
+/// Disk-backed storage in a sled database
+///
+/// Batches are applied atomically, and state survives process restarts once
+/// flushed. sled also flushes in the background, so an unflushed write may
+/// or may not survive a crash.
+#[derive(Debug, Clone)]
+pub struct SledStorage {
+    db: sled::Db,
+}
+
+impl SledStorage {
+    /// Opens or creates the database at `path`
+    pub fn open(path: impl AsRef<Path>) -> StorageResult<Self> {
+        Ok(Self { db: sled::open(path).map_err(backend)? })
+    }
+
+    /// Opens a database deleted when dropped, for tests and scratch runs
+    pub fn temporary() -> StorageResult<Self> {
+        Ok(Self { db: sled::Config::new().temporary(true).open().map_err(backend)? })
+    }
+}
+
+impl From<sled::Db> for SledStorage {
+    fn from(db: sled::Db) -> Self {
+        Self { db }
+    }
+}
+
+fn backend(error: sled::Error) -> StorageError {
+    StorageError::Backend(error.to_string())
+}
+
+impl Storage for SledStorage {
+    fn get(&self, key: &[u8]) -> StorageResult<Option<Vec<u8>>> {
+        Ok(self.db.get(key).map_err(backend)?.map(|value| value.to_vec()))
+    }
+
+    fn apply(&mut self, batch: WriteBatch) -> StorageResult<()> {
+        let mut sled_batch = sled::Batch::default();
+        for (key, value) in batch.into_writes() {
+            match value {
+                Some(value) => sled_batch.insert(key, value),
+                None => sled_batch.remove(key),
+            }
+        }
+        self.db.apply_batch(sled_batch).map_err(backend)
+    }
+
+    fn scan_prefix<'a>(&'a self, prefix: &[u8]) -> StorageIter<'a> {
+        Box::new(self.db.scan_prefix(prefix).map(|entry| {
+            entry.map(|(key, value)| (key.to_vec(), value.to_vec())).map_err(backend)
+        }))
+    }
+
+    fn flush(&mut self) -> StorageResult<()> {
+        self.db.flush().map(|_| ()).map_err(backend)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sled_batches_and_prefix_scan() {
+        let mut storage = SledStorage::temporary().unwrap();
+        let mut batch = WriteBatch::new();
+        batch.put(b"a/2".to_vec(), b"two".to_vec());
+        batch.put(b"a/1".to_vec(), b"one".to_vec());
+        batch.put(b"b/1".to_vec(), b"other".to_vec());
+        batch.delete(b"a/2".to_vec());
+        storage.apply(batch).unwrap();
+        storage.flush().unwrap();
+
+        let scanned: Vec<_> = storage.scan_prefix(b"a/").map(Result::unwrap).collect();
+        assert_eq!(scanned, vec![(b"a/1".to_vec(), b"one".to_vec())]);
+        assert_eq!(storage.get(b"b/1").unwrap(), Some(b"other".to_vec()));
+    }
+}
@@ -0,0 +1,222 @@
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::core::pool_manager::PoolId;
+use crate::core::state::{Pool, Position, PositionKey};
+use crate::tokens::erc6909::ERC6909;
+use super::{Storage, StorageError, StorageResult, WriteBatch};
+
+/// Prefix of pool records, followed by the 32-byte pool ID
+const POOL_PREFIX: &[u8] = b"pool/";
+
+/// Prefix of position records, followed by the pool ID and encoded position key
+const POSITION_PREFIX: &[u8] = b"position/";
+
+/// Key of the manager's ERC6909 claims
+const CLAIMS_KEY: &[u8] = b"claims";
+
+/// Length of an encoded position key: owner, both ticks and salt
+const POSITION_KEY_LEN: usize = 20 + 4 + 4 + 32;
+
+/// Pools, positions and claims laid out on a [`Storage`] backend
+///
+/// Each position is its own record under its pool's prefix, so positions can
+/// be streamed for exports without decoding the whole pool. Records are
+/// encoded with bincode.
+#[derive(Debug, Clone, Default)]
+pub struct PoolStore<S> {
+    storage: S,
+}
+
+impl<S: Storage> PoolStore<S> {
+    /// Lays pools out on `storage`
+    pub fn new(storage: S) -> Self {
+        Self { storage }
+    }
+
+    /// Gets the backend
+    pub fn storage(&self) -> &S {
+        &self.storage
+    }
+
+    /// Returns the backend
+    pub fn into_inner(self) -> S {
+        self.storage
+    }
+
+    /// Stages writing a pool and all its positions, removing stored
+    /// positions the pool no longer has
+    pub fn write_pool(&self, batch: &mut WriteBatch, pool_id: &PoolId, pool: &Pool) -> StorageResult<()> {
+        for entry in self.storage.scan_prefix(&position_prefix(pool_id)) {
+            let (key, _) = entry?;
+            let position_key = decode_position_key(&key[POSITION_PREFIX.len() + 32..])
+                .ok_or_else(|| StorageError::InvalidKey(key.clone()))?;
+            if pool.position_manager.get(&position_key).is_none() {
+                batch.delete(key);
+            }
+        }
+        batch.put(pool_key(pool_id), encode("pool", pool)?);
+        for (key, position) in pool.position_manager.iter() {
+            batch.put(position_key(pool_id, key), encode("position", position)?);
+        }
+        Ok(())
+    }
+
+    /// Stages removing a pool and its positions
+    pub fn remove_pool(&self, batch: &mut WriteBatch, pool_id: &PoolId) -> StorageResult<()> {
+        for entry in self.storage.scan_prefix(&position_prefix(pool_id)) {
+            batch.delete(entry?.0);
+        }
+        batch.delete(pool_key(pool_id));
+        Ok(())
+    }
+
+    /// Stages writing the manager's claims
+    pub fn write_claims(&self, batch: &mut WriteBatch, claims: &ERC6909) -> StorageResult<()> {
+        batch.put(CLAIMS_KEY, encode("claims", claims)?);
+        Ok(())
+    }
+
+    /// Applies staged writes atomically
+    pub fn apply(&mut self, batch: WriteBatch) -> StorageResult<()> {
+        self.storage.apply(batch)
+    }
+
+    /// Makes applied writes durable
+    pub fn flush(&mut self) -> StorageResult<()> {
+        self.storage.flush()
+    }
+
+    /// Writes a single pool and its positions
+    pub fn save_pool(&mut self, pool_id: &PoolId, pool: &Pool) -> StorageResult<()> {
+        let mut batch = WriteBatch::new();
+        self.write_pool(&mut batch, pool_id, pool)?;
+        self.apply(batch)
+    }
+
+    /// Loads a pool with all its positions
+    pub fn load_pool(&self, pool_id: &PoolId) -> StorageResult<Option<Pool>> {
+        let Some(bytes) = self.storage.get(&pool_key(pool_id))? else {
+            return Ok(None);
+        };
+        let mut pool: Pool = decode("pool", &bytes)?;
+        for entry in self.positions(pool_id) {
+            let (key, position) = entry?;
+            pool.position_manager.insert(key, position);
+        }
+        Ok(Some(pool))
+    }
+
+    /// Loads the manager's claims
+    pub fn load_claims(&self) -> StorageResult<Option<ERC6909>> {
+        self.storage.get(CLAIMS_KEY)?.map(|bytes| decode("claims", &bytes)).transpose()
+    }
+
+    /// Iterates over the IDs of stored pools in order
+    pub fn pool_ids(&self) -> impl Iterator<Item = StorageResult<PoolId>> + '_ {
+        self.storage.scan_prefix(POOL_PREFIX).map(|entry| {
+            let (key, _) = entry?;
+            <[u8; 32]>::try_from(&key[POOL_PREFIX.len()..])
+                .map(PoolId)
+                .map_err(|_| StorageError::InvalidKey(key.clone()))
+        })
+    }
+
+    /// Streams the stored positions of a pool, ordered by owner, ticks and salt
+    pub fn positions(&self, pool_id: &PoolId) -> impl Iterator<Item = StorageResult<(PositionKey, Position)>> + '_ {
+        self.storage.scan_prefix(&position_prefix(pool_id)).map(|entry| {
+            let (key, value) = entry?;
+            let position_key = decode_position_key(&key[POSITION_PREFIX.len() + 32..])
+                .ok_or_else(|| StorageError::InvalidKey(key.clone()))?;
+            Ok((position_key, decode("position", &value)?))
+        })
+    }
+}
+
+fn encode<T: Serialize>(record: &'static str, value: &T) -> StorageResult<Vec<u8>> {
+    bincode::serialize(value).map_err(|e| StorageError::Codec(record, e.to_string()))
+}
+
+fn decode<T: DeserializeOwned>(record: &'static str, bytes: &[u8]) -> StorageResult<T> {
+    bincode::deserialize(bytes).map_err(|e| StorageError::Codec(record, e.to_string()))
+}
+
+fn pool_key(pool_id: &PoolId) -> Vec<u8> {
+    [POOL_PREFIX, pool_id.as_bytes()].concat()
+}
+
+fn position_prefix(pool_id: &PoolId) -> Vec<u8> {
+    [POSITION_PREFIX, pool_id.as_bytes()].concat()
+}
+
+/// Ticks are stored with the sign bit flipped so byte order matches tick order
+fn position_key(pool_id: &PoolId, key: &PositionKey) -> Vec<u8> {
+    let mut encoded = position_prefix(pool_id);
+    encoded.extend_from_slice(&key.owner);
+    encoded.extend_from_slice(&((key.tick_lower as u32) ^ 0x8000_0000).to_be_bytes());
+    encoded.extend_from_slice(&((key.tick_upper as u32) ^ 0x8000_0000).to_be_bytes());
+    encoded.extend_from_slice(&key.salt);
+    encoded
+}
+
+fn decode_position_key(bytes: &[u8]) -> Option<PositionKey> {
+    if bytes.len() != POSITION_KEY_LEN {
+        return None;
+    }
+    let tick = |at: usize| (u32::from_be_bytes(bytes[at..at + 4].try_into().unwrap()) ^ 0x8000_0000) as i32;
+    Some(PositionKey {
+        owner: bytes[..20].try_into().ok()?,
+        tick_lower: tick(20),
+        tick_upper: tick(24),
+        salt: bytes[28..].try_into().ok()?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::math::{FeePips, SqrtPrice, TickSpacing};
+    use crate::core::storage::MemoryStorage;
+
+    #[test]
+    fn test_pool_round_trip() {
+        let mut pool = Pool::new();
+        pool.initialize(SqrtPrice::ONE, FeePips::new(3000)).unwrap();
+        let tick_spacing = TickSpacing::new(60).unwrap();
+        pool.modify_position([1; 20], -120, 120, 1_000_000, tick_spacing, [0; 32]).unwrap();
+        pool.modify_position([2; 20], -600, -60, 2_000_000, tick_spacing, [9; 32]).unwrap();
+        pool.swap(-10_000, SqrtPrice::new(crate::core::math::TickMath::MIN_SQRT_PRICE + 1), true, tick_spacing, None).unwrap();
+
+        let mut store = PoolStore::new(MemoryStorage::new());
+        let id = PoolId([7; 32]);
+        store.save_pool(&id, &pool).unwrap();
+        assert!(store.load_pool(&id).unwrap() == Some(pool.clone()));
+        assert_eq!(store.pool_ids().collect::<StorageResult<Vec<_>>>().unwrap(), vec![id]);
+
+        // Positions stream in key order, with negative ticks first
+        let keys: Vec<_> = store.positions(&id).map(|entry| entry.unwrap().0.tick_lower).collect();
+        assert_eq!(keys, vec![-120, -600]);
+
+        // Positions removed from the pool are removed from storage
+        pool.position_manager = crate::core::state::PositionManager::new();
+        store.save_pool(&id, &pool).unwrap();
+        assert_eq!(store.positions(&id).count(), 0);
+
+        let mut batch = WriteBatch::new();
+        store.remove_pool(&mut batch, &id).unwrap();
+        store.apply(batch).unwrap();
+        assert!(store.load_pool(&id).unwrap().is_none());
+        assert!(store.storage().is_empty());
+    }
+
+    #[test]
+    fn test_position_keys_sort_by_tick() {
+        let id = PoolId::default();
+        let key = |tick_lower| PositionKey { owner: [1; 20], tick_lower, tick_upper: 887_220, salt: [3; 32] };
+        let ticks = [-887_220, -1, 0, 1, 887_220];
+        let encoded: Vec<_> = ticks.iter().map(|tick| position_key(&id, &key(*tick))).collect();
+        assert!(encoded.windows(2).all(|pair| pair[0] < pair[1]));
+        for (tick, bytes) in ticks.iter().zip(&encoded) {
+            assert_eq!(decode_position_key(&bytes[POSITION_PREFIX.len() + 32..]), Some(key(*tick)));
+        }
+    }
+}
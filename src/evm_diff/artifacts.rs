@@ -0,0 +1,110 @@
+use std::path::Path;
+
+use super::{EvmDiffError, Result};
+
+/// Creation bytecode of the contracts deployed by the harness
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Artifacts {
+    /// v4-core `PoolManager`
+    pub pool_manager: Vec<u8>,
+    /// v4-core `PoolModifyLiquidityTest` router
+    pub modify_liquidity_router: Vec<u8>,
+    /// v4-core `PoolSwapTest` router
+    pub swap_router: Vec<u8>,
+    /// solmate `MockERC20`, used for both currencies
+    pub token: Vec<u8>,
+}
+
+impl Artifacts {
+    /// Loads the contracts from a forge `out` directory
+    pub fn from_forge_out(out_dir: impl AsRef<Path>) -> Result<Self> {
+        let out_dir = out_dir.as_ref();
+        let load = |name: &str| load_bytecode(out_dir.join(format!("{name}.sol")).join(format!("{name}.json")));
+        Ok(Self {
+            pool_manager: load("PoolManager")?,
+            modify_liquidity_router: load("PoolModifyLiquidityTest")?,
+            swap_router: load("PoolSwapTest")?,
+            token: load("MockERC20")?,
+        })
+    }
+}
+
+/// Reads creation bytecode from a forge or hardhat JSON artifact, or from a
+/// file holding only the hex-encoded bytecode
+pub fn load_bytecode(path: impl AsRef<Path>) -> Result<Vec<u8>> {
+    let path = path.as_ref();
+    let contents = std::fs::read_to_string(path).map_err(|source| EvmDiffError::Io {
+        path: path.display().to_string(),
+        source,
+    })?;
+    parse_bytecode(&contents).map_err(|reason| EvmDiffError::InvalidArtifact {
+        path: path.display().to_string(),
+        reason,
+    })
+}
+
+/// Parses creation bytecode from the contents of an artifact
+pub fn parse_bytecode(contents: &str) -> std::result::Result<Vec<u8>, String> {
+    let contents = contents.trim();
+    let hex = if contents.starts_with('{') {
+        let artifact: serde_json::Value = serde_json::from_str(contents).map_err(|e| e.to_string())?;
+        // Forge nests the code under `bytecode.object`, hardhat stores it in `bytecode`
+        let bytecode = &artifact["bytecode"];
+        bytecode["object"]
+            .as_str()
+            .or_else(|| bytecode.as_str())
+            .ok_or_else(|| "no bytecode field".to_string())?
+            .to_string()
+    } else {
+        contents.to_string()
+    };
+
+    let hex = hex.strip_prefix("0x").unwrap_or(&hex);
+    if hex.contains("__$") {
+        return Err("bytecode has unlinked libraries".to_string());
+    }
+    let bytes = ethers::utils::hex::decode(hex).map_err(|e| e.to_string())?;
+    if bytes.is_empty() {
+        return Err("bytecode is empty".to_string());
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_forge_artifact() {
+        let artifact = r#"{"abi": [], "bytecode": {"object": "0x6080", "sourceMap": ""}}"#;
+        assert_eq!(parse_bytecode(artifact).unwrap(), vec![0x60, 0x80]);
+    }
+
+    #[test]
+    fn test_parse_hardhat_artifact() {
+        let artifact = r#"{"abi": [], "bytecode": "0x6080"}"#;
+        assert_eq!(parse_bytecode(artifact).unwrap(), vec![0x60, 0x80]);
+    }
+
+    #[test]
+    fn test_parse_raw_hex() {
+        assert_eq!(parse_bytecode("6080\n").unwrap(), vec![0x60, 0x80]);
+        assert_eq!(parse_bytecode("0x6080").unwrap(), vec![0x60, 0x80]);
+    }
+
+    #[test]
+    fn test_parse_invalid_bytecode() {
+        assert!(parse_bytecode(r#"{"abi": []}"#).is_err());
+        assert!(parse_bytecode("0x").is_err());
+        assert!(parse_bytecode("0x60zz").is_err());
+        assert!(parse_bytecode("0x6080__$abcdef$__").is_err());
+    }
+
+    #[test]
+    fn test_missing_artifact() {
+        assert!(matches!(
+            Artifacts::from_forge_out("/nonexistent/out"),
+            Err(EvmDiffError::Io { .. })
+        ));
+    }
+}
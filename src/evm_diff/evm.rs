@@ -0,0 +1,350 @@
+use ethers::{
+    abi::{self, ParamType, Token},
+    types::{Address, U256},
+    utils::{id, keccak256},
+};
+use revm::{
+    db::{CacheDB, EmptyDB},
+    primitives::{
+        Address as EvmAddress, Bytes, ExecutionResult, Output, SpecId, TransactTo, U256 as EvmU256,
+    },
+    Database, EVM,
+};
+
+use crate::core::hooks::hook_interface::ModifyLiquidityParams;
+use crate::core::pool_manager::PoolId;
+
+use super::{Artifacts, EvmDiffError, Result};
+
+/// Storage slot of the `_pools` mapping in the v4-core `PoolManager`
+pub const POOLS_SLOT: u64 = 6;
+
+/// Offset of `liquidity` within a pool's state
+pub const LIQUIDITY_OFFSET: u64 = 3;
+
+/// Sender of every transaction executed by the harness
+pub const CALLER: Address = Address::repeat_byte(0xca);
+
+/// Pool key in the layout of the Solidity `PoolKey` struct
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EvmPoolKey {
+    pub currency0: Address,
+    pub currency1: Address,
+    pub fee: u32,
+    pub tick_spacing: i32,
+    pub hooks: Address,
+}
+
+impl EvmPoolKey {
+    /// ABI token for the key
+    pub fn to_token(&self) -> Token {
+        Token::Tuple(vec![
+            Token::Address(self.currency0),
+            Token::Address(self.currency1),
+            Token::Uint(U256::from(self.fee)),
+            int_token(self.tick_spacing as i128),
+            Token::Address(self.hooks),
+        ])
+    }
+
+    /// Pool ID, the hash of the ABI-encoded key
+    pub fn to_id(&self) -> PoolId {
+        PoolId(keccak256(abi::encode(&[self.to_token()])))
+    }
+
+    /// Storage slot of the pool's packed `Slot0`
+    pub fn state_slot(&self) -> U256 {
+        let mut preimage = [0u8; 64];
+        preimage[..32].copy_from_slice(self.to_id().as_bytes());
+        U256::from(POOLS_SLOT).to_big_endian(&mut preimage[32..]);
+        U256::from_big_endian(&keccak256(preimage))
+    }
+}
+
+/// Pool state read from `PoolManager` storage
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PoolSnapshot {
+    pub sqrt_price_x96: U256,
+    pub tick: i32,
+    pub liquidity: u128,
+}
+
+/// Outcome of a call into the EVM
+pub type CallOutcome = std::result::Result<Vec<u8>, Vec<u8>>;
+
+/// The v4-core contracts running in revm
+///
+/// Tokens are minted to [`CALLER`] and approved to both routers during
+/// setup, so liquidity and swaps settle with real ERC20 transfers.
+pub struct EvmPoolManager {
+    evm: EVM<CacheDB<EmptyDB>>,
+    pool_manager: Address,
+    modify_liquidity_router: Address,
+    swap_router: Address,
+    currency0: Address,
+    currency1: Address,
+}
+
+impl EvmPoolManager {
+    /// Deploys the contracts and funds the caller
+    pub fn deploy(artifacts: &Artifacts) -> Result<Self> {
+        let mut evm = EVM::new();
+        evm.database(CacheDB::new(EmptyDB::default()));
+        // v4-core relies on transient storage
+        evm.env.cfg.spec_id = SpecId::CANCUN;
+        evm.env.cfg.limit_contract_code_size = Some(usize::MAX);
+        evm.env.tx.caller = to_evm_address(CALLER);
+        evm.env.tx.gas_limit = 30_000_000;
+
+        let mut this = Self {
+            evm,
+            pool_manager: Address::zero(),
+            modify_liquidity_router: Address::zero(),
+            swap_router: Address::zero(),
+            currency0: Address::zero(),
+            currency1: Address::zero(),
+        };
+
+        this.pool_manager = this.create("PoolManager", &artifacts.pool_manager, &[Token::Address(CALLER)])?;
+        let manager = Token::Address(this.pool_manager);
+        this.modify_liquidity_router =
+            this.create("PoolModifyLiquidityTest", &artifacts.modify_liquidity_router, std::slice::from_ref(&manager))?;
+        this.swap_router = this.create("PoolSwapTest", &artifacts.swap_router, &[manager])?;
+
+        let mut tokens = [Address::zero(); 2];
+        for (i, token) in tokens.iter_mut().enumerate() {
+            *token = this.create(
+                "MockERC20",
+                &artifacts.token,
+                &[Token::String(format!("Token{i}")), Token::String(format!("TK{i}")), Token::Uint(18.into())],
+            )?;
+            this.setup_call(*token, "mint(address,uint256)", &[Token::Address(CALLER), Token::Uint(U256::one() << 200)])?;
+            for spender in [this.modify_liquidity_router, this.swap_router] {
+                this.setup_call(*token, "approve(address,uint256)", &[Token::Address(spender), Token::Uint(U256::MAX)])?;
+            }
+        }
+        tokens.sort();
+        [this.currency0, this.currency1] = tokens;
+        Ok(this)
+    }
+
+    /// Sorted currencies of the deployed tokens
+    pub fn currencies(&self) -> (Address, Address) {
+        (self.currency0, self.currency1)
+    }
+
+    /// Router that owns every position created through the harness
+    pub fn modify_liquidity_router(&self) -> Address {
+        self.modify_liquidity_router
+    }
+
+    /// Calls `PoolManager.initialize`, returning the initial tick on success
+    pub fn initialize(&mut self, key: &EvmPoolKey, sqrt_price_x96: U256) -> Result<std::result::Result<i32, Vec<u8>>> {
+        let outcome = self.call(
+            self.pool_manager,
+            "initialize((address,address,uint24,int24,address),uint160)",
+            &[key.to_token(), Token::Uint(sqrt_price_x96)],
+        )?;
+        match outcome {
+            Ok(output) => Ok(Ok(decode_int(&output, 24)? as i32)),
+            Err(revert) => Ok(Err(revert)),
+        }
+    }
+
+    /// Modifies liquidity through the router, returning the caller delta on success
+    pub fn modify_liquidity(
+        &mut self,
+        key: &EvmPoolKey,
+        params: &ModifyLiquidityParams,
+    ) -> Result<std::result::Result<(i128, i128), Vec<u8>>> {
+        let params = Token::Tuple(vec![
+            int_token(params.tick_lower as i128),
+            int_token(params.tick_upper as i128),
+            int_token(params.liquidity_delta),
+            Token::FixedBytes(params.salt.0.to_vec()),
+        ]);
+        let outcome = self.call(
+            self.modify_liquidity_router,
+            "modifyLiquidity((address,address,uint24,int24,address),(int24,int24,int256,bytes32),bytes)",
+            &[key.to_token(), params, Token::Bytes(vec![])],
+        )?;
+        match outcome {
+            Ok(output) => Ok(Ok(decode_balance_delta(&output)?)),
+            Err(revert) => Ok(Err(revert)),
+        }
+    }
+
+    /// Swaps through the router, returning the swap delta on success
+    pub fn swap(
+        &mut self,
+        key: &EvmPoolKey,
+        zero_for_one: bool,
+        amount_specified: i128,
+        sqrt_price_limit_x96: U256,
+    ) -> Result<std::result::Result<(i128, i128), Vec<u8>>> {
+        let params = Token::Tuple(vec![
+            Token::Bool(zero_for_one),
+            int_token(amount_specified),
+            Token::Uint(sqrt_price_limit_x96),
+        ]);
+        // Settle and take tokens rather than ERC6909 claims
+        let settings = Token::Tuple(vec![Token::Bool(false), Token::Bool(false)]);
+        let outcome = self.call(
+            self.swap_router,
+            "swap((address,address,uint24,int24,address),(bool,int256,uint160),(bool,bool),bytes)",
+            &[key.to_token(), params, settings, Token::Bytes(vec![])],
+        )?;
+        match outcome {
+            Ok(output) => Ok(Ok(decode_balance_delta(&output)?)),
+            Err(revert) => Ok(Err(revert)),
+        }
+    }
+
+    /// Reads a pool's price, tick and liquidity from storage
+    pub fn snapshot(&mut self, key: &EvmPoolKey) -> Result<PoolSnapshot> {
+        let state_slot = key.state_slot();
+        let slot0 = self.storage(state_slot)?;
+        let liquidity = self.storage(state_slot + LIQUIDITY_OFFSET)?;
+
+        // Slot0 packs the price in the low 160 bits followed by a 24-bit tick
+        let sqrt_price_x96 = slot0 & ((U256::one() << 160) - 1);
+        let tick_bits = ((slot0 >> 160) & U256::from(0xff_ffffu32)).as_u32();
+        let tick = ((tick_bits << 8) as i32) >> 8;
+        Ok(PoolSnapshot { sqrt_price_x96, tick, liquidity: liquidity.low_u128() })
+    }
+
+    fn storage(&mut self, slot: U256) -> Result<U256> {
+        let address = to_evm_address(self.pool_manager);
+        let db = self.evm.db().ok_or_else(|| EvmDiffError::Evm("no database".to_string()))?;
+        let value = db.storage(address, to_evm_u256(slot)).map_err(|e| EvmDiffError::Evm(format!("{e:?}")))?;
+        Ok(U256::from_big_endian(&value.to_be_bytes::<32>()))
+    }
+
+    fn create(&mut self, contract: &'static str, code: &[u8], args: &[Token]) -> Result<Address> {
+        let mut data = code.to_vec();
+        data.extend(abi::encode(args));
+        self.evm.env.tx.transact_to = TransactTo::create();
+        self.evm.env.tx.data = Bytes::from(data);
+        match self.transact()? {
+            ExecutionResult::Success { output: Output::Create(_, Some(address)), .. } => Ok(Address::from(address.0 .0)),
+            result => Err(EvmDiffError::Deployment { contract, reason: format!("{result:?}") }),
+        }
+    }
+
+    fn setup_call(&mut self, to: Address, signature: &str, args: &[Token]) -> Result<()> {
+        self.call(to, signature, args)?
+            .map(|_| ())
+            .map_err(|revert| EvmDiffError::Setup(format!("{signature} reverted with 0x{}", ethers::utils::hex::encode(revert))))
+    }
+
+    fn call(&mut self, to: Address, signature: &str, args: &[Token]) -> Result<CallOutcome> {
+        let mut data = id(signature).to_vec();
+        data.extend(abi::encode(args));
+        self.evm.env.tx.transact_to = TransactTo::call(to_evm_address(to));
+        self.evm.env.tx.data = Bytes::from(data);
+        match self.transact()? {
+            ExecutionResult::Success { output, .. } => Ok(Ok(output.into_data().to_vec())),
+            ExecutionResult::Revert { output, .. } => Ok(Err(output.to_vec())),
+            ExecutionResult::Halt { reason, .. } => Ok(Err(format!("{reason:?}").into_bytes())),
+        }
+    }
+
+    fn transact(&mut self) -> Result<ExecutionResult> {
+        self.evm.transact_commit().map_err(|e| EvmDiffError::Evm(format!("{e:?}")))
+    }
+}
+
+/// ABI token for a signed integer of any width up to 256 bits
+pub fn int_token(value: i128) -> Token {
+    let magnitude = U256::from(value.unsigned_abs());
+    Token::Int(if value < 0 { (!magnitude).overflowing_add(U256::one()).0 } else { magnitude })
+}
+
+/// Splits a packed `BalanceDelta` into its token0 and token1 amounts
+pub fn decode_balance_delta(output: &[u8]) -> Result<(i128, i128)> {
+    if output.len() < 32 {
+        return Err(EvmDiffError::Decode(format!("expected 32 bytes, got {}", output.len())));
+    }
+    let amount0 = i128::from_be_bytes(output[..16].try_into().expect("16 bytes"));
+    let amount1 = i128::from_be_bytes(output[16..32].try_into().expect("16 bytes"));
+    Ok((amount0, amount1))
+}
+
+fn decode_int(output: &[u8], bits: usize) -> Result<i128> {
+    let tokens = abi::decode(&[ParamType::Int(bits)], output).map_err(|e| EvmDiffError::Decode(e.to_string()))?;
+    match tokens.as_slice() {
+        [Token::Int(value)] => Ok(value.low_u128() as i128),
+        _ => Err(EvmDiffError::Decode("expected a single integer".to_string())),
+    }
+}
+
+fn to_evm_address(address: Address) -> EvmAddress {
+    EvmAddress::from(address.0)
+}
+
+fn to_evm_u256(value: U256) -> EvmU256 {
+    let mut bytes = [0u8; 32];
+    value.to_big_endian(&mut bytes);
+    EvmU256::from_be_bytes(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_int_token_twos_complement() {
+        assert_eq!(int_token(5), Token::Int(U256::from(5)));
+        assert_eq!(int_token(-1), Token::Int(U256::MAX));
+        assert_eq!(int_token(-60), Token::Int(U256::MAX - 59));
+    }
+
+    #[test]
+    fn test_decode_balance_delta() {
+        let mut output = [0u8; 32];
+        output[..16].copy_from_slice(&(-1000i128).to_be_bytes());
+        output[16..].copy_from_slice(&997i128.to_be_bytes());
+        assert_eq!(decode_balance_delta(&output).unwrap(), (-1000, 997));
+        assert!(decode_balance_delta(&output[..31]).is_err());
+    }
+
+    #[test]
+    fn test_decode_int24() {
+        let output = abi::encode(&[int_token(-887272)]);
+        assert_eq!(decode_int(&output, 24).unwrap(), -887272);
+    }
+
+    #[test]
+    fn test_pool_key_slots() {
+        let key = EvmPoolKey {
+            currency0: Address::from_low_u64_be(1),
+            currency1: Address::from_low_u64_be(2),
+            fee: 3000,
+            tick_spacing: 60,
+            hooks: Address::zero(),
+        };
+        let encoded = abi::encode(&[key.to_token()]);
+        assert_eq!(encoded.len(), 5 * 32);
+        assert_eq!(key.to_id(), PoolId(keccak256(&encoded)));
+
+        let mut preimage = key.to_id().as_bytes().to_vec();
+        preimage.extend([0u8; 31]);
+        preimage.push(POOLS_SLOT as u8);
+        assert_eq!(key.state_slot(), U256::from_big_endian(&keccak256(preimage)));
+    }
+
+    #[test]
+    fn test_deploy_reports_failed_creation() {
+        // INVALID opcode as creation code
+        let artifacts = Artifacts {
+            pool_manager: vec![0xfe],
+            modify_liquidity_router: vec![0xfe],
+            swap_router: vec![0xfe],
+            token: vec![0xfe],
+        };
+        assert!(matches!(
+            EvmPoolManager::deploy(&artifacts),
+            Err(EvmDiffError::Deployment { contract: "PoolManager", .. })
+        ));
+    }
+}
@@ -0,0 +1,317 @@
+use ethers::types::{Address, U256};
+
+use crate::core::{
+    amounts::SwapAmount,
+    hooks::{hook_interface::ModifyLiquidityParams, RevertData},
+    math::{tick_math::TickMath, types::{SqrtPrice, TickSpacing}},
+    pool::PoolError,
+    pool_manager::{ManagerPoolKey, PoolManager},
+    rng::Rng,
+    state::StateError,
+};
+
+use super::{Artifacts, EvmDiffError, EvmPoolKey, EvmPoolManager, PoolSnapshot, Result};
+
+/// Fee of the pool exercised by the harness
+pub const DIFF_POOL_FEE: u32 = 3000;
+
+/// Tick spacing of the pool exercised by the harness
+pub const DIFF_TICK_SPACING: i32 = 60;
+
+/// An operation applied to both implementations
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Operation {
+    Initialize { sqrt_price_x96: U256 },
+    ModifyLiquidity { tick_lower: i32, tick_upper: i32, liquidity_delta: i128, salt: [u8; 32] },
+    Swap { zero_for_one: bool, amount_specified: i128, sqrt_price_limit_x96: U256 },
+}
+
+/// Result of an operation in one implementation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// The pool was initialized at the tick
+    Initialized { tick: i32 },
+    /// The caller's balance changed by the delta
+    Delta { amount0: i128, amount1: i128 },
+    /// The operation failed
+    Failed {
+        /// The error, or the decoded revert
+        reason: String,
+        /// Raw revert data, for EVM reverts and crate hook reverts
+        revert: Option<RevertData>,
+    },
+}
+
+impl Outcome {
+    /// Whether two outcomes agree
+    ///
+    /// Failures that both carry revert data agree when the data decodes to
+    /// the same error, or is byte for byte equal when it does not decode.
+    /// A crate failure without revert data agrees with any failure, since
+    /// the crate's typed errors do not map one to one onto Solidity custom
+    /// errors.
+    pub fn agrees_with(&self, other: &Outcome) -> bool {
+        match (self, other) {
+            (
+                Outcome::Failed { revert: Some(ours), .. },
+                Outcome::Failed { revert: Some(theirs), .. },
+            ) => match (ours.decode(), theirs.decode()) {
+                (Some(ours), Some(theirs)) => ours == theirs,
+                _ => ours == theirs,
+            },
+            (Outcome::Failed { .. }, Outcome::Failed { .. }) => true,
+            _ => self == other,
+        }
+    }
+}
+
+/// A step where the implementations disagree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// Index of the operation in the run
+    pub step: usize,
+    pub operation: Operation,
+    pub crate_outcome: Outcome,
+    pub evm_outcome: Outcome,
+    /// Pool state after the operation, if the pool exists
+    pub crate_state: Option<PoolSnapshot>,
+    pub evm_state: PoolSnapshot,
+}
+
+/// Applies operations to a crate `PoolManager` and the v4-core contracts in lockstep
+pub struct DiffHarness {
+    manager: PoolManager,
+    evm: EvmPoolManager,
+    key: ManagerPoolKey,
+    evm_key: EvmPoolKey,
+    /// Owner of the crate positions, the router that owns the EVM positions
+    owner: Address,
+    steps: usize,
+}
+
+impl DiffHarness {
+    /// Deploys the contracts and creates an empty crate manager for the same pair
+    pub fn new(artifacts: &Artifacts) -> Result<Self> {
+        let evm = EvmPoolManager::deploy(artifacts)?;
+        let (currency0, currency1) = evm.currencies();
+        let tick_spacing = TickSpacing::new(DIFF_TICK_SPACING).expect("valid tick spacing");
+        let key = ManagerPoolKey::new(currency0, currency1, DIFF_POOL_FEE, tick_spacing, Address::zero())
+            .map_err(|e| EvmDiffError::Setup(e.to_string()))?;
+        let evm_key = EvmPoolKey {
+            currency0,
+            currency1,
+            fee: DIFF_POOL_FEE,
+            tick_spacing: DIFF_TICK_SPACING,
+            hooks: Address::zero(),
+        };
+        let owner = evm.modify_liquidity_router();
+        Ok(Self { manager: PoolManager::new(), evm, key, evm_key, owner, steps: 0 })
+    }
+
+    /// The crate manager, for inspecting its state between operations
+    pub fn manager(&self) -> &PoolManager {
+        &self.manager
+    }
+
+    /// Applies an operation to both implementations and compares the results
+    pub fn apply(&mut self, operation: &Operation) -> Result<Option<Divergence>> {
+        let (crate_outcome, evm_outcome) = match *operation {
+            Operation::Initialize { sqrt_price_x96 } => (
+                match self.manager.initialize_pool(self.key.clone(), SqrtPrice::new(sqrt_price_x96)) {
+                    Ok(tick) => Outcome::Initialized { tick },
+                    Err(e) => crate_failed(e),
+                },
+                match self.evm.initialize(&self.evm_key, sqrt_price_x96)? {
+                    Ok(tick) => Outcome::Initialized { tick },
+                    Err(revert) => failed(revert),
+                },
+            ),
+            Operation::ModifyLiquidity { tick_lower, tick_upper, liquidity_delta, salt } => {
+                let params = ModifyLiquidityParams { owner: self.owner, tick_lower, tick_upper, liquidity_delta, salt: salt.into() };
+                (
+                    match self.manager.modify_liquidity(self.key.clone(), params.clone(), &[]) {
+                        Ok((delta, _)) => Outcome::Delta { amount0: delta.amount0(), amount1: delta.amount1() },
+                        Err(e) => crate_failed(e),
+                    },
+                    match self.evm.modify_liquidity(&self.evm_key, &params)? {
+                        Ok((amount0, amount1)) => Outcome::Delta { amount0, amount1 },
+                        Err(revert) => failed(revert),
+                    },
+                )
+            }
+            Operation::Swap { zero_for_one, amount_specified, sqrt_price_limit_x96 } => (
+                match self.manager.swap(&self.key, zero_for_one, amount_specified, sqrt_price_limit_x96, &[]) {
+                    Ok(delta) => Outcome::Delta { amount0: delta.amount0(), amount1: delta.amount1() },
+                    Err(e) => crate_failed(e),
+                },
+                match self.evm.swap(&self.evm_key, zero_for_one, amount_specified, sqrt_price_limit_x96)? {
+                    Ok((amount0, amount1)) => Outcome::Delta { amount0, amount1 },
+                    Err(revert) => failed(revert),
+                },
+            ),
+        };
+
+        let step = self.steps;
+        self.steps += 1;
+        let crate_state = self.manager.get_pool(&self.key).map(|pool| PoolSnapshot {
+            sqrt_price_x96: pool.slot0.sqrt_price_x96.to_u256(),
+            tick: pool.slot0.tick,
+            liquidity: pool.liquidity.as_u128(),
+        });
+        let evm_state = self.evm.snapshot(&self.evm_key)?;
+        // An uninitialized crate pool matches the zeroed EVM state
+        let states_agree = crate_state.unwrap_or_default() == evm_state;
+
+        if crate_outcome.agrees_with(&evm_outcome) && states_agree {
+            return Ok(None);
+        }
+        Ok(Some(Divergence {
+            step,
+            operation: operation.clone(),
+            crate_outcome,
+            evm_outcome,
+            crate_state,
+            evm_state,
+        }))
+    }
+
+    /// Applies every operation, collecting the divergences
+    pub fn run<'a>(&mut self, operations: impl IntoIterator<Item = &'a Operation>) -> Result<Vec<Divergence>> {
+        let mut divergences = Vec::new();
+        for operation in operations {
+            divergences.extend(self.apply(operation)?);
+        }
+        Ok(divergences)
+    }
+}
+
+/// Runs `steps` random operations generated from `seed` against a fresh harness
+pub fn fuzz(artifacts: &Artifacts, seed: u64, steps: usize) -> Result<Vec<Divergence>> {
+    let operations = OperationGenerator::new(seed).take(steps).collect::<Vec<_>>();
+    DiffHarness::new(artifacts)?.run(&operations)
+}
+
+/// Deterministic stream of random operations on the harness pool
+///
+/// The stream starts by initializing the pool at price 1, then mixes adding
+/// liquidity, removing previously added liquidity and swaps in either
+/// direction with exact input or output.
+pub struct OperationGenerator {
+    rng: Rng,
+    initialized: bool,
+    open_positions: Vec<(i32, i32, i128, [u8; 32])>,
+}
+
+impl OperationGenerator {
+    /// Creates a generator seeded with `seed`
+    pub fn new(seed: u64) -> Self {
+        Self { rng: Rng::seed_from_u64(seed), initialized: false, open_positions: Vec::new() }
+    }
+}
+
+impl Iterator for OperationGenerator {
+    type Item = Operation;
+
+    fn next(&mut self) -> Option<Operation> {
+        if !self.initialized {
+            self.initialized = true;
+            return Some(Operation::Initialize { sqrt_price_x96: U256::one() << 96 });
+        }
+
+        let roll = self.rng.next_f64();
+        if roll < 0.4 || self.open_positions.is_empty() {
+            let tick_lower = -self.rng.gen_range_i32(1..20) * DIFF_TICK_SPACING;
+            let tick_upper = self.rng.gen_range_i32(1..20) * DIFF_TICK_SPACING;
+            let liquidity_delta = self.rng.gen_range(1_000_000..1_000_000_000_000) as i128;
+            // A few salts, so positions are both reused and distinct
+            let salt = [self.rng.gen_range(0..3) as u8; 32];
+            self.open_positions.push((tick_lower, tick_upper, liquidity_delta, salt));
+            Some(Operation::ModifyLiquidity { tick_lower, tick_upper, liquidity_delta, salt })
+        } else if roll < 0.55 {
+            let index = self.rng.gen_range(0..self.open_positions.len() as u64) as usize;
+            let (tick_lower, tick_upper, liquidity, salt) = self.open_positions.swap_remove(index);
+            Some(Operation::ModifyLiquidity { tick_lower, tick_upper, liquidity_delta: -liquidity, salt })
+        } else {
+            let zero_for_one = self.rng.next_f64() < 0.5;
+            let amount = self.rng.gen_range(1..1_000_000_000) as u128;
+            let amount = if self.rng.next_f64() < 0.7 { SwapAmount::ExactIn(amount) } else { SwapAmount::ExactOut(amount) };
+            let sqrt_price_limit_x96 = if zero_for_one {
+                TickMath::MIN_SQRT_PRICE + 1
+            } else {
+                TickMath::MAX_SQRT_PRICE - 1
+            };
+            Some(Operation::Swap {
+                zero_for_one,
+                amount_specified: amount.amount_specified().ok()?,
+                sqrt_price_limit_x96,
+            })
+        }
+    }
+}
+
+fn failed(revert: Vec<u8>) -> Outcome {
+    let revert = RevertData::from(revert);
+    Outcome::Failed { reason: revert.to_string(), revert: Some(revert) }
+}
+
+fn crate_failed(error: impl Into<PoolError> + ToString) -> Outcome {
+    let reason = error.to_string();
+    let revert = match error.into() {
+        PoolError::HookError(e) | PoolError::StateError(StateError::Hook(e)) => e.revert_data().cloned(),
+        _ => None,
+    };
+    Outcome::Failed { reason, revert }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::hooks::{HookCallback, HookError};
+
+    #[test]
+    fn test_generator_is_deterministic() {
+        let first: Vec<_> = OperationGenerator::new(7).take(50).collect();
+        let second: Vec<_> = OperationGenerator::new(7).take(50).collect();
+        assert_eq!(first, second);
+        assert_ne!(first, OperationGenerator::new(8).take(50).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_generator_removes_only_added_liquidity() {
+        let mut added = std::collections::HashMap::new();
+        let operations: Vec<_> = OperationGenerator::new(1).take(200).collect();
+        assert!(matches!(operations[0], Operation::Initialize { .. }));
+        for operation in &operations[1..] {
+            if let Operation::ModifyLiquidity { tick_lower, tick_upper, liquidity_delta, salt } = *operation {
+                assert_eq!(tick_lower % DIFF_TICK_SPACING, 0);
+                assert_eq!(tick_upper % DIFF_TICK_SPACING, 0);
+                let balance = added.entry((tick_lower, tick_upper, salt)).or_insert(0i128);
+                *balance += liquidity_delta;
+                assert!(*balance >= 0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_failures_agree_on_revert_data() {
+        let crate_failure = crate_failed(StateError::PoolNotInitialized);
+        let evm_failure = failed(vec![0x48, 0x6a, 0xa3, 0x07]);
+        assert!(crate_failure.agrees_with(&evm_failure));
+        assert!(!crate_failure.agrees_with(&Outcome::Delta { amount0: 0, amount1: 0 }));
+
+        // Hook reverts are compared by what they decode to
+        let hook = Address::repeat_byte(0x11);
+        let wrapped = |message| RevertData::wrapped(hook, HookCallback::BeforeSwap, &RevertData::message(message));
+        let crate_revert = crate_failed(StateError::from(HookError::HookCallRevertedWithData(wrapped("paused"))));
+        assert!(crate_revert.agrees_with(&failed(wrapped("paused").bytes().to_vec())));
+        assert!(!crate_revert.agrees_with(&failed(wrapped("closed").bytes().to_vec())));
+        assert!(!crate_revert.agrees_with(&Outcome::Failed {
+            reason: String::new(),
+            revert: Some(RevertData::message("paused")),
+        }));
+        assert!(failed(vec![0xde, 0xad]).agrees_with(&failed(vec![0xde, 0xad])));
+        assert!(!failed(vec![0xde, 0xad]).agrees_with(&failed(vec![0xbe, 0xef])));
+        assert!(Outcome::Initialized { tick: 0 }.agrees_with(&Outcome::Initialized { tick: 0 }));
+        assert!(!Outcome::Initialized { tick: 0 }.agrees_with(&Outcome::Initialized { tick: 1 }));
+    }
+}
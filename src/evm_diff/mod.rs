@@ -0,0 +1,52 @@
+//! Differential testing against the Solidity v4-core contracts
+//!
+//! Enabled with the `evm-diff` feature. The harness deploys the compiled
+//! v4-core `PoolManager`, its test routers and two mock tokens into revm,
+//! applies every operation both there and to a crate `PoolManager`, and
+//! reports each step where the returned values or the resulting pool state
+//! differ.
+//!
+//! The bytecode is not bundled. Build v4-core with `forge build` and point
+//! [`Artifacts::from_forge_out`] at its `out` directory.
+
+pub mod artifacts;
+pub mod evm;
+pub mod harness;
+
+pub use artifacts::*;
+pub use evm::*;
+pub use harness::*;
+
+use thiserror::Error;
+
+/// Error types for the differential harness
+///
+/// These are failures of the harness itself; disagreements between the two
+/// implementations are reported as [`Divergence`]s instead.
+#[derive(Debug, Error)]
+pub enum EvmDiffError {
+    #[error("Failed to read artifact {path}: {source}")]
+    Io {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("Invalid artifact {path}: {reason}")]
+    InvalidArtifact { path: String, reason: String },
+
+    #[error("EVM error: {0}")]
+    Evm(String),
+
+    #[error("Deployment of {contract} failed: {reason}")]
+    Deployment { contract: &'static str, reason: String },
+
+    #[error("Setup call failed: {0}")]
+    Setup(String),
+
+    #[error("Failed to decode return data: {0}")]
+    Decode(String),
+}
+
+/// Result type for the differential harness
+pub type Result<T> = std::result::Result<T, EvmDiffError>;
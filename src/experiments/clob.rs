@@ -0,0 +1,518 @@
+use std::{cell::RefCell, collections::BTreeMap, rc::Rc};
+use ethers::types::Address;
+use primitive_types::{U256, U512};
+
+use crate::core::{
+    hooks::{
+        hook_interface::{PoolKey, SwapParams},
+        BeforeSwapDelta, Hook, HookDescriptor, HookFlags, HookPermissions, HookWithReturns,
+    },
+    math::TickMath,
+    pool_manager::{ManagerPoolKey, PoolManager},
+    state::{BalanceDelta, Result as StateResult, StateError},
+};
+
+/// Hook flags the address of a [`ClobHook`] must carry
+pub const CLOB_HOOK_FLAGS: u16 = HookFlags::BEFORE_SWAP | HookFlags::BEFORE_SWAP_RETURNS_DELTA;
+
+/// Error types for the order book
+#[derive(Debug, thiserror::Error)]
+pub enum ClobError {
+    #[error("Order tick out of range: {0}")]
+    InvalidTick(i32),
+
+    #[error("Invalid order amount: {0}")]
+    InvalidAmount(u128),
+
+    #[error("Order not found: {0}")]
+    OrderNotFound(u64),
+
+    #[error("Order {0} belongs to another owner")]
+    NotOwner(u64),
+
+    #[error("State error: {0}")]
+    State(#[from] StateError),
+}
+
+/// Result type for the order book
+pub type ClobResult<T> = std::result::Result<T, ClobError>;
+
+/// Side of a resting limit order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    /// Buys token0 with token1, filled by zero-for-one swaps
+    Bid,
+    /// Sells token0 for token1, filled by one-for-zero swaps
+    Ask,
+}
+
+/// A resting limit order at the price of a tick, in token1 per token0
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LimitOrder {
+    /// Owner of the order
+    pub owner: Address,
+    /// Side of the order
+    pub side: Side,
+    /// Tick of the order price
+    pub tick: i32,
+    /// Unfilled amount of the currency sold: token1 for bids, token0 for asks
+    pub remaining: u128,
+    /// Unclaimed amount of the currency bought: token0 for bids, token1 for asks
+    pub proceeds: u128,
+}
+
+/// Part of a swap filled by one order, from the taker's side
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OrderFill {
+    /// Filled order
+    pub order_id: u64,
+    /// Input paid by the taker to the maker
+    pub amount_in: u128,
+    /// Output paid by the maker to the taker
+    pub amount_out: u128,
+}
+
+/// Part of a swap filled by the book before it reached the pool
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BookFill {
+    /// Direction of the swap
+    pub zero_for_one: bool,
+    /// Fills in matching order
+    pub fills: Vec<OrderFill>,
+    /// Total input paid by the taker
+    pub amount_in: u128,
+    /// Total output paid to the taker
+    pub amount_out: u128,
+}
+
+impl BookFill {
+    /// Delta of the hook, which takes the input and owes the output
+    pub fn hook_delta(&self) -> BalanceDelta {
+        let (amount_in, amount_out) = (self.amount_in as i128, self.amount_out as i128);
+        if self.zero_for_one {
+            BalanceDelta::new(amount_in, -amount_out)
+        } else {
+            BalanceDelta::new(-amount_out, amount_in)
+        }
+    }
+
+    /// Hook delta in the specified and unspecified currencies of the swap
+    pub fn before_swap_delta(&self, exact_input: bool) -> BeforeSwapDelta {
+        let (amount_in, amount_out) = (self.amount_in as i128, self.amount_out as i128);
+        if exact_input {
+            BeforeSwapDelta { delta_specified: amount_in, delta_unspecified: -amount_out }
+        } else {
+            BeforeSwapDelta { delta_specified: -amount_out, delta_unspecified: amount_in }
+        }
+    }
+}
+
+/// Outcome of a swap routed through the book and then the pool
+#[derive(Debug, Clone)]
+pub struct HybridSwap {
+    /// Part filled by resting orders
+    pub book: BookFill,
+    /// Delta of the part swapped against pool liquidity
+    pub amm_delta: BalanceDelta,
+}
+
+impl HybridSwap {
+    /// Total delta of the taker, negative for amounts paid
+    pub fn taker_delta(&self) -> BalanceDelta {
+        let hook_delta = self.book.hook_delta();
+        BalanceDelta::new(
+            self.amm_delta.amount0() - hook_delta.amount0(),
+            self.amm_delta.amount1() - hook_delta.amount1(),
+        )
+    }
+}
+
+/// Converts an amount of one currency into the other at a square-root price
+fn convert(amount: u128, from_token0: bool, sqrt_price_x96: U256, round_up: bool) -> u128 {
+    let price_x192 = U512::from(sqrt_price_x96) * U512::from(sqrt_price_x96);
+    let (numerator, denominator) = if from_token0 {
+        (U512::from(amount) * price_x192, U512::one() << 192)
+    } else {
+        (U512::from(amount) << 192, price_x192)
+    };
+    let mut quotient = numerator / denominator;
+    if round_up && !(numerator % denominator).is_zero() {
+        quotient += U512::one();
+    }
+    if quotient > U512::from(u128::MAX) { u128::MAX } else { quotient.low_u128() }
+}
+
+/// Orders resting on one pool
+#[derive(Debug)]
+struct OrderBook {
+    key: ManagerPoolKey,
+    orders: BTreeMap<u64, LimitOrder>,
+    next_id: u64,
+    last_fill: Option<BookFill>,
+}
+
+impl OrderBook {
+    /// Fills as much of a swap as orders priced at or better than the swap's
+    /// limit and `reference` allow, best price first and oldest first within
+    /// a price
+    ///
+    /// Rounding favors the makers: takers get less output for exact input
+    /// and pay more input for exact output.
+    fn match_swap(&mut self, params: &SwapParams, reference: Option<U256>) -> StateResult<BookFill> {
+        let zero_for_one = params.zero_for_one;
+        let exact_input = params.amount_specified < 0;
+        let side = if zero_for_one { Side::Bid } else { Side::Ask };
+        let limit = params.sqrt_price_limit_x96.to_u256();
+
+        let mut candidates = Vec::new();
+        for (id, order) in &self.orders {
+            if order.side != side || order.remaining == 0 {
+                continue;
+            }
+            let sqrt_price = TickMath::get_sqrt_price_at_tick(order.tick).map_err(|_| StateError::InvalidPrice)?;
+            // Zero-for-one takers sell token0, so higher prices are better for them
+            let acceptable = |bound: U256| if zero_for_one { sqrt_price >= bound } else { sqrt_price <= bound };
+            if acceptable(limit) && reference.is_none_or(acceptable) {
+                candidates.push((order.tick, *id, sqrt_price));
+            }
+        }
+        if zero_for_one {
+            candidates.sort_by_key(|(tick, id, _)| (std::cmp::Reverse(*tick), *id));
+        } else {
+            candidates.sort_by_key(|(tick, id, _)| (*tick, *id));
+        }
+
+        let mut fill = BookFill { zero_for_one, ..Default::default() };
+        let mut remaining = params.amount_specified.unsigned_abs();
+        for (_, order_id, sqrt_price) in candidates {
+            if remaining == 0 {
+                break;
+            }
+            let Some(order) = self.orders.get_mut(&order_id) else { continue };
+            let (amount_in, amount_out) = if exact_input {
+                let capacity = convert(order.remaining, !zero_for_one, sqrt_price, false);
+                let amount_in = remaining.min(capacity);
+                (amount_in, convert(amount_in, zero_for_one, sqrt_price, false))
+            } else {
+                let amount_out = remaining.min(order.remaining);
+                (convert(amount_out, !zero_for_one, sqrt_price, true), amount_out)
+            };
+            // Skip dust fills and keep the hook delta representable
+            if amount_in == 0 || amount_out == 0 {
+                continue;
+            }
+            if fill.amount_in + amount_in > i128::MAX as u128 || fill.amount_out + amount_out > i128::MAX as u128 {
+                break;
+            }
+
+            order.remaining -= amount_out;
+            order.proceeds += amount_in;
+            remaining -= if exact_input { amount_in } else { amount_out };
+            fill.amount_in += amount_in;
+            fill.amount_out += amount_out;
+            fill.fills.push(OrderFill { order_id, amount_in, amount_out });
+        }
+        Ok(fill)
+    }
+
+    /// Matches a swap on the book's pool, recording the fill for the adapter
+    fn before_swap(&mut self, key: &PoolKey, params: &SwapParams, hook_data: &[u8]) -> StateResult<Option<BookFill>> {
+        if *key != self.key.to_hook_key() || params.amount_specified == 0 {
+            return Ok(None);
+        }
+        // Routers pass the pool price as hook data so only orders that beat it fill
+        let reference = (hook_data.len() == 32).then(|| U256::from_big_endian(hook_data));
+        let fill = self.match_swap(params, reference)?;
+        self.last_fill = Some(fill.clone());
+        Ok((!fill.fills.is_empty()).then_some(fill))
+    }
+}
+
+/// Hybrid AMM/CLOB experiment: a limit order book filled by a hook before
+/// swaps reach pool liquidity
+///
+/// The adapter manages the book of one pool, and [`ClobAdapter::hook`] gives
+/// the hook to register at the pool's hook address, which needs
+/// [`CLOB_HOOK_FLAGS`]. On each swap the hook fills resting orders priced at
+/// least as well as the swap's price limit, returns the fill as its before
+/// swap delta, and leaves the rest of the amount to the pool. The book is
+/// not interleaved with the curve: orders only fill before the pool price
+/// moves. Makers' sold currency is treated as escrowed by the hook, so the
+/// hook's delta in the manager is what the book pays and takes.
+#[derive(Debug)]
+pub struct ClobAdapter {
+    book: Rc<RefCell<OrderBook>>,
+}
+
+impl ClobAdapter {
+    /// Creates an empty book for the pool of `key`
+    pub fn new(key: ManagerPoolKey) -> Self {
+        Self {
+            book: Rc::new(RefCell::new(OrderBook {
+                key,
+                orders: BTreeMap::new(),
+                next_id: 0,
+                last_fill: None,
+            })),
+        }
+    }
+
+    /// Key of the book's pool
+    pub fn key(&self) -> ManagerPoolKey {
+        self.book.borrow().key.clone()
+    }
+
+    /// Creates the hook that fills swaps from this book
+    pub fn hook(&self) -> ClobHook {
+        ClobHook { book: Rc::clone(&self.book) }
+    }
+
+    /// Places an order selling `amount` at the price of `tick` and returns its id
+    pub fn place_order(&mut self, owner: Address, side: Side, tick: i32, amount: u128) -> ClobResult<u64> {
+        if !(TickMath::MIN_TICK..=TickMath::MAX_TICK).contains(&tick) {
+            return Err(ClobError::InvalidTick(tick));
+        }
+        if amount == 0 || amount > i128::MAX as u128 {
+            return Err(ClobError::InvalidAmount(amount));
+        }
+        let mut book = self.book.borrow_mut();
+        let id = book.next_id;
+        book.next_id += 1;
+        book.orders.insert(id, LimitOrder { owner, side, tick, remaining: amount, proceeds: 0 });
+        Ok(id)
+    }
+
+    /// Cancels an order, returning it with the unfilled amount and unclaimed
+    /// proceeds owed to the owner
+    pub fn cancel_order(&mut self, owner: Address, id: u64) -> ClobResult<LimitOrder> {
+        let mut book = self.book.borrow_mut();
+        match book.orders.get(&id) {
+            None => Err(ClobError::OrderNotFound(id)),
+            Some(order) if order.owner != owner => Err(ClobError::NotOwner(id)),
+            Some(_) => Ok(book.orders.remove(&id).expect("order exists")),
+        }
+    }
+
+    /// Claims the proceeds of an order; fully filled orders are removed
+    pub fn claim(&mut self, owner: Address, id: u64) -> ClobResult<u128> {
+        let mut book = self.book.borrow_mut();
+        let order = book.orders.get_mut(&id).ok_or(ClobError::OrderNotFound(id))?;
+        if order.owner != owner {
+            return Err(ClobError::NotOwner(id));
+        }
+        let proceeds = std::mem::take(&mut order.proceeds);
+        if order.remaining == 0 {
+            book.orders.remove(&id);
+        }
+        Ok(proceeds)
+    }
+
+    /// Gets an order
+    pub fn order(&self, id: u64) -> Option<LimitOrder> {
+        self.book.borrow().orders.get(&id).cloned()
+    }
+
+    /// Gets all orders, including filled orders with unclaimed proceeds, by id
+    pub fn orders(&self) -> Vec<(u64, LimitOrder)> {
+        self.book.borrow().orders.iter().map(|(id, order)| (*id, order.clone())).collect()
+    }
+
+    /// Tick of the highest bid with an unfilled amount
+    pub fn best_bid(&self) -> Option<i32> {
+        self.depth(Side::Bid).first().map(|(tick, _)| *tick)
+    }
+
+    /// Tick of the lowest ask with an unfilled amount
+    pub fn best_ask(&self) -> Option<i32> {
+        self.depth(Side::Ask).first().map(|(tick, _)| *tick)
+    }
+
+    /// Unfilled amount per tick on one side, best price first
+    pub fn depth(&self, side: Side) -> Vec<(i32, u128)> {
+        let mut levels = BTreeMap::<i32, u128>::new();
+        for order in self.book.borrow().orders.values() {
+            if order.side == side && order.remaining > 0 {
+                *levels.entry(order.tick).or_default() += order.remaining;
+            }
+        }
+        match side {
+            Side::Bid => levels.into_iter().rev().collect(),
+            Side::Ask => levels.into_iter().collect(),
+        }
+    }
+
+    /// Swaps on the book's pool, filling orders that beat the pool price
+    /// before the rest of the amount is swapped against pool liquidity
+    pub fn swap(
+        &self,
+        manager: &mut PoolManager,
+        zero_for_one: bool,
+        amount_specified: i128,
+        sqrt_price_limit_x96: U256,
+    ) -> ClobResult<HybridSwap> {
+        let key = self.key();
+        let pool = manager.get_pool(&key).ok_or(StateError::PoolNotInitialized)?;
+        let mut hook_data = [0u8; 32];
+        pool.slot0.sqrt_price_x96.to_u256().to_big_endian(&mut hook_data);
+
+        self.book.borrow_mut().last_fill = None;
+        let taker_delta = manager.swap(&key, zero_for_one, amount_specified, sqrt_price_limit_x96, &hook_data)?;
+        let book = self.book.borrow_mut().last_fill.take().unwrap_or(BookFill { zero_for_one, ..Default::default() });
+        // The manager returns the taker's delta, which the book's fill was taken out of
+        let hook_delta = book.hook_delta();
+        let amm_delta = BalanceDelta::new(
+            taker_delta.amount0() + hook_delta.amount0(),
+            taker_delta.amount1() + hook_delta.amount1(),
+        );
+        Ok(HybridSwap { book, amm_delta })
+    }
+}
+
+/// Hook filling swaps from a [`ClobAdapter`]'s book
+///
+/// The fill is returned from `before_swap_with_delta` as a
+/// [`BeforeSwapDelta`], whose specified part the manager takes out of the
+/// amount the pool swaps. Each call fills the book, so `before_swap` leaves
+/// the swap alone.
+#[derive(Debug)]
+pub struct ClobHook {
+    book: Rc<RefCell<OrderBook>>,
+}
+
+impl Hook for ClobHook {
+    fn describe(&self) -> HookDescriptor {
+        let permissions = HookPermissions {
+            before_swap: true,
+            before_swap_returns_delta: true,
+            ..Default::default()
+        };
+        let book = self.book.borrow();
+        HookDescriptor::new("ClobHook", env!("CARGO_PKG_VERSION"), permissions)
+            .with_config("orders", book.orders.len())
+    }
+}
+
+impl HookWithReturns for ClobHook {
+    fn before_swap_with_delta(
+        &mut self,
+        _sender: Address,
+        key: &PoolKey,
+        params: &SwapParams,
+        hook_data: &[u8],
+    ) -> StateResult<BeforeSwapDelta> {
+        let fill = self.book.borrow_mut().before_swap(key, params, hook_data)?;
+        Ok(fill.map_or_else(BeforeSwapDelta::default, |fill| fill.before_swap_delta(params.amount_specified < 0)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{
+        flash_loan::Currency,
+        hooks::hook_interface::ModifyLiquidityParams,
+        math::types::{SqrtPrice, TickSpacing},
+    };
+
+    fn setup() -> (PoolManager, ClobAdapter) {
+        let mut manager = PoolManager::new();
+        let key = ManagerPoolKey::new(
+            Address::from_low_u64_be(1),
+            Address::from_low_u64_be(2),
+            3000,
+            TickSpacing::new(60).unwrap(),
+            HookFlags::new(CLOB_HOOK_FLAGS).apply_to_address(Address::zero()),
+        ).unwrap();
+        let adapter = ClobAdapter::new(key.clone());
+        manager.hook_registry_mut().register_hook(key.hooks(), Box::new(adapter.hook()));
+        manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+        manager.modify_liquidity(
+            key,
+            ModifyLiquidityParams::default_position(Address::repeat_byte(9), -600, 600, 1_000_000_000),
+            &[],
+        ).unwrap();
+        (manager, adapter)
+    }
+
+    #[test]
+    fn test_book_management() {
+        let (_, mut adapter) = setup();
+        let maker = Address::repeat_byte(1);
+        let bid = adapter.place_order(maker, Side::Bid, 60, 1_000).unwrap();
+        adapter.place_order(maker, Side::Bid, 120, 500).unwrap();
+        adapter.place_order(maker, Side::Bid, 120, 250).unwrap();
+        adapter.place_order(maker, Side::Ask, -60, 700).unwrap();
+
+        assert_eq!(adapter.best_bid(), Some(120));
+        assert_eq!(adapter.best_ask(), Some(-60));
+        assert_eq!(adapter.depth(Side::Bid), vec![(120, 750), (60, 1_000)]);
+        assert!(matches!(adapter.place_order(maker, Side::Ask, TickMath::MAX_TICK + 1, 1), Err(ClobError::InvalidTick(_))));
+        assert!(matches!(adapter.place_order(maker, Side::Ask, 0, 0), Err(ClobError::InvalidAmount(0))));
+
+        assert!(matches!(adapter.cancel_order(Address::repeat_byte(2), bid), Err(ClobError::NotOwner(_))));
+        assert_eq!(adapter.cancel_order(maker, bid).unwrap().remaining, 1_000);
+        assert!(matches!(adapter.claim(maker, bid), Err(ClobError::OrderNotFound(_))));
+        assert_eq!(adapter.depth(Side::Bid), vec![(120, 750)]);
+    }
+
+    #[test]
+    fn test_swap_fills_book_before_pool() {
+        let (mut manager, mut adapter) = setup();
+        let maker = Address::repeat_byte(1);
+        let key = adapter.key();
+        // Bids above the pool price fill first; the bid below it does not
+        let best = adapter.place_order(maker, Side::Bid, 120, 500).unwrap();
+        let next = adapter.place_order(maker, Side::Bid, 60, 2_000).unwrap();
+        let below = adapter.place_order(maker, Side::Bid, -60, 2_000).unwrap();
+
+        let swap = adapter.swap(&mut manager, true, -3_000, TickMath::MIN_SQRT_PRICE + 1).unwrap();
+        let ids: Vec<_> = swap.book.fills.iter().map(|fill| fill.order_id).collect();
+        assert_eq!(ids, vec![best, next]);
+        // Rounding in the maker's favor can leave dust on a consumed order
+        assert!(adapter.order(best).unwrap().remaining <= 1);
+        assert_eq!(adapter.order(below).unwrap().remaining, 2_000);
+
+        // The book's output beats the pool price, and the pool swaps the rest
+        assert!(swap.book.amount_out > swap.book.amount_in);
+        assert_eq!(swap.amm_delta.amount0(), -(3_000 - swap.book.amount_in as i128));
+        let taker = swap.taker_delta();
+        assert_eq!(taker.amount0(), -3_000);
+        assert_eq!(taker.amount1(), swap.book.amount_out as i128 + swap.amm_delta.amount1());
+
+        // The hook owes the makers' output and holds their proceeds
+        let hook_delta = swap.book.hook_delta();
+        assert_eq!(manager.get_delta(key.hooks(), Currency::from_address(key.token0())), hook_delta.amount0());
+        assert_eq!(manager.get_delta(key.hooks(), Currency::from_address(key.token1())), hook_delta.amount1());
+        assert_eq!(adapter.claim(maker, best).unwrap(), swap.book.fills[0].amount_in);
+        assert_eq!(adapter.order(best).unwrap().proceeds, 0);
+        assert_eq!(adapter.claim(maker, best).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_exact_output_fully_filled_by_book() {
+        let (mut manager, mut adapter) = setup();
+        let maker = Address::repeat_byte(1);
+        let ask = adapter.place_order(maker, Side::Ask, -120, 10_000).unwrap();
+        let price_before = manager.get_pool(&adapter.key()).unwrap().slot0.sqrt_price_x96;
+
+        let swap = adapter.swap(&mut manager, false, 4_000, TickMath::MAX_SQRT_PRICE - 1).unwrap();
+        assert!(swap.amm_delta.is_zero());
+        assert_eq!(swap.book.amount_out, 4_000);
+        // Input rounds up against the taker, still below the 1:1 pool price
+        assert!(swap.book.amount_in < 4_000);
+        assert_eq!(swap.book.amount_in, convert(4_000, true, TickMath::get_sqrt_price_at_tick(-120).unwrap(), true));
+        assert_eq!(adapter.order(ask).unwrap().remaining, 6_000);
+        assert_eq!(manager.get_pool(&adapter.key()).unwrap().slot0.sqrt_price_x96, price_before);
+
+        // Engines using returned deltas see the fill in specified/unspecified terms
+        let mut hook = adapter.hook();
+        let params = SwapParams {
+            amount_specified: 1_000,
+            zero_for_one: false,
+            sqrt_price_limit_x96: SqrtPrice::new(TickMath::MAX_SQRT_PRICE - 1),
+        };
+        let delta = hook.before_swap_with_delta(Address::zero(), &adapter.key().to_hook_key(), &params, &[]).unwrap();
+        assert_eq!(delta.delta_specified, -1_000);
+        assert!(delta.delta_unspecified > 0 && delta.delta_unspecified < 1_000);
+    }
+}
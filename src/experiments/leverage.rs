@@ -0,0 +1,455 @@
+use std::collections::BTreeMap;
+use primitive_types::{U256, U512};
+
+use crate::core::{
+    hooks::hook_interface::ModifyLiquidityParams,
+    math::{sqrt_price_math::SqrtPriceMath, types::{Liquidity, SqrtPrice}, TickMath},
+    pool_manager::{pool_key_to_id, ManagerPoolKey, PoolId, PoolManager},
+    state::{PositionKey, StateError},
+};
+
+/// Basis point denominator (10,000) for leverage and health factors - represents 1x
+pub const LEVERAGE_DENOMINATOR: u32 = 10_000;
+
+/// Health factor reported for positions without debt
+pub const HEALTH_FACTOR_NO_DEBT: u128 = u128::MAX;
+
+/// Error types for leveraged positions
+#[derive(Debug, thiserror::Error)]
+pub enum LeverageError {
+    #[error("Leverage out of range: {0} bps")]
+    InvalidLeverage(u32),
+
+    #[error("Leveraged position not found: {0}")]
+    PositionNotFound(u64),
+
+    #[error("Liquidity overflow")]
+    LiquidityOverflow,
+
+    #[error("State error: {0}")]
+    State(#[from] StateError),
+}
+
+/// Result type for leveraged positions
+pub type Result<T> = std::result::Result<T, LeverageError>;
+
+/// Parameters of the lending side of the model
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LeverageConfig {
+    /// Maximum leverage in basis points (20,000 = 2x)
+    pub max_leverage_bps: u32,
+    /// Interest charged on debt per block, in pips (1,000,000 = 100%), see
+    /// [`LeverageAdapter::accrue_interest`] for how it compounds
+    pub borrow_rate_per_block_pips: u32,
+}
+
+impl Default for LeverageConfig {
+    fn default() -> Self {
+        Self {
+            max_leverage_bps: 5 * LEVERAGE_DENOMINATOR,
+            borrow_rate_per_block_pips: 0,
+        }
+    }
+}
+
+/// Parameters for opening a leveraged position
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpenParams {
+    /// Owner of the position
+    pub owner: [u8; 20],
+    /// Lower tick bound
+    pub tick_lower: i32,
+    /// Upper tick bound
+    pub tick_upper: i32,
+    /// Liquidity funded by the owner
+    pub collateral_liquidity: u128,
+    /// Leverage in basis points (10,000 = no borrowing)
+    pub leverage_bps: u32,
+}
+
+/// An LP position partly funded with borrowed liquidity
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LeveragedPosition {
+    /// Pool the position is in
+    pub pool_id: PoolId,
+    /// Key of the underlying pool position
+    pub position_key: PositionKey,
+    /// Liquidity funded by the owner
+    pub collateral_liquidity: u128,
+    /// Liquidity funded by the lender
+    pub borrowed_liquidity: u128,
+    /// Token0 owed to the lender
+    pub debt0: u128,
+    /// Token1 owed to the lender
+    pub debt1: u128,
+    /// Health factors recorded by `snapshot_health`, in basis points
+    pub health_history: Vec<u128>,
+}
+
+impl LeveragedPosition {
+    /// Total liquidity provided to the pool
+    pub fn total_liquidity(&self) -> u128 {
+        self.collateral_liquidity + self.borrowed_liquidity
+    }
+}
+
+/// Outcome of closing a leveraged position
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClosedPosition {
+    /// Token0 returned to the owner after repaying the lender; negative when
+    /// the position could not cover its debt
+    pub equity0: i128,
+    /// Token1 returned to the owner after repaying the lender; negative when
+    /// the position could not cover its debt
+    pub equity1: i128,
+    /// Health factor at closing, in basis points
+    pub health_factor_bps: u128,
+}
+
+/// Models leveraged LP positions on top of a `PoolManager`
+///
+/// The adapter plays the lender: opening a position mints the owner's
+/// collateral liquidity plus the borrowed liquidity in one pool position and
+/// records the tokens that funded the borrowed share as debt. Positions are
+/// never liquidated; their health factor (position value over debt value, both
+/// in token1 at the pool price) is only tracked, so underwater positions stay
+/// open until closed and any shortfall shows up as negative equity.
+#[derive(Debug, Default)]
+pub struct LeverageAdapter {
+    config: LeverageConfig,
+    positions: BTreeMap<u64, LeveragedPosition>,
+    next_id: u64,
+}
+
+impl LeverageAdapter {
+    /// Creates an adapter with the given lending parameters
+    pub fn new(config: LeverageConfig) -> Self {
+        Self {
+            config,
+            positions: BTreeMap::new(),
+            next_id: 0,
+        }
+    }
+
+    /// Lending parameters
+    pub fn config(&self) -> &LeverageConfig {
+        &self.config
+    }
+
+    /// Gets a leveraged position
+    pub fn position(&self, id: u64) -> Option<&LeveragedPosition> {
+        self.positions.get(&id)
+    }
+
+    /// Iterates over open leveraged positions in opening order
+    pub fn positions(&self) -> impl Iterator<Item = (u64, &LeveragedPosition)> {
+        self.positions.iter().map(|(id, position)| (*id, position))
+    }
+
+    /// Opens a leveraged position of `collateral_liquidity * leverage_bps / 10,000`
+    /// total liquidity and returns its id
+    ///
+    /// The underlying pool position uses a salt derived from the id, so it is
+    /// kept apart from the owner's other positions.
+    pub fn open(&mut self, manager: &mut PoolManager, key: &ManagerPoolKey, params: OpenParams) -> Result<u64> {
+        let OpenParams { owner, tick_lower, tick_upper, collateral_liquidity, leverage_bps } = params;
+        if leverage_bps < LEVERAGE_DENOMINATOR || leverage_bps > self.config.max_leverage_bps {
+            return Err(LeverageError::InvalidLeverage(leverage_bps));
+        }
+
+        let total_liquidity = U256::from(collateral_liquidity) * U256::from(leverage_bps)
+            / U256::from(LEVERAGE_DENOMINATOR);
+        if total_liquidity > U256::from(i128::MAX as u128) {
+            return Err(LeverageError::LiquidityOverflow);
+        }
+        let total_liquidity = total_liquidity.as_u128();
+        let borrowed_liquidity = total_liquidity - collateral_liquidity;
+
+        let id = self.next_id;
+        let salt = Self::salt_for(id);
+        let (caller_delta, _) = manager.modify_liquidity(
+            key.clone(),
+            ModifyLiquidityParams {
+                owner: owner.into(),
+                tick_lower,
+                tick_upper,
+                liquidity_delta: total_liquidity as i128,
+                salt: salt.into(),
+            },
+            &[],
+        )?;
+
+        // The lender funds the borrowed share of the tokens deposited
+        let share = |amount: i128| -> u128 {
+            if total_liquidity == 0 {
+                return 0;
+            }
+            (U256::from(amount.unsigned_abs()) * U256::from(borrowed_liquidity) / U256::from(total_liquidity)).as_u128()
+        };
+
+        self.next_id += 1;
+        self.positions.insert(id, LeveragedPosition {
+            pool_id: pool_key_to_id(key),
+            position_key: PositionKey { owner, tick_lower, tick_upper, salt },
+            collateral_liquidity,
+            borrowed_liquidity,
+            debt0: share(caller_delta.amount0()),
+            debt1: share(caller_delta.amount1()),
+            health_history: Vec::new(),
+        });
+        Ok(id)
+    }
+
+    /// Charges `blocks` blocks of interest on every position's debt
+    ///
+    /// Interest within one call is simple, but is charged on the debt
+    /// including the interest of earlier calls, so it compounds once per
+    /// call: accruing one block at a time compounds every block.
+    pub fn accrue_interest(&mut self, blocks: u64) {
+        let rate = U256::from(self.config.borrow_rate_per_block_pips) * U256::from(blocks);
+        let pips = U256::from(1_000_000u32);
+        for position in self.positions.values_mut() {
+            let grow = |debt: u128| -> u128 {
+                let interest = U256::from(debt) * rate / pips;
+                debt.saturating_add(if interest > U256::from(u128::MAX) { u128::MAX } else { interest.as_u128() })
+            };
+            position.debt0 = grow(position.debt0);
+            position.debt1 = grow(position.debt1);
+        }
+    }
+
+    /// Token amounts the position would withdraw at the pool's current price
+    ///
+    /// Uncollected fees are not included.
+    pub fn position_amounts(&self, manager: &PoolManager, key: &ManagerPoolKey, id: u64) -> Result<(u128, u128)> {
+        let position = self.positions.get(&id).ok_or(LeverageError::PositionNotFound(id))?;
+        let pool = manager.get_pool(key).ok_or(StateError::PoolNotInitialized)?;
+        amounts_for_liquidity(
+            pool.slot0.sqrt_price_x96,
+            pool.slot0.tick,
+            position.position_key.tick_lower,
+            position.position_key.tick_upper,
+            position.total_liquidity(),
+        )
+    }
+
+    /// Health factor of a position at the pool's current price, in basis points
+    ///
+    /// 10,000 means the position is worth exactly its debt. Positions without
+    /// debt report `HEALTH_FACTOR_NO_DEBT`.
+    pub fn health_factor(&self, manager: &PoolManager, key: &ManagerPoolKey, id: u64) -> Result<u128> {
+        let position = self.positions.get(&id).ok_or(LeverageError::PositionNotFound(id))?;
+        let pool = manager.get_pool(key).ok_or(StateError::PoolNotInitialized)?;
+        let sqrt_price = pool.slot0.sqrt_price_x96.to_u256();
+        let (amount0, amount1) = self.position_amounts(manager, key, id)?;
+
+        let debt_value = value_in_token1(sqrt_price, position.debt0, position.debt1);
+        if debt_value.is_zero() {
+            return Ok(HEALTH_FACTOR_NO_DEBT);
+        }
+        let health = value_in_token1(sqrt_price, amount0, amount1) * U512::from(LEVERAGE_DENOMINATOR) / debt_value;
+        Ok(if health > U512::from(u128::MAX) { u128::MAX } else { health.low_u128() })
+    }
+
+    /// Records the current health factor of every position in `key`'s pool
+    ///
+    /// Call after each price move to build a health history per position.
+    pub fn snapshot_health(&mut self, manager: &PoolManager, key: &ManagerPoolKey) -> Result<Vec<(u64, u128)>> {
+        let pool_id = pool_key_to_id(key);
+        let ids: Vec<u64> = self.positions
+            .iter()
+            .filter(|(_, position)| position.pool_id == pool_id)
+            .map(|(id, _)| *id)
+            .collect();
+
+        let mut snapshot = Vec::with_capacity(ids.len());
+        for id in ids {
+            let health = self.health_factor(manager, key, id)?;
+            if let Some(position) = self.positions.get_mut(&id) {
+                position.health_history.push(health);
+            }
+            snapshot.push((id, health));
+        }
+        Ok(snapshot)
+    }
+
+    /// Burns a leveraged position, repays the lender and returns the owner's equity
+    pub fn close(&mut self, manager: &mut PoolManager, key: &ManagerPoolKey, id: u64) -> Result<ClosedPosition> {
+        let health_factor_bps = self.health_factor(manager, key, id)?;
+        let position = self.positions.get(&id).ok_or(LeverageError::PositionNotFound(id))?;
+
+        let (caller_delta, _) = manager.modify_liquidity(
+            key.clone(),
+            ModifyLiquidityParams {
+                owner: position.position_key.owner.into(),
+                tick_lower: position.position_key.tick_lower,
+                tick_upper: position.position_key.tick_upper,
+                liquidity_delta: -(position.total_liquidity() as i128),
+                salt: position.position_key.salt.into(),
+            },
+            &[],
+        )?;
+
+        let closed = ClosedPosition {
+            equity0: caller_delta.amount0().saturating_sub_unsigned(position.debt0),
+            equity1: caller_delta.amount1().saturating_sub_unsigned(position.debt1),
+            health_factor_bps,
+        };
+        self.positions.remove(&id);
+        Ok(closed)
+    }
+
+    fn salt_for(id: u64) -> [u8; 32] {
+        let mut salt = [0u8; 32];
+        salt[..8].copy_from_slice(b"leverage");
+        salt[24..].copy_from_slice(&id.to_be_bytes());
+        salt
+    }
+}
+
+/// Token amounts held by `liquidity` over a tick range at a price, rounded down
+///
+/// Mirrors `Pool::modify_position` by selecting the in-range case on the tick.
+fn amounts_for_liquidity(
+    sqrt_price: SqrtPrice,
+    tick: i32,
+    tick_lower: i32,
+    tick_upper: i32,
+    liquidity: u128,
+) -> Result<(u128, u128)> {
+    let sqrt_price_lower = SqrtPrice::new(
+        TickMath::get_sqrt_price_at_tick(tick_lower).map_err(|_| StateError::InvalidPrice)?,
+    );
+    let sqrt_price_upper = SqrtPrice::new(
+        TickMath::get_sqrt_price_at_tick(tick_upper).map_err(|_| StateError::InvalidPrice)?,
+    );
+    let liquidity = Liquidity::new(liquidity);
+    let amount0 = |lower: SqrtPrice, upper: SqrtPrice| {
+        SqrtPriceMath::get_amount0_delta(lower, upper, liquidity, false).map_err(|_| StateError::InvalidPrice)
+    };
+    let amount1 = |lower: SqrtPrice, upper: SqrtPrice| {
+        SqrtPriceMath::get_amount1_delta(lower, upper, liquidity, false).map_err(|_| StateError::InvalidPrice)
+    };
+
+    let (amount0, amount1) = if tick < tick_lower {
+        (amount0(sqrt_price_lower, sqrt_price_upper)?, U256::zero())
+    } else if tick < tick_upper {
+        (amount0(sqrt_price, sqrt_price_upper)?, amount1(sqrt_price_lower, sqrt_price)?)
+    } else {
+        (U256::zero(), amount1(sqrt_price_lower, sqrt_price_upper)?)
+    };
+    Ok((amount0.low_u128(), amount1.low_u128()))
+}
+
+/// Value of a token0/token1 pair in token1 at a square-root price, scaled by 2^192
+fn value_in_token1(sqrt_price_x96: U256, amount0: u128, amount1: u128) -> U512 {
+    let price_x192 = U512::from(sqrt_price_x96) * U512::from(sqrt_price_x96);
+    U512::from(amount0) * price_x192 + (U512::from(amount1) << 192)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::math::types::TickSpacing;
+    use ethers::types::Address;
+
+    fn setup() -> (PoolManager, ManagerPoolKey) {
+        let mut manager = PoolManager::new();
+        let key = ManagerPoolKey::new(
+            Address::from_low_u64_be(1),
+            Address::from_low_u64_be(2),
+            3000,
+            TickSpacing::new(60).unwrap(),
+            Address::zero(),
+        ).unwrap();
+        manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+        (manager, key)
+    }
+
+    fn open_params(leverage_bps: u32) -> OpenParams {
+        OpenParams {
+            owner: [1u8; 20],
+            tick_lower: -120,
+            tick_upper: 120,
+            collateral_liquidity: 1_000_000,
+            leverage_bps,
+        }
+    }
+
+    #[test]
+    fn test_open_records_debt_for_borrowed_share() {
+        let (mut manager, key) = setup();
+        let mut adapter = LeverageAdapter::new(LeverageConfig::default());
+
+        let id = adapter.open(&mut manager, &key, open_params(30_000)).unwrap();
+        let position = adapter.position(id).unwrap().clone();
+        assert_eq!(position.total_liquidity(), 3_000_000);
+        assert_eq!(position.borrowed_liquidity, 2_000_000);
+        assert!(position.debt0 > 0 && position.debt1 > 0);
+        assert!(manager.get_position(&key, &position.position_key).is_some());
+
+        // Borrowing two thirds of a position that is worth its deposit gives a health factor of 1.5
+        let health = adapter.health_factor(&manager, &key, id).unwrap();
+        assert!((14_990..=15_010).contains(&health), "health {}", health);
+    }
+
+    #[test]
+    fn test_leverage_bounds() {
+        let (mut manager, key) = setup();
+        let mut adapter = LeverageAdapter::new(LeverageConfig { max_leverage_bps: 20_000, ..Default::default() });
+
+        assert!(matches!(
+            adapter.open(&mut manager, &key, open_params(9_999)),
+            Err(LeverageError::InvalidLeverage(9_999))
+        ));
+        assert!(matches!(
+            adapter.open(&mut manager, &key, open_params(20_001)),
+            Err(LeverageError::InvalidLeverage(20_001))
+        ));
+
+        let id = adapter.open(&mut manager, &key, open_params(LEVERAGE_DENOMINATOR)).unwrap();
+        assert_eq!(adapter.health_factor(&manager, &key, id).unwrap(), HEALTH_FACTOR_NO_DEBT);
+    }
+
+    #[test]
+    fn test_interest_erodes_health_without_liquidation() {
+        let (mut manager, key) = setup();
+        let mut adapter = LeverageAdapter::new(LeverageConfig {
+            max_leverage_bps: 50_000,
+            borrow_rate_per_block_pips: 10_000,
+        });
+        let id = adapter.open(&mut manager, &key, open_params(50_000)).unwrap();
+
+        let before = adapter.snapshot_health(&manager, &key).unwrap()[0].1;
+        // 50 blocks at 1% per block grows the debt by half, pushing the position underwater
+        adapter.accrue_interest(50);
+        let after = adapter.snapshot_health(&manager, &key).unwrap()[0].1;
+        assert!(before > LEVERAGE_DENOMINATOR as u128);
+        assert!(after < LEVERAGE_DENOMINATOR as u128);
+        assert_eq!(adapter.position(id).unwrap().health_history, vec![before, after]);
+
+        // The underwater position can still be closed and reports the shortfall
+        let closed = adapter.close(&mut manager, &key, id).unwrap();
+        assert!(closed.equity0 < 0 && closed.equity1 < 0);
+        assert!(adapter.position(id).is_none());
+    }
+
+    #[test]
+    fn test_interest_compounds_between_accruals() {
+        let (mut manager, key) = setup();
+        let config = LeverageConfig { max_leverage_bps: 50_000, borrow_rate_per_block_pips: 10_000 };
+        let mut at_once = LeverageAdapter::new(config);
+        let mut per_block = LeverageAdapter::new(config);
+        let id = at_once.open(&mut manager, &key, open_params(20_000)).unwrap();
+        let per_block_id = per_block.open(&mut manager, &key, open_params(20_000)).unwrap();
+        let debt = at_once.position(id).unwrap().debt0;
+        assert_eq!(per_block.position(per_block_id).unwrap().debt0, debt);
+
+        at_once.accrue_interest(2);
+        per_block.accrue_interest(1);
+        per_block.accrue_interest(1);
+        assert_eq!(at_once.position(id).unwrap().debt0, debt + debt * 2 / 100);
+        let compounded = debt + debt / 100;
+        assert_eq!(per_block.position(per_block_id).unwrap().debt0, compounded + compounded / 100);
+    }
+}
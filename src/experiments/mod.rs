@@ -0,0 +1,10 @@
+//! Experimental models built on top of the core protocol
+//!
+//! Enabled with the `experiments` feature. APIs in this module may change
+//! without notice.
+
+pub mod clob;
+pub mod leverage;
+
+pub use clob::*;
+pub use leverage::*;
@@ -2,6 +2,7 @@ use ethers::types::Address;
 use crate::core::state::Result as StateResult;
 use crate::core::hooks::hook_interface::PoolKey;
 use crate::core::flash_loan::Currency;
+use crate::core::pool_manager::PoolId;
 use super::types::{ProtocolFee, ProtocolFeesAccrued};
 use primitive_types::U256;
 
@@ -39,7 +40,7 @@ pub enum ProtocolFeeEvent {
     ProtocolFeeControllerUpdated { controller: Address },
     
     /// Protocol fee update event
-    ProtocolFeeUpdated { pool_id: [u8; 32], protocol_fee: u32 },
+    ProtocolFeeUpdated { pool_id: PoolId, protocol_fee: u32 },
 }
 
 /// Protocol fee manager
@@ -92,7 +93,7 @@ impl ProtocolFeeManager {
         caller: Address, 
         key: &PoolKey, 
         new_protocol_fee: ProtocolFee,
-        pool_id: [u8; 32]
+        pool_id: PoolId
     ) -> Result<(), ProtocolFeeError> {
         // Check if caller is the fee controller
         if caller != self.controller {
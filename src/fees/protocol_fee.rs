@@ -1,7 +1,12 @@
-use crate::core::state::{Pool, StateError, Result as StateResult};
+use std::collections::HashMap;
+
+use crate::core::state::{Pool, BalanceDelta, StateError, Result as StateResult};
+use crate::core::pool_manager::PoolId;
 use crate::core::hooks::hook_interface::PoolKey;
 use crate::core::flash_loan::Currency;
-use super::types::{ProtocolFee, MAX_PROTOCOL_FEE, PIPS_DENOMINATOR};
+use crate::core::math::{types::{Percent, Ratio}, FeePips};
+use crate::core::amounts::SwapAmount;
+use super::types::{ProtocolFee, MAX_PROTOCOL_FEE};
 use super::controller::{ProtocolFeeManager, ProtocolFeeError};
 use primitive_types::U256;
 use ethers::types::Address;
@@ -9,7 +14,7 @@ use ethers::types::Address;
 /// Trait for protocol fee calculation and collection
 pub trait ProtocolFeesHandler {
     /// Calculate protocol fee amount from an input amount
-    fn calculate_protocol_fee(&self, amount: u128, fee: u16) -> u128;
+    fn calculate_protocol_fee(&self, amount: u128, fee: FeePips) -> u128;
     
     /// Set protocol fee for a pool
     fn set_protocol_fee(&mut self, pool_key: &PoolKey, protocol_fee: ProtocolFee) -> StateResult<()>;
@@ -35,32 +40,20 @@ pub trait ProtocolFeesHandler {
 
 impl ProtocolFeesHandler for Pool {
     /// Calculate protocol fee amount from an input amount
-    fn calculate_protocol_fee(&self, amount: u128, fee: u16) -> u128 {
+    fn calculate_protocol_fee(&self, amount: u128, fee: FeePips) -> u128 {
         // Protocol fee is in hundredths of a bip (0.0001%)
         // 1000 = 0.1%
-        // We multiply by fee and divide by PIPS_DENOMINATOR (1,000,000)
-        ((amount as u128) * (fee as u128)) / (PIPS_DENOMINATOR as u128)
+        Ratio::from(fee).checked_apply_u128(amount).unwrap_or(u128::MAX)
     }
     
     /// Set protocol fee for a pool
-    fn set_protocol_fee(&mut self, pool_key: &PoolKey, protocol_fee: ProtocolFee) -> StateResult<()> {
-        // In a real implementation, we would store the protocol fee in the pool state
-        // For now, we'll just check if the fee is valid
-        if !protocol_fee.is_valid() {
-            return Err(StateError::InvalidFeeForExactOut);
-        }
-        
-        // Update pool's protocol fee (would be implemented in actual Pool struct)
-        // self.protocol_fee = protocol_fee.0;
-        
-        Ok(())
+    fn set_protocol_fee(&mut self, _pool_key: &PoolKey, protocol_fee: ProtocolFee) -> StateResult<()> {
+        Pool::set_protocol_fee(self, protocol_fee.0)
     }
     
     /// Get protocol fee for a pool
     fn get_protocol_fee(&self, _pool_key: &PoolKey) -> StateResult<ProtocolFee> {
-        // In a real implementation, we would retrieve the protocol fee from the pool state
-        // For now, return a default value
-        Ok(ProtocolFee::new(0, 0))
+        Ok(ProtocolFee(self.slot0.protocol_fee))
     }
     
     /// Update accrued protocol fees - this would be called during swap operations
@@ -111,39 +104,41 @@ impl ProtocolFeeSwap for Pool {
         zero_for_one: bool,
         protocol_fee: ProtocolFee
     ) -> u128 {
-        // If amount specified is negative, it's an exact input swap
         // Protocol fee only applies to exact input swaps
-        if amount_specified >= 0 {
+        let Ok(SwapAmount::ExactIn(amount_in)) = SwapAmount::from_amount_specified(amount_specified) else {
             return 0;
-        }
-        
-        let fee = if zero_for_one {
-            protocol_fee.get_zero_for_one_fee()
-        } else {
-            protocol_fee.get_one_for_zero_fee()
         };
         
-        // Calculate fee from absolute value of amount_specified
-        self.calculate_protocol_fee((-amount_specified) as u128, fee)
+        let fee = protocol_fee.fee(zero_for_one);
+        self.calculate_protocol_fee(amount_in, fee)
     }
     
     /// Apply protocol fee to a swap amount
     fn apply_protocol_fee(&self, input_amount: u128, zero_for_one: bool, protocol_fee: ProtocolFee) -> u128 {
-        let fee = if zero_for_one {
-            protocol_fee.get_zero_for_one_fee()
-        } else {
-            protocol_fee.get_one_for_zero_fee()
-        };
-        
-        let protocol_fee_amount = self.calculate_protocol_fee(input_amount, fee);
-        input_amount - protocol_fee_amount
+        // A fee above 100% leaves nothing
+        match Percent::from_pips(protocol_fee.fee(zero_for_one)) {
+            Some(fee) => input_amount - fee.of_u128(input_amount),
+            None => 0,
+        }
     }
 }
 
+/// How a donation was split between the protocol treasury and LPs
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DonationSplit {
+    /// Amounts owed by the donor, the whole donation
+    pub delta: BalanceDelta,
+    /// Amounts accrued as protocol fees
+    pub protocol0: u128,
+    pub protocol1: u128,
+}
+
 /// Implementation of protocol fee integration for pool operations
 pub struct ProtocolFeeIntegration {
     /// Protocol fee manager
     pub manager: ProtocolFeeManager,
+    /// Share of donations routed to the treasury, for the pools opted in
+    donation_shares: HashMap<PoolId, Percent>,
 }
 
 impl ProtocolFeeIntegration {
@@ -151,6 +146,7 @@ impl ProtocolFeeIntegration {
     pub fn new(initial_owner: Address) -> Self {
         Self {
             manager: ProtocolFeeManager::new(initial_owner),
+            donation_shares: HashMap::new(),
         }
     }
     
@@ -173,4 +169,57 @@ impl ProtocolFeeIntegration {
         
         fee_amount
     }
-} 
\ No newline at end of file
+
+    /// Opts a pool in to routing a share of its donations to the protocol
+    /// treasury; a zero share opts it out
+    pub fn set_treasury_donation_share(&mut self, caller: Address, pool_id: PoolId, share: Percent) -> Result<(), ProtocolFeeError> {
+        if caller != self.manager.controller {
+            return Err(ProtocolFeeError::InvalidCaller);
+        }
+        if share.is_zero() {
+            self.donation_shares.remove(&pool_id);
+        } else {
+            self.donation_shares.insert(pool_id, share);
+        }
+        Ok(())
+    }
+
+    /// Share of a pool's donations routed to the treasury, zero unless the
+    /// pool opted in
+    pub fn treasury_donation_share(&self, pool_id: &PoolId) -> Percent {
+        self.donation_shares.get(pool_id).copied().unwrap_or(Percent::ZERO)
+    }
+
+    /// Donates to a pool, accruing the pool's treasury share as protocol fees
+    /// of the key's currencies instead of crediting it to LPs
+    ///
+    /// The treasury share rounds down, so LPs receive any remainder. The rest
+    /// is donated to the pool as by [`Pool::donate`], which needs liquidity
+    /// in range; a donation going entirely to the treasury doesn't.
+    pub fn donate(
+        &mut self,
+        pool: &mut Pool,
+        pool_id: PoolId,
+        key: &PoolKey,
+        amount0: u128,
+        amount1: u128,
+    ) -> StateResult<DonationSplit> {
+        // The delta is signed, so larger amounts could not be owed by the donor
+        let delta0 = i128::try_from(amount0).map_err(|_| StateError::AmountOverflow)?;
+        let delta1 = i128::try_from(amount1).map_err(|_| StateError::AmountOverflow)?;
+
+        let share = self.treasury_donation_share(&pool_id);
+        let (protocol0, protocol1) = (share.of_u128(amount0), share.of_u128(amount1));
+        let (lp0, lp1) = (amount0 - protocol0, amount1 - protocol1);
+        if lp0 > 0 || lp1 > 0 {
+            pool.donate(lp0, lp1)?;
+        }
+
+        for (token, amount) in [(key.token0, protocol0), (key.token1, protocol1)] {
+            if amount > 0 {
+                self.manager.update_protocol_fees(Currency::from_address(token), U256::from(amount));
+            }
+        }
+        Ok(DonationSplit { delta: BalanceDelta::new(-delta0, -delta1), protocol0, protocol1 })
+    }
+}
@@ -1,11 +1,13 @@
 use primitive_types::U256;
 use ethers::types::Address;
+use crate::core::math::FeePips;
+use super::controller::ProtocolFeeError;
 
 /// Maximum protocol fee is 0.1% (1000 pips)
 pub const MAX_PROTOCOL_FEE: u16 = 1000;
 
 /// Fee denominator (1,000,000) for fee calculations - represents 100%
-pub const PIPS_DENOMINATOR: u32 = 1_000_000;
+pub const PIPS_DENOMINATOR: u32 = FeePips::DENOMINATOR;
 
 /// Fee threshold for zero-for-one direction
 pub const FEE_0_THRESHOLD: u32 = 1001;
@@ -13,44 +15,92 @@ pub const FEE_0_THRESHOLD: u32 = 1001;
 /// Fee threshold for one-for-zero direction
 pub const FEE_1_THRESHOLD: u32 = 1001 << 12;
 
-/// Protocol fee type that contains both zero-for-one and one-for-zero fees
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Protocol fees of a pool for both swap directions, packed as in v4
+///
+/// The fee of zero-for-one swaps takes the low 12 bits and the fee of
+/// one-for-zero swaps the next 12, so a valid fee fits in a `uint24`. Each
+/// direction's fee is in pips and at most [`MAX_PROTOCOL_FEE`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub struct ProtocolFee(pub u32);
 
 impl ProtocolFee {
-    /// Create a new protocol fee
-    pub fn new(zero_for_one: u16, one_for_zero: u16) -> Self {
-        let value = (zero_for_one as u32) | ((one_for_zero as u32) << 12);
-        Self(value)
+    /// Bits of the fee of one direction
+    const DIRECTION_MASK: u32 = 0xfff;
+    /// Offset of the fee of one-for-zero swaps
+    const ONE_FOR_ZERO_OFFSET: u32 = 12;
+
+    /// Create a new protocol fee, failing if either fee is wider than its
+    /// 12 bits
+    ///
+    /// Fees that fit but are above the maximum are kept, so they can be
+    /// [`validate`](Self::validate)d later; use
+    /// [`from_parts`](Self::from_parts) to reject them up front.
+    pub fn new(zero_for_one: u16, one_for_zero: u16) -> Result<Self, ProtocolFeeError> {
+        let (zero_for_one, one_for_zero) = (zero_for_one as u32, one_for_zero as u32);
+        for fee in [zero_for_one, one_for_zero] {
+            if fee > Self::DIRECTION_MASK {
+                return Err(ProtocolFeeError::ProtocolFeeTooLarge(fee));
+            }
+        }
+        Ok(Self(zero_for_one | (one_for_zero << Self::ONE_FOR_ZERO_OFFSET)))
+    }
+
+    /// Packs the fees of both directions, failing if either is above
+    /// [`MAX_PROTOCOL_FEE`]
+    pub fn from_parts(zero_for_one: FeePips, one_for_zero: FeePips) -> Result<Self, ProtocolFeeError> {
+        for fee in [zero_for_one, one_for_zero] {
+            if fee.get() > MAX_PROTOCOL_FEE as u32 {
+                return Err(ProtocolFeeError::ProtocolFeeTooLarge(fee.get()));
+            }
+        }
+        Ok(Self(zero_for_one.get() | (one_for_zero.get() << Self::ONE_FOR_ZERO_OFFSET)))
     }
 
     /// Get the fee for zero-for-one swaps
-    pub fn get_zero_for_one_fee(&self) -> u16 {
-        (self.0 & 0xfff) as u16
+    pub fn get_zero_for_one_fee(&self) -> FeePips {
+        FeePips::new(self.0 & Self::DIRECTION_MASK)
     }
 
     /// Get the fee for one-for-zero swaps
-    pub fn get_one_for_zero_fee(&self) -> u16 {
-        ((self.0 >> 12) & 0xfff) as u16
+    pub fn get_one_for_zero_fee(&self) -> FeePips {
+        FeePips::new((self.0 >> Self::ONE_FOR_ZERO_OFFSET) & Self::DIRECTION_MASK)
+    }
+
+    /// Get the fee for swaps in the given direction
+    pub fn fee(&self, zero_for_one: bool) -> FeePips {
+        if zero_for_one {
+            self.get_zero_for_one_fee()
+        } else {
+            self.get_one_for_zero_fee()
+        }
+    }
+
+    /// Checks that the fee fits in 24 bits and neither direction's fee is
+    /// above [`MAX_PROTOCOL_FEE`]
+    pub fn validate(&self) -> Result<(), ProtocolFeeError> {
+        let too_large = self.0 >> (2 * Self::ONE_FOR_ZERO_OFFSET) != 0
+            || self.0 & Self::DIRECTION_MASK >= FEE_0_THRESHOLD
+            || self.0 & (Self::DIRECTION_MASK << Self::ONE_FOR_ZERO_OFFSET) >= FEE_1_THRESHOLD;
+        if too_large {
+            return Err(ProtocolFeeError::ProtocolFeeTooLarge(self.0));
+        }
+        Ok(())
     }
 
     /// Check if this protocol fee is valid
     pub fn is_valid(&self) -> bool {
-        (self.0 & 0xfff) < FEE_0_THRESHOLD && (self.0 & 0xfff000) < FEE_1_THRESHOLD
+        self.validate().is_ok()
     }
 
     /// Calculate the swap fee combining protocol fee and LP fee
     /// The protocol fee is taken from the input amount first and then the LP fee is taken from the remaining
-    pub fn calculate_swap_fee(&self, direction: bool, lp_fee: u32) -> u32 {
-        let protocol_fee = if direction {
-            self.get_zero_for_one_fee() as u32
-        } else {
-            self.get_one_for_zero_fee() as u32
-        };
+    /// Matches `ProtocolFeeLibrary.calculateSwapFee` in v4
+    pub fn calculate_swap_fee(&self, direction: bool, lp_fee: FeePips) -> FeePips {
+        let protocol_fee = self.fee(direction).get();
 
         // protocolFee + lpFee - (protocolFee * lpFee / 1_000_000)
-        let numerator = protocol_fee * lp_fee;
-        protocol_fee + lp_fee - (numerator / PIPS_DENOMINATOR)
+        let numerator = protocol_fee * lp_fee.get();
+        FeePips::new(protocol_fee + lp_fee.get() - (numerator / PIPS_DENOMINATOR))
     }
 }
 
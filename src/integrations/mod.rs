@@ -0,0 +1,181 @@
+//! Feeding simulation activity to external systems
+//!
+//! A [`PoolManager`](crate::core::PoolManager) with an [`EventSink`] records
+//! a [`SimulationEvent`] for every pool it initializes and every liquidity
+//! change, swap and donation that succeeds, so dashboards and databases can
+//! follow a simulation as it runs. Events serialize to JSON with 128- and
+//! 256-bit numbers as decimal strings. [`EventLog`] keeps events in memory;
+//! the `reqwest` feature adds a [`webhook`] sink posting them to an HTTP
+//! endpoint in batches.
+
+#[cfg(feature = "reqwest")]
+pub mod webhook;
+
+use std::{cell::RefCell, rc::Rc};
+
+use ethers::types::Address;
+use primitive_types::U256;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::core::pool_manager::PoolId;
+
+/// Error types for event sinks
+#[derive(Debug, Error)]
+pub enum IntegrationError {
+    #[error("Failed to encode events: {0}")]
+    Encode(#[from] serde_json::Error),
+
+    #[error("Endpoint rejected {events} events with status {status}")]
+    Rejected { status: u16, events: usize },
+
+    #[error("Failed to deliver {events} events after {attempts} attempts: {reason}")]
+    Delivery { events: usize, attempts: u32, reason: String },
+
+    #[cfg(feature = "reqwest")]
+    #[error("Failed to create HTTP client: {0}")]
+    Client(#[from] reqwest::Error),
+}
+
+/// Result type for event sinks
+pub type IntegrationResult<T> = std::result::Result<T, IntegrationError>;
+
+/// Something that happened in a manager's pools, at the manager's timestamp
+///
+/// Amounts are the caller's deltas, negative when paid to the pool. Events
+/// of operations a failed batched unlock rolls back are not withdrawn.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum SimulationEvent {
+    Initialize {
+        pool_id: PoolId,
+        timestamp: u64,
+        currency0: Address,
+        currency1: Address,
+        fee: u32,
+        tick_spacing: i32,
+        hooks: Address,
+        #[serde(with = "decimal")]
+        sqrt_price_x96: U256,
+        tick: i32,
+    },
+    ModifyLiquidity {
+        pool_id: PoolId,
+        timestamp: u64,
+        owner: Address,
+        tick_lower: i32,
+        tick_upper: i32,
+        #[serde(with = "decimal")]
+        liquidity_delta: i128,
+        #[serde(with = "decimal")]
+        amount0: i128,
+        #[serde(with = "decimal")]
+        amount1: i128,
+    },
+    Swap {
+        pool_id: PoolId,
+        timestamp: u64,
+        zero_for_one: bool,
+        #[serde(with = "decimal")]
+        amount0: i128,
+        #[serde(with = "decimal")]
+        amount1: i128,
+        /// Price, tick and liquidity the swap ended at
+        #[serde(with = "decimal")]
+        sqrt_price_x96: U256,
+        tick: i32,
+        #[serde(with = "decimal")]
+        liquidity: u128,
+    },
+    Donate {
+        pool_id: PoolId,
+        timestamp: u64,
+        donor: Address,
+        #[serde(with = "decimal")]
+        amount0: u128,
+        #[serde(with = "decimal")]
+        amount1: u128,
+    },
+}
+
+impl SimulationEvent {
+    /// ID of the pool the event belongs to
+    pub fn pool_id(&self) -> PoolId {
+        match self {
+            SimulationEvent::Initialize { pool_id, .. }
+            | SimulationEvent::ModifyLiquidity { pool_id, .. }
+            | SimulationEvent::Swap { pool_id, .. }
+            | SimulationEvent::Donate { pool_id, .. } => *pool_id,
+        }
+    }
+}
+
+/// Large numbers as decimal strings, which JSON consumers can't misread as
+/// floats
+mod decimal {
+    use std::fmt::Display;
+
+    use serde::Serializer;
+
+    pub fn serialize<T: Display, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(value)
+    }
+}
+
+/// Receiver of the events of a manager, see
+/// [`PoolManager::set_event_sink`](crate::core::PoolManager::set_event_sink)
+pub trait EventSink {
+    /// Takes an event; sinks that deliver elsewhere may hold it back until
+    /// a batch is full
+    ///
+    /// Delivery failures don't fail the operation that caused the event, so
+    /// sinks report them from [`flush`](Self::flush).
+    fn record(&mut self, event: &SimulationEvent);
+
+    /// Delivers every event held back, reporting any delivery that failed
+    /// since the last flush
+    fn flush(&mut self) -> IntegrationResult<()> {
+        Ok(())
+    }
+}
+
+/// Events kept in memory, shared between its clones
+///
+/// Give the manager one clone and keep another to read the events.
+#[derive(Debug, Clone, Default)]
+pub struct EventLog {
+    events: Rc<RefCell<Vec<SimulationEvent>>>,
+}
+
+impl EventLog {
+    /// Creates an empty log
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The events recorded so far, oldest first
+    pub fn events(&self) -> Vec<SimulationEvent> {
+        self.events.borrow().clone()
+    }
+
+    /// Takes the events recorded so far
+    pub fn drain(&self) -> Vec<SimulationEvent> {
+        std::mem::take(&mut *self.events.borrow_mut())
+    }
+
+    /// Number of events recorded
+    pub fn len(&self) -> usize {
+        self.events.borrow().len()
+    }
+
+    /// Whether no event was recorded
+    pub fn is_empty(&self) -> bool {
+        self.events.borrow().is_empty()
+    }
+}
+
+impl EventSink for EventLog {
+    fn record(&mut self, event: &SimulationEvent) {
+        self.events.borrow_mut().push(event.clone());
+    }
+}
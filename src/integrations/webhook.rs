@@ -0,0 +1,312 @@
+//! Posting simulation events to an HTTP endpoint
+//!
+//! [`WebhookSink`] buffers events and posts them as JSON batches of the form
+//! `{"events": [...]}`. Connection failures, `429` and `5xx` responses are
+//! retried with exponential backoff; other responses outside `2xx` reject the
+//! batch at once.
+//!
+//! The sink uses the blocking `reqwest` client, which sends from the thread
+//! that records the event. It must not be used from inside an async runtime
+//! such as tokio, where the blocking client panics.
+
+use std::{thread, time::Duration};
+
+use reqwest::{blocking::Client, StatusCode};
+use serde::Serialize;
+
+use super::{EventSink, IntegrationError, IntegrationResult, SimulationEvent};
+
+/// Where and how a [`WebhookSink`] delivers events
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebhookConfig {
+    /// Endpoint the batches are posted to
+    pub url: String,
+    /// Number of events posted together
+    pub batch_size: usize,
+    /// Number of times a failed post is retried
+    pub max_retries: u32,
+    /// Wait before the first retry, doubled for every later one
+    pub initial_backoff: Duration,
+    /// Longest wait between retries
+    pub max_backoff: Duration,
+    /// Time limit of each post
+    pub timeout: Duration,
+    /// Extra headers sent with every post, such as authorization
+    pub headers: Vec<(String, String)>,
+}
+
+impl WebhookConfig {
+    /// Posts batches of 100 events to `url`, retrying 3 times from 200ms
+    /// up to 5s apart
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            batch_size: 100,
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(5),
+            timeout: Duration::from_secs(10),
+            headers: Vec::new(),
+        }
+    }
+
+    /// Sets the number of events posted together, at least one
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Sets the number of times a failed post is retried
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Sets the first and the longest wait between retries
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    /// Sets the time limit of each post
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Adds a header sent with every post
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Wait before retry number `retry`, counting from zero
+    pub fn backoff(&self, retry: u32) -> Duration {
+        backoff_delay(retry, self.initial_backoff, self.max_backoff)
+    }
+}
+
+/// Exponential backoff: `initial` doubled `retry` times, capped at `max`
+pub fn backoff_delay(retry: u32, initial: Duration, max: Duration) -> Duration {
+    2u32.checked_pow(retry)
+        .and_then(|factor| initial.checked_mul(factor))
+        .map_or(max, |delay| delay.min(max))
+}
+
+/// Body of a post
+#[derive(Serialize)]
+struct Batch<'a> {
+    events: &'a [SimulationEvent],
+}
+
+/// Event sink posting batches of events to a webhook
+///
+/// A batch is posted once it is full and on [`flush`](EventSink::flush),
+/// which should be called when a simulation ends: events still buffered when
+/// the sink is dropped are lost. A batch that can't be delivered is dropped
+/// and counted, and the first such failure is returned by the next flush.
+pub struct WebhookSink {
+    client: Client,
+    config: WebhookConfig,
+    buffer: Vec<SimulationEvent>,
+    dropped: usize,
+    error: Option<IntegrationError>,
+}
+
+impl WebhookSink {
+    /// Creates a sink delivering as configured
+    pub fn new(config: WebhookConfig) -> IntegrationResult<Self> {
+        let client = Client::builder().timeout(config.timeout).build()?;
+        Ok(Self {
+            client,
+            buffer: Vec::with_capacity(config.batch_size),
+            config,
+            dropped: 0,
+            error: None,
+        })
+    }
+
+    /// The configuration of the sink
+    pub fn config(&self) -> &WebhookConfig {
+        &self.config
+    }
+
+    /// Number of events waiting for their batch to be posted
+    pub fn pending(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Number of events in batches that couldn't be delivered
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    /// Posts the buffered events, dropping them if that fails
+    fn send_buffer(&mut self) -> IntegrationResult<()> {
+        let events = std::mem::take(&mut self.buffer);
+        let result = self.post(&events);
+        if result.is_err() {
+            self.dropped += events.len();
+        }
+        result
+    }
+
+    /// Posts a batch, retrying as configured
+    fn post(&self, events: &[SimulationEvent]) -> IntegrationResult<()> {
+        let body = serde_json::to_vec(&Batch { events })?;
+        let mut reason = String::new();
+        for attempt in 0..=self.config.max_retries {
+            if attempt > 0 {
+                thread::sleep(self.config.backoff(attempt - 1));
+            }
+            let mut request = self.client
+                .post(&self.config.url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json");
+            for (name, value) in &self.config.headers {
+                request = request.header(name.as_str(), value.as_str());
+            }
+            match request.body(body.clone()).send() {
+                Ok(response) if response.status().is_success() => return Ok(()),
+                Ok(response) if is_retryable(response.status()) => {
+                    reason = format!("status {}", response.status().as_u16());
+                }
+                Ok(response) => {
+                    return Err(IntegrationError::Rejected { status: response.status().as_u16(), events: events.len() });
+                }
+                // A request that can't be built fails the same way every time
+                Err(err) if err.is_builder() => {
+                    return Err(IntegrationError::Delivery { events: events.len(), attempts: attempt + 1, reason: err.to_string() });
+                }
+                Err(err) => reason = err.to_string(),
+            }
+        }
+        Err(IntegrationError::Delivery { events: events.len(), attempts: self.config.max_retries + 1, reason })
+    }
+}
+
+/// Whether a response status is worth retrying
+fn is_retryable(status: StatusCode) -> bool {
+    status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+}
+
+impl EventSink for WebhookSink {
+    fn record(&mut self, event: &SimulationEvent) {
+        self.buffer.push(event.clone());
+        if self.buffer.len() >= self.config.batch_size {
+            if let Err(err) = self.send_buffer() {
+                self.error.get_or_insert(err);
+            }
+        }
+    }
+
+    fn flush(&mut self) -> IntegrationResult<()> {
+        if !self.buffer.is_empty() {
+            if let Err(err) = self.send_buffer() {
+                self.error.get_or_insert(err);
+            }
+        }
+        self.error.take().map_or(Ok(()), Err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::mpsc::{self, Receiver};
+
+    use ethers::types::Address;
+
+    use crate::core::pool_manager::PoolId;
+
+    /// Serves one request per status, sending the request bodies back
+    fn serve(statuses: Vec<u16>) -> (String, Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/events", listener.local_addr().unwrap());
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for status in statuses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line == "\r\n" {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            content_length = value.trim().parse().unwrap();
+                        }
+                    }
+                }
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+                sender.send(String::from_utf8(body).unwrap()).unwrap();
+                let response = format!("HTTP/1.1 {status} Status\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+                reader.into_inner().write_all(response.as_bytes()).unwrap();
+            }
+        });
+        (url, receiver)
+    }
+
+    fn donation(amount0: u128) -> SimulationEvent {
+        SimulationEvent::Donate {
+            pool_id: PoolId::default(),
+            timestamp: 1,
+            donor: Address::repeat_byte(1),
+            amount0,
+            amount1: 0,
+        }
+    }
+
+    fn config(url: String) -> WebhookConfig {
+        WebhookConfig::new(url)
+            .with_batch_size(2)
+            .with_max_retries(1)
+            .with_backoff(Duration::from_millis(1), Duration::from_millis(1))
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_the_cap() {
+        let (initial, max) = (Duration::from_millis(100), Duration::from_secs(1));
+        let delays: Vec<_> = [0, 1, 3, 4, 64].iter().map(|&retry| backoff_delay(retry, initial, max)).collect();
+        assert_eq!(delays, [100, 200, 800, 1000, 1000].map(Duration::from_millis));
+    }
+
+    #[test]
+    fn test_batches_are_retried_and_flushed() {
+        let (url, bodies) = serve(vec![500, 200, 200]);
+        let mut sink = WebhookSink::new(config(url)).unwrap();
+        for amount in 1..=3 {
+            sink.record(&donation(amount));
+        }
+        assert_eq!(sink.pending(), 1);
+        sink.flush().unwrap();
+        assert_eq!((sink.pending(), sink.dropped()), (0, 0));
+
+        let bodies: Vec<serde_json::Value> = bodies.iter().map(|body| serde_json::from_str(&body).unwrap()).collect();
+        assert_eq!(bodies.len(), 3);
+        // The failed batch was posted again as it was
+        assert_eq!(bodies[0], bodies[1]);
+        assert_eq!(bodies[1]["events"].as_array().unwrap().len(), 2);
+        assert_eq!(bodies[1]["events"][1]["amount0"], "2");
+        assert_eq!(bodies[2]["events"][0]["event"], "donate");
+    }
+
+    #[test]
+    fn test_failed_batches_are_dropped_and_reported() {
+        let (url, _bodies) = serve(vec![400, 503, 503]);
+        let mut sink = WebhookSink::new(config(url)).unwrap();
+        for amount in 1..=4 {
+            sink.record(&donation(amount));
+        }
+        assert_eq!(sink.dropped(), 4);
+        // The first failure is reported, once
+        assert!(matches!(sink.flush(), Err(IntegrationError::Rejected { status: 400, events: 2 })));
+        sink.flush().unwrap();
+    }
+}
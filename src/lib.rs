@@ -1,5 +1,14 @@
 //! Uniswap V4 Core implementation in Rust
 //! This crate provides a Rust implementation of the Uniswap V4 Core protocol
+//!
+//! # API stability
+//!
+//! - **Stable**: the [`prelude`], which follows semver.
+//! - **Supported**: the other public modules, e.g. [`core`], [`scenario`] and
+//!   [`router`], which are maintained but whose paths and less used items
+//!   may be reorganized between minor versions.
+//! - **Unstable**: `experiments` and `analytics`, only built with the
+//!   features of the same name, which may change without notice.
 
 pub mod core {
     pub mod pool;
@@ -7,9 +16,17 @@ pub mod core {
     pub mod state;
     pub mod flash_loan;
     pub mod pool_manager;
+    pub mod amounts;
+    pub mod oracle;
     pub mod hooks;
+    pub mod rng;
+    pub mod storage;
+    pub mod state_view;
     
-    pub use pool_manager::PoolManager;
+    pub use pool_manager::{PoolManager, PoolId};
+    pub use state_view::StateView;
+    pub use amounts::{SwapAmount, SwapFlows};
+    pub use rng::Rng;
     pub use flash_loan::*;
     pub use flash_loan::currency::Currency;
     
@@ -59,17 +76,35 @@ pub mod core {
     };
 }
 
+#[deprecated(note = "use `core::hooks`, or the hook types of the `prelude`")]
 pub mod hooks {
     pub use crate::core::hooks::*;
 }
 
+pub mod prelude;
+#[cfg(feature = "analytics")]
+pub mod analytics;
 pub mod fees;
+pub mod integrations;
 pub mod bindings;
 pub mod tokens;
+pub mod risk;
+pub mod replay;
+pub mod router;
+pub mod sampling;
+pub mod scenario;
+pub mod simulation;
+pub mod sweeps;
+pub mod vectors;
+#[cfg(feature = "experiments")]
+pub mod experiments;
+#[cfg(feature = "evm-diff")]
+pub mod evm_diff;
 
 // Re-export commonly used types
 pub use ethers;
 pub use core::flash_loan::currency::Currency;
+pub use core::rng::Rng;
 
 /// Common error types for the crate
 #[derive(Debug, thiserror::Error)]
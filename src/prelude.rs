@@ -0,0 +1,35 @@
+//! The stable surface of the crate
+//!
+//! Everything exported here follows semver: it is only removed or changed
+//! incompatibly in a new major version, whatever happens to the modules that
+//! define it. Bring it in with `use uniswap_v4_core::prelude::*;`.
+//!
+//! The error enums and the structs with public fields that are built or
+//! matched outside the crate are `#[non_exhaustive]`, so variants and fields
+//! can be added in minor versions: match them with a wildcard arm and build
+//! them through their constructors, such as [`Slot0::new`] or
+//! [`BeforeHookResult::default`] with its `with_*` setters.
+//!
+//! Other public modules are supported but may still be reorganized between
+//! minor versions, and the `experiments` and `analytics` modules, behind
+//! their features, may change without notice. See the crate documentation
+//! for the tiers.
+
+pub use crate::core::{
+    flash_loan::currency::Currency,
+    hooks::{
+        hook_interface::{ModifyLiquidityParams, PoolKey, SwapParams},
+        AfterHookResult, BeforeHookResult, BeforeSwapDelta, Hook, HookError, HookFlags, HookPermissions,
+        HookWithReturns,
+    },
+    math::{
+        types::{Liquidity, Percent, SqrtPrice, TickSpacing},
+        Bps, FeePips, FullMath, MathError, SqrtPriceMath, SwapMath, TickMath,
+    },
+    pool_manager::{pool_key_to_id, ManagerPoolKey, PoolId, PoolManager, QuoteRequest},
+    rng::Rng,
+    state::{BalanceDelta, Pool, Position, PositionKey, Result as StateResult, Slot0, StateError, TickInfo},
+    state_view::StateView,
+};
+pub use ethers::types::Address;
+pub use primitive_types::U256;
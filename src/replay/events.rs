@@ -0,0 +1,102 @@
+use ethers::{
+    abi::RawLog,
+    contract::{abigen, EthEvent, EthLogDecode},
+    types::{Log, H256},
+};
+
+use crate::core::pool_manager::PoolId;
+
+abigen!(
+    IPoolManager,
+    r#"[
+        event Initialize(bytes32 indexed id, address indexed currency0, address indexed currency1, uint24 fee, int24 tickSpacing, address hooks, uint160 sqrtPriceX96, int24 tick)
+        event ModifyLiquidity(bytes32 indexed id, address indexed sender, int24 tickLower, int24 tickUpper, int256 liquidityDelta, bytes32 salt)
+        event Swap(bytes32 indexed id, address indexed sender, int128 amount0, int128 amount1, uint160 sqrtPriceX96, uint128 liquidity, int24 tick, uint24 fee)
+        event Donate(bytes32 indexed id, address indexed sender, uint256 amount0, uint256 amount1)
+        function extsload(bytes32 slot) external view returns (bytes32)
+        function extsload(bytes32[] slots) external view returns (bytes32[])
+    ]"#,
+);
+
+/// A pool event emitted by the v4 `PoolManager`
+pub type PoolEvent = IPoolManagerEvents;
+
+/// Topics of the events the replay consumes, for filtering logs
+pub fn event_signatures() -> Vec<H256> {
+    vec![
+        InitializeFilter::signature(),
+        ModifyLiquidityFilter::signature(),
+        SwapFilter::signature(),
+        DonateFilter::signature(),
+    ]
+}
+
+/// A pool event with its position in the chain
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoggedEvent {
+    pub block_number: u64,
+    pub log_index: u64,
+    pub event: PoolEvent,
+}
+
+impl LoggedEvent {
+    /// Decodes a `PoolManager` log, returning `None` for other events and
+    /// for pending logs without a position
+    pub fn from_log(log: &Log) -> Option<Self> {
+        let event = PoolEvent::decode_log(&RawLog::from(log.clone())).ok()?;
+        Some(Self {
+            block_number: log.block_number?.as_u64(),
+            log_index: log.log_index?.as_u64(),
+            event,
+        })
+    }
+
+    /// ID of the pool the event belongs to
+    pub fn pool_id(&self) -> PoolId {
+        match &self.event {
+            PoolEvent::InitializeFilter(event) => event.id,
+            PoolEvent::ModifyLiquidityFilter(event) => event.id,
+            PoolEvent::SwapFilter(event) => event.id,
+            PoolEvent::DonateFilter(event) => event.id,
+        }
+        .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::{abi::{encode, Token}, types::{Address, U256, U64}};
+
+    #[test]
+    fn test_decode_swap_log() {
+        let id = [7u8; 32];
+        let sender = Address::repeat_byte(2);
+        let data = encode(&[
+            Token::Int(U256::MAX - 999), // -1000
+            Token::Int(U256::from(990)),
+            Token::Uint(U256::one() << 96),
+            Token::Uint(U256::from(1_000_000)),
+            Token::Int(U256::MAX - 59), // -60
+            Token::Uint(U256::from(3000)),
+        ]);
+        let log = Log {
+            address: Address::repeat_byte(1),
+            topics: vec![SwapFilter::signature(), H256(id), H256::from(sender)],
+            data: data.into(),
+            block_number: Some(U64::from(12)),
+            log_index: Some(U256::from(3)),
+            ..Default::default()
+        };
+
+        let logged = LoggedEvent::from_log(&log).unwrap();
+        assert_eq!((logged.block_number, logged.log_index, logged.pool_id()), (12, 3, PoolId(id)));
+        let PoolEvent::SwapFilter(swap) = logged.event else { panic!("expected a swap") };
+        assert_eq!((swap.sender, swap.amount_0, swap.amount_1), (sender, -1000, 990));
+        assert_eq!((swap.tick, swap.fee, swap.liquidity), (-60, 3000, 1_000_000));
+
+        // Unrelated events and pending logs are skipped
+        assert!(LoggedEvent::from_log(&Log { topics: vec![H256::zero()], ..log.clone() }).is_none());
+        assert!(LoggedEvent::from_log(&Log { block_number: None, ..log }).is_none());
+    }
+}
@@ -0,0 +1,226 @@
+use ethers::types::{Address, U256};
+
+use crate::core::{
+    hooks::hook_interface::ModifyLiquidityParams,
+    pool_manager::PoolId,
+    state::Salt,
+};
+
+use super::{LoggedEvent, ReplayError, Replayer, Result};
+
+/// Owner of the positions opened by [`simulate_position_fees`]
+pub const SIMULATED_OWNER: Address = Address::repeat_byte(0x5f);
+
+/// A position that was never opened, whose fees are simulated over a replay
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HypotheticalPosition {
+    /// On-chain ID of the pool
+    pub pool_id: PoolId,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub liquidity: u128,
+    /// Block the position is opened at, before the events of that block
+    pub created_at: u64,
+}
+
+/// Fees a hypothetical position would have earned
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SimulatedFees {
+    /// Whether the position was opened, which it isn't if its pool had no
+    /// `Initialize` by the end of the events or it was created after them
+    pub opened: bool,
+    pub amount0: u128,
+    pub amount1: u128,
+}
+
+/// Replays events with hypothetical positions added, returning the fees each
+/// would have earned by the last event
+///
+/// The events must start from the `Initialize` of the pools, as for a
+/// [`Replayer`]. Each position is opened in its pool as soon as both its
+/// creation block and the pool's `Initialize` are reached, and its fees are
+/// read from the growth of the pool's fee growth inside its range, so they
+/// account for the position diluting the liquidity it joins. Swaps are
+/// replayed as exact-input swaps of the logged amounts, which the added
+/// liquidity fills at better prices than the chain did, so the replay
+/// diverges from the logged prices by design and divergences are not
+/// reported. Positions are replayed together and see each other's liquidity;
+/// simulate them one at a time to compare independent alternatives. The
+/// real pools are untouched, since the replay runs on its own manager.
+pub fn simulate_position_fees(
+    events: &[LoggedEvent],
+    positions: &[HypotheticalPosition],
+) -> Result<Vec<SimulatedFees>> {
+    let mut replayer = Replayer::new();
+    let mut opened = vec![false; positions.len()];
+    for event in events {
+        for (index, position) in positions.iter().enumerate() {
+            if !opened[index] && position.created_at <= event.block_number {
+                opened[index] = open(&mut replayer, index, position)?;
+            }
+        }
+        replayer.apply(event)?;
+    }
+
+    positions
+        .iter()
+        .enumerate()
+        .map(|(index, position)| {
+            if !opened[index] {
+                return Ok(SimulatedFees::default());
+            }
+            let key = replayer.pool_key(&position.pool_id).ok_or(ReplayError::UnknownPool(position.pool_id))?;
+            let snapshot = replayer
+                .manager()
+                .position_fee_growth_snapshot(key, &params(index, position).position_key())
+                .ok_or_else(|| ReplayError::InvalidPosition(format!("position {} was closed", index)))?;
+            let (amount0, amount1) = snapshot.pending_fees();
+            Ok(SimulatedFees { opened: true, amount0, amount1 })
+        })
+        .collect()
+}
+
+/// Position parameters, with the position's index as salt so positions with
+/// the same range stay apart
+fn params(index: usize, position: &HypotheticalPosition) -> ModifyLiquidityParams {
+    let mut salt = [0; 32];
+    U256::from(index).to_big_endian(&mut salt);
+    ModifyLiquidityParams {
+        owner: SIMULATED_OWNER,
+        tick_lower: position.tick_lower,
+        tick_upper: position.tick_upper,
+        liquidity_delta: position.liquidity as i128,
+        salt: Salt::from(salt),
+    }
+}
+
+/// Opens a position if its pool is initialized, returning whether it was
+fn open(replayer: &mut Replayer, index: usize, position: &HypotheticalPosition) -> Result<bool> {
+    let Some(key) = replayer.pool_key(&position.pool_id).cloned() else {
+        return Ok(false);
+    };
+    if i128::try_from(position.liquidity).is_err() {
+        return Err(ReplayError::InvalidPosition(format!("liquidity {}", position.liquidity)));
+    }
+    replayer
+        .manager_mut()
+        .modify_liquidity(key, params(index, position), &[])
+        .map_err(|e| ReplayError::InvalidPosition(e.to_string()))?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::I256;
+    use crate::core::{
+        math::{tick_math::TickMath, types::{SqrtPrice, TickSpacing}},
+        pool_manager::{ManagerPoolKey, PoolManager},
+    };
+    use super::super::{InitializeFilter, ModifyLiquidityFilter, PoolEvent, PoolSnapshot, SwapFilter};
+
+    const POOL_ID: PoolId = PoolId([9; 32]);
+    const SWAPS: [(u64, bool, i128); 3] = [(3, true, -100_000), (5, false, -250_000), (6, true, -50_000)];
+
+    fn logged(block_number: u64, event: PoolEvent) -> LoggedEvent {
+        LoggedEvent { block_number, log_index: 0, event }
+    }
+
+    fn key() -> ManagerPoolKey {
+        ManagerPoolKey::new(
+            Address::from_low_u64_be(1),
+            Address::from_low_u64_be(2),
+            3000,
+            TickSpacing::new(60).unwrap(),
+            Address::zero(),
+        )
+        .unwrap()
+    }
+
+    /// Runs the swaps on a pool with one position, really opening `extra` as
+    /// the first simulated position at its creation block, and returns the
+    /// logged events with the fees `extra` earned
+    fn run_chain(extra: Option<HypotheticalPosition>) -> (Vec<LoggedEvent>, (u128, u128)) {
+        let sender = Address::repeat_byte(0xaa);
+        let mut chain = PoolManager::new();
+        let tick = chain.initialize_pool(key(), SqrtPrice::ONE).unwrap();
+        let mut events = vec![logged(1, PoolEvent::InitializeFilter(InitializeFilter {
+            id: POOL_ID.0,
+            currency_0: key().token0(),
+            currency_1: key().token1(),
+            fee: 3000,
+            tick_spacing: 60,
+            hooks: Address::zero(),
+            sqrt_price_x96: SqrtPrice::ONE.to_u256(),
+            tick,
+        }))];
+        let lp = ModifyLiquidityParams::default_position(sender, -600, 600, 1_000_000_000);
+        chain.modify_liquidity(key(), lp.clone(), &[]).unwrap();
+        events.push(logged(2, PoolEvent::ModifyLiquidityFilter(ModifyLiquidityFilter {
+            id: POOL_ID.0,
+            sender,
+            tick_lower: lp.tick_lower,
+            tick_upper: lp.tick_upper,
+            liquidity_delta: I256::from(lp.liquidity_delta),
+            salt: lp.salt.into(),
+        })));
+
+        for (block_number, zero_for_one, amount) in SWAPS {
+            if let Some(position) = extra.filter(|position| position.created_at == block_number) {
+                chain.modify_liquidity(key(), params(0, &position), &[]).unwrap();
+            }
+            let limit = if zero_for_one { TickMath::MIN_SQRT_PRICE + 1 } else { TickMath::MAX_SQRT_PRICE - 1 };
+            let delta = chain.swap(&key(), zero_for_one, amount, limit, &[]).unwrap();
+            let state = PoolSnapshot::of(chain.get_pool(&key()).unwrap());
+            events.push(logged(block_number, PoolEvent::SwapFilter(SwapFilter {
+                id: POOL_ID.0,
+                sender,
+                amount_0: delta.amount0(),
+                amount_1: delta.amount1(),
+                sqrt_price_x96: state.sqrt_price_x96,
+                liquidity: state.liquidity,
+                tick: state.tick,
+                fee: 3000,
+            })));
+        }
+
+        let fees = extra
+            .and_then(|position| chain.position_fee_growth_snapshot(&key(), &params(0, &position).position_key()))
+            .map_or((0, 0), |snapshot| snapshot.pending_fees());
+        (events, fees)
+    }
+
+    #[test]
+    fn test_simulated_fees_match_opening_the_position() {
+        let (events, _) = run_chain(None);
+        let position = HypotheticalPosition { pool_id: POOL_ID, tick_lower: -120, tick_upper: 120, liquidity: 500_000_000, created_at: 5 };
+        let simulated = simulate_position_fees(&events, &[position]).unwrap();
+
+        // The same swaps with the position really opened earn the same fees
+        let (_, expected) = run_chain(Some(position));
+        assert!(expected.0 > 0 && expected.1 > 0);
+        assert_eq!(simulated, vec![SimulatedFees { opened: true, amount0: expected.0, amount1: expected.1 }]);
+    }
+
+    #[test]
+    fn test_positions_outside_the_replay_earn_nothing() {
+        let (events, _) = run_chain(None);
+        let position = HypotheticalPosition { pool_id: POOL_ID, tick_lower: -600, tick_upper: 600, liquidity: 1_000_000_000, created_at: 0 };
+        let positions = [
+            position,
+            // Above the price the swaps reach
+            HypotheticalPosition { tick_lower: 6000, tick_upper: 6600, ..position },
+            // After the last swap
+            HypotheticalPosition { created_at: 7, ..position },
+            HypotheticalPosition { pool_id: PoolId([1; 32]), ..position },
+        ];
+        let simulated = simulate_position_fees(&events, &positions).unwrap();
+        assert!(simulated[0].amount0 > 0 && simulated[0].amount1 > 0);
+        assert_eq!(simulated[1], SimulatedFees { opened: true, amount0: 0, amount1: 0 });
+        assert_eq!(simulated[2], SimulatedFees::default());
+        assert_eq!(simulated[3], SimulatedFees::default());
+
+        let invalid = HypotheticalPosition { tick_lower: 600, tick_upper: -600, ..position };
+        assert!(matches!(simulate_position_fees(&events, &[invalid]), Err(ReplayError::InvalidPosition(_))));
+    }
+}
@@ -0,0 +1,58 @@
+//! Archival replay of v4 `PoolManager` event logs
+//!
+//! [`fetch_events`] pulls the `Initialize`, `ModifyLiquidity`, `Swap` and
+//! `Donate` logs of a deployed `PoolManager` from a node, and a [`Replayer`]
+//! applies them in chain order to a crate `PoolManager`, reconstructing every
+//! pool's state. Each swap is checked against the price, tick and liquidity
+//! it logged, and [`replay_range`] periodically compares the reconstructed
+//! pools with their on-chain state read through `extsload`, so historical
+//! analytics can run on the replayed state instead of querying an archive
+//! node for every question. [`simulate_position_fees`] reuses the replay to
+//! estimate what positions that were never opened would have earned, and
+//! the [`periphery`] helpers map positions minted by the v4-periphery
+//! `PositionManager` to and from its token IDs. [`fetch_pool_state`] reads a
+//! pool's full state at a block, ticks and positions included, in batched
+//! storage reads, and records the block it came from.
+
+pub mod events;
+pub mod fees;
+pub mod periphery;
+pub mod replayer;
+pub mod snapshot;
+pub mod source;
+
+pub use events::*;
+pub use fees::*;
+pub use periphery::*;
+pub use replayer::*;
+pub use snapshot::*;
+pub use source::*;
+
+use thiserror::Error;
+
+use crate::core::pool_manager::PoolId;
+
+/// Error types for the replay
+///
+/// These stop the replay; disagreements between the engine and the chain are
+/// reported as [`ReplayDivergence`]s instead.
+#[derive(Debug, Error)]
+pub enum ReplayError {
+    #[error("Provider error: {0}")]
+    Provider(String),
+
+    #[error("Event for unknown pool {0}")]
+    UnknownPool(PoolId),
+
+    #[error("Invalid event: {0}")]
+    InvalidEvent(String),
+
+    #[error("Invalid hypothetical position: {0}")]
+    InvalidPosition(String),
+
+    #[error("Invalid pool state: {0}")]
+    InvalidState(String),
+}
+
+/// Result type for the replay
+pub type Result<T> = std::result::Result<T, ReplayError>;
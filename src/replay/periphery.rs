@@ -0,0 +1,153 @@
+//! Positions managed by the v4-periphery `PositionManager`
+//!
+//! The periphery's `PositionManager` owns every position it mints in the
+//! core `PoolManager` and tells them apart by salt: the position of token
+//! `tokenId` has salt `bytes32(tokenId)`. Its `positionInfo` mapping packs the
+//! pool and range of each token into a [`PositionInfo`] word. These helpers
+//! convert between token IDs and [`PositionKey`]s, so positions replayed from
+//! chain can be found by token ID, and replayed positions traced back to
+//! their token.
+
+use ethers::types::{Address, H256, U256};
+
+use crate::core::{pool_manager::PoolId, state::PositionKey};
+
+/// Salt of the position of a periphery token, `bytes32(tokenId)`
+pub fn token_id_salt(token_id: U256) -> [u8; 32] {
+    let mut salt = [0; 32];
+    token_id.to_big_endian(&mut salt);
+    salt
+}
+
+/// Token ID of a periphery position from its salt, inverse of
+/// [`token_id_salt`]
+pub fn salt_token_id(salt: &[u8; 32]) -> U256 {
+    U256::from_big_endian(salt)
+}
+
+/// Key of the position of a periphery token, owned by the `PositionManager`
+/// at `position_manager`
+pub fn periphery_position_key(position_manager: Address, token_id: U256, info: &PositionInfo) -> PositionKey {
+    PositionKey {
+        owner: position_manager.0,
+        tick_lower: info.tick_lower(),
+        tick_upper: info.tick_upper(),
+        salt: token_id_salt(token_id),
+    }
+}
+
+/// Token ID of a position owned by the `PositionManager` at
+/// `position_manager`, or `None` for positions of other owners
+pub fn periphery_token_id(position_manager: Address, key: &PositionKey) -> Option<U256> {
+    (key.owner == position_manager.0).then(|| salt_token_id(&key.salt))
+}
+
+/// ID of a position in the core `PoolManager`'s storage, as computed by its
+/// `Position.calculatePositionKey`
+///
+/// The ID hashes the packed owner, 24-bit ticks and salt, and keys the
+/// position in its pool's `positions` mapping.
+pub fn position_id(key: &PositionKey) -> H256 {
+    H256(key.storage_id())
+}
+
+/// Pool and range of a periphery token, packed as in its `PositionInfo`
+///
+/// From the most significant bit: the first 25 bytes of the pool ID, the
+/// upper and lower ticks in 24 bits each, and a byte flagging whether the
+/// token has a subscriber.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct PositionInfo(pub U256);
+
+impl PositionInfo {
+    const TICK_LOWER_OFFSET: usize = 8;
+    const TICK_UPPER_OFFSET: usize = 32;
+    const POOL_ID_OFFSET: usize = 56;
+
+    /// Packs the pool and range of a token without a subscriber
+    pub fn new(pool_id: PoolId, tick_lower: i32, tick_upper: i32) -> Self {
+        let pool_id = U256::from_big_endian(pool_id.as_bytes()) >> Self::POOL_ID_OFFSET << Self::POOL_ID_OFFSET;
+        let tick = |tick: i32, offset: usize| U256::from(tick as u32 & 0xFF_FFFF) << offset;
+        Self(pool_id | tick(tick_upper, Self::TICK_UPPER_OFFSET) | tick(tick_lower, Self::TICK_LOWER_OFFSET))
+    }
+
+    /// First 25 bytes of the pool ID, all the info keeps of it
+    pub fn pool_id_prefix(&self) -> [u8; 25] {
+        let mut word = [0; 32];
+        self.0.to_big_endian(&mut word);
+        word[..25].try_into().expect("25 of 32 bytes")
+    }
+
+    /// Whether the info is of a token in the pool with the given ID
+    pub fn matches_pool(&self, pool_id: &PoolId) -> bool {
+        self.pool_id_prefix() == pool_id.as_bytes()[..25]
+    }
+
+    /// Lower tick of the range
+    pub fn tick_lower(&self) -> i32 {
+        Self::tick_at(self.0, Self::TICK_LOWER_OFFSET)
+    }
+
+    /// Upper tick of the range
+    pub fn tick_upper(&self) -> i32 {
+        Self::tick_at(self.0, Self::TICK_UPPER_OFFSET)
+    }
+
+    /// Whether the token has a subscriber notified of its changes
+    pub fn has_subscriber(&self) -> bool {
+        self.0.low_u32() & 0xFF != 0
+    }
+
+    /// Sets whether the token has a subscriber
+    pub fn with_subscriber(self, has_subscriber: bool) -> Self {
+        Self((self.0 >> 8 << 8) | U256::from(has_subscriber as u8))
+    }
+
+    /// Sign extends the 24-bit tick at `offset`
+    fn tick_at(word: U256, offset: usize) -> i32 {
+        (((word >> offset).low_u32() << 8) as i32) >> 8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::utils::keccak256;
+
+    #[test]
+    fn test_position_info_packing() {
+        let pool_id = PoolId([0xab; 32]);
+        let info = PositionInfo::new(pool_id, -887_220, 60);
+        let expected = (U256::from_big_endian(&[0xab; 25]) << 56)
+            | (U256::from(60) << 32)
+            | (U256::from(-887_220i32 as u32 & 0xFF_FFFF) << 8);
+        assert_eq!(info.0, expected);
+        assert_eq!((info.tick_lower(), info.tick_upper()), (-887_220, 60));
+        assert!(info.matches_pool(&pool_id));
+        assert!(!info.matches_pool(&PoolId([0xac; 32])));
+
+        let subscribed = info.with_subscriber(true);
+        assert!(subscribed.has_subscriber() && !info.has_subscriber());
+        assert_eq!((subscribed.tick_lower(), subscribed.tick_upper()), (-887_220, 60));
+        assert_eq!(subscribed.with_subscriber(false), info);
+    }
+
+    #[test]
+    fn test_token_ids_map_to_position_keys() {
+        let position_manager = Address::repeat_byte(0xbd);
+        let token_id = U256::from(12_345);
+        let info = PositionInfo::new(PoolId([1; 32]), -120, 240);
+        let key = periphery_position_key(position_manager, token_id, &info);
+        assert_eq!((key.tick_lower, key.tick_upper), (-120, 240));
+        assert_eq!(key.salt[30..], [0x30, 0x39]);
+        assert_eq!(periphery_token_id(position_manager, &key), Some(token_id));
+        assert_eq!(periphery_token_id(Address::zero(), &key), None);
+        assert_eq!(salt_token_id(&token_id_salt(U256::MAX)), U256::MAX);
+
+        // The core storage key packs the ticks into 24 bits each
+        let mut preimage = position_manager.0.to_vec();
+        preimage.extend([0xff, 0xff, 0x88, 0x00, 0x00, 0xf0]);
+        preimage.extend(key.salt);
+        assert_eq!(position_id(&key), H256(keccak256(preimage)));
+    }
+}
@@ -0,0 +1,360 @@
+use std::collections::HashMap;
+use ethers::types::U256;
+
+use crate::core::{
+    hooks::hook_interface::ModifyLiquidityParams,
+    math::{tick_math::TickMath, types::{SqrtPrice, TickSpacing}, FeePips},
+    pool_manager::{ManagerPoolKey, PoolId, PoolManager},
+    state::Pool,
+};
+
+use super::{LoggedEvent, PoolEvent, PoolState, ReplayError, Result};
+
+/// State of a pool compared between the chain and the replay
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PoolSnapshot {
+    pub sqrt_price_x96: U256,
+    pub tick: i32,
+    pub liquidity: u128,
+}
+
+impl PoolSnapshot {
+    /// Takes a snapshot of a crate pool
+    pub fn of(pool: &Pool) -> Self {
+        Self {
+            sqrt_price_x96: pool.slot0.sqrt_price_x96.to_u256(),
+            tick: pool.slot0.tick,
+            liquidity: pool.liquidity.as_u128(),
+        }
+    }
+}
+
+/// How the replay disagrees with the chain
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mismatch {
+    /// The engine rejected an operation that succeeded on chain
+    Rejected(String),
+    /// The pool state after the event differs from the chain
+    State { expected: PoolSnapshot, actual: PoolSnapshot },
+}
+
+/// A point where the replay disagrees with the chain
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplayDivergence {
+    pub block_number: u64,
+    /// Index of the event in its block, or `None` for a snapshot check
+    pub log_index: Option<u64>,
+    pub pool_id: PoolId,
+    pub mismatch: Mismatch,
+}
+
+/// Reconstructs pool state by replaying `PoolManager` events through a crate
+/// `PoolManager`
+///
+/// Events must be applied in chain order, starting with the `Initialize` of
+/// every pool they touch. Swap events carry the resulting price, tick and
+/// liquidity, so each swap is cross-checked as it is replayed. Hooks are not
+/// replayed: pools whose hooks return deltas or override fees beyond the
+/// logged swap fee will diverge.
+pub struct Replayer {
+    manager: PoolManager,
+    /// Keys of the replayed pools by their on-chain ID
+    pools: HashMap<PoolId, ManagerPoolKey>,
+    events_applied: usize,
+}
+
+impl Default for Replayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Replayer {
+    /// Creates a replayer with an empty manager
+    pub fn new() -> Self {
+        Self { manager: PoolManager::new(), pools: HashMap::new(), events_applied: 0 }
+    }
+
+    /// The reconstructed manager
+    pub fn manager(&self) -> &PoolManager {
+        &self.manager
+    }
+
+    /// The reconstructed manager, for changes the events don't make
+    pub(super) fn manager_mut(&mut self) -> &mut PoolManager {
+        &mut self.manager
+    }
+
+    /// Number of events applied so far
+    pub fn events_applied(&self) -> usize {
+        self.events_applied
+    }
+
+    /// On-chain IDs of the replayed pools, sorted
+    pub fn pool_ids(&self) -> Vec<PoolId> {
+        let mut ids: Vec<_> = self.pools.keys().copied().collect();
+        ids.sort();
+        ids
+    }
+
+    /// Gets the key of a replayed pool by its on-chain ID
+    pub fn pool_key(&self, pool_id: &PoolId) -> Option<&ManagerPoolKey> {
+        self.pools.get(pool_id)
+    }
+
+    /// Gets the reconstructed state of a pool by its on-chain ID
+    pub fn snapshot(&self, pool_id: &PoolId) -> Option<PoolSnapshot> {
+        self.manager.get_pool(self.pools.get(pool_id)?).map(PoolSnapshot::of)
+    }
+
+    /// Starts replaying a pool from its state read from the chain, instead
+    /// of from its `Initialize` event
+    pub fn fork_pool(&mut self, key: ManagerPoolKey, state: &PoolState) -> Result<()> {
+        let pool = state.to_pool(key.tick_spacing()).map_err(|e| ReplayError::InvalidState(e.to_string()))?;
+        self.manager
+            .initialize_pool(key.clone(), pool.slot0.sqrt_price_x96)
+            .map_err(|e| ReplayError::InvalidState(e.to_string()))?;
+        *self.manager.get_pool_mut(&key).expect("pool was just initialized") = pool;
+        self.pools.insert(state.pool_id, key);
+        Ok(())
+    }
+
+    /// Compares the reconstructed state of a pool with a state read from the chain
+    pub fn check(&self, block_number: u64, pool_id: PoolId, expected: PoolSnapshot) -> Result<Option<ReplayDivergence>> {
+        let actual = self.snapshot(&pool_id).ok_or(ReplayError::UnknownPool(pool_id))?;
+        Ok((actual != expected).then_some(ReplayDivergence {
+            block_number,
+            log_index: None,
+            pool_id,
+            mismatch: Mismatch::State { expected, actual },
+        }))
+    }
+
+    /// Applies an event, returning a divergence if the engine disagrees with it
+    ///
+    /// Fails only for events that cannot be replayed at all, such as events
+    /// of a pool whose `Initialize` was not applied or was rejected.
+    pub fn apply(&mut self, logged: &LoggedEvent) -> Result<Option<ReplayDivergence>> {
+        let pool_id = logged.pool_id();
+        let mismatch = match &logged.event {
+            PoolEvent::InitializeFilter(event) => {
+                let tick_spacing = TickSpacing::new(event.tick_spacing)
+                    .map_err(|_| ReplayError::InvalidEvent(format!("tick spacing {}", event.tick_spacing)))?;
+                let key = ManagerPoolKey::new(
+                    event.currency_0,
+                    event.currency_1,
+                    event.fee,
+                    tick_spacing,
+                    event.hooks,
+                )
+                    .map_err(|e| ReplayError::InvalidEvent(e.to_string()))?;
+                let expected = PoolSnapshot { sqrt_price_x96: event.sqrt_price_x96, tick: event.tick, liquidity: 0 };
+                match self.manager.initialize_pool(key.clone(), SqrtPrice::new(event.sqrt_price_x96)) {
+                    Ok(_) => {
+                        self.pools.insert(pool_id, key);
+                        self.state_mismatch(&pool_id, expected)
+                    }
+                    Err(e) => Some(Mismatch::Rejected(e.to_string())),
+                }
+            }
+            PoolEvent::ModifyLiquidityFilter(event) => {
+                let key = self.key(&pool_id)?;
+                let liquidity_delta = i128::try_from(event.liquidity_delta)
+                    .map_err(|_| ReplayError::InvalidEvent(format!("liquidity delta {}", event.liquidity_delta)))?;
+                let params = ModifyLiquidityParams {
+                    owner: event.sender,
+                    tick_lower: event.tick_lower,
+                    tick_upper: event.tick_upper,
+                    liquidity_delta,
+                    salt: event.salt.into(),
+                };
+                self.manager.modify_liquidity(key, params, &[]).err().map(|e| Mismatch::Rejected(e.to_string()))
+            }
+            PoolEvent::SwapFilter(event) => {
+                let key = self.key(&pool_id)?;
+                let expected = PoolSnapshot {
+                    sqrt_price_x96: event.sqrt_price_x96,
+                    tick: event.tick,
+                    liquidity: event.liquidity,
+                };
+                match self.replay_swap(&key, event.amount_0, event.amount_1, event.sqrt_price_x96, event.fee) {
+                    Ok(()) => self.state_mismatch(&pool_id, expected),
+                    Err(e) => Some(Mismatch::Rejected(e)),
+                }
+            }
+            PoolEvent::DonateFilter(event) => {
+                let key = self.key(&pool_id)?;
+                let amounts = (u128::try_from(event.amount_0), u128::try_from(event.amount_1));
+                let (Ok(amount0), Ok(amount1)) = amounts else {
+                    return Err(ReplayError::InvalidEvent(format!("donation {} {}", event.amount_0, event.amount_1)));
+                };
+                let pool = self.manager.get_pool_mut(&key).ok_or(ReplayError::UnknownPool(pool_id))?;
+                pool.donate(amount0, amount1).err().map(|e| Mismatch::Rejected(e.to_string()))
+            }
+        };
+        self.events_applied += 1;
+
+        Ok(mismatch.map(|mismatch| ReplayDivergence {
+            block_number: logged.block_number,
+            log_index: Some(logged.log_index),
+            pool_id,
+            mismatch,
+        }))
+    }
+
+    /// Applies events in order, collecting the divergences
+    pub fn apply_all<'a>(&mut self, events: impl IntoIterator<Item = &'a LoggedEvent>) -> Result<Vec<ReplayDivergence>> {
+        let mut divergences = Vec::new();
+        for event in events {
+            divergences.extend(self.apply(event)?);
+        }
+        Ok(divergences)
+    }
+
+    fn key(&self, pool_id: &PoolId) -> Result<ManagerPoolKey> {
+        self.pools.get(pool_id).cloned().ok_or(ReplayError::UnknownPool(*pool_id))
+    }
+
+    fn state_mismatch(&self, pool_id: &PoolId, expected: PoolSnapshot) -> Option<Mismatch> {
+        let actual = self.snapshot(pool_id).unwrap_or_default();
+        (actual != expected).then_some(Mismatch::State { expected, actual })
+    }
+
+    /// Replays a logged swap as an exact-input swap of the amount the swapper
+    /// paid, limited at the logged price so rounding cannot overshoot it
+    ///
+    /// The logged fee is the total swap fee, which equals the LP fee when the
+    /// pool charges no protocol fee.
+    fn replay_swap(&mut self, key: &ManagerPoolKey, amount0: i128, amount1: i128, sqrt_price_x96: U256, fee: u32) -> std::result::Result<(), String> {
+        let zero_for_one = amount0 < 0;
+        let amount_in = if zero_for_one { amount0 } else { amount1 };
+        if amount_in >= 0 {
+            // Nothing was paid in, so the swap did not move the pool
+            return Ok(());
+        }
+        let pool = self.manager.get_pool_mut(key).ok_or("Pool not initialized")?;
+        let current = pool.slot0.sqrt_price_x96.to_u256();
+        let moves_toward_limit = if zero_for_one { sqrt_price_x96 < current } else { sqrt_price_x96 > current };
+        let limit = if moves_toward_limit {
+            sqrt_price_x96
+        } else if zero_for_one {
+            TickMath::MIN_SQRT_PRICE + 1
+        } else {
+            TickMath::MAX_SQRT_PRICE - 1
+        };
+        pool.swap(amount_in, SqrtPrice::new(limit), zero_for_one, key.tick_spacing(), Some(FeePips::new(fee)))
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::{Address, I256};
+    use crate::core::state::Salt;
+    use super::super::{InitializeFilter, ModifyLiquidityFilter, SwapFilter};
+
+    const POOL_ID: PoolId = PoolId([9; 32]);
+
+    fn logged(block_number: u64, event: PoolEvent) -> LoggedEvent {
+        LoggedEvent { block_number, log_index: 0, event }
+    }
+
+    /// Events of a pool, as the chain would log them, produced by running the
+    /// same operations on a separate manager
+    fn chain_events() -> Vec<LoggedEvent> {
+        let sender = Address::repeat_byte(0xaa);
+        let key = ManagerPoolKey::new(
+            Address::from_low_u64_be(1),
+            Address::from_low_u64_be(2),
+            3000,
+            TickSpacing::new(60).unwrap(),
+            Address::zero(),
+        ).unwrap();
+        let mut chain = PoolManager::new();
+        let tick = chain.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+        let mut events = vec![logged(1, PoolEvent::InitializeFilter(InitializeFilter {
+            id: POOL_ID.0,
+            currency_0: key.token0(),
+            currency_1: key.token1(),
+            fee: key.fee(),
+            tick_spacing: 60,
+            hooks: key.hooks(),
+            sqrt_price_x96: SqrtPrice::ONE.to_u256(),
+            tick,
+        }))];
+
+        let params = ModifyLiquidityParams { owner: sender, tick_lower: -600, tick_upper: 600, liquidity_delta: 1_000_000_000, salt: Salt::ZERO };
+        chain.modify_liquidity(key.clone(), params.clone(), &[]).unwrap();
+        events.push(logged(2, PoolEvent::ModifyLiquidityFilter(ModifyLiquidityFilter {
+            id: POOL_ID.0,
+            sender,
+            tick_lower: params.tick_lower,
+            tick_upper: params.tick_upper,
+            liquidity_delta: I256::from(params.liquidity_delta),
+            salt: params.salt.into(),
+        })));
+
+        for (block_number, zero_for_one, amount) in [(3, true, -100_000), (4, false, -250_000)] {
+            let limit = if zero_for_one { TickMath::MIN_SQRT_PRICE + 1 } else { TickMath::MAX_SQRT_PRICE - 1 };
+            let delta = chain.swap(&key, zero_for_one, amount, limit, &[]).unwrap();
+            let state = PoolSnapshot::of(chain.get_pool(&key).unwrap());
+            events.push(logged(block_number, PoolEvent::SwapFilter(SwapFilter {
+                id: POOL_ID.0,
+                sender,
+                amount_0: delta.amount0(),
+                amount_1: delta.amount1(),
+                sqrt_price_x96: state.sqrt_price_x96,
+                liquidity: state.liquidity,
+                tick: state.tick,
+                fee: 3000,
+            })));
+        }
+        events
+    }
+
+    #[test]
+    fn test_replay_matches_chain() {
+        let events = chain_events();
+        let mut replayer = Replayer::new();
+        assert!(replayer.apply_all(&events).unwrap().is_empty());
+        assert_eq!(replayer.events_applied(), 4);
+        assert_eq!(replayer.pool_ids(), vec![POOL_ID]);
+
+        let PoolEvent::SwapFilter(last) = &events[3].event else { unreachable!() };
+        let expected = PoolSnapshot { sqrt_price_x96: last.sqrt_price_x96, tick: last.tick, liquidity: last.liquidity };
+        assert_eq!(replayer.snapshot(&POOL_ID), Some(expected));
+        assert_eq!(replayer.check(4, POOL_ID, expected).unwrap(), None);
+        assert!(replayer.check(4, POOL_ID, PoolSnapshot { liquidity: 1, ..expected }).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_replay_reports_divergences() {
+        let mut events = chain_events();
+        let PoolEvent::SwapFilter(swap) = &mut events[2].event else { unreachable!() };
+        swap.tick -= 1;
+
+        let mut replayer = Replayer::new();
+        let divergences = replayer.apply_all(&events).unwrap();
+        assert_eq!(divergences.len(), 1);
+        assert_eq!(divergences[0].block_number, 3);
+        assert!(matches!(
+            divergences[0].mismatch,
+            Mismatch::State { expected, actual } if expected.tick == actual.tick - 1
+        ));
+
+        // Removing liquidity that was never added is rejected by the engine
+        let PoolEvent::ModifyLiquidityFilter(mut burn) = events[1].event.clone() else { unreachable!() };
+        burn.liquidity_delta = I256::from(-2_000_000_000i128);
+        let divergence = replayer.apply(&logged(5, PoolEvent::ModifyLiquidityFilter(burn))).unwrap().unwrap();
+        assert!(matches!(divergence.mismatch, Mismatch::Rejected(_)));
+    }
+
+    #[test]
+    fn test_replay_requires_initialize() {
+        let events = chain_events();
+        let mut replayer = Replayer::new();
+        assert!(matches!(replayer.apply(&events[1]), Err(ReplayError::UnknownPool(id)) if id == POOL_ID));
+    }
+}
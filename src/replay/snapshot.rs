@@ -0,0 +1,393 @@
+//! Full pool state at a block, read in batches
+//!
+//! [`fetch_snapshot`](super::fetch_snapshot) reads the three values a replay
+//! check compares. [`fetch_pool_state`] reads everything a pool holds at a
+//! block: its `Slot0`, fee growth and liquidity, every initialized tick found
+//! by scanning its tick bitmap, and the positions asked for. Reads go through
+//! the `PoolManager`'s `extsload(bytes32[])`, which returns many storage slots
+//! in one call, so a pool costs a handful of requests however many ticks it
+//! has. All of them are pinned to the block's hash, and the snapshot records
+//! the block's number, hash and timestamp so it can be fetched again and
+//! compared.
+
+use std::sync::Arc;
+
+use ethers::{
+    providers::Middleware,
+    types::{Address, BlockId, H256, U256},
+    utils::keccak256,
+};
+
+use crate::core::{
+    math::{tick_math::TickMath, types::{Liquidity, SqrtPrice, TickSpacing}, FeePips},
+    pool_manager::PoolId,
+    state::{PackedSlot0, Pool, Position, PositionKey, Result as StateResult, Slot0, TickInfo},
+};
+
+use super::{
+    pool_state_slot, position_id, IPoolManager, PoolSnapshot, ReplayError, Result, LIQUIDITY_OFFSET,
+};
+
+/// Offset of a pool's `ticks` mapping from the start of its state
+pub const TICKS_OFFSET: u64 = 4;
+
+/// Offset of a pool's `tickBitmap` mapping from the start of its state
+pub const TICK_BITMAP_OFFSET: u64 = 5;
+
+/// Offset of a pool's `positions` mapping from the start of its state
+pub const POSITIONS_OFFSET: u64 = 6;
+
+/// Block a snapshot was read at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Provenance {
+    pub block_number: u64,
+    pub block_hash: H256,
+    pub timestamp: u64,
+}
+
+/// An initialized tick as stored on chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TickState {
+    pub tick: i32,
+    pub liquidity_gross: u128,
+    pub liquidity_net: i128,
+    pub fee_growth_outside_0_x128: U256,
+    pub fee_growth_outside_1_x128: U256,
+}
+
+/// A position as stored on chain
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PositionState {
+    pub key: PositionKey,
+    pub liquidity: u128,
+    pub fee_growth_inside_0_last_x128: U256,
+    pub fee_growth_inside_1_last_x128: U256,
+}
+
+/// Everything a pool holds at a block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoolState {
+    pub pool_id: PoolId,
+    pub sqrt_price_x96: U256,
+    pub tick: i32,
+    pub protocol_fee: u32,
+    pub lp_fee: u32,
+    pub fee_growth_global_0_x128: U256,
+    pub fee_growth_global_1_x128: U256,
+    pub liquidity: u128,
+    /// Initialized ticks, in ascending order
+    pub ticks: Vec<TickState>,
+    /// Positions asked for, in the order asked; empty positions are kept
+    pub positions: Vec<PositionState>,
+    pub provenance: Provenance,
+}
+
+impl PoolState {
+    /// The part of the state replay checks compare
+    pub fn snapshot(&self) -> PoolSnapshot {
+        PoolSnapshot { sqrt_price_x96: self.sqrt_price_x96, tick: self.tick, liquidity: self.liquidity }
+    }
+
+    /// Builds a crate pool holding the state, to simulate on top of it
+    ///
+    /// The pool keeps the chain's tick rather than recomputing it from the
+    /// price, and only the positions read with the state. The tick spacing
+    /// is the pool key's, to rebuild the tick bitmap.
+    pub fn to_pool(&self, tick_spacing: TickSpacing) -> StateResult<Pool> {
+        let mut pool = Pool::new();
+        pool.initialize(SqrtPrice::new(self.sqrt_price_x96), FeePips::new(self.lp_fee))?;
+        pool.set_protocol_fee(self.protocol_fee)?;
+        pool.slot0.tick = self.tick;
+        pool.fee_growth_global_0_x128 = self.fee_growth_global_0_x128;
+        pool.fee_growth_global_1_x128 = self.fee_growth_global_1_x128;
+        pool.liquidity = Liquidity::new(self.liquidity);
+        for tick in &self.ticks {
+            pool.tick_manager.restore_tick(tick.tick, TickInfo {
+                liquidity_gross: Liquidity::new(tick.liquidity_gross),
+                liquidity_net: tick.liquidity_net,
+                fee_growth_outside_0_x128: tick.fee_growth_outside_0_x128,
+                fee_growth_outside_1_x128: tick.fee_growth_outside_1_x128,
+            }, tick_spacing)?;
+        }
+        for position in self.positions.iter().filter(|position| position.liquidity > 0) {
+            pool.position_manager.insert(position.key.clone(), Position {
+                liquidity: Liquidity::new(position.liquidity),
+                fee_growth_inside_0_last_x128: position.fee_growth_inside_0_last_x128,
+                fee_growth_inside_1_last_x128: position.fee_growth_inside_1_last_x128,
+                ..Default::default()
+            });
+        }
+        Ok(pool)
+    }
+}
+
+/// Options for fetching pool state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SnapshotOptions {
+    /// Storage slots per `extsload` call
+    pub batch_size: usize,
+}
+
+impl Default for SnapshotOptions {
+    fn default() -> Self {
+        Self { batch_size: 500 }
+    }
+}
+
+/// Storage slot `offset` slots into a pool's state
+pub fn pool_field_slot(pool_id: PoolId, offset: u64) -> H256 {
+    slot_at(pool_state_slot(pool_id), offset)
+}
+
+/// Storage slot of the value of a signed key in a mapping, as Solidity lays
+/// it out for `int24` and `int16` keys
+fn mapping_slot(key: i32, mapping: H256) -> H256 {
+    let mut preimage = [if key < 0 { 0xff } else { 0 }; 64];
+    preimage[28..32].copy_from_slice(&key.to_be_bytes());
+    preimage[32..].copy_from_slice(mapping.as_bytes());
+    H256(keccak256(preimage))
+}
+
+/// Storage slot of a tick's info, the first of three
+pub fn tick_slot(pool_id: PoolId, tick: i32) -> H256 {
+    mapping_slot(tick, pool_field_slot(pool_id, TICKS_OFFSET))
+}
+
+/// Storage slot of a word of a pool's tick bitmap
+pub fn tick_bitmap_slot(pool_id: PoolId, word_pos: i16) -> H256 {
+    mapping_slot(word_pos.into(), pool_field_slot(pool_id, TICK_BITMAP_OFFSET))
+}
+
+/// Storage slot of a position's state, the first of three
+pub fn position_slot(pool_id: PoolId, key: &PositionKey) -> H256 {
+    let mut preimage = [0u8; 64];
+    preimage[..32].copy_from_slice(position_id(key).as_bytes());
+    preimage[32..].copy_from_slice(pool_field_slot(pool_id, POSITIONS_OFFSET).as_bytes());
+    H256(keccak256(preimage))
+}
+
+fn slot_at(base: H256, offset: u64) -> H256 {
+    let slot = U256::from_big_endian(base.as_bytes()).overflowing_add(U256::from(offset)).0;
+    let mut word = [0u8; 32];
+    slot.to_big_endian(&mut word);
+    H256(word)
+}
+
+/// Positions of the tick bitmap words that can hold usable ticks of the spacing
+pub fn tick_bitmap_words(tick_spacing: i32) -> std::ops::RangeInclusive<i16> {
+    let word = |tick: i32| (tick.div_euclid(tick_spacing) >> 8) as i16;
+    word(TickMath::min_usable_tick(tick_spacing))..=word(TickMath::max_usable_tick(tick_spacing))
+}
+
+/// Initialized ticks flagged in a bitmap word, in ascending order
+pub fn word_ticks(word_pos: i16, word: U256, tick_spacing: i32) -> impl Iterator<Item = i32> {
+    (0..256).filter(move |bit| word.bit(*bit)).map(move |bit| (i32::from(word_pos) * 256 + bit as i32) * tick_spacing)
+}
+
+/// Reads many storage slots at a block, `batch_size` per call
+async fn load_slots<M: Middleware + 'static>(
+    contract: &IPoolManager<M>,
+    slots: &[H256],
+    block: BlockId,
+    batch_size: usize,
+) -> Result<Vec<U256>> {
+    let mut values = Vec::with_capacity(slots.len());
+    for batch in slots.chunks(batch_size.max(1)) {
+        let words = contract
+            .extsload_with_slots(batch.iter().map(|slot| slot.0).collect())
+            .block(block)
+            .call()
+            .await
+            .map_err(|e| ReplayError::Provider(e.to_string()))?;
+        if words.len() != batch.len() {
+            return Err(ReplayError::Provider(format!("extsload returned {} of {} slots", words.len(), batch.len())));
+        }
+        values.extend(words.iter().map(|word| U256::from_big_endian(word)));
+    }
+    Ok(values)
+}
+
+/// Reads the full state of a pool, and the positions given, at the end of a
+/// block
+///
+/// The tick spacing tells which bitmap words to scan; it is the pool key's,
+/// as in its `Initialize` event.
+pub async fn fetch_pool_state<M: Middleware + 'static>(
+    client: Arc<M>,
+    manager: Address,
+    pool_id: PoolId,
+    tick_spacing: i32,
+    positions: &[PositionKey],
+    block_number: u64,
+    options: SnapshotOptions,
+) -> Result<PoolState> {
+    let block = client
+        .get_block(block_number)
+        .await
+        .map_err(|e| ReplayError::Provider(e.to_string()))?
+        .ok_or_else(|| ReplayError::Provider(format!("block {block_number} not found")))?;
+    let block_hash = block.hash.ok_or_else(|| ReplayError::Provider(format!("block {block_number} is pending")))?;
+    let provenance = Provenance { block_number, block_hash, timestamp: block.timestamp.low_u64() };
+    let at = BlockId::Hash(block_hash);
+    let contract = IPoolManager::new(manager, client);
+
+    // Slot0, the fee growths, the liquidity and the bitmap, then the ticks
+    // the bitmap flags and the positions
+    let words = tick_bitmap_words(tick_spacing);
+    let mut slots: Vec<_> = (0..=LIQUIDITY_OFFSET).map(|offset| pool_field_slot(pool_id, offset)).collect();
+    slots.extend(words.clone().map(|word_pos| tick_bitmap_slot(pool_id, word_pos)));
+    let values = load_slots(&contract, &slots, at, options.batch_size).await?;
+    let (fields, bitmap) = values.split_at(LIQUIDITY_OFFSET as usize + 1);
+    let ticks: Vec<i32> = words
+        .zip(bitmap)
+        .flat_map(|(word_pos, word)| word_ticks(word_pos, *word, tick_spacing))
+        .collect();
+
+    let three = |first: H256| (0..3).map(move |offset| slot_at(first, offset));
+    let mut slots: Vec<_> = ticks.iter().flat_map(|tick| three(tick_slot(pool_id, *tick))).collect();
+    slots.extend(positions.iter().flat_map(|key| three(position_slot(pool_id, key))));
+    let values = load_slots(&contract, &slots, at, options.batch_size).await?;
+    let (tick_values, position_values) = values.split_at(ticks.len() * 3);
+
+    let slot0 = Slot0::unpack(PackedSlot0(fields[0]))
+        .map_err(|err| ReplayError::InvalidState(format!("pool {pool_id}: {err}")))?;
+    Ok(PoolState {
+        pool_id,
+        sqrt_price_x96: slot0.sqrt_price_x96.to_u256(),
+        tick: slot0.tick,
+        protocol_fee: slot0.protocol_fee,
+        lp_fee: slot0.lp_fee.get(),
+        fee_growth_global_0_x128: fields[1],
+        fee_growth_global_1_x128: fields[2],
+        liquidity: fields[3].low_u128(),
+        ticks: ticks
+            .iter()
+            .zip(tick_values.chunks(3))
+            .map(|(tick, info)| TickState {
+                tick: *tick,
+                liquidity_gross: info[0].low_u128(),
+                liquidity_net: (info[0] >> 128).low_u128() as i128,
+                fee_growth_outside_0_x128: info[1],
+                fee_growth_outside_1_x128: info[2],
+            })
+            .collect(),
+        positions: positions
+            .iter()
+            .zip(position_values.chunks(3))
+            .map(|(key, state)| PositionState {
+                key: key.clone(),
+                liquidity: state[0].low_u128(),
+                fee_growth_inside_0_last_x128: state[1],
+                fee_growth_inside_1_last_x128: state[2],
+            })
+            .collect(),
+        provenance,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::{
+        abi::{encode, Token},
+        providers::Provider,
+        types::{Block, Bytes, I256},
+    };
+
+    fn word(value: U256) -> Token {
+        let mut word = [0u8; 32];
+        value.to_big_endian(&mut word);
+        Token::FixedBytes(word.to_vec())
+    }
+
+    #[test]
+    fn test_mapping_slots_match_solidity() {
+        let id = PoolId([3u8; 32]);
+        let ticks = U256::from_big_endian(pool_state_slot(id).as_bytes()) + 4;
+        let expected = keccak256(encode(&[Token::Int(I256::from(-60).into_raw()), Token::Uint(ticks)]));
+        assert_eq!(tick_slot(id, -60), H256(expected));
+        let bitmap = U256::from_big_endian(pool_field_slot(id, TICK_BITMAP_OFFSET).as_bytes());
+        let expected = keccak256(encode(&[Token::Int(7.into()), Token::Uint(bitmap)]));
+        assert_eq!(tick_bitmap_slot(id, 7), H256(expected));
+
+        assert_eq!(tick_bitmap_words(60), -58..=57);
+        assert_eq!(tick_bitmap_words(TickMath::MAX_TICK_SPACING), -1..=0);
+        let flagged = (U256::one() << 255) | U256::one();
+        assert_eq!(word_ticks(-1, flagged, 10).collect::<Vec<_>>(), vec![-2560, -10]);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_pool_state() {
+        let manager = Address::repeat_byte(0x44);
+        let pool_id = PoolId([5u8; 32]);
+        let tick_spacing = TickMath::MAX_TICK_SPACING;
+        let block_hash = H256::repeat_byte(0xbb);
+        let position = PositionKey { owner: [9; 20], tick_lower: -tick_spacing, tick_upper: tick_spacing, salt: [0; 32] };
+
+        // Responses are served last in, first out: the block, the pool's
+        // fields and bitmap words -1 and 0, then both ticks and the position
+        let (provider, mock) = Provider::mocked();
+        let tick_info = |net: i128| vec![
+            word(U256::from(1000u64) | (U256::from(net as u128) << 128)),
+            word(U256::from(11)),
+            word(U256::from(12)),
+        ];
+        let second: Vec<_> = [tick_info(1000), tick_info(-1000), vec![word(1000.into()), word(21.into()), word(22.into())]].concat();
+        mock.push::<Bytes, _>(Bytes::from(encode(&[Token::Array(second)]))).unwrap();
+        let slot0 = SqrtPrice::ONE.to_u256() | (U256::from(3000) << 208) | (U256::from(0x00_0100_u32) << 184);
+        let first = vec![
+            word(slot0),
+            word(1.into()),
+            word(2.into()),
+            word(1000.into()),
+            word(U256::one() << 255),
+            word(U256::one() << 1),
+        ];
+        mock.push::<Bytes, _>(Bytes::from(encode(&[Token::Array(first)]))).unwrap();
+        let block = Block::<H256> { hash: Some(block_hash), timestamp: 1_700_000_000.into(), ..Default::default() };
+        mock.push::<Block<H256>, _>(block).unwrap();
+
+        let options = SnapshotOptions::default();
+        let state = fetch_pool_state(Arc::new(provider), manager, pool_id, tick_spacing, std::slice::from_ref(&position), 100, options)
+            .await
+            .unwrap();
+        assert_eq!(state.provenance, Provenance { block_number: 100, block_hash, timestamp: 1_700_000_000 });
+        assert_eq!(state.snapshot(), PoolSnapshot { sqrt_price_x96: SqrtPrice::ONE.to_u256(), tick: 0, liquidity: 1000 });
+        assert_eq!((state.lp_fee, state.protocol_fee), (3000, 0x100));
+        assert_eq!((state.fee_growth_global_0_x128, state.fee_growth_global_1_x128), (1.into(), 2.into()));
+        assert_eq!(state.ticks, vec![
+            TickState {
+                tick: -tick_spacing,
+                liquidity_gross: 1000,
+                liquidity_net: 1000,
+                fee_growth_outside_0_x128: 11.into(),
+                fee_growth_outside_1_x128: 12.into(),
+            },
+            TickState {
+                tick: tick_spacing,
+                liquidity_gross: 1000,
+                liquidity_net: -1000,
+                fee_growth_outside_0_x128: 11.into(),
+                fee_growth_outside_1_x128: 12.into(),
+            },
+        ]);
+        assert_eq!(state.positions, vec![PositionState {
+            key: position,
+            liquidity: 1000,
+            fee_growth_inside_0_last_x128: 21.into(),
+            fee_growth_inside_1_last_x128: 22.into(),
+        }]);
+
+        // The state can be simulated on as a crate pool
+        let spacing = TickSpacing::new(tick_spacing).unwrap();
+        let pool = state.to_pool(spacing).unwrap();
+        assert_eq!(PoolSnapshot::of(&pool), state.snapshot());
+        assert!(pool.tick_manager.tick_bitmap().is_initialized(-tick_spacing, spacing));
+        assert_eq!(pool.tick_manager.next_initialized_tick_within_one_word(0, spacing, false).unwrap(), (tick_spacing, true));
+        assert_eq!(pool.slot0.lp_fee, FeePips::new(3000));
+        let lower = pool.tick_manager.get_tick(-tick_spacing).unwrap();
+        assert_eq!((lower.liquidity_net, lower.fee_growth_outside_0_x128), (1000, 11.into()));
+        let position = pool.position_manager.get(&state.positions[0].key).unwrap();
+        assert_eq!((position.liquidity.as_u128(), position.fee_growth_inside_1_last_x128), (1000, 22.into()));
+    }
+}
@@ -0,0 +1,250 @@
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use ethers::{
+    providers::Middleware,
+    types::{Address, Filter, H256, U256},
+    utils::keccak256,
+};
+
+use crate::core::pool_manager::{ManagerPoolKey, PoolId};
+use crate::core::state::PackedSlot0;
+
+use super::{event_signatures, IPoolManager, LoggedEvent, PoolSnapshot, ReplayDivergence, ReplayError, Replayer, Result};
+
+/// Storage slot of the `pools` mapping in the v4 `PoolManager`
+pub const POOLS_SLOT: u64 = 6;
+
+/// Offset of a pool's liquidity from the start of its state
+pub const LIQUIDITY_OFFSET: u64 = 3;
+
+/// ID of a pool in the v4 `PoolManager`, the hash of its ABI-encoded key,
+/// which the crate's [`PoolId::from_key`] also uses
+pub fn onchain_pool_id(key: &ManagerPoolKey) -> PoolId {
+    PoolId::from_key(key)
+}
+
+/// Storage slot of a pool's state, which starts with its packed `Slot0`
+pub fn pool_state_slot(pool_id: PoolId) -> H256 {
+    let mut preimage = [0u8; 64];
+    preimage[..32].copy_from_slice(pool_id.as_bytes());
+    U256::from(POOLS_SLOT).to_big_endian(&mut preimage[32..]);
+    H256(keccak256(preimage))
+}
+
+/// Storage slot of a pool's active liquidity
+pub fn liquidity_slot(pool_id: PoolId) -> H256 {
+    let slot = U256::from_big_endian(pool_state_slot(pool_id).as_bytes()) + LIQUIDITY_OFFSET;
+    let mut word = [0u8; 32];
+    slot.to_big_endian(&mut word);
+    H256(word)
+}
+
+/// Unpacks the sqrt price and tick from a `Slot0` storage word, unchecked
+pub fn decode_slot0(word: H256) -> (U256, i32) {
+    let packed = PackedSlot0::from_be_bytes(word.0);
+    (packed.sqrt_price_x96(), packed.tick())
+}
+
+/// Options for replaying a block range from a node
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReplayOptions {
+    /// Blocks per `eth_getLogs` request
+    pub chunk_size: u64,
+    /// Minimum number of blocks between snapshot checks, or 0 to only check swaps
+    pub check_interval: u64,
+}
+
+impl Default for ReplayOptions {
+    fn default() -> Self {
+        Self { chunk_size: 2_000, check_interval: 1_000 }
+    }
+}
+
+/// Fetches the pool events of a `PoolManager` in a block range, in chain order
+pub async fn fetch_events<M: Middleware>(
+    client: &M,
+    manager: Address,
+    from_block: u64,
+    to_block: u64,
+    chunk_size: u64,
+) -> Result<Vec<LoggedEvent>> {
+    let mut events = Vec::new();
+    let mut start = from_block;
+    while start <= to_block {
+        let end = to_block.min(start.saturating_add(chunk_size.max(1) - 1));
+        let filter = Filter::new()
+            .address(manager)
+            .topic0(event_signatures())
+            .from_block(start)
+            .to_block(end);
+        let logs = client.get_logs(&filter).await.map_err(|e| ReplayError::Provider(e.to_string()))?;
+        events.extend(logs.iter().filter_map(LoggedEvent::from_log));
+        if end == u64::MAX {
+            break;
+        }
+        start = end + 1;
+    }
+    events.sort_by_key(|event| (event.block_number, event.log_index));
+    Ok(events)
+}
+
+/// Reads the state of a pool at the end of a block with `extsload`
+pub async fn fetch_snapshot<M: Middleware + 'static>(
+    client: Arc<M>,
+    manager: Address,
+    pool_id: PoolId,
+    block_number: u64,
+) -> Result<PoolSnapshot> {
+    let contract = IPoolManager::new(manager, client);
+    let load = |slot: H256| contract.extsload(slot.0).block(block_number);
+    let slot0 = load(pool_state_slot(pool_id)).call().await.map_err(|e| ReplayError::Provider(e.to_string()))?;
+    let liquidity = load(liquidity_slot(pool_id)).call().await.map_err(|e| ReplayError::Provider(e.to_string()))?;
+    let (sqrt_price_x96, tick) = decode_slot0(H256(slot0));
+    Ok(PoolSnapshot {
+        sqrt_price_x96,
+        tick,
+        liquidity: U256::from_big_endian(&liquidity).low_u128(),
+    })
+}
+
+/// Replays the events of a block range into `replayer`, checking the pools
+/// it touched against on-chain snapshots
+///
+/// Snapshots are taken at the end of a block once `check_interval` blocks
+/// have passed since the last check, and at the last block with events, so
+/// every check sees all of its block's events applied.
+pub async fn replay_range<M: Middleware + 'static>(
+    replayer: &mut Replayer,
+    client: Arc<M>,
+    manager: Address,
+    from_block: u64,
+    to_block: u64,
+    options: ReplayOptions,
+) -> Result<Vec<ReplayDivergence>> {
+    let events = fetch_events(&*client, manager, from_block, to_block, options.chunk_size).await?;
+    let mut divergences = Vec::new();
+    let mut touched = BTreeSet::new();
+    let mut last_check = from_block;
+    for (index, event) in events.iter().enumerate() {
+        divergences.extend(replayer.apply(event)?);
+        touched.insert(event.pool_id());
+
+        let next_block = events.get(index + 1).map(|next| next.block_number);
+        let block_complete = next_block != Some(event.block_number);
+        let due = next_block.is_none() || event.block_number >= last_check.saturating_add(options.check_interval);
+        if options.check_interval == 0 || !block_complete || !due {
+            continue;
+        }
+        for pool_id in std::mem::take(&mut touched) {
+            // Pools whose initialization the engine rejected have no state to compare
+            if replayer.pool_key(&pool_id).is_none() {
+                continue;
+            }
+            let snapshot = fetch_snapshot(client.clone(), manager, pool_id, event.block_number).await?;
+            divergences.extend(replayer.check(event.block_number, pool_id, snapshot)?);
+        }
+        last_check = event.block_number;
+    }
+    Ok(divergences)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::{
+        abi::{encode, Token},
+        contract::EthEvent,
+        providers::Provider,
+        types::{Bytes, Log, U64},
+    };
+    use crate::core::math::types::{SqrtPrice, TickSpacing};
+    use super::super::{InitializeFilter, Mismatch};
+
+    #[test]
+    fn test_decode_slot0() {
+        let sqrt_price_x96 = SqrtPrice::ONE.to_u256();
+        let tick = -887_272i32;
+        let word = sqrt_price_x96 | (U256::from(tick as u32 & 0xFF_FFFF) << 160) | (U256::from(3000) << 208);
+        let mut bytes = [0u8; 32];
+        word.to_big_endian(&mut bytes);
+        assert_eq!(decode_slot0(H256(bytes)), (sqrt_price_x96, tick));
+    }
+
+    #[test]
+    fn test_onchain_pool_id_hashes_the_key() {
+        let key = ManagerPoolKey::new(
+            Address::repeat_byte(0x11),
+            Address::repeat_byte(0x22),
+            500,
+            TickSpacing::new(10).unwrap(),
+            Address::repeat_byte(0x33),
+        ).unwrap();
+        let mut preimage = [0u8; 160];
+        preimage[12..32].copy_from_slice(&[0x11; 20]);
+        preimage[44..64].copy_from_slice(&[0x22; 20]);
+        preimage[94..96].copy_from_slice(&500u16.to_be_bytes());
+        preimage[127] = 10;
+        preimage[140..160].copy_from_slice(&[0x33; 20]);
+        assert_eq!(onchain_pool_id(&key), PoolId(keccak256(preimage)));
+    }
+
+    #[test]
+    fn test_liquidity_slot_follows_state_slot() {
+        let id = PoolId([1u8; 32]);
+        let state = U256::from_big_endian(pool_state_slot(id).as_bytes());
+        assert_eq!(U256::from_big_endian(liquidity_slot(id).as_bytes()), state + 3);
+        assert_ne!(pool_state_slot(id), pool_state_slot(PoolId([2u8; 32])));
+    }
+
+    #[tokio::test]
+    async fn test_replay_range_checks_snapshots() {
+        let manager = Address::repeat_byte(0x44);
+        let pool_id = PoolId([5u8; 32]);
+        let sqrt_price_x96 = SqrtPrice::ONE.to_u256();
+        let log = Log {
+            address: manager,
+            topics: vec![
+                InitializeFilter::signature(),
+                H256(pool_id.0),
+                H256::from(Address::from_low_u64_be(1)),
+                H256::from(Address::from_low_u64_be(2)),
+            ],
+            data: encode(&[
+                Token::Uint(3000.into()),
+                Token::Int(60.into()),
+                Token::Address(Address::zero()),
+                Token::Uint(sqrt_price_x96),
+                Token::Int(0.into()),
+            ]).into(),
+            block_number: Some(U64::from(100)),
+            log_index: Some(U256::zero()),
+            ..Default::default()
+        };
+
+        // Responses are served last in, first out: the logs, then slot0 and
+        // the liquidity of the snapshot, which reports liquidity the replay lacks
+        let (provider, mock) = Provider::mocked();
+        let word = |value: U256| {
+            let mut word = [0u8; 32];
+            value.to_big_endian(&mut word);
+            Bytes::from(encode(&[Token::FixedBytes(word.to_vec())]))
+        };
+        mock.push::<Bytes, _>(word(U256::from(7))).unwrap();
+        mock.push::<Bytes, _>(word(sqrt_price_x96)).unwrap();
+        mock.push::<Vec<Log>, _>(vec![log]).unwrap();
+
+        let mut replayer = Replayer::new();
+        let options = ReplayOptions { chunk_size: 10, check_interval: 1 };
+        let divergences = replay_range(&mut replayer, Arc::new(provider), manager, 100, 105, options).await.unwrap();
+        assert_eq!(replayer.pool_ids(), vec![pool_id]);
+        assert_eq!(divergences, vec![ReplayDivergence {
+            block_number: 100,
+            log_index: None,
+            pool_id,
+            mismatch: Mismatch::State {
+                expected: PoolSnapshot { sqrt_price_x96, tick: 0, liquidity: 7 },
+                actual: PoolSnapshot { sqrt_price_x96, tick: 0, liquidity: 0 },
+            },
+        }]);
+    }
+}
@@ -0,0 +1,376 @@
+use std::collections::{HashMap, HashSet};
+use primitive_types::{U256, U512};
+
+use crate::core::pool_manager::PoolId;
+
+use super::types::{
+    BreakerAction, CircuitBreakerConfig, RiskError, RiskEvent, TripReason, BPS_DENOMINATOR,
+};
+
+/// Price at the start of the current block for a pool
+#[derive(Debug, Clone, Copy)]
+struct BlockReference {
+    block: u64,
+    sqrt_price_x96: U256,
+}
+
+/// Volume accumulated in the current window for a pool
+#[derive(Debug, Clone, Copy)]
+struct VolumeWindow {
+    start_block: u64,
+    volume: u128,
+}
+
+/// Pause and withdraw-only state and circuit breakers for a pool manager
+///
+/// Swaps are reported through `record_swap`; when a configured limit is
+/// exceeded the breaker pauses the pool or the whole manager and records a
+/// `RiskEvent`. The swap that trips a breaker is not reverted, later
+/// operations are rejected until the pause is lifted.
+#[derive(Debug, Default)]
+pub struct RiskManager {
+    /// Current block number
+    block_number: u64,
+    /// Whether every pool is paused
+    manager_paused: bool,
+    /// Individually paused pools
+    paused_pools: HashSet<PoolId>,
+    /// Pools that are or will be withdraw-only, by the block it takes effect
+    withdraw_only: HashMap<PoolId, u64>,
+    /// Breaker applied to pools without their own configuration
+    global_breaker: Option<CircuitBreakerConfig>,
+    /// Per-pool breakers
+    pool_breakers: HashMap<PoolId, CircuitBreakerConfig>,
+    /// Reference prices for the current block
+    references: HashMap<PoolId, BlockReference>,
+    /// Volume windows
+    volumes: HashMap<PoolId, VolumeWindow>,
+    /// Event history
+    events: Vec<RiskEvent>,
+}
+
+impl RiskManager {
+    /// Creates a risk manager with nothing paused and no breakers
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Current block number
+    pub fn block_number(&self) -> u64 {
+        self.block_number
+    }
+
+    /// Moves to a later block, starting a new price reference for every pool
+    pub fn set_block_number(&mut self, block_number: u64) {
+        self.block_number = self.block_number.max(block_number);
+    }
+
+    /// Advances to the next block
+    pub fn advance_block(&mut self) -> u64 {
+        self.block_number = self.block_number.saturating_add(1);
+        self.block_number
+    }
+
+    /// Sets the breaker used by pools without their own configuration
+    pub fn set_global_breaker(&mut self, config: CircuitBreakerConfig) -> Result<(), RiskError> {
+        Self::validate(&config)?;
+        self.global_breaker = Some(config);
+        Ok(())
+    }
+
+    /// Sets the breaker of a pool
+    pub fn set_pool_breaker(&mut self, pool_id: PoolId, config: CircuitBreakerConfig) -> Result<(), RiskError> {
+        Self::validate(&config)?;
+        self.pool_breakers.insert(pool_id, config);
+        Ok(())
+    }
+
+    /// Removes the breaker of a pool, falling back to the global breaker
+    pub fn remove_pool_breaker(&mut self, pool_id: &PoolId) -> Option<CircuitBreakerConfig> {
+        self.pool_breakers.remove(pool_id)
+    }
+
+    /// Breaker in effect for a pool
+    pub fn breaker_for(&self, pool_id: &PoolId) -> Option<&CircuitBreakerConfig> {
+        self.pool_breakers.get(pool_id).or(self.global_breaker.as_ref())
+    }
+
+    fn validate(config: &CircuitBreakerConfig) -> Result<(), RiskError> {
+        if config.max_volume_per_window.is_some() && config.window_blocks == 0 {
+            return Err(RiskError::InvalidWindow);
+        }
+        Ok(())
+    }
+
+    /// Pauses every pool
+    pub fn pause_manager(&mut self) {
+        if !self.manager_paused {
+            self.manager_paused = true;
+            self.events.push(RiskEvent::ManagerPaused);
+        }
+    }
+
+    /// Lifts the manager-wide pause; individually paused pools stay paused
+    pub fn unpause_manager(&mut self) {
+        if self.manager_paused {
+            self.manager_paused = false;
+            self.events.push(RiskEvent::ManagerUnpaused);
+        }
+    }
+
+    /// Pauses a single pool
+    pub fn pause_pool(&mut self, pool_id: PoolId) {
+        if self.paused_pools.insert(pool_id) {
+            self.events.push(RiskEvent::PoolPaused { pool_id });
+        }
+    }
+
+    /// Unpauses a single pool
+    pub fn unpause_pool(&mut self, pool_id: PoolId) {
+        if self.paused_pools.remove(&pool_id) {
+            self.events.push(RiskEvent::PoolUnpaused { pool_id });
+        }
+    }
+
+    /// Whether the whole manager is paused
+    pub fn is_manager_paused(&self) -> bool {
+        self.manager_paused
+    }
+
+    /// Whether a pool is paused, either individually or by the manager pause
+    pub fn is_pool_paused(&self, pool_id: &PoolId) -> bool {
+        self.manager_paused || self.paused_pools.contains(pool_id)
+    }
+
+    /// Makes a pool withdraw-only from the current block
+    ///
+    /// Swaps, donations and new liquidity are then rejected, while LPs can
+    /// still remove their liquidity and collect their fees, e.g. for a pool
+    /// being migrated.
+    pub fn set_withdraw_only(&mut self, pool_id: PoolId) {
+        self.schedule_withdraw_only(pool_id, 0);
+    }
+
+    /// Makes a pool withdraw-only once `delay_blocks` blocks have passed, as
+    /// a timelocked governance action would, and returns the block it takes
+    /// effect
+    ///
+    /// Scheduling a pool again replaces the earlier block.
+    pub fn schedule_withdraw_only(&mut self, pool_id: PoolId, delay_blocks: u64) -> u64 {
+        let effective_block = self.block_number.saturating_add(delay_blocks);
+        self.withdraw_only.insert(pool_id, effective_block);
+        self.events.push(RiskEvent::WithdrawOnlyScheduled { pool_id, effective_block });
+        effective_block
+    }
+
+    /// Lifts the withdraw-only state of a pool, or cancels its schedule
+    pub fn lift_withdraw_only(&mut self, pool_id: PoolId) {
+        if self.withdraw_only.remove(&pool_id).is_some() {
+            self.events.push(RiskEvent::WithdrawOnlyLifted { pool_id });
+        }
+    }
+
+    /// Block from which a pool is withdraw-only, if it is or is scheduled to be
+    pub fn withdraw_only_from(&self, pool_id: &PoolId) -> Option<u64> {
+        self.withdraw_only.get(pool_id).copied()
+    }
+
+    /// Whether a pool is withdraw-only at the current block
+    pub fn is_withdraw_only(&self, pool_id: &PoolId) -> bool {
+        self.withdraw_only_from(pool_id).is_some_and(|block| block <= self.block_number)
+    }
+
+    /// Moves a pool's pause, withdraw-only state, breaker and windows to a
+    /// new ID, e.g. when the pool's key changes
+    ///
+    /// Events recorded under the old ID are kept as they were.
+    pub fn move_pool(&mut self, from: &PoolId, to: PoolId) {
+        if self.paused_pools.remove(from) {
+            self.paused_pools.insert(to);
+        }
+        if let Some(block) = self.withdraw_only.remove(from) {
+            self.withdraw_only.insert(to, block);
+        }
+        if let Some(config) = self.pool_breakers.remove(from) {
+            self.pool_breakers.insert(to, config);
+        }
+        if let Some(reference) = self.references.remove(from) {
+            self.references.insert(to, reference);
+        }
+        if let Some(volume) = self.volumes.remove(from) {
+            self.volumes.insert(to, volume);
+        }
+    }
+
+    /// Event history
+    pub fn events(&self) -> &[RiskEvent] {
+        &self.events
+    }
+
+    /// Takes the event history, leaving it empty
+    pub fn drain_events(&mut self) -> Vec<RiskEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Records a swap and trips the pool's breaker if a limit is exceeded
+    ///
+    /// `volume` is the absolute token0 amount of the swap. Returns the reason
+    /// the breaker tripped, if it did.
+    pub fn record_swap(
+        &mut self,
+        pool_id: PoolId,
+        sqrt_price_before_x96: U256,
+        sqrt_price_after_x96: U256,
+        volume: u128,
+    ) -> Option<TripReason> {
+        let block = self.block_number;
+        // Pools without a breaker keep no per-block bookkeeping
+        let config = *self.breaker_for(&pool_id)?;
+
+        let reference = self.references.entry(pool_id).or_insert(BlockReference {
+            block,
+            sqrt_price_x96: sqrt_price_before_x96,
+        });
+        if reference.block != block {
+            *reference = BlockReference { block, sqrt_price_x96: sqrt_price_before_x96 };
+        }
+        let reference_price = reference.sqrt_price_x96;
+
+        let window = self.volumes.entry(pool_id).or_insert(VolumeWindow { start_block: block, volume: 0 });
+        // A window too long to end before `u64::MAX` never ends
+        let window_end = window.start_block.checked_add(config.window_blocks.max(1));
+        if window_end.is_some_and(|end| block >= end) {
+            *window = VolumeWindow { start_block: block, volume: 0 };
+        }
+        window.volume = window.volume.saturating_add(volume);
+        let window_volume = window.volume;
+
+        let mut reason = None;
+        if let Some(max_bps) = config.max_price_move_bps {
+            let move_bps = price_move_bps(reference_price, sqrt_price_after_x96);
+            if move_bps > max_bps {
+                reason = Some(TripReason::PriceDeviation { move_bps, max_bps });
+            }
+        }
+        if reason.is_none() {
+            if let Some(max_volume) = config.max_volume_per_window {
+                if window_volume > max_volume {
+                    reason = Some(TripReason::VolumeExceeded { volume: window_volume, max_volume });
+                }
+            }
+        }
+
+        let reason = reason?;
+        self.events.push(RiskEvent::BreakerTripped { pool_id, block, reason, action: config.action });
+        match config.action {
+            BreakerAction::PausePool => self.pause_pool(pool_id),
+            BreakerAction::PauseManager => self.pause_manager(),
+        }
+        Some(reason)
+    }
+}
+
+/// Move between two square-root prices in basis points of the price, saturating at `u32::MAX`
+fn price_move_bps(sqrt_price_from_x96: U256, sqrt_price_to_x96: U256) -> u32 {
+    if sqrt_price_from_x96.is_zero() {
+        return 0;
+    }
+    let from = U512::from(sqrt_price_from_x96) * U512::from(sqrt_price_from_x96);
+    let to = U512::from(sqrt_price_to_x96) * U512::from(sqrt_price_to_x96);
+    let diff = if to > from { to - from } else { from - to };
+    let bps = diff * U512::from(BPS_DENOMINATOR) / from;
+    if bps > U512::from(u32::MAX) {
+        u32::MAX
+    } else {
+        bps.low_u32()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POOL: PoolId = PoolId([1u8; 32]);
+    const OTHER_POOL: PoolId = PoolId([2u8; 32]);
+
+    fn q96() -> U256 {
+        U256::from(1u128 << 96)
+    }
+
+    #[test]
+    fn test_price_move_bps() {
+        assert_eq!(price_move_bps(q96(), q96()), 0);
+        // Doubling the square-root price quadruples the price
+        assert_eq!(price_move_bps(q96(), q96() * 2), 30_000);
+        assert_eq!(price_move_bps(q96() * 2, q96()), 7_500);
+    }
+
+    #[test]
+    fn test_price_deviation_pauses_pool() {
+        let mut risk = RiskManager::new();
+        risk.set_pool_breaker(POOL, CircuitBreakerConfig::new().with_max_price_move_bps(500)).unwrap();
+
+        // 1% up in sqrt price is ~2% in price
+        let step = q96() / 100;
+        assert_eq!(risk.record_swap(POOL, q96(), q96() + step, 0), None);
+        // Moves accumulate against the price at the start of the block
+        let reason = risk.record_swap(POOL, q96() + step, q96() + step * 3, 0);
+        assert!(matches!(reason, Some(TripReason::PriceDeviation { max_bps: 500, .. })));
+        assert!(risk.is_pool_paused(&POOL));
+        assert!(!risk.is_pool_paused(&OTHER_POOL));
+        assert_eq!(risk.events().last(), Some(&RiskEvent::PoolPaused { pool_id: POOL }));
+
+        risk.unpause_pool(POOL);
+        assert!(!risk.is_pool_paused(&POOL));
+    }
+
+    #[test]
+    fn test_new_block_resets_price_reference() {
+        let mut risk = RiskManager::new();
+        risk.set_global_breaker(CircuitBreakerConfig::new().with_max_price_move_bps(500)).unwrap();
+
+        let step = q96() / 100;
+        assert_eq!(risk.record_swap(POOL, q96(), q96() + step * 2, 0), None);
+        risk.advance_block();
+        assert_eq!(risk.record_swap(POOL, q96() + step * 2, q96() + step * 4, 0), None);
+        assert!(!risk.is_pool_paused(&POOL));
+    }
+
+    #[test]
+    fn test_volume_window() {
+        let mut risk = RiskManager::new();
+        risk.set_global_breaker(
+            CircuitBreakerConfig::new()
+                .with_max_volume(1_000, 2)
+                .with_action(BreakerAction::PauseManager),
+        )
+        .unwrap();
+
+        assert_eq!(risk.record_swap(POOL, q96(), q96(), 600), None);
+        risk.advance_block();
+        let reason = risk.record_swap(POOL, q96(), q96(), 600);
+        assert_eq!(reason, Some(TripReason::VolumeExceeded { volume: 1_200, max_volume: 1_000 }));
+        assert!(risk.is_manager_paused());
+        assert!(risk.is_pool_paused(&OTHER_POOL));
+
+        risk.unpause_manager();
+        // A new window starts after two blocks
+        risk.advance_block();
+        assert_eq!(risk.record_swap(POOL, q96(), q96(), 600), None);
+
+        // Windows of any length don't overflow the block number
+        let mut risk = RiskManager::new();
+        risk.set_global_breaker(CircuitBreakerConfig::new().with_max_volume(1_000, u64::MAX)).unwrap();
+        risk.set_block_number(u64::MAX - 1);
+        assert_eq!(risk.record_swap(POOL, q96(), q96(), 600), None);
+        risk.advance_block();
+        assert!(matches!(risk.record_swap(POOL, q96(), q96(), 600), Some(TripReason::VolumeExceeded { volume: 1_200, .. })));
+    }
+
+    #[test]
+    fn test_invalid_window_rejected() {
+        let mut risk = RiskManager::new();
+        let config = CircuitBreakerConfig::new().with_max_volume(1, 0);
+        assert!(matches!(risk.set_global_breaker(config), Err(RiskError::InvalidWindow)));
+    }
+}
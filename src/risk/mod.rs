@@ -0,0 +1,5 @@
+pub mod circuit_breaker;
+pub mod types;
+
+pub use circuit_breaker::*;
+pub use types::*;
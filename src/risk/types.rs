@@ -0,0 +1,92 @@
+use crate::core::pool_manager::PoolId;
+
+/// Basis point denominator (10,000) for price move limits - represents 100%
+pub const BPS_DENOMINATOR: u32 = 10_000;
+
+/// What a circuit breaker pauses when it trips
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BreakerAction {
+    /// Pause only the pool whose activity tripped the breaker
+    #[default]
+    PausePool,
+    /// Pause every pool in the manager
+    PauseManager,
+}
+
+/// Limits enforced by a circuit breaker
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CircuitBreakerConfig {
+    /// Maximum price move within a block, in basis points of the price at the
+    /// start of the block
+    pub max_price_move_bps: Option<u32>,
+    /// Maximum swap volume, in token0, within a window of blocks
+    pub max_volume_per_window: Option<u128>,
+    /// Length of the volume window in blocks
+    pub window_blocks: u64,
+    /// What to pause when a limit is exceeded
+    pub action: BreakerAction,
+}
+
+impl CircuitBreakerConfig {
+    /// Creates a breaker with no limits that pauses the offending pool
+    pub fn new() -> Self {
+        Self {
+            window_blocks: 1,
+            ..Default::default()
+        }
+    }
+
+    /// Sets the maximum price move per block
+    pub fn with_max_price_move_bps(mut self, bps: u32) -> Self {
+        self.max_price_move_bps = Some(bps);
+        self
+    }
+
+    /// Sets the maximum volume per window of `window_blocks` blocks
+    pub fn with_max_volume(mut self, volume: u128, window_blocks: u64) -> Self {
+        self.max_volume_per_window = Some(volume);
+        self.window_blocks = window_blocks;
+        self
+    }
+
+    /// Sets what to pause when the breaker trips
+    pub fn with_action(mut self, action: BreakerAction) -> Self {
+        self.action = action;
+        self
+    }
+}
+
+/// Reason a circuit breaker tripped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TripReason {
+    /// The price moved more than allowed within a block
+    PriceDeviation { move_bps: u32, max_bps: u32 },
+    /// The swap volume exceeded the window limit
+    VolumeExceeded { volume: u128, max_volume: u128 },
+}
+
+/// Risk events
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RiskEvent {
+    /// A circuit breaker tripped on a pool
+    BreakerTripped { pool_id: PoolId, block: u64, reason: TripReason, action: BreakerAction },
+    /// A pool was paused
+    PoolPaused { pool_id: PoolId },
+    /// A pool was unpaused
+    PoolUnpaused { pool_id: PoolId },
+    /// The whole manager was paused
+    ManagerPaused,
+    /// The whole manager was unpaused
+    ManagerUnpaused,
+    /// A pool was made withdraw-only from a block
+    WithdrawOnlyScheduled { pool_id: PoolId, effective_block: u64 },
+    /// A pool's withdraw-only state or schedule was lifted
+    WithdrawOnlyLifted { pool_id: PoolId },
+}
+
+/// Error types for risk configuration
+#[derive(Debug, thiserror::Error)]
+pub enum RiskError {
+    #[error("Volume window must span at least one block")]
+    InvalidWindow,
+}
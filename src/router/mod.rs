@@ -0,0 +1,11 @@
+//! Routing swaps across the pools of a manager
+//!
+//! The [`PathFinder`] builds a graph of currencies from the pools it knows,
+//! searches it for paths of a few hops with the best spot rates, then quotes
+//! the best of them against the manager to rank routes by actual output.
+
+pub mod pathfinder;
+pub mod types;
+
+pub use pathfinder::*;
+pub use types::*;
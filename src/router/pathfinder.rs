@@ -0,0 +1,328 @@
+use std::{cmp::Reverse, collections::{BTreeMap, HashMap}};
+
+use ethers::types::Address;
+
+use crate::core::{
+    amounts::{SwapAmount, SwapFlows},
+    math::{FeePips, TickMath},
+    pool_manager::{ManagerPoolKey, PoolId, PoolManager, QuoteRequest},
+};
+
+use super::{Hop, Path, Route, RouterError, RouterResult};
+
+/// Directed edge of the currency graph
+#[derive(Debug, Clone)]
+struct Edge {
+    hop: Hop,
+    to: Address,
+    /// `-ln(price * (1 - fee))` of the hop at the pool's current price
+    weight: f64,
+}
+
+/// Partial path reaching a currency during the search
+#[derive(Debug, Clone)]
+struct Label {
+    at: Address,
+    path: Path,
+}
+
+/// Finds multi-hop routes between currencies through a set of pools
+///
+/// Each pool gives the graph an edge in both directions, weighted by the
+/// negative log of its spot rate after the LP fee, so the sum of weights
+/// along a path is the negative log of its spot rate. The search is a
+/// Bellman-Ford relaxation bounded to `max_hops` rounds that keeps only
+/// simple paths and the `candidates` best labels per currency in each round,
+/// which also keeps arbitrage cycles from looping. Spot rates ignore price
+/// impact, so [`find_routes`](Self::find_routes) quotes the candidates for
+/// the actual amount and ranks them by output.
+#[derive(Debug, Clone)]
+pub struct PathFinder {
+    pools: BTreeMap<PoolId, ManagerPoolKey>,
+    max_hops: usize,
+    candidates: usize,
+    quote_hooks: bool,
+}
+
+impl Default for PathFinder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PathFinder {
+    /// Creates a path finder with no pools, searching up to 3 hops and
+    /// keeping 8 candidates per currency
+    pub fn new() -> Self {
+        Self { pools: BTreeMap::new(), max_hops: 3, candidates: 8, quote_hooks: false }
+    }
+
+    /// Sets the maximum number of hops of a path
+    pub fn with_max_hops(mut self, max_hops: usize) -> Self {
+        self.max_hops = max_hops;
+        self
+    }
+
+    /// Sets the number of paths kept per currency in each search round, and
+    /// so the number of candidates quoted
+    pub fn with_candidates(mut self, candidates: usize) -> Self {
+        self.candidates = candidates.max(1);
+        self
+    }
+
+    /// Quotes candidates with pool hooks called, see
+    /// [`PoolManager::quote_with_hooks`]
+    pub fn with_hook_quotes(mut self, quote_hooks: bool) -> Self {
+        self.quote_hooks = quote_hooks;
+        self
+    }
+
+    /// Adds a pool to the graph, returning whether it was new
+    pub fn add_pool(&mut self, key: ManagerPoolKey) -> bool {
+        self.pools.insert(PoolId::from_key(&key), key).is_none()
+    }
+
+    /// Removes a pool from the graph, returning whether it was there
+    pub fn remove_pool(&mut self, key: &ManagerPoolKey) -> bool {
+        self.pools.remove(&PoolId::from_key(key)).is_some()
+    }
+
+    /// Gets the pools of the graph, sorted by pool ID
+    pub fn pools(&self) -> impl Iterator<Item = &ManagerPoolKey> {
+        self.pools.values()
+    }
+
+    /// Builds the graph from the pools' current prices, skipping pools that
+    /// are not initialized or have no active liquidity
+    fn graph(&self, manager: &PoolManager) -> HashMap<Address, Vec<Edge>> {
+        let mut graph: HashMap<Address, Vec<Edge>> = HashMap::new();
+        for key in self.pools.values() {
+            let Some(pool) = manager.get_pool(key) else {
+                continue;
+            };
+            if pool.liquidity.as_u128() == 0 {
+                continue;
+            }
+            let price = pool.slot0.sqrt_price_x96.to_price_f64();
+            if price <= 0.0 {
+                continue;
+            }
+            for (zero_for_one, rate) in [(true, price), (false, 1.0 / price)] {
+                let fee_factor = 1.0 - pool.slot0.lp_fee_for(zero_for_one).get() as f64 / FeePips::DENOMINATOR as f64;
+                if fee_factor <= 0.0 {
+                    continue;
+                }
+                let hop = Hop { key: key.clone(), zero_for_one };
+                let edge = Edge { to: hop.currency_out(), weight: -(rate * fee_factor).ln(), hop };
+                graph.entry(edge.hop.currency_in()).or_default().push(edge);
+            }
+        }
+        graph
+    }
+
+    /// Finds the paths from `from` to `to` with the best spot rates, best
+    /// first
+    pub fn candidates(&self, manager: &PoolManager, from: Address, to: Address) -> RouterResult<Vec<Path>> {
+        if from == to {
+            return Err(RouterError::SameCurrency(from));
+        }
+        if self.max_hops == 0 {
+            return Err(RouterError::ZeroHops);
+        }
+
+        let graph = self.graph(manager);
+        let mut found = Vec::new();
+        let mut frontier = vec![Label { at: from, path: Path { hops: Vec::new(), log_weight: 0.0 } }];
+        for _ in 0..self.max_hops {
+            let mut reached: HashMap<Address, Vec<Label>> = HashMap::new();
+            for label in &frontier {
+                for edge in graph.get(&label.at).into_iter().flatten() {
+                    // Paths stay simple, so they never revisit a currency
+                    if edge.to == from || label.path.hops.iter().any(|hop| hop.currency_out() == edge.to) {
+                        continue;
+                    }
+                    let mut path = label.path.clone();
+                    path.hops.push(edge.hop.clone());
+                    path.log_weight += edge.weight;
+                    reached.entry(edge.to).or_default().push(Label { at: edge.to, path });
+                }
+            }
+
+            frontier.clear();
+            for (currency, mut labels) in reached {
+                labels.sort_by(|a, b| a.path.log_weight.total_cmp(&b.path.log_weight));
+                labels.truncate(self.candidates);
+                if currency == to {
+                    found.extend(labels.into_iter().map(|label| label.path));
+                } else {
+                    frontier.extend(labels);
+                }
+            }
+        }
+
+        if found.is_empty() {
+            return Err(RouterError::NoRoute { from, to });
+        }
+        found.sort_by(|a, b| a.log_weight.total_cmp(&b.log_weight));
+        found.truncate(self.candidates);
+        Ok(found)
+    }
+
+    /// Quotes an exact input amount along a path, returning the output, or
+    /// `None` if a hop fails or can't take its whole input
+    pub fn quote_path(&self, manager: &PoolManager, path: &Path, amount_in: u128) -> Option<u128> {
+        let mut amount = amount_in;
+        for hop in &path.hops {
+            let limit = if hop.zero_for_one {
+                TickMath::MIN_SQRT_PRICE + 1
+            } else {
+                TickMath::MAX_SQRT_PRICE - 1
+            };
+            let request = QuoteRequest::new(hop.key.clone(), hop.zero_for_one, SwapAmount::ExactIn(amount), limit).ok()?;
+            let quote = if self.quote_hooks {
+                manager.quote_with_hooks(&request, &[])
+            } else {
+                manager.quote(&request)
+            };
+            let flows = SwapFlows::of(quote.ok()?.delta, hop.zero_for_one);
+            // A swap that ran out of liquidity leaves some input unspent
+            if !flows.fills(SwapAmount::ExactIn(amount)) || flows.received == 0 {
+                return None;
+            }
+            amount = flows.received;
+        }
+        Some(amount)
+    }
+
+    /// Finds up to `max_routes` routes from `from` to `to` for an exact input
+    /// amount, ranked by quoted output
+    ///
+    /// Candidates from [`candidates`](Self::candidates) that can't be filled
+    /// are dropped; fails with [`RouterError::NoRoute`] if none can.
+    pub fn find_routes(
+        &self,
+        manager: &PoolManager,
+        from: Address,
+        to: Address,
+        amount_in: u128,
+        max_routes: usize,
+    ) -> RouterResult<Vec<Route>> {
+        if amount_in == 0 || i128::try_from(amount_in).is_err() {
+            return Err(RouterError::InvalidAmount);
+        }
+        let mut routes: Vec<_> = self
+            .candidates(manager, from, to)?
+            .into_iter()
+            .filter_map(|path| {
+                let amount_out = self.quote_path(manager, &path, amount_in)?;
+                Some(Route { path, amount_in, amount_out })
+            })
+            .collect();
+        if routes.is_empty() {
+            return Err(RouterError::NoRoute { from, to });
+        }
+        // Stable, so equal outputs keep the spot rate order
+        routes.sort_by_key(|route| Reverse(route.amount_out));
+        routes.truncate(max_routes);
+        Ok(routes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{
+        hooks::hook_interface::ModifyLiquidityParams,
+        math::types::{SqrtPrice, TickSpacing},
+    };
+
+    fn token(byte: u8) -> Address {
+        Address::repeat_byte(byte)
+    }
+
+    fn add_pool(manager: &mut PoolManager, finder: &mut PathFinder, a: u8, b: u8, fee: u32, liquidity: i128) -> ManagerPoolKey {
+        let key = ManagerPoolKey::new(token(a), token(b), fee, TickSpacing::new(60).unwrap(), Address::zero()).unwrap();
+        manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+        let params = ModifyLiquidityParams::default_position(token(0xEE), -600, 600, liquidity);
+        manager.modify_liquidity(key.clone(), params, &[]).unwrap();
+        finder.add_pool(key.clone());
+        key
+    }
+
+    /// Deep A/B and B/C pools with a shallow, cheaper direct A/C pool
+    fn setup() -> (PoolManager, PathFinder, [ManagerPoolKey; 3]) {
+        let mut manager = PoolManager::new();
+        let mut finder = PathFinder::new();
+        let ab = add_pool(&mut manager, &mut finder, 1, 2, 3000, 1_000_000_000_000);
+        let bc = add_pool(&mut manager, &mut finder, 2, 3, 3000, 1_000_000_000_000);
+        let ac = add_pool(&mut manager, &mut finder, 1, 3, 500, 1_000_000);
+        (manager, finder, [ab, bc, ac])
+    }
+
+    #[test]
+    fn test_candidates_ranked_by_spot_rate() {
+        let (manager, finder, [ab, bc, ac]) = setup();
+
+        let paths = finder.candidates(&manager, token(1), token(3)).unwrap();
+        assert_eq!(paths.len(), 2);
+        assert_eq!(paths[0].hops, vec![Hop { key: ac, zero_for_one: true }]);
+        assert!((paths[0].spot_rate() - 0.9995).abs() < 1e-12);
+        assert_eq!(paths[1].currencies(), vec![token(1), token(2), token(3)]);
+        assert_eq!(paths[1].hops[1], Hop { key: bc.clone(), zero_for_one: true });
+        assert!((paths[1].spot_rate() - 0.997 * 0.997).abs() < 1e-12);
+
+        // Reverse paths swap one for zero
+        let paths = finder.candidates(&manager, token(3), token(1)).unwrap();
+        assert!(paths.iter().all(|path| path.hops.iter().all(|hop| !hop.zero_for_one)));
+
+        let direct = finder.clone().with_max_hops(1).candidates(&manager, token(2), token(3)).unwrap();
+        assert_eq!(direct.len(), 1);
+        assert!(direct[0].hops.iter().all(|hop| hop.key != ab));
+    }
+
+    #[test]
+    fn test_routes_ranked_by_quoted_output() {
+        let (manager, finder, [_, _, ac]) = setup();
+
+        // A small trade gets the best rate from the cheaper direct pool
+        let routes = finder.find_routes(&manager, token(1), token(3), 1_000, 5).unwrap();
+        assert_eq!(routes[0].path.hops.len(), 1);
+        assert!(routes[0].amount_out > routes[1].amount_out);
+
+        // A larger one moves the shallow pool's price too far
+        let routes = finder.find_routes(&manager, token(1), token(3), 20_000, 5).unwrap();
+        assert_eq!(routes.len(), 2);
+        assert_eq!(routes[0].path.hops.len(), 2);
+        assert!(routes[0].amount_out > routes[1].amount_out);
+        assert_eq!(routes[0].amount_in, 20_000);
+
+        // The shallow pool can't fill at all, so only the two hop route is left
+        let routes = finder.find_routes(&manager, token(1), token(3), 10_000_000, 5).unwrap();
+        assert_eq!(routes.len(), 1);
+        assert!(routes[0].path.hops.iter().all(|hop| hop.key != ac));
+        assert_eq!(finder.find_routes(&manager, token(1), token(3), 20_000, 1).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_invalid_searches_rejected() {
+        let (manager, mut finder, [ab, bc, _]) = setup();
+
+        assert_eq!(finder.candidates(&manager, token(1), token(1)), Err(RouterError::SameCurrency(token(1))));
+        assert_eq!(finder.find_routes(&manager, token(1), token(3), 0, 1), Err(RouterError::InvalidAmount));
+        assert_eq!(finder.clone().with_max_hops(0).candidates(&manager, token(1), token(3)), Err(RouterError::ZeroHops));
+        assert_eq!(
+            finder.candidates(&manager, token(1), token(9)),
+            Err(RouterError::NoRoute { from: token(1), to: token(9) })
+        );
+
+        // Uninitialized pools are not part of the graph
+        assert!(finder.remove_pool(&ab));
+        assert!(finder.remove_pool(&bc));
+        let key = ManagerPoolKey::new(token(4), token(3), 3000, TickSpacing::new(60).unwrap(), Address::zero()).unwrap();
+        assert!(finder.add_pool(key));
+        assert_eq!(
+            finder.candidates(&manager, token(4), token(1)),
+            Err(RouterError::NoRoute { from: token(4), to: token(1) })
+        );
+    }
+}
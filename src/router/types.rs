@@ -0,0 +1,77 @@
+use ethers::types::Address;
+
+use crate::core::pool_manager::ManagerPoolKey;
+
+/// One swap of a path, through a single pool
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hop {
+    /// Pool swapped in
+    pub key: ManagerPoolKey,
+    /// Whether the hop swaps token0 for token1
+    pub zero_for_one: bool,
+}
+
+impl Hop {
+    /// Gets the currency paid into the pool
+    pub fn currency_in(&self) -> Address {
+        if self.zero_for_one { self.key.token0() } else { self.key.token1() }
+    }
+
+    /// Gets the currency received from the pool
+    pub fn currency_out(&self) -> Address {
+        if self.zero_for_one { self.key.token1() } else { self.key.token0() }
+    }
+}
+
+/// Sequence of hops from one currency to another, found from spot prices
+#[derive(Debug, Clone, PartialEq)]
+pub struct Path {
+    /// The hops, in swap order
+    pub hops: Vec<Hop>,
+    /// Sum over the hops of `-ln(price * (1 - fee))`, lower is better
+    pub log_weight: f64,
+}
+
+impl Path {
+    /// Gets the output per unit of input at spot prices, after fees
+    pub fn spot_rate(&self) -> f64 {
+        (-self.log_weight).exp()
+    }
+
+    /// Gets the currencies visited, from the input to the output
+    pub fn currencies(&self) -> Vec<Address> {
+        let mut currencies: Vec<_> = self.hops.first().map(Hop::currency_in).into_iter().collect();
+        currencies.extend(self.hops.iter().map(Hop::currency_out));
+        currencies
+    }
+}
+
+/// Path quoted for an exact input amount
+#[derive(Debug, Clone, PartialEq)]
+pub struct Route {
+    /// The path swapped along
+    pub path: Path,
+    /// Amount paid into the first hop
+    pub amount_in: u128,
+    /// Amount received from the last hop
+    pub amount_out: u128,
+}
+
+/// Error types for routing
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum RouterError {
+    #[error("Cannot route {0:?} to itself")]
+    SameCurrency(Address),
+
+    #[error("Routes must have at least one hop")]
+    ZeroHops,
+
+    #[error("Input amount must be between 1 and i128::MAX")]
+    InvalidAmount,
+
+    #[error("No route from {from:?} to {to:?}")]
+    NoRoute { from: Address, to: Address },
+}
+
+/// Result type for routing
+pub type RouterResult<T> = std::result::Result<T, RouterError>;
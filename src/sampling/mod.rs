@@ -0,0 +1,11 @@
+//! Time series of pool state for charting
+//!
+//! A [`PoolSampler`] follows a manager's clock and records metrics such as
+//! the price, tick and liquidity of the pools it tracks into ring buffers of
+//! candles, downsampled to coarser resolutions for long ranges.
+
+pub mod sampler;
+pub mod types;
+
+pub use sampler::*;
+pub use types::*;
@@ -0,0 +1,305 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use crate::core::pool_manager::{ManagerPoolKey, PoolId, PoolManager};
+
+use super::{Candle, Metric, SamplerConfig, SamplingError, SamplingResult, Series};
+
+/// Candles of one metric at one resolution, oldest first
+#[derive(Debug, Clone)]
+struct Resolution {
+    interval: u64,
+    candles: VecDeque<Candle>,
+}
+
+/// History of one metric at every resolution, finest first
+#[derive(Debug, Clone)]
+struct MetricHistory {
+    resolutions: Vec<Resolution>,
+}
+
+impl MetricHistory {
+    fn new(config: &SamplerConfig) -> Self {
+        let resolutions = (0..config.resolutions)
+            .map(|level| Resolution {
+                interval: config.interval_at(level).expect("validated configs have intervals that fit"),
+                candles: VecDeque::with_capacity(config.capacity),
+            })
+            .collect();
+        Self { resolutions }
+    }
+
+    /// Adds a sample to the candle of its period at every resolution,
+    /// dropping the oldest candle of a full resolution
+    fn record(&mut self, timestamp: u64, value: f64, capacity: usize) {
+        for resolution in &mut self.resolutions {
+            let start = timestamp - timestamp % resolution.interval;
+            match resolution.candles.back_mut() {
+                Some(candle) if candle.timestamp == start => candle.update(value),
+                _ => {
+                    if resolution.candles.len() == capacity {
+                        resolution.candles.pop_front();
+                    }
+                    resolution.candles.push_back(Candle::new(start, value));
+                }
+            }
+        }
+    }
+
+    /// Picks the finest resolution that covers `from..=to` in at most
+    /// `max_points` candles, or the coarsest one
+    fn query(&self, from: u64, to: u64, max_points: usize, capacity: usize) -> Series {
+        let in_range = |resolution: &Resolution| -> Vec<Candle> {
+            let start = from - from % resolution.interval;
+            resolution.candles
+                .iter()
+                .filter(|candle| candle.timestamp >= start && candle.timestamp <= to)
+                .copied()
+                .collect()
+        };
+        for resolution in &self.resolutions {
+            // A resolution that dropped candles may no longer reach back to `from`
+            let covers = resolution.candles.len() < capacity
+                || resolution.candles.front().is_some_and(|candle| candle.timestamp <= from);
+            let candles = in_range(resolution);
+            if covers && candles.len() <= max_points {
+                return Series { interval: resolution.interval, candles };
+            }
+        }
+
+        let coarsest = self.resolutions.last().expect("validated configs have a resolution");
+        let mut candles = in_range(coarsest);
+        candles.drain(..candles.len().saturating_sub(max_points));
+        Series { interval: coarsest.interval, candles }
+    }
+}
+
+/// Pool tracked by a sampler
+#[derive(Debug, Clone)]
+struct TrackedPool {
+    key: ManagerPoolKey,
+    metrics: BTreeMap<Metric, MetricHistory>,
+}
+
+/// Records periodic snapshots of pool metrics for charting
+///
+/// The sampler follows the manager's clock: [`on_block`](Self::on_block) is
+/// called whenever the clock advances and takes at most one sample of every
+/// tracked pool per interval, at the first block in it. Each metric is kept
+/// in ring buffers of candles at the finest resolution and at coarser,
+/// downsampled ones, so long ranges can be charted from few points.
+#[derive(Debug, Clone)]
+pub struct PoolSampler {
+    config: SamplerConfig,
+    pools: BTreeMap<PoolId, TrackedPool>,
+    /// Interval of the last sample, counted from timestamp 0
+    last_slot: Option<u64>,
+}
+
+impl PoolSampler {
+    /// Creates a sampler tracking no pools
+    pub fn new(config: SamplerConfig) -> SamplingResult<Self> {
+        config.validate()?;
+        Ok(Self { config, pools: BTreeMap::new(), last_slot: None })
+    }
+
+    /// Gets the configuration
+    pub fn config(&self) -> &SamplerConfig {
+        &self.config
+    }
+
+    /// Starts sampling a pool; tracking a pool again keeps its history
+    pub fn track(&mut self, key: ManagerPoolKey) {
+        let config = &self.config;
+        self.pools.entry(PoolId::from_key(&key)).or_insert_with(|| TrackedPool {
+            metrics: config.metrics.iter().map(|metric| (*metric, MetricHistory::new(config))).collect(),
+            key,
+        });
+    }
+
+    /// Stops sampling a pool and drops its history, returning whether it
+    /// was tracked
+    pub fn untrack(&mut self, key: &ManagerPoolKey) -> bool {
+        self.pools.remove(&PoolId::from_key(key)).is_some()
+    }
+
+    /// Gets the keys of the tracked pools, sorted by pool ID
+    pub fn tracked_pools(&self) -> impl Iterator<Item = &ManagerPoolKey> {
+        self.pools.values().map(|pool| &pool.key)
+    }
+
+    /// Samples every tracked pool if no sample was taken yet in the interval
+    /// of the manager's timestamp, returning whether a sample was taken
+    ///
+    /// Pools that are not initialized in the manager are skipped.
+    pub fn on_block(&mut self, manager: &PoolManager) -> bool {
+        let timestamp = manager.timestamp();
+        let slot = timestamp / self.config.interval;
+        if self.last_slot.is_some_and(|last| slot <= last) {
+            return false;
+        }
+        self.last_slot = Some(slot);
+
+        for tracked in self.pools.values_mut() {
+            let Some(pool) = manager.get_pool(&tracked.key) else {
+                continue;
+            };
+            for (metric, history) in &mut tracked.metrics {
+                history.record(timestamp, metric.read(pool), self.config.capacity);
+            }
+        }
+        true
+    }
+
+    /// Gets the most recent candle of a pool's metric at the finest resolution
+    pub fn latest(&self, key: &ManagerPoolKey, metric: Metric) -> SamplingResult<Option<Candle>> {
+        let history = self.history(key, metric)?;
+        Ok(history.resolutions[0].candles.back().copied())
+    }
+
+    /// Gets every stored candle of a pool's metric at a resolution, where 0
+    /// is the finest; resolutions past the coarsest give the coarsest
+    pub fn series(&self, key: &ManagerPoolKey, metric: Metric, resolution: usize) -> SamplingResult<Series> {
+        let history = self.history(key, metric)?;
+        let resolution = &history.resolutions[resolution.min(history.resolutions.len() - 1)];
+        Ok(Series { interval: resolution.interval, candles: resolution.candles.iter().copied().collect() })
+    }
+
+    /// Gets the candles of a pool's metric from `from` to `to`, inclusive, in
+    /// at most `max_points` candles
+    ///
+    /// The finest resolution that still holds the whole range in at most
+    /// `max_points` candles is used. If none does, the most recent
+    /// `max_points` candles of the coarsest resolution are returned.
+    pub fn query(
+        &self,
+        key: &ManagerPoolKey,
+        metric: Metric,
+        from: u64,
+        to: u64,
+        max_points: usize,
+    ) -> SamplingResult<Series> {
+        let history = self.history(key, metric)?;
+        Ok(history.query(from, to, max_points, self.config.capacity))
+    }
+
+    fn history(&self, key: &ManagerPoolKey, metric: Metric) -> SamplingResult<&MetricHistory> {
+        let pool_id = PoolId::from_key(key);
+        let tracked = self.pools.get(&pool_id).ok_or(SamplingError::PoolNotTracked(pool_id))?;
+        tracked.metrics.get(&metric).ok_or(SamplingError::MetricNotRecorded(metric))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{
+        hooks::hook_interface::ModifyLiquidityParams,
+        math::{types::{SqrtPrice, TickSpacing}, TickMath},
+    };
+    use ethers::types::Address;
+
+    fn key() -> ManagerPoolKey {
+        ManagerPoolKey::new(
+            Address::from_low_u64_be(1),
+            Address::from_low_u64_be(2),
+            3000,
+            TickSpacing::new(60).unwrap(),
+            Address::zero(),
+        )
+        .unwrap()
+    }
+
+    fn history(config: &SamplerConfig, samples: &[(u64, f64)]) -> MetricHistory {
+        let mut history = MetricHistory::new(config);
+        for (timestamp, value) in samples {
+            history.record(*timestamp, *value, config.capacity);
+        }
+        history
+    }
+
+    #[test]
+    fn test_samples_once_per_interval() {
+        let mut manager = PoolManager::new();
+        manager.initialize_pool(key(), SqrtPrice::ONE).unwrap();
+        let params = ModifyLiquidityParams::default_position(Address::repeat_byte(1), -600, 600, 1_000_000_000);
+        manager.modify_liquidity(key(), params, &[]).unwrap();
+        let config = SamplerConfig::new(10).with_metrics(&[Metric::Price, Metric::Tick, Metric::VirtualReserve1]);
+        let mut sampler = PoolSampler::new(config).unwrap();
+        sampler.track(key());
+
+        let mut taken = Vec::new();
+        for timestamp in [3, 7, 12, 35] {
+            manager.set_timestamp(timestamp);
+            taken.push(sampler.on_block(&manager));
+            manager.swap(&key(), true, -1_000_000, TickMath::MIN_SQRT_PRICE + 1, &[]).unwrap();
+        }
+        assert_eq!(taken, vec![true, false, true, true]);
+
+        let ticks = sampler.series(&key(), Metric::Tick, 0).unwrap();
+        assert_eq!(ticks.interval, 10);
+        assert_eq!(ticks.candles.iter().map(|candle| candle.timestamp).collect::<Vec<_>>(), vec![0, 10, 30]);
+        assert_eq!(ticks.candles[0].close, 0.0);
+        // Each sample sees the swaps before it
+        let pool = manager.get_pool(&key()).unwrap();
+        assert!(ticks.candles[2].close < ticks.candles[1].close);
+        assert!(ticks.candles[2].close > pool.slot0.tick as f64);
+        // Reserves of liquidity 1e9 at price 1
+        assert_eq!(sampler.series(&key(), Metric::VirtualReserve1, 0).unwrap().candles[0].close, 1e9);
+        assert_eq!(sampler.latest(&key(), Metric::Price).unwrap().unwrap().timestamp, 30);
+        assert_eq!(sampler.latest(&key(), Metric::Liquidity), Err(SamplingError::MetricNotRecorded(Metric::Liquidity)));
+
+        assert!(sampler.untrack(&key()));
+        let pool_id = PoolId::from_key(&key());
+        assert_eq!(sampler.latest(&key(), Metric::Price), Err(SamplingError::PoolNotTracked(pool_id)));
+    }
+
+    #[test]
+    fn test_downsampled_resolutions() {
+        let config = SamplerConfig::new(10).with_capacity(4).with_downsampling(4, 2);
+        let samples: Vec<_> = (0..8).map(|i| (i * 10, [5.0, 7.0, 3.0, 4.0, 6.0, 1.0, 2.0, 9.0][i as usize])).collect();
+        let history = history(&config, &samples);
+
+        let fine = &history.resolutions[0];
+        assert_eq!(fine.candles.len(), 4);
+        assert_eq!(fine.candles[0], Candle::new(40, 6.0));
+        let coarse: Vec<_> = history.resolutions[1].candles.iter().copied().collect();
+        assert_eq!(coarse, vec![
+            Candle { timestamp: 0, open: 5.0, high: 7.0, low: 3.0, close: 4.0 },
+            Candle { timestamp: 40, open: 6.0, high: 9.0, low: 1.0, close: 9.0 },
+        ]);
+
+        // Recent ranges come from the finest resolution
+        let series = history.query(50, 70, 10, config.capacity);
+        assert_eq!(series.interval, 10);
+        assert_eq!(series.candles.len(), 3);
+        // Ranges the finest resolution dropped, or too many points, use a coarser one
+        assert_eq!(history.query(0, 70, 10, config.capacity).interval, 40);
+        assert_eq!(history.query(40, 70, 2, config.capacity).candles, vec![coarse[1]]);
+        // Without a fitting resolution the most recent coarse candles are kept
+        assert_eq!(history.query(0, 70, 1, config.capacity).candles, vec![coarse[1]]);
+    }
+
+    #[test]
+    fn test_invalid_configs_rejected() {
+        let invalid = [
+            (SamplerConfig::new(0), SamplingError::ZeroInterval),
+            (SamplerConfig::new(1).with_capacity(0), SamplingError::ZeroCapacity),
+            (
+                SamplerConfig::new(1).with_downsampling(1, 3),
+                SamplingError::InvalidDownsampling { factor: 1, resolutions: 3 },
+            ),
+            (
+                SamplerConfig::new(1).with_downsampling(2, 65),
+                SamplingError::IntervalOverflow { interval: 1, factor: 2, resolutions: 65 },
+            ),
+            (
+                SamplerConfig::new(u64::MAX / 2).with_downsampling(3, 2),
+                SamplingError::IntervalOverflow { interval: u64::MAX / 2, factor: 3, resolutions: 2 },
+            ),
+            (SamplerConfig::new(1).with_metrics(&[]), SamplingError::NoMetrics),
+        ];
+        for (config, error) in invalid {
+            assert_eq!(PoolSampler::new(config).err(), Some(error));
+        }
+    }
+}
@@ -0,0 +1,196 @@
+use serde::{Deserialize, Serialize};
+
+use crate::core::{pool_manager::PoolId, state::Pool};
+
+/// Pool metric recorded by a sampler
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Metric {
+    /// Price of token0 in token1
+    Price,
+    /// Current tick
+    Tick,
+    /// Liquidity active at the current tick
+    Liquidity,
+    /// Token0 the active liquidity would hold over the whole price range,
+    /// `liquidity / sqrt(price)`
+    VirtualReserve0,
+    /// Token1 the active liquidity would hold over the whole price range,
+    /// `liquidity * sqrt(price)`
+    VirtualReserve1,
+}
+
+impl Metric {
+    /// Every metric, in order
+    pub const ALL: [Metric; 5] = [
+        Metric::Price,
+        Metric::Tick,
+        Metric::Liquidity,
+        Metric::VirtualReserve0,
+        Metric::VirtualReserve1,
+    ];
+
+    /// Reads the metric from a pool
+    pub fn read(self, pool: &Pool) -> f64 {
+        let price = pool.slot0.sqrt_price_x96.to_price_f64();
+        let liquidity = pool.liquidity.as_u128() as f64;
+        match self {
+            Metric::Price => price,
+            Metric::Tick => pool.slot0.tick as f64,
+            Metric::Liquidity => liquidity,
+            Metric::VirtualReserve0 => liquidity / price.sqrt(),
+            Metric::VirtualReserve1 => liquidity * price.sqrt(),
+        }
+    }
+}
+
+/// Samples of a metric within one period
+///
+/// At the finest resolution each period usually holds a single sample, so
+/// all four values are equal.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Candle {
+    /// Start of the period
+    pub timestamp: u64,
+    /// First sample in the period
+    pub open: f64,
+    /// Highest sample in the period
+    pub high: f64,
+    /// Lowest sample in the period
+    pub low: f64,
+    /// Last sample in the period
+    pub close: f64,
+}
+
+impl Candle {
+    /// Creates the candle of a period from its first sample
+    pub fn new(timestamp: u64, value: f64) -> Self {
+        Self { timestamp, open: value, high: value, low: value, close: value }
+    }
+
+    /// Adds a later sample in the same period
+    pub fn update(&mut self, value: f64) {
+        self.high = self.high.max(value);
+        self.low = self.low.min(value);
+        self.close = value;
+    }
+}
+
+/// Candles of a metric at one resolution, oldest first
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Series {
+    /// Length of each candle's period in seconds
+    pub interval: u64,
+    /// The candles
+    pub candles: Vec<Candle>,
+}
+
+/// How often a sampler records metrics and how much history it keeps
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SamplerConfig {
+    /// Seconds between samples, which is also the finest resolution
+    pub interval: u64,
+    /// Candles kept at each resolution
+    pub capacity: usize,
+    /// Each coarser resolution's period is this many times the previous one
+    pub downsample_factor: u64,
+    /// Number of resolutions, including the finest
+    pub resolutions: usize,
+    /// Metrics recorded for every tracked pool
+    pub metrics: Vec<Metric>,
+}
+
+impl SamplerConfig {
+    /// Records every metric every `interval` seconds, keeping 1024 candles at
+    /// the finest resolution only
+    pub fn new(interval: u64) -> Self {
+        Self {
+            interval,
+            capacity: 1024,
+            downsample_factor: 1,
+            resolutions: 1,
+            metrics: Metric::ALL.to_vec(),
+        }
+    }
+
+    /// Sets the number of candles kept at each resolution
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Keeps `resolutions` resolutions, each `factor` times coarser than the
+    /// previous one
+    pub fn with_downsampling(mut self, factor: u64, resolutions: usize) -> Self {
+        self.downsample_factor = factor;
+        self.resolutions = resolutions;
+        self
+    }
+
+    /// Records only the given metrics
+    pub fn with_metrics(mut self, metrics: &[Metric]) -> Self {
+        self.metrics = metrics.to_vec();
+        self
+    }
+
+    /// Seconds per candle at a resolution, `level` 0 being the finest, or
+    /// `None` when it overflows
+    pub fn interval_at(&self, level: usize) -> Option<u64> {
+        let level = u32::try_from(level).ok()?;
+        self.downsample_factor.checked_pow(level)?.checked_mul(self.interval)
+    }
+
+    /// Checks that the configuration can be sampled
+    pub fn validate(&self) -> SamplingResult<()> {
+        if self.interval == 0 {
+            return Err(SamplingError::ZeroInterval);
+        }
+        if self.capacity == 0 {
+            return Err(SamplingError::ZeroCapacity);
+        }
+        if self.resolutions == 0 || (self.resolutions > 1 && self.downsample_factor < 2) {
+            return Err(SamplingError::InvalidDownsampling {
+                factor: self.downsample_factor,
+                resolutions: self.resolutions,
+            });
+        }
+        if self.interval_at(self.resolutions - 1).is_none() {
+            return Err(SamplingError::IntervalOverflow {
+                interval: self.interval,
+                factor: self.downsample_factor,
+                resolutions: self.resolutions,
+            });
+        }
+        if self.metrics.is_empty() {
+            return Err(SamplingError::NoMetrics);
+        }
+        Ok(())
+    }
+}
+
+/// Error types for sampling
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum SamplingError {
+    #[error("Sampling interval must be at least one second")]
+    ZeroInterval,
+
+    #[error("Sampler must keep at least one candle")]
+    ZeroCapacity,
+
+    #[error("Invalid downsampling: factor {factor} with {resolutions} resolutions")]
+    InvalidDownsampling { factor: u64, resolutions: usize },
+
+    #[error("Interval {interval} downsampled by {factor} over {resolutions} resolutions overflows")]
+    IntervalOverflow { interval: u64, factor: u64, resolutions: usize },
+
+    #[error("Sampler records no metrics")]
+    NoMetrics,
+
+    #[error("Pool {0} is not tracked")]
+    PoolNotTracked(PoolId),
+
+    #[error("Metric {0:?} is not recorded")]
+    MetricNotRecorded(Metric),
+}
+
+/// Result type for sampling
+pub type SamplingResult<T> = std::result::Result<T, SamplingError>;
@@ -0,0 +1,35 @@
+//! Scenarios of pool operations described in JSON
+//!
+//! A [`Scenario`] names the pools to initialize and lists the operations to
+//! run on them, so a sequence that exposes a bug can be shared as a file and
+//! rerun by anyone, from Rust or with the `uniswap-v4-sim` binary. Running a
+//! scenario records the outcome of every step; failing steps are reported,
+//! not fatal, since they are usually what the scenario is about.
+
+pub mod runner;
+
+pub use runner::*;
+
+use thiserror::Error;
+
+/// Error types for scenarios
+///
+/// These stop a scenario before its steps run; steps that fail are recorded
+/// in its [`StepOutcome`]s instead.
+#[derive(Debug, Error)]
+pub enum ScenarioError {
+    #[error("Invalid scenario: {0}")]
+    Parse(#[from] serde_json::Error),
+
+    #[error("Pool {0} is defined twice")]
+    DuplicatePool(String),
+
+    #[error("Step {step} uses unknown pool {pool}")]
+    UnknownPool { step: usize, pool: String },
+
+    #[error("Pool {pool} cannot be initialized: {reason}")]
+    InvalidPool { pool: String, reason: String },
+}
+
+/// Result type for scenarios
+pub type ScenarioResult<T> = std::result::Result<T, ScenarioError>;
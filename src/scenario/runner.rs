@@ -0,0 +1,322 @@
+use std::collections::BTreeMap;
+
+use ethers::types::{Address, H256, U256};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::core::{
+    hooks::hook_interface::ModifyLiquidityParams,
+    math::{tick_math::TickMath, types::{SqrtPrice, TickSpacing}},
+    pool_manager::{ManagerPoolKey, PoolManager},
+    state::{BalanceDelta, Salt},
+};
+
+use super::{ScenarioError, ScenarioResult};
+
+/// Pools to initialize and operations to run on them
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Scenario {
+    pub pools: Vec<PoolSpec>,
+    #[serde(default)]
+    pub steps: Vec<Step>,
+}
+
+/// A pool of a scenario, initialized before its steps run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoolSpec {
+    /// Name steps refer to the pool by
+    pub name: String,
+    pub currency0: Address,
+    pub currency1: Address,
+    pub fee: u32,
+    pub tick_spacing: i32,
+    #[serde(default)]
+    pub hooks: Address,
+    /// Starting tick, used unless a starting price is given
+    #[serde(default)]
+    pub tick: i32,
+    /// Starting price, overriding `tick`
+    #[serde(default)]
+    pub sqrt_price_x96: Option<U256>,
+}
+
+impl PoolSpec {
+    /// Gets the key of the pool
+    pub fn key(&self) -> ScenarioResult<ManagerPoolKey> {
+        let invalid = |reason: String| ScenarioError::InvalidPool { pool: self.name.clone(), reason };
+        let tick_spacing = TickSpacing::new(self.tick_spacing).map_err(|e| invalid(e.to_string()))?;
+        ManagerPoolKey::new(self.currency0, self.currency1, self.fee, tick_spacing, self.hooks)
+            .map_err(|e| invalid(e.to_string()))
+    }
+
+    /// Gets the starting price of the pool
+    pub fn sqrt_price(&self) -> ScenarioResult<SqrtPrice> {
+        match self.sqrt_price_x96 {
+            Some(sqrt_price_x96) => Ok(SqrtPrice::new(sqrt_price_x96)),
+            None => SqrtPrice::from_tick(self.tick)
+                .map_err(|e| ScenarioError::InvalidPool { pool: self.name.clone(), reason: e.to_string() }),
+        }
+    }
+}
+
+/// An operation of a scenario
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Step {
+    ModifyLiquidity {
+        pool: String,
+        #[serde(default)]
+        owner: Address,
+        tick_lower: i32,
+        tick_upper: i32,
+        #[serde(with = "amount")]
+        liquidity_delta: i128,
+        #[serde(default)]
+        salt: H256,
+    },
+    Swap {
+        pool: String,
+        zero_for_one: bool,
+        /// Negative for exact input, positive for exact output
+        #[serde(with = "amount")]
+        amount_specified: i128,
+        /// Price limit, by default the bound of the swap's direction
+        #[serde(default)]
+        sqrt_price_limit_x96: Option<U256>,
+    },
+    Donate {
+        pool: String,
+        #[serde(default)]
+        sender: Address,
+        #[serde(with = "amount")]
+        amount0: u128,
+        #[serde(with = "amount")]
+        amount1: u128,
+    },
+    SetTimestamp {
+        timestamp: u64,
+    },
+}
+
+impl Step {
+    /// Name of the pool the step operates on, if any
+    pub fn pool(&self) -> Option<&str> {
+        match self {
+            Step::ModifyLiquidity { pool, .. } | Step::Swap { pool, .. } | Step::Donate { pool, .. } => Some(pool),
+            Step::SetTimestamp { .. } => None,
+        }
+    }
+}
+
+/// 128-bit amounts as decimal strings, also accepting JSON numbers small
+/// enough for serde to buffer, which tagged enums need
+mod amount {
+    use std::{fmt::Display, str::FromStr};
+
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Repr {
+        Signed(i64),
+        Unsigned(u64),
+        Decimal(String),
+    }
+
+    pub fn serialize<T: Display, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(value)
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: FromStr + TryFrom<i64> + TryFrom<u64>,
+        D: Deserializer<'de>,
+    {
+        let value = match Repr::deserialize(deserializer)? {
+            Repr::Signed(value) => T::try_from(value).ok(),
+            Repr::Unsigned(value) => T::try_from(value).ok(),
+            Repr::Decimal(value) => value.parse().ok(),
+        };
+        value.ok_or_else(|| D::Error::custom("amount out of range"))
+    }
+}
+
+/// What a step did: the delta it left the caller with, or why it failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StepOutcome {
+    pub step: usize,
+    pub result: Result<(i128, i128), String>,
+}
+
+/// A scenario after its steps ran
+pub struct ScenarioRun {
+    /// Manager holding the final state
+    pub manager: PoolManager,
+    /// Keys of the pools by name
+    pub keys: BTreeMap<String, ManagerPoolKey>,
+    pub outcomes: Vec<StepOutcome>,
+}
+
+impl ScenarioRun {
+    /// Gets the outcomes and final state as JSON, with amounts as decimal
+    /// strings since they can exceed JSON's safe integers
+    pub fn to_json(&self) -> Value {
+        let steps: Vec<Value> = self.outcomes
+            .iter()
+            .map(|outcome| match &outcome.result {
+                Ok((amount0, amount1)) => json!({
+                    "step": outcome.step,
+                    "amount0": amount0.to_string(),
+                    "amount1": amount1.to_string(),
+                }),
+                Err(error) => json!({ "step": outcome.step, "error": error }),
+            })
+            .collect();
+        let state: Value = serde_json::from_slice(&self.manager.export_state()).expect("exported state is JSON");
+        json!({ "steps": steps, "state": state })
+    }
+}
+
+impl Scenario {
+    /// Parses a scenario from JSON
+    pub fn from_json(json: &str) -> ScenarioResult<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Initializes the pools on a new manager and runs the steps in order
+    pub fn run(&self) -> ScenarioResult<ScenarioRun> {
+        self.run_on(PoolManager::new())
+    }
+
+    /// Initializes the pools on `manager` and runs the steps in order
+    ///
+    /// The manager can be set up beforehand, say with the hooks the pools
+    /// use registered.
+    pub fn run_on(&self, mut manager: PoolManager) -> ScenarioResult<ScenarioRun> {
+        let mut keys = BTreeMap::new();
+        for pool in &self.pools {
+            if keys.insert(pool.name.clone(), pool.key()?).is_some() {
+                return Err(ScenarioError::DuplicatePool(pool.name.clone()));
+            }
+        }
+        for (step, pool) in self.steps.iter().enumerate().filter_map(|(index, step)| Some((index, step.pool()?))) {
+            if !keys.contains_key(pool) {
+                return Err(ScenarioError::UnknownPool { step, pool: pool.to_string() });
+            }
+        }
+
+        for pool in &self.pools {
+            manager
+                .initialize_pool(keys[&pool.name].clone(), pool.sqrt_price()?)
+                .map_err(|e| ScenarioError::InvalidPool { pool: pool.name.clone(), reason: e.to_string() })?;
+        }
+        let outcomes = self.steps
+            .iter()
+            .enumerate()
+            .map(|(index, step)| StepOutcome {
+                step: index,
+                result: run_step(&mut manager, &keys, step)
+                    .map(|delta| (delta.amount0(), delta.amount1()))
+                    .map_err(|e| e.to_string()),
+            })
+            .collect();
+        Ok(ScenarioRun { manager, keys, outcomes })
+    }
+}
+
+fn run_step(
+    manager: &mut PoolManager,
+    keys: &BTreeMap<String, ManagerPoolKey>,
+    step: &Step,
+) -> crate::core::state::Result<BalanceDelta> {
+    match step {
+        Step::ModifyLiquidity { pool, owner, tick_lower, tick_upper, liquidity_delta, salt } => {
+            let params = ModifyLiquidityParams {
+                owner: *owner,
+                tick_lower: *tick_lower,
+                tick_upper: *tick_upper,
+                liquidity_delta: *liquidity_delta,
+                salt: Salt(salt.0),
+            };
+            manager.modify_liquidity(keys[pool].clone(), params, &[]).map(|(delta, _)| delta)
+        }
+        Step::Swap { pool, zero_for_one, amount_specified, sqrt_price_limit_x96 } => {
+            let limit = sqrt_price_limit_x96.unwrap_or(if *zero_for_one {
+                TickMath::MIN_SQRT_PRICE + 1
+            } else {
+                TickMath::MAX_SQRT_PRICE - 1
+            });
+            manager.swap(&keys[pool], *zero_for_one, *amount_specified, limit, &[])
+        }
+        Step::Donate { pool, sender, amount0, amount1 } => manager.donate(&keys[pool], *sender, *amount0, *amount1, &[]),
+        Step::SetTimestamp { timestamp } => {
+            manager.set_timestamp(*timestamp);
+            Ok(BalanceDelta::default())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCENARIO: &str = r#"{
+        "pools": [
+            { "name": "a", "currency0": "0x0000000000000000000000000000000000000001",
+              "currency1": "0x0000000000000000000000000000000000000002", "fee": 3000, "tick_spacing": 60 }
+        ],
+        "steps": [
+            { "op": "modify_liquidity", "pool": "a", "tick_lower": -600, "tick_upper": 600, "liquidity_delta": 1000000000 },
+            { "op": "set_timestamp", "timestamp": 100 },
+            { "op": "swap", "pool": "a", "zero_for_one": true, "amount_specified": -1000000 },
+            { "op": "swap", "pool": "a", "zero_for_one": true, "amount_specified": "0" }
+        ]
+    }"#;
+
+    #[test]
+    fn test_run_scenario() {
+        let scenario = Scenario::from_json(SCENARIO).unwrap();
+        assert_eq!(scenario.steps[1], Step::SetTimestamp { timestamp: 100 });
+        // Amounts round-trip as decimal strings
+        let json = serde_json::to_string(&scenario).unwrap();
+        assert!(json.contains(r#""amount_specified":"-1000000""#));
+        assert_eq!(Scenario::from_json(&json).unwrap(), scenario);
+        let run = scenario.run().unwrap();
+        assert_eq!(run.outcomes.len(), 4);
+        let (amount0, amount1) = run.outcomes[0].result.clone().unwrap();
+        assert!(amount0 < 0 && amount1 < 0);
+        let (amount0, amount1) = run.outcomes[2].result.clone().unwrap();
+        assert_eq!(amount0, -1_000_000);
+        assert!(amount1 > 0);
+        // Failing steps are recorded and the rest still run
+        assert!(run.outcomes[3].result.is_err());
+        assert_eq!(run.manager.timestamp(), 100);
+
+        let json = run.to_json();
+        assert_eq!(json["steps"][2]["amount0"], "-1000000");
+        assert!(json["steps"][3]["error"].is_string());
+        assert_eq!(json["state"]["pools"].as_array().unwrap().len(), 1);
+
+        // Runs are reproducible
+        assert_eq!(scenario.run().unwrap().to_json(), json);
+    }
+
+    #[test]
+    fn test_invalid_scenarios() {
+        let mut scenario = Scenario::from_json(SCENARIO).unwrap();
+        scenario.pools.push(scenario.pools[0].clone());
+        assert!(matches!(scenario.run(), Err(ScenarioError::DuplicatePool(name)) if name == "a"));
+
+        let mut scenario = Scenario::from_json(SCENARIO).unwrap();
+        scenario.steps.push(Step::Donate { pool: "b".into(), sender: Address::zero(), amount0: 1, amount1: 1 });
+        assert!(matches!(scenario.run(), Err(ScenarioError::UnknownPool { step: 4, .. })));
+
+        let mut scenario = Scenario::from_json(SCENARIO).unwrap();
+        scenario.pools[0].tick = TickMath::MAX_TICK + 1;
+        assert!(matches!(scenario.run(), Err(ScenarioError::InvalidPool { .. })));
+        assert!(matches!(Scenario::from_json("{}"), Err(ScenarioError::Parse(_))));
+        let negative_donation = SCENARIO.replace(r#""timestamp": 100"#, r#""timestamp": 100 },
+            { "op": "donate", "pool": "a", "amount0": -1, "amount1": 0"#);
+        assert!(matches!(Scenario::from_json(&negative_donation), Err(ScenarioError::Parse(_))));
+    }
+}
@@ -0,0 +1,32 @@
+//! Many independent simulations in one process
+//!
+//! A [`SimulationRegistry`] keeps a [`PoolManager`](crate::core::PoolManager)
+//! per simulation id, so variants of a setup, say with different fees or
+//! hooks, can run the same workload side by side and be compared. The
+//! managers share read-only math tables and nothing else, and the registry
+//! can snapshot and restore all of them at once to rerun a workload from the
+//! same starting point.
+
+pub mod registry;
+
+pub use registry::*;
+
+use thiserror::Error;
+
+use crate::core::state::StateError;
+
+/// Error types for simulation registries
+#[derive(Debug, Error)]
+pub enum SimulationError {
+    #[error("Simulation {0} already exists")]
+    DuplicateSimulation(String),
+
+    #[error("Unknown simulation {0}")]
+    UnknownSimulation(String),
+
+    #[error("State error in simulation {id}: {source}")]
+    State { id: String, source: StateError },
+}
+
+/// Result type for simulation registries
+pub type SimulationResult<T> = std::result::Result<T, SimulationError>;
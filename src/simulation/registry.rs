@@ -0,0 +1,232 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use crate::core::{
+    math::SqrtPriceTable,
+    pool_manager::{ManagerSnapshot, PoolManager},
+    state::StateError,
+};
+
+use super::{SimulationError, SimulationResult};
+
+/// Pool managers of independent simulations, by simulation id
+///
+/// Every manager has its own pools, claims, hooks and clock. When the
+/// registry has a [`SqrtPriceTable`], every manager it creates or takes in
+/// shares it, so the table is computed once for all of them.
+#[derive(Default)]
+pub struct SimulationRegistry {
+    managers: BTreeMap<String, PoolManager>,
+    sqrt_price_table: Option<Arc<SqrtPriceTable>>,
+}
+
+/// Snapshots of the managers of a registry, by simulation id
+#[derive(Debug, Clone, Default)]
+pub struct RegistrySnapshot {
+    managers: BTreeMap<String, ManagerSnapshot>,
+}
+
+impl RegistrySnapshot {
+    /// Gets the snapshot of a simulation
+    pub fn get(&self, id: &str) -> Option<&ManagerSnapshot> {
+        self.managers.get(id)
+    }
+
+    /// Ids of the simulations in the snapshot, in order
+    pub fn ids(&self) -> impl Iterator<Item = &str> {
+        self.managers.keys().map(String::as_str)
+    }
+
+    /// Number of simulations in the snapshot
+    pub fn len(&self) -> usize {
+        self.managers.len()
+    }
+
+    /// Whether the snapshot holds no simulation
+    pub fn is_empty(&self) -> bool {
+        self.managers.is_empty()
+    }
+}
+
+impl SimulationRegistry {
+    /// Creates an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an empty registry whose managers share a table of tick sqrt
+    /// prices
+    pub fn with_sqrt_price_table(table: Arc<SqrtPriceTable>) -> Self {
+        Self { managers: BTreeMap::new(), sqrt_price_table: Some(table) }
+    }
+
+    /// Gets the table of tick sqrt prices the managers share, if any
+    pub fn sqrt_price_table(&self) -> Option<&Arc<SqrtPriceTable>> {
+        self.sqrt_price_table.as_ref()
+    }
+
+    /// Creates a simulation with a new manager
+    pub fn create(&mut self, id: impl Into<String>) -> SimulationResult<&mut PoolManager> {
+        self.insert(id, PoolManager::new())
+    }
+
+    /// Adds a simulation running `manager`, sharing the registry's table
+    /// with it if the registry has one
+    pub fn insert(&mut self, id: impl Into<String>, mut manager: PoolManager) -> SimulationResult<&mut PoolManager> {
+        let id = id.into();
+        if self.managers.contains_key(&id) {
+            return Err(SimulationError::DuplicateSimulation(id));
+        }
+        if self.sqrt_price_table.is_some() {
+            manager.set_sqrt_price_table(self.sqrt_price_table.clone());
+        }
+        Ok(self.managers.entry(id).or_insert(manager))
+    }
+
+    /// Gets the manager of a simulation
+    pub fn get(&self, id: &str) -> Option<&PoolManager> {
+        self.managers.get(id)
+    }
+
+    /// Gets the manager of a simulation mutably
+    pub fn get_mut(&mut self, id: &str) -> Option<&mut PoolManager> {
+        self.managers.get_mut(id)
+    }
+
+    /// Removes a simulation, returning its manager
+    pub fn remove(&mut self, id: &str) -> Option<PoolManager> {
+        self.managers.remove(id)
+    }
+
+    /// Ids of the simulations, in order
+    pub fn ids(&self) -> impl Iterator<Item = &str> {
+        self.managers.keys().map(String::as_str)
+    }
+
+    /// The simulations and their managers, in id order
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&str, &mut PoolManager)> {
+        self.managers.iter_mut().map(|(id, manager)| (id.as_str(), manager))
+    }
+
+    /// Number of simulations
+    pub fn len(&self) -> usize {
+        self.managers.len()
+    }
+
+    /// Whether the registry holds no simulation
+    pub fn is_empty(&self) -> bool {
+        self.managers.is_empty()
+    }
+
+    /// Snapshots every manager, see [`PoolManager::snapshot`]
+    pub fn snapshot(&self) -> SimulationResult<RegistrySnapshot> {
+        let managers = self.managers
+            .iter()
+            .map(|(id, manager)| {
+                let snapshot = manager.snapshot().map_err(|source| SimulationError::State { id: id.clone(), source })?;
+                Ok((id.clone(), snapshot))
+            })
+            .collect::<SimulationResult<_>>()?;
+        Ok(RegistrySnapshot { managers })
+    }
+
+    /// Restores every manager in a snapshot, see [`PoolManager::restore`]
+    ///
+    /// Simulations added since the snapshot are left as they are. Nothing is
+    /// restored if a simulation in the snapshot was removed or can't be
+    /// restored now.
+    pub fn restore(&mut self, snapshot: &RegistrySnapshot) -> SimulationResult<()> {
+        for id in snapshot.ids() {
+            let manager = self.managers.get(id).ok_or_else(|| SimulationError::UnknownSimulation(id.to_string()))?;
+            if manager.is_unlocked() {
+                return Err(SimulationError::State { id: id.to_string(), source: StateError::ManagerUnlocked });
+            }
+        }
+        for (id, manager_snapshot) in &snapshot.managers {
+            let manager = self.managers.get_mut(id).expect("checked above");
+            manager
+                .restore(manager_snapshot)
+                .map_err(|source| SimulationError::State { id: id.clone(), source })?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::Address;
+    use crate::core::{
+        hooks::hook_interface::ModifyLiquidityParams,
+        math::{types::{SqrtPrice, TickSpacing}, TickMath},
+        pool_manager::ManagerPoolKey,
+        SwapAmount,
+    };
+
+    fn key(fee: u32) -> ManagerPoolKey {
+        let spacing = TickSpacing::new(60).unwrap();
+        ManagerPoolKey::new(Address::from_low_u64_be(1), Address::from_low_u64_be(2), fee, spacing, Address::zero()).unwrap()
+    }
+
+    /// The same pool at two fees, with the same liquidity
+    fn fee_variants() -> SimulationRegistry {
+        let table = SqrtPriceTable::new(TickSpacing::new(60).unwrap()).unwrap();
+        let mut registry = SimulationRegistry::with_sqrt_price_table(Arc::new(table));
+        for (id, fee) in [("low_fee", 500), ("high_fee", 10_000)] {
+            let manager = registry.create(id).unwrap();
+            manager.initialize_pool(key(fee), SqrtPrice::ONE).unwrap();
+            let params = ModifyLiquidityParams::default_position(Address::repeat_byte(1), -600, 600, 1_000_000_000);
+            manager.modify_liquidity(key(fee), params, &[]).unwrap();
+        }
+        registry
+    }
+
+    fn run_workload(registry: &mut SimulationRegistry) -> Vec<i128> {
+        registry
+            .iter_mut()
+            .map(|(id, manager)| {
+                let fee = if id == "low_fee" { 500 } else { 10_000 };
+                let limit = TickMath::MIN_SQRT_PRICE + 1;
+                manager.swap_exact(&key(fee), true, SwapAmount::ExactIn(1_000_000), limit, &[]).unwrap().amount1()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_simulations_are_independent_and_share_tables() {
+        let mut registry = fee_variants();
+        assert!(matches!(registry.create("low_fee"), Err(SimulationError::DuplicateSimulation(_))));
+        assert_eq!(registry.ids().collect::<Vec<_>>(), vec!["high_fee", "low_fee"]);
+
+        // The higher fee pays out less for the same input
+        let outputs = run_workload(&mut registry);
+        assert!(outputs[0] < outputs[1], "{outputs:?}");
+        let pool = registry.get("high_fee").unwrap().get_pool(&key(10_000)).unwrap();
+        assert_eq!(pool.slot0.lp_fee.get(), 10_000);
+
+        let table = registry.sqrt_price_table().unwrap();
+        for fee in [500, 10_000] {
+            let id = if fee == 500 { "low_fee" } else { "high_fee" };
+            let pool = registry.get(id).unwrap().get_pool(&key(fee)).unwrap();
+            assert!(Arc::ptr_eq(pool.sqrt_price_cache().table().unwrap(), table));
+        }
+    }
+
+    #[test]
+    fn test_bulk_snapshot_and_restore() {
+        let mut registry = fee_variants();
+        let snapshot = registry.snapshot().unwrap();
+        assert_eq!(snapshot.len(), 2);
+
+        let first = run_workload(&mut registry);
+        let moved = run_workload(&mut registry);
+        assert_ne!(first, moved);
+
+        // Both simulations rerun the workload from the same state
+        registry.restore(&snapshot).unwrap();
+        assert_eq!(run_workload(&mut registry), first);
+
+        registry.remove("low_fee");
+        assert!(matches!(registry.restore(&snapshot), Err(SimulationError::UnknownSimulation(id)) if id == "low_fee"));
+    }
+}
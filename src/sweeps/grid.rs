@@ -0,0 +1,351 @@
+use std::sync::Arc;
+
+use ethers::types::Address;
+use rayon::prelude::*;
+use serde::Serialize;
+
+use crate::core::{
+    hooks::hook_interface::HookWithReturns,
+    pool_manager::{ManagerPoolKey, PoolManager},
+    Rng,
+};
+use crate::scenario::{Scenario, Step};
+
+use super::{SweepFailure, SweepRow, SweepTable};
+
+/// Makes the random steps of a point, appended to the scenario's own steps
+pub type StepGenerator = Arc<dyn Fn(&SweepPoint, &mut Rng) -> Vec<Step> + Send + Sync>;
+
+/// Computes a custom metric of a pool from the final state of a point
+pub type PoolMetric = Arc<dyn Fn(&PoolManager, &ManagerPoolKey) -> f64 + Send + Sync>;
+
+/// A hook setup the pools of a sweep can run with
+///
+/// The hook is created anew for every point, so points don't share its state.
+#[derive(Clone)]
+pub struct HookConfig {
+    name: String,
+    address: Address,
+    factory: Option<Arc<dyn Fn() -> Box<dyn HookWithReturns> + Send + Sync>>,
+}
+
+impl HookConfig {
+    /// Runs the pools with the hook `factory` makes, at `address`, whose
+    /// flags must match the hook's permissions
+    pub fn new(
+        name: impl Into<String>,
+        address: Address,
+        factory: impl Fn() -> Box<dyn HookWithReturns> + Send + Sync + 'static,
+    ) -> Self {
+        Self { name: name.into(), address, factory: Some(Arc::new(factory)) }
+    }
+
+    /// Runs the pools without hooks
+    pub fn none() -> Self {
+        Self { name: "none".to_string(), address: Address::zero(), factory: None }
+    }
+
+    /// Name of the configuration, as shown in the table
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Hook address the pools use
+    pub fn address(&self) -> Address {
+        self.address
+    }
+}
+
+/// A combination of parameters a sweep runs the scenario with
+///
+/// Parameters left out of the grid are `None`, and the pools keep the values
+/// the scenario gives them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SweepPoint {
+    /// Position of the point in the grid
+    pub index: usize,
+    pub fee: Option<u32>,
+    pub tick_spacing: Option<i32>,
+    /// Name of the hook configuration
+    pub hooks: Option<String>,
+    /// Seed of the point's random steps
+    pub seed: u64,
+}
+
+/// A scenario and the grid of parameters to run it with
+///
+/// Fees, tick spacings and hooks apply to every pool of the scenario. The
+/// grid is their product with the seeds, fees varying slowest.
+#[derive(Clone)]
+pub struct Sweep {
+    scenario: Scenario,
+    fees: Vec<u32>,
+    tick_spacings: Vec<i32>,
+    hooks: Vec<HookConfig>,
+    seeds: Vec<u64>,
+    generator: Option<StepGenerator>,
+    metrics: Vec<(String, PoolMetric)>,
+}
+
+impl Sweep {
+    /// Sweeps `scenario` over a grid of a single point, seed zero
+    pub fn new(scenario: Scenario) -> Self {
+        Self {
+            scenario,
+            fees: Vec::new(),
+            tick_spacings: Vec::new(),
+            hooks: Vec::new(),
+            seeds: vec![0],
+            generator: None,
+            metrics: Vec::new(),
+        }
+    }
+
+    /// Sets the fees to run the pools with
+    pub fn with_fees(mut self, fees: impl IntoIterator<Item = u32>) -> Self {
+        self.fees = fees.into_iter().collect();
+        self
+    }
+
+    /// Sets the tick spacings to run the pools with
+    pub fn with_tick_spacings(mut self, tick_spacings: impl IntoIterator<Item = i32>) -> Self {
+        self.tick_spacings = tick_spacings.into_iter().collect();
+        self
+    }
+
+    /// Sets the hook configurations to run the pools with, which should have
+    /// distinct names
+    pub fn with_hooks(mut self, hooks: impl IntoIterator<Item = HookConfig>) -> Self {
+        self.hooks = hooks.into_iter().collect();
+        self
+    }
+
+    /// Sets the seeds to run every combination of parameters with; an empty
+    /// list runs none
+    pub fn with_seeds(mut self, seeds: impl IntoIterator<Item = u64>) -> Self {
+        self.seeds = seeds.into_iter().collect();
+        self
+    }
+
+    /// Appends the steps `generator` makes for every point, from the point's
+    /// seeded generator, to the scenario's steps
+    pub fn with_steps(mut self, generator: impl Fn(&SweepPoint, &mut Rng) -> Vec<Step> + Send + Sync + 'static) -> Self {
+        self.generator = Some(Arc::new(generator));
+        self
+    }
+
+    /// Adds a metric column computed for every pool
+    pub fn with_metric(
+        mut self,
+        name: impl Into<String>,
+        metric: impl Fn(&PoolManager, &ManagerPoolKey) -> f64 + Send + Sync + 'static,
+    ) -> Self {
+        self.metrics.push((name.into(), Arc::new(metric)));
+        self
+    }
+
+    /// The points of the grid, in order
+    pub fn points(&self) -> Vec<SweepPoint> {
+        fn axis<T: Clone>(values: &[T]) -> Vec<Option<T>> {
+            if values.is_empty() {
+                vec![None]
+            } else {
+                values.iter().cloned().map(Some).collect()
+            }
+        }
+        let hook_names: Vec<String> = self.hooks.iter().map(|hook| hook.name.clone()).collect();
+
+        let mut points = Vec::new();
+        for fee in axis(&self.fees) {
+            for tick_spacing in axis(&self.tick_spacings) {
+                for hooks in axis(&hook_names) {
+                    for &seed in &self.seeds {
+                        let index = points.len();
+                        points.push(SweepPoint { index, fee, tick_spacing, hooks: hooks.clone(), seed });
+                    }
+                }
+            }
+        }
+        points
+    }
+
+    /// Runs every point in parallel
+    ///
+    /// Points whose pools can't be initialized, say for an invalid tick
+    /// spacing, are reported as failures; steps that fail are counted in the
+    /// rows.
+    pub fn run(&self) -> SweepTable {
+        let results: Vec<_> = self.points()
+            .into_par_iter()
+            .map(|point| {
+                let result = self.run_point(&point);
+                (point, result)
+            })
+            .collect();
+
+        let mut table = SweepTable::new(self.metrics.iter().map(|(name, _)| name.clone()).collect());
+        for (point, result) in results {
+            match result {
+                Ok(rows) => table.rows.extend(rows),
+                Err(error) => table.failures.push(SweepFailure { point, error }),
+            }
+        }
+        table
+    }
+
+    /// Runs one point on the current thread, giving the rows of its pools
+    pub fn run_point(&self, point: &SweepPoint) -> Result<Vec<SweepRow>, String> {
+        let hook = match &point.hooks {
+            Some(name) => Some(
+                self.hooks
+                    .iter()
+                    .find(|hook| &hook.name == name)
+                    .ok_or_else(|| format!("Unknown hook configuration {name}"))?,
+            ),
+            None => None,
+        };
+
+        let mut scenario = self.scenario.clone();
+        for pool in &mut scenario.pools {
+            pool.fee = point.fee.unwrap_or(pool.fee);
+            pool.tick_spacing = point.tick_spacing.unwrap_or(pool.tick_spacing);
+            pool.hooks = hook.map_or(pool.hooks, |hook| hook.address);
+        }
+        if let Some(generator) = &self.generator {
+            let mut rng = Rng::seed_from_u64(point.seed);
+            scenario.steps.extend(generator(point, &mut rng));
+        }
+
+        let mut manager = PoolManager::new();
+        if let Some(HookConfig { address, factory: Some(factory), .. }) = hook {
+            manager.hook_registry_mut().register_hook(*address, factory());
+        }
+        let run = scenario.run_on(manager).map_err(|e| e.to_string())?;
+
+        let rows = scenario.pools
+            .iter()
+            .map(|pool| {
+                let key = &run.keys[&pool.name];
+                let mut row = SweepRow::new(point, &pool.name, key, &run.manager);
+                for outcome in &run.outcomes {
+                    let step = &scenario.steps[outcome.step];
+                    if step.pool() != Some(pool.name.as_str()) {
+                        continue;
+                    }
+                    match (&outcome.result, step) {
+                        (Ok((amount0, amount1)), Step::Swap { .. }) => {
+                            row.swaps += 1;
+                            row.volume0 += amount0.unsigned_abs();
+                            row.volume1 += amount1.unsigned_abs();
+                        }
+                        (Ok(_), _) => {}
+                        (Err(_), _) => row.failed_steps += 1,
+                    }
+                }
+                for (name, metric) in &self.metrics {
+                    row.metrics.insert(name.clone(), metric(&run.manager, key));
+                }
+                row
+            })
+            .collect();
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{hooks::{HookFlags, ProtocolFeeHook}, math::Bps};
+
+    const SCENARIO: &str = r#"{
+        "pools": [
+            { "name": "eth_usdc", "currency0": "0x0000000000000000000000000000000000000001",
+              "currency1": "0x0000000000000000000000000000000000000002", "fee": 3000, "tick_spacing": 60 }
+        ],
+        "steps": [
+            { "op": "modify_liquidity", "pool": "eth_usdc", "tick_lower": -1200, "tick_upper": 1200, "liquidity_delta": 1000000000000 }
+        ]
+    }"#;
+
+    fn sweep() -> Sweep {
+        let hooks = HookFlags::new(HookFlags::AFTER_SWAP | HookFlags::AFTER_SWAP_RETURNS_DELTA)
+            .apply_to_address(Address::repeat_byte(0xA0));
+        let protocol_fee = HookConfig::new("protocol_fee", hooks, || {
+            Box::new(ProtocolFeeHook::new(Bps::new(30), Address::repeat_byte(0xFE)))
+        });
+        Sweep::new(Scenario::from_json(SCENARIO).unwrap())
+            .with_fees([500, 3000])
+            .with_hooks([HookConfig::none(), protocol_fee])
+            .with_seeds([1, 2])
+            .with_steps(|_, rng| {
+                (0..20)
+                    .map(|_| Step::Swap {
+                        pool: "eth_usdc".to_string(),
+                        zero_for_one: rng.next_bool(),
+                        amount_specified: -(rng.gen_range(1_000..1_000_000) as i128),
+                        sqrt_price_limit_x96: None,
+                    })
+                    .collect()
+            })
+            .with_metric("liquidity_millions", |manager, key| {
+                manager.get_pool(key).unwrap().liquidity.as_u128() as f64 / 1e6
+            })
+            .with_metric("hooked", |manager, key| {
+                manager.hook_registry().get_hook(&key.hooks()).is_some() as u8 as f64
+            })
+    }
+
+    #[test]
+    fn test_sweep_is_deterministic() {
+        let sweep = sweep();
+        let table = sweep.run();
+        assert!(table.failures().is_empty(), "{:?}", table.failures());
+        assert_eq!(table.rows().len(), 8);
+        assert_eq!(table, sweep.run());
+
+        // A point reruns alone to the same row
+        let point = &sweep.points()[5];
+        assert_eq!((point.fee, point.hooks.as_deref(), point.seed), (Some(3000), Some("none"), 2));
+        assert_eq!(sweep.run_point(point).unwrap()[0], table.rows()[5]);
+
+        for row in table.rows() {
+            assert_eq!(row.swaps, 20);
+            assert_eq!(row.metrics["liquidity_millions"], 1e6);
+            // Every point has its own hook
+            assert_eq!(row.metrics["hooked"], (row.hooks.as_deref() == Some("protocol_fee")) as u8 as f64);
+        }
+        // Points differing only in fee see the same workload, and the higher
+        // fee moves the price less
+        let start = Scenario::from_json(SCENARIO).unwrap().pools[0].sqrt_price().unwrap().to_u256();
+        let moved = |row: &SweepRow| row.sqrt_price_x96.max(start) - row.sqrt_price_x96.min(start);
+        for (low, high) in table.rows()[..4].iter().zip(&table.rows()[4..]) {
+            assert_eq!((low.seed, low.hooks.as_deref()), (high.seed, high.hooks.as_deref()));
+            assert_eq!((low.fee, high.fee), (500, 3000));
+            assert!(moved(high) < moved(low), "{} vs {}", moved(high), moved(low));
+        }
+
+        let csv = table.to_csv();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 9);
+        assert!(lines[0].starts_with("point,seed,fee,tick_spacing,hooks,pool,"));
+        assert!(lines[0].ends_with(",liquidity_millions,hooked"));
+        assert!(lines[1].starts_with("0,1,500,60,none,eth_usdc,20,0,"));
+    }
+
+    #[test]
+    fn test_points_that_cannot_run_are_reported() {
+        let mut scenario = Scenario::from_json(SCENARIO).unwrap();
+        scenario.steps.push(Step::Swap {
+            pool: "eth_usdc".to_string(),
+            zero_for_one: true,
+            amount_specified: 0,
+            sqrt_price_limit_x96: None,
+        });
+        let table = Sweep::new(scenario).with_tick_spacings([0, 10]).run();
+        // Failing steps are counted and the point still runs
+        assert_eq!(table.rows().len(), 1);
+        assert_eq!((table.rows()[0].point, table.rows()[0].failed_steps, table.rows()[0].swaps), (1, 1, 0));
+        assert_eq!(table.failures().len(), 1);
+        assert_eq!(table.failures()[0].point.tick_spacing, Some(0));
+    }
+}
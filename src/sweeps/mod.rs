@@ -0,0 +1,18 @@
+//! Parameter sweeps over scenarios
+//!
+//! A [`Sweep`] runs the same [`Scenario`](crate::scenario::Scenario) at every
+//! point of a grid of fees, tick spacings, hook configurations and seeds, and
+//! collects summary metrics of every pool into a [`SweepTable`] with one row
+//! per point and pool, ready for a dataframe.
+//!
+//! Points run in parallel on the rayon thread pool, each on its own manager
+//! with its own hooks, so they share no state. Random steps come from an
+//! [`Rng`](crate::core::Rng) seeded by the point's seed alone: points that
+//! differ only in fee, tick spacing or hooks see the same random workload,
+//! and a table is the same whatever the number of threads.
+
+pub mod grid;
+pub mod table;
+
+pub use grid::*;
+pub use table::*;
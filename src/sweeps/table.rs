@@ -0,0 +1,155 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use primitive_types::U256;
+use serde::Serialize;
+
+use crate::core::pool_manager::{ManagerPoolKey, PoolManager};
+
+use super::SweepPoint;
+
+/// Summary of a pool at the end of a point of a sweep
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SweepRow {
+    /// Index of the point
+    pub point: usize,
+    pub seed: u64,
+    /// Fee and tick spacing the pool ran with
+    pub fee: u32,
+    pub tick_spacing: i32,
+    /// Name of the hook configuration, if the grid has hooks
+    pub hooks: Option<String>,
+    /// Name of the pool in the scenario
+    pub pool: String,
+    /// Number of swaps that succeeded
+    pub swaps: usize,
+    /// Number of steps on the pool that failed
+    pub failed_steps: usize,
+    /// Amounts swapped in and out, in either direction
+    #[serde(with = "decimal")]
+    pub volume0: u128,
+    #[serde(with = "decimal")]
+    pub volume1: u128,
+    /// Final state of the pool
+    pub tick: i32,
+    #[serde(with = "decimal")]
+    pub sqrt_price_x96: U256,
+    #[serde(with = "decimal")]
+    pub liquidity: u128,
+    /// Custom metrics, by name
+    pub metrics: BTreeMap<String, f64>,
+}
+
+impl SweepRow {
+    /// A row for a pool with no swaps counted yet
+    pub(super) fn new(point: &SweepPoint, pool: &str, key: &ManagerPoolKey, manager: &PoolManager) -> Self {
+        let state = manager.get_pool(key).expect("scenario pools are initialized");
+        Self {
+            point: point.index,
+            seed: point.seed,
+            fee: key.fee(),
+            tick_spacing: key.tick_spacing().get(),
+            hooks: point.hooks.clone(),
+            pool: pool.to_string(),
+            swaps: 0,
+            failed_steps: 0,
+            volume0: 0,
+            volume1: 0,
+            tick: state.slot0.tick,
+            sqrt_price_x96: state.slot0.sqrt_price_x96.to_u256(),
+            liquidity: state.liquidity.as_u128(),
+            metrics: BTreeMap::new(),
+        }
+    }
+}
+
+/// A point of a sweep that couldn't run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SweepFailure {
+    pub point: SweepPoint,
+    pub error: String,
+}
+
+/// Results of a sweep, one row per point and pool in grid order
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SweepTable {
+    pub(super) metrics: Vec<String>,
+    pub(super) rows: Vec<SweepRow>,
+    pub(super) failures: Vec<SweepFailure>,
+}
+
+impl SweepTable {
+    pub(super) fn new(metrics: Vec<String>) -> Self {
+        Self { metrics, rows: Vec::new(), failures: Vec::new() }
+    }
+
+    /// Names of the custom metric columns
+    pub fn metric_names(&self) -> &[String] {
+        &self.metrics
+    }
+
+    /// The rows, in grid order
+    pub fn rows(&self) -> &[SweepRow] {
+        &self.rows
+    }
+
+    /// The points that couldn't run, in grid order
+    pub fn failures(&self) -> &[SweepFailure] {
+        &self.failures
+    }
+
+    /// Gets the rows as CSV with a header line, custom metrics last
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("point,seed,fee,tick_spacing,hooks,pool,swaps,failed_steps,volume0,volume1,tick,sqrt_price_x96,liquidity");
+        for name in &self.metrics {
+            csv.push(',');
+            csv.push_str(&csv_field(name));
+        }
+        csv.push('\n');
+        for row in &self.rows {
+            let _ = write!(
+                csv,
+                "{},{},{},{},{},{},{},{},{},{},{},{},{}",
+                row.point,
+                row.seed,
+                row.fee,
+                row.tick_spacing,
+                csv_field(row.hooks.as_deref().unwrap_or("")),
+                csv_field(&row.pool),
+                row.swaps,
+                row.failed_steps,
+                row.volume0,
+                row.volume1,
+                row.tick,
+                row.sqrt_price_x96,
+                row.liquidity,
+            );
+            for name in &self.metrics {
+                let _ = write!(csv, ",{}", row.metrics.get(name).copied().unwrap_or(f64::NAN));
+            }
+            csv.push('\n');
+        }
+        csv
+    }
+}
+
+/// Quotes a field that contains separators or quotes
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Large numbers as decimal strings, which JSON consumers can't misread as
+/// floats
+mod decimal {
+    use std::fmt::Display;
+
+    use serde::Serializer;
+
+    pub fn serialize<T: Display, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(value)
+    }
+}
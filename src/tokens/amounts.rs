@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+use primitive_types::U256;
+
+use crate::core::flash_loan::Currency;
+
+/// Decimals of the native currency
+pub const NATIVE_DECIMALS: u8 = 18;
+
+/// Errors converting between human readable and raw token amounts
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum AmountError {
+    #[error("Invalid amount: {0:?}")]
+    InvalidAmount(String),
+
+    #[error("Amount {amount:?} has more than {decimals} decimal places")]
+    TooManyDecimalPlaces { amount: String, decimals: u8 },
+
+    #[error("Amount {0:?} overflows 256 bits")]
+    Overflow(String),
+
+    #[error("Decimals of currency {0} are not registered")]
+    UnknownCurrency(Currency),
+}
+
+/// Result type for amount conversions
+pub type AmountResult<T> = std::result::Result<T, AmountError>;
+
+/// Parses a decimal amount such as `"1.5"` into raw units of a token with
+/// `decimals` decimals
+///
+/// Amounts with more decimal places than the token has are rejected rather
+/// than rounded, so a typo cannot silently lose value.
+pub fn parse_amount(amount: &str, decimals: u8) -> AmountResult<U256> {
+    let invalid = || AmountError::InvalidAmount(amount.to_string());
+    let (integer, fraction) = amount.split_once('.').unwrap_or((amount, ""));
+    let is_digits = |part: &str| part.bytes().all(|byte| byte.is_ascii_digit());
+    if integer.is_empty() || !is_digits(integer) || !is_digits(fraction) || amount.ends_with('.') {
+        return Err(invalid());
+    }
+    if fraction.len() > decimals as usize {
+        return Err(AmountError::TooManyDecimalPlaces { amount: amount.to_string(), decimals });
+    }
+
+    let padding = "0".repeat(decimals as usize - fraction.len());
+    let digits = format!("{integer}{fraction}{padding}");
+    let digits = digits.trim_start_matches('0');
+    if digits.is_empty() {
+        return Ok(U256::zero());
+    }
+    U256::from_dec_str(digits).map_err(|_| AmountError::Overflow(amount.to_string()))
+}
+
+/// Formats raw units of a token with `decimals` decimals as a decimal amount,
+/// without trailing zeros
+pub fn format_amount(amount: U256, decimals: u8) -> String {
+    let digits = format!("{:0>width$}", amount.to_string(), width = decimals as usize + 1);
+    let (integer, fraction) = digits.split_at(digits.len() - decimals as usize);
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        integer.to_string()
+    } else {
+        format!("{integer}.{fraction}")
+    }
+}
+
+/// Decimals of each currency, for converting amounts of many tokens
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CurrencyDecimals {
+    decimals: HashMap<Currency, u8>,
+}
+
+impl Default for CurrencyDecimals {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CurrencyDecimals {
+    /// Creates a registry that knows the native currency's decimals
+    pub fn new() -> Self {
+        Self {
+            decimals: HashMap::from([(Currency::Native, NATIVE_DECIMALS)]),
+        }
+    }
+
+    /// Registers the decimals of a currency
+    pub fn set(&mut self, currency: Currency, decimals: u8) -> &mut Self {
+        self.decimals.insert(currency, decimals);
+        self
+    }
+
+    /// Gets the decimals of a currency, if registered
+    pub fn decimals(&self, currency: Currency) -> Option<u8> {
+        self.decimals.get(&currency).copied()
+    }
+
+    /// Parses a decimal amount of a currency into raw units
+    pub fn parse(&self, currency: Currency, amount: &str) -> AmountResult<U256> {
+        parse_amount(amount, self.require(currency)?)
+    }
+
+    /// Formats raw units of a currency as a decimal amount
+    pub fn format(&self, currency: Currency, amount: U256) -> AmountResult<String> {
+        Ok(format_amount(amount, self.require(currency)?))
+    }
+
+    fn require(&self, currency: Currency) -> AmountResult<u8> {
+        self.decimals(currency).ok_or(AmountError::UnknownCurrency(currency))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::Address;
+
+    #[test]
+    fn test_parse_amount() {
+        assert_eq!(parse_amount("1.5", 6).unwrap(), U256::from(1_500_000));
+        assert_eq!(parse_amount("1", 18).unwrap(), U256::exp10(18));
+        assert_eq!(parse_amount("0.000001", 6).unwrap(), U256::one());
+        assert_eq!(parse_amount("007.10", 2).unwrap(), U256::from(710));
+        assert_eq!(parse_amount("0", 0).unwrap(), U256::zero());
+
+        for invalid in ["", ".5", "1.", "-1", "1.2.3", "1e18", " 1", "1,000"] {
+            assert_eq!(parse_amount(invalid, 18), Err(AmountError::InvalidAmount(invalid.to_string())));
+        }
+        assert!(matches!(parse_amount("1.0000001", 6), Err(AmountError::TooManyDecimalPlaces { decimals: 6, .. })));
+        assert!(matches!(parse_amount("1", 78), Err(AmountError::Overflow(_))));
+    }
+
+    #[test]
+    fn test_format_amount() {
+        assert_eq!(format_amount(U256::from(1_500_000), 6), "1.5");
+        assert_eq!(format_amount(U256::one(), 18), "0.000000000000000001");
+        assert_eq!(format_amount(U256::from(42), 0), "42");
+        assert_eq!(format_amount(U256::zero(), 6), "0");
+        assert_eq!(format_amount(U256::exp10(20), 18), "100");
+
+        for (amount, decimals) in [("123.456", 18), ("0.1", 1), ("99", 6)] {
+            assert_eq!(format_amount(parse_amount(amount, decimals).unwrap(), decimals), amount);
+        }
+        assert_eq!(format_amount(U256::MAX, 77).len(), U256::MAX.to_string().len() + 1);
+    }
+
+    #[test]
+    fn test_currency_decimals() {
+        let usdc = Currency::from_address(Address::from_low_u64_be(1));
+        let mut registry = CurrencyDecimals::new();
+        assert_eq!(registry.decimals(Currency::Native), Some(18));
+        assert_eq!(registry.parse(usdc, "1"), Err(AmountError::UnknownCurrency(usdc)));
+
+        registry.set(usdc, 6);
+        assert_eq!(registry.parse(usdc, "2.5").unwrap(), U256::from(2_500_000));
+        assert_eq!(registry.format(usdc, U256::from(2_500_000)).unwrap(), "2.5");
+        assert_eq!(registry.parse(Currency::Native, "0.5").unwrap(), U256::exp10(17) * 5);
+    }
+}
@@ -262,6 +262,11 @@ impl LiquidityTokenClaims {
         self.claims.erc6909().balance_of(owner, pool_id)
     }
     
+    /// 获取流动性令牌总供应量
+    pub fn total_supply(&self, pool_id: U256) -> U256 {
+        self.claims.erc6909().total_supply(pool_id)
+    }
+    
     /// 创建流动性令牌声明
     pub fn create_liquidity_claim(
         &mut self,
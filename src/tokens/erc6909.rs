@@ -2,6 +2,7 @@ use std::collections::HashMap;
 use primitive_types::U256;
 use ethers::types::Address;
 use thiserror::Error;
+use serde::{Deserialize, Serialize};
 
 /// ERC6909 令牌错误类型
 #[derive(Debug, Error)]
@@ -20,10 +21,16 @@ pub enum ERC6909Error {
     
     #[error("Invalid recipient")]
     InvalidRecipient,
+    
+    #[error("Balance overflow")]
+    BalanceOverflow,
+    
+    #[error("Total supply overflow")]
+    SupplyOverflow,
 }
 
 /// ERC6909 令牌事件
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ERC6909Event {
     /// 从 `from` 向 `to` 转移 `amount` 个id为 `id` 的令牌
     Transfer {
@@ -49,8 +56,32 @@ pub enum ERC6909Event {
     },
 }
 
+/// 单个令牌id的元数据 (ERC6909 Metadata 扩展)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenMetadata {
+    /// 令牌名称
+    pub name: String,
+    
+    /// 令牌符号
+    pub symbol: String,
+    
+    /// 令牌精度
+    pub decimals: u8,
+}
+
+impl TokenMetadata {
+    /// 创建新的令牌元数据
+    pub fn new(name: impl Into<String>, symbol: impl Into<String>, decimals: u8) -> Self {
+        Self {
+            name: name.into(),
+            symbol: symbol.into(),
+            decimals,
+        }
+    }
+}
+
 /// ERC6909 令牌类型 - 实现多令牌标准
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ERC6909 {
     /// 余额映射 (owner, id) => balance
     balances: HashMap<(Address, U256), U256>,
@@ -61,6 +92,12 @@ pub struct ERC6909 {
     /// 操作员映射 (owner, operator) => approved
     operators: HashMap<(Address, Address), bool>,
     
+    /// 总供应量映射 id => total supply
+    total_supplies: HashMap<U256, U256>,
+    
+    /// 元数据映射 id => metadata
+    metadata: HashMap<U256, TokenMetadata>,
+    
     /// 事件历史 - 在实际实现中将被替换为区块链事件
     events: Vec<ERC6909Event>,
 }
@@ -72,6 +109,8 @@ impl ERC6909 {
             balances: HashMap::new(),
             allowances: HashMap::new(),
             operators: HashMap::new(),
+            total_supplies: HashMap::new(),
+            metadata: HashMap::new(),
             events: Vec::new(),
         }
     }
@@ -91,6 +130,36 @@ impl ERC6909 {
         *self.operators.get(&(owner, operator)).unwrap_or(&false)
     }
     
+    /// 查询某个id的总供应量
+    pub fn total_supply(&self, id: U256) -> U256 {
+        *self.total_supplies.get(&id).unwrap_or(&U256::zero())
+    }
+    
+    /// 设置某个id的元数据
+    pub fn set_metadata(&mut self, id: U256, metadata: TokenMetadata) {
+        self.metadata.insert(id, metadata);
+    }
+    
+    /// 查询某个id的元数据
+    pub fn metadata(&self, id: U256) -> Option<&TokenMetadata> {
+        self.metadata.get(&id)
+    }
+    
+    /// 查询某个id的名称
+    pub fn name(&self, id: U256) -> Option<&str> {
+        self.metadata.get(&id).map(|m| m.name.as_str())
+    }
+    
+    /// 查询某个id的符号
+    pub fn symbol(&self, id: U256) -> Option<&str> {
+        self.metadata.get(&id).map(|m| m.symbol.as_str())
+    }
+    
+    /// 查询某个id的精度，未设置元数据时返回0
+    pub fn decimals(&self, id: U256) -> u8 {
+        self.metadata.get(&id).map_or(0, |m| m.decimals)
+    }
+    
     /// 授权操作员
     pub fn set_operator(&mut self, caller: Address, operator: Address, approved: bool) -> Result<(), ERC6909Error> {
         if caller == Address::zero() {
@@ -163,18 +232,14 @@ impl ERC6909 {
             return Err(ERC6909Error::InvalidRecipient);
         }
         
-        // 检查授权
-        if from != caller && !self.is_operator(from, caller) {
-            let allowed = self.allowance(from, caller, id);
-            if allowed < amount {
-                return Err(ERC6909Error::InsufficientAllowance);
-            }
-            
-            // 更新授权额度
-            self.allowances.insert((from, caller, id), allowed - amount);
+        // 检查授权, 转移成功后才扣减额度
+        let remaining = self.remaining_allowance(caller, from, id, amount)?;
+        self._transfer(caller, from, to, id, amount)?;
+        if let Some(remaining) = remaining {
+            self.allowances.insert((from, caller, id), remaining);
         }
         
-        self._transfer(caller, from, to, id, amount)
+        Ok(())
     }
     
     /// 铸造代币
@@ -183,9 +248,13 @@ impl ERC6909 {
             return Err(ERC6909Error::InvalidRecipient);
         }
         
-        // 增加接收方余额
-        let balance = self.balance_of(to, id);
-        self.balances.insert((to, id), balance + amount);
+        // 先检查溢出, 失败时不修改任何状态
+        let balance = self.balance_of(to, id).checked_add(amount).ok_or(ERC6909Error::BalanceOverflow)?;
+        let supply = self.total_supply(id).checked_add(amount).ok_or(ERC6909Error::SupplyOverflow)?;
+        
+        // 增加接收方余额和总供应量
+        self.balances.insert((to, id), balance);
+        self.total_supplies.insert(id, supply);
         
         // 触发事件
         self.events.push(ERC6909Event::Transfer {
@@ -204,14 +273,13 @@ impl ERC6909 {
             return Err(ERC6909Error::InvalidSender);
         }
         
-        // 检查余额
-        let balance = self.balance_of(caller, id);
-        if balance < amount {
-            return Err(ERC6909Error::InsufficientBalance);
-        }
+        // 检查余额, 总供应量不小于任一余额
+        let balance = self.balance_of(caller, id).checked_sub(amount).ok_or(ERC6909Error::InsufficientBalance)?;
+        let supply = self.total_supply(id).checked_sub(amount).ok_or(ERC6909Error::InsufficientBalance)?;
         
-        // 减少余额
-        self.balances.insert((caller, id), balance - amount);
+        // 减少余额和总供应量
+        self.balances.insert((caller, id), balance);
+        self.total_supplies.insert(id, supply);
         
         // 触发事件
         self.events.push(ERC6909Event::Transfer {
@@ -224,19 +292,32 @@ impl ERC6909 {
         Ok(())
     }
     
+    /// 计算 `spender` 从 `owner` 转移 `amount` 后剩余的授权额度
+    ///
+    /// 所有者本人、操作员和无限授权 (`U256::MAX`) 不扣减额度, 返回 `None`
+    fn remaining_allowance(&self, spender: Address, owner: Address, id: U256, amount: U256) -> Result<Option<U256>, ERC6909Error> {
+        if spender == owner || self.is_operator(owner, spender) {
+            return Ok(None);
+        }
+        
+        let allowed = self.allowance(owner, spender, id);
+        if allowed == U256::MAX {
+            return Ok(None);
+        }
+        allowed.checked_sub(amount).map(Some).ok_or(ERC6909Error::InsufficientAllowance)
+    }
+    
     /// 内部转移实现
     fn _transfer(&mut self, operator: Address, from: Address, to: Address, id: U256, amount: U256) -> Result<(), ERC6909Error> {
         // 检查余额
-        let from_balance = self.balance_of(from, id);
-        if from_balance < amount {
-            return Err(ERC6909Error::InsufficientBalance);
-        }
-        
-        // 更新余额
-        self.balances.insert((from, id), from_balance - amount);
+        let from_balance = self.balance_of(from, id).checked_sub(amount).ok_or(ERC6909Error::InsufficientBalance)?;
         
-        let to_balance = self.balance_of(to, id);
-        self.balances.insert((to, id), to_balance + amount);
+        // 更新余额, 转给自己时余额不变
+        if from != to {
+            let to_balance = self.balance_of(to, id).checked_add(amount).ok_or(ERC6909Error::BalanceOverflow)?;
+            self.balances.insert((from, id), from_balance);
+            self.balances.insert((to, id), to_balance);
+        }
         
         // 触发事件
         self.events.push(ERC6909Event::Transfer {
@@ -251,7 +332,7 @@ impl ERC6909 {
 }
 
 /// 流动性令牌 - 基于ERC6909实现的Uniswap V4流动性令牌
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct LiquidityToken {
     /// 底层的ERC6909实现
     erc6909: ERC6909,
@@ -298,6 +379,11 @@ impl LiquidityToken {
         self.erc6909.balance_of(owner, pool_id)
     }
     
+    /// 获取流动性令牌总供应量
+    pub fn total_supply(&self, pool_id: U256) -> U256 {
+        self.erc6909.total_supply(pool_id)
+    }
+    
     /// 委托所有ERC6909函数
     pub fn transfer(&mut self, caller: Address, to: Address, id: U256, amount: U256) -> Result<(), ERC6909Error> {
         self.erc6909.transfer(caller, to, id, amount)
@@ -0,0 +1,223 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::RwLock;
+use ethers::{
+    abi::{decode, ParamType, Token},
+    providers::Middleware,
+    types::{transaction::eip2718::TypedTransaction, Address, Bytes, TransactionRequest, U256},
+};
+
+use crate::core::flash_loan::Currency;
+use super::{format_amount, CurrencyDecimals, TokenMetadata, NATIVE_DECIMALS};
+
+/// Selector of ERC20 `symbol()`
+const SYMBOL_SELECTOR: [u8; 4] = [0x95, 0xd8, 0x9b, 0x41];
+
+/// Selector of ERC20 `name()`
+const NAME_SELECTOR: [u8; 4] = [0x06, 0xfd, 0xde, 0x03];
+
+/// Selector of ERC20 `decimals()`
+const DECIMALS_SELECTOR: [u8; 4] = [0x31, 0x3c, 0xe5, 0x67];
+
+/// Errors fetching currency metadata
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum MetadataError {
+    #[error("Provider error: {0}")]
+    Provider(String),
+
+    #[error("Invalid {field} returned for currency {currency}")]
+    InvalidResponse { currency: Currency, field: &'static str },
+
+    #[error("Currency {0} has no token metadata")]
+    Unsupported(Currency),
+}
+
+/// Result type for currency metadata
+pub type MetadataResult<T> = std::result::Result<T, MetadataError>;
+
+/// Lazily fetched, cached metadata of currencies
+///
+/// ERC20 metadata is read from the token contract the first time a currency
+/// is asked for and cached for the life of the service; the native currency
+/// is never fetched. The cache is shared behind a lock, so one service can
+/// label output from several tasks. Output helpers only consult the cache
+/// and fall back to the raw currency, so formatting never waits on a node,
+/// and exporters such as [`DeltaJournal::to_json_labeled`] take the service
+/// whatever its client.
+///
+/// `ethers` is a required dependency of the crate, already used by the
+/// replay sources, so the service is not behind a feature.
+///
+/// [`DeltaJournal::to_json_labeled`]: crate::core::flash_loan::DeltaJournal::to_json_labeled
+#[derive(Debug)]
+pub struct CurrencyMetadata<M> {
+    client: M,
+    cache: RwLock<HashMap<Currency, TokenMetadata>>,
+}
+
+impl<M> CurrencyMetadata<M> {
+    /// Creates a service reading from `client`, with ether as the native currency
+    pub fn new(client: M) -> Self {
+        Self::with_native(client, TokenMetadata::new("Ether", "ETH", NATIVE_DECIMALS))
+    }
+
+    /// Creates a service reading from `client` for a chain whose native
+    /// currency is described by `native`
+    pub fn with_native(client: M, native: TokenMetadata) -> Self {
+        Self {
+            client,
+            cache: RwLock::new(HashMap::from([(Currency::Native, native)])),
+        }
+    }
+
+    /// Caches metadata for a currency, e.g. for tokens without a deployment
+    pub fn insert(&self, currency: Currency, metadata: TokenMetadata) {
+        self.cache.write().expect("metadata cache poisoned").insert(currency, metadata);
+    }
+
+    /// Gets the cached metadata of a currency without fetching it
+    pub fn cached(&self, currency: Currency) -> Option<TokenMetadata> {
+        self.cache.read().expect("metadata cache poisoned").get(&currency).cloned()
+    }
+
+    /// Labels a currency with its cached symbol
+    pub fn label(&self, currency: Currency) -> CurrencyLabel {
+        CurrencyLabel { currency, symbol: self.cached(currency).map(|metadata| metadata.symbol) }
+    }
+
+    /// Formats a raw amount with the cached decimals and symbol of its
+    /// currency, or as a raw amount when the currency is not cached
+    pub fn format(&self, currency: Currency, amount: U256) -> String {
+        match self.cached(currency) {
+            Some(metadata) => format!("{} {}", format_amount(amount, metadata.decimals), metadata.symbol),
+            None => format!("{} {}", amount, currency),
+        }
+    }
+
+    /// Formats a signed change of a balance like [`format`](Self::format)
+    pub fn format_delta(&self, currency: Currency, delta: i128) -> String {
+        let sign = if delta < 0 { "-" } else { "" };
+        format!("{sign}{}", self.format(currency, U256::from(delta.unsigned_abs())))
+    }
+
+    /// Decimals of every cached currency, for parsing amounts
+    pub fn decimals(&self) -> CurrencyDecimals {
+        let mut decimals = CurrencyDecimals::new();
+        for (currency, metadata) in self.cache.read().expect("metadata cache poisoned").iter() {
+            decimals.set(*currency, metadata.decimals);
+        }
+        decimals
+    }
+}
+
+impl<M: Middleware> CurrencyMetadata<M> {
+    /// Gets the metadata of a currency, fetching it on first use
+    pub async fn get(&self, currency: Currency) -> MetadataResult<TokenMetadata> {
+        if let Some(metadata) = self.cached(currency) {
+            return Ok(metadata);
+        }
+        let Currency::Erc20(token) = currency else {
+            return Err(MetadataError::Unsupported(currency));
+        };
+
+        let symbol = self.call(token, SYMBOL_SELECTOR).await?;
+        let name = self.call(token, NAME_SELECTOR).await?;
+        let decimals = self.call(token, DECIMALS_SELECTOR).await?;
+        let metadata = TokenMetadata::new(
+            decode_string(&name).ok_or(MetadataError::InvalidResponse { currency, field: "name" })?,
+            decode_string(&symbol).ok_or(MetadataError::InvalidResponse { currency, field: "symbol" })?,
+            decode_decimals(&decimals).ok_or(MetadataError::InvalidResponse { currency, field: "decimals" })?,
+        );
+        self.insert(currency, metadata.clone());
+        Ok(metadata)
+    }
+
+    /// Fetches the metadata of several currencies, stopping at the first failure
+    pub async fn prefetch(&self, currencies: impl IntoIterator<Item = Currency>) -> MetadataResult<()> {
+        for currency in currencies {
+            self.get(currency).await?;
+        }
+        Ok(())
+    }
+
+    /// Calls a view function without arguments on a token
+    async fn call(&self, token: Address, selector: [u8; 4]) -> MetadataResult<Bytes> {
+        let tx: TypedTransaction = TransactionRequest::new().to(token).data(selector.to_vec()).into();
+        self.client.call(&tx, None).await.map_err(|e| MetadataError::Provider(e.to_string()))
+    }
+}
+
+/// Displays a currency by its symbol when known, or as the raw currency
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CurrencyLabel {
+    currency: Currency,
+    symbol: Option<String>,
+}
+
+impl fmt::Display for CurrencyLabel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.symbol {
+            Some(symbol) => write!(f, "{}", symbol),
+            None => write!(f, "{}", self.currency),
+        }
+    }
+}
+
+/// Decodes a string return value, also accepting the `bytes32` some older
+/// tokens return
+fn decode_string(data: &[u8]) -> Option<String> {
+    if data.len() == 32 {
+        let end = data.iter().position(|byte| *byte == 0).unwrap_or(32);
+        return String::from_utf8(data[..end].to_vec()).ok();
+    }
+    match decode(&[ParamType::String], data).ok()?.pop()? {
+        Token::String(value) => Some(value),
+        _ => None,
+    }
+}
+
+/// Decodes a `uint8` return value
+fn decode_decimals(data: &[u8]) -> Option<u8> {
+    match decode(&[ParamType::Uint(8)], data).ok()?.pop()? {
+        Token::Uint(value) if value <= U256::from(u8::MAX) => Some(value.low_u32() as u8),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::{abi::encode, providers::Provider};
+
+    #[tokio::test]
+    async fn test_fetches_and_caches_metadata() {
+        let token = Currency::Erc20(Address::repeat_byte(0xAA));
+        let (provider, mock) = Provider::mocked();
+        // Responses are served last in, first out: symbol, name, then decimals
+        mock.push::<Bytes, _>(Bytes::from(encode(&[Token::Uint(6.into())]))).unwrap();
+        mock.push::<Bytes, _>(Bytes::from(encode(&[Token::String("USD Coin".to_string())]))).unwrap();
+        mock.push::<Bytes, _>(Bytes::from(encode(&[Token::String("USDC".to_string())]))).unwrap();
+
+        let metadata = CurrencyMetadata::new(provider);
+        assert_eq!(metadata.label(token).to_string(), token.to_string());
+        let usdc = metadata.get(token).await.unwrap();
+        assert_eq!(usdc, TokenMetadata::new("USD Coin", "USDC", 6));
+
+        // Cached currencies are served without another request
+        assert_eq!(metadata.get(token).await.unwrap(), usdc);
+        assert_eq!(metadata.label(token).to_string(), "USDC");
+        assert_eq!(metadata.format(token, U256::from(1_500_000)), "1.5 USDC");
+        assert_eq!(metadata.format(Currency::Native, U256::exp10(18)), "1 ETH");
+        assert_eq!(metadata.decimals().parse(token, "2.25").unwrap(), U256::from(2_250_000));
+        assert!(matches!(metadata.get(Currency::Pool(U256::one())).await, Err(MetadataError::Unsupported(_))));
+    }
+
+    #[test]
+    fn test_decode_bytes32_symbol() {
+        let mut word = [0u8; 32];
+        word[..3].copy_from_slice(b"MKR");
+        assert_eq!(decode_string(&word).as_deref(), Some("MKR"));
+        assert_eq!(decode_string(&encode(&[Token::String("DAI".to_string())])).as_deref(), Some("DAI"));
+        assert_eq!(decode_decimals(&encode(&[Token::Uint(256.into())])), None);
+    }
+}
@@ -1,5 +1,9 @@
 pub mod erc6909;
 pub mod claims;
+pub mod amounts;
+pub mod metadata;
 
 pub use erc6909::*;
-pub use claims::*; 
\ No newline at end of file
+pub use claims::*;
+pub use amounts::*;
+pub use metadata::*;
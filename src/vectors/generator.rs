@@ -0,0 +1,349 @@
+use primitive_types::U256;
+use serde::{Deserialize, Serialize};
+
+use crate::core::{
+    math::{
+        types::{Liquidity, SqrtPrice, TickSpacing},
+        FeePips, SwapMath, TickMath,
+    },
+    state::Pool,
+    Rng,
+};
+
+use super::{VectorError, VectorResult, SCHEMA_VERSION};
+
+/// LP fees the swap vectors draw from, besides random ones
+const FEES: [u32; 5] = [0, 100, 500, 3000, 10000];
+
+/// Tick spacings the swap vectors draw from
+const TICK_SPACINGS: [i32; 4] = [1, 10, 60, 200];
+
+/// Attempts at drawing a valid input before giving up on a vector
+const MAX_ATTEMPTS: usize = 100;
+
+/// The sqrt price of a tick, from `getSqrtPriceAtTick`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SqrtPriceAtTickVector {
+    pub tick: i32,
+    /// Expected sqrt price as a Q64.96
+    pub sqrt_price_x96: String,
+}
+
+/// The tick of a sqrt price, from `getTickAtSqrtPrice`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TickAtSqrtPriceVector {
+    pub sqrt_price_x96: String,
+    /// Expected tick, the largest whose sqrt price is at most the input
+    pub tick: i32,
+}
+
+/// A single step of a swap within one range, from `computeSwapStep`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SwapStepVector {
+    pub sqrt_price_current_x96: String,
+    pub sqrt_price_target_x96: String,
+    pub liquidity: String,
+    /// Negative for exact input, positive for exact output
+    pub amount_remaining: String,
+    pub fee_pips: u32,
+    /// Expected outputs
+    pub sqrt_price_next_x96: String,
+    pub amount_in: String,
+    pub amount_out: String,
+    pub fee_amount: String,
+}
+
+/// A position a swap vector's pool starts with
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PositionVector {
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub liquidity: String,
+}
+
+/// A whole swap, across ticks, on a pool with some positions
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SwapVector {
+    /// The pool, initialized at `sqrt_price_x96` before the positions are
+    /// added in order
+    pub fee: u32,
+    pub tick_spacing: i32,
+    pub sqrt_price_x96: String,
+    pub positions: Vec<PositionVector>,
+    /// The swap, negative amounts for exact input
+    pub zero_for_one: bool,
+    pub amount_specified: String,
+    pub sqrt_price_limit_x96: String,
+    /// Expected balance delta of the swapper, negative amounts paid to the
+    /// pool
+    pub amount0: String,
+    pub amount1: String,
+    /// Expected state of the pool after the swap
+    pub sqrt_price_x96_after: String,
+    pub tick_after: i32,
+    pub liquidity_after: String,
+}
+
+/// Number of vectors of each kind a set has
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VectorCounts {
+    pub sqrt_price_at_tick: usize,
+    pub tick_at_sqrt_price: usize,
+    pub swap_steps: usize,
+    pub swaps: usize,
+}
+
+impl VectorCounts {
+    /// The same number of vectors of every kind
+    pub fn uniform(count: usize) -> Self {
+        Self { sqrt_price_at_tick: count, tick_at_sqrt_price: count, swap_steps: count, swaps: count }
+    }
+}
+
+/// Test vectors with the outputs of this crate
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VectorSet {
+    pub schema_version: u32,
+    /// Crate and version that computed the outputs
+    pub generator: String,
+    pub seed: u64,
+    pub sqrt_price_at_tick: Vec<SqrtPriceAtTickVector>,
+    pub tick_at_sqrt_price: Vec<TickAtSqrtPriceVector>,
+    pub swap_steps: Vec<SwapStepVector>,
+    pub swaps: Vec<SwapVector>,
+}
+
+impl VectorSet {
+    /// Draws the vectors of a set from `seed`
+    ///
+    /// Every kind draws from its own generator, so the vectors of a kind
+    /// don't change with the counts of the others.
+    pub fn generate(seed: u64, counts: VectorCounts) -> Self {
+        let mut rng = Rng::seed_from_u64(seed);
+        let mut tick_rng = rng.fork();
+        let mut price_rng = rng.fork();
+        let mut step_rng = rng.fork();
+        let mut swap_rng = rng.fork();
+        Self {
+            schema_version: SCHEMA_VERSION,
+            generator: format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
+            seed,
+            sqrt_price_at_tick: (0..counts.sqrt_price_at_tick).map(|_| sqrt_price_at_tick(&mut tick_rng)).collect(),
+            tick_at_sqrt_price: (0..counts.tick_at_sqrt_price).map(|_| tick_at_sqrt_price(&mut price_rng)).collect(),
+            swap_steps: (0..counts.swap_steps).filter_map(|_| swap_step(&mut step_rng)).collect(),
+            swaps: (0..counts.swaps).filter_map(|_| swap(&mut swap_rng)).collect(),
+        }
+    }
+
+    /// Gets the set as JSON
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("vectors serialize")
+    }
+
+    /// Parses a set, which must have the current schema version
+    pub fn from_json(json: &str) -> VectorResult<Self> {
+        #[derive(Deserialize)]
+        struct Versioned {
+            schema_version: u32,
+        }
+
+        // Check the version first, as other versions may not parse
+        let Versioned { schema_version } = serde_json::from_str(json)?;
+        if schema_version != SCHEMA_VERSION {
+            return Err(VectorError::UnsupportedSchemaVersion { found: schema_version, expected: SCHEMA_VERSION });
+        }
+        Ok(serde_json::from_str(json)?)
+    }
+}
+
+/// A tick over the whole range, often close to zero or to the bounds where
+/// implementations tend to break
+fn random_tick(rng: &mut Rng) -> i32 {
+    match rng.gen_range(0..4) {
+        0 => rng.gen_range_i32(-1000..1001),
+        1 => TickMath::MIN_TICK + rng.gen_range_i32(0..1000),
+        2 => TickMath::MAX_TICK - rng.gen_range_i32(0..1000),
+        _ => rng.gen_range_i32(TickMath::MIN_TICK..TickMath::MAX_TICK + 1),
+    }
+}
+
+/// A nonzero amount of any magnitude up to `bits` bits
+fn random_amount(rng: &mut Rng, bits: u32) -> u128 {
+    let shift = 128 - rng.gen_range(1..bits as u64 + 1) as u32;
+    (rng.next_u128() >> shift).max(1)
+}
+
+/// An amount of up to 100 bits, negative for exact input
+fn random_amount_specified(rng: &mut Rng) -> i128 {
+    let amount = random_amount(rng, 100) as i128;
+    if rng.next_bool() { -amount } else { amount }
+}
+
+/// A fee of the usual tiers or any below 100%
+fn random_fee(rng: &mut Rng) -> u32 {
+    match rng.gen_range(0..(FEES.len() as u64 + 1)) as usize {
+        index if index < FEES.len() => FEES[index],
+        _ => rng.gen_range(0..FeePips::MAX.get() as u64) as u32,
+    }
+}
+
+fn sqrt_price(tick: i32) -> U256 {
+    TickMath::get_sqrt_price_at_tick(tick).expect("ticks are drawn in range")
+}
+
+fn sqrt_price_at_tick(rng: &mut Rng) -> SqrtPriceAtTickVector {
+    let tick = random_tick(rng);
+    SqrtPriceAtTickVector { tick, sqrt_price_x96: sqrt_price(tick).to_string() }
+}
+
+/// A price between the prices of a tick and the next, so most inputs aren't
+/// the price of a tick
+fn tick_at_sqrt_price(rng: &mut Rng) -> TickAtSqrtPriceVector {
+    let tick = random_tick(rng).min(TickMath::MAX_TICK - 1);
+    let lower = sqrt_price(tick);
+    let gap = sqrt_price(tick + 1) - lower;
+    let offset = U256([rng.next_u64(), rng.next_u64(), rng.next_u64(), 0]) % gap;
+    let sqrt_price_x96 = lower + offset;
+    TickAtSqrtPriceVector {
+        sqrt_price_x96: sqrt_price_x96.to_string(),
+        tick: TickMath::get_tick_at_sqrt_price(sqrt_price_x96).expect("prices are drawn in range"),
+    }
+}
+
+/// A step toward a price up to 20000 ticks away, or `None` if no valid
+/// input was drawn
+fn swap_step(rng: &mut Rng) -> Option<SwapStepVector> {
+    (0..MAX_ATTEMPTS).find_map(|_| {
+        let current = random_tick(rng);
+        let target = (current + rng.gen_range_i32(-20000..20001)).clamp(TickMath::MIN_TICK, TickMath::MAX_TICK);
+        let (current, target) = (sqrt_price(current), sqrt_price(target));
+        let liquidity = random_amount(rng, 120);
+        let amount_remaining = random_amount_specified(rng);
+        let fee = random_fee(rng);
+
+        let (next, amount_in, amount_out, fee_amount) = SwapMath::compute_swap_step(
+            SqrtPrice::new(current),
+            SqrtPrice::new(target),
+            Liquidity::new(liquidity),
+            amount_remaining,
+            FeePips::new(fee),
+        )
+        .ok()?;
+        Some(SwapStepVector {
+            sqrt_price_current_x96: current.to_string(),
+            sqrt_price_target_x96: target.to_string(),
+            liquidity: liquidity.to_string(),
+            amount_remaining: amount_remaining.to_string(),
+            fee_pips: fee,
+            sqrt_price_next_x96: next.to_u256().to_string(),
+            amount_in: amount_in.to_string(),
+            amount_out: amount_out.to_string(),
+            fee_amount: fee_amount.to_string(),
+        })
+    })
+}
+
+/// A swap on a pool with up to four positions around the price, or `None`
+/// if no valid input was drawn
+fn swap(rng: &mut Rng) -> Option<SwapVector> {
+    (0..MAX_ATTEMPTS).find_map(|_| {
+        let fee = FEES[rng.gen_range(0..FEES.len() as u64) as usize];
+        let spacing = TickSpacing::new(TICK_SPACINGS[rng.gen_range(0..TICK_SPACINGS.len() as u64) as usize]).ok()?;
+        let tick = rng.gen_range_i32(-50000..50001);
+        let sqrt_price_x96 = sqrt_price(tick);
+
+        let mut pool = Pool::new();
+        pool.initialize(SqrtPrice::new(sqrt_price_x96), FeePips::new(fee)).ok()?;
+        let aligned = |tick: i32| (tick.div_euclid(spacing.get()) * spacing.get())
+            .clamp(spacing.min_usable_tick(), spacing.max_usable_tick());
+        let mut positions = Vec::new();
+        for _ in 0..rng.gen_range(1..5) {
+            let tick_lower = aligned(tick - spacing.get() * rng.gen_range_i32(0..200));
+            let tick_upper = aligned(tick + spacing.get() * rng.gen_range_i32(1..200));
+            let liquidity = random_amount(rng, 80);
+            if tick_lower >= tick_upper {
+                continue;
+            }
+            pool.modify_position([1u8; 20], tick_lower, tick_upper, liquidity as i128, spacing, [0u8; 32]).ok()?;
+            positions.push(PositionVector { tick_lower, tick_upper, liquidity: liquidity.to_string() });
+        }
+
+        let zero_for_one = rng.next_bool();
+        let distance = rng.gen_range_i32(1..100000);
+        let limit_tick = if zero_for_one { tick - distance } else { tick + distance };
+        let limit = sqrt_price(limit_tick.clamp(TickMath::MIN_TICK + 1, TickMath::MAX_TICK - 1));
+        let amount_specified = random_amount_specified(rng);
+        let (delta, _) = pool.swap(amount_specified, SqrtPrice::new(limit), zero_for_one, spacing, None).ok()?;
+        Some(SwapVector {
+            fee,
+            tick_spacing: spacing.get(),
+            sqrt_price_x96: sqrt_price_x96.to_string(),
+            positions,
+            zero_for_one,
+            amount_specified: amount_specified.to_string(),
+            sqrt_price_limit_x96: limit.to_string(),
+            amount0: delta.amount0().to_string(),
+            amount1: delta.amount1().to_string(),
+            sqrt_price_x96_after: pool.slot0.sqrt_price_x96.to_u256().to_string(),
+            tick_after: pool.slot0.tick,
+            liquidity_after: pool.liquidity.as_u128().to_string(),
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_seed_same_vectors() {
+        let counts = VectorCounts::uniform(10);
+        let vectors = VectorSet::generate(7, counts);
+        assert_eq!(vectors, VectorSet::generate(7, counts));
+        assert_ne!(vectors, VectorSet::generate(8, counts));
+        assert_eq!(vectors.sqrt_price_at_tick.len(), 10);
+        assert_eq!(vectors.swap_steps.len(), 10);
+        assert_eq!(vectors.swaps.len(), 10);
+
+        // Kinds don't depend on each other's counts
+        let fewer_swaps = VectorSet::generate(7, VectorCounts { swaps: 2, ..counts });
+        assert_eq!(fewer_swaps.swap_steps, vectors.swap_steps);
+        assert_eq!(fewer_swaps.swaps[..], vectors.swaps[..2]);
+    }
+
+    #[test]
+    fn test_vectors_round_trip_at_the_schema_version() {
+        let vectors = VectorSet::generate(1, VectorCounts::uniform(3));
+        assert_eq!(VectorSet::from_json(&vectors.to_json()).unwrap(), vectors);
+
+        let newer = VectorSet { schema_version: SCHEMA_VERSION + 1, ..vectors };
+        assert!(matches!(
+            VectorSet::from_json(&newer.to_json()),
+            Err(VectorError::UnsupportedSchemaVersion { found, expected: SCHEMA_VERSION }) if found == SCHEMA_VERSION + 1
+        ));
+    }
+
+    #[test]
+    fn test_vectors_are_consistent() {
+        let vectors = VectorSet::generate(3, VectorCounts::uniform(50));
+        for vector in &vectors.tick_at_sqrt_price {
+            let price = U256::from_dec_str(&vector.sqrt_price_x96).unwrap();
+            assert!(sqrt_price(vector.tick) <= price);
+            assert!(vector.tick == TickMath::MAX_TICK || sqrt_price(vector.tick + 1) > price);
+        }
+        for vector in &vectors.swaps {
+            let exact_input = vector.amount_specified.starts_with('-');
+            let (specified, other) = if vector.zero_for_one == exact_input {
+                (&vector.amount0, &vector.amount1)
+            } else {
+                (&vector.amount1, &vector.amount0)
+            };
+            // The swapper never gets more than it asked for, nor pays more
+            // than it offered, and pays for what it gets
+            let (specified, amount) = (specified.parse::<i128>().unwrap(), vector.amount_specified.parse::<i128>().unwrap());
+            assert!(specified.abs() <= amount.abs(), "{vector:?}");
+            let other = other.parse::<i128>().unwrap();
+            assert!(other == 0 || specified == 0 || other.signum() != specified.signum(), "{vector:?}");
+        }
+    }
+}
@@ -0,0 +1,34 @@
+//! Test vectors for other implementations of the pool math
+//!
+//! A [`VectorSet`] holds inputs of the tick math, of single swap steps and of
+//! whole swaps on a pool, with the outputs this crate computes for them, so
+//! implementations in other languages or in circuits can check themselves
+//! against it. Vectors are random but drawn from a seed alone, and the same
+//! seed and counts give the same set, also from the `uniswap-v4-sim
+//! test-vectors` command. 128- and 256-bit numbers are decimal strings.
+//!
+//! Sets carry the [`SCHEMA_VERSION`] of their layout, which changes whenever
+//! fields are added, removed or change meaning, and readers reject sets of
+//! another version.
+
+pub mod generator;
+
+pub use generator::*;
+
+use thiserror::Error;
+
+/// Version of the layout of [`VectorSet`]s
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Error types for test vectors
+#[derive(Debug, Error)]
+pub enum VectorError {
+    #[error("Invalid test vectors: {0}")]
+    Parse(#[from] serde_json::Error),
+
+    #[error("Test vectors have schema version {found}, expected {expected}")]
+    UnsupportedSchemaVersion { found: u32, expected: u32 },
+}
+
+/// Result type for test vectors
+pub type VectorResult<T> = std::result::Result<T, VectorError>;
@@ -0,0 +1,143 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use ethers::types::{Address, U256};
+use uniswap_v4_core::core::{
+    hooks::{hook_interface::ModifyLiquidityParams, NoOpHook},
+    math::types::{SqrtPrice, TickSpacing},
+    pool_manager::{ManagerPoolKey, PoolManager},
+    state::{Pool, Salt},
+};
+
+/// Allocator that counts the allocations made by the current thread
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Returns the number of allocations made while running `f`
+fn count_allocations<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let before = ALLOCATIONS.with(Cell::get);
+    let result = f();
+    (result, ALLOCATIONS.with(Cell::get) - before)
+}
+
+fn create_key(hooks: Address) -> ManagerPoolKey {
+    ManagerPoolKey::new(
+        Address::from_low_u64_be(1),
+        Address::from_low_u64_be(2),
+        3000,
+        TickSpacing::new(60).unwrap(),
+        hooks,
+    ).unwrap()
+}
+
+fn setup_manager(hooks: Address) -> (PoolManager, ManagerPoolKey) {
+    let mut manager = PoolManager::new();
+    if hooks != Address::zero() {
+        manager.hook_registry_mut().register_hook(hooks, Box::new(NoOpHook));
+    }
+    let key = create_key(hooks);
+    manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+    manager.modify_liquidity(key.clone(), ModifyLiquidityParams {
+        owner: Address::repeat_byte(1),
+        tick_lower: -600,
+        tick_upper: 600,
+        liquidity_delta: 1_000_000_000_000,
+        salt: Salt::ZERO,
+    }, &[]).unwrap();
+    (manager, key)
+}
+
+#[test]
+fn test_pool_swap_does_not_allocate() {
+    let mut pool = Pool::new();
+    pool.initialize(SqrtPrice::ONE, Default::default()).unwrap();
+    let limit = SqrtPrice::new(U256::from(78228162514264337593543950336u128));
+
+    // The first swap caches the sqrt prices of the ticks it reaches, then
+    // the same swap runs again from the same price
+    let slot0 = pool.slot0.clone();
+    pool.swap(-1_000, limit, true, TickSpacing::new(60).unwrap(), None).unwrap();
+    pool.slot0 = slot0;
+
+    let (result, allocations) = count_allocations(|| {
+        pool.swap(-1_000, limit, true, TickSpacing::new(60).unwrap(), None)
+    });
+    result.unwrap();
+    assert_eq!(allocations, 0);
+}
+
+#[test]
+fn test_pool_swap_crossing_ticks_does_not_allocate() {
+    let tick_spacing = TickSpacing::new(60).unwrap();
+    let mut pool = Pool::new();
+    pool.initialize(SqrtPrice::ONE, Default::default()).unwrap();
+    pool.modify_position([1; 20], -600, 600, 1_000_000_000_000, tick_spacing, [0; 32]).unwrap();
+    pool.modify_position([1; 20], -60, 60, 1_000_000_000_000, tick_spacing, [0; 32]).unwrap();
+    let limit = SqrtPrice::new(U256::from(78228162514264337593543950336u128));
+
+    // The first swap crosses the initialized tick at -60 and caches the
+    // sqrt prices it reaches, then a swap back moves the price above the
+    // tick again
+    pool.swap(-10_000_000_000, limit, true, tick_spacing, None).unwrap();
+    assert!(pool.slot0.tick < -60);
+    let upper_limit = SqrtPrice::new(U256::from(2u128) << 96);
+    pool.swap(-10_000_000_000, upper_limit, false, tick_spacing, None).unwrap();
+    assert!(pool.slot0.tick >= -60);
+
+    let (result, allocations) = count_allocations(|| {
+        pool.swap(-10_000_000_000, limit, true, tick_spacing, None)
+    });
+    result.unwrap();
+    assert!(pool.slot0.tick < -60);
+    assert_eq!(allocations, 0);
+}
+
+#[test]
+fn test_manager_swap_does_not_allocate() {
+    let (mut manager, key) = setup_manager(Address::zero());
+    let limit = U256::from(78228162514264337593543950336u128);
+    // Caches the sqrt prices of the ticks the swaps reach
+    manager.swap(&key, true, -1_000, limit, &[]).unwrap();
+
+    let (result, allocations) = count_allocations(|| {
+        manager.swap(&key, true, -1_000, limit, &[])
+    });
+    result.unwrap();
+    assert_eq!(allocations, 0);
+}
+
+#[test]
+fn test_hooked_swap_does_not_allocate() {
+    let (mut manager, key) = setup_manager(Address::from_low_u64_be(0xffff));
+    let limit = U256::from(78228162514264337593543950336u128);
+    // Caches the sqrt prices of the ticks the swaps reach
+    manager.swap(&key, true, -1_000, limit, &[]).unwrap();
+
+    let (result, allocations) = count_allocations(|| {
+        manager.swap(&key, true, -1_000, limit, &[])
+    });
+    result.unwrap();
+    assert_eq!(allocations, 0);
+}
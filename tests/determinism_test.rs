@@ -0,0 +1,82 @@
+use ethers::types::Address;
+use uniswap_v4_core::{
+    core::{
+        hooks::hook_interface::ModifyLiquidityParams,
+        pool_manager::{ManagerPoolKey, PoolManager},
+        math::types::{SqrtPrice, TickSpacing},
+    },
+    Rng,
+};
+
+/// Runs a randomized liquidity scenario and returns the exported manager state
+fn run_scenario(seed: u64) -> Vec<u8> {
+    let mut rng = Rng::seed_from_u64(seed);
+    let mut manager = PoolManager::new();
+
+    let mut keys = Vec::new();
+    for tick_spacing in [10, 60] {
+        let key = ManagerPoolKey::new(
+            rng.address(),
+            rng.address(),
+            3000,
+            TickSpacing::new(tick_spacing).unwrap(),
+            Address::zero(),
+        ).unwrap();
+        manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+        keys.push(key);
+    }
+
+    let owners: Vec<Address> = (0..4).map(|_| rng.address()).collect();
+    let mut open_positions = Vec::new();
+
+    for _ in 0..50 {
+        let key = &keys[rng.gen_range(0..keys.len() as u64) as usize];
+        let pool_key = key.clone();
+
+        if !open_positions.is_empty() && rng.next_f64() < 0.3 {
+            // Close a random open position
+            let index = rng.gen_range(0..open_positions.len() as u64) as usize;
+            let (close_key, params): (ManagerPoolKey, ModifyLiquidityParams) = open_positions.swap_remove(index);
+            let params = ModifyLiquidityParams {
+                liquidity_delta: -params.liquidity_delta,
+                ..params
+            };
+            manager.modify_liquidity(close_key, params, &[]).unwrap();
+        } else {
+            // Open a position around the current price
+            let width = rng.gen_range_i32(1..20) * key.tick_spacing().get();
+            let params = ModifyLiquidityParams {
+                owner: owners[rng.gen_range(0..owners.len() as u64) as usize],
+                tick_lower: -width,
+                tick_upper: width,
+                liquidity_delta: rng.gen_range(1_000_000..1_000_000_000) as i128,
+                salt: rng.salt(),
+            };
+            manager.modify_liquidity(pool_key.clone(), params.clone(), &[]).unwrap();
+            open_positions.push((pool_key.clone(), params));
+        }
+
+        // Add noise in the form of donations to pools with liquidity
+        if rng.next_bool() {
+            let pool = manager.get_pool_mut(&pool_key).unwrap();
+            if pool.liquidity.as_u128() > 0 {
+                pool.donate(rng.gen_range(0..10_000) as u128, rng.gen_range(0..10_000) as u128).unwrap();
+            }
+        }
+    }
+
+    manager.export_state()
+}
+
+#[test]
+fn test_scenario_is_reproducible() {
+    let first = run_scenario(2024);
+    let second = run_scenario(2024);
+    assert_eq!(first, second);
+    assert!(!first.is_empty());
+}
+
+#[test]
+fn test_different_seeds_diverge() {
+    assert_ne!(run_scenario(1), run_scenario(2));
+}
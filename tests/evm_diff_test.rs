@@ -0,0 +1,20 @@
+#![cfg(feature = "evm-diff")]
+
+use uniswap_v4_core::evm_diff::{fuzz, Artifacts};
+
+/// Fuzzes against the contracts in the forge `out` directory named by `V4_CORE_OUT`
+///
+/// Skipped when the variable is unset, since the bytecode is not bundled.
+#[test]
+fn test_differential_fuzz() {
+    let Ok(out_dir) = std::env::var("V4_CORE_OUT") else {
+        eprintln!("V4_CORE_OUT is not set, skipping differential fuzzing");
+        return;
+    };
+    let artifacts = Artifacts::from_forge_out(out_dir).unwrap();
+
+    for seed in 0..4 {
+        let divergences = fuzz(&artifacts, seed, 100).unwrap();
+        assert!(divergences.is_empty(), "seed {seed} diverged: {:#?}", divergences.first());
+    }
+}
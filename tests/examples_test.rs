@@ -0,0 +1,318 @@
+//! The golden paths shown in `examples/`, run through `PoolManager` with assertions
+
+use std::cell::Cell;
+use std::rc::Rc;
+
+use ethers::types::{Address, U256};
+use uniswap_v4_core::{
+    core::{
+        hooks::{
+            deployer::HookDeployer,
+            examples::{AutoRangeHook, DynamicFeeHook, LiquidityBootstrappingHook},
+            hook_interface::{ModifyLiquidityParams, PoolKey, SwapParams},
+            BeforeHookResult, Hook, HookDescriptor, HookFlags, HookPermissions, HookWithReturns,
+        },
+        flash_loan::{ArbitrageFlashLoanExample, FlashFeeRecipient, MultiTokenFlashLoanExample, SimpleFlashLoanExample},
+        math::{Bps, FeePips, SqrtPrice, TickMath, TickSpacing},
+        pool_manager::{ManagerPoolKey, PoolManager},
+        state::{Result as StateResult, StateError},
+    },
+    fees::types::ProtocolFee,
+    tokens::{
+        amounts::{format_amount, CurrencyDecimals},
+        erc6909::{ERC6909, ERC6909Error},
+        LiquidityToken,
+    },
+    Currency, Rng,
+};
+
+const DYNAMIC_FEE: u32 = 0x800000;
+
+fn pool_key(fee: u32, hooks: Address) -> ManagerPoolKey {
+    ManagerPoolKey::new(
+        Address::from_low_u64_be(1),
+        Address::from_low_u64_be(2),
+        fee,
+        TickSpacing::new(60).unwrap(),
+        hooks,
+    ).unwrap()
+}
+
+/// Initializes a pool with deep liquidity around price 1, so the swaps below
+/// stay within one tick range
+fn setup_pool(manager: &mut PoolManager, key: &ManagerPoolKey) {
+    manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+    let params = ModifyLiquidityParams::default_position(Address::repeat_byte(1), -600, 600, 1_000_000_000_000);
+    manager.modify_liquidity(key.clone(), params, &[]).unwrap();
+}
+
+/// Hook that overrides the LP fee of every swap and counts its calls
+struct FeeTierHook {
+    fee: FeePips,
+    swaps: Rc<Cell<u32>>,
+}
+
+impl Hook for FeeTierHook {
+    fn describe(&self) -> HookDescriptor {
+        HookDescriptor::new("FeeTierHook", "1", HookPermissions { before_swap: true, ..Default::default() })
+    }
+
+    fn before_swap(
+        &mut self,
+        _sender: Address,
+        _key: &PoolKey,
+        _params: &SwapParams,
+        _hook_data: &[u8],
+    ) -> StateResult<BeforeHookResult> {
+        self.swaps.set(self.swaps.get() + 1);
+        Ok(BeforeHookResult::default().with_fee_override(self.fee))
+    }
+}
+
+impl HookWithReturns for FeeTierHook {}
+
+/// `examples/protocol_fee_example.rs`: packed protocol fees, human-readable
+/// amounts, and the protocol's share of a swap's fees
+#[test]
+fn test_protocol_fee_example() {
+    let protocol_fee = ProtocolFee::new(100, 200).unwrap();
+    assert!(protocol_fee.is_valid());
+    assert_eq!(protocol_fee.get_zero_for_one_fee(), FeePips::new(100));
+    assert_eq!(protocol_fee.get_one_for_zero_fee(), FeePips::new(200));
+
+    let key = pool_key(3000, Address::zero());
+    let mut decimals = CurrencyDecimals::new();
+    decimals
+        .set(Currency::from_address(key.token0()), 6)
+        .set(Currency::from_address(key.token1()), 6);
+    let amount = decimals.parse(Currency::from_address(key.token0()), "1").unwrap();
+    assert_eq!(amount, U256::from(1_000_000));
+    assert_eq!(format_amount(protocol_fee.get_zero_for_one_fee().of(amount), 6), "0.0001");
+
+    let mut manager = PoolManager::new();
+    setup_pool(&mut manager, &key);
+    manager.get_pool_mut(&key).unwrap().set_protocol_fee(ProtocolFee::new(100, 0).unwrap().0).unwrap();
+    let delta = manager.swap(&key, true, -(amount.as_u128() as i128), TickMath::MIN_SQRT_PRICE + 1, &[]).unwrap();
+    assert_eq!(delta.amount0(), -1_000_000);
+    assert!(delta.amount1() > 0);
+
+    // The protocol fee is charged on the input before the 0.3% LP fee
+    let stats = manager.pool_stats(&key).unwrap();
+    assert_eq!((stats.protocol_fees0, stats.lp_fees0), (100, 3000));
+    assert_eq!(stats.swap_count, 1);
+}
+
+/// `examples/hooked_swap_example.rs`: a registered hook sets the fee of
+/// swaps routed through the manager
+#[test]
+fn test_hooked_swap_example() {
+    let swaps = Rc::new(Cell::new(0));
+    let mut manager = PoolManager::new();
+    let mut deployer = HookDeployer::new(Address::repeat_byte(0xF0));
+    let hook = FeeTierHook { fee: FeePips::new(500), swaps: swaps.clone() };
+    let hooks = deployer.deploy(manager.hook_registry_mut(), Box::new(hook)).unwrap();
+    assert_eq!(HookFlags::from_address(hooks), HookFlags::new(HookFlags::BEFORE_SWAP));
+    let key = pool_key(DYNAMIC_FEE, hooks);
+    setup_pool(&mut manager, &key);
+    assert_eq!(manager.get_pool(&key).unwrap().slot0.lp_fee, FeePips::ZERO);
+
+    let delta = manager.swap(&key, true, -1_000_000, TickMath::MIN_SQRT_PRICE + 1, &[]).unwrap();
+    let reverse = manager.swap(&key, false, -1_000_000, TickMath::MAX_SQRT_PRICE - 1, &[]).unwrap();
+    assert_eq!(swaps.get(), 2);
+    assert_eq!(delta.amount0(), -1_000_000);
+    assert_eq!(reverse.amount1(), -1_000_000);
+
+    // Both swaps paid the hook's fee instead of the pool's
+    let stats = manager.pool_stats(&key).unwrap();
+    assert_eq!((stats.lp_fees0, stats.lp_fees1), (500, 500));
+    assert_eq!(stats.swap_count, 2);
+
+    // The same swap on an unhooked pool pays the static fee
+    let mut plain = PoolManager::new();
+    let plain_key = pool_key(3000, Address::zero());
+    setup_pool(&mut plain, &plain_key);
+    let plain_delta = plain.swap(&plain_key, true, -1_000_000, TickMath::MIN_SQRT_PRICE + 1, &[]).unwrap();
+    assert_eq!(plain.pool_stats(&plain_key).unwrap().lp_fees0, 3000);
+    assert!(plain_delta.amount1() < delta.amount1());
+
+    // The library's dynamic fee hook plugs in the same way
+    let dynamic_hooks = HookFlags::new(HookFlags::BEFORE_SWAP).apply_to_address(Address::repeat_byte(0xD0));
+    let hook = DynamicFeeHook::new(FeePips::new(1000), FeePips::new(500), FeePips::new(10_000));
+    manager.hook_registry_mut().register_hook(dynamic_hooks, Box::new(hook));
+    let tick_spacing = TickSpacing::new(60).unwrap();
+    let dynamic_key = ManagerPoolKey::new(
+        Address::zero(),
+        Address::from_low_u64_be(2),
+        DYNAMIC_FEE,
+        tick_spacing,
+        dynamic_hooks,
+    ).unwrap();
+    setup_pool(&mut manager, &dynamic_key);
+    manager.swap(&dynamic_key, true, -1_000_000, TickMath::MIN_SQRT_PRICE + 1, &[]).unwrap();
+    assert_eq!(manager.pool_stats(&dynamic_key).unwrap().lp_fees0, 1000);
+}
+
+/// `examples/lbp_example.rs`: a sale hook follows the manager's clock to
+/// decay the fee buyers pay, and only takes donations after the sale
+#[test]
+fn test_lbp_example() {
+    let mut manager = PoolManager::new();
+    let hooks = HookFlags::new(HookFlags::BEFORE_SWAP | HookFlags::BEFORE_DONATE).apply_to_address(Address::repeat_byte(0xB0));
+    let hook = LiquidityBootstrappingHook::new(manager.clock(), 1_000, 2_000, true);
+    assert_eq!(hook.fee_at(999, true), None);
+    assert_eq!(hook.fee_at(1_500, false), Some(FeePips::new(100_000)));
+    manager.hook_registry_mut().register_hook(hooks, Box::new(hook));
+    let key = pool_key(DYNAMIC_FEE, hooks);
+    manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+    let params = ModifyLiquidityParams::default_position(Address::repeat_byte(1), 0, 6000, 1_000_000_000);
+    manager.modify_liquidity(key.clone(), params, &[]).unwrap();
+
+    let fees_of = |manager: &mut PoolManager, zero_for_one: bool| -> StateResult<u128> {
+        let stats = manager.pool_stats(&key).unwrap();
+        let before = stats.lp_fees0 + stats.lp_fees1;
+        let limit = if zero_for_one { TickMath::MIN_SQRT_PRICE + 1 } else { TickMath::MAX_SQRT_PRICE - 1 };
+        manager.swap(&key, zero_for_one, -1_000_000, limit, &[])?;
+        let stats = manager.pool_stats(&key).unwrap();
+        Ok(stats.lp_fees0 + stats.lp_fees1 - before)
+    };
+    manager.set_timestamp(500);
+    assert!(matches!(fees_of(&mut manager, false), Err(StateError::Hook(_))));
+
+    // Buys pay a premium falling from 10% to 0.3%, sales back pay 10%
+    manager.set_timestamp(1_000);
+    assert_eq!(fees_of(&mut manager, false).unwrap(), 100_000);
+    manager.set_timestamp(1_500);
+    assert_eq!(fees_of(&mut manager, false).unwrap(), 51_500);
+    assert_eq!(fees_of(&mut manager, true).unwrap(), 100_000);
+    assert!(manager.donate(&key, Address::repeat_byte(1), 0, 10_000, &[]).is_err());
+    manager.set_timestamp(2_000);
+    assert_eq!(fees_of(&mut manager, false).unwrap(), 3000);
+    assert_eq!(fees_of(&mut manager, true).unwrap(), 3000);
+
+    let growth_before = manager.get_pool(&key).unwrap().fee_growth_global_1_x128;
+    let delta = manager.donate(&key, Address::repeat_byte(1), 0, 10_000, &[]).unwrap();
+    assert_eq!(delta.amount1(), -10_000);
+    assert!(manager.get_pool(&key).unwrap().fee_growth_global_1_x128 > growth_before);
+}
+
+/// `examples/auto_range_example.rs`: a hook keeps its own position centered
+/// on the price, moving it once a swap takes the price out of its band
+#[test]
+fn test_auto_range_example() {
+    let mut manager = PoolManager::new();
+    let hooks = HookFlags::new(HookFlags::AFTER_SWAP).apply_to_address(Address::repeat_byte(0xC0));
+    let keeper = AutoRangeHook::new(hooks, manager.hook_context(), 2, 1);
+    manager.hook_registry_mut().register_hook(hooks, Box::new(keeper.clone()));
+    let key = pool_key(3000, hooks);
+    manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+    let params = ModifyLiquidityParams::default_position(Address::repeat_byte(1), -6000, 6000, 1_000_000_000_000);
+    manager.modify_liquidity(key.clone(), params, &[]).unwrap();
+    let swap_to = |manager: &mut PoolManager, tick: i32| {
+        let zero_for_one = tick < manager.get_pool(&key).unwrap().slot0.tick;
+        let limit = TickMath::get_sqrt_price_at_tick(tick).unwrap();
+        manager.swap(&key, zero_for_one, -1_000_000_000_000, limit, &[]).unwrap();
+    };
+
+    // Funding places the position around the price
+    keeper.fund(&key, 1_000_000_000, 1_000_000_000);
+    let range = keeper.rebalance(&mut manager, &key).unwrap().unwrap();
+    assert_eq!((range.tick_lower, range.tick_upper), (-120, 120));
+    assert!(range.liquidity > 0 && range.idle0 <= 1 && range.idle1 <= 1);
+    let position = manager.get_default_position(&key, hooks.0, -120, 120).unwrap();
+    assert_eq!(position.liquidity.as_u128(), range.liquidity);
+
+    // Within the band the position stays, and without a swap there is
+    // nothing to check
+    swap_to(&mut manager, 30);
+    assert!(!keeper.needs_rebalance(&manager, &key));
+    assert_eq!(keeper.rebalance(&mut manager, &key).unwrap(), None);
+    swap_to(&mut manager, 200);
+    let moved = keeper.rebalance(&mut manager, &key).unwrap();
+    assert!(!keeper.needs_rebalance(&manager, &key));
+
+    // Out of it, the position moves around the new price; the token0 it
+    // sold limits the new liquidity and the rest of its token1 stays idle
+    let moved = moved.unwrap();
+    assert_eq!((moved.tick_lower, moved.tick_upper), (60, 300));
+    assert!(moved.liquidity > 0 && moved.idle1 > 0);
+    assert_eq!(manager.get_default_position(&key, hooks.0, -120, 120).map_or(0, |position| position.liquidity.as_u128()), 0);
+    assert_eq!(manager.get_default_position(&key, hooks.0, 60, 300).unwrap().liquidity.as_u128(), moved.liquidity);
+    assert_eq!(keeper.managed_range(&key), moved);
+}
+
+/// `examples/erc6909_example.rs`: balances move with transfers, spenders are
+/// bounded by their allowance unless they are operators, and LP tokens
+/// follow mints, transfers and burns
+#[test]
+fn test_erc6909_example() {
+    let mut rng = Rng::seed_from_u64(6909);
+    let (owner, spender, recipient) = (rng.address(), rng.address(), rng.address());
+    let (id1, id2) = (U256::from(1), U256::from(2));
+
+    let mut token = ERC6909::new();
+    token.mint(owner, id1, U256::from(1000)).unwrap();
+    token.mint(owner, id2, U256::from(500)).unwrap();
+    token.transfer(owner, recipient, id1, U256::from(300)).unwrap();
+    assert_eq!(token.balance_of(owner, id1), U256::from(700));
+    assert_eq!(token.balance_of(recipient, id1), U256::from(300));
+
+    // The allowance bounds the spender and shrinks as it is used
+    token.approve(owner, spender, id2, U256::from(200)).unwrap();
+    token.transfer_from(spender, owner, recipient, id2, U256::from(150)).unwrap();
+    assert_eq!(token.allowance(owner, spender, id2), U256::from(50));
+    assert!(matches!(
+        token.transfer_from(spender, owner, recipient, id2, U256::from(100)),
+        Err(ERC6909Error::InsufficientAllowance)
+    ));
+    assert_eq!(token.balance_of(owner, id2), U256::from(350));
+    assert_eq!(token.balance_of(recipient, id2), U256::from(150));
+
+    // An operator moves any token without an allowance
+    token.set_operator(owner, spender, true).unwrap();
+    assert!(token.is_operator(owner, spender));
+    token.transfer_from(spender, owner, recipient, id1, U256::from(100)).unwrap();
+    assert_eq!(token.balance_of(owner, id1), U256::from(600));
+    assert_eq!(token.balance_of(recipient, id1), U256::from(400));
+    assert_eq!(token.allowance(owner, spender, id1), U256::zero());
+
+    let mut lp_token = LiquidityToken::new("Uniswap V4 LP".to_string(), "UNI-V4-LP".to_string());
+    let (pool1, pool2) = (U256::from(1001), U256::from(1002));
+    lp_token.mint_liquidity_token(owner, pool1, U256::from(5000)).unwrap();
+    lp_token.mint_liquidity_token(owner, pool2, U256::from(3000)).unwrap();
+    lp_token.transfer(owner, recipient, pool1, U256::from(1000)).unwrap();
+    lp_token.burn_liquidity_token(owner, pool2, U256::from(500)).unwrap();
+    assert_eq!(lp_token.balance_of(owner, pool1), U256::from(4000));
+    assert_eq!(lp_token.balance_of(recipient, pool1), U256::from(1000));
+    assert_eq!(lp_token.balance_of(owner, pool2), U256::from(2500));
+    assert_eq!(lp_token.total_supply(pool2), U256::from(2500));
+}
+
+/// `examples/flash_loan_example.rs`: single, arbitrage and multi-token loans
+/// repay the principal and fee within the unlock, leaving no debt behind
+#[test]
+fn test_flash_loan_example() {
+    let mut manager = PoolManager::new();
+    let currencies: Vec<_> = (1..=3).map(|i| Currency::from_address(Address::from_low_u64_be(i))).collect();
+    for currency in &currencies {
+        manager.set_flash_fee(*currency, Bps::new(30)).unwrap();
+    }
+    let recipient = Address::from_low_u64_be(2);
+
+    SimpleFlashLoanExample::new(currencies[0], 1000, recipient).execute(&mut manager).unwrap();
+    ArbitrageFlashLoanExample::new(currencies[0], 1000, currencies[1], recipient).execute(&mut manager).unwrap();
+    MultiTokenFlashLoanExample::new(recipient)
+        .add_loan(currencies[0], 1000)
+        .add_loan(currencies[1], 2000)
+        .add_loan(currencies[2], 3000)
+        .execute(&mut manager)
+        .unwrap();
+
+    assert!(!manager.is_unlocked());
+    for currency in &currencies {
+        assert_eq!(manager.get_delta(recipient, *currency), 0);
+    }
+    // Every loan paid its fee to the protocol
+    let fees: Vec<_> = currencies.iter().map(|c| manager.flash_fees_accrued(FlashFeeRecipient::ProtocolFees, *c)).collect();
+    assert_eq!(fees, vec![3 * manager.flash_fee(currencies[0], 1000), manager.flash_fee(currencies[1], 2000), manager.flash_fee(currencies[2], 3000)]);
+    assert!(fees.iter().all(|fee| *fee > 0));
+}
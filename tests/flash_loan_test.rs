@@ -5,11 +5,56 @@ use uniswap_v4_core::{
             SimpleFlashLoanExample,
             ArbitrageFlashLoanExample,
             MultiTokenFlashLoanExample,
+            FlashLoanCallback,
+            FlashLoanError,
+            FlashLoanManager,
+            FlashFeeRecipient,
+            CurrencyPolicy,
+            AccountDelta,
+            UnlockObserver,
+            LedgerError,
+            TokenLedger,
+            POOL_MANAGER_ADDRESS,
         },
+        hooks::hook_interface::ModifyLiquidityParams,
+        math::{Bps, SqrtPrice, TickMath, TickSpacing},
+        pool_manager::{ManagerPoolKey, OperationError, OperationOutput, QuoteRequest, SwapSettlement, UnlockOperation},
+        state::StateError,
         PoolManager,
     },
 };
-use ethers::types::Address;
+use ethers::types::{Address, U256};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Callback that borrows an amount and repays a fixed value
+struct RepayCallback {
+    currency: Currency,
+    borrower: Address,
+    amount: u128,
+    repay: u128,
+}
+
+impl FlashLoanCallback for RepayCallback {
+    fn unlock_callback(&mut self, _data: &[u8]) -> Result<Vec<u8>, FlashLoanError> {
+        Ok(Vec::new())
+    }
+
+    fn unlock_callback_with_manager(
+        &mut self,
+        manager: &mut FlashLoanManager,
+        _data: &[u8],
+    ) -> Result<Vec<u8>, FlashLoanError> {
+        manager.take(self.currency, self.borrower, self.amount)?;
+        assert_eq!(
+            manager.outstanding_loan(self.borrower, self.currency),
+            self.amount + manager.flash_fee(self.currency, self.amount)
+        );
+        manager.sync(self.currency);
+        manager.settle(self.borrower, U256::from(self.repay))?;
+        Ok(Vec::new())
+    }
+}
 
 #[test]
 fn test_simple_flash_loan() {
@@ -101,4 +146,500 @@ fn test_multi_token_flash_loan() {
     // Execute multi-token Flash Loan
     let result = flash_loan.execute(&mut pool_manager);
     assert!(result.is_ok(), "Multi-token flash loan should succeed");
-} 
\ No newline at end of file
+}
+
+#[test]
+fn test_flash_loan_fee_accrues_to_recipient() {
+    let mut pool_manager = PoolManager::new();
+    let currency = Currency::from_address(Address::from_low_u64_be(1));
+    let fee_recipient = FlashFeeRecipient::Account(Address::from_low_u64_be(9));
+
+    pool_manager.set_flash_fee(currency, Bps::new(30)).unwrap(); // 0.3%
+    pool_manager.set_flash_fee_recipient(fee_recipient);
+    assert_eq!(pool_manager.flash_fee(currency, 1000), 3);
+    // Fees round up in favour of the lender
+    assert_eq!(pool_manager.flash_fee(currency, 1001), 4);
+
+    // The example repays the principal plus the quoted fee
+    let flash_loan = SimpleFlashLoanExample::new(currency, 1000, Address::from_low_u64_be(2));
+    flash_loan.execute(&mut pool_manager).unwrap();
+
+    assert_eq!(pool_manager.flash_fees_accrued(fee_recipient, currency), 3);
+    assert_eq!(pool_manager.flash_fees_accrued(FlashFeeRecipient::ProtocolFees, currency), 0);
+    assert_eq!(pool_manager.collect_flash_fees(fee_recipient, currency), 3);
+    assert_eq!(pool_manager.flash_fees_accrued(fee_recipient, currency), 0);
+}
+
+#[test]
+fn test_flash_loan_fee_defaults_to_protocol_fees() {
+    let mut pool_manager = PoolManager::new();
+    let currency = Currency::from_address(Address::from_low_u64_be(1));
+    let other_currency = Currency::from_address(Address::from_low_u64_be(2));
+    pool_manager.set_flash_fee(currency, Bps::new(100)).unwrap(); // 1%
+
+    let flash_loan = MultiTokenFlashLoanExample::new(Address::from_low_u64_be(3))
+        .add_loan(currency, 5000)
+        .add_loan(other_currency, 5000);
+    flash_loan.execute(&mut pool_manager).unwrap();
+
+    // Only the currency with a configured fee charges one
+    assert_eq!(pool_manager.flash_fees_accrued(FlashFeeRecipient::ProtocolFees, currency), 50);
+    assert_eq!(pool_manager.flash_fees_accrued(FlashFeeRecipient::ProtocolFees, other_currency), 0);
+}
+
+#[test]
+fn test_flash_loan_requires_principal_plus_fee() {
+    let mut pool_manager = PoolManager::new();
+    let currency = Currency::from_address(Address::from_low_u64_be(1));
+    let borrower = Address::from_low_u64_be(2);
+    pool_manager.set_flash_fee(currency, Bps::new(30)).unwrap();
+
+    // Repaying only the principal leaves the fee unsettled
+    let mut callback = RepayCallback { currency, borrower, amount: 1000, repay: 1000 };
+    let result = pool_manager.unlock(&mut callback, &[]);
+    assert!(matches!(result, Err(FlashLoanError::CurrencyNotSettled)));
+    assert_eq!(pool_manager.flash_fees_accrued(FlashFeeRecipient::ProtocolFees, currency), 0);
+    assert_eq!(pool_manager.get_delta(borrower, currency), 0);
+
+    // Repaying principal and fee succeeds
+    let mut callback = RepayCallback { currency, borrower, amount: 1000, repay: 1003 };
+    pool_manager.unlock(&mut callback, &[]).unwrap();
+    assert_eq!(pool_manager.flash_fees_accrued(FlashFeeRecipient::ProtocolFees, currency), 3);
+}
+
+#[test]
+fn test_flash_loan_fee_bounds() {
+    let mut pool_manager = PoolManager::new();
+    let currency = Currency::from_address(Address::from_low_u64_be(1));
+
+    assert!(matches!(
+        pool_manager.set_flash_fee(currency, Bps::new(10_001)),
+        Err(FlashLoanError::FlashFeeTooLarge(10_001))
+    ));
+    assert_eq!(pool_manager.flash_fee(currency, 1000), 0);
+
+    // Taking outside of an unlock is rejected
+    assert!(matches!(
+        pool_manager.take(currency, Address::from_low_u64_be(2), 1000),
+        Err(FlashLoanError::NotCalledInCallback)
+    ));
+}
+
+#[test]
+fn test_currency_policy_allowlist() {
+    let mut pool_manager = PoolManager::new();
+    let allowed = Currency::from_address(Address::from_low_u64_be(1));
+    let other = Currency::from_address(Address::from_low_u64_be(2));
+    let borrower = Address::from_low_u64_be(3);
+    pool_manager.set_currency_policy(CurrencyPolicy::allowlist([allowed]));
+
+    let mut callback = RepayCallback { currency: other, borrower, amount: 1000, repay: 1000 };
+    assert!(matches!(
+        pool_manager.unlock(&mut callback, &[]),
+        Err(FlashLoanError::CurrencyNotAllowed(currency)) if currency == other
+    ));
+    let mut callback = RepayCallback { currency: allowed, borrower, amount: 1000, repay: 1000 };
+    pool_manager.unlock(&mut callback, &[]).unwrap();
+
+    // Claims can only be minted on allowed currencies, not arbitrary synthetic IDs
+    pool_manager.mint(borrower, allowed.to_id(), 10).unwrap();
+    assert!(matches!(
+        pool_manager.mint(borrower, U256::from(12345), 10),
+        Err(StateError::CurrencyNotAllowed(_))
+    ));
+    assert!(matches!(
+        pool_manager.burn(borrower, other.to_id(), 10),
+        Err(StateError::CurrencyNotAllowed(_))
+    ));
+}
+
+#[test]
+fn test_currency_policy_take_cap_per_unlock() {
+    let mut pool_manager = PoolManager::new();
+    let currency = Currency::from_address(Address::from_low_u64_be(1));
+    let borrower = Address::from_low_u64_be(2);
+    let mut policy = CurrencyPolicy::new();
+    policy.set_take_cap(currency, 1500);
+    pool_manager.set_currency_policy(policy);
+
+    // Two unlocks of 1000 each stay under the cap, which resets per unlock
+    for _ in 0..2 {
+        let mut callback = RepayCallback { currency, borrower, amount: 1000, repay: 1000 };
+        pool_manager.unlock(&mut callback, &[]).unwrap();
+    }
+
+    // Two takes of 1000 in one unlock exceed it
+    let flash_loan = MultiTokenFlashLoanExample::new(borrower)
+        .add_loan(currency, 1000)
+        .add_loan(currency, 1000);
+    assert!(matches!(
+        flash_loan.execute(&mut pool_manager),
+        Err(FlashLoanError::TakeCapExceeded { cap: 1500, taken: 2000, .. })
+    ));
+    assert_eq!(pool_manager.get_delta(borrower, currency), 0);
+}
+
+/// Callback that credits the borrower before taking, as a swap output would,
+/// and checks how much of each take is lent
+struct CreditedTakeCallback {
+    currency: Currency,
+    borrower: Address,
+    credit: i128,
+}
+
+impl FlashLoanCallback for CreditedTakeCallback {
+    fn unlock_callback(&mut self, _data: &[u8]) -> Result<Vec<u8>, FlashLoanError> {
+        Ok(Vec::new())
+    }
+
+    fn unlock_callback_with_manager(
+        &mut self,
+        manager: &mut FlashLoanManager,
+        _data: &[u8],
+    ) -> Result<Vec<u8>, FlashLoanError> {
+        let (currency, borrower) = (self.currency, self.borrower);
+        manager.update_delta(borrower, currency, self.credit).unwrap();
+
+        // A take covered by the credit is a withdrawal, with no loan or fee
+        manager.take(currency, borrower, 500)?;
+        assert_eq!(manager.get_delta(borrower, currency), self.credit - 500);
+        assert_eq!(manager.outstanding_loan(borrower, currency), 0);
+        assert_eq!(manager.taken_this_unlock(currency), 0);
+
+        // Only the part beyond the remaining credit is lent and charged
+        manager.take(currency, borrower, 1000)?;
+        let loan = 1500 - self.credit as u128;
+        let owed = loan + manager.flash_fee(currency, loan);
+        assert_eq!(manager.get_delta(borrower, currency), -(owed as i128));
+        assert_eq!(manager.outstanding_loan(borrower, currency), owed);
+        assert_eq!(manager.taken_this_unlock(currency), loan);
+
+        manager.sync(currency);
+        manager.settle(borrower, U256::from(owed))?;
+        Ok(Vec::new())
+    }
+}
+
+#[test]
+fn test_take_withdraws_positive_delta_before_lending() {
+    let mut pool_manager = PoolManager::new();
+    let currency = Currency::from_address(Address::from_low_u64_be(1));
+    let borrower = Address::from_low_u64_be(2);
+    pool_manager.set_flash_fee(currency, Bps::new(30)).unwrap();
+    // The cap only counts the lent part, so 1500 taken against 900 of credit fits
+    let mut policy = CurrencyPolicy::new();
+    policy.set_take_cap(currency, 600);
+    pool_manager.set_currency_policy(policy);
+
+    let mut callback = CreditedTakeCallback { currency, borrower, credit: 900 };
+    pool_manager.unlock(&mut callback, &[]).unwrap();
+    // 0.3% of the 600 lent, not of the 1500 taken
+    assert_eq!(pool_manager.flash_fees_accrued(FlashFeeRecipient::ProtocolFees, currency), 2);
+    assert_eq!(pool_manager.get_delta(borrower, currency), 0);
+
+    // Without the credit the whole take is lent and exceeds the cap
+    let mut callback = RepayCallback { currency, borrower, amount: 1500, repay: 1500 };
+    assert!(matches!(
+        pool_manager.unlock(&mut callback, &[]),
+        Err(FlashLoanError::TakeCapExceeded { cap: 600, .. })
+    ));
+}
+
+/// Callback that moves an amount of a currency between two accounts
+struct TransferCallback {
+    currency: Currency,
+    from: Address,
+    to: Address,
+    amount: i128,
+}
+
+impl FlashLoanCallback for TransferCallback {
+    fn unlock_callback(&mut self, _data: &[u8]) -> Result<Vec<u8>, FlashLoanError> {
+        Ok(Vec::new())
+    }
+
+    fn unlock_callback_with_manager(
+        &mut self,
+        manager: &mut FlashLoanManager,
+        _data: &[u8],
+    ) -> Result<Vec<u8>, FlashLoanError> {
+        manager.update_delta(self.from, self.currency, -self.amount).unwrap();
+        manager.update_delta(self.to, self.currency, self.amount).unwrap();
+        Ok(vec![1])
+    }
+}
+
+/// Callback that makes a transfer and then reverses it, tracking the open
+/// balances in between
+struct SettledTransferCallback(TransferCallback);
+
+impl FlashLoanCallback for SettledTransferCallback {
+    fn unlock_callback(&mut self, _data: &[u8]) -> Result<Vec<u8>, FlashLoanError> {
+        Ok(Vec::new())
+    }
+
+    fn unlock_callback_with_manager(
+        &mut self,
+        manager: &mut FlashLoanManager,
+        data: &[u8],
+    ) -> Result<Vec<u8>, FlashLoanError> {
+        let TransferCallback { currency, from, to, amount } = self.0;
+        let result = self.0.unlock_callback_with_manager(manager, data)?;
+        assert_eq!(manager.nonzero_delta_count(), 2);
+        assert_eq!(manager.currency_delta(to, currency), amount);
+        manager.update_delta(to, currency, -amount).unwrap();
+        manager.update_delta(from, currency, amount).unwrap();
+        assert_eq!(manager.nonzero_delta_count(), 0);
+        Ok(result)
+    }
+}
+
+/// Observer that records the unlocks it sees and caps any account's debt
+struct DebtLimitObserver {
+    max_debt: i128,
+    events: Rc<RefCell<Vec<Option<Vec<AccountDelta>>>>>,
+}
+
+impl UnlockObserver for DebtLimitObserver {
+    fn on_unlock_start(&mut self) -> Result<(), FlashLoanError> {
+        self.events.borrow_mut().push(None);
+        Ok(())
+    }
+
+    fn on_unlock_end(&mut self, deltas: &[AccountDelta]) -> Result<(), FlashLoanError> {
+        self.events.borrow_mut().push(Some(deltas.to_vec()));
+        if deltas.iter().any(|delta| delta.delta < -self.max_debt) {
+            return Err(FlashLoanError::Other("debt limit exceeded".to_string()));
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_unlock_observers() {
+    let mut pool_manager = PoolManager::new();
+    let currency = Currency::from_address(Address::from_low_u64_be(1));
+    let (alice, bob) = (Address::from_low_u64_be(2), Address::from_low_u64_be(3));
+    let events = Rc::new(RefCell::new(Vec::new()));
+    let id = pool_manager.add_unlock_observer(Box::new(DebtLimitObserver { max_debt: 100, events: events.clone() }));
+
+    // The observer sees the balances the unlock left open, sorted by account,
+    // before the unlock fails for leaving them open
+    let mut callback = TransferCallback { currency, from: bob, to: alice, amount: 60 };
+    assert!(matches!(pool_manager.unlock(&mut callback, &[]), Err(FlashLoanError::CurrencyNotSettled)));
+    assert_eq!(*events.borrow(), vec![
+        None,
+        Some(vec![
+            AccountDelta { account: alice, currency, delta: 60 },
+            AccountDelta { account: bob, currency, delta: -60 },
+        ]),
+    ]);
+    assert_eq!(pool_manager.get_delta(bob, currency), 0);
+
+    // A rejection fails the unlock first
+    let mut callback = TransferCallback { currency, from: bob, to: alice, amount: 200 };
+    assert!(matches!(pool_manager.unlock(&mut callback, &[]), Err(FlashLoanError::Other(_))));
+
+    // Without the observer the unlock is still unsettled
+    assert!(pool_manager.remove_unlock_observer(id).is_some());
+    assert!(matches!(pool_manager.unlock(&mut callback, &[]), Err(FlashLoanError::CurrencyNotSettled)));
+    assert_eq!(pool_manager.get_delta(bob, currency), 0);
+
+    // A transfer settled within the same unlock goes through
+    let mut callback = SettledTransferCallback(callback);
+    assert_eq!(pool_manager.unlock(&mut callback, &[]).unwrap(), vec![1]);
+    assert_eq!(pool_manager.get_delta(alice, currency), 0);
+}
+
+#[test]
+fn test_multi_currency_flash_loan_with_swap() {
+    // Same flow as examples/multi_currency_flash_example.rs
+    let mut pool_manager = PoolManager::new();
+    let key = ManagerPoolKey::new(
+        Address::from_low_u64_be(1),
+        Address::from_low_u64_be(2),
+        3000,
+        TickSpacing::new(60).unwrap(),
+        Address::zero(),
+    ).unwrap();
+    let (currency0, currency1) = (Currency::from_address(key.token0()), Currency::from_address(key.token1()));
+    pool_manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+    let params = ModifyLiquidityParams::default_position(Address::repeat_byte(1), -600, 600, 1_000_000_000_000);
+    pool_manager.modify_liquidity(key.clone(), params, &[]).unwrap();
+    pool_manager.set_flash_fee(currency0, Bps::new(5)).unwrap();
+    pool_manager.set_flash_fee(currency1, Bps::new(5)).unwrap();
+
+    let borrower = Address::repeat_byte(2);
+    let (borrow0, borrow1) = (1_000_000u128, 500_000u128);
+    let fee0 = pool_manager.flash_fee(currency0, borrow0);
+    let fee1 = pool_manager.flash_fee(currency1, borrow1);
+    let limit = TickMath::MIN_SQRT_PRICE + 1;
+    let request = QuoteRequest { key: key.clone(), zero_for_one: true, amount_specified: -(borrow0 as i128), sqrt_price_limit_x96: limit };
+    let swap_out = pool_manager.quote(&request).unwrap().delta.amount1() as u128;
+    let dust = swap_out % 1000;
+    assert!(dust > 0);
+
+    let take0 = UnlockOperation::Take { currency: currency0, to: borrower, amount: borrow0 };
+    let take1 = UnlockOperation::Take { currency: currency1, to: borrower, amount: borrow1 };
+    let swap = UnlockOperation::Swap {
+        key: key.clone(),
+        zero_for_one: true,
+        amount_specified: -(borrow0 as i128),
+        sqrt_price_limit_x96: limit,
+        settlement: SwapSettlement::Deltas { owner: borrower },
+        hook_data: vec![],
+    };
+    let take_output = UnlockOperation::Take { currency: currency1, to: borrower, amount: swap_out - dust };
+    let settle0 = UnlockOperation::Settle { currency: currency0, recipient: borrower, value: U256::from(2 * borrow0 + fee0) };
+    let settle1 = UnlockOperation::Settle { currency: currency1, recipient: borrower, value: U256::from(borrow1 + fee1) };
+    let clear = UnlockOperation::Clear { currency: currency1, account: borrower, amount: dust };
+
+    // Leaving the token1 loan unpaid fails the unlock and rolls the swap back,
+    // and clearing more than the dust is rejected. The swap output would only
+    // partly cover the withdrawal, so the rest is another loan
+    let too_much = UnlockOperation::Clear { currency: currency1, account: borrower, amount: dust + 1 };
+    let result = pool_manager.unlock_batch(&[take0.clone(), take1.clone(), swap.clone(), take_output.clone(), settle0.clone(), too_much]);
+    assert!(result.results[5].is_err());
+    assert!(matches!(result.unlock_error, Some(FlashLoanError::CurrencyNotSettled)));
+    assert!(pool_manager.get_pool(&key).unwrap().slot0.sqrt_price_x96 == SqrtPrice::ONE);
+    assert_eq!(pool_manager.get_delta(borrower, currency0), 0);
+    assert_eq!(pool_manager.get_delta(borrower, currency1), 0);
+
+    let result = pool_manager.unlock_batch(&[take0, take1, swap, settle1, take_output, settle0, clear]);
+    assert!(result.success, "{:?}", result);
+    assert!(matches!(
+        result.results[2],
+        Ok(OperationOutput::Delta(delta)) if delta.amount0() == -(borrow0 as i128) && delta.amount1() as u128 == swap_out
+    ));
+    assert!(matches!(result.results[5], Ok(OperationOutput::Settled(value)) if value == U256::from(2 * borrow0 + fee0)));
+    assert!(pool_manager.get_pool(&key).unwrap().slot0.sqrt_price_x96 < SqrtPrice::ONE);
+
+    // Every delta is settled, and only the loans paid fees
+    assert_eq!(pool_manager.get_delta(borrower, currency0), 0);
+    assert_eq!(pool_manager.get_delta(borrower, currency1), 0);
+    assert_eq!(pool_manager.flash_fees_accrued(FlashFeeRecipient::ProtocolFees, currency0), fee0);
+    assert_eq!(pool_manager.flash_fees_accrued(FlashFeeRecipient::ProtocolFees, currency1), fee1);
+}
+
+/// Callback that takes a currency, pays it back into the manager's ledger
+/// account and settles what was paid, sending `value` along
+struct LedgerRepayCallback {
+    currency: Currency,
+    borrower: Address,
+    amount: u128,
+    value: u128,
+}
+
+impl FlashLoanCallback for LedgerRepayCallback {
+    fn unlock_callback(&mut self, _data: &[u8]) -> Result<Vec<u8>, FlashLoanError> {
+        Ok(Vec::new())
+    }
+
+    fn unlock_callback_with_manager(
+        &mut self,
+        manager: &mut FlashLoanManager,
+        _data: &[u8],
+    ) -> Result<Vec<u8>, FlashLoanError> {
+        manager.take(self.currency, self.borrower, self.amount)?;
+        manager.sync(self.currency);
+        if !self.currency.is_native() {
+            let amount = U256::from(self.amount);
+            manager.token_ledger_mut().unwrap().transfer(self.currency, self.borrower, POOL_MANAGER_ADDRESS, amount)?;
+        }
+        let paid = manager.settle(self.borrower, U256::from(self.value))?;
+        Ok(paid.as_u128().to_be_bytes().to_vec())
+    }
+}
+
+#[test]
+fn test_take_and_settle_move_ledger_balances() {
+    let mut pool_manager = PoolManager::new();
+    let token = Currency::from_address(Address::from_low_u64_be(1));
+    let borrower = Address::from_low_u64_be(2);
+    let mut ledger = TokenLedger::new();
+    ledger.mint(token, POOL_MANAGER_ADDRESS, U256::from(10_000)).unwrap();
+    ledger.mint(Currency::Native, POOL_MANAGER_ADDRESS, U256::from(500)).unwrap();
+    ledger.mint(token, borrower, U256::from(10)).unwrap();
+    pool_manager.set_token_ledger(ledger);
+    pool_manager.set_flash_fee(token, Bps::new(100)).unwrap();
+
+    // Takes pay out of the manager's balance, and settles credit what the
+    // borrower paid back in, fee included
+    let take = |currency, amount| UnlockOperation::Take { currency, to: borrower, amount };
+    let settle = |currency, value: u128| UnlockOperation::Settle { currency, recipient: borrower, value: U256::from(value) };
+    let result = pool_manager.unlock_batch(&[
+        take(token, 1000),
+        take(Currency::Native, 500),
+        settle(token, 1010),
+        settle(Currency::Native, 500),
+    ]);
+    assert!(result.success, "{:?}", result);
+    assert!(matches!(result.results[2], Ok(OperationOutput::Settled(value)) if value == U256::from(1010)));
+    let ledger = pool_manager.token_ledger().unwrap();
+    assert_eq!(ledger.balance_of(token, POOL_MANAGER_ADDRESS), U256::from(10_010));
+    assert_eq!(ledger.balance_of(token, borrower), U256::zero());
+    assert_eq!(ledger.balance_of(Currency::Native, POOL_MANAGER_ADDRESS), U256::from(500));
+
+    // The manager can't pay out more than it holds, and an unsettled
+    // unlock gives back what it took
+    let result = pool_manager.unlock_batch(&[take(token, 20_000), take(token, 1000)]);
+    assert!(matches!(
+        &result.results[0],
+        Err(OperationError::FlashLoan(FlashLoanError::TransferFailed(error))) if **error == LedgerError::InsufficientBalance {
+            currency: token,
+            account: POOL_MANAGER_ADDRESS,
+            balance: U256::from(10_010),
+            amount: U256::from(20_000),
+        }
+    ));
+    assert!(matches!(result.unlock_error, Some(FlashLoanError::CurrencyNotSettled)));
+    assert_eq!(pool_manager.token_ledger().unwrap().balance_of(token, borrower), U256::zero());
+}
+
+#[test]
+fn test_settle_pays_the_balance_change_since_sync() {
+    let mut pool_manager = PoolManager::new();
+    let token = Currency::from_address(Address::from_low_u64_be(1));
+    let borrower = Address::from_low_u64_be(2);
+    let mut ledger = TokenLedger::new();
+    ledger.mint(token, POOL_MANAGER_ADDRESS, U256::from(1000)).unwrap();
+    ledger.mint(Currency::Native, POOL_MANAGER_ADDRESS, U256::from(1000)).unwrap();
+    pool_manager.set_token_ledger(ledger);
+
+    // Only the tokens transferred in since the sync are settled
+    let mut callback = LedgerRepayCallback { currency: token, borrower, amount: 600, value: 0 };
+    assert_eq!(pool_manager.unlock(&mut callback, &[]).unwrap(), 600u128.to_be_bytes().to_vec());
+
+    // Native value can't be sent along with another currency
+    let mut callback = LedgerRepayCallback { currency: token, borrower, amount: 600, value: 600 };
+    assert!(matches!(pool_manager.unlock(&mut callback, &[]), Err(FlashLoanError::NonzeroNativeValue)));
+    assert_eq!(pool_manager.token_ledger().unwrap().balance_of(token, POOL_MANAGER_ADDRESS), U256::from(1000));
+
+    // Native currency is settled by the value sent, which the payer must hold
+    let mut callback = LedgerRepayCallback { currency: Currency::Native, borrower, amount: 600, value: 600 };
+    pool_manager.unlock(&mut callback, &[]).unwrap();
+    let mut callback = LedgerRepayCallback { currency: Currency::Native, borrower, amount: 600, value: 601 };
+    assert!(matches!(
+        pool_manager.unlock(&mut callback, &[]),
+        Err(FlashLoanError::TransferFailed(error)) if matches!(*error, LedgerError::InsufficientBalance { .. })
+    ));
+    assert_eq!(pool_manager.token_ledger().unwrap().balance_of(Currency::Native, borrower), U256::zero());
+}
+
+#[test]
+fn test_pay_and_settle_while_locked_keeps_the_tokens() {
+    let mut pool_manager = PoolManager::new();
+    let token = Currency::from_address(Address::from_low_u64_be(1));
+    let payer = Address::from_low_u64_be(2);
+    let mut ledger = TokenLedger::new();
+    ledger.mint(token, payer, U256::from(1000)).unwrap();
+    pool_manager.set_token_ledger(ledger.clone());
+
+    // Settling outside an unlock fails after the transfer, which is undone
+    assert!(matches!(
+        pool_manager.pay_and_settle(token, payer, U256::from(600)),
+        Err(FlashLoanError::NotCalledInCallback)
+    ));
+    assert_eq!(pool_manager.token_ledger(), Some(&ledger));
+    assert_eq!(pool_manager.currency_delta(payer, token), 0);
+}
@@ -6,8 +6,8 @@ use uniswap_v4_core::core::{
         hook_interface::{PoolKey, SwapParams, ModifyLiquidityParams},
         examples::{DynamicFeeHook, TwapOracleHook, LiquidityMiningHook},
     },
-    math::types::SqrtPrice,
-    state::BalanceDelta,
+    math::{types::{SqrtPrice, TickSpacing}, FeePips},
+    state::{BalanceDelta, Salt},
 };
 
 /// Test hook that tracks calls
@@ -57,7 +57,7 @@ impl TestHook {
 impl Hook for TestHook {
     fn before_initialize(
         &mut self,
-        _sender: [u8; 20],
+        _sender: Address,
         _key: &PoolKey,
         _sqrt_price_x96: SqrtPrice,
         _hook_data: &[u8],
@@ -68,7 +68,7 @@ impl Hook for TestHook {
     
     fn after_initialize(
         &mut self,
-        _sender: [u8; 20],
+        _sender: Address,
         _key: &PoolKey,
         _sqrt_price_x96: SqrtPrice,
         _tick: i32,
@@ -80,7 +80,7 @@ impl Hook for TestHook {
     
     fn before_swap(
         &mut self,
-        _sender: [u8; 20],
+        _sender: Address,
         _key: &PoolKey,
         _params: &SwapParams,
         _hook_data: &[u8],
@@ -91,7 +91,7 @@ impl Hook for TestHook {
     
     fn after_swap(
         &mut self,
-        _sender: [u8; 20],
+        _sender: Address,
         _key: &PoolKey,
         _params: &SwapParams,
         _delta: &BalanceDelta,
@@ -103,7 +103,7 @@ impl Hook for TestHook {
     
     fn before_add_liquidity(
         &mut self,
-        _sender: [u8; 20],
+        _sender: Address,
         _key: &PoolKey,
         _params: &ModifyLiquidityParams,
         _hook_data: &[u8],
@@ -114,7 +114,7 @@ impl Hook for TestHook {
     
     fn after_add_liquidity(
         &mut self,
-        _sender: [u8; 20],
+        _sender: Address,
         _key: &PoolKey,
         _params: &ModifyLiquidityParams,
         _delta: &BalanceDelta,
@@ -127,7 +127,7 @@ impl Hook for TestHook {
     
     fn before_remove_liquidity(
         &mut self,
-        _sender: [u8; 20],
+        _sender: Address,
         _key: &PoolKey,
         _params: &ModifyLiquidityParams,
         _hook_data: &[u8],
@@ -138,7 +138,7 @@ impl Hook for TestHook {
     
     fn after_remove_liquidity(
         &mut self,
-        _sender: [u8; 20],
+        _sender: Address,
         _key: &PoolKey,
         _params: &ModifyLiquidityParams,
         _delta: &BalanceDelta,
@@ -151,7 +151,7 @@ impl Hook for TestHook {
     
     fn before_donate(
         &mut self,
-        _sender: [u8; 20],
+        _sender: Address,
         _key: &PoolKey,
         _amount0: u128,
         _amount1: u128,
@@ -163,7 +163,7 @@ impl Hook for TestHook {
     
     fn after_donate(
         &mut self,
-        _sender: [u8; 20],
+        _sender: Address,
         _key: &PoolKey,
         _amount0: u128,
         _amount1: u128,
@@ -179,7 +179,7 @@ impl HookWithReturns for TestHook {}
 #[test]
 fn test_hook_registry() {
     let mut registry = HookRegistry::new();
-    let hook_address = [1u8; 20];
+    let hook_address = Address::repeat_byte(1);
     let hook = Box::new(TestHook::new());
     
     // Register hook
@@ -198,33 +198,61 @@ fn test_hook_registry() {
     assert!(!registry.has_hook(&hook_address));
 }
 
+#[test]
+fn test_hook_registry_describe_hooks() {
+    let mut registry = HookRegistry::new();
+    registry.register_hook(Address::repeat_byte(2), Box::new(DynamicFeeHook::new(FeePips::new(3000), FeePips::new(500), FeePips::new(10000))));
+    registry.register_hook(Address::repeat_byte(1), Box::new(TestHook::new()));
+
+    let descriptors = registry.describe_hooks();
+    assert_eq!(descriptors.len(), 2);
+
+    // Descriptors are ordered by hook address
+    assert_eq!(descriptors[0].0, Address::repeat_byte(1));
+    assert!(descriptors[0].1.name.ends_with("TestHook"));
+    assert!(!descriptors[0].1.permissions.before_swap);
+
+    let dynamic_fee = &descriptors[1].1;
+    assert_eq!(dynamic_fee.name, "DynamicFeeHook");
+    assert!(dynamic_fee.permissions.before_swap);
+    assert!(!dynamic_fee.permissions.after_swap);
+    assert_eq!(dynamic_fee.config.get("base_fee").map(String::as_str), Some("3000"));
+    assert_eq!(dynamic_fee.config.get("max_fee").map(String::as_str), Some("10000"));
+
+    // Descriptors can be serialized for simulation reports
+    let json = serde_json::to_value(dynamic_fee).unwrap();
+    assert_eq!(json["name"], "DynamicFeeHook");
+    assert_eq!(json["permissions"]["before_swap"], true);
+    assert_eq!(json["config"]["min_fee"], "500");
+}
+
 #[test]
 fn test_dynamic_fee_hook() {
-    let mut hook = DynamicFeeHook::new(3000, 500, 10000);
+    let mut hook = DynamicFeeHook::new(FeePips::new(3000), FeePips::new(500), FeePips::new(10000));
     
     // Create swap params
     let params = SwapParams {
         amount_specified: 1000000,
         zero_for_one: true,
-        sqrt_price_limit_x96: SqrtPrice::new(U256::from(1u128 << 96)),
+        sqrt_price_limit_x96: SqrtPrice::ONE,
     };
     
     // Create pool key
     let key = PoolKey {
-        token0: [1u8; 20],
-        token1: [2u8; 20],
+        token0: Address::repeat_byte(1),
+        token1: Address::repeat_byte(2),
         fee: 3000,
-        tick_spacing: 60,
-        hooks: [0u8; 20],
+        tick_spacing: TickSpacing::new(60).unwrap(),
+        hooks: Address::zero(),
         extension_data: vec![],
     };
     
     // Call before_swap
-    let result = hook.before_swap([0u8; 20], &key, &params, &[]).unwrap();
+    let result = hook.before_swap(Address::zero(), &key, &params, &[]).unwrap();
     
     // Check that fee override is set
     assert!(result.fee_override.is_some());
-    assert_eq!(result.fee_override.unwrap(), 3000); // First call should return base fee
+    assert_eq!(result.fee_override.unwrap(), FeePips::new(3000)); // First call should return base fee
     
     // Call again with different price
     let params2 = SwapParams {
@@ -233,11 +261,11 @@ fn test_dynamic_fee_hook() {
         sqrt_price_limit_x96: SqrtPrice::new(U256::from(2u128 << 96)),
     };
     
-    let result2 = hook.before_swap([0u8; 20], &key, &params2, &[]).unwrap();
+    let result2 = hook.before_swap(Address::zero(), &key, &params2, &[]).unwrap();
     
     // Check that fee is adjusted
     assert!(result2.fee_override.is_some());
-    assert!(result2.fee_override.unwrap() > 3000); // Fee should increase due to price change
+    assert!(result2.fee_override.unwrap() > FeePips::new(3000)); // Fee should increase due to price change
 }
 
 #[test]
@@ -248,22 +276,22 @@ fn test_twap_oracle_hook() {
     let params = SwapParams {
         amount_specified: 1000000,
         zero_for_one: true,
-        sqrt_price_limit_x96: SqrtPrice::new(U256::from(1u128 << 96)),
+        sqrt_price_limit_x96: SqrtPrice::ONE,
     };
     
     // Create pool key
     let key = PoolKey {
-        token0: [1u8; 20],
-        token1: [2u8; 20],
+        token0: Address::repeat_byte(1),
+        token1: Address::repeat_byte(2),
         fee: 3000,
-        tick_spacing: 60,
-        hooks: [0u8; 20],
+        tick_spacing: TickSpacing::new(60).unwrap(),
+        hooks: Address::zero(),
         extension_data: vec![],
     };
     
     // Call after_swap to update oracle
     let delta = BalanceDelta { amount0: -1000000, amount1: 1000000 };
-    hook.after_swap([0u8; 20], &key, &params, &delta, &[]).unwrap();
+    hook.after_swap(Address::zero(), &key, &params, &delta, &[]).unwrap();
     
     // Get TWAP
     let twap = hook.get_twap(60); // 60 second TWAP
@@ -279,7 +307,7 @@ fn test_twap_oracle_hook() {
         sqrt_price_limit_x96: SqrtPrice::new(U256::from(2u128 << 96)),
     };
     
-    hook.after_swap([0u8; 20], &key, &params2, &delta, &[]).unwrap();
+    hook.after_swap(Address::zero(), &key, &params2, &delta, &[]).unwrap();
     
     // Since we can't directly access the observations field, we'll check the TWAP again
     // to verify that the oracle has been updated
@@ -294,16 +322,16 @@ fn test_liquidity_mining_hook() {
     
     // Create pool key
     let key = PoolKey {
-        token0: [1u8; 20],
-        token1: [2u8; 20],
+        token0: Address::repeat_byte(1),
+        token1: Address::repeat_byte(2),
         fee: 3000,
-        tick_spacing: 60,
-        hooks: [0u8; 20],
+        tick_spacing: TickSpacing::new(60).unwrap(),
+        hooks: Address::zero(),
         extension_data: vec![],
     };
     
     // Create user
-    let user = [3u8; 20];
+    let user = Address::repeat_byte(3);
     
     // Create add liquidity params
     let add_params = ModifyLiquidityParams {
@@ -311,13 +339,13 @@ fn test_liquidity_mining_hook() {
         tick_lower: -100,
         tick_upper: 100,
         liquidity_delta: 1000000,
-        salt: [0u8; 32],
+        salt: Salt::ZERO,
     };
     
     // Add liquidity
     let delta = BalanceDelta { amount0: -1000, amount1: -1000 };
     let fees = BalanceDelta { amount0: 0, amount1: 0 };
-    hook.after_add_liquidity([0u8; 20], &key, &add_params, &delta, &fees, &[]).unwrap();
+    hook.after_add_liquidity(Address::zero(), &key, &add_params, &delta, &fees, &[]).unwrap();
     
     // Wait a bit and check rewards
     std::thread::sleep(std::time::Duration::from_secs(1));
@@ -328,10 +356,10 @@ fn test_liquidity_mining_hook() {
         tick_lower: -100,
         tick_upper: 100,
         liquidity_delta: -1000000,
-        salt: [0u8; 32],
+        salt: Salt::ZERO,
     };
     
-    hook.after_remove_liquidity([0u8; 20], &key, &remove_params, &delta, &fees, &[]).unwrap();
+    hook.after_remove_liquidity(Address::zero(), &key, &remove_params, &delta, &fees, &[]).unwrap();
     
     // Check that user has rewards
     let rewards = hook.claim_rewards(user);
@@ -343,11 +371,11 @@ struct CustomProtocolFeeHook {
     fee_fraction: u32,
     collected_fees_0: u128,
     collected_fees_1: u128,
-    fee_recipient: [u8; 20],
+    fee_recipient: Address,
 }
 
 impl CustomProtocolFeeHook {
-    fn new(fee_fraction: u32, fee_recipient: [u8; 20]) -> Self {
+    fn new(fee_fraction: u32, fee_recipient: Address) -> Self {
         Self {
             fee_fraction,
             collected_fees_0: 0,
@@ -380,7 +408,7 @@ impl Hook for CustomProtocolFeeHook {}
 impl HookWithReturns for CustomProtocolFeeHook {
     fn after_swap_with_delta(
         &mut self,
-        _sender: [u8; 20],
+        _sender: Address,
         _key: &PoolKey,
         _params: &SwapParams,
         delta: &BalanceDelta,
@@ -402,22 +430,22 @@ impl HookWithReturns for CustomProtocolFeeHook {
 
 #[test]
 fn test_protocol_fee_hook() {
-    let mut hook = CustomProtocolFeeHook::new(30, [4u8; 20]); // 0.3% fee
+    let mut hook = CustomProtocolFeeHook::new(30, Address::repeat_byte(4)); // 0.3% fee
     
     // Create swap params
     let params = SwapParams {
         amount_specified: 1000000,
         zero_for_one: true,
-        sqrt_price_limit_x96: SqrtPrice::new(U256::from(1u128 << 96)),
+        sqrt_price_limit_x96: SqrtPrice::ONE,
     };
     
     // Create pool key
     let key = PoolKey {
-        token0: [1u8; 20],
-        token1: [2u8; 20],
+        token0: Address::repeat_byte(1),
+        token1: Address::repeat_byte(2),
         fee: 3000,
-        tick_spacing: 60,
-        hooks: [0u8; 20],
+        tick_spacing: TickSpacing::new(60).unwrap(),
+        hooks: Address::zero(),
         extension_data: vec![],
     };
     
@@ -425,7 +453,7 @@ fn test_protocol_fee_hook() {
     let delta = BalanceDelta { amount0: 0, amount1: 1000000 };
     
     // Call after_swap_with_delta
-    let fee_delta = hook.after_swap_with_delta([0u8; 20], &key, &params, &delta, &[]).unwrap();
+    let fee_delta = hook.after_swap_with_delta(Address::zero(), &key, &params, &delta, &[]).unwrap();
     
     // Check that fee was collected
     assert_eq!(fee_delta, 3000); // 0.3% of 1000000
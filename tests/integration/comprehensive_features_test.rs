@@ -6,8 +6,8 @@ use uniswap_v4_core::{
             hook_interface::{PoolKey, SwapParams, ModifyLiquidityParams},
             HookWithReturns
         },
-        state::{BalanceDelta, StateError, Pool},
-        math::types::SqrtPrice,
+        state::{BalanceDelta, StateError, Pool, Salt},
+        math::{types::{SqrtPrice, TickSpacing}, FeePips},
         flash_loan::{Currency, FlashLoanCallback},
     },
     fees::{
@@ -57,7 +57,7 @@ impl ComprehensiveHook {
     
     /// Set protocol fee for a specific currency pair
     fn set_protocol_fee(&mut self, token0: Address, token1: Address, fee0: u16, fee1: u16) {
-        let protocol_fee = ProtocolFee::new(fee0, fee1);
+        let protocol_fee = ProtocolFee::new(fee0, fee1).unwrap();
         self.fee_map.insert((token0, token1), protocol_fee);
         println!("Protocol fee set: {}% for token0->token1, {}% for token1->token0", 
                  fee0 as f64 / 10000.0, 
@@ -66,7 +66,7 @@ impl ComprehensiveHook {
     
     /// Get protocol fee for a specific currency pair
     fn get_protocol_fee(&self, token0: Address, token1: Address) -> ProtocolFee {
-        *self.fee_map.get(&(token0, token1)).unwrap_or(&ProtocolFee::new(0, 0))
+        *self.fee_map.get(&(token0, token1)).unwrap_or(&ProtocolFee::default())
     }
     
     /// Calculate volatility based on price history
@@ -129,9 +129,9 @@ impl ComprehensiveHook {
         
         // Determine which fee rate to use based on swap direction
         let fee_rate = if zero_for_one {
-            protocol_fee.get_zero_for_one_fee()
+            protocol_fee.get_zero_for_one_fee().get()
         } else {
-            protocol_fee.get_one_for_zero_fee()
+            protocol_fee.get_one_for_zero_fee().get()
         };
         
         // Calculate fee amount (fee_rate is in hundredths of a bip, e.g. 100 = 0.01%)
@@ -152,7 +152,7 @@ impl ComprehensiveHook {
 impl Hook for ComprehensiveHook {
     fn before_swap(
         &mut self,
-        _sender: [u8; 20],
+        _sender: Address,
         _key: &PoolKey,
         params: &SwapParams,
         _hook_data: &[u8],
@@ -170,16 +170,12 @@ impl Hook for ComprehensiveHook {
         let dynamic_fee = self.calculate_dynamic_fee();
         
         // Return dynamic fee as fee override
-        Ok(BeforeHookResult {
-            amount: None,
-            delta: None,
-            fee_override: Some(dynamic_fee),
-        })
+        Ok(BeforeHookResult::default().with_fee_override(FeePips::new(dynamic_fee)))
     }
     
     fn after_add_liquidity(
         &mut self,
-        _sender: [u8; 20],
+        _sender: Address,
         key: &PoolKey,
         params: &ModifyLiquidityParams,
         _delta: &BalanceDelta,
@@ -187,7 +183,7 @@ impl Hook for ComprehensiveHook {
         _hook_data: &[u8],
     ) -> Result<AfterHookResult, StateError> {
         // Convert owner to Address
-        let owner = Address::from_slice(&params.owner);
+        let owner = params.owner;
         
         // If liquidity is positive, mint LP tokens as a reward
         if params.liquidity_delta > 0 {
@@ -210,7 +206,7 @@ impl Hook for ComprehensiveHook {
     // Implement other required Hook methods with default implementations
     fn after_swap(
         &mut self,
-        _sender: [u8; 20],
+        _sender: Address,
         _key: &PoolKey,
         _params: &SwapParams,
         _delta: &BalanceDelta,
@@ -221,7 +217,7 @@ impl Hook for ComprehensiveHook {
     
     fn before_initialize(
         &mut self,
-        _sender: [u8; 20],
+        _sender: Address,
         _key: &PoolKey,
         _sqrt_price: SqrtPrice,
         _hook_data: &[u8],
@@ -231,7 +227,7 @@ impl Hook for ComprehensiveHook {
     
     fn after_initialize(
         &mut self,
-        _sender: [u8; 20],
+        _sender: Address,
         _key: &PoolKey,
         _sqrt_price: SqrtPrice,
         _tick: i32,
@@ -242,7 +238,7 @@ impl Hook for ComprehensiveHook {
     
     fn before_add_liquidity(
         &mut self,
-        _sender: [u8; 20],
+        _sender: Address,
         _key: &PoolKey,
         _params: &ModifyLiquidityParams,
         _hook_data: &[u8],
@@ -252,7 +248,7 @@ impl Hook for ComprehensiveHook {
     
     fn before_remove_liquidity(
         &mut self,
-        _sender: [u8; 20],
+        _sender: Address,
         _key: &PoolKey,
         _params: &ModifyLiquidityParams,
         _hook_data: &[u8],
@@ -262,7 +258,7 @@ impl Hook for ComprehensiveHook {
     
     fn after_remove_liquidity(
         &mut self,
-        _sender: [u8; 20],
+        _sender: Address,
         _key: &PoolKey,
         _params: &ModifyLiquidityParams,
         _delta: &BalanceDelta,
@@ -274,7 +270,7 @@ impl Hook for ComprehensiveHook {
     
     fn before_donate(
         &mut self,
-        _sender: [u8; 20],
+        _sender: Address,
         _key: &PoolKey,
         _amount0: u128,
         _amount1: u128,
@@ -285,7 +281,7 @@ impl Hook for ComprehensiveHook {
     
     fn after_donate(
         &mut self,
-        _sender: [u8; 20],
+        _sender: Address,
         _key: &PoolKey,
         _amount0: u128,
         _amount1: u128,
@@ -299,14 +295,14 @@ impl Hook for ComprehensiveHook {
 impl HookWithReturns for ComprehensiveHook {
     fn before_swap_with_delta(
         &mut self,
-        _sender: [u8; 20],
+        _sender: Address,
         key: &PoolKey,
         params: &SwapParams,
         _hook_data: &[u8],
     ) -> Result<BeforeSwapDelta, StateError> {
         // Extract token addresses from key
-        let token0 = Address::from_slice(&key.token0);
-        let token1 = Address::from_slice(&key.token1);
+        let token0 = key.token0;
+        let token1 = key.token1;
         
         // Calculate protocol fee based on swap direction and amount
         let fee_amount = self.calculate_protocol_fee_amount(
@@ -381,12 +377,11 @@ fn test_comprehensive_features() {
     let mut registry = HookRegistry::new();
     
     // Create hook address with required flags
-    let hook_address = [
-        (HookFlags::BEFORE_SWAP | 
-         HookFlags::AFTER_ADD_LIQUIDITY | 
-         HookFlags::BEFORE_SWAP_RETURNS_DELTA) as u8,
-        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0
-    ];
+    let hook_address = HookFlags::new(
+        HookFlags::BEFORE_SWAP |
+        HookFlags::AFTER_ADD_LIQUIDITY |
+        HookFlags::BEFORE_SWAP_RETURNS_DELTA
+    ).apply_to_address(Address::zero());
     
     // Register hook
     registry.register_hook(hook_address, Box::new(hook));
@@ -396,10 +391,10 @@ fn test_comprehensive_features() {
     
     // Create pool key
     let pool_key = PoolKey {
-        token0: token0.0,
-        token1: token1.0,
+        token0,
+        token1,
         fee: 3000, // 0.3% base fee
-        tick_spacing: 60,
+        tick_spacing: TickSpacing::new(60).unwrap(),
         hooks: hook_address,
         extension_data: vec![],
     };
@@ -409,7 +404,7 @@ fn test_comprehensive_features() {
     
     // Create pool
     let mut pool = Pool::new();
-    let tick = pool.initialize(sqrt_price, 3000).unwrap();
+    let tick = pool.initialize(sqrt_price, FeePips::new(3000)).unwrap();
     println!("Pool initialized at tick: {}", tick);
     
     println!("\n2. Adding Liquidity");
@@ -424,13 +419,13 @@ fn test_comprehensive_features() {
         tick_lower: -100,
         tick_upper: 100,
         liquidity_delta: 1_000_000,
-        salt: [0u8; 32],
+        salt: Salt::ZERO,
     };
     
     // Call after_add_liquidity to simulate adding liquidity
     let delta = BalanceDelta::new(0, 0);
     let fees = BalanceDelta::new(0, 0);
-    hook.after_add_liquidity([0u8; 20], &pool_key, &liquidity_params, &delta, &fees, &[]).unwrap();
+    hook.after_add_liquidity(Address::zero(), &pool_key, &liquidity_params, &delta, &fees, &[]).unwrap();
     
     // Check LP token balance
     let pool_id = U256::from_big_endian(&pool_key.token0) + U256::from_big_endian(&pool_key.token1);
@@ -460,10 +455,10 @@ fn test_comprehensive_features() {
         };
         
         // Call before_swap
-        let result = hook.before_swap([0u8; 20], &pool_key, &swap_params, &[]).unwrap();
+        let result = hook.before_swap(Address::zero(), &pool_key, &swap_params, &[]).unwrap();
         
         // Check dynamic fee
-        println!("  Dynamic fee: {} (base fee: 3000)", result.fee_override.unwrap_or(3000));
+        println!("  Dynamic fee: {} (base fee: 3000)", result.fee_override.unwrap_or(FeePips::new(3000)));
     }
     
     println!("\n4. Protocol Fee Collection");
@@ -477,7 +472,7 @@ fn test_comprehensive_features() {
     };
     
     // Call before_swap_with_delta
-    let delta = hook.before_swap_with_delta([0u8; 20], &pool_key, &swap_params, &[]).unwrap();
+    let delta = hook.before_swap_with_delta(Address::zero(), &pool_key, &swap_params, &[]).unwrap();
     
     // Check protocol fee collection
     println!("Protocol fee collected: {} (from amount: {})", delta.delta_specified, swap_params.amount_specified.abs());
@@ -513,11 +508,11 @@ fn test_high_volatility_scenario() {
     
     // Create pool key for testing
     let pool_key = PoolKey {
-        token0: [0u8; 20],
-        token1: [0u8; 20],
+        token0: Address::zero(),
+        token1: Address::zero(),
         fee: 3000,
-        tick_spacing: 60,
-        hooks: [0u8; 20],
+        tick_spacing: TickSpacing::new(60).unwrap(),
+        hooks: Address::zero(),
         extension_data: vec![],
     };
     
@@ -555,11 +550,11 @@ fn test_high_volatility_scenario() {
     };
     
     // Call before_swap
-    let result = hook.before_swap([0u8; 20], &pool_key, &swap_params, &[]).unwrap();
+    let result = hook.before_swap(Address::zero(), &pool_key, &swap_params, &[]).unwrap();
     
     // Check if fee override is provided
     assert!(result.fee_override.is_some(), "Hook should provide fee override");
-    assert!(result.fee_override.unwrap() > 3000, "Fee override should be higher than base fee");
+    assert!(result.fee_override.unwrap() > FeePips::new(3000), "Fee override should be higher than base fee");
     
     println!("High Volatility Scenario test completed successfully!");
 } 
\ No newline at end of file
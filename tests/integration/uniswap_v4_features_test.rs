@@ -5,15 +5,16 @@
 
 use ethers::types::Address;
 use primitive_types::U256;
+use uniswap_v4_core::Rng;
 use uniswap_v4_core::{
     core::{
-        state::{Pool, BalanceDelta},
+        state::{Pool, BalanceDelta, Salt},
         hooks::{
             Hook, HookWithReturns, HookRegistry, HookFlags, BeforeSwapDelta,
             BeforeHookResult, AfterHookResult, 
             hook_interface::{PoolKey, SwapParams, ModifyLiquidityParams}
         },
-        math::types::SqrtPrice,
+        math::{types::{SqrtPrice, TickSpacing}, FeePips},
         flash_loan::currency::Currency
     },
     fees::{ProtocolFee, ProtocolFeeManager, ProtocolFeeIntegration},
@@ -80,7 +81,7 @@ impl Hook for IntegrationTestHook {
     // Before swap, set dynamic fee and collect protocol fee
     fn before_swap(
         &mut self,
-        sender: [u8; 20],
+        sender: Address,
         _key: &PoolKey,
         params: &SwapParams,
         _hook_data: &[u8],
@@ -90,17 +91,13 @@ impl Hook for IntegrationTestHook {
         let dynamic_fee = self.calculate_dynamic_fee(current_price);
         
         // Return result with fee override
-        Ok(BeforeHookResult {
-            amount: None,
-            delta: None,
-            fee_override: Some(dynamic_fee),
-        })
+        Ok(BeforeHookResult::default().with_fee_override(FeePips::new(dynamic_fee)))
     }
     
     // After adding liquidity, reward liquidity providers
     fn after_add_liquidity(
         &mut self,
-        _sender: [u8; 20],
+        _sender: Address,
         _key: &PoolKey,
         params: &ModifyLiquidityParams,
         _delta: &BalanceDelta,
@@ -108,7 +105,7 @@ impl Hook for IntegrationTestHook {
         _hook_data: &[u8],
     ) -> Result<AfterHookResult, uniswap_v4_core::core::state::StateError> {
         // Convert owner to Address
-        let owner = Address::from_slice(&params.owner);
+        let owner = params.owner;
         
         // If liquidity is positive, mint LP tokens as a reward
         if params.liquidity_delta > 0 {
@@ -126,7 +123,7 @@ impl HookWithReturns for IntegrationTestHook {
     // Return Delta values before swap
     fn before_swap_with_delta(
         &mut self,
-        _sender: [u8; 20],
+        _sender: Address,
         _key: &PoolKey,
         params: &SwapParams,
         _hook_data: &[u8],
@@ -155,20 +152,21 @@ impl HookWithReturns for IntegrationTestHook {
 
 #[test]
 fn test_integrated_features() {
+    let mut rng = Rng::seed_from_u64(1);
     // Create test environment
-    let owner = Address::random();
-    let trader = Address::random();
+    let owner = rng.address();
+    let trader = rng.address();
     
     // Create pool
     let mut pool = Pool::new();
-    let sqrt_price = SqrtPrice::new(U256::from(2).pow(U256::from(96)));
-    pool.initialize(sqrt_price, 3000).unwrap(); // 0.3% base fee rate
+    let sqrt_price = SqrtPrice::ONE;
+    pool.initialize(sqrt_price, FeePips::new(3000)).unwrap(); // 0.3% base fee rate
     
     // Initialize liquidity token
     pool.initialize_liquidity_token("Test Pool LP".to_string(), "TPLP".to_string());
     
     // Create protocol fee
-    let protocol_fee = ProtocolFee::new(100, 200); // 0.01% for 0->1, 0.02% for 1->0
+    let protocol_fee = ProtocolFee::new(100, 200).unwrap(); // 0.01% for 0->1, 0.02% for 1->0
     
     // Create test Hook
     let mut test_hook = IntegrationTestHook::new(owner);
@@ -177,20 +175,18 @@ fn test_integrated_features() {
     let mut registry = HookRegistry::new();
     
     // Test Hook address - including BEFORE_SWAP and BEFORE_SWAP_RETURNS_DELTA flags
-    let hook_address = [
-        (HookFlags::BEFORE_SWAP | HookFlags::BEFORE_SWAP_RETURNS_DELTA) as u8,
-        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0
-    ];
+    let hook_address = HookFlags::new(HookFlags::BEFORE_SWAP | HookFlags::BEFORE_SWAP_RETURNS_DELTA)
+        .apply_to_address(Address::zero());
     
     // Register Hook
     registry.register_hook(hook_address, Box::new(test_hook));
     
     // Create pool key
     let pool_key = PoolKey {
-        token0: [0u8; 20],
-        token1: [0u8; 20],
+        token0: Address::zero(),
+        token1: Address::zero(),
         fee: 3000,
-        tick_spacing: 60,
+        tick_spacing: TickSpacing::new(60).unwrap(),
         hooks: hook_address,
         extension_data: vec![],
     };
@@ -198,19 +194,13 @@ fn test_integrated_features() {
     // Test integrated features
     
     // 1. Add liquidity, test LP token minting
-    let sender = [0u8; 20];
-    let owner_bytes = {
-        let mut bytes = [0u8; 20];
-        bytes.copy_from_slice(&trader.as_bytes()[0..20]);
-        bytes
-    };
-    
+    let sender = Address::zero();
     let params = ModifyLiquidityParams {
-        owner: owner_bytes,
+        owner: trader,
         tick_lower: -100,
         tick_upper: 100,
         liquidity_delta: 1_000_000,
-        salt: [0u8; 32],
+        salt: Salt::ZERO,
     };
     
     // Get Hook as mutable reference
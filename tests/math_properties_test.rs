@@ -0,0 +1,87 @@
+//! Property tests of the core math against exact reference arithmetic
+
+use primitive_types::{U256, U512};
+use proptest::prelude::*;
+use uniswap_v4_core::core::{
+    math::{types::{Liquidity, SqrtPrice, TickSpacing}, FeePips, SqrtPriceMath, TickMath},
+    state::Pool,
+};
+
+fn sqrt_price() -> impl Strategy<Value = U256> {
+    (TickMath::MIN_SQRT_PRICE.as_u128()..u128::MAX, any::<u32>()).prop_map(|(low, high)| {
+        let price = U256::from(high) << 128 | U256::from(low);
+        price.clamp(TickMath::MIN_SQRT_PRICE, TickMath::MAX_SQRT_PRICE - 1)
+    })
+}
+
+/// `numerator / denominator`, rounded up or down
+fn div(numerator: U512, denominator: U512, round_up: bool) -> U256 {
+    let (quotient, remainder) = numerator.div_mod(denominator);
+    let quotient = if round_up && !remainder.is_zero() { quotient + 1 } else { quotient };
+    U256::try_from(quotient).unwrap()
+}
+
+proptest! {
+    #[test]
+    fn tick_round_trips_through_its_sqrt_price(tick in TickMath::MIN_TICK..TickMath::MAX_TICK) {
+        let sqrt_price = TickMath::get_sqrt_price_at_tick(tick).unwrap();
+        prop_assert_eq!(TickMath::get_tick_at_sqrt_price(sqrt_price).unwrap(), tick);
+    }
+
+    #[test]
+    fn tick_at_sqrt_price_is_the_greatest_below(sqrt_price in sqrt_price()) {
+        let tick = TickMath::get_tick_at_sqrt_price(sqrt_price).unwrap();
+        prop_assert!(TickMath::get_sqrt_price_at_tick(tick).unwrap() <= sqrt_price);
+        if tick < TickMath::MAX_TICK {
+            prop_assert!(TickMath::get_sqrt_price_at_tick(tick + 1).unwrap() > sqrt_price);
+        }
+    }
+
+    #[test]
+    fn amount0_delta_is_exact(a in sqrt_price(), b in sqrt_price(), liquidity in any::<u128>(), round_up in any::<bool>()) {
+        let (lower, upper) = (a.min(b), a.max(b));
+        // liquidity * (upper - lower) * 2^96 / (upper * lower), in one division
+        let numerator = (U256::from(liquidity) << 96).full_mul(upper - lower);
+        let expected = div(numerator, lower.full_mul(upper), round_up);
+        let amount = SqrtPriceMath::get_amount0_delta(SqrtPrice::new(a), SqrtPrice::new(b), Liquidity::new(liquidity), round_up);
+        prop_assert_eq!(amount.unwrap(), expected);
+    }
+
+    #[test]
+    fn amount1_delta_is_exact(a in sqrt_price(), b in sqrt_price(), liquidity in any::<u128>(), round_up in any::<bool>()) {
+        let numerator = U256::from(liquidity).full_mul(a.max(b) - a.min(b));
+        let expected = div(numerator, U512::one() << 96, round_up);
+        let amount = SqrtPriceMath::get_amount1_delta(SqrtPrice::new(a), SqrtPrice::new(b), Liquidity::new(liquidity), round_up);
+        prop_assert_eq!(amount.unwrap(), expected);
+    }
+
+    #[test]
+    fn exact_input_swap_stays_within_amount_and_limit(
+        amount_in in 1i128..1_000_000_000,
+        limit_tick in 1i32..2_000,
+        zero_for_one in any::<bool>(),
+    ) {
+        let tick_spacing = TickSpacing::new(60).unwrap();
+        let mut pool = Pool::new();
+        pool.initialize(SqrtPrice::ONE, FeePips::new(3000)).unwrap();
+        pool.modify_position([1; 20], -1200, 1200, 1_000_000_000, tick_spacing, [0; 32]).unwrap();
+
+        let limit_tick = if zero_for_one { -limit_tick } else { limit_tick };
+        let limit = TickMath::get_sqrt_price_at_tick(limit_tick).unwrap();
+        let (delta, _) = pool.swap(-amount_in, SqrtPrice::new(limit), zero_for_one, tick_spacing, None).unwrap();
+
+        // The pool takes at most the amount in and pays out the other token
+        let (paid, received) = if zero_for_one { (delta.amount0, delta.amount1) } else { (delta.amount1, delta.amount0) };
+        prop_assert!(-amount_in <= paid && paid <= 0);
+        prop_assert!(received >= 0);
+
+        // The price moves towards the limit without passing it
+        let price = pool.slot0.sqrt_price_x96.to_u256();
+        if zero_for_one {
+            prop_assert!(limit <= price && price <= SqrtPrice::ONE.to_u256());
+        } else {
+            prop_assert!(SqrtPrice::ONE.to_u256() <= price && price <= limit);
+        }
+        prop_assert_eq!(pool.slot0.tick, TickMath::get_tick_at_sqrt_price(price).unwrap());
+    }
+}
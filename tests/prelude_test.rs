@@ -0,0 +1,45 @@
+//! Guards the stable surface: every item of the prelude is named here, so
+//! removing or renaming one fails to compile
+
+use uniswap_v4_core::prelude::*;
+
+#[test]
+fn test_prelude_runs_a_swap() {
+    let spacing = TickSpacing::new(60).unwrap();
+    let key = ManagerPoolKey::new(
+        Address::from_low_u64_be(1),
+        Address::from_low_u64_be(2),
+        3000,
+        spacing,
+        Address::zero(),
+    )
+    .unwrap();
+    let mut manager = PoolManager::new();
+    assert_eq!(manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap(), 0);
+
+    let owner = Rng::seed_from_u64(1).address();
+    let params = ModifyLiquidityParams::default_position(owner, -600, 600, 1_000_000_000);
+    manager.modify_liquidity(key.clone(), params, &[]).unwrap();
+    let limit: U256 = TickMath::get_sqrt_price_at_tick(-60).unwrap();
+    let delta: BalanceDelta = manager.swap(&key, true, -1_000, limit, &[]).unwrap();
+    assert_eq!(delta.amount0(), -1_000);
+
+    let pool: &Pool = manager.get_pool(&key).unwrap();
+    let slot0: &Slot0 = &pool.slot0;
+    assert!(slot0.tick < 0);
+    let _: PoolId = pool_key_to_id(&key);
+    let _: Option<Currency> = None;
+    let _: Option<(PositionKey, Liquidity, FeePips, Bps, Percent)> = None;
+}
+
+#[allow(dead_code)]
+fn names_the_rest(
+    _: &dyn HookWithReturns,
+    _: Option<&dyn Hook>,
+    _: (HookFlags, HookPermissions, BeforeHookResult, AfterHookResult, BeforeSwapDelta),
+    _: (PoolKey, SwapParams, QuoteRequest),
+    _: (HookError, StateError, MathError),
+    _: StateResult<()>,
+) {
+    let _ = (FullMath::mul_div, SqrtPriceMath::get_amount0_delta, SwapMath::compute_swap_step);
+}
@@ -0,0 +1,249 @@
+//! Swaps and liquidity operations at numeric extremes
+//!
+//! These tests pin down the operating envelope of the crate: every input
+//! inside it succeeds, and every input outside it fails with a typed error
+//! rather than a panic or a silently truncated amount.
+//!
+//! - Prices run from `MIN_SQRT_PRICE` to `MAX_SQRT_PRICE - 1`, as in v4,
+//!   and price limits must lie strictly inside that range.
+//! - A tick holds at most `u128::MAX` divided by the number of usable ticks
+//!   of its spacing, so no liquidity delta reaches `i128::MIN`.
+//! - Amounts are `i128`: swaps of `i128::MIN` and donations above
+//!   `i128::MAX` are rejected, as are swaps and donations whose amounts
+//!   would leave a currency delta beyond `i128`.
+
+use ethers::types::Address;
+use uniswap_v4_core::core::{
+    flash_loan::Currency,
+    hooks::hook_interface::ModifyLiquidityParams,
+    math::{tick_math::TickMath, types::{SqrtPrice, TickSpacing}},
+    pool::PoolError,
+    pool_manager::{ManagerPoolKey, PoolManager},
+    state::StateError,
+};
+
+const OWNER: Address = Address::repeat_byte(1);
+
+fn key(tick_spacing: i32) -> ManagerPoolKey {
+    ManagerPoolKey::new(
+        Address::from_low_u64_be(1),
+        Address::from_low_u64_be(2),
+        3000,
+        TickSpacing::new(tick_spacing).unwrap(),
+        Address::zero(),
+    )
+    .unwrap()
+}
+
+fn full_range(key: &ManagerPoolKey, liquidity_delta: i128) -> ModifyLiquidityParams {
+    let tick_spacing = key.tick_spacing().get();
+    ModifyLiquidityParams::default_position(
+        OWNER,
+        TickMath::min_usable_tick(tick_spacing),
+        TickMath::max_usable_tick(tick_spacing),
+        liquidity_delta,
+    )
+}
+
+/// Most liquidity a tick of the spacing can hold, as in v4's
+/// `tickSpacingToMaxLiquidityPerTick`
+fn max_liquidity_per_tick(tick_spacing: i32) -> u128 {
+    let ticks = (TickMath::max_usable_tick(tick_spacing) - TickMath::min_usable_tick(tick_spacing)) / tick_spacing + 1;
+    u128::MAX / ticks as u128
+}
+
+/// A pool at price 1 with a full range position
+fn pool_with_liquidity(tick_spacing: i32, liquidity: i128) -> (PoolManager, ManagerPoolKey) {
+    let mut manager = PoolManager::new();
+    let key = key(tick_spacing);
+    manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+    manager.modify_liquidity(key.clone(), full_range(&key, liquidity), &[]).unwrap();
+    (manager, key)
+}
+
+#[test]
+fn test_prices_at_bounds() {
+    let min = SqrtPrice::new(TickMath::MIN_SQRT_PRICE);
+    let max = SqrtPrice::new(TickMath::MAX_SQRT_PRICE - 1);
+    assert_eq!(PoolManager::new().initialize_pool(key(60), min).unwrap(), TickMath::MIN_TICK);
+    // The maximum tick's price is excluded, so the highest price is in the tick below
+    assert_eq!(PoolManager::new().initialize_pool(key(60), max).unwrap(), TickMath::MAX_TICK - 1);
+    for price in [TickMath::MIN_SQRT_PRICE - 1, TickMath::MAX_SQRT_PRICE, 0.into()] {
+        let result = PoolManager::new().initialize_pool(key(60), SqrtPrice::new(price));
+        assert!(matches!(result, Err(PoolError::StateError(StateError::InvalidPrice))));
+    }
+
+    // Limits at the bounds themselves are rejected
+    let (mut manager, key) = pool_with_liquidity(60, 1_000_000);
+    assert!(matches!(
+        manager.swap(&key, true, -1000, TickMath::MIN_SQRT_PRICE, &[]),
+        Err(StateError::PriceLimitOutOfBounds(_))
+    ));
+    assert!(matches!(
+        manager.swap(&key, false, -1000, TickMath::MAX_SQRT_PRICE, &[]),
+        Err(StateError::PriceLimitOutOfBounds(_))
+    ));
+
+    // A full range position can be opened at either end of the price range,
+    // holding a single currency, and swapped against
+    for (price, zero_for_one, limit) in [
+        (max, true, TickMath::MIN_SQRT_PRICE + 1),
+        (min, false, TickMath::MAX_SQRT_PRICE - 1),
+    ] {
+        let mut manager = PoolManager::new();
+        manager.initialize_pool(key.clone(), price).unwrap();
+        let (added, _) = manager.modify_liquidity(key.clone(), full_range(&key, 1_000_000), &[]).unwrap();
+        let delta = manager.swap(&key, zero_for_one, -1000, limit, &[]).unwrap();
+        if zero_for_one {
+            assert_eq!(added.amount0(), 0);
+            assert_eq!(delta.amount0(), -1000);
+            // Nearly all of the position's token1 is bought at the top price
+            assert!(delta.amount1() > 0 && delta.amount1() <= -added.amount1());
+        } else {
+            assert_eq!(added.amount1(), 0);
+            assert_eq!(delta.amount1(), -1000);
+            assert!(delta.amount0() > 0 && delta.amount0() <= -added.amount0());
+        }
+    }
+}
+
+#[test]
+fn test_liquidity_at_max_per_tick() {
+    for tick_spacing in [1, 60, TickMath::MAX_TICK_SPACING] {
+        let mut manager = PoolManager::new();
+        let key = key(tick_spacing);
+        manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+        let max = max_liquidity_per_tick(tick_spacing) as i128;
+        let (delta, _) = manager.modify_liquidity(key.clone(), full_range(&key, max), &[]).unwrap();
+        assert!(delta.amount0() < 0 && delta.amount1() < 0);
+
+        // One more unit of liquidity on the same ticks is too much
+        let lower = TickMath::min_usable_tick(tick_spacing);
+        assert!(matches!(
+            manager.modify_liquidity(key.clone(), full_range(&key, 1), &[]),
+            Err(StateError::TickLiquidityOverflow(tick)) if tick == lower
+        ));
+        // The position can be removed in full, but no more
+        assert!(manager.modify_liquidity(key.clone(), full_range(&key, -max - 1), &[]).is_err());
+        manager.modify_liquidity(key.clone(), full_range(&key, -max), &[]).unwrap();
+        assert_eq!(manager.get_pool(&key).unwrap().liquidity.as_u128(), 0);
+    }
+
+    let (mut manager, key) = pool_with_liquidity(60, 1_000_000);
+    assert!(matches!(
+        manager.modify_liquidity(key.clone(), full_range(&key, i128::MAX), &[]),
+        Err(StateError::TickLiquidityOverflow(_))
+    ));
+    assert!(matches!(
+        manager.modify_liquidity(key.clone(), full_range(&key, i128::MIN), &[]),
+        Err(StateError::LiquidityOverflow)
+    ));
+
+    // Ticks may reach the bounds but not pass them
+    let mut manager = PoolManager::new();
+    let key = self::key(1);
+    manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+    let bounds = ModifyLiquidityParams::default_position(OWNER, TickMath::MIN_TICK, TickMath::MAX_TICK, 1000);
+    manager.modify_liquidity(key.clone(), bounds.clone(), &[]).unwrap();
+    let below = ModifyLiquidityParams { tick_lower: TickMath::MIN_TICK - 1, ..bounds.clone() };
+    assert!(matches!(
+        manager.modify_liquidity(key.clone(), below, &[]),
+        Err(StateError::TickLowerOutOfBounds(_))
+    ));
+    let above = ModifyLiquidityParams { tick_upper: TickMath::MAX_TICK + 1, ..bounds };
+    assert!(matches!(
+        manager.modify_liquidity(key, above, &[]),
+        Err(StateError::TickUpperOutOfBounds(_))
+    ));
+}
+
+#[test]
+fn test_swaps_of_extreme_amounts() {
+    let (mut manager, key) = pool_with_liquidity(60, 1_000_000_000_000);
+    let pool_before = manager.get_pool(&key).unwrap().clone();
+    assert!(matches!(
+        manager.swap(&key, true, i128::MIN, TickMath::MIN_SQRT_PRICE + 1, &[]),
+        Err(StateError::AmountOverflow)
+    ));
+    assert!(*manager.get_pool(&key).unwrap() == pool_before);
+
+    // Swaps too large for the pool stop at their limit, both ways and for
+    // exact input and output alike
+    for amount in [i128::MIN + 1, i128::MAX] {
+        let mut down = PoolManager::new();
+        down.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+        down.modify_liquidity(key.clone(), full_range(&key, 1_000_000_000_000), &[]).unwrap();
+        let limit = TickMath::MIN_SQRT_PRICE + 1;
+        let delta = down.swap(&key, true, amount, limit, &[]).unwrap();
+        assert!(delta.amount0() < 0 && delta.amount1() > 0);
+        assert_eq!(down.get_pool(&key).unwrap().slot0.sqrt_price_x96.to_u256(), limit);
+
+        let limit = TickMath::MAX_SQRT_PRICE - 1;
+        let delta = down.swap(&key, false, amount, limit, &[]).unwrap();
+        assert!(delta.amount0() > 0 && delta.amount1() < 0);
+        assert_eq!(down.get_pool(&key).unwrap().slot0.sqrt_price_x96.to_u256(), limit);
+    }
+}
+
+#[test]
+fn test_swaps_through_deep_liquidity() {
+    // Positions at the maximum liquidity on every usable tick of the widest
+    // spacing, so the pool's liquidity comes close to u128::MAX / 2
+    let tick_spacing = TickMath::MAX_TICK_SPACING;
+    let mut manager = PoolManager::new();
+    let key = key(tick_spacing);
+    manager.initialize_pool(key.clone(), SqrtPrice::ONE).unwrap();
+    let max = max_liquidity_per_tick(tick_spacing) as i128;
+    let widths = TickMath::max_usable_tick(tick_spacing) / tick_spacing;
+    for width in 1..=widths {
+        let range = width * tick_spacing;
+        let params = ModifyLiquidityParams::default_position(OWNER, -range, range, max);
+        manager.modify_liquidity(key.clone(), params, &[]).unwrap();
+    }
+    let liquidity = manager.get_pool(&key).unwrap().liquidity.as_u128();
+    assert_eq!(liquidity, max as u128 * widths as u128);
+    assert!(liquidity > u128::MAX / 3);
+
+    // Buying an i128 of token0 at price 1 would take more than an i128 of
+    // token1
+    let pool_before = manager.get_pool(&key).unwrap().clone();
+    assert!(matches!(
+        manager.swap(&key, false, i128::MAX, TickMath::MAX_SQRT_PRICE - 1, &[]),
+        Err(StateError::AmountOverflow)
+    ));
+    assert!(*manager.get_pool(&key).unwrap() == pool_before);
+
+    // The largest exact input is filled in full, and once it has moved the
+    // price down it can be bought back
+    let sold = manager.swap(&key, true, i128::MIN + 1, TickMath::MIN_SQRT_PRICE + 1, &[]).unwrap();
+    assert_eq!(sold.amount0(), -i128::MAX);
+    assert!(sold.amount1() > 0);
+    let bought = manager.swap(&key, false, i128::MAX, TickMath::MAX_SQRT_PRICE - 1, &[]).unwrap();
+    assert_eq!(bought.amount0(), i128::MAX);
+    assert!(-bought.amount1() > sold.amount1());
+}
+
+#[test]
+fn test_donations_beyond_i128() {
+    let (mut manager, key) = pool_with_liquidity(60, 1);
+    let donor = Address::repeat_byte(2);
+    assert!(matches!(
+        manager.donate(&key, donor, u128::MAX, 0, &[]),
+        Err(StateError::AmountOverflow)
+    ));
+    assert!(matches!(
+        manager.donate(&key, donor, 0, i128::MAX as u128 + 1, &[]),
+        Err(StateError::AmountOverflow)
+    ));
+
+    // The largest donation goes through, but leaves no room in the donor's
+    // delta for another
+    let delta = manager.donate(&key, donor, i128::MAX as u128, 0, &[]).unwrap();
+    assert_eq!(delta.amount0(), -i128::MAX);
+    let currency0 = Currency::from_address(key.token0());
+    assert_eq!(manager.get_delta(donor, currency0), -i128::MAX);
+    assert!(matches!(
+        manager.donate(&key, donor, 2, 0, &[]),
+        Err(StateError::AmountOverflow)
+    ));
+}
@@ -5,7 +5,7 @@ use uniswap_v4_core::{
             hook_interface::{PoolKey, SwapParams, ModifyLiquidityParams}
         },
         state::{BalanceDelta, StateError},
-        math::types::SqrtPrice,
+        math::{types::{SqrtPrice, TickSpacing}, FeePips},
         pool_manager::{PoolManager, ManagerPoolKey}
     },
 };
@@ -94,7 +94,7 @@ impl DynamicFeeHook {
 impl Hook for DynamicFeeHook {
     fn before_swap(
         &mut self,
-        _sender: [u8; 20],
+        _sender: Address,
         _key: &PoolKey,
         params: &SwapParams,
         _hook_data: &[u8],
@@ -112,17 +112,13 @@ impl Hook for DynamicFeeHook {
         let dynamic_fee = self.calculate_dynamic_fee();
         
         // Return dynamic fee as fee override
-        Ok(BeforeHookResult {
-            amount: None,
-            delta: None,
-            fee_override: Some(dynamic_fee),
-        })
+        Ok(BeforeHookResult::default().with_fee_override(FeePips::new(dynamic_fee)))
     }
     
     // Implement other required Hook methods with default implementations
     fn after_swap(
         &mut self,
-        _sender: [u8; 20],
+        _sender: Address,
         _key: &PoolKey,
         _params: &SwapParams,
         _delta: &BalanceDelta,
@@ -133,7 +129,7 @@ impl Hook for DynamicFeeHook {
     
     fn before_initialize(
         &mut self,
-        _sender: [u8; 20],
+        _sender: Address,
         _key: &PoolKey,
         _sqrt_price: SqrtPrice,
         _hook_data: &[u8],
@@ -143,7 +139,7 @@ impl Hook for DynamicFeeHook {
     
     fn after_initialize(
         &mut self,
-        _sender: [u8; 20],
+        _sender: Address,
         _key: &PoolKey,
         _sqrt_price: SqrtPrice,
         _tick: i32,
@@ -154,7 +150,7 @@ impl Hook for DynamicFeeHook {
     
     fn before_add_liquidity(
         &mut self,
-        _sender: [u8; 20],
+        _sender: Address,
         _key: &PoolKey,
         _params: &ModifyLiquidityParams,
         _hook_data: &[u8],
@@ -164,7 +160,7 @@ impl Hook for DynamicFeeHook {
     
     fn after_add_liquidity(
         &mut self,
-        _sender: [u8; 20],
+        _sender: Address,
         _key: &PoolKey,
         _params: &ModifyLiquidityParams,
         _delta: &BalanceDelta,
@@ -176,7 +172,7 @@ impl Hook for DynamicFeeHook {
     
     fn before_remove_liquidity(
         &mut self,
-        _sender: [u8; 20],
+        _sender: Address,
         _key: &PoolKey,
         _params: &ModifyLiquidityParams,
         _hook_data: &[u8],
@@ -186,7 +182,7 @@ impl Hook for DynamicFeeHook {
     
     fn after_remove_liquidity(
         &mut self,
-        _sender: [u8; 20],
+        _sender: Address,
         _key: &PoolKey,
         _params: &ModifyLiquidityParams,
         _delta: &BalanceDelta,
@@ -198,7 +194,7 @@ impl Hook for DynamicFeeHook {
     
     fn before_donate(
         &mut self,
-        _sender: [u8; 20],
+        _sender: Address,
         _key: &PoolKey,
         _amount0: u128,
         _amount1: u128,
@@ -209,7 +205,7 @@ impl Hook for DynamicFeeHook {
     
     fn after_donate(
         &mut self,
-        _sender: [u8; 20],
+        _sender: Address,
         _key: &PoolKey,
         _amount0: u128,
         _amount1: u128,
@@ -230,20 +226,17 @@ fn test_dynamic_fee_hook() {
     let dynamic_fee_hook = DynamicFeeHook::new(3000);
     
     // Create hook address with BEFORE_SWAP flag
-    let hook_address = [
-        HookFlags::BEFORE_SWAP as u8,
-        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0
-    ];
+    let hook_address = HookFlags::new(HookFlags::BEFORE_SWAP).apply_to_address(Address::zero());
     
     // Register hook
     registry.register_hook(hook_address, Box::new(dynamic_fee_hook));
     
     // Create pool key for testing
     let pool_key = PoolKey {
-        token0: [0u8; 20],
-        token1: [0u8; 20],
+        token0: Address::zero(),
+        token1: Address::zero(),
         fee: 3000,
-        tick_spacing: 60,
+        tick_spacing: TickSpacing::new(60).unwrap(),
         hooks: hook_address,
         extension_data: vec![],
     };
@@ -272,7 +265,7 @@ fn test_dynamic_fee_hook() {
         };
         
         // Call before_swap
-        let result = hook.before_swap([0u8; 20], &pool_key, &swap_params, &[]).unwrap();
+        let result = hook.before_swap(Address::zero(), &pool_key, &swap_params, &[]).unwrap();
         
         // Check if fee override is provided
         assert!(result.fee_override.is_some(), "Hook should provide fee override");
@@ -293,11 +286,11 @@ fn test_dynamic_fee_hook_with_high_volatility() {
     
     // Create pool key for testing
     let pool_key = PoolKey {
-        token0: [0u8; 20],
-        token1: [0u8; 20],
+        token0: Address::zero(),
+        token1: Address::zero(),
         fee: 3000,
-        tick_spacing: 60,
-        hooks: [0u8; 20],
+        tick_spacing: TickSpacing::new(60).unwrap(),
+        hooks: Address::zero(),
         extension_data: vec![],
     };
     
@@ -335,11 +328,11 @@ fn test_dynamic_fee_hook_with_high_volatility() {
     };
     
     // Call before_swap
-    let result = hook.before_swap([0u8; 20], &pool_key, &swap_params, &[]).unwrap();
+    let result = hook.before_swap(Address::zero(), &pool_key, &swap_params, &[]).unwrap();
     
     // Check if fee override is provided
     assert!(result.fee_override.is_some(), "Hook should provide fee override");
-    assert!(result.fee_override.unwrap() > 3000, "Fee override should be higher than base fee");
+    assert!(result.fee_override.unwrap() > FeePips::new(3000), "Fee override should be higher than base fee");
     
     println!("Dynamic Fee Hook high volatility test completed successfully!");
 } 
\ No newline at end of file
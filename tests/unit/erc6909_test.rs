@@ -2,16 +2,18 @@
 mod erc6909_tests {
     use ethers::types::Address;
     use primitive_types::U256;
+    use uniswap_v4_core::Rng;
     use uniswap_v4_core::tokens::{
-        ERC6909, LiquidityToken, ERC6909Error,
+        ERC6909, LiquidityToken, ERC6909Error, TokenMetadata,
         LiquidityTokenClaims, ERC6909Claims, ClaimsError
     };
 
     #[test]
     fn test_erc6909_basic_operations() {
+        let mut rng = Rng::seed_from_u64(1);
         let mut token = ERC6909::new();
-        let owner = Address::random();
-        let recipient = Address::random();
+        let owner = rng.address();
+        let recipient = rng.address();
         let token_id = U256::from(1);
         let amount = U256::from(1000);
 
@@ -50,9 +52,10 @@ mod erc6909_tests {
 
     #[test]
     fn test_erc6909_error_conditions() {
+        let mut rng = Rng::seed_from_u64(2);
         let mut token = ERC6909::new();
-        let owner = Address::random();
-        let recipient = Address::random();
+        let owner = rng.address();
+        let recipient = rng.address();
         let token_id = U256::from(1);
 
         // 测试余额不足错误
@@ -71,13 +74,117 @@ mod erc6909_tests {
         assert!(matches!(result, Err(ERC6909Error::InvalidRecipient)));
     }
 
+    #[test]
+    fn test_erc6909_total_supply_and_metadata() {
+        let mut rng = Rng::seed_from_u64(3);
+        let mut token = ERC6909::new();
+        let alice = rng.address();
+        let bob = rng.address();
+        let id_a = U256::from(1);
+        let id_b = U256::from(2);
+
+        // 铸造更新总供应量
+        token.mint(alice, id_a, U256::from(1000)).unwrap();
+        token.mint(bob, id_a, U256::from(500)).unwrap();
+        token.mint(bob, id_b, U256::from(42)).unwrap();
+        assert_eq!(token.total_supply(id_a), U256::from(1500));
+        assert_eq!(token.total_supply(id_b), U256::from(42));
+        assert_eq!(token.total_supply(U256::from(3)), U256::zero());
+
+        // 转账不改变总供应量
+        token.transfer(alice, bob, id_a, U256::from(300)).unwrap();
+        assert_eq!(token.total_supply(id_a), U256::from(1500));
+
+        // 销毁减少总供应量, 失败的销毁不改变总供应量
+        token.burn(bob, id_a, U256::from(800)).unwrap();
+        assert_eq!(token.total_supply(id_a), U256::from(700));
+        assert!(token.burn(bob, id_a, U256::from(1)).is_err());
+        assert_eq!(token.total_supply(id_a), U256::from(700));
+
+        // 元数据
+        assert!(token.metadata(id_a).is_none());
+        assert_eq!(token.decimals(id_a), 0);
+        token.set_metadata(id_a, TokenMetadata::new("Wrapped Ether", "WETH", 18));
+        assert_eq!(token.name(id_a), Some("Wrapped Ether"));
+        assert_eq!(token.symbol(id_a), Some("WETH"));
+        assert_eq!(token.decimals(id_a), 18);
+        assert!(token.name(id_b).is_none());
+    }
+
+    #[test]
+    fn test_erc6909_overflow() {
+        let mut rng = Rng::seed_from_u64(7);
+        let mut token = ERC6909::new();
+        let alice = rng.address();
+        let bob = rng.address();
+        let id = U256::from(1);
+
+        // 溢出的铸造失败且不修改余额和总供应量
+        token.mint(alice, id, U256::MAX).unwrap();
+        let result = token.mint(alice, id, U256::one());
+        assert!(matches!(result, Err(ERC6909Error::BalanceOverflow)));
+        let result = token.mint(bob, id, U256::one());
+        assert!(matches!(result, Err(ERC6909Error::SupplyOverflow)));
+        assert_eq!(token.balance_of(bob, id), U256::zero());
+        assert_eq!(token.total_supply(id), U256::MAX);
+
+        // 销毁后可以再次铸造
+        token.burn(alice, id, U256::from(10)).unwrap();
+        token.mint(bob, id, U256::from(10)).unwrap();
+        assert_eq!(token.total_supply(id), U256::MAX);
+        token.transfer(bob, alice, id, U256::from(10)).unwrap();
+        assert_eq!(token.balance_of(alice, id), U256::MAX);
+    }
+
+    #[test]
+    fn test_erc6909_transfer_edge_cases() {
+        let mut rng = Rng::seed_from_u64(8);
+        let mut token = ERC6909::new();
+        let owner = rng.address();
+        let spender = rng.address();
+        let operator = rng.address();
+        let id = U256::from(1);
+        token.mint(owner, id, U256::MAX).unwrap();
+
+        // 转给自己不改变余额, 余额不足时仍然失败
+        token.transfer(owner, owner, id, U256::MAX).unwrap();
+        assert_eq!(token.balance_of(owner, id), U256::MAX);
+        let result = token.transfer(spender, spender, id, U256::one());
+        assert!(matches!(result, Err(ERC6909Error::InsufficientBalance)));
+
+        // 零数量转移无需余额或授权
+        token.transfer(spender, owner, id, U256::zero()).unwrap();
+        token.transfer_from(spender, owner, spender, id, U256::zero()).unwrap();
+        assert_eq!(token.balance_of(owner, id), U256::MAX);
+
+        // 转移失败时不扣减授权额度
+        token.approve(owner, spender, id, U256::from(100)).unwrap();
+        token.burn(owner, id, U256::MAX - U256::from(50)).unwrap();
+        let result = token.transfer_from(spender, owner, spender, id, U256::from(80));
+        assert!(matches!(result, Err(ERC6909Error::InsufficientBalance)));
+        assert_eq!(token.allowance(owner, spender, id), U256::from(100));
+        token.transfer_from(spender, owner, spender, id, U256::from(30)).unwrap();
+        assert_eq!(token.allowance(owner, spender, id), U256::from(70));
+
+        // 操作员和无限授权不扣减额度
+        token.set_operator(owner, operator, true).unwrap();
+        token.approve(owner, operator, id, U256::from(5)).unwrap();
+        token.transfer_from(operator, owner, operator, id, U256::from(10)).unwrap();
+        assert_eq!(token.allowance(owner, operator, id), U256::from(5));
+        token.approve(owner, spender, id, U256::MAX).unwrap();
+        token.transfer_from(spender, owner, spender, id, U256::from(10)).unwrap();
+        assert_eq!(token.allowance(owner, spender, id), U256::MAX);
+        assert_eq!(token.balance_of(spender, id), U256::from(40));
+    }
+
     #[test]
     fn test_liquidity_token() {
+        let mut rng = Rng::seed_from_u64(4);
         let mut liquidity_token = LiquidityToken::new(
             "Uniswap V4 LP".to_string(),
             "UNI-V4-LP".to_string()
         );
-        let owner = Address::random();
+        let owner = rng.address();
         let pool_id = U256::from(1);
         let amount = U256::from(1000);
 
@@ -90,7 +197,7 @@ mod erc6909_tests {
         assert_eq!(liquidity_token.balance_of(owner, pool_id), amount);
 
         // 测试转移流动性令牌
-        let recipient = Address::random();
+        let recipient = rng.address();
         liquidity_token.transfer(owner, recipient, pool_id, U256::from(400)).unwrap();
         assert_eq!(liquidity_token.balance_of(owner, pool_id), U256::from(600));
         assert_eq!(liquidity_token.balance_of(recipient, pool_id), U256::from(400));
@@ -98,13 +205,15 @@ mod erc6909_tests {
         // 测试销毁流动性令牌
         liquidity_token.burn_liquidity_token(owner, pool_id, U256::from(600)).unwrap();
         assert_eq!(liquidity_token.balance_of(owner, pool_id), U256::from(0));
+        assert_eq!(liquidity_token.total_supply(pool_id), U256::from(400));
     }
 
     #[test]
     fn test_erc6909_claims() {
+        let mut rng = Rng::seed_from_u64(5);
         let mut claims = ERC6909Claims::new();
-        let owner = Address::random();
-        let recipient = Address::random();
+        let owner = rng.address();
+        let recipient = rng.address();
         let token_id = U256::from(1);
         let amount = U256::from(1000);
 
@@ -141,12 +250,13 @@ mod erc6909_tests {
 
     #[test]
     fn test_liquidity_token_claims() {
+        let mut rng = Rng::seed_from_u64(6);
         let mut liquidity_claims = LiquidityTokenClaims::new(
             "Uniswap V4 LP Claims".to_string(),
             "UNI-V4-LP-C".to_string()
         );
-        let owner = Address::random();
-        let recipient = Address::random();
+        let owner = rng.address();
+        let recipient = rng.address();
         let pool_id = U256::from(1);
         let amount = U256::from(1000);
 
@@ -9,8 +9,8 @@ mod hook_tests {
             hook_interface::{Hook, HookWithReturns, PoolKey, SwapParams, ModifyLiquidityParams},
             examples::{DynamicFeeHook, TwapOracleHook, LiquidityMiningHook, VolumeDiscountHook}
         },
-        state::{BalanceDelta, Result as StateResult},
-        math::types::SqrtPrice
+        state::{BalanceDelta, Salt, Result as StateResult},
+        math::{types::{SqrtPrice, TickSpacing}, FeePips}
     };
     use std::collections::HashMap;
     use std::thread::sleep;
@@ -46,23 +46,22 @@ mod hook_tests {
     impl Hook for TestDeltaHook {
         fn before_swap(
             &mut self,
-            _sender: [u8; 20],
+            _sender: Address,
             _key: &PoolKey,
             _params: &SwapParams,
             _hook_data: &[u8],
         ) -> StateResult<BeforeHookResult> {
-            Ok(BeforeHookResult {
-                amount: Some(100),
-                delta: Some(BalanceDelta::new(100, -50)),
-                fee_override: Some(2000),
-            })
+            Ok(BeforeHookResult::default()
+                .with_amount(100)
+                .with_delta(BalanceDelta::new(100, -50))
+                .with_fee_override(FeePips::new(2000)))
         }
     }
     
     impl HookWithReturns for TestDeltaHook {
         fn before_swap_with_delta(
             &mut self,
-            _sender: [u8; 20],
+            _sender: Address,
             _key: &PoolKey,
             _params: &SwapParams,
             _hook_data: &[u8],
@@ -75,7 +74,7 @@ mod hook_tests {
         
         fn after_swap_with_delta(
             &mut self,
-            _sender: [u8; 20],
+            _sender: Address,
             _key: &PoolKey,
             _params: &SwapParams,
             _delta: &BalanceDelta,
@@ -88,7 +87,7 @@ mod hook_tests {
     #[test]
     fn test_hook_registry() {
         let mut registry = HookRegistry::new();
-        let hook_address = [1u8; 20];
+        let hook_address = Address::repeat_byte(1);
         
         // Register hook
         registry.register_hook(hook_address, Box::new(TestDeltaHook {}));
@@ -96,19 +95,19 @@ mod hook_tests {
         
         // Test calling hooks that return Delta
         let pool_key = PoolKey {
-            token0: [0u8; 20],
-            token1: [0u8; 20],
+            token0: Address::zero(),
+            token1: Address::zero(),
             fee: 3000,
-            tick_spacing: 60,
+            tick_spacing: TickSpacing::new(60).unwrap(),
             hooks: hook_address,
             extension_data: vec![],
         };
         
-        let sender = [0u8; 20];
+        let sender = Address::zero();
         let params = SwapParams {
             amount_specified: -1000,
             zero_for_one: true,
-            sqrt_price_limit_x96: SqrtPrice::new(U256::from(1) << 96),
+            sqrt_price_limit_x96: SqrtPrice::ONE,
         };
         
         // Hook address doesn't have BEFORE_SWAP_RETURNS_DELTA flag set, so it should return default values
@@ -124,14 +123,14 @@ mod hook_tests {
     
     #[test]
     fn test_dynamic_fee_hook() {
-        let mut hook = DynamicFeeHook::new(3000, 500, 10000);
-        let sender = [0u8; 20];
+        let mut hook = DynamicFeeHook::new(FeePips::new(3000), FeePips::new(500), FeePips::new(10000));
+        let sender = Address::zero();
         let key = PoolKey {
-            token0: [0u8; 20],
-            token1: [0u8; 20],
+            token0: Address::zero(),
+            token1: Address::zero(),
             fee: 3000,
-            tick_spacing: 60,
-            hooks: [0u8; 20],
+            tick_spacing: TickSpacing::new(60).unwrap(),
+            hooks: Address::zero(),
             extension_data: vec![],
         };
         
@@ -143,7 +142,7 @@ mod hook_tests {
         
         // First call will set the initial price
         let result = hook.before_swap(sender, &key, &params, &[]).unwrap();
-        assert_eq!(result.fee_override, Some(3000)); // Should return base fee rate
+        assert_eq!(result.fee_override, Some(FeePips::new(3000))); // Should return base fee rate
         
         // Change price, causing increased volatility
         let params2 = SwapParams {
@@ -154,12 +153,12 @@ mod hook_tests {
         
         // Second call should return a higher fee rate
         let result2 = hook.before_swap(sender, &key, &params2, &[]).unwrap();
-        assert!(result2.fee_override.unwrap() > 3000);
+        assert!(result2.fee_override.unwrap() > FeePips::new(3000));
     }
     
     // Custom MockLiquidityMiningHook for testing
     struct MockLiquidityMiningHook {
-        user_rewards: HashMap<[u8; 20], U256>,
+        user_rewards: HashMap<Address, U256>,
     }
     
     impl MockLiquidityMiningHook {
@@ -169,7 +168,7 @@ mod hook_tests {
             }
         }
         
-        fn claim_rewards(&mut self, user: [u8; 20]) -> U256 {
+        fn claim_rewards(&mut self, user: Address) -> U256 {
             let rewards = *self.user_rewards.get(&user).unwrap_or(&U256::zero());
             if !rewards.is_zero() {
                 self.user_rewards.insert(user, U256::zero());
@@ -181,7 +180,7 @@ mod hook_tests {
     impl Hook for MockLiquidityMiningHook {
         fn after_add_liquidity(
             &mut self,
-            _sender: [u8; 20],
+            _sender: Address,
             _key: &PoolKey,
             params: &ModifyLiquidityParams,
             _delta: &BalanceDelta,
@@ -194,7 +193,7 @@ mod hook_tests {
         
         fn after_remove_liquidity(
             &mut self,
-            _sender: [u8; 20],
+            _sender: Address,
             _key: &PoolKey,
             params: &ModifyLiquidityParams,
             _delta: &BalanceDelta,
@@ -215,14 +214,14 @@ mod hook_tests {
     fn test_liquidity_mining_hook() {
         // Create a mock hook for testing
         let mut hook = MockLiquidityMiningHook::new();
-        let sender = [0u8; 20];
-        let owner = [1u8; 20];
+        let sender = Address::zero();
+        let owner = Address::repeat_byte(1);
         let key = PoolKey {
-            token0: [0u8; 20],
-            token1: [0u8; 20],
+            token0: Address::zero(),
+            token1: Address::zero(),
             fee: 3000,
-            tick_spacing: 60,
-            hooks: [0u8; 20],
+            tick_spacing: TickSpacing::new(60).unwrap(),
+            hooks: Address::zero(),
             extension_data: vec![],
         };
         
@@ -231,7 +230,7 @@ mod hook_tests {
             tick_lower: -100,
             tick_upper: 100,
             liquidity_delta: 1000,
-            salt: [0u8; 32],
+            salt: Salt::ZERO,
         };
         
         let delta = BalanceDelta::new(0, 0);
@@ -246,7 +245,7 @@ mod hook_tests {
             tick_lower: -100,
             tick_upper: 100,
             liquidity_delta: -1000,
-            salt: [0u8; 32],
+            salt: Salt::ZERO,
         };
         
         hook.after_remove_liquidity(sender, &key, &remove_params, &delta, &fees, &[]).unwrap();
@@ -256,4 +255,21 @@ mod hook_tests {
         println!("Rewards: {}", rewards);
         assert!(rewards > U256::zero());
     }
-} 
\ No newline at end of file
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_raw_param_shims() {
+        let key = PoolKey::from_raw([1u8; 20], [2u8; 20], 3000, TickSpacing::new(60).unwrap(), [0u8; 20], vec![]);
+        assert_eq!(key.token0, Address::repeat_byte(1));
+        assert_eq!(key.token1, Address::repeat_byte(2));
+        assert!(key.hooks.is_zero());
+
+        let params = ModifyLiquidityParams::from_raw([3u8; 20], -60, 60, 1000, [7u8; 32]);
+        assert_eq!(params.owner, Address::repeat_byte(3));
+        assert_eq!(params.salt, Salt::from([7u8; 32]));
+
+        let position_key = params.position_key();
+        assert_eq!(position_key.owner, [3u8; 20]);
+        assert_eq!(position_key.salt, <[u8; 32]>::from(params.salt));
+    }
+}
@@ -1,71 +1,98 @@
 #[cfg(test)]
 mod protocol_fee_tests {
-    use ethers::types::Address;
     use primitive_types::U256;
+    use uniswap_v4_core::Rng;
     use uniswap_v4_core::fees::{
-        ProtocolFee, ProtocolFeeManager, ProtocolFeesAccrued,
+        ProtocolFee, ProtocolFeeError, ProtocolFeeManager, ProtocolFeesAccrued,
         types::MAX_PROTOCOL_FEE, ProtocolFeeIntegration
     };
+    use ethers::types::Address;
+    use uniswap_v4_core::core::PoolId;
+    use uniswap_v4_core::core::math::types::{Percent, SqrtPrice, TickSpacing};
+    use uniswap_v4_core::core::state::StateError;
     use uniswap_v4_core::core::flash_loan::currency::Currency;
     use uniswap_v4_core::core::hooks::hook_interface::PoolKey;
     use uniswap_v4_core::core::state::Pool;
+    use uniswap_v4_core::core::math::FeePips;
 
     #[test]
     fn test_protocol_fee_creation() {
         // Test creating protocol fee from zero
-        let fee = ProtocolFee::new(0, 0);
-        assert_eq!(fee.get_zero_for_one_fee(), 0);
-        assert_eq!(fee.get_one_for_zero_fee(), 0);
+        let fee = ProtocolFee::new(0, 0).unwrap();
+        assert_eq!(fee.get_zero_for_one_fee(), FeePips::ZERO);
+        assert_eq!(fee.get_one_for_zero_fee(), FeePips::ZERO);
         assert!(fee.is_valid());
 
         // Test setting maximum protocol fee
-        let max_fee = ProtocolFee::new(MAX_PROTOCOL_FEE, MAX_PROTOCOL_FEE);
-        assert_eq!(max_fee.get_zero_for_one_fee(), MAX_PROTOCOL_FEE);
-        assert_eq!(max_fee.get_one_for_zero_fee(), MAX_PROTOCOL_FEE);
+        let max_fee = ProtocolFee::new(MAX_PROTOCOL_FEE, MAX_PROTOCOL_FEE).unwrap();
+        assert_eq!(max_fee.get_zero_for_one_fee(), FeePips::new(MAX_PROTOCOL_FEE as u32));
+        assert_eq!(max_fee.get_one_for_zero_fee(), FeePips::new(MAX_PROTOCOL_FEE as u32));
         assert!(max_fee.is_valid());
 
         // Test different directions of protocol fee
-        let asymmetric_fee = ProtocolFee::new(100, 200);
-        assert_eq!(asymmetric_fee.get_zero_for_one_fee(), 100);
-        assert_eq!(asymmetric_fee.get_one_for_zero_fee(), 200);
+        let asymmetric_fee = ProtocolFee::new(100, 200).unwrap();
+        assert_eq!(asymmetric_fee.get_zero_for_one_fee(), FeePips::new(100));
+        assert_eq!(asymmetric_fee.get_one_for_zero_fee(), FeePips::new(200));
         assert!(asymmetric_fee.is_valid());
     }
 
+    #[test]
+    fn test_protocol_fee_packing() {
+        let fee = ProtocolFee::from_parts(FeePips::new(MAX_PROTOCOL_FEE as u32), FeePips::new(250)).unwrap();
+        assert_eq!(fee.0, 1000 | (250 << 12));
+        assert_eq!((fee.fee(true), fee.fee(false)), (FeePips::new(1000), FeePips::new(250)));
+        assert!(fee.validate().is_ok());
+
+        // Either direction above the maximum is rejected
+        assert!(matches!(
+            ProtocolFee::from_parts(FeePips::ZERO, FeePips::new(1001)),
+            Err(ProtocolFeeError::ProtocolFeeTooLarge(1001))
+        ));
+        assert!(ProtocolFee::new(1001, 0).unwrap().validate().is_err());
+        assert!(ProtocolFee::new(0, 1001).unwrap().validate().is_err());
+        // As are bits above the 24 of the packed fee
+        assert!(!ProtocolFee(1 << 24).is_valid());
+        // Fees wider than 12 bits are rejected rather than masked
+        assert!(matches!(ProtocolFee::new(0x1001, 0), Err(ProtocolFeeError::ProtocolFeeTooLarge(0x1001))));
+        assert!(matches!(ProtocolFee::new(0, 0x1000), Err(ProtocolFeeError::ProtocolFeeTooLarge(0x1000))));
+    }
+
     #[test]
     fn test_protocol_fee_calculation() {
-        let fee = ProtocolFee::new(100, 200); // 0.01% for 0->1, 0.02% for 1->0
-        let lp_fee = 3000; // 0.3%
+        let fee = ProtocolFee::new(100, 200).unwrap(); // 0.01% for 0->1, 0.02% for 1->0
+        let lp_fee = FeePips::new(3000); // 0.3%
 
         // Test zero-for-one direction protocol fee calculation
         let zero_for_one_swap_fee = fee.calculate_swap_fee(true, lp_fee);
         // Expected result: 3000 + 100 - (3000 * 100 / 1_000_000) = 3100 - 0 = 3100
-        assert_eq!(zero_for_one_swap_fee, 3100);
+        assert_eq!(zero_for_one_swap_fee, FeePips::new(3100));
 
         // Test one-for-zero direction protocol fee calculation
         let one_for_zero_swap_fee = fee.calculate_swap_fee(false, lp_fee);
         // Expected result: 3000 + 200 - (3000 * 200 / 1_000_000) = 3200 - 0 = 3200
-        assert_eq!(one_for_zero_swap_fee, 3200);
+        assert_eq!(one_for_zero_swap_fee, FeePips::new(3200));
     }
 
     #[test]
     fn test_protocol_fee_manager() {
-        let owner = Address::random();
+        let mut rng = Rng::seed_from_u64(1);
+        let owner = rng.address();
         let mut manager = ProtocolFeeManager::new(owner);
 
         // Test setting protocol fee controller
-        let new_controller = Address::random();
+        let new_controller = rng.address();
         manager.set_protocol_fee_controller(new_controller, owner).unwrap();
         assert_eq!(manager.controller, new_controller);
 
         // Test updating protocol fee
-        let currency = Currency::from_address(Address::random());
+        let currency = Currency::from_address(rng.address());
         manager.update_protocol_fees(currency, U256::from(1000));
         assert_eq!(manager.protocol_fees_accrued(currency), U256::from(1000));
 
         // Test collecting protocol fee
         let collected = manager.collect_protocol_fees(
             new_controller, 
-            Address::random(), 
+            rng.address(), 
             currency, 
             U256::zero(),
             false
@@ -76,7 +103,8 @@ mod protocol_fee_tests {
 
     #[test]
     fn test_protocol_fee_integration() {
-        let owner = Address::random();
+        let mut rng = Rng::seed_from_u64(2);
+        let owner = rng.address();
         let mut integration = ProtocolFeeIntegration::new(owner);
         let mut pool = Pool::new();
         
@@ -84,12 +112,12 @@ mod protocol_fee_tests {
         let sqrt_price = uniswap_v4_core::core::math::types::SqrtPrice::new(
             U256::from(2).pow(U256::from(96))
         );
-        pool.initialize(sqrt_price, 3000).unwrap();
+        pool.initialize(sqrt_price, FeePips::new(3000)).unwrap();
         
         // Test updating swap fees
-        let protocol_fee = ProtocolFee::new(100, 200); // 0.01% for 0->1, 0.02% for 1->0
+        let protocol_fee = ProtocolFee::new(100, 200).unwrap(); // 0.01% for 0->1, 0.02% for 1->0
         let amount_specified = -1_000_000i128; // Negative value means exactInput
-        let currency = Currency::from_address(Address::random());
+        let currency = Currency::from_address(rng.address());
         
         // Update zero-for-one direction swap fees
         let fee_amount = integration.update_fees_for_swap(
@@ -100,4 +128,104 @@ mod protocol_fee_tests {
         assert!(fee_amount > 0);
         assert!(integration.manager.protocol_fees_accrued(currency) > U256::zero());
     }
-} 
\ No newline at end of file
+
+    fn donation_key(rng: &mut Rng) -> PoolKey {
+        PoolKey {
+            token0: rng.address(),
+            token1: rng.address(),
+            fee: 3000,
+            tick_spacing: TickSpacing::new(60).unwrap(),
+            hooks: Address::zero(),
+            extension_data: Vec::new(),
+        }
+    }
+
+    /// A pool with a single position, of the owner `[1; 20]`
+    fn pool_with_position() -> Pool {
+        let mut pool = Pool::new();
+        pool.initialize(SqrtPrice::ONE, FeePips::new(3000)).unwrap();
+        pool.modify_position([1; 20], -120, 120, 1_000_000_000, TickSpacing::new(60).unwrap(), [0; 32]).unwrap();
+        pool
+    }
+
+    /// Fees the position of `pool_with_position` collects
+    fn collect_lp_fees(pool: &mut Pool) -> (u128, u128) {
+        let (_, fees) = pool.modify_position([1; 20], -120, 120, 0, TickSpacing::new(60).unwrap(), [0; 32]).unwrap();
+        (fees.amount0.unsigned_abs(), fees.amount1.unsigned_abs())
+    }
+
+    #[test]
+    fn test_treasury_donations_conserve_amounts() {
+        let mut rng = Rng::seed_from_u64(3);
+        let owner = rng.address();
+        let mut integration = ProtocolFeeIntegration::new(owner);
+        let key = donation_key(&mut rng);
+        let (opted_in, other) = (PoolId([1; 32]), PoolId([2; 32]));
+        integration.set_treasury_donation_share(owner, opted_in, Percent::new(25).unwrap()).unwrap();
+        assert_eq!(integration.treasury_donation_share(&other), Percent::ZERO);
+
+        let (amount0, amount1) = (1_000_003u128, 7u128);
+        let mut pool = pool_with_position();
+        let split = integration.donate(&mut pool, opted_in, &key, amount0, amount1).unwrap();
+        assert_eq!((split.delta.amount0, split.delta.amount1), (-(amount0 as i128), -(amount1 as i128)));
+        // The treasury share rounds down, LPs get the rest
+        assert_eq!((split.protocol0, split.protocol1), (250_000, 1));
+        let currency0 = Currency::from_address(key.token0);
+        let currency1 = Currency::from_address(key.token1);
+        assert_eq!(integration.manager.protocol_fees_accrued(currency0), U256::from(250_000));
+        assert_eq!(integration.manager.protocol_fees_accrued(currency1), U256::from(1));
+
+        // Everything donated goes to the treasury or the LP, but for the
+        // rounding of fee growth in the pool's favour
+        let (lp0, lp1) = collect_lp_fees(&mut pool);
+        for (donated, protocol, lp) in [(amount0, split.protocol0, lp0), (amount1, split.protocol1, lp1)] {
+            assert!(protocol + lp <= donated);
+            assert!(donated - protocol - lp <= 1, "{donated} donated, {protocol} to the treasury, {lp} to the LP");
+        }
+
+        // A pool that didn't opt in credits its LPs with everything
+        let mut pool = pool_with_position();
+        let split = integration.donate(&mut pool, other, &key, amount0, amount1).unwrap();
+        assert_eq!((split.protocol0, split.protocol1), (0, 0));
+        let (lp0, _) = collect_lp_fees(&mut pool);
+        assert!(amount0 - lp0 <= 1);
+        assert_eq!(integration.manager.protocol_fees_accrued(currency0), U256::from(250_000));
+    }
+
+    #[test]
+    fn test_treasury_donation_share_opt_in() {
+        let mut rng = Rng::seed_from_u64(4);
+        let owner = rng.address();
+        let mut integration = ProtocolFeeIntegration::new(owner);
+        let key = donation_key(&mut rng);
+        let pool_id = PoolId([1; 32]);
+
+        // Only the fee controller sets shares
+        assert!(matches!(
+            integration.set_treasury_donation_share(rng.address(), pool_id, Percent::HUNDRED),
+            Err(ProtocolFeeError::InvalidCaller)
+        ));
+
+        // A donation entirely to the treasury needs no liquidity
+        let mut empty = Pool::new();
+        empty.initialize(SqrtPrice::ONE, FeePips::new(3000)).unwrap();
+        assert!(matches!(
+            integration.donate(&mut empty, pool_id, &key, 100, 0),
+            Err(StateError::NoLiquidityToReceiveFees)
+        ));
+        integration.set_treasury_donation_share(owner, pool_id, Percent::HUNDRED).unwrap();
+        let split = integration.donate(&mut empty, pool_id, &key, 100, 0).unwrap();
+        assert_eq!((split.protocol0, split.protocol1), (100, 0));
+        assert!(empty.fee_growth_global_0_x128.is_zero());
+
+        // Amounts the donor's signed delta can't hold are refused
+        assert!(matches!(
+            integration.donate(&mut empty, pool_id, &key, u128::MAX, 0),
+            Err(StateError::AmountOverflow)
+        ));
+
+        // A zero share opts the pool out again
+        integration.set_treasury_donation_share(owner, pool_id, Percent::ZERO).unwrap();
+        assert_eq!(integration.treasury_donation_share(&pool_id), Percent::ZERO);
+    }
+}